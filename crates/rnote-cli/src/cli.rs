@@ -1,5 +1,5 @@
 // Imports
-use crate::{export, import, test};
+use crate::{export, import, test, thumbnail};
 use anyhow::Context;
 use clap::Parser;
 use rnote_compose::SplitOrder;
@@ -69,6 +69,15 @@ pub(crate) enum Command {
         #[arg(long, action = clap::ArgAction::SetTrue, global = true)]
         open: bool,
     },
+    /// Extracts the thumbnail embedded in the Rnote file and saves it as png file.{n}
+    /// Does not load the strokes, so it can serve as thumbnailer for file managers.
+    Thumbnail {
+        /// The rnote save file.
+        rnote_file: PathBuf,
+        /// The png output file.
+        #[arg(short = 'o', long)]
+        output_file: PathBuf,
+    },
 }
 
 #[derive(clap::ValueEnum, Debug, Clone, Copy, Default)]
@@ -151,6 +160,9 @@ pub(crate) enum ExportCommand {
         /// The margin around the to be exported content.
         #[arg(long, default_value_t = SelectionExportPrefs::default().margin, global = true)]
         margin: f64,
+        /// Export Svg and Pdf files true to scale, in physical units according to the DPI of the document.
+        #[arg(long, action = clap::ArgAction::SetTrue, global = true)]
+        true_to_scale: bool,
     },
 }
 
@@ -242,6 +254,12 @@ pub(crate) async fn run() -> anyhow::Result<()> {
             .await?;
             println!("Export finished!");
         }
+        Command::Thumbnail {
+            rnote_file,
+            output_file,
+        } => {
+            thumbnail::run_thumbnail(&rnote_file, &output_file).await?;
+        }
     }
 
     Ok(())
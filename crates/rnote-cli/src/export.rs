@@ -6,8 +6,9 @@ use p2d::bounding_volume::Aabb;
 use rnote_compose::SplitOrder;
 use rnote_engine::engine::export::{
     DocExportFormat, DocExportPrefs, DocPagesExportFormat, DocPagesExportPrefs,
-    SelectionExportFormat, SelectionExportPrefs,
+    SelectionExportFormat, SelectionExportPrefs, SelectionPhysicalSize,
 };
+use rnote_engine::engine::journal::journal_file_path;
 use rnote_engine::engine::EngineSnapshot;
 use rnote_engine::{Engine, SelectionCollision};
 use std::io::{self, IsTerminal};
@@ -227,6 +228,7 @@ fn apply_export_prefs(
             bitmap_scalefactor,
             jpeg_quality,
             margin,
+            true_to_scale,
             ..
         } => {
             engine.export_prefs.selection_export_prefs = create_selection_export_prefs_from_args(
@@ -238,6 +240,7 @@ fn apply_export_prefs(
                 *bitmap_scalefactor,
                 *jpeg_quality,
                 *margin,
+                *true_to_scale,
             )?;
         }
     }
@@ -350,6 +353,7 @@ pub(crate) fn create_selection_export_prefs_from_args(
     bitmap_scalefactor: f64,
     jpeg_quality: u8,
     margin: f64,
+    true_to_scale: bool,
 ) -> anyhow::Result<SelectionExportPrefs> {
     let format = match (output_file, output_format) {
         (Some(file), None) => match file.as_ref().extension().and_then(|ext| ext.to_str()) {
@@ -383,6 +387,11 @@ pub(crate) fn create_selection_export_prefs_from_args(
         bitmap_scalefactor,
         jpeg_quality,
         margin,
+        physical_size: if true_to_scale {
+            SelectionPhysicalSize::TrueToScale
+        } else {
+            SelectionPhysicalSize::None
+        },
     };
 
     Ok(prefs)
@@ -393,6 +402,7 @@ fn get_selection_export_format(format: &str) -> anyhow::Result<SelectionExportFo
         "svg" => Ok(SelectionExportFormat::Svg),
         "png" => Ok(SelectionExportFormat::Png),
         "jpg" | "jpeg" => Ok(SelectionExportFormat::Jpeg),
+        "pdf" => Ok(SelectionExportFormat::Pdf),
         ext => Err(anyhow::anyhow!(
             "Exporting selection to format with extension \"{ext}\" is not supported."
         )),
@@ -528,7 +538,14 @@ pub(crate) async fn export_to_file(
     open: bool,
 ) -> anyhow::Result<()> {
     let rnote_bytes = cli::read_bytes_from_file(&rnote_file).await?;
-    let engine_snapshot = EngineSnapshot::load_from_rnote_bytes(rnote_bytes).await?;
+    // Include the incremental saves of the journal next to the file
+    let journal_file = journal_file_path(&rnote_file);
+    let engine_snapshot = if journal_file.exists() {
+        let journal_bytes = cli::read_bytes_from_file(&journal_file).await?;
+        EngineSnapshot::load_from_rnote_bytes_w_journal(rnote_bytes, journal_bytes).await?
+    } else {
+        EngineSnapshot::load_from_rnote_bytes(rnote_bytes).await?
+    };
     let _ = engine.load_snapshot(engine_snapshot);
 
     match export_command {
@@ -7,6 +7,7 @@ pub(crate) mod cli;
 pub(crate) mod export;
 pub(crate) mod import;
 pub(crate) mod test;
+pub(crate) mod thumbnail;
 pub(crate) mod validators;
 
 // Renames
@@ -0,0 +1,21 @@
+// Imports
+use crate::{cli, validators};
+use rnote_engine::Engine;
+use std::path::Path;
+
+/// Extract the thumbnail that is embedded in the rnote file and save it as png file.
+///
+/// Meant to be usable as thumbnailer for file managers, so the strokes are never loaded.
+pub(crate) async fn run_thumbnail(rnote_file: &Path, output_file: &Path) -> anyhow::Result<()> {
+    validators::file_has_ext(rnote_file, "rnote")?;
+
+    let rnote_bytes = cli::read_bytes_from_file(rnote_file).await?;
+    let Some(png_data) = Engine::document_thumbnail(&rnote_bytes) else {
+        return Err(anyhow::anyhow!(
+            "File \"{}\" does not contain a thumbnail.",
+            rnote_file.display()
+        ));
+    };
+    cli::create_overwrite_file_w_bytes(output_file, &png_data).await?;
+    Ok(())
+}
@@ -123,6 +123,20 @@ impl Color {
             && approx::relative_eq!(self.a as f32, other.a as f32)
     }
 
+    /// The perceptual distance to the other color.
+    ///
+    /// The euclidean distance in the Oklab color space, plus the difference of the alpha values.
+    /// Zero for identical colors, distances below 0.02 are barely noticeable.
+    pub fn perceptual_distance(self, other: Self) -> f64 {
+        let lab: palette::Oklaba<f64> = self.into_color();
+        let other_lab: palette::Oklaba<f64> = other.into_color();
+        ((lab.l - other_lab.l).powi(2)
+            + (lab.a - other_lab.a).powi(2)
+            + (lab.b - other_lab.b).powi(2))
+        .sqrt()
+            + (lab.alpha - other_lab.alpha).abs()
+    }
+
     /// The luma value, ranging [0.0 - 1.0].
     ///
     /// see: <https://en.wikipedia.org/wiki/Luma_(video)>
@@ -153,6 +167,40 @@ impl Color {
         }
     }
 
+    /// The given number of shades, in perceptually even steps towards black.
+    ///
+    /// Ordered from the closest to the darkest, the color itself and black are not included.
+    pub fn shades(self, n: usize) -> Vec<Self> {
+        self.lightness_steps(n, palette::Oklab::new(0.0, 0.0, 0.0))
+    }
+
+    /// The given number of tints, in perceptually even steps towards white.
+    ///
+    /// Ordered from the closest to the lightest, the color itself and white are not included.
+    pub fn tints(self, n: usize) -> Vec<Self> {
+        self.lightness_steps(n, palette::Oklab::new(1.0, 0.0, 0.0))
+    }
+
+    /// Mix the color in the Oklab color space with the target in even steps, keeping the alpha.
+    ///
+    /// Steps outside of the srgb gamut are clamped.
+    fn lightness_steps(self, n: usize, target: palette::Oklab<f64>) -> Vec<Self> {
+        let oklaba: palette::Oklaba<f64> = self.into_color();
+
+        (1..=n)
+            .map(|i| {
+                let t = i as f64 / (n + 1) as f64;
+                let step = palette::Oklaba::new(
+                    oklaba.color.l + (target.l - oklaba.color.l) * t,
+                    oklaba.color.a + (target.a - oklaba.color.a) * t,
+                    oklaba.color.b + (target.b - oklaba.color.b) * t,
+                    oklaba.alpha,
+                );
+                step.into_color()
+            })
+            .collect()
+    }
+
     /// Convert to a css color attribute in the style: `rgba(xxx,xxx,xxx,xxx)`.
     /// The values are 8 bit integers, ranging [0, 255].
     pub fn to_css_color_attr(self) -> String {
@@ -9,87 +9,185 @@ use std::collections::HashSet;
 /// Motion event already encode state as they would only be valid if they are preceded by a down event.
 /// As a result, multiple down events are emitted while the pen is pressed down and being moved.
 /// This should be handled accordingly by the state machines which receive the events.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "pen_event")]
 pub enum PenEvent {
     /// A pen down event. Is repeatedly emitted while the pen is pressed down and moved.
+    #[serde(rename = "down")]
     Down {
         /// The element for the down event.
+        #[serde(rename = "element")]
         element: Element,
         /// Modifier keys pressed during the event.
+        #[serde(rename = "modifier_keys")]
         modifier_keys: HashSet<ModifierKey>,
+        /// The pen button that is pressed during the event.
+        #[serde(default, rename = "pen_button")]
+        pen_button: PenButton,
     },
     /// A pen up event.
+    #[serde(rename = "up")]
     Up {
         /// The element for the up event.
+        #[serde(rename = "element")]
         element: Element,
         /// Modifier keys pressed during the event.
+        #[serde(rename = "modifier_keys")]
         modifier_keys: HashSet<ModifierKey>,
+        /// The pen button that is pressed during the event.
+        #[serde(default, rename = "pen_button")]
+        pen_button: PenButton,
     },
     /// A pen down event. Is repeatedly emitted while the pen is in proximity and moved.
+    #[serde(rename = "proximity")]
     Proximity {
         /// The element for the proximity event.
+        #[serde(rename = "element")]
         element: Element,
         /// Modifier keys pressed during the event.
+        #[serde(rename = "modifier_keys")]
         modifier_keys: HashSet<ModifierKey>,
+        /// The pen button that is pressed during the event.
+        #[serde(default, rename = "pen_button")]
+        pen_button: PenButton,
     },
     /// A keyboard key pressed event.
+    #[serde(rename = "key_pressed")]
     KeyPressed {
         /// the key
+        #[serde(rename = "keyboard_key")]
         keyboard_key: KeyboardKey,
         /// Modifier keys pressed during the event.
+        #[serde(rename = "modifier_keys")]
         modifier_keys: HashSet<ModifierKey>,
     },
     /// Text input event.
+    #[serde(rename = "text")]
     Text {
         /// The committed text.
+        #[serde(rename = "text")]
         text: String,
     },
     /// Cancel event when the pen vanishes unexpected.
     ///
     /// Should finish all current actions and reset all state.
+    #[serde(rename = "cancel")]
     Cancel,
 }
 
+impl PenEvent {
+    /// A down event of the [PenButton::Primary] button.
+    pub fn down(element: Element, modifier_keys: HashSet<ModifierKey>) -> Self {
+        Self::Down {
+            element,
+            modifier_keys,
+            pen_button: PenButton::default(),
+        }
+    }
+
+    /// An up event of the [PenButton::Primary] button.
+    pub fn up(element: Element, modifier_keys: HashSet<ModifierKey>) -> Self {
+        Self::Up {
+            element,
+            modifier_keys,
+            pen_button: PenButton::default(),
+        }
+    }
+
+    /// A proximity event of the [PenButton::Primary] button.
+    pub fn proximity(element: Element, modifier_keys: HashSet<ModifierKey>) -> Self {
+        Self::Proximity {
+            element,
+            modifier_keys,
+            pen_button: PenButton::default(),
+        }
+    }
+
+    /// Replace the pressed pen button of down, up and proximity events.
+    ///
+    /// Other events are returned unchanged.
+    pub fn with_pen_button(mut self, pen_button: PenButton) -> Self {
+        if let Self::Down {
+            pen_button: button, ..
+        }
+        | Self::Up {
+            pen_button: button, ..
+        }
+        | Self::Proximity {
+            pen_button: button, ..
+        } = &mut self
+        {
+            *button = pen_button;
+        }
+        self
+    }
+}
+
 /// A key on the keyboard.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename = "keyboard_key")]
 pub enum KeyboardKey {
     /// A Unicode character.
     ///
     /// Expects that control characters are already converted and filtered out with the method [KeyboardKey::filter_convert_unicode_control_chars].
+    #[serde(rename = "unicode")]
     Unicode(char),
     /// Backspace.
+    #[serde(rename = "backspace")]
     BackSpace,
     /// Tab.
+    #[serde(rename = "horizontal_tab")]
     HorizontalTab,
     /// Line feed.
+    #[serde(rename = "linefeed")]
     Linefeed,
     /// Carriage return.
+    #[serde(rename = "carriage_return")]
     CarriageReturn,
     /// Escape.
+    #[serde(rename = "escape")]
     Escape,
     /// Delete.
+    #[serde(rename = "delete")]
     Delete,
     /// Arrow up.
+    #[serde(rename = "nav_up")]
     NavUp,
     /// Arrow down.
+    #[serde(rename = "nav_down")]
     NavDown,
     /// Arrow left.
+    #[serde(rename = "nav_left")]
     NavLeft,
     /// Arrow right.
+    #[serde(rename = "nav_right")]
     NavRight,
     /// Shift left.
+    #[serde(rename = "shift_left")]
     ShiftLeft,
     /// Shift right.
+    #[serde(rename = "shift_right")]
     ShiftRight,
     /// Ctrl left.
+    #[serde(rename = "ctrl_left")]
     CtrlLeft,
     /// Ctrl right.
+    #[serde(rename = "ctrl_right")]
     CtrlRight,
     /// Home.
+    #[serde(rename = "home")]
     Home,
     /// End.
+    #[serde(rename = "end")]
     End,
+    /// Page up.
+    #[serde(rename = "page_up")]
+    PageUp,
+    /// Page down.
+    #[serde(rename = "page_down")]
+    PageDown,
     /// Unsupported Key.
+    #[serde(rename = "unsupported")]
     Unsupported,
 }
 
@@ -161,10 +259,64 @@ pub enum ModifierKey {
     #[serde(rename = "keyboard_ctrl")]
     KeyboardCtrl,
     /// Alt.
+    ///
+    /// Dragging while holding Alt (and Shift) pans (and zooms) the canvas for every pen,
+    /// so pens use it only in keyboard shortcuts and never for pointer gestures.
     #[serde(rename = "keyboard_alt")]
     KeyboardAlt,
 }
 
+/// A pen button, pressed during a pen event.
+///
+/// Stylus barrel buttons are reported separately from the tip,
+/// so that pens can route them to alternate actions.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename = "pen_button")]
+pub enum PenButton {
+    /// The stylus tip, the primary mouse button or a touch.
+    #[default]
+    #[serde(rename = "primary")]
+    Primary,
+    /// The primary (lower) stylus barrel button.
+    #[serde(rename = "stylus_primary")]
+    StylusPrimary,
+    /// The secondary (upper) stylus barrel button.
+    #[serde(rename = "stylus_secondary")]
+    StylusSecondary,
+}
+
+impl PenButton {
+    /// Whether the button is one of the stylus barrel buttons.
+    pub fn is_barrel_button(&self) -> bool {
+        matches!(self, Self::StylusPrimary | Self::StylusSecondary)
+    }
+}
+
+/// The kind of device that generated the input.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename = "input_source")]
+pub enum InputSource {
+    /// A stylus, which reports real pressure.
+    ///
+    /// Input that is not generated by a device is treated as stylus input as well.
+    #[default]
+    #[serde(rename = "stylus")]
+    Stylus,
+    /// A mouse or touchpad.
+    #[serde(rename = "mouse")]
+    Mouse,
+    /// A touchscreen.
+    #[serde(rename = "touch")]
+    Touch,
+}
+
+impl InputSource {
+    /// Whether the pressure of the input is reported by the device.
+    pub fn reports_pressure(&self) -> bool {
+        matches!(self, Self::Stylus)
+    }
+}
+
 /// The current pen state. Used wherever there is internal state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum PenState {
@@ -1,5 +1,7 @@
 // Imports
+use crate::penevent::InputSource;
 use crate::transform::Transformable;
+use crate::Transform;
 use p2d::bounding_volume::Aabb;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +15,9 @@ pub struct Element {
     #[serde(rename = "pressure", with = "crate::serialize::f64_dp3")]
     /// The pen pressure. The valid range is [0.0, 1.0].
     pub pressure: f64,
+    #[serde(skip)]
+    /// The kind of device that generated the element. Not saved, only relevant while the element is handled.
+    pub source: InputSource,
 }
 
 impl Default for Element {
@@ -35,6 +40,12 @@ impl Transformable for Element {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.pos = self.pos.component_mul(&scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        self.pos = transform.transform_point(self.pos.into()).coords;
+    }
 }
 
 impl Element {
@@ -46,9 +57,16 @@ impl Element {
         Self {
             pos,
             pressure: pressure.clamp(0.0, 1.0),
+            source: InputSource::default(),
         }
     }
 
+    /// The element generated by the given kind of device.
+    pub fn with_source(mut self, source: InputSource) -> Self {
+        self.source = source;
+        self
+    }
+
     /// Sets the pressure, clamped to the range [0.0 - 1.0].
     pub fn set_pressure_clamped(&mut self, pressure: f64) {
         self.pressure = pressure.clamp(0.0, 1.0);
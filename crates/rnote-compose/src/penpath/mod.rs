@@ -98,6 +98,13 @@ impl Transformable for PenPath {
             segment.scale(scale);
         });
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.start.shear(shear, pivot);
+        self.segments.iter_mut().for_each(|segment| {
+            segment.shear(shear, pivot);
+        });
+    }
 }
 
 impl PenPath {
@@ -1,6 +1,7 @@
 // Imports
 use super::Element;
 use crate::transform::Transformable;
+use crate::Transform;
 use serde::{Deserialize, Serialize};
 
 /// A single segment, usually of a pen path.
@@ -93,6 +94,25 @@ impl Transformable for Segment {
             }
         }
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        match self {
+            Self::LineTo { end } => {
+                end.pos = transform.transform_point(end.pos.into()).coords;
+            }
+            Self::QuadBezTo { cp, end } => {
+                *cp = transform.transform_point((*cp).into()).coords;
+                end.pos = transform.transform_point(end.pos.into()).coords;
+            }
+            Self::CubBezTo { cp1, cp2, end } => {
+                *cp1 = transform.transform_point((*cp1).into()).coords;
+                *cp2 = transform.transform_point((*cp2).into()).coords;
+                end.pos = transform.transform_point(end.pos.into()).coords;
+            }
+        }
+    }
 }
 
 impl Segment {
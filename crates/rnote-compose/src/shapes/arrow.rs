@@ -3,6 +3,7 @@ use super::Line;
 use crate::ext::Vector2Ext;
 use crate::shapes::Shapeable;
 use crate::transform::Transformable;
+use crate::Transform;
 use kurbo::{PathEl, Shape};
 use na::Rotation2;
 use p2d::bounding_volume::Aabb;
@@ -56,6 +57,13 @@ impl Transformable for Arrow {
         self.start = self.start.component_mul(&scale);
         self.tip = self.tip.component_mul(&scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        self.start = transform.transform_point(self.start.into()).coords;
+        self.tip = transform.transform_point(self.tip.into()).coords;
+    }
 }
 
 impl Shapeable for Arrow {
@@ -94,6 +102,20 @@ impl Arrow {
         Self { start, tip }
     }
 
+    /// The hitboxes of the stem and the tip lines drawn with the given stroke width,
+    /// padded by half the stroke width.
+    ///
+    /// Subdivided so that the hitboxes follow the ink closely also for long diagonal arrows.
+    pub fn hitboxes_w_stroke_width(&self, stroke_width: f64) -> Vec<Aabb> {
+        let lline = self.compute_lline(Some(stroke_width));
+        let rline = self.compute_rline(Some(stroke_width));
+
+        super::segment_hitboxes(self.start, self.tip, stroke_width)
+            .chain(super::segment_hitboxes(lline, self.tip, stroke_width))
+            .chain(super::segment_hitboxes(self.tip, rline, stroke_width))
+            .collect()
+    }
+
     /// Split the stem of the arrow into the given number of lines.
     pub fn split(&self, n_splits: i32) -> Vec<Line> {
         (0..n_splits)
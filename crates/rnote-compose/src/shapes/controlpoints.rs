@@ -0,0 +1,89 @@
+// Imports
+use super::{CubicBezier, Line, Polygon, Polyline, QuadraticBezier};
+
+/// Shapes that are defined by points which can be edited individually.
+pub trait ControlPoints {
+    /// The defining points of the shape, e.g. endpoints, vertices or bezier control points.
+    fn control_points(&self) -> Vec<na::Vector2<f64>>;
+    /// Move the control point at the index to the position.
+    ///
+    /// Indices that are out of range are ignored.
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>);
+}
+
+impl ControlPoints for Line {
+    fn control_points(&self) -> Vec<na::Vector2<f64>> {
+        vec![self.start, self.end]
+    }
+
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>) {
+        match idx {
+            0 => self.start = pos,
+            1 => self.end = pos,
+            _ => {}
+        }
+    }
+}
+
+impl ControlPoints for QuadraticBezier {
+    fn control_points(&self) -> Vec<na::Vector2<f64>> {
+        vec![self.start, self.cp, self.end]
+    }
+
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>) {
+        match idx {
+            0 => self.start = pos,
+            1 => self.cp = pos,
+            2 => self.end = pos,
+            _ => {}
+        }
+    }
+}
+
+impl ControlPoints for CubicBezier {
+    fn control_points(&self) -> Vec<na::Vector2<f64>> {
+        vec![self.start, self.cp1, self.cp2, self.end]
+    }
+
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>) {
+        match idx {
+            0 => self.start = pos,
+            1 => self.cp1 = pos,
+            2 => self.cp2 = pos,
+            3 => self.end = pos,
+            _ => {}
+        }
+    }
+}
+
+impl ControlPoints for Polyline {
+    fn control_points(&self) -> Vec<na::Vector2<f64>> {
+        std::iter::once(self.start)
+            .chain(self.path.iter().copied())
+            .collect()
+    }
+
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>) {
+        if idx == 0 {
+            self.start = pos;
+        } else if let Some(p) = self.path.get_mut(idx - 1) {
+            *p = pos;
+        }
+    }
+}
+
+impl ControlPoints for Polygon {
+    fn control_points(&self) -> Vec<na::Vector2<f64>> {
+        std::iter::once(self.start)
+            .chain(self.path.iter().copied())
+            .collect()
+    }
+
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>) {
+        if idx == 0 {
+            self.start = pos;
+        } else if let Some(p) = self.path.get_mut(idx - 1) {
+            *p = pos;
+        }
+    }
+}
@@ -4,6 +4,7 @@ use super::quadbez::QuadraticBezier;
 use crate::ext::{KurboShapeExt, Vector2Ext};
 use crate::shapes::Shapeable;
 use crate::transform::Transformable;
+use crate::Transform;
 use kurbo::Shape;
 use p2d::bounding_volume::Aabb;
 use serde::{Deserialize, Serialize};
@@ -50,6 +51,15 @@ impl Transformable for CubicBezier {
         self.cp2 = self.cp2.component_mul(&scale);
         self.end = self.end.component_mul(&scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        self.start = transform.transform_point(self.start.into()).coords;
+        self.cp1 = transform.transform_point(self.cp1.into()).coords;
+        self.cp2 = transform.transform_point(self.cp2.into()).coords;
+        self.end = transform.transform_point(self.end.into()).coords;
+    }
 }
 
 impl Shapeable for CubicBezier {
@@ -41,6 +41,10 @@ impl Transformable for Ellipse {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.transform.append_scale_mut(scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.transform.append_shear_wrt_point_mut(shear, pivot);
+    }
 }
 
 impl Shapeable for Ellipse {
@@ -93,6 +97,18 @@ impl Ellipse {
         Self { radii, transform }
     }
 
+    /// The diameters of the ellipse along its own (possibly rotated) axes.
+    pub fn diameters(&self) -> na::Vector2<f64> {
+        na::vector![
+            self.transform
+                .transform_vec(na::vector![self.radii[0] * 2.0, 0.0])
+                .magnitude(),
+            self.transform
+                .transform_vec(na::vector![0.0, self.radii[1] * 2.0])
+                .magnitude()
+        ]
+    }
+
     /// Approximate with lines.
     pub fn approx_with_lines(&self) -> Vec<Line> {
         let mut lines = Vec::new();
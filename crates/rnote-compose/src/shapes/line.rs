@@ -38,6 +38,13 @@ impl Transformable for Line {
         self.start = self.start.component_mul(&scale);
         self.end = self.end.component_mul(&scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        self.start = transform.transform_point(self.start.into()).coords;
+        self.end = transform.transform_point(self.end.into()).coords;
+    }
 }
 
 impl Shapeable for Line {
@@ -65,6 +72,23 @@ impl Line {
         Self { start, end }
     }
 
+    /// The hitboxes of the line drawn with the given stroke width, padded by half the stroke width.
+    ///
+    /// Subdivided so that the hitboxes follow the ink closely also for long diagonal lines.
+    pub fn hitboxes_w_stroke_width(&self, stroke_width: f64) -> Vec<Aabb> {
+        super::segment_hitboxes(self.start, self.end, stroke_width).collect()
+    }
+
+    /// The length of the line.
+    pub fn length(&self) -> f64 {
+        (self.end - self.start).magnitude()
+    }
+
+    /// The midpoint of the line.
+    pub fn midpoint(&self) -> na::Vector2<f64> {
+        self.start.lerp(&self.end, 0.5)
+    }
+
     /// Create a rectangle rotated in the direction of the line, with the given width.
     pub fn line_w_width_to_rect(&self, width: f64) -> Rectangle {
         let vec = self.end - self.start;
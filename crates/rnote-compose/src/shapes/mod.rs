@@ -1,6 +1,8 @@
 // Modules
 /// Arrow
 pub mod arrow;
+/// Control points
+pub mod controlpoints;
 /// Cubic-bezier curve
 pub mod cubbez;
 /// Ellipse
@@ -22,6 +24,7 @@ pub mod shapeable;
 
 // Re-exports
 pub use arrow::Arrow;
+pub use controlpoints::ControlPoints;
 pub use cubbez::CubicBezier;
 pub use ellipse::Ellipse;
 pub use line::Line;
@@ -32,9 +35,46 @@ pub use rectangle::Rectangle;
 pub use shape::Shape;
 pub use shapeable::Shapeable;
 
+// Imports
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+
 /// Calculate the number hitbox elems for the given length ( e.g. length of a line, curve, etc.).
 fn hitbox_elems_for_shape_len(len: f64) -> i32 {
     const MAX_HITBOX_DIAGONAL: f64 = 15.0;
 
     ((len / MAX_HITBOX_DIAGONAL).ceil() as i32).max(1)
 }
+
+/// Generate the hitboxes for a straight segment drawn with the given stroke width.
+///
+/// The segment is subdivided into pieces no longer than `max(stroke_width * 4, 20)`.
+/// Diagonal pieces are shortened further, because their axis-aligned bounds reach beyond the ink,
+/// so the overreach stays within about the stroke width.
+/// The hitboxes are padded by half the stroke width.
+fn segment_hitboxes(
+    start: na::Vector2<f64>,
+    end: na::Vector2<f64>,
+    stroke_width: f64,
+) -> impl Iterator<Item = Aabb> {
+    const MAX_PIECE_LEN: f64 = 20.0;
+    const MIN_PIECE_LEN: f64 = 2.0;
+
+    let stroke_width = stroke_width.max(0.0);
+    let dir = end - start;
+    let len = dir.magnitude();
+    let mut piece_len = (stroke_width * 4.0).max(MAX_PIECE_LEN);
+    if len > 0.0 {
+        // The perpendicular overreach of a piece's bounds is `piece_len * |sin(2θ)| / 2`
+        let diagonality = (2.0 * dir[0] * dir[1]).abs() / (len * len);
+        if diagonality > 0.0 {
+            piece_len = piece_len.min((2.0 * stroke_width / diagonality).max(MIN_PIECE_LEN));
+        }
+    }
+    let n_pieces = ((len / piece_len).ceil() as i32).max(1);
+
+    (0..n_pieces).map(move |i| {
+        let piece_start = start.lerp(&end, f64::from(i) / f64::from(n_pieces));
+        let piece_end = start.lerp(&end, f64::from(i + 1) / f64::from(n_pieces));
+        Aabb::new_positive(piece_start.into(), piece_end.into()).loosened(stroke_width * 0.5)
+    })
+}
@@ -2,7 +2,8 @@
 use super::{Line, Shapeable};
 use crate::ext::{AabbExt, Vector2Ext};
 use crate::transform::Transformable;
-use p2d::bounding_volume::Aabb;
+use crate::Transform;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -41,6 +42,15 @@ impl Transformable for Polygon {
             *p = p.component_mul(&scale);
         }
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        self.start = transform.transform_point(self.start.into()).coords;
+        for p in &mut self.path {
+            *p = transform.transform_point((*p).into()).coords;
+        }
+    }
 }
 
 impl Shapeable for Polygon {
@@ -83,6 +93,24 @@ impl Shapeable for Polygon {
 }
 
 impl Polygon {
+    /// The hitboxes of the polygon edges drawn with the given stroke width, padded by half the stroke width.
+    ///
+    /// Each edge is subdivided so that the hitboxes follow the ink closely also for long diagonal edges.
+    pub fn hitboxes_w_stroke_width(&self, stroke_width: f64) -> Vec<Aabb> {
+        let mut hitboxes =
+            vec![Aabb::new(self.start.into(), self.start.into())
+                .loosened(stroke_width.max(0.0) * 0.5)];
+
+        let mut prev = self.start;
+        for p in &self.path {
+            hitboxes.extend(super::segment_hitboxes(prev, *p, stroke_width));
+            prev = *p;
+        }
+        hitboxes.extend(super::segment_hitboxes(prev, self.start, stroke_width));
+
+        hitboxes
+    }
+
     /// A new polygon
     pub fn new(start: na::Vector2<f64>) -> Self {
         Self {
@@ -90,6 +118,58 @@ impl Polygon {
             path: Vec::new(),
         }
     }
+
+    /// The minimum number of points of a polygon.
+    pub const MIN_POINTS: usize = 3;
+
+    /// The number of points, including the start.
+    pub fn n_points(&self) -> usize {
+        self.path.len() + 1
+    }
+
+    /// The midpoints of the segments, including the closing segment from the last point back to the start.
+    ///
+    /// The segment at index `i` starts at the point at index `i`.
+    pub fn segment_midpoints(&self) -> Vec<na::Vector2<f64>> {
+        let points = std::iter::once(self.start)
+            .chain(self.path.iter().copied())
+            .collect::<Vec<na::Vector2<f64>>>();
+        if points.len() < 2 {
+            return vec![];
+        }
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| (a + b) * 0.5)
+            .collect()
+    }
+
+    /// Insert the point at the index, with the start being the point at index 0.
+    ///
+    /// Indices beyond the last point append the point.
+    pub fn insert_point(&mut self, idx: usize, point: na::Vector2<f64>) {
+        if idx == 0 {
+            let prev_start = std::mem::replace(&mut self.start, point);
+            self.path.insert(0, prev_start);
+        } else {
+            self.path.insert((idx - 1).min(self.path.len()), point);
+        }
+    }
+
+    /// Remove the point at the index, with the start being the point at index 0.
+    ///
+    /// Returns None when the index is out of range, or when fewer than [Self::MIN_POINTS] points would be left.
+    pub fn remove_point(&mut self, idx: usize) -> Option<na::Vector2<f64>> {
+        if idx >= self.n_points() || self.n_points() <= Self::MIN_POINTS {
+            return None;
+        }
+        if idx == 0 {
+            let new_start = self.path.remove(0);
+            Some(std::mem::replace(&mut self.start, new_start))
+        } else {
+            Some(self.path.remove(idx - 1))
+        }
+    }
 }
 
 impl Extend<na::Vector2<f64>> for Polygon {
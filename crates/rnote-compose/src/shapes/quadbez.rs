@@ -4,6 +4,7 @@ use super::CubicBezier;
 use crate::ext::{KurboShapeExt, Vector2Ext};
 use crate::shapes::Shapeable;
 use crate::transform::Transformable;
+use crate::Transform;
 use kurbo::Shape;
 use p2d::bounding_volume::Aabb;
 use serde::{Deserialize, Serialize};
@@ -44,6 +45,14 @@ impl Transformable for QuadraticBezier {
         self.cp = self.cp.component_mul(&scale);
         self.end = self.end.component_mul(&scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        self.start = transform.transform_point(self.start.into()).coords;
+        self.cp = transform.transform_point(self.cp.into()).coords;
+        self.end = transform.transform_point(self.end.into()).coords;
+    }
 }
 
 impl Shapeable for QuadraticBezier {
@@ -81,6 +81,10 @@ impl Transformable for Rectangle {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.transform.append_scale_mut(scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.transform.append_shear_wrt_point_mut(shear, pivot);
+    }
 }
 
 impl Rectangle {
@@ -112,6 +116,18 @@ impl Rectangle {
         Self { cuboid, transform }
     }
 
+    /// The size of the rect along its own (possibly rotated) axes.
+    pub fn size(&self) -> na::Vector2<f64> {
+        na::vector![
+            self.transform
+                .transform_vec(na::vector![self.cuboid.half_extents[0] * 2.0, 0.0])
+                .magnitude(),
+            self.transform
+                .transform_vec(na::vector![0.0, self.cuboid.half_extents[1] * 2.0])
+                .magnitude()
+        ]
+    }
+
     /// The outlines of the rect.
     pub fn outline_lines(&self) -> [Line; 4] {
         let upper_left = self.transform.transform_point(na::point![
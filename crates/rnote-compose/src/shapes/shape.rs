@@ -1,10 +1,15 @@
 // Imports
 use super::{
-    Arrow, CubicBezier, Ellipse, Line, Polygon, Polyline, QuadraticBezier, Rectangle, Shapeable,
+    Arrow, ControlPoints, CubicBezier, Ellipse, Line, Polygon, Polyline, QuadraticBezier,
+    Rectangle, Shapeable,
 };
+use crate::style::smooth::{Fill, SmoothOptions};
 use crate::transform::Transformable;
-use p2d::bounding_volume::Aabb;
+use crate::Color;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
 use serde::{Deserialize, Serialize};
+use svg::node::element;
+use svg::Node;
 
 /// Shape, storing shape variants.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,6 +134,35 @@ impl Transformable for Shape {
             }
         }
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        match self {
+            Self::Line(line) => {
+                line.shear(shear, pivot);
+            }
+            Self::Arrow(arrow) => {
+                arrow.shear(shear, pivot);
+            }
+            Self::Rectangle(rectangle) => {
+                rectangle.shear(shear, pivot);
+            }
+            Self::Ellipse(ellipse) => {
+                ellipse.shear(shear, pivot);
+            }
+            Self::QuadraticBezier(quadbez) => {
+                quadbez.shear(shear, pivot);
+            }
+            Self::CubicBezier(cubbez) => {
+                cubbez.shear(shear, pivot);
+            }
+            Self::Polyline(polyline) => {
+                polyline.shear(shear, pivot);
+            }
+            Self::Polygon(polygon) => {
+                polygon.shear(shear, pivot);
+            }
+        }
+    }
 }
 
 impl Shapeable for Shape {
@@ -171,3 +205,275 @@ impl Shapeable for Shape {
         }
     }
 }
+
+impl ControlPoints for Shape {
+    /// Empty for the variants that don't support editing their control points.
+    fn control_points(&self) -> Vec<na::Vector2<f64>> {
+        match self {
+            Self::Line(line) => line.control_points(),
+            Self::QuadraticBezier(quadbez) => quadbez.control_points(),
+            Self::CubicBezier(cubbez) => cubbez.control_points(),
+            Self::Polyline(polyline) => polyline.control_points(),
+            Self::Polygon(polygon) => polygon.control_points(),
+            Self::Arrow(_) | Self::Rectangle(_) | Self::Ellipse(_) => vec![],
+        }
+    }
+
+    fn set_control_point(&mut self, idx: usize, pos: na::Vector2<f64>) {
+        match self {
+            Self::Line(line) => line.set_control_point(idx, pos),
+            Self::QuadraticBezier(quadbez) => quadbez.set_control_point(idx, pos),
+            Self::CubicBezier(cubbez) => cubbez.set_control_point(idx, pos),
+            Self::Polyline(polyline) => polyline.set_control_point(idx, pos),
+            Self::Polygon(polygon) => polygon.set_control_point(idx, pos),
+            Self::Arrow(_) | Self::Rectangle(_) | Self::Ellipse(_) => {}
+        }
+    }
+}
+
+impl Shape {
+    /// The hitboxes of the shape drawn with the given stroke width, padded by half the stroke width.
+    ///
+    /// Straight segments are subdivided depending on their length and the stroke width,
+    /// so containment and intersection queries reflect the visible ink.
+    pub fn hitboxes_w_stroke_width(&self, stroke_width: f64) -> Vec<Aabb> {
+        match self {
+            Self::Line(line) => line.hitboxes_w_stroke_width(stroke_width),
+            Self::Arrow(arrow) => arrow.hitboxes_w_stroke_width(stroke_width),
+            Self::Polyline(polyline) => polyline.hitboxes_w_stroke_width(stroke_width),
+            Self::Polygon(polygon) => polygon.hitboxes_w_stroke_width(stroke_width),
+            Self::Rectangle(_)
+            | Self::Ellipse(_)
+            | Self::QuadraticBezier(_)
+            | Self::CubicBezier(_) => self
+                .hitboxes()
+                .into_iter()
+                .map(|hitbox| hitbox.loosened(stroke_width * 0.5))
+                .collect(),
+        }
+    }
+
+    /// Generate a native Svg element for the shape drawn in the smooth style.
+    ///
+    /// Rectangles, ellipses, lines, polylines and polygons are mapped to their Svg primitives,
+    /// beziers and arrows fall back to paths. The options are carried as presentation attributes.
+    /// The width profile can't be expressed with them and is ignored.
+    pub fn to_svg_element(&self, options: &SmoothOptions) -> element::Element {
+        // Mirrors what is drawn in the smooth style composer
+        let filled = matches!(
+            self,
+            Self::Rectangle(_)
+                | Self::Ellipse(_)
+                | Self::QuadraticBezier(_)
+                | Self::CubicBezier(_)
+                | Self::Polygon(_)
+        );
+        if !filled && options.stroke_color.is_none() {
+            // Nothing is drawn
+            return element::Group::new().into();
+        }
+        let (fill_paint, fill_defs) = match options.fill.as_ref() {
+            Some(fill) if filled => svg_fill_paint(fill),
+            _ => (String::from("none"), None),
+        };
+
+        let mut node: element::Element = match self {
+            Self::Rectangle(rectangle) => {
+                match orthogonal_decomposition(&rectangle.transform.affine) {
+                    Some((transform, scale)) => {
+                        let extents = (rectangle.cuboid.half_extents * 2.0).component_mul(&scale);
+                        element::Rectangle::new()
+                            .set("x", -extents[0] * 0.5)
+                            .set("y", -extents[1] * 0.5)
+                            .set("width", extents[0])
+                            .set("height", extents[1])
+                            .set("transform", transform)
+                            .into()
+                    }
+                    None => svg_path(self.outline_path()),
+                }
+            }
+            Self::Ellipse(ellipse) => match orthogonal_decomposition(&ellipse.transform.affine) {
+                Some((transform, scale)) => {
+                    let radii = ellipse.radii.component_mul(&scale);
+                    element::Ellipse::new()
+                        .set("cx", 0.0)
+                        .set("cy", 0.0)
+                        .set("rx", radii[0])
+                        .set("ry", radii[1])
+                        .set("transform", transform)
+                        .into()
+                }
+                None => svg_path(self.outline_path()),
+            },
+            Self::Line(line) => element::Line::new()
+                .set("x1", line.start[0])
+                .set("y1", line.start[1])
+                .set("x2", line.end[0])
+                .set("y2", line.end[1])
+                .into(),
+            Self::Arrow(arrow) => svg_path(arrow.to_kurbo(Some(options.stroke_width))),
+            Self::QuadraticBezier(_) | Self::CubicBezier(_) => svg_path(self.outline_path()),
+            Self::Polyline(polyline) => {
+                return svg_points_element(
+                    polyline.start,
+                    &polyline.path,
+                    options,
+                    (String::from("none"), None),
+                    false,
+                );
+            }
+            Self::Polygon(polygon) => {
+                return svg_points_element(
+                    polygon.start,
+                    &polygon.path,
+                    options,
+                    (fill_paint, fill_defs),
+                    true,
+                );
+            }
+        };
+
+        node.assign("fill", fill_paint);
+        apply_svg_stroke_attrs(&mut node, options);
+
+        match fill_defs {
+            Some(defs) => element::Group::new().add(defs).add(node).into(),
+            None => node,
+        }
+    }
+}
+
+/// Decomposes the affine into an orthonormal transform and the scale along its axes.
+///
+/// Returns None when the axes are skewed, which can't be expressed by scaling the extents of a primitive.
+fn orthogonal_decomposition(affine: &na::Affine2<f64>) -> Option<(String, na::Vector2<f64>)> {
+    let matrix = affine.matrix();
+    let x_axis = na::vector![matrix[(0, 0)], matrix[(1, 0)]];
+    let y_axis = na::vector![matrix[(0, 1)], matrix[(1, 1)]];
+    let scale = na::vector![x_axis.magnitude(), y_axis.magnitude()];
+    if scale[0] <= 0.0 || scale[1] <= 0.0 || x_axis.dot(&y_axis).abs() > 1e-6 * scale[0] * scale[1]
+    {
+        return None;
+    }
+    let (x_axis, y_axis) = (x_axis / scale[0], y_axis / scale[1]);
+
+    Some((
+        format!(
+            "matrix({:.6} {:.6} {:.6} {:.6} {:.3} {:.3})",
+            x_axis[0],
+            x_axis[1],
+            y_axis[0],
+            y_axis[1],
+            matrix[(0, 2)],
+            matrix[(1, 2)],
+        ),
+        scale,
+    ))
+}
+
+fn svg_path(bez_path: kurbo::BezPath) -> element::Element {
+    element::Path::new().set("d", bez_path.to_svg()).into()
+}
+
+/// Generate the polyline or polygon element, mirroring the special treatment of single positions
+/// in the smooth style composer.
+fn svg_points_element(
+    start: na::Vector2<f64>,
+    path: &[na::Vector2<f64>],
+    options: &SmoothOptions,
+    (fill_paint, fill_defs): (String, Option<element::Definitions>),
+    closed: bool,
+) -> element::Element {
+    let Some(stroke_color) = options.stroke_color else {
+        return element::Group::new().into();
+    };
+    if path.iter().all(|p| *p == start) {
+        return element::Circle::new()
+            .set("cx", start[0])
+            .set("cy", start[1])
+            .set("r", options.stroke_width)
+            .set("fill", stroke_color.to_css_color_attr())
+            .into();
+    }
+
+    let points = std::iter::once(start)
+        .chain(path.iter().copied())
+        .map(|p| format!("{:.3},{:.3}", p[0], p[1]))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let mut node: element::Element = if closed {
+        element::Polygon::new().set("points", points).into()
+    } else {
+        element::Polyline::new().set("points", points).into()
+    };
+    node.assign("fill", fill_paint);
+    apply_svg_stroke_attrs(&mut node, options);
+    node.assign("stroke-linejoin", "bevel");
+
+    match fill_defs {
+        Some(defs) => element::Group::new().add(defs).add(node).into(),
+        None => node,
+    }
+}
+
+fn apply_svg_stroke_attrs(node: &mut element::Element, options: &SmoothOptions) {
+    match options.stroke_color {
+        Some(stroke_color) => {
+            node.assign("stroke", stroke_color.to_css_color_attr());
+            node.assign("stroke-width", options.stroke_width);
+            // The defaults of piet differ from the Svg defaults
+            node.assign("stroke-miterlimit", 10.0);
+        }
+        None => node.assign("stroke", "none"),
+    }
+}
+
+/// The fill paint attribute value, and the definitions it references for gradients.
+fn svg_fill_paint(fill: &Fill) -> (String, Option<element::Definitions>) {
+    fn stop_element(pos: f64, color: Color) -> element::Stop {
+        element::Stop::new()
+            .set("offset", pos.clamp(0.0, 1.0))
+            .set("stop-color", Color { a: 1.0, ..color }.to_css_color_attr())
+            .set("stop-opacity", color.a)
+    }
+
+    let id = crate::utils::svg_random_id_prefix() + "_fill_gradient";
+    let gradient: element::Element = match fill {
+        Fill::Solid(color) => return (color.to_css_color_attr(), None),
+        Fill::LinearGradient { start, end, stops } => stops
+            .iter()
+            .fold(
+                element::LinearGradient::new()
+                    .set("id", id.as_str())
+                    .set("gradientUnits", "userSpaceOnUse")
+                    .set("x1", start[0])
+                    .set("y1", start[1])
+                    .set("x2", end[0])
+                    .set("y2", end[1]),
+                |gradient, stop| gradient.add(stop_element(stop.pos, stop.color)),
+            )
+            .into(),
+        Fill::RadialGradient {
+            center,
+            radius,
+            stops,
+        } => stops
+            .iter()
+            .fold(
+                element::RadialGradient::new()
+                    .set("id", id.as_str())
+                    .set("gradientUnits", "userSpaceOnUse")
+                    .set("cx", center[0])
+                    .set("cy", center[1])
+                    .set("r", *radius),
+                |gradient, stop| gradient.add(stop_element(stop.pos, stop.color)),
+            )
+            .into(),
+    };
+
+    (
+        format!("url(#{id})"),
+        Some(element::Definitions::new().add(gradient)),
+    )
+}
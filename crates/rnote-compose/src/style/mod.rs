@@ -19,8 +19,8 @@ use self::textured::TexturedOptions;
 use crate::shapes::{
     Arrow, CubicBezier, Ellipse, Line, Polygon, Polyline, QuadraticBezier, Rectangle,
 };
+use crate::transform::Transformable;
 use crate::{Color, PenPath, Shape};
-use anyhow::Context;
 pub use composer::Composer;
 use serde::{Deserialize, Serialize};
 
@@ -58,7 +58,7 @@ impl Style {
     /// Set the stroke width. Available on all styles.
     pub fn set_stroke_width(&mut self, stroke_width: f64) {
         match self {
-            Style::Smooth(options) => options.stroke_width = stroke_width,
+            Style::Smooth(options) => options.set_stroke_width(stroke_width),
             Style::Rough(options) => options.stroke_width = stroke_width,
             Style::Textured(options) => options.stroke_width = stroke_width,
         }
@@ -94,7 +94,7 @@ impl Style {
     /// Get the fill color of the style.
     pub fn fill_color(&self) -> Option<Color> {
         match self {
-            Style::Smooth(options) => options.fill_color,
+            Style::Smooth(options) => options.fill_color(),
             Style::Rough(options) => options.fill_color,
             Style::Textured(_) => None,
         }
@@ -112,11 +112,85 @@ impl Style {
     /// Set the fill color of the style.
     pub fn set_fill_color(&mut self, color: Color) {
         match self {
-            Style::Smooth(options) => options.fill_color = Some(color),
+            Style::Smooth(options) => options.set_fill_color(Some(color)),
             Style::Rough(options) => options.fill_color = Some(color),
             Style::Textured(_) => {}
         };
     }
+
+    /// Update all fill colors of the style, keeping gradients intact.
+    pub fn map_fill_colors(&mut self, f: impl Fn(Color) -> Color) {
+        match self {
+            Style::Smooth(options) => {
+                if let Some(fill) = &mut options.fill {
+                    fill.map_colors(f);
+                }
+            }
+            Style::Rough(options) => {
+                if let Some(color) = &mut options.fill_color {
+                    *color = f(*color);
+                }
+            }
+            Style::Textured(_) => {}
+        };
+    }
+
+    /// Map a fill template with coordinates relative to the bounds to document coordinates.
+    ///
+    /// See [smooth::Fill::mapped_to_bounds()].
+    pub fn map_fill_to_bounds(&mut self, bounds: p2d::bounding_volume::Aabb) {
+        if let Style::Smooth(options) = self {
+            if let Some(fill) = &mut options.fill {
+                *fill = fill.mapped_to_bounds(bounds);
+            }
+        }
+    }
+
+    /// Set the width profile of the outline from the pressures of the input. Only available on the smooth style.
+    pub fn set_width_profile_from_pressures(&mut self, pressures: &[f64]) {
+        if let Style::Smooth(options) = self {
+            options.set_width_profile_from_pressures(pressures);
+        }
+    }
+}
+
+/// Transforms the coordinates of gradient fills, so that they stay attached to the shape.
+impl Transformable for Style {
+    fn translate(&mut self, offset: na::Vector2<f64>) {
+        if let Style::Smooth(SmoothOptions {
+            fill: Some(fill), ..
+        }) = self
+        {
+            fill.translate(offset);
+        }
+    }
+
+    fn rotate(&mut self, angle: f64, center: na::Point2<f64>) {
+        if let Style::Smooth(SmoothOptions {
+            fill: Some(fill), ..
+        }) = self
+        {
+            fill.rotate(angle, center);
+        }
+    }
+
+    fn scale(&mut self, scale: na::Vector2<f64>) {
+        if let Style::Smooth(SmoothOptions {
+            fill: Some(fill), ..
+        }) = self
+        {
+            fill.scale(scale);
+        }
+    }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        if let Style::Smooth(SmoothOptions {
+            fill: Some(fill), ..
+        }) = self
+        {
+            fill.shear(shear, pivot);
+        }
+    }
 }
 
 impl Composer<Style> for Line {
@@ -310,14 +384,14 @@ impl Composer<Style> for Shape {
 }
 
 /// The pressure curve used by some styles.
-#[derive(
-    Debug, Clone, Copy, Serialize, Deserialize, num_derive::FromPrimitive, num_derive::ToPrimitive,
-)]
+///
+/// Maps the pressure to a factor between [0.0 - 1.0] that the width is multiplied with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename = "pressure_curve")]
 pub enum PressureCurve {
     /// Constant.
     #[serde(rename = "const")]
-    Const = 0,
+    Const,
     /// Linear.
     #[serde(rename = "linear")]
     Linear,
@@ -333,6 +407,24 @@ pub enum PressureCurve {
     /// Cubic polynomial.
     #[serde(rename = "pow3")]
     Pow3,
+    /// S-shaped curve, flat for light and hard pressure and steep in between.
+    #[serde(rename = "s_curve")]
+    SCurve {
+        /// How steep the curve is around the middle.
+        #[serde(rename = "steepness", with = "crate::serialize::f64_dp3")]
+        steepness: f64,
+    },
+    /// Curve through the given (pressure, factor) control points, interpolated with a monotone cubic spline.
+    ///
+    /// The points are expected to be within [0.0 - 1.0] and monotonically increasing,
+    /// so the output never reverses. Pressure outside of the points maps to the first or last factor,
+    /// so a first point like (0.0, 0.2) sets a minimum width.
+    #[serde(rename = "custom")]
+    Custom {
+        /// The control points.
+        #[serde(rename = "points")]
+        points: Vec<(f64, f64)>,
+    },
 }
 
 impl Default for PressureCurve {
@@ -342,17 +434,57 @@ impl Default for PressureCurve {
 }
 
 impl PressureCurve {
+    /// The minimum steepness of the S-curve.
+    pub const SCURVE_STEEPNESS_MIN: f64 = 0.1;
+    /// The maximum steepness of the S-curve.
+    pub const SCURVE_STEEPNESS_MAX: f64 = 50.0;
+    /// The default steepness of the S-curve.
+    pub const SCURVE_STEEPNESS_DEFAULT: f64 = 8.0;
+    /// The default control points of the custom curve.
+    pub const CUSTOM_POINTS_DEFAULT: [(f64, f64); 3] = [(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)];
+
     /// Apply the pressure curve to a width and the given pressure.
     ///
     /// Expects pressure to be between range [0.0 - 1.0].
     pub fn apply(&self, width: f64, pressure: f64) -> f64 {
+        width * self.factor(pressure)
+    }
+
+    /// The factor the width is multiplied with for the given pressure, clamped to [0.0 - 1.0].
+    pub fn factor(&self, pressure: f64) -> f64 {
+        let pressure = if pressure.is_finite() {
+            pressure.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let factor = match self {
+            Self::Const => 1.0,
+            Self::Linear => pressure,
+            Self::Sqrt => pressure.sqrt(),
+            Self::Cbrt => pressure.cbrt(),
+            Self::Pow2 => pressure.powi(2),
+            Self::Pow3 => pressure.powi(3),
+            Self::SCurve { steepness } => scurve(*steepness, pressure),
+            Self::Custom { points } => monotone_cubic(points, pressure),
+        };
+        if factor.is_finite() {
+            factor.clamp(0.0, 1.0)
+        } else {
+            pressure
+        }
+    }
+
+    /// The index of the curve kind, matching [PressureCurve::try_from()].
+    pub fn index(&self) -> u32 {
         match self {
-            Self::Const => width,
-            Self::Linear => width * pressure,
-            Self::Sqrt => width * pressure.sqrt(),
-            Self::Cbrt => width * pressure.cbrt(),
-            Self::Pow2 => width * pressure.powi(2),
-            Self::Pow3 => width * pressure.powi(3),
+            Self::Const => 0,
+            Self::Linear => 1,
+            Self::Sqrt => 2,
+            Self::Cbrt => 3,
+            Self::Pow2 => 4,
+            Self::Pow3 => 5,
+            Self::SCurve { .. } => 6,
+            Self::Custom { .. } => 7,
         }
     }
 }
@@ -360,8 +492,116 @@ impl PressureCurve {
 impl TryFrom<u32> for PressureCurve {
     type Error = anyhow::Error;
 
+    /// The curve kind for the index, with default parameters.
     fn try_from(value: u32) -> Result<Self, Self::Error> {
-        num_traits::FromPrimitive::from_u32(value)
-            .with_context(|| format!("PressureCurve try_from::<u32>() for value {value} failed"))
+        match value {
+            0 => Ok(Self::Const),
+            1 => Ok(Self::Linear),
+            2 => Ok(Self::Sqrt),
+            3 => Ok(Self::Cbrt),
+            4 => Ok(Self::Pow2),
+            5 => Ok(Self::Pow3),
+            6 => Ok(Self::SCurve {
+                steepness: Self::SCURVE_STEEPNESS_DEFAULT,
+            }),
+            7 => Ok(Self::Custom {
+                points: Self::CUSTOM_POINTS_DEFAULT.to_vec(),
+            }),
+            _ => Err(anyhow::anyhow!(
+                "PressureCurve try_from::<u32>() for value {value} failed"
+            )),
+        }
     }
 }
+
+/// A logistic curve normalized to pass through (0.0, 0.0) and (1.0, 1.0).
+fn scurve(steepness: f64, x: f64) -> f64 {
+    let steepness = steepness.clamp(
+        PressureCurve::SCURVE_STEEPNESS_MIN,
+        PressureCurve::SCURVE_STEEPNESS_MAX,
+    );
+    let logistic = |x: f64| 1.0 / (1.0 + (-steepness * (x - 0.5)).exp());
+    let (low, high) = (logistic(0.0), logistic(1.0));
+    (logistic(x) - low) / (high - low)
+}
+
+/// Evaluate the monotone cubic (PCHIP) interpolation through the points at x.
+///
+/// Points that are not finite are dropped, the others are clamped to [0.0 - 1.0], sorted and made monotone.
+/// With no points the mapping is linear, with a single point it is constant.
+fn monotone_cubic(points: &[(f64, f64)], x: f64) -> f64 {
+    let is_sanitized = points
+        .iter()
+        .all(|(x, y)| (0.0..=1.0).contains(x) && (0.0..=1.0).contains(y))
+        && points
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1);
+    let sanitized;
+    let points = if is_sanitized {
+        points
+    } else {
+        sanitized = sanitize_points(points);
+        &sanitized
+    };
+
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return x,
+    };
+    if points.len() < 2 || x <= first.0 {
+        return first.1;
+    }
+    if x >= last.0 {
+        return last.1;
+    }
+
+    let secant = |i: usize| {
+        let (p0, p1) = (points[i], points[i + 1]);
+        (p1.1 - p0.1) / (p1.0 - p0.0)
+    };
+    let tangent = |i: usize| {
+        if i == 0 {
+            return secant(0);
+        }
+        if i == points.len() - 1 {
+            return secant(i - 1);
+        }
+        let (d0, d1) = (secant(i - 1), secant(i));
+        if d0 <= 0.0 || d1 <= 0.0 {
+            return 0.0;
+        }
+        // weighted harmonic mean, which keeps the interpolation monotone
+        let (h0, h1) = (points[i].0 - points[i - 1].0, points[i + 1].0 - points[i].0);
+        let (w0, w1) = (2.0 * h1 + h0, h1 + 2.0 * h0);
+        (w0 + w1) / (w0 / d0 + w1 / d1)
+    };
+
+    let i = points
+        .windows(2)
+        .position(|w| x < w[1].0)
+        .unwrap_or(points.len() - 2);
+    let (p0, p1) = (points[i], points[i + 1]);
+    let h = p1.0 - p0.0;
+    let t = (x - p0.0) / h;
+    let (t2, t3) = (t * t, t * t * t);
+    (2.0 * t3 - 3.0 * t2 + 1.0) * p0.1
+        + (t3 - 2.0 * t2 + t) * h * tangent(i)
+        + (-2.0 * t3 + 3.0 * t2) * p1.1
+        + (t3 - t2) * h * tangent(i + 1)
+}
+
+fn sanitize_points(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut points = points
+        .iter()
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .map(|(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+        .collect::<Vec<(f64, f64)>>();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    points.dedup_by(|b, a| a.0 == b.0);
+    let mut max = 0.0_f64;
+    for p in points.iter_mut() {
+        max = max.max(p.1);
+        p.1 = max;
+    }
+    points
+}
@@ -0,0 +1,270 @@
+// Imports
+use crate::ext::Vector2Ext;
+use crate::transform::Transformable;
+use crate::Color;
+use crate::Transform;
+use p2d::bounding_volume::Aabb;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A color stop of a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "gradient_stop")]
+pub struct GradientStop {
+    /// The position of the stop along the gradient, between 0.0 and 1.0.
+    #[serde(rename = "pos", with = "crate::serialize::f64_dp3")]
+    pub pos: f64,
+    /// The color of the stop.
+    #[serde(rename = "color")]
+    pub color: Color,
+}
+
+impl Default for GradientStop {
+    fn default() -> Self {
+        Self {
+            pos: 0.0,
+            color: Color::BLACK,
+        }
+    }
+}
+
+impl GradientStop {
+    /// A new gradient stop.
+    pub fn new(pos: f64, color: Color) -> Self {
+        Self { pos, color }
+    }
+}
+
+impl From<GradientStop> for piet::GradientStop {
+    fn from(stop: GradientStop) -> Self {
+        piet::GradientStop {
+            pos: stop.pos.clamp(0.0, 1.0) as f32,
+            color: stop.color.into(),
+        }
+    }
+}
+
+/// The fill of a shape.
+///
+/// Gradient coordinates are in document coordinates.
+/// When used as a template for new shapes, they are relative to the bounds of the shape (from 0.0 to 1.0)
+/// and need to be mapped with [Fill::mapped_to_bounds()].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "fill")]
+pub enum Fill {
+    /// A single color.
+    #[serde(rename = "solid")]
+    Solid(Color),
+    /// A linear gradient.
+    #[serde(rename = "linear_gradient")]
+    LinearGradient {
+        /// The start coordinate, where the stop position is 0.0.
+        #[serde(rename = "start", with = "crate::serialize::na_vector2_f64_dp3")]
+        start: na::Vector2<f64>,
+        /// The end coordinate, where the stop position is 1.0.
+        #[serde(rename = "end", with = "crate::serialize::na_vector2_f64_dp3")]
+        end: na::Vector2<f64>,
+        /// The color stops.
+        #[serde(rename = "stops")]
+        stops: Vec<GradientStop>,
+    },
+    /// A radial gradient.
+    #[serde(rename = "radial_gradient")]
+    RadialGradient {
+        /// The center coordinate, where the stop position is 0.0.
+        #[serde(rename = "center", with = "crate::serialize::na_vector2_f64_dp3")]
+        center: na::Vector2<f64>,
+        /// The radius, where the stop position is 1.0.
+        #[serde(rename = "radius", with = "crate::serialize::f64_dp3")]
+        radius: f64,
+        /// The color stops.
+        #[serde(rename = "stops")]
+        stops: Vec<GradientStop>,
+    },
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Self::Solid(Color::BLACK)
+    }
+}
+
+impl From<Color> for Fill {
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
+    }
+}
+
+impl Transformable for Fill {
+    fn translate(&mut self, offset: na::Vector2<f64>) {
+        match self {
+            Fill::Solid(_) => {}
+            Fill::LinearGradient { start, end, .. } => {
+                *start += offset;
+                *end += offset;
+            }
+            Fill::RadialGradient { center, .. } => {
+                *center += offset;
+            }
+        }
+    }
+
+    fn rotate(&mut self, angle: f64, center: na::Point2<f64>) {
+        let mut isometry = na::Isometry2::identity();
+        isometry.append_rotation_wrt_point_mut(&na::UnitComplex::new(angle), &center);
+
+        match self {
+            Fill::Solid(_) => {}
+            Fill::LinearGradient { start, end, .. } => {
+                *start = isometry.transform_point(&(*start).into()).coords;
+                *end = isometry.transform_point(&(*end).into()).coords;
+            }
+            Fill::RadialGradient {
+                center: gradient_center,
+                ..
+            } => {
+                *gradient_center = isometry.transform_point(&(*gradient_center).into()).coords;
+            }
+        }
+    }
+
+    fn scale(&mut self, scale: na::Vector2<f64>) {
+        match self {
+            Fill::Solid(_) => {}
+            Fill::LinearGradient { start, end, .. } => {
+                *start = start.component_mul(&scale);
+                *end = end.component_mul(&scale);
+            }
+            Fill::RadialGradient { center, radius, .. } => {
+                *center = center.component_mul(&scale);
+                // Using the geometric mean behaves the best when scaling non-uniformly.
+                *radius *= (scale[0] * scale[1]).abs().sqrt();
+            }
+        }
+    }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        let transform = Transform::new_w_shear_wrt_point(shear, pivot);
+
+        match self {
+            Fill::Solid(_) => {}
+            Fill::LinearGradient { start, end, .. } => {
+                *start = transform.transform_point((*start).into()).coords;
+                *end = transform.transform_point((*end).into()).coords;
+            }
+            Fill::RadialGradient { center, radius, .. } => {
+                *center = transform.transform_point((*center).into()).coords;
+                // Keeps the area of the gradient the same as the sheared area.
+                *radius *= (1.0 - shear[0] * shear[1]).abs().sqrt();
+            }
+        }
+    }
+}
+
+impl Fill {
+    /// A linear gradient that fades the color out along the given angle, relative to the bounds of a shape.
+    ///
+    /// Needs to be mapped with [Fill::mapped_to_bounds()] before it is drawn.
+    pub fn linear_fade_out_relative(color: Color, angle: f64) -> Self {
+        let dir = na::Vector2::new(angle.cos(), angle.sin()) * 0.5;
+        let center = na::Vector2::new(0.5, 0.5);
+
+        Self::LinearGradient {
+            start: center - dir,
+            end: center + dir,
+            stops: vec![
+                GradientStop::new(0.0, color),
+                GradientStop::new(1.0, Color { a: 0.0, ..color }),
+            ],
+        }
+    }
+
+    /// The color that represents the fill best.
+    ///
+    /// For gradients this is the color of the first stop.
+    pub fn representative_color(&self) -> Color {
+        match self {
+            Fill::Solid(color) => *color,
+            Fill::LinearGradient { stops, .. } | Fill::RadialGradient { stops, .. } => {
+                stops.first().map(|s| s.color).unwrap_or(Color::TRANSPARENT)
+            }
+        }
+    }
+
+    /// Update all colors of the fill.
+    pub fn map_colors(&mut self, f: impl Fn(Color) -> Color) {
+        match self {
+            Fill::Solid(color) => *color = f(*color),
+            Fill::LinearGradient { stops, .. } | Fill::RadialGradient { stops, .. } => {
+                stops.iter_mut().for_each(|stop| stop.color = f(stop.color))
+            }
+        }
+    }
+
+    /// Map the gradient coordinates from coordinates relative to the bounds to document coordinates.
+    pub fn mapped_to_bounds(&self, bounds: Aabb) -> Self {
+        let extents = bounds.extents();
+        let map = |v: na::Vector2<f64>| bounds.mins.coords + v.component_mul(&extents);
+
+        match self {
+            Fill::Solid(color) => Fill::Solid(*color),
+            Fill::LinearGradient { start, end, stops } => Fill::LinearGradient {
+                start: map(*start),
+                end: map(*end),
+                stops: stops.clone(),
+            },
+            Fill::RadialGradient {
+                center,
+                radius,
+                stops,
+            } => Fill::RadialGradient {
+                center: map(*center),
+                radius: radius * extents.max(),
+                stops: stops.clone(),
+            },
+        }
+    }
+
+    /// Create the brush for the fill.
+    pub fn brush<R: piet::RenderContext>(&self, cx: &mut R) -> Result<R::Brush, piet::Error> {
+        match self {
+            Fill::Solid(color) => Ok(cx.solid_brush((*color).into())),
+            Fill::LinearGradient { start, end, stops } => {
+                cx.gradient(piet::FixedGradient::Linear(piet::FixedLinearGradient {
+                    start: start.to_kurbo_point(),
+                    end: end.to_kurbo_point(),
+                    stops: stops.iter().map(|&s| s.into()).collect(),
+                }))
+            }
+            Fill::RadialGradient {
+                center,
+                radius,
+                stops,
+            } => cx.gradient(piet::FixedGradient::Radial(piet::FixedRadialGradient {
+                center: center.to_kurbo_point(),
+                origin_offset: kurbo::Vec2::ZERO,
+                radius: *radius,
+                stops: stops.iter().map(|&s| s.into()).collect(),
+            })),
+        }
+    }
+}
+
+/// Deserializes the fill, also accepting a plain color from the former `fill_color` field.
+pub(super) fn deserialize_fill_or_color<'de, D>(deserializer: D) -> Result<Option<Fill>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FillOrColor {
+        Fill(Fill),
+        Color(Color),
+    }
+
+    Ok(
+        Option::<FillOrColor>::deserialize(deserializer)?.map(|fill| match fill {
+            FillOrColor::Fill(fill) => fill,
+            FillOrColor::Color(color) => Fill::Solid(color),
+        }),
+    )
+}
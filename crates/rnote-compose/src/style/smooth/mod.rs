@@ -1,7 +1,10 @@
 // Modules
+mod fill;
 mod smoothoptions;
+mod widthprofile;
 
 // Re-exports
+pub use fill::{Fill, GradientStop};
 pub use smoothoptions::SmoothOptions;
 
 // Imports
@@ -11,7 +14,7 @@ use crate::penpath::{self, Segment};
 use crate::shapes::{
     Arrow, CubicBezier, Ellipse, Line, Polygon, Polyline, QuadraticBezier, Rectangle, Shapeable,
 };
-use crate::PenPath;
+use crate::{Color, PenPath};
 use kurbo::Shape;
 use p2d::bounding_volume::{Aabb, BoundingVolume};
 
@@ -25,8 +28,13 @@ impl Composer<SmoothOptions> for Line {
         let line = self.outline_path();
 
         if let Some(stroke_color) = options.stroke_color {
-            let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(line, &stroke_brush, options.stroke_width);
+            stroke_outline(
+                cx,
+                line,
+                stroke_color,
+                options,
+                &piet::StrokeStyle::default(),
+            );
         }
         cx.restore().unwrap();
     }
@@ -43,10 +51,12 @@ impl Composer<SmoothOptions> for Arrow {
 
         if let Some(stroke_color) = options.stroke_color {
             let arrow = self.to_kurbo(Some(options.stroke_width));
-            cx.stroke(
+            stroke_outline(
+                cx,
                 arrow,
-                &Into::<piet::Color>::into(stroke_color),
-                options.stroke_width,
+                stroke_color,
+                options,
+                &piet::StrokeStyle::default(),
             );
         }
 
@@ -63,14 +73,18 @@ impl Composer<SmoothOptions> for Rectangle {
         cx.save().unwrap();
         let shape = self.outline_path();
 
-        if let Some(fill_color) = options.fill_color {
-            let fill_brush = cx.solid_brush(fill_color.into());
+        if let Some(fill_brush) = options.fill.as_ref().and_then(|f| f.brush(cx).ok()) {
             cx.fill(shape.clone(), &fill_brush);
         }
 
         if let Some(stroke_color) = options.stroke_color {
-            let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(shape, &stroke_brush, options.stroke_width);
+            stroke_outline(
+                cx,
+                shape,
+                stroke_color,
+                options,
+                &piet::StrokeStyle::default(),
+            );
         }
         cx.restore().unwrap();
     }
@@ -85,14 +99,18 @@ impl Composer<SmoothOptions> for Ellipse {
         cx.save().unwrap();
         let ellipse = self.outline_path();
 
-        if let Some(fill_color) = options.fill_color {
-            let fill_brush = cx.solid_brush(fill_color.into());
+        if let Some(fill_brush) = options.fill.as_ref().and_then(|f| f.brush(cx).ok()) {
             cx.fill(&ellipse, &fill_brush);
         }
 
         if let Some(stroke_color) = options.stroke_color {
-            let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(ellipse, &stroke_brush, options.stroke_width);
+            stroke_outline(
+                cx,
+                ellipse,
+                stroke_color,
+                options,
+                &piet::StrokeStyle::default(),
+            );
         }
         cx.restore().unwrap();
     }
@@ -107,14 +125,18 @@ impl Composer<SmoothOptions> for QuadraticBezier {
         cx.save().unwrap();
         let quadbez = self.outline_path();
 
-        if let Some(fill_color) = options.fill_color {
-            let fill_brush = cx.solid_brush(fill_color.into());
+        if let Some(fill_brush) = options.fill.as_ref().and_then(|f| f.brush(cx).ok()) {
             cx.fill(&quadbez, &fill_brush);
         }
 
         if let Some(stroke_color) = options.stroke_color {
-            let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(quadbez, &stroke_brush, options.stroke_width);
+            stroke_outline(
+                cx,
+                quadbez,
+                stroke_color,
+                options,
+                &piet::StrokeStyle::default(),
+            );
         }
         cx.restore().unwrap();
     }
@@ -129,14 +151,18 @@ impl Composer<SmoothOptions> for CubicBezier {
         cx.save().unwrap();
         let cubbez = self.outline_path();
 
-        if let Some(fill_color) = options.fill_color {
-            let fill_brush = cx.solid_brush(fill_color.into());
+        if let Some(fill_brush) = options.fill.as_ref().and_then(|f| f.brush(cx).ok()) {
             cx.fill(&cubbez, &fill_brush);
         }
 
         if let Some(stroke_color) = options.stroke_color {
-            let stroke_brush = cx.solid_brush(stroke_color.into());
-            cx.stroke(cubbez, &stroke_brush, options.stroke_width);
+            stroke_outline(
+                cx,
+                cubbez,
+                stroke_color,
+                options,
+                &piet::StrokeStyle::default(),
+            );
         }
         cx.restore().unwrap();
     }
@@ -161,10 +187,11 @@ impl Composer<SmoothOptions> for Polyline {
                 &Into::<piet::Color>::into(color),
             );
         } else {
-            cx.stroke_styled(
+            stroke_outline(
+                cx,
                 self.outline_path(),
-                &Into::<piet::Color>::into(color),
-                options.stroke_width,
+                color,
+                options,
                 &piet::StrokeStyle::default()
                     .line_cap(piet::LineCap::Butt)
                     .line_join(piet::LineJoin::Bevel),
@@ -193,14 +220,15 @@ impl Composer<SmoothOptions> for Polygon {
             );
         } else {
             let outline_path = self.outline_path();
-            if let Some(fill_color) = options.fill_color {
-                cx.fill(&outline_path, &Into::<piet::Color>::into(fill_color));
+            if let Some(fill_brush) = options.fill.as_ref().and_then(|f| f.brush(cx).ok()) {
+                cx.fill(&outline_path, &fill_brush);
             }
 
-            cx.stroke_styled(
-                &outline_path,
-                &Into::<piet::Color>::into(color),
-                options.stroke_width,
+            stroke_outline(
+                cx,
+                outline_path,
+                color,
+                options,
                 &piet::StrokeStyle::default()
                     .line_cap(piet::LineCap::Butt)
                     .line_join(piet::LineJoin::Bevel),
@@ -361,6 +389,39 @@ impl Composer<SmoothOptions> for crate::Shape {
     }
 }
 
+/// Strokes the outline of a shape, or fills it composed with variable widths when the options have a width profile.
+///
+/// Round joins of variable width outlines are beveled.
+fn stroke_outline(
+    cx: &mut impl piet::RenderContext,
+    outline: kurbo::BezPath,
+    color: Color,
+    options: &SmoothOptions,
+    style: &piet::StrokeStyle,
+) {
+    match options.width_profile.as_deref() {
+        Some(width_profile) => {
+            let miter_limit = match style.line_join {
+                piet::LineJoin::Miter { limit } => Some(limit),
+                piet::LineJoin::Round | piet::LineJoin::Bevel => None,
+            };
+            let bez_path = widthprofile::compose_outline_variable_width(
+                &outline,
+                width_profile,
+                options.stroke_width,
+                miter_limit,
+            );
+            cx.fill(bez_path, &Into::<piet::Color>::into(color));
+        }
+        None => cx.stroke_styled(
+            outline,
+            &Into::<piet::Color>::into(color),
+            options.stroke_width,
+            style,
+        ),
+    }
+}
+
 /// Composes lines with variable width. Must be drawn with only a fill.
 fn compose_lines_variable_width(
     lines: &[Line],
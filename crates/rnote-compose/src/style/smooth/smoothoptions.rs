@@ -1,33 +1,146 @@
 // Imports
+use super::fill::{self, Fill};
+use super::widthprofile;
 use crate::style::PressureCurve;
 use crate::Color;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::warn;
 
 /// Options for shapes that can be drawn in a smooth style.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "smooth_options")]
 pub struct SmoothOptions {
-    /// Stroke width.
-    #[serde(rename = "stroke_width", with = "crate::serialize::f64_dp3")]
-    pub stroke_width: f64,
+    /// Stroke width, see [SmoothOptions::set_stroke_width].
+    ///
+    /// Invalid widths in loaded files are repaired.
+    #[serde(
+        rename = "stroke_width",
+        serialize_with = "crate::serialize::f64_dp3::serialize",
+        deserialize_with = "deserialize_stroke_width"
+    )]
+    pub(crate) stroke_width: f64,
     /// Stroke color. When set to None, the stroke outline is not drawn.
     #[serde(rename = "stroke_color")]
     pub stroke_color: Option<Color>,
-    /// Fill color. When set to None, the fill is not drawn.
-    #[serde(rename = "fill_color")]
-    pub fill_color: Option<Color>,
+    /// Fill. When set to None, the fill is not drawn.
+    ///
+    /// Also loaded from the former `fill_color` field, which was only a flat color.
+    #[serde(
+        rename = "fill",
+        alias = "fill_color",
+        deserialize_with = "fill::deserialize_fill_or_color"
+    )]
+    pub fill: Option<Fill>,
     /// Pressure curve.
     #[serde(rename = "pressure_curve")]
     pub pressure_curve: PressureCurve,
+    /// The widths along the outline of shapes, as pairs of the parameter along the outline in the range [0.0 - 1.0]
+    /// and the factor the stroke width is multiplied with. Expected to be sorted by the parameter.
+    ///
+    /// When set to None, the outline is drawn with the constant stroke width.
+    #[serde(rename = "width_profile", skip_serializing_if = "Option::is_none")]
+    pub width_profile: Option<Vec<(f64, f64)>>,
 }
 
 impl Default for SmoothOptions {
     fn default() -> Self {
         Self {
-            stroke_width: 2.0,
+            stroke_width: Self::STROKE_WIDTH_DEFAULT,
             stroke_color: Some(Color::BLACK),
-            fill_color: None,
+            fill: None,
             pressure_curve: PressureCurve::default(),
+            width_profile: None,
+        }
+    }
+}
+
+impl SmoothOptions {
+    /// The default stroke width.
+    pub const STROKE_WIDTH_DEFAULT: f64 = 2.0;
+    /// The minimum stroke width.
+    pub const STROKE_WIDTH_MIN: f64 = 0.1;
+    /// The maximum stroke width.
+    pub const STROKE_WIDTH_MAX: f64 = 1000.0;
+    /// The maximum number of points of the width profile.
+    pub const WIDTH_PROFILE_POINTS_MAX: usize = 64;
+
+    /// The stroke width.
+    pub fn stroke_width(&self) -> f64 {
+        self.stroke_width
+    }
+
+    /// Set the stroke width, clamped to the range from [SmoothOptions::STROKE_WIDTH_MIN]
+    /// to [SmoothOptions::STROKE_WIDTH_MAX].
+    ///
+    /// Non-finite widths are rejected, leaving the current width unchanged.
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        if !stroke_width.is_finite() {
+            warn!("Setting invalid smooth stroke width {stroke_width} rejected.");
+            return;
+        }
+        self.stroke_width = stroke_width.clamp(Self::STROKE_WIDTH_MIN, Self::STROKE_WIDTH_MAX);
+    }
+
+    /// The fill color. For gradient fills this is the color of the first stop.
+    pub fn fill_color(&self) -> Option<Color> {
+        self.fill.as_ref().map(Fill::representative_color)
+    }
+
+    /// Set a flat fill color, replacing any gradient.
+    pub fn set_fill_color(&mut self, color: Option<Color>) {
+        self.fill = color.map(Fill::Solid);
+    }
+
+    /// The factor the stroke width is multiplied with at the parameter along the outline.
+    ///
+    /// Is 1.0 when there is no width profile.
+    pub fn width_factor_at(&self, t: f64) -> f64 {
+        self.width_profile
+            .as_deref()
+            .map(|width_profile| widthprofile::sample(width_profile, t))
+            .unwrap_or(1.0)
+    }
+
+    /// Set the width profile from the pressures of the input, mapped through the pressure curve.
+    ///
+    /// The pressures are distributed evenly along the outline
+    /// and resampled to at most [SmoothOptions::WIDTH_PROFILE_POINTS_MAX] points.
+    pub fn set_width_profile_from_pressures(&mut self, pressures: &[f64]) {
+        let factors = pressures
+            .iter()
+            .map(|pressure| self.pressure_curve.factor(*pressure))
+            .collect::<Vec<f64>>();
+        self.width_profile = if factors.is_empty() {
+            None
+        } else {
+            Some(widthprofile::from_values(
+                &factors,
+                Self::WIDTH_PROFILE_POINTS_MAX,
+            ))
+        };
+    }
+}
+
+/// Deserialize the stroke width, repairing widths that are out of range or not a number.
+///
+/// Non-finite numbers are serialized as `null`, so these are accepted and replaced with the default width.
+fn deserialize_stroke_width<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    match Option::<f64>::deserialize(d)? {
+        Some(stroke_width) if stroke_width.is_finite() => {
+            let clamped = stroke_width.clamp(
+                SmoothOptions::STROKE_WIDTH_MIN,
+                SmoothOptions::STROKE_WIDTH_MAX,
+            );
+            if clamped != stroke_width {
+                warn!("Loaded smooth stroke width {stroke_width} is out of range, clamped to {clamped}.");
+            }
+            Ok(clamped)
+        }
+        stroke_width => {
+            warn!(
+                "Loaded invalid smooth stroke width {stroke_width:?}, replaced with the default width."
+            );
+            Ok(SmoothOptions::STROKE_WIDTH_DEFAULT)
         }
     }
 }
@@ -0,0 +1,220 @@
+// Imports
+use crate::ext::Vector2Ext;
+
+/// Sample the width factor of the profile at the parameter, interpolating linearly between the points.
+///
+/// Expects the points to be sorted by their parameter. Outside of the profile the factor of the nearest point is used.
+pub(super) fn sample(width_profile: &[(f64, f64)], t: f64) -> f64 {
+    let (Some(first), Some(last)) = (width_profile.first(), width_profile.last()) else {
+        return 1.0;
+    };
+    if t <= first.0 {
+        return first.1;
+    }
+    if t >= last.0 {
+        return last.1;
+    }
+    width_profile
+        .windows(2)
+        .find(|w| t <= w[1].0)
+        .map(|w| {
+            let (start, end) = (w[0], w[1]);
+            let span = end.0 - start.0;
+            if span <= 0.0 {
+                end.1
+            } else {
+                start.1 + (end.1 - start.1) * (t - start.0) / span
+            }
+        })
+        .unwrap_or(last.1)
+}
+
+/// Resample the values evenly distributed over the parameter range down to at most the given number of points.
+pub(super) fn from_values(values: &[f64], points_max: usize) -> Vec<(f64, f64)> {
+    let n_values = values.len();
+    if n_values <= 1 {
+        return values.iter().map(|v| (0.0, *v)).collect();
+    }
+    let n_points = n_values.min(points_max.max(2));
+
+    (0..n_points)
+        .map(|i| {
+            let t = i as f64 / (n_points - 1) as f64;
+            let pos = t * (n_values - 1) as f64;
+            let i_value = (pos.floor() as usize).min(n_values - 2);
+            let frac = pos - i_value as f64;
+            let value = values[i_value] + (values[i_value + 1] - values[i_value]) * frac;
+            (t, value)
+        })
+        .collect()
+}
+
+/// A flattened subpath of an outline.
+#[derive(Debug, Clone)]
+struct FlatSubpath {
+    points: Vec<na::Vector2<f64>>,
+    closed: bool,
+}
+
+/// Flatten the outline into its subpaths, dropping consecutive duplicate points.
+fn flatten_subpaths(outline: &kurbo::BezPath, tolerance: f64) -> Vec<FlatSubpath> {
+    let mut subpaths = Vec::new();
+    let mut current: Option<FlatSubpath> = None;
+
+    kurbo::flatten(outline.iter(), tolerance, |el| match el {
+        kurbo::PathEl::MoveTo(p) => {
+            subpaths.extend(current.take());
+            current = Some(FlatSubpath {
+                points: vec![na::Vector2::from_kurbo_point(p)],
+                closed: false,
+            });
+        }
+        kurbo::PathEl::LineTo(p) => {
+            let p = na::Vector2::from_kurbo_point(p);
+            let subpath = current.get_or_insert_with(|| FlatSubpath {
+                points: vec![],
+                closed: false,
+            });
+            if subpath.points.last() != Some(&p) {
+                subpath.points.push(p);
+            }
+        }
+        kurbo::PathEl::ClosePath => {
+            if let Some(mut subpath) = current.take() {
+                subpath.closed = true;
+                if subpath.points.len() > 1 && subpath.points.first() == subpath.points.last() {
+                    subpath.points.pop();
+                }
+                subpaths.push(subpath);
+            }
+        }
+        // Flattening only emits lines
+        kurbo::PathEl::QuadTo(..) | kurbo::PathEl::CurveTo(..) => {}
+    });
+    subpaths.extend(current);
+
+    subpaths
+        .into_iter()
+        .filter(|subpath| subpath.points.len() > 1)
+        .collect()
+}
+
+/// The offsets of the point at both sides of the outline, for the normals of the adjacent segments.
+///
+/// Joins are mitered up to the miter limit and beveled otherwise, so a join can have two offset points.
+fn join_offsets(
+    normal_in: Option<na::Vector2<f64>>,
+    normal_out: Option<na::Vector2<f64>>,
+    half_width: f64,
+    miter_limit: Option<f64>,
+) -> Vec<na::Vector2<f64>> {
+    match (normal_in, normal_out) {
+        (Some(normal_in), Some(normal_out)) => {
+            let miter_dir = normal_in + normal_out;
+            let miter_dir_len = miter_dir.magnitude();
+            // The cosine of the angle between the normals and the miter direction
+            let cos = miter_dir_len * 0.5;
+            match miter_limit {
+                Some(miter_limit) if miter_dir_len > 0.0 && cos * miter_limit >= 1.0 => {
+                    vec![miter_dir / miter_dir_len * half_width / cos]
+                }
+                _ => vec![normal_in * half_width, normal_out * half_width],
+            }
+        }
+        (Some(normal), None) | (None, Some(normal)) => vec![normal * half_width],
+        (None, None) => vec![],
+    }
+}
+
+/// Compose the outline with the widths of the profile along it. Must be drawn with only a fill.
+///
+/// The parameter of the profile is the length along all subpaths of the outline, normalized to [0.0 - 1.0].
+/// Mirrors stroking the outline with butt caps, the curves are flattened with a tolerance relative to the stroke width.
+pub(super) fn compose_outline_variable_width(
+    outline: &kurbo::BezPath,
+    width_profile: &[(f64, f64)],
+    stroke_width: f64,
+    miter_limit: Option<f64>,
+) -> kurbo::BezPath {
+    let tolerance = (stroke_width * 0.05).clamp(0.01, 0.25);
+    let subpaths = flatten_subpaths(outline, tolerance);
+    let subpath_len = |subpath: &FlatSubpath| -> f64 {
+        let open_len = subpath
+            .points
+            .windows(2)
+            .map(|w| (w[1] - w[0]).magnitude())
+            .sum::<f64>();
+        if subpath.closed {
+            open_len + (subpath.points[0] - subpath.points[subpath.points.len() - 1]).magnitude()
+        } else {
+            open_len
+        }
+    };
+    let total_len = subpaths.iter().map(subpath_len).sum::<f64>();
+    let mut bez_path = kurbo::BezPath::new();
+    if total_len <= 0.0 {
+        return bez_path;
+    }
+
+    let mut len = 0.0;
+    for subpath in subpaths {
+        let points = &subpath.points;
+        let n_points = points.len();
+        let n_segments = if subpath.closed {
+            n_points
+        } else {
+            n_points - 1
+        };
+        let normals = (0..n_segments)
+            .map(|i| (points[(i + 1) % n_points] - points[i]).orth_unit())
+            .collect::<Vec<na::Vector2<f64>>>();
+
+        let mut pos_offset_coords = Vec::with_capacity(n_points);
+        let mut neg_offset_coords = Vec::with_capacity(n_points);
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                len += (point - points[i - 1]).magnitude();
+            }
+            let half_width = stroke_width * sample(width_profile, len / total_len) * 0.5;
+            let normal_in = match i {
+                0 if subpath.closed => normals.last().copied(),
+                0 => None,
+                i => Some(normals[i - 1]),
+            };
+            let normal_out = normals.get(i).copied();
+
+            let offsets = join_offsets(normal_in, normal_out, half_width, miter_limit);
+            pos_offset_coords.extend(offsets.iter().map(|offset| point + offset));
+            neg_offset_coords.extend(offsets.iter().map(|offset| point - offset));
+        }
+
+        let mut pos_offset_coords = pos_offset_coords.into_iter();
+        let mut neg_offset_coords = neg_offset_coords.into_iter().rev();
+        if subpath.closed {
+            len += (points[0] - points[n_points - 1]).magnitude();
+            // Both sides are separate rings, the reversed inner one cuts out the enclosed area
+            if let Some(first) = pos_offset_coords.next() {
+                bez_path.move_to(first.to_kurbo_point());
+                bez_path
+                    .extend(pos_offset_coords.map(|c| kurbo::PathEl::LineTo(c.to_kurbo_point())));
+                bez_path.close_path();
+            }
+            if let Some(first) = neg_offset_coords.next() {
+                bez_path.move_to(first.to_kurbo_point());
+                bez_path
+                    .extend(neg_offset_coords.map(|c| kurbo::PathEl::LineTo(c.to_kurbo_point())));
+                bez_path.close_path();
+            }
+        } else if let Some(first) = pos_offset_coords.next() {
+            bez_path.move_to(first.to_kurbo_point());
+            bez_path.extend(
+                pos_offset_coords
+                    .chain(neg_offset_coords)
+                    .map(|c| kurbo::PathEl::LineTo(c.to_kurbo_point())),
+            );
+            bez_path.close_path();
+        }
+    }
+
+    bez_path
+}
@@ -53,6 +53,10 @@ impl Transformable for Transform {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.append_scale_mut(scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.append_shear_wrt_point_mut(shear, pivot);
+    }
 }
 
 impl Transform {
@@ -68,6 +72,19 @@ impl Transform {
         }
     }
 
+    /// Construct a new transform that shears by the shear-factors around the pivot.
+    pub fn new_w_shear_wrt_point(shear: na::Vector2<f64>, pivot: na::Point2<f64>) -> Self {
+        #[rustfmt::skip]
+        let matrix = na::Matrix3::new(
+            1.0, shear[0], -shear[0] * pivot[1],
+            shear[1], 1.0, -shear[1] * pivot[0],
+            0.0, 0.0, 1.0,
+        );
+        Self {
+            affine: na::Affine2::from_matrix_unchecked(matrix),
+        }
+    }
+
     /// The translation part of the transform.
     pub fn translation_part(&self) -> na::Vector2<f64> {
         (self.affine * na::point![0.0, 0.0]).coords
@@ -120,6 +137,11 @@ impl Transform {
         .unwrap();
     }
 
+    /// Append a shear around a point to the transform.
+    pub fn append_shear_wrt_point_mut(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.affine = Self::new_w_shear_wrt_point(shear, pivot).affine * self.affine;
+    }
+
     /// Convert the transform to a Svg attribute string, insertable into svg elements.
     pub fn to_svg_transform_attr_str(&self) -> String {
         let matrix = self.affine;
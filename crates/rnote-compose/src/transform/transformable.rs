@@ -6,4 +6,9 @@ pub trait Transformable {
     fn rotate(&mut self, angle: f64, center: na::Point2<f64>);
     /// Scale by the given scale-factor.
     fn scale(&mut self, scale: na::Vector2<f64>);
+    /// Shear by the given shear-factors around the pivot.
+    ///
+    /// The x component shears horizontally proportional to the vertical distance from the pivot,
+    /// the y component vertically proportional to the horizontal distance.
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>);
 }
@@ -83,6 +83,33 @@ pub fn wrap_svg_root(
     svg_node_to_string(&svg_root).unwrap()
 }
 
+/// Wrap the svg data in a svg root element with the width and height in the given units,
+/// mapping the viewbox onto it.
+///
+/// The width and height must include their unit, e.g. `"25.400mm"`.
+pub fn wrap_svg_root_w_size(svg_data: &str, width: &str, height: &str, viewbox: Aabb) -> String {
+    let viewbox = format!(
+        "{:.3} {:.3} {:.3} {:.3}",
+        viewbox.mins[0],
+        viewbox.mins[1],
+        viewbox.extents()[0],
+        viewbox.extents()[1]
+    );
+
+    let svg_root = svg::node::element::SVG::new()
+        .set("xmlns", "http://www.w3.org/2000/svg")
+        .set("xmlns:svg", "http://www.w3.org/2000/svg")
+        .set("xmlns:xlink", "http://www.w3.org/1999/xlink")
+        .set("width", width)
+        .set("height", height)
+        .set("viewBox", viewbox.as_str())
+        .set("preserveAspectRatio", "none")
+        .add(svg::node::Blob::new(svg_data));
+
+    // unwrapping because we know its a valid Svg
+    svg_node_to_string(&svg_root).unwrap()
+}
+
 /// Convert a [svg::Node] to a String
 pub fn svg_node_to_string<N>(node: &N) -> Result<String, anyhow::Error>
 where
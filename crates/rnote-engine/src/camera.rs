@@ -77,6 +77,11 @@ impl Camera {
     pub const ZOOM_DEFAULT: f64 = 1.0;
     // The zoom timeout time.
     pub const ZOOM_TIMEOUT: Duration = Duration::from_millis(400);
+    /// The margin inside the viewport edges where the ramped nudge starts, in surface coordinates.
+    pub const NUDGE_RAMP_MARGIN: f64 = 40.0;
+    /// The nudge amount of the ramped nudge when the position is at or past the viewport edges,
+    /// in surface coordinates.
+    pub const NUDGE_RAMP_MAX_AMOUNT: f64 = 30.0;
     // when performing a drag - zoom 0.5% zoom for every pixel in y dir
     pub const DRAG_ZOOM_MAGN_ZOOM_FACTOR: f64 = 0.005;
     pub const OVERSHOOT_HORIZONTAL: f64 = 96.0;
@@ -221,6 +226,19 @@ impl Camera {
         widget_flags
     }
 
+    /// Zoom with a timeout so that the bounds fit into the viewport and center the viewport on them.
+    ///
+    /// `padding` is in surface coordinates.
+    pub(crate) fn zoom_to_bounds_w_timeout(
+        &mut self,
+        bounds: Aabb,
+        padding: f64,
+        tasks_tx: EngineTaskSender,
+    ) -> WidgetFlags {
+        let new_zoom = self.zoom_fitting_bounds(bounds, padding);
+        self.zoom_w_timeout(new_zoom, tasks_tx) | self.set_viewport_center(bounds.center().coords)
+    }
+
     /// The total zoom of the camera, including the temporary zoom.
     pub fn total_zoom(&self) -> f64 {
         self.zoom * self.temporary_zoom
@@ -262,6 +280,18 @@ impl Camera {
         (self.offset + self.size * 0.5) / self.total_zoom()
     }
 
+    /// The zoom at which the bounds fit into the viewport, keeping a padding around them.
+    ///
+    /// `padding` is in surface coordinates. The zoom is clamped to the min and max zoom.
+    pub fn zoom_fitting_bounds(&self, bounds: Aabb, padding: f64) -> f64 {
+        let available_size = (self.size - na::Vector2::repeat(2.0 * padding)).map(|s| s.max(1.0));
+        let extents = bounds.extents();
+
+        (available_size[0] / extents[0].max(1e-6))
+            .min(available_size[1] / extents[1].max(1e-6))
+            .clamp(Self::ZOOM_MIN, Self::ZOOM_MAX)
+    }
+
     /// Set the viewport center.
     ///
     /// `center` must be in document coordinate space.
@@ -360,10 +390,52 @@ impl Camera {
         self.nudge_by(NUDGE_AMOUNT, direction, doc)
     }
 
-    pub fn nudge_w_pos(&mut self, pos: na::Vector2<f64>, doc: &Document) -> WidgetFlags {
+    /// The offset of the ramped nudge for the given position, in surface coordinates.
+    ///
+    /// Per axis it increases linearly from zero at [Self::NUDGE_RAMP_MARGIN] inside the viewport edge
+    /// up to [Self::NUDGE_RAMP_MAX_AMOUNT] at the edge.
+    pub fn nudge_ramp_offset(&self, pos: na::Vector2<f64>) -> na::Vector2<f64> {
+        let surface_pos = self.transform().transform_point(&pos.into()).coords;
+        let ramp = |p: f64, size: f64| -> f64 {
+            if p < Self::NUDGE_RAMP_MARGIN {
+                -((Self::NUDGE_RAMP_MARGIN - p) / Self::NUDGE_RAMP_MARGIN).min(1.0)
+            } else if p > size - Self::NUDGE_RAMP_MARGIN {
+                ((p - (size - Self::NUDGE_RAMP_MARGIN)) / Self::NUDGE_RAMP_MARGIN).min(1.0)
+            } else {
+                0.0
+            }
+        };
+
+        na::vector![
+            ramp(surface_pos[0], self.size[0]),
+            ramp(surface_pos[1], self.size[1])
+        ] * Self::NUDGE_RAMP_MAX_AMOUNT
+    }
+
+    /// Nudge the camera when the position is close to an edge of the current viewport.
+    ///
+    /// Without a strength a constant nudge is applied. With a strength the nudge is ramped up
+    /// the closer the position is to the edges and is multiplied by the strength,
+    /// see [Self::nudge_ramp_offset()].
+    pub fn nudge_w_pos(
+        &mut self,
+        pos: na::Vector2<f64>,
+        doc: &Document,
+        strength: Option<f64>,
+    ) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
-        if let Some(nudge_direction) = self.detect_nudge_needed(pos) {
-            widget_flags |= self.nudge(nudge_direction, doc);
+        match strength {
+            None => {
+                if let Some(nudge_direction) = self.detect_nudge_needed(pos) {
+                    widget_flags |= self.nudge(nudge_direction, doc);
+                }
+            }
+            Some(strength) => {
+                let nudge_offset = self.nudge_ramp_offset(pos) * strength;
+                if nudge_offset != na::Vector2::zeros() {
+                    widget_flags |= self.set_offset(self.offset() + nudge_offset, doc);
+                }
+            }
         }
         widget_flags
     }
@@ -373,6 +445,7 @@ impl Camera {
 mod tests {
     use crate::Camera;
     use approx::assert_relative_eq;
+    use p2d::bounding_volume::Aabb;
 
     #[test]
     fn transform_vec() {
@@ -408,4 +481,62 @@ mod tests {
         assert_relative_eq!(viewport.mins, mins);
         assert_relative_eq!(viewport.maxs, maxs);
     }
+
+    #[test]
+    fn zoom_fitting_bounds() {
+        let camera = Camera::default().with_size(na::vector![800.0, 600.0]);
+
+        // the height is limiting
+        assert_relative_eq!(
+            camera.zoom_fitting_bounds(
+                Aabb::new(na::point![100.0, 100.0], na::point![300.0, 400.0]),
+                0.0
+            ),
+            2.0
+        );
+        assert_relative_eq!(
+            camera.zoom_fitting_bounds(
+                Aabb::new(na::point![100.0, 100.0], na::point![300.0, 400.0]),
+                30.0
+            ),
+            1.8
+        );
+        // clamped to the max zoom for tiny bounds
+        assert_relative_eq!(
+            camera.zoom_fitting_bounds(Aabb::new(na::point![0.0, 0.0], na::point![1.0, 1.0]), 0.0),
+            Camera::ZOOM_MAX
+        );
+    }
+
+    #[test]
+    fn nudge_ramp_offset() {
+        let camera = Camera::default()
+            .with_zoom(1.0)
+            .with_offset(na::vector![0.0, 0.0])
+            .with_size(na::vector![800.0, 600.0]);
+        let margin = Camera::NUDGE_RAMP_MARGIN;
+        let max = Camera::NUDGE_RAMP_MAX_AMOUNT;
+
+        assert_relative_eq!(
+            camera.nudge_ramp_offset(na::vector![400.0, 300.0]),
+            na::Vector2::zeros()
+        );
+        assert_relative_eq!(
+            camera.nudge_ramp_offset(na::vector![margin, 300.0]),
+            na::Vector2::zeros()
+        );
+        assert_relative_eq!(
+            camera.nudge_ramp_offset(na::vector![margin * 0.5, 300.0]),
+            na::vector![-max * 0.5, 0.0]
+        );
+        assert_relative_eq!(
+            camera.nudge_ramp_offset(na::vector![800.0 - margin * 0.75, 600.0]),
+            na::vector![max * 0.25, max]
+        );
+        // clamped past the edges
+        assert_relative_eq!(
+            camera.nudge_ramp_offset(na::vector![-1000.0, 2000.0]),
+            na::vector![-max, max]
+        );
+    }
 }
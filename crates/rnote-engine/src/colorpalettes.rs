@@ -0,0 +1,212 @@
+// Imports
+use rnote_compose::Color;
+use serde::{Deserialize, Serialize};
+
+/// A named color palette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "color_palette")]
+pub struct ColorPalette {
+    /// The palette name.
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The ordered palette colors.
+    ///
+    /// Empty slots are `None`, which is distinct from any color, including black or transparent.
+    #[serde(rename = "colors")]
+    pub colors: Vec<Option<Color>>,
+}
+
+impl Default for ColorPalette {
+    fn default() -> Self {
+        Self {
+            // Same as the default colorpicker colors
+            name: String::from("Default"),
+            colors: vec![
+                Some(Color::BLACK),
+                Some(Color::WHITE),
+                Some(Color::TRANSPARENT),
+                // blue 1
+                Some(Color::new(0.597, 0.753, 0.941, 1.0)),
+                // blue 5
+                Some(Color::new(0.101, 0.371, 0.703, 1.0)),
+                // green 5
+                Some(Color::new(0.148, 0.632, 0.410, 1.0)),
+                // yellow 4
+                Some(Color::new(0.957, 0.757, 0.066, 1.0)),
+                // orange 4
+                Some(Color::new(0.898, 0.378, 0.0, 1.0)),
+                // red 5
+                Some(Color::new(0.644, 0.113, 0.175, 1.0)),
+            ],
+        }
+    }
+}
+
+impl ColorPalette {
+    pub fn new(name: impl Into<String>, colors: Vec<Option<Color>>) -> Self {
+        Self {
+            name: name.into(),
+            colors,
+        }
+    }
+}
+
+/// A list of named color palettes of which one is active.
+///
+/// There is always at least one palette, an empty deserialized list falls back to the default palette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "color_palettes", from = "ColorPalettesUnchecked")]
+pub struct ColorPalettes {
+    #[serde(rename = "palettes")]
+    palettes: Vec<ColorPalette>,
+    #[serde(rename = "active_index")]
+    active_index: usize,
+}
+
+impl Default for ColorPalettes {
+    fn default() -> Self {
+        Self {
+            palettes: vec![ColorPalette::default()],
+            active_index: 0,
+        }
+    }
+}
+
+/// The deserialized palettes before the invariants are ensured.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename = "color_palettes")]
+struct ColorPalettesUnchecked {
+    #[serde(rename = "palettes")]
+    palettes: Vec<ColorPalette>,
+    #[serde(rename = "active_index")]
+    active_index: usize,
+}
+
+impl From<ColorPalettesUnchecked> for ColorPalettes {
+    fn from(unchecked: ColorPalettesUnchecked) -> Self {
+        if unchecked.palettes.is_empty() {
+            return Self::default();
+        }
+        Self {
+            active_index: unchecked.active_index.min(unchecked.palettes.len() - 1),
+            palettes: unchecked.palettes,
+        }
+    }
+}
+
+impl ColorPalettes {
+    pub fn palettes(&self) -> &[ColorPalette] {
+        &self.palettes
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active_index
+    }
+
+    pub fn active(&self) -> &ColorPalette {
+        &self.palettes[self.active_index]
+    }
+
+    pub fn active_mut(&mut self) -> &mut ColorPalette {
+        &mut self.palettes[self.active_index]
+    }
+
+    /// Add a new palette. Returns its index.
+    pub fn add(&mut self, palette: ColorPalette) -> usize {
+        self.palettes.push(palette);
+        self.palettes.len() - 1
+    }
+
+    /// Remove the palette at the given index.
+    ///
+    /// The last remaining palette can't be removed.
+    /// If the active palette is removed, the previous one becomes active.
+    pub fn remove(&mut self, index: usize) -> Option<ColorPalette> {
+        if self.palettes.len() <= 1 || index >= self.palettes.len() {
+            return None;
+        }
+        let removed = self.palettes.remove(index);
+        if self.active_index >= index && self.active_index > 0 {
+            self.active_index -= 1;
+        }
+        Some(removed)
+    }
+
+    /// Switch to the palette at the given index.
+    ///
+    /// Returns the new active palette, or None if the index is out of range.
+    pub fn switch(&mut self, index: usize) -> Option<&ColorPalette> {
+        if index >= self.palettes.len() {
+            return None;
+        }
+        self.active_index = index;
+        Some(self.active())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes_serialization_roundtrip() {
+        let mut palettes = ColorPalettes::default();
+        let index = palettes.add(ColorPalette::new(
+            "math notes",
+            vec![
+                Some(Color::BLACK),
+                None,
+                Some(Color::RED),
+                Some(Color::new(0.1, 0.2, 0.3, 0.5)),
+            ],
+        ));
+        palettes.switch(index).unwrap();
+
+        let serialized = serde_json::to_string(&palettes).unwrap();
+        let deserialized: ColorPalettes = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(palettes, deserialized);
+        assert_eq!(deserialized.active().name, "math notes");
+        assert_eq!(deserialized.active().colors[0], Some(Color::BLACK));
+        assert_eq!(deserialized.active().colors[1], None);
+    }
+
+    #[test]
+    fn palettes_without_empty_slots_deserialized() {
+        let mut palettes = ColorPalettes::default();
+        palettes.active_mut().colors = vec![Some(Color::BLACK), Some(Color::WHITE)];
+        let serialized = serde_json::to_string(&palettes).unwrap();
+        assert!(!serialized.contains("null"));
+
+        let deserialized: ColorPalettes = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(
+            deserialized.active().colors,
+            vec![Some(Color::BLACK), Some(Color::WHITE)]
+        );
+    }
+
+    #[test]
+    fn invalid_palettes_deserialized_valid() {
+        let palettes: ColorPalettes =
+            serde_json::from_str(r#"{"palettes":[],"active_index":3}"#).unwrap();
+        assert_eq!(palettes, ColorPalettes::default());
+        assert_eq!(palettes.active().name, "Default");
+
+        let palettes: ColorPalettes = serde_json::from_str(
+            r#"{"palettes":[{"name":"math notes","colors":[]},{"name":"sketching","colors":[]}],"active_index":3}"#,
+        )
+        .unwrap();
+        assert_eq!(palettes.active_index(), 1);
+        assert_eq!(palettes.active().name, "sketching");
+    }
+
+    #[test]
+    fn last_palette_not_removable() {
+        let mut palettes = ColorPalettes::default();
+        assert!(palettes.remove(0).is_none());
+
+        let index = palettes.add(ColorPalette::new("sketching", vec![]));
+        palettes.switch(index).unwrap();
+        assert!(palettes.remove(index).is_some());
+        assert_eq!(palettes.active_index(), 0);
+    }
+}
@@ -1,9 +1,12 @@
 // Imports
+use super::Format;
 use crate::render;
 use anyhow::Context;
-use p2d::bounding_volume::Aabb;
-use rnote_compose::Color;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use rnote_compose::ext::AabbExt;
+use rnote_compose::{Color, SplitOrder};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use svg::node::element;
 use svg::Node;
 
@@ -331,7 +334,195 @@ fn gen_iso_dots_pattern(
     group.into()
 }
 
+/// How the background image is laid out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "background_image_tiling")]
+pub enum BackgroundImageTiling {
+    /// Stretched over the document, or over the exported bounds.
+    #[serde(rename = "stretch")]
+    Stretch,
+    /// Repeated in both directions, aligned to the origin.
+    #[default]
+    #[serde(rename = "tile_both")]
+    TileBoth,
+    /// Repeated downwards in a single column, aligned to the origin.
+    #[serde(rename = "tile_vertical")]
+    TileVertical,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "background_image_options")]
+pub struct BackgroundImageOptions {
+    /// The resolution of the image, determining its size in the document.
+    #[serde(rename = "dpi", with = "rnote_compose::serialize::f64_dp3")]
+    pub dpi: f64,
+    #[serde(rename = "tiling")]
+    pub tiling: BackgroundImageTiling,
+    /// The opacity, in the range [0.0, 1.0].
+    #[serde(rename = "opacity", with = "rnote_compose::serialize::f64_dp3")]
+    pub opacity: f64,
+}
+
+impl Default for BackgroundImageOptions {
+    fn default() -> Self {
+        Self {
+            dpi: Format::DPI_DEFAULT,
+            tiling: BackgroundImageTiling::default(),
+            opacity: 1.0,
+        }
+    }
+}
+
+impl BackgroundImageOptions {
+    fn dpi_sanitized(&self) -> f64 {
+        if self.dpi.is_finite() {
+            self.dpi.clamp(Format::DPI_MIN, Format::DPI_MAX)
+        } else {
+            Format::DPI_DEFAULT
+        }
+    }
+
+    pub(crate) fn opacity_sanitized(&self) -> f64 {
+        if self.opacity.is_finite() {
+            self.opacity.clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// An image that is drawn beneath the background pattern, for example a scanned template.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename = "background_image")]
+pub struct BackgroundImage {
+    /// The image, embedded in the document like the image of bitmap image strokes.
+    #[serde(rename = "image")]
+    pub image: render::Image,
+    /// The size of a single tile of the image, in document coordinates.
+    #[serde(rename = "size", with = "rnote_compose::serialize::na_vector2_f64_dp3")]
+    pub size: na::Vector2<f64>,
+    #[serde(rename = "options")]
+    pub options: BackgroundImageOptions,
+}
+
+impl BackgroundImage {
+    /// Decode the image from the encoded bytes (Png, Jpeg, ..).
+    ///
+    /// The size of the image is its pixel size at the image DPI, converted to the DPI of the document.
+    pub fn try_from_encoded_bytes(
+        bytes: &[u8],
+        options: BackgroundImageOptions,
+        document_dpi: f64,
+    ) -> anyhow::Result<Self> {
+        let image = render::Image::try_from_encoded_bytes(bytes)?;
+        let size = crate::utils::convert_coord_dpi(
+            na::vector![f64::from(image.pixel_width), f64::from(image.pixel_height)],
+            options.dpi_sanitized(),
+            document_dpi,
+        );
+        Ok(Self {
+            image,
+            size,
+            options,
+        })
+    }
+
+    /// The bounds of the image tiles that cover the area.
+    ///
+    /// A stretched image covers the stretch bounds instead.
+    pub(crate) fn tiles(&self, area: Aabb, stretch_bounds: Aabb) -> Vec<Aabb> {
+        if !(self.size[0] > 0.0 && self.size[1] > 0.0) {
+            return vec![];
+        }
+        match self.options.tiling {
+            BackgroundImageTiling::Stretch => vec![stretch_bounds],
+            BackgroundImageTiling::TileBoth => {
+                area.split_extended_origin_aligned(self.size, SplitOrder::default())
+            }
+            BackgroundImageTiling::TileVertical => {
+                let first = (area.mins[1] / self.size[1]).floor() as i64;
+                let last = (area.maxs[1] / self.size[1]).ceil() as i64;
+                (first..last)
+                    .map(|i| {
+                        Aabb::new(
+                            na::point![0.0, i as f64 * self.size[1]],
+                            na::point![self.size[0], (i + 1) as f64 * self.size[1]],
+                        )
+                    })
+                    .filter(|tile| tile.intersects(&area))
+                    .collect()
+            }
+        }
+    }
+
+    /// Generate the Svg element of the image, covering the bounds.
+    fn gen_svg_element(&self, bounds: Aabb) -> anyhow::Result<element::Group> {
+        let image_id = rnote_compose::utils::svg_random_id_prefix() + "_bg_image";
+        let png_bytes = self
+            .image
+            .clone()
+            .into_encoded_bytes(image::ImageFormat::Png, None)
+            .context("Encoding the background image as Png failed.")?;
+        let href = format!(
+            "data:image/png;base64,{}",
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, png_bytes)
+        );
+        let group = element::Group::new().set("opacity", self.options.opacity_sanitized());
+
+        if self.options.tiling == BackgroundImageTiling::Stretch {
+            return Ok(group.add(
+                element::Image::new()
+                    .set("x", bounds.mins[0])
+                    .set("y", bounds.mins[1])
+                    .set("width", bounds.extents()[0])
+                    .set("height", bounds.extents()[1])
+                    .set("preserveAspectRatio", "none")
+                    .set("href", href),
+            ));
+        }
+
+        let pattern = element::Definitions::new().add(
+            element::Pattern::new()
+                .set("id", image_id.as_str())
+                .set("x", 0_f64)
+                .set("y", 0_f64)
+                .set("width", self.size[0])
+                .set("height", self.size[1])
+                .set("patternUnits", "userSpaceOnUse")
+                .set("patternContentUnits", "userSpaceOnUse")
+                .add(
+                    element::Image::new()
+                        .set("x", 0_f64)
+                        .set("y", 0_f64)
+                        .set("width", self.size[0])
+                        .set("height", self.size[1])
+                        .set("preserveAspectRatio", "none")
+                        .set("href", href),
+                ),
+        );
+        // The single column is at the origin
+        let (x, width) = match self.options.tiling {
+            BackgroundImageTiling::TileVertical => {
+                let x = bounds.mins[0].max(0.0);
+                (x, bounds.maxs[0].min(self.size[0]) - x)
+            }
+            _ => (bounds.mins[0], bounds.extents()[0]),
+        };
+        if width <= 0.0 {
+            return Ok(group);
+        }
+
+        let mut rect = element::Rectangle::new().set("fill", format!("url(#{image_id})"));
+        rect.assign("x", format!("{x}px"));
+        rect.assign("y", format!("{}px", bounds.mins[1]));
+        rect.assign("width", format!("{width}px"));
+        rect.assign("height", format!("{}px", bounds.extents()[1]));
+
+        Ok(group.add(pattern).add(rect))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "background")]
 pub struct Background {
     #[serde(rename = "color")]
@@ -345,6 +536,11 @@ pub struct Background {
     pub pattern_size: na::Vector2<f64>,
     #[serde(rename = "pattern_color")]
     pub pattern_color: Color,
+    /// Is drawn between the background color and the pattern.
+    ///
+    /// Changes to it are recorded in the history of the store, so it should only be set through the engine.
+    #[serde(rename = "image")]
+    pub image: Option<Arc<BackgroundImage>>,
 }
 
 impl Default for Background {
@@ -354,6 +550,7 @@ impl Default for Background {
             pattern: PatternStyle::default(),
             pattern_size: Self::PATTERN_SIZE_DEFAULT,
             pattern_color: Self::PATTERN_COLOR_DEFAULT,
+            image: None,
         }
     }
 }
@@ -373,6 +570,16 @@ impl Background {
         a: 1.0,
     };
 
+    /// The spacing between the horizontal lines of the pattern, for patterns that are made of rows of lines.
+    pub(crate) fn line_spacing(&self) -> Option<f64> {
+        match self.pattern {
+            PatternStyle::Lines | PatternStyle::Grid if self.pattern_size[1] > 0.0 => {
+                Some(self.pattern_size[1])
+            }
+            _ => None,
+        }
+    }
+
     /// Calculates the tile size as multiple of pattern_size with max size TITLE_MAX_SIZE
     pub(crate) fn tile_size(&self) -> na::Vector2<f64> {
         let pattern_size = match self.pattern {
@@ -414,6 +621,18 @@ impl Background {
         bounds: Aabb,
         with_pattern: bool,
         optimize_printing: bool,
+    ) -> Result<render::Svg, anyhow::Error> {
+        self.gen_svg_layers(bounds, true, with_pattern, optimize_printing)
+    }
+
+    /// Generate the background svg with the base layers, the color and the image,
+    /// and optionally the pattern on top of them.
+    fn gen_svg_layers(
+        &self,
+        bounds: Aabb,
+        with_base: bool,
+        with_pattern: bool,
+        optimize_printing: bool,
     ) -> Result<render::Svg, anyhow::Error> {
         let (color, pattern_color) = if optimize_printing {
             if self.color.luma() > 0.5 {
@@ -430,15 +649,21 @@ impl Background {
             (self.color, self.pattern_color)
         };
 
-        // background color
-        let mut color_rect = element::Rectangle::new().set("fill", color.to_css_color_attr());
-        color_rect.assign("x", format!("{}px", bounds.mins[0]));
-        color_rect.assign("y", format!("{}px", bounds.mins[1]));
-        color_rect.assign("width", format!("{}px", bounds.extents()[0]));
-        color_rect.assign("height", format!("{}px", bounds.extents()[1]));
-
         let mut svg_group = element::Group::new();
-        svg_group = svg_group.add(color_rect);
+
+        if with_base {
+            // background color
+            let mut color_rect = element::Rectangle::new().set("fill", color.to_css_color_attr());
+            color_rect.assign("x", format!("{}px", bounds.mins[0]));
+            color_rect.assign("y", format!("{}px", bounds.mins[1]));
+            color_rect.assign("width", format!("{}px", bounds.extents()[0]));
+            color_rect.assign("height", format!("{}px", bounds.extents()[1]));
+            svg_group = svg_group.add(color_rect);
+
+            if let Some(image) = &self.image {
+                svg_group = svg_group.add(image.gen_svg_element(bounds)?);
+            }
+        }
 
         if with_pattern {
             match self.pattern {
@@ -494,9 +719,13 @@ impl Background {
         Ok(render::Svg { svg_data, bounds })
     }
 
+    /// Generate the image of a single pattern tile.
+    ///
+    /// When there is a background image, the tile only contains the pattern,
+    /// because the color and the image are drawn separately beneath the tiles.
     pub(crate) fn gen_tile_image(&self, image_scale: f64) -> Result<render::Image, anyhow::Error> {
         let tile_bounds = Aabb::new(na::point![0.0, 0.0], self.tile_size().into());
-        self.gen_svg(tile_bounds, true, false)?
+        self.gen_svg_layers(tile_bounds, self.image.is_none(), true, false)?
             .gen_image(image_scale)
     }
 
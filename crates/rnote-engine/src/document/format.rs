@@ -113,6 +113,15 @@ impl TryFrom<u32> for MeasureUnit {
 impl MeasureUnit {
     pub const AMOUNT_MM_IN_INCH: f64 = 25.4;
 
+    /// The abbreviated unit name.
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            MeasureUnit::Px => "px",
+            MeasureUnit::Mm => "mm",
+            MeasureUnit::Cm => "cm",
+        }
+    }
+
     pub fn convert_measurement(
         value: f64,
         value_unit: MeasureUnit,
@@ -0,0 +1,61 @@
+// Imports
+use p2d::bounding_volume::Aabb;
+use serde::{Deserialize, Serialize};
+
+/// The distances of the margin guides from the edges of the pages.
+///
+/// Guides with a distance of zero are not shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "page_margins")]
+pub struct PageMargins {
+    #[serde(rename = "top", with = "rnote_compose::serialize::f64_dp3")]
+    pub top: f64,
+    #[serde(rename = "bottom", with = "rnote_compose::serialize::f64_dp3")]
+    pub bottom: f64,
+    #[serde(rename = "left", with = "rnote_compose::serialize::f64_dp3")]
+    pub left: f64,
+    #[serde(rename = "right", with = "rnote_compose::serialize::f64_dp3")]
+    pub right: f64,
+}
+
+impl PageMargins {
+    /// Whether no margin guides are shown.
+    pub fn is_empty(&self) -> bool {
+        self.top <= 0.0 && self.bottom <= 0.0 && self.left <= 0.0 && self.right <= 0.0
+    }
+
+    /// The margins with negative or invalid distances replaced by zero.
+    pub(crate) fn sanitized(self) -> Self {
+        let sanitize = |v: f64| if v.is_finite() { v.max(0.0) } else { 0.0 };
+        Self {
+            top: sanitize(self.top),
+            bottom: sanitize(self.bottom),
+            left: sanitize(self.left),
+            right: sanitize(self.right),
+        }
+    }
+
+    /// The positions of the vertical guides of the page, left to right.
+    pub(crate) fn vertical_guides(&self, page_bounds: Aabb) -> Vec<f64> {
+        let mut guides = vec![];
+        if self.left > 0.0 {
+            guides.push(page_bounds.mins[0] + self.left);
+        }
+        if self.right > 0.0 {
+            guides.push(page_bounds.maxs[0] - self.right);
+        }
+        guides
+    }
+
+    /// The positions of the horizontal guides of the page, top to bottom.
+    pub(crate) fn horizontal_guides(&self, page_bounds: Aabb) -> Vec<f64> {
+        let mut guides = vec![];
+        if self.top > 0.0 {
+            guides.push(page_bounds.mins[1] + self.top);
+        }
+        if self.bottom > 0.0 {
+            guides.push(page_bounds.maxs[1] - self.bottom);
+        }
+        guides
+    }
+}
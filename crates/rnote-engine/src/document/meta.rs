@@ -0,0 +1,350 @@
+// Imports
+use anyhow::Context;
+use p2d::bounding_volume::Aabb;
+use serde::{Deserialize, Serialize};
+
+/// A named region of the document, to jump back to it.
+///
+/// The bookmark is positional, it stays valid when the strokes in the region are removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "bookmark")]
+pub struct Bookmark {
+    /// The name, unique in the document.
+    #[serde(rename = "name")]
+    pub name: String,
+    /// The position of the region, in document coordinates.
+    #[serde(rename = "pos", with = "rnote_compose::serialize::na_vector2_f64_dp3")]
+    pub pos: na::Vector2<f64>,
+    /// The size of the region.
+    #[serde(rename = "size", with = "rnote_compose::serialize::na_vector2_f64_dp3")]
+    pub size: na::Vector2<f64>,
+}
+
+impl Default for Bookmark {
+    fn default() -> Self {
+        Self {
+            name: String::default(),
+            pos: na::Vector2::zeros(),
+            size: na::Vector2::zeros(),
+        }
+    }
+}
+
+impl Bookmark {
+    pub fn new(name: String, bounds: Aabb) -> Self {
+        Self {
+            name,
+            pos: bounds.mins.coords,
+            size: bounds.extents(),
+        }
+    }
+
+    /// The bounds of the bookmarked region.
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(self.pos.into(), (self.pos + self.size).into())
+    }
+}
+
+/// The user facing metadata of the document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "document_meta")]
+pub struct DocumentMeta {
+    /// The title.
+    #[serde(rename = "title")]
+    pub title: String,
+    /// The author.
+    #[serde(rename = "author")]
+    pub author: String,
+    /// The tags.
+    #[serde(rename = "tags")]
+    pub tags: Vec<String>,
+    /// When the document was first saved, as unix timestamp in seconds. Zero when unknown.
+    #[serde(rename = "created")]
+    pub created: i64,
+    /// When the document was last saved, as unix timestamp in seconds. Zero when unknown.
+    #[serde(rename = "modified")]
+    pub modified: i64,
+    /// The bookmarks, in the order they were added.
+    #[serde(rename = "bookmarks")]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl DocumentMeta {
+    /// The png text chunk keywords, as registered in the png specification where available.
+    const PNG_KEYWORD_TITLE: &'static str = "Title";
+    const PNG_KEYWORD_AUTHOR: &'static str = "Author";
+    const PNG_KEYWORD_KEYWORDS: &'static str = "Keywords";
+    const PNG_KEYWORD_CREATION_TIME: &'static str = "Creation Time";
+
+    /// The current time as unix timestamp in seconds.
+    pub fn now_timestamp() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    /// Update the timestamps when the document is saved.
+    pub(crate) fn touch(&mut self, now: i64) {
+        if self.created == 0 {
+            self.created = now;
+        }
+        self.modified = now;
+    }
+
+    /// Formats the timestamp as ISO 8601 date, in UTC.
+    ///
+    /// Returns None when the timestamp is unknown.
+    fn format_timestamp(timestamp: i64) -> Option<String> {
+        if timestamp == 0 {
+            return None;
+        }
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .map(|datetime| datetime.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+    }
+
+    /// The bookmark with the name.
+    pub fn bookmark(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.name == name)
+    }
+
+    /// The tags joined to a single string.
+    fn keywords(&self) -> String {
+        self.tags.join(", ")
+    }
+
+    /// Set the metadata in the info dictionary of the pdf surface.
+    ///
+    /// The fallback title is used when the document doesn't have one.
+    pub(crate) fn apply_to_pdf_surface(
+        &self,
+        surface: &cairo::PdfSurface,
+        fallback_title: &str,
+    ) -> anyhow::Result<()> {
+        let title = if self.title.is_empty() {
+            fallback_title
+        } else {
+            self.title.as_str()
+        };
+        surface
+            .set_metadata(cairo::PdfMetadata::Title, title)
+            .context("Set pdf surface title metadata failed.")?;
+        if !self.author.is_empty() {
+            surface
+                .set_metadata(cairo::PdfMetadata::Author, self.author.as_str())
+                .context("Set pdf surface author metadata failed.")?;
+        }
+        if !self.tags.is_empty() {
+            surface
+                .set_metadata(cairo::PdfMetadata::Keywords, self.keywords().as_str())
+                .context("Set pdf surface keywords metadata failed.")?;
+        }
+        let create_date = Self::format_timestamp(self.created)
+            .unwrap_or_else(|| Self::format_timestamp(Self::now_timestamp()).unwrap_or_default());
+        surface
+            .set_metadata(cairo::PdfMetadata::CreateDate, create_date.as_str())
+            .context("Set pdf surface date metadata failed.")?;
+        if let Some(mod_date) = Self::format_timestamp(self.modified) {
+            surface
+                .set_metadata(cairo::PdfMetadata::ModDate, mod_date.as_str())
+                .context("Set pdf surface modification date metadata failed.")?;
+        }
+        Ok(())
+    }
+
+    /// Generate the Svg `<metadata>` element with the metadata as Dublin Core elements.
+    ///
+    /// Returns None when there is no metadata to embed.
+    pub(crate) fn gen_svg_metadata(&self) -> Option<String> {
+        if self.title.is_empty() && self.author.is_empty() && self.tags.is_empty() {
+            return None;
+        }
+        let mut w = xmlwriter::XmlWriter::new(xmlwriter::Options {
+            use_single_quote: false,
+            indent: xmlwriter::Indent::None,
+            attributes_indent: xmlwriter::Indent::None,
+        });
+        w.start_element("metadata");
+        w.start_element("rdf:RDF");
+        w.write_attribute("xmlns:rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#");
+        w.write_attribute("xmlns:dc", "http://purl.org/dc/elements/1.1/");
+        w.start_element("rdf:Description");
+        w.write_attribute("rdf:about", "");
+        if !self.title.is_empty() {
+            w.start_element("dc:title");
+            w.write_text(&self.title);
+            w.end_element();
+        }
+        if !self.author.is_empty() {
+            w.start_element("dc:creator");
+            w.write_text(&self.author);
+            w.end_element();
+        }
+        for tag in self.tags.iter() {
+            w.start_element("dc:subject");
+            w.write_text(tag);
+            w.end_element();
+        }
+        if let Some(created) = Self::format_timestamp(self.created) {
+            w.start_element("dc:date");
+            w.write_text(&created);
+            w.end_element();
+        }
+        Some(w.end_document())
+    }
+
+    /// Embed the metadata as text chunks into the encoded png image.
+    ///
+    /// Text that can't be represented in Latin-1 is stored in international text chunks.
+    pub(crate) fn embed_in_png(&self, png_bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+        // The header chunk always comes first and has a fixed length
+        const HEADER_CHUNK_END: usize = SIGNATURE.len() + 4 + 4 + 13 + 4;
+
+        if !png_bytes.starts_with(SIGNATURE)
+            || png_bytes.len() < HEADER_CHUNK_END
+            || &png_bytes[SIGNATURE.len() + 4..SIGNATURE.len() + 8] != b"IHDR"
+        {
+            return Err(anyhow::anyhow!(
+                "Embedding metadata into png failed, not a valid png image."
+            ));
+        }
+        let mut entries = vec![];
+        if !self.title.is_empty() {
+            entries.push((Self::PNG_KEYWORD_TITLE, self.title.clone()));
+        }
+        if !self.author.is_empty() {
+            entries.push((Self::PNG_KEYWORD_AUTHOR, self.author.clone()));
+        }
+        if !self.tags.is_empty() {
+            entries.push((Self::PNG_KEYWORD_KEYWORDS, self.keywords()));
+        }
+        if let Some(created) = Self::format_timestamp(self.created) {
+            entries.push((Self::PNG_KEYWORD_CREATION_TIME, created));
+        }
+        if entries.is_empty() {
+            return Ok(png_bytes);
+        }
+
+        let mut chunks = Vec::new();
+        for (keyword, text) in entries {
+            let latin1 = text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).ok())
+                .collect::<Option<Vec<u8>>>();
+            let mut data = keyword.as_bytes().to_vec();
+            data.push(0);
+            let chunk_type = match latin1 {
+                Some(latin1) => {
+                    data.extend_from_slice(&latin1);
+                    b"tEXt"
+                }
+                None => {
+                    // no compression, no language tag and no translated keyword
+                    data.extend_from_slice(&[0, 0, 0, 0]);
+                    data.extend_from_slice(text.as_bytes());
+                    b"iTXt"
+                }
+            };
+            let mut crc = flate2::Crc::new();
+            crc.update(chunk_type);
+            crc.update(&data);
+            chunks.extend_from_slice(&u32::try_from(data.len())?.to_be_bytes());
+            chunks.extend_from_slice(chunk_type);
+            chunks.extend_from_slice(&data);
+            chunks.extend_from_slice(&crc.sum().to_be_bytes());
+        }
+        let mut png_bytes = png_bytes;
+        png_bytes.splice(HEADER_CHUNK_END..HEADER_CHUNK_END, chunks);
+        Ok(png_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta() -> DocumentMeta {
+        DocumentMeta {
+            title: String::from("Notes & <draft"),
+            author: String::from("Zoë"),
+            tags: vec![String::from("math")],
+            created: 1_700_000_000,
+            modified: 1_700_000_100,
+            bookmarks: vec![],
+        }
+    }
+
+    /// The keywords and texts of the text chunks in the png image.
+    fn png_text_chunks(bytes: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut chunks = vec![];
+        let mut i = 8;
+        while i + 8 <= bytes.len() {
+            let len = u32::from_be_bytes(bytes[i..i + 4].try_into().unwrap()) as usize;
+            let chunk_type = &bytes[i + 4..i + 8];
+            let data = &bytes[i + 8..i + 8 + len];
+            if chunk_type == b"tEXt" || chunk_type == b"iTXt" {
+                chunks.push((chunk_type.to_vec(), data.to_vec()));
+            }
+            i += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn png_text_chunks_embedded() {
+        let mut png_bytes = Vec::new();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png_bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+
+        let embedded = meta().embed_in_png(png_bytes).unwrap();
+        // still a valid image
+        assert!(image::load_from_memory_with_format(&embedded, image::ImageFormat::Png).is_ok());
+        let chunks = png_text_chunks(&embedded);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(
+            chunks[0],
+            (b"tEXt".to_vec(), b"Title\0Notes & <draft".to_vec())
+        );
+        // "ë" can be represented in Latin-1
+        assert_eq!(chunks[1], (b"tEXt".to_vec(), b"Author\0Zo\xeb".to_vec()));
+        assert_eq!(
+            chunks[3],
+            (
+                b"tEXt".to_vec(),
+                b"Creation Time\x002023-11-14T22:13:20Z".to_vec()
+            )
+        );
+
+        let meta = DocumentMeta {
+            title: String::from("ノート"),
+            ..Default::default()
+        };
+        let mut png_bytes = Vec::new();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png_bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let chunks = png_text_chunks(&meta.embed_in_png(png_bytes).unwrap());
+        assert_eq!(
+            chunks,
+            vec![(
+                b"iTXt".to_vec(),
+                [b"Title\0\0\0\0\0".as_slice(), "ノート".as_bytes()].concat()
+            )]
+        );
+    }
+
+    #[test]
+    fn svg_metadata_escaped() {
+        let svg_metadata = meta().gen_svg_metadata().unwrap();
+        assert!(svg_metadata.starts_with("<metadata>"));
+        assert!(svg_metadata.contains("<dc:title>Notes &amp; &lt;draft</dc:title>"));
+        assert!(svg_metadata.contains("<dc:creator>Zoë</dc:creator>"));
+        assert!(svg_metadata.contains("<dc:date>2023-11-14T22:13:20Z</dc:date>"));
+        assert!(DocumentMeta::default().gen_svg_metadata().is_none());
+    }
+}
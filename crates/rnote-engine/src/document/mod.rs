@@ -1,10 +1,18 @@
 // Modules
 pub mod background;
 pub mod format;
+pub mod margins;
+pub mod meta;
+pub mod pdfunderlay;
+pub mod snapconfig;
 
 // Re-exports
 pub use background::Background;
 pub use format::Format;
+pub use margins::PageMargins;
+pub use meta::{Bookmark, DocumentMeta};
+pub use pdfunderlay::PdfUnderlay;
+pub use snapconfig::SnapConfig;
 
 // Imports
 use crate::{Camera, CloneConfig, StrokeStore, WidgetFlags};
@@ -13,6 +21,7 @@ use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::ext::{AabbExt, Vector2Ext};
 use rnote_compose::{Color, SplitOrder};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(
     Debug,
@@ -105,8 +114,13 @@ pub struct Document {
     pub background: Background,
     #[serde(rename = "layout", alias = "expand_mode")]
     pub layout: Layout,
-    #[serde(rename = "snap_positions")]
-    pub snap_positions: bool,
+    #[serde(rename = "snap_config")]
+    snap_config: SnapConfig,
+    #[serde(rename = "margins")]
+    margins: PageMargins,
+    /// The Pdf that is drawn beneath the strokes. There is at most one per document.
+    #[serde(rename = "pdf_underlay")]
+    pub pdf_underlay: Option<Arc<PdfUnderlay>>,
 }
 
 impl Default for Document {
@@ -119,7 +133,9 @@ impl Default for Document {
             format: Format::default(),
             background: Background::default(),
             layout: Layout::default(),
-            snap_positions: false,
+            snap_config: SnapConfig::default(),
+            margins: PageMargins::default(),
+            pdf_underlay: None,
         }
     }
 }
@@ -273,6 +289,69 @@ impl Document {
         true
     }
 
+    /// The number of vertically stacked pages in layouts with a fixed width.
+    pub(crate) fn n_vertical_pages(&self) -> usize {
+        let format_height = self.format.height();
+        if format_height <= 0.0 {
+            return 0;
+        }
+        (self.height / format_height).ceil().max(1.0) as usize
+    }
+
+    /// The index of the vertically stacked page that contains the larger share of the bounds,
+    /// in layouts with a fixed width.
+    ///
+    /// Bounds outside of the document are assigned to the nearest page.
+    pub(crate) fn vertical_page_idx_of_bounds(&self, bounds: Aabb) -> usize {
+        let n_pages = self.n_vertical_pages();
+        let format_height = self.format.height();
+        if n_pages == 0 {
+            return 0;
+        }
+        let page_idx_at = |y: f64| {
+            ((y - self.y) / format_height)
+                .floor()
+                .clamp(0.0, (n_pages - 1) as f64) as usize
+        };
+        let share = |idx: usize| {
+            let page_top = self.y + idx as f64 * format_height;
+            (bounds.maxs[1].min(page_top + format_height) - bounds.mins[1].max(page_top)).max(0.0)
+        };
+
+        let mut page_idx = page_idx_at(bounds.mins[1]);
+        for idx in page_idx + 1..=page_idx_at(bounds.maxs[1]) {
+            if share(idx) > share(page_idx) {
+                page_idx = idx;
+            }
+        }
+        page_idx
+    }
+
+    /// The height needed to fit the strokes and the Pdf underlay.
+    fn content_height(&self, store: &StrokeStore) -> f64 {
+        let underlay_max_y = self
+            .pdf_underlay
+            .as_ref()
+            .and_then(|underlay| underlay.bounds())
+            .map_or(0.0, |bounds| bounds.maxs[1]);
+        store.calc_height().max(underlay_max_y)
+    }
+
+    /// The bounds of the strokes and the Pdf underlay.
+    fn content_bounds(&self, store: &StrokeStore) -> Option<Aabb> {
+        let strokes_bounds = store.bounds_for_strokes(&store.stroke_keys_as_rendered());
+        let underlay_bounds = self
+            .pdf_underlay
+            .as_ref()
+            .and_then(|underlay| underlay.bounds());
+        match (strokes_bounds, underlay_bounds) {
+            (Some(strokes_bounds), Some(underlay_bounds)) => {
+                Some(strokes_bounds.merged(&underlay_bounds))
+            }
+            (strokes_bounds, underlay_bounds) => strokes_bounds.or(underlay_bounds),
+        }
+    }
+
     /// Returns true if a resize happened.
     #[must_use = "Determines if the resize flag should be set"]
     fn resize_doc_fixed_size_layout(&mut self, store: &StrokeStore) -> bool {
@@ -280,7 +359,8 @@ impl Document {
 
         let new_width = self.format.width();
         // max(1.0) because then 'fraction'.ceil() is at least 1
-        let new_height = ((store.calc_height().max(1.0)) / format_height).ceil() * format_height;
+        let new_height =
+            ((self.content_height(store).max(1.0)) / format_height).ceil() * format_height;
 
         set_dimensions_checked(
             &mut self.x,
@@ -298,7 +378,7 @@ impl Document {
     #[must_use = "Determines if the resize flag should be set"]
     fn resize_doc_continuous_vertical_layout(&mut self, store: &StrokeStore) -> bool {
         let padding_bottom = self.format.height();
-        let new_height = store.calc_height() + padding_bottom;
+        let new_height = self.content_height(store) + padding_bottom;
         let new_width = self.format.width();
 
         set_dimensions_checked(
@@ -334,8 +414,7 @@ impl Document {
         );
 
         if include_content {
-            let keys = store.stroke_keys_as_rendered();
-            let content_bounds = if let Some(content_bounds) = store.bounds_for_strokes(&keys) {
+            let content_bounds = if let Some(content_bounds) = self.content_bounds(store) {
                 content_bounds
                     .extend_right_and_bottom_by(na::vector![padding_horizontal, padding_vertical])
             } else {
@@ -379,8 +458,7 @@ impl Document {
             .merged(&viewport.extend_by(na::vector![padding_horizontal, padding_vertical]));
 
         if include_content {
-            let keys = store.stroke_keys_as_rendered();
-            let content_bounds = if let Some(content_bounds) = store.bounds_for_strokes(&keys) {
+            let content_bounds = if let Some(content_bounds) = self.content_bounds(store) {
                 content_bounds.extend_by(na::vector![padding_horizontal, padding_vertical])
             } else {
                 // If doc is empty, resize to one page with the format size
@@ -402,34 +480,96 @@ impl Document {
         )
     }
 
-    /// Snap the position to the document and pattern grid when `snap_positions` is enabled.
+    pub fn snap_config(&self) -> &SnapConfig {
+        &self.snap_config
+    }
+
+    pub fn set_snap_enabled(&mut self, enabled: bool) {
+        self.snap_config.set_enabled(enabled);
+    }
+
+    /// Set the custom grid spacing, clamped to the valid range.
+    pub fn set_snap_spacing(&mut self, spacing: na::Vector2<f64>) {
+        self.snap_config.set_spacing(spacing);
+    }
+
+    /// Set the offset of the custom grid from the document origin.
+    pub fn set_snap_origin(&mut self, origin: na::Vector2<f64>) {
+        self.snap_config.set_origin(origin);
+    }
+
+    /// Set whether positions are snapped to the background pattern instead of the custom grid.
+    pub fn set_snap_to_background_pattern(&mut self, snap_to_background_pattern: bool) {
+        self.snap_config
+            .set_snap_to_background_pattern(snap_to_background_pattern);
+    }
+
+    /// The margin guides of the pages.
+    pub fn margins(&self) -> PageMargins {
+        self.margins
+    }
+
+    /// Set the margin guides of the pages, negative distances are replaced by zero.
+    pub fn set_margins(&mut self, margins: PageMargins) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let margins = margins.sanitized();
+        if self.margins != margins {
+            self.margins = margins;
+            widget_flags.redraw = true;
+        }
+        widget_flags
+    }
+
+    /// Snap the position to the document and snap grid when snapping is enabled in the snap config.
     ///
-    /// If not, the original coordinates are returned.
+    /// Close to the document edges and the margin guides, the position is snapped to the nearest of them instead
+    /// of the grid. If snapping is not enabled, the original coordinates are returned.
     pub(crate) fn snap_position(&self, pos: na::Vector2<f64>) -> na::Vector2<f64> {
         const DOCUMENT_SNAP_DIST: f64 = 10.;
         let doc_format_size = self.format.size();
-        let pattern_size = self.background.pattern_size;
 
-        if !self.snap_positions {
+        if !self.snap_config.enabled() {
             return pos;
         }
+        let (grid_spacing, grid_origin) = self.snap_config.grid(&self.background);
 
         let snap_to_grid = |pos: na::Vector2<f64>, grid_size: na::Vector2<f64>| {
             let grid_pos = pos.component_div(&grid_size);
             grid_size.component_mul(&grid_pos.round())
         };
 
-        let pos_snapped_pattern = snap_to_grid(pos, pattern_size);
+        let mut pos_snapped = snap_to_grid(pos - grid_origin, grid_spacing) + grid_origin;
         let pos_snapped_document = snap_to_grid(pos, doc_format_size);
+        // Axes with a degenerate spacing are not snapped
+        for i in 0..2 {
+            if !pos_snapped[i].is_finite() {
+                pos_snapped[i] = pos[i];
+            }
+        }
 
-        let mut pos_snapped = pos_snapped_pattern;
-
-        // If the position is close to the document edges, then it is instead snapped to them.
-        if (pos_snapped_document - pos)[0].abs() < DOCUMENT_SNAP_DIST {
-            pos_snapped[0] = pos_snapped_document[0];
+        // The guides of the neighbouring pages are considered as well, for margins smaller than the snap distance
+        let page_origin = pos
+            .component_div(&doc_format_size)
+            .map(|v| v.floor())
+            .component_mul(&doc_format_size);
+        let mut guides = [vec![pos_snapped_document[0]], vec![pos_snapped_document[1]]];
+        for page in [-1.0, 0.0, 1.0] {
+            let page_mins = page_origin + doc_format_size * page;
+            let page_bounds = Aabb::new(page_mins.into(), (page_mins + doc_format_size).into());
+            guides[0].extend(self.margins.vertical_guides(page_bounds));
+            guides[1].extend(self.margins.horizontal_guides(page_bounds));
         }
-        if (pos_snapped_document - pos)[1].abs() < DOCUMENT_SNAP_DIST {
-            pos_snapped[1] = pos_snapped_document[1];
+
+        // If the position is close to the document edges or margin guides, then it is instead snapped to them.
+        for (i, guides) in guides.iter().enumerate() {
+            if let Some(guide) = guides
+                .iter()
+                .copied()
+                .filter(|guide| (guide - pos[i]).abs() < DOCUMENT_SNAP_DIST)
+                .min_by(|a, b| (a - pos[i]).abs().total_cmp(&(b - pos[i]).abs()))
+            {
+                pos_snapped[i] = guide;
+            }
         }
 
         pos_snapped
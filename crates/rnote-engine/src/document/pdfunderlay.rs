@@ -0,0 +1,205 @@
+// Imports
+use super::Format;
+use crate::engine::import::{PdfImportPageSpacing, PdfImportPrefs};
+use crate::render;
+use crate::strokes::Stroke;
+use anyhow::Context;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use serde::{Deserialize, Serialize};
+
+/// The placement of a page of the underlay Pdf in the document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "pdf_underlay_page")]
+pub struct PdfUnderlayPage {
+    /// The index of the page in the Pdf.
+    #[serde(rename = "page_index")]
+    pub page_index: u32,
+    /// The position of the page, in document coordinates.
+    #[serde(rename = "pos", with = "rnote_compose::serialize::na_vector2_f64_dp3")]
+    pub pos: na::Vector2<f64>,
+    /// The size of the page in the document.
+    #[serde(rename = "size", with = "rnote_compose::serialize::na_vector2_f64_dp3")]
+    pub size: na::Vector2<f64>,
+}
+
+impl Default for PdfUnderlayPage {
+    fn default() -> Self {
+        Self {
+            page_index: 0,
+            pos: na::Vector2::zeros(),
+            size: na::Vector2::zeros(),
+        }
+    }
+}
+
+impl PdfUnderlayPage {
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(self.pos.into(), (self.pos + self.size).into())
+    }
+}
+
+/// A Pdf whose pages are drawn beneath the strokes, so that they can be annotated.
+///
+/// Unlike imported Pdf pages the underlay is not made up of strokes,
+/// so it can't be selected or erased. The pages are rendered lazily, only when they become visible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "pdf_underlay")]
+pub struct PdfUnderlay {
+    /// The bytes of the Pdf, embedded in the document.
+    #[serde(rename = "pdf_data", with = "crate::utils::glib_bytes_base64")]
+    pub pdf_data: glib::Bytes,
+    #[serde(rename = "pages")]
+    pub pages: Vec<PdfUnderlayPage>,
+}
+
+impl Default for PdfUnderlay {
+    fn default() -> Self {
+        Self {
+            pdf_data: glib::Bytes::from_owned(Vec::new()),
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl PdfUnderlay {
+    /// The maximum number of pixels of a rendered page.
+    const PAGE_IMAGE_PIXELS_MAX: f64 = 4096.0 * 4096.0;
+
+    /// Place the pages of the Pdf bytes like imported Pdf pages, starting at the insert position.
+    pub fn from_pdf_bytes(
+        bytes: &[u8],
+        pdf_import_prefs: PdfImportPrefs,
+        insert_pos: na::Vector2<f64>,
+        format: &Format,
+    ) -> anyhow::Result<Self> {
+        let pdf_data = glib::Bytes::from(bytes);
+        let doc = poppler::Document::from_bytes(&pdf_data, None)?;
+
+        let page_width = if pdf_import_prefs.adjust_document {
+            format.width()
+        } else {
+            format.width() * (pdf_import_prefs.page_width_perc / 100.0)
+        };
+        // calculate the page zoom based on the width of the first page.
+        let Some(first_page) = doc.page(0) else {
+            return Err(anyhow::anyhow!("The Pdf has no pages."));
+        };
+        let page_zoom = page_width / first_page.size().0;
+        let mut pos = insert_pos;
+
+        let pages = (0..doc.n_pages().max(0) as u32)
+            .filter_map(|page_index| {
+                let intrinsic_size = doc.page(page_index as i32)?.size();
+                let size = na::vector![intrinsic_size.0, intrinsic_size.1] * page_zoom;
+                let page = PdfUnderlayPage {
+                    page_index,
+                    pos,
+                    size,
+                };
+
+                if pdf_import_prefs.adjust_document {
+                    pos[1] += size[1];
+                } else {
+                    pos[1] += match pdf_import_prefs.page_spacing {
+                        PdfImportPageSpacing::Continuous => {
+                            size[1] + Stroke::IMPORT_OFFSET_DEFAULT[1] * 0.5
+                        }
+                        PdfImportPageSpacing::OnePerDocumentPage => format.height(),
+                    };
+                }
+                Some(page)
+            })
+            .collect();
+
+        Ok(Self { pdf_data, pages })
+    }
+
+    /// The bounds of all pages.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.pages
+            .iter()
+            .map(|page| page.bounds())
+            .reduce(|acc, bounds| acc.merged(&bounds))
+    }
+
+    /// The indices of the pages that intersect the bounds.
+    pub(crate) fn pages_intersecting(&self, bounds: Aabb) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.bounds().intersects(&bounds))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Parse the Pdf. Is not cached, because the poppler document can't be shared between threads.
+    pub(crate) fn poppler_document(&self) -> anyhow::Result<poppler::Document> {
+        poppler::Document::from_bytes(&self.pdf_data, None)
+            .context("Parsing the underlay Pdf failed.")
+    }
+
+    /// Draw the page with the original vector content of the Pdf, on white paper.
+    fn draw_page_to_cairo(
+        doc: &poppler::Document,
+        page: &PdfUnderlayPage,
+        cx: &cairo::Context,
+    ) -> anyhow::Result<()> {
+        let poppler_page = doc
+            .page(page.page_index as i32)
+            .ok_or_else(|| anyhow::anyhow!("The underlay Pdf has no page {}.", page.page_index))?;
+        let intrinsic_size = poppler_page.size();
+
+        cx.save()?;
+        cx.translate(page.pos[0], page.pos[1]);
+        cx.rectangle(0.0, 0.0, page.size[0], page.size[1]);
+        cx.clip();
+        cx.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+        cx.paint()?;
+        cx.scale(
+            page.size[0] / intrinsic_size.0,
+            page.size[1] / intrinsic_size.1,
+        );
+        poppler_page.render_for_printing(cx);
+        cx.restore()?;
+        Ok(())
+    }
+
+    /// Draw the pages that intersect the bounds, keeping their vector content (e.g. when exporting as Pdf).
+    pub(crate) fn draw_to_cairo(&self, cx: &cairo::Context, bounds: Aabb) -> anyhow::Result<()> {
+        let pages = self.pages_intersecting(bounds);
+        if pages.is_empty() {
+            return Ok(());
+        }
+        let doc = self.poppler_document()?;
+        for i in pages {
+            Self::draw_page_to_cairo(&doc, &self.pages[i], cx)?;
+        }
+        Ok(())
+    }
+
+    /// Render the page with the image scale.
+    ///
+    /// The scale is lowered for pages that would exceed the maximum number of pixels.
+    pub(crate) fn gen_page_image(
+        &self,
+        i: usize,
+        image_scale: f64,
+    ) -> anyhow::Result<render::Image> {
+        let page = self
+            .pages
+            .get(i)
+            .ok_or_else(|| anyhow::anyhow!("The underlay has no page placement {i}."))?;
+        let pixels = page.size[0] * page.size[1] * image_scale * image_scale;
+        let image_scale = if pixels > Self::PAGE_IMAGE_PIXELS_MAX {
+            image_scale * (Self::PAGE_IMAGE_PIXELS_MAX / pixels).sqrt()
+        } else {
+            image_scale
+        };
+        let doc = self.poppler_document()?;
+        render::Image::gen_with_cairo(
+            |cx| Self::draw_page_to_cairo(&doc, page, cx),
+            page.bounds(),
+            image_scale,
+        )
+    }
+}
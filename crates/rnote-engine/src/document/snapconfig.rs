@@ -0,0 +1,181 @@
+// Imports
+use super::Background;
+use serde::{Deserialize, Serialize};
+
+/// The configuration of the grid that positions are snapped to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename = "snap_config")]
+pub struct SnapConfig {
+    /// Whether positions are snapped.
+    #[serde(rename = "enabled")]
+    enabled: bool,
+    /// The horizontal spacing of the grid.
+    #[serde(rename = "spacing_x", with = "rnote_compose::serialize::f64_dp3")]
+    spacing_x: f64,
+    /// The vertical spacing of the grid.
+    #[serde(rename = "spacing_y", with = "rnote_compose::serialize::f64_dp3")]
+    spacing_y: f64,
+    /// The offset of the grid from the document origin.
+    #[serde(
+        rename = "origin",
+        with = "rnote_compose::serialize::na_vector2_f64_dp3"
+    )]
+    origin: na::Vector2<f64>,
+    /// Whether the grid of the background pattern is used instead of the custom spacing and origin.
+    #[serde(rename = "snap_to_background_pattern")]
+    snap_to_background_pattern: bool,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spacing_x: Self::SPACING_DEFAULT,
+            spacing_y: Self::SPACING_DEFAULT,
+            origin: na::Vector2::zeros(),
+            snap_to_background_pattern: true,
+        }
+    }
+}
+
+impl SnapConfig {
+    pub const SPACING_MIN: f64 = 1.0;
+    pub const SPACING_MAX: f64 = 10000.0;
+    pub const SPACING_DEFAULT: f64 = 32.0;
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn spacing(&self) -> na::Vector2<f64> {
+        na::vector![self.spacing_x, self.spacing_y]
+    }
+
+    pub fn set_spacing(&mut self, spacing: na::Vector2<f64>) {
+        self.spacing_x = spacing[0].clamp(Self::SPACING_MIN, Self::SPACING_MAX);
+        self.spacing_y = spacing[1].clamp(Self::SPACING_MIN, Self::SPACING_MAX);
+    }
+
+    pub fn origin(&self) -> na::Vector2<f64> {
+        self.origin
+    }
+
+    pub fn set_origin(&mut self, origin: na::Vector2<f64>) {
+        if origin.iter().all(|c| c.is_finite()) {
+            self.origin = origin;
+        }
+    }
+
+    pub fn snap_to_background_pattern(&self) -> bool {
+        self.snap_to_background_pattern
+    }
+
+    pub fn set_snap_to_background_pattern(&mut self, snap_to_background_pattern: bool) {
+        self.snap_to_background_pattern = snap_to_background_pattern;
+    }
+
+    /// The spacing and origin of the grid that is snapped to.
+    ///
+    /// The background pattern is always aligned to the document origin.
+    pub(crate) fn grid(&self, background: &Background) -> (na::Vector2<f64>, na::Vector2<f64>) {
+        if self.snap_to_background_pattern {
+            (background.pattern_size, na::Vector2::zeros())
+        } else {
+            (self.spacing(), self.origin)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Document;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn snap_to_custom_grid() {
+        let mut document = Document::default();
+        let pos = na::vector![243.0, 171.0];
+        assert_relative_eq!(document.snap_position(pos), pos);
+
+        document.set_snap_enabled(true);
+        document.set_snap_to_background_pattern(false);
+        document.set_snap_spacing(na::vector![20.0, 50.0]);
+        document.set_snap_origin(na::vector![5.0, 7.0]);
+        assert_relative_eq!(document.snap_position(pos), na::vector![245.0, 157.0]);
+        // Positions on the grid are kept
+        assert_relative_eq!(
+            document.snap_position(na::vector![265.0, 207.0]),
+            na::vector![265.0, 207.0]
+        );
+    }
+
+    #[test]
+    fn snap_to_background_pattern() {
+        let mut document = Document::default();
+        document.set_snap_enabled(true);
+        document.set_snap_origin(na::vector![5.0, 7.0]);
+        document.background.pattern_size = na::vector![32.0, 32.0];
+        // The custom origin is ignored
+        assert_relative_eq!(
+            document.snap_position(na::vector![243.0, 171.0]),
+            na::vector![256.0, 160.0]
+        );
+    }
+
+    #[test]
+    fn snap_to_margin_guides() {
+        use crate::document::PageMargins;
+
+        let mut document = Document::default();
+        document.set_snap_enabled(true);
+        document.set_snap_to_background_pattern(false);
+        document.set_snap_spacing(na::vector![32.0, 32.0]);
+        let widget_flags = document.set_margins(PageMargins {
+            top: 60.0,
+            left: 30.0,
+            right: 20.0,
+            bottom: -5.0,
+        });
+        assert!(widget_flags.redraw);
+        assert_relative_eq!(document.margins().bottom, 0.0);
+
+        // Both the grid lines and the margin guides are within the snap distance,
+        // the guides take priority even though the grid lines are closer
+        assert_relative_eq!(
+            document.snap_position(na::vector![31.5, 63.0]),
+            na::vector![30.0, 60.0]
+        );
+        let page_width = document.format.width();
+        assert_relative_eq!(
+            document.snap_position(na::vector![page_width - 22.0, 200.0])[0],
+            page_width - 20.0
+        );
+        // Further away from the guides the grid is snapped to
+        assert_relative_eq!(
+            document.snap_position(na::vector![45.0, 200.0]),
+            na::vector![32.0, 192.0]
+        );
+    }
+
+    #[test]
+    fn snap_config_sanitized() {
+        let mut snap_config = SnapConfig::default();
+        snap_config.set_spacing(na::vector![0.0, -3.0]);
+        assert_relative_eq!(
+            snap_config.spacing(),
+            na::Vector2::repeat(SnapConfig::SPACING_MIN)
+        );
+        snap_config.set_origin(na::vector![f64::NAN, 1.0]);
+        assert_relative_eq!(snap_config.origin(), na::Vector2::zeros());
+
+        // Documents without a snap config deserialize to the default
+        let document: Document = serde_json::from_str("{}").unwrap();
+        assert_eq!(document.snap_config(), &SnapConfig::default());
+        assert!(document.margins().is_empty());
+    }
+}
@@ -0,0 +1,305 @@
+// Imports
+use crate::strokes::Stroke;
+use anyhow::Context;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use rnote_compose::shapes::Shapeable;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Strokes in the rnote clipboard format.
+///
+/// Used to copy and paste strokes between documents. The strokes are serialized with the same types as the save
+/// format, wrapped together with the version they were created with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename = "clipboard_strokes")]
+pub struct ClipboardStrokes {
+    #[serde(rename = "strokes")]
+    pub strokes: Vec<Arc<Stroke>>,
+}
+
+/// The clipboard strokes wrapper.
+///
+/// Used to extract and match the version up front, before deserializing the strokes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "clipboard_strokes_wrapper")]
+struct ClipboardStrokesWrapper {
+    #[serde(rename = "version")]
+    version: semver::Version,
+    #[serde(rename = "data")]
+    data: ijson::IValue,
+}
+
+impl ClipboardStrokes {
+    pub const MIME_TYPE: &'static str = "application/x-rnote-strokes";
+    pub const SEMVER: &'static str = crate::utils::crate_version();
+
+    pub fn new(strokes: Vec<Arc<Stroke>>) -> Self {
+        Self { strokes }
+    }
+
+    /// The bounds enclosing all strokes.
+    pub fn bounds(&self) -> Option<Aabb> {
+        if self.strokes.is_empty() {
+            return None;
+        }
+        Some(
+            self.strokes
+                .iter()
+                .map(|s| s.bounds())
+                .fold(Aabb::new_invalid(), |acc, x| acc.merged(&x)),
+        )
+    }
+
+    /// Serialize into the clipboard payload.
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let wrapper = ClipboardStrokesWrapper {
+            version: semver::Version::parse(Self::SEMVER).unwrap(),
+            data: ijson::to_value(self)
+                .context("converting ClipboardStrokes to JSON value failed.")?,
+        };
+        serde_json::to_vec(&wrapper).context("serializing ClipboardStrokesWrapper failed.")
+    }
+
+    /// Deserialize from the clipboard payload.
+    ///
+    /// The serialization of strokes is only compatible within the same minor version,
+    /// payloads from other versions are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let wrapper = serde_json::from_slice::<ClipboardStrokesWrapper>(bytes)
+            .context("deserializing ClipboardStrokesWrapper failed.")?;
+        let current = semver::Version::parse(Self::SEMVER).unwrap();
+
+        if wrapper.version.major != current.major || wrapper.version.minor != current.minor {
+            return Err(anyhow::anyhow!(
+                "failed to load clipboard strokes, unsupported version: {}, expected: {}.",
+                wrapper.version,
+                current
+            ));
+        }
+        ijson::from_value::<Self>(&wrapper.data).context("deserializing ClipboardStrokes failed.")
+    }
+}
+
+/// Where pasted clipboard strokes are placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PastePlacement {
+    /// Place the top left corner of the strokes at the position.
+    ///
+    /// Repeated pastes of the same payload at the same position are offset by [PastePlacement::CASCADE_STEP_DEFAULT].
+    AtPos(na::Vector2<f64>),
+    /// Place the strokes exactly at their original coordinates.
+    InPlace,
+    /// Place the strokes at their original coordinates,
+    /// offset by the step for every repeated paste of the same payload.
+    Cascade { step: na::Vector2<f64> },
+}
+
+impl PastePlacement {
+    pub const CASCADE_STEP_DEFAULT: na::Vector2<f64> = na::vector![32.0, 32.0];
+}
+
+/// Tracks repeated pastes of the same clipboard payload, so that they can be cascaded.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PasteCascade {
+    /// The hash of the last pasted payload.
+    payload_hash: Option<u64>,
+    /// The position the cascade started from.
+    anchor: na::Vector2<f64>,
+    /// The number of repeated pastes since the cascade started.
+    count: u32,
+}
+
+impl Default for PasteCascade {
+    fn default() -> Self {
+        Self {
+            payload_hash: None,
+            anchor: na::Vector2::zeros(),
+            count: 0,
+        }
+    }
+}
+
+impl PasteCascade {
+    /// The position of the top left corner of the pasted strokes, advancing the cascade.
+    ///
+    /// `original_pos` is the top left corner of the strokes in the payload.
+    pub(crate) fn next_pos(
+        &mut self,
+        payload: &[u8],
+        placement: PastePlacement,
+        original_pos: na::Vector2<f64>,
+    ) -> na::Vector2<f64> {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        payload.hash(&mut hasher);
+        let payload_hash = hasher.finish();
+        let repeated = self.payload_hash == Some(payload_hash);
+        self.payload_hash = Some(payload_hash);
+
+        match placement {
+            PastePlacement::AtPos(pos) => {
+                if repeated && (self.anchor - pos).norm() < 1e-6 {
+                    self.count += 1;
+                } else {
+                    self.anchor = pos;
+                    self.count = 0;
+                }
+                self.anchor + PastePlacement::CASCADE_STEP_DEFAULT * f64::from(self.count)
+            }
+            PastePlacement::InPlace => {
+                self.anchor = original_pos;
+                self.count = 0;
+                original_pos
+            }
+            PastePlacement::Cascade { step } => {
+                if repeated {
+                    self.count += 1;
+                } else {
+                    self.anchor = original_pos;
+                    self.count = 0;
+                }
+                self.anchor + step * f64::from(self.count)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::insert_rect;
+    use crate::Engine;
+    use std::time::Instant;
+
+    #[test]
+    fn version_mismatch_is_rejected() {
+        let bytes = ClipboardStrokes::default().to_bytes().unwrap();
+        assert!(ClipboardStrokes::from_bytes(&bytes).is_ok());
+
+        let mut wrapper = serde_json::from_slice::<ClipboardStrokesWrapper>(&bytes).unwrap();
+        wrapper.version = semver::Version::new(wrapper.version.major + 1, 0, 0);
+        let bytes = serde_json::to_vec(&wrapper).unwrap();
+        assert!(ClipboardStrokes::from_bytes(&bytes).is_err());
+
+        assert!(ClipboardStrokes::from_bytes(b"not a payload").is_err());
+    }
+
+    /// The clipboard payload of a document with a single rectangle stroke, and the bounds of the stroke.
+    ///
+    /// The outline of the rectangle starts at the origin, the bounds include the stroke width.
+    fn rectangle_clipboard_bytes() -> (Vec<u8>, Aabb) {
+        let mut source = Engine::default();
+        let key = insert_rect(
+            &mut source.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        source.store.record(Instant::now());
+        let _ = source.select_all_strokes();
+        let bytes = source.fetch_clipboard_selection();
+        assert!(!bytes.is_empty());
+        (bytes, source.store.bounds_for_strokes(&[key]).unwrap())
+    }
+
+    fn pasted_pos(engine: &Engine) -> na::Vector2<f64> {
+        let selection = engine.store.selection_keys_unordered();
+        engine
+            .store
+            .bounds_for_strokes(&selection)
+            .unwrap()
+            .mins
+            .coords
+    }
+
+    #[test]
+    fn paste_into_other_document() {
+        let (bytes, _) = rectangle_clipboard_bytes();
+        let mut target = Engine::default();
+        let _ = target
+            .insert_clipboard_strokes(&bytes, PastePlacement::AtPos(na::vector![200.0, 300.0]))
+            .unwrap();
+        let selection = target.store.selection_keys_unordered();
+        assert_eq!(selection.len(), 1);
+        let bounds = target.store.bounds_for_strokes(&selection).unwrap();
+        approx::assert_relative_eq!(bounds.mins.coords, na::vector![200.0, 300.0]);
+
+        // the paste is undoable
+        let _ = target.undo(Instant::now());
+        assert!(target.store.stroke_keys_unordered().is_empty());
+    }
+
+    #[test]
+    fn repeated_pastes_cascade() {
+        let (bytes, source_bounds) = rectangle_clipboard_bytes();
+        let source_pos = source_bounds.mins.coords;
+        let mut engine = Engine::default();
+        let mut paste = |placement| {
+            let _ = engine.insert_clipboard_strokes(&bytes, placement).unwrap();
+            assert_eq!(engine.store.selection_keys_unordered().len(), 1);
+            pasted_pos(&engine)
+        };
+        let pos = na::vector![200.0, 300.0];
+
+        approx::assert_relative_eq!(paste(PastePlacement::AtPos(pos)), pos);
+        approx::assert_relative_eq!(
+            paste(PastePlacement::AtPos(pos)),
+            pos + PastePlacement::CASCADE_STEP_DEFAULT
+        );
+        // pasting at another position restarts the cascade
+        approx::assert_relative_eq!(
+            paste(PastePlacement::AtPos(na::vector![20.0, 30.0])),
+            na::vector![20.0, 30.0]
+        );
+        approx::assert_relative_eq!(paste(PastePlacement::InPlace), source_pos);
+        approx::assert_relative_eq!(paste(PastePlacement::InPlace), source_pos);
+        let step = na::vector![10.0, 0.0];
+        approx::assert_relative_eq!(paste(PastePlacement::Cascade { step }), source_pos + step);
+        approx::assert_relative_eq!(
+            paste(PastePlacement::Cascade { step }),
+            source_pos + step * 2.0
+        );
+        assert_eq!(engine.store.stroke_keys_unordered().len(), 7);
+
+        // pastes outside of the viewport move the camera to them
+        let far_pos = na::vector![5000.0, 8000.0];
+        let _ = engine
+            .insert_clipboard_strokes(&bytes, PastePlacement::AtPos(far_pos))
+            .unwrap();
+        let selection = engine.store.selection_keys_unordered();
+        let bounds = engine.store.bounds_for_strokes(&selection).unwrap();
+        assert!(engine.camera.viewport().contains(&bounds));
+    }
+
+    #[test]
+    fn cascade_counts_from_first_paste() {
+        let payload = b"payload";
+        let original_pos = na::vector![10.0, 20.0];
+        let step = na::vector![5.0, 5.0];
+        let pos = na::vector![200.0, 300.0];
+
+        // the first paste is never offset, every following one by another step
+        let mut cascade = PasteCascade::default();
+        approx::assert_relative_eq!(
+            cascade.next_pos(payload, PastePlacement::AtPos(pos), original_pos),
+            pos
+        );
+        approx::assert_relative_eq!(
+            cascade.next_pos(payload, PastePlacement::AtPos(pos), original_pos),
+            pos + PastePlacement::CASCADE_STEP_DEFAULT
+        );
+
+        let mut cascade = PasteCascade::default();
+        approx::assert_relative_eq!(
+            cascade.next_pos(payload, PastePlacement::Cascade { step }, original_pos),
+            original_pos
+        );
+        approx::assert_relative_eq!(
+            cascade.next_pos(payload, PastePlacement::Cascade { step }, original_pos),
+            original_pos + step
+        );
+        // another payload restarts the cascade
+        approx::assert_relative_eq!(
+            cascade.next_pos(b"other", PastePlacement::Cascade { step }, original_pos),
+            original_pos
+        );
+    }
+}
@@ -0,0 +1,336 @@
+// Imports
+use super::Engine;
+use crate::pens::{PenMode, PenStyle};
+use crate::WidgetFlags;
+use anyhow::Context;
+use rnote_compose::penevent::{InputSource, PenEvent, ShortcutKey};
+use rnote_compose::penpath::Element;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// An input that entered the penholder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "recorded_input")]
+pub enum RecordedInput {
+    #[serde(rename = "pen_event")]
+    PenEvent {
+        #[serde(rename = "event")]
+        event: PenEvent,
+        #[serde(rename = "pen_mode")]
+        pen_mode: Option<PenMode>,
+        /// The source of the element of the event, which is not serialized with the element.
+        #[serde(rename = "input_source")]
+        input_source: Option<InputSource>,
+    },
+    #[serde(rename = "shortcut_key")]
+    ShortcutKey {
+        #[serde(rename = "shortcut_key")]
+        shortcut_key: ShortcutKey,
+    },
+}
+
+/// A recorded input, with the time it was handled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "recorded_event")]
+pub struct RecordedEvent {
+    /// The time since the first event of the recording.
+    #[serde(rename = "time")]
+    pub time: Duration,
+    /// The active pen style, including the override, before the input was handled.
+    #[serde(rename = "pen_style")]
+    pub pen_style: PenStyle,
+    #[serde(rename = "input")]
+    pub input: RecordedInput,
+}
+
+/// A log of recorded inputs, to reproduce bugs by replaying them with [Engine::replay_events].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename = "event_log")]
+pub struct EventLog {
+    #[serde(rename = "events")]
+    pub events: Vec<RecordedEvent>,
+}
+
+impl EventLog {
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let bytes = serde_json::to_vec(self).context("Serializing the event log failed.")?;
+        std::fs::write(path, bytes)
+            .context(format!("Failed to write event log '{}'", path.display()))
+    }
+
+    pub fn load_from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .context(format!("Failed to read event log '{}'", path.display()))?;
+        serde_json::from_slice(&bytes).context("Deserializing the event log failed.")
+    }
+}
+
+/// Records the inputs entering the penholder while it is enabled.
+#[derive(Debug, Default)]
+pub(crate) struct EventRecorder {
+    /// When the first event was recorded.
+    start: Option<Instant>,
+    log: EventLog,
+}
+
+impl EventRecorder {
+    fn record(&mut self, now: Instant, pen_style: PenStyle, input: RecordedInput) {
+        let start = *self.start.get_or_insert(now);
+        self.log.events.push(RecordedEvent {
+            time: now.saturating_duration_since(start),
+            pen_style,
+            input,
+        });
+    }
+}
+
+fn pen_event_element_mut(event: &mut PenEvent) -> Option<&mut Element> {
+    match event {
+        PenEvent::Down { element, .. }
+        | PenEvent::Up { element, .. }
+        | PenEvent::Proximity { element, .. } => Some(element),
+        PenEvent::KeyPressed { .. } | PenEvent::Text { .. } | PenEvent::Cancel => None,
+    }
+}
+
+impl Engine {
+    /// Start recording the inputs that enter the penholder. Discards a recording that is still running.
+    pub fn start_recording_events(&mut self) {
+        self.event_recorder = Some(EventRecorder::default());
+    }
+
+    /// Stop recording and return the log, or None when it was not recording.
+    pub fn stop_recording_events(&mut self) -> Option<EventLog> {
+        self.event_recorder.take().map(|recorder| recorder.log)
+    }
+
+    pub fn is_recording_events(&self) -> bool {
+        self.event_recorder.is_some()
+    }
+
+    pub(super) fn record_pen_event(
+        &mut self,
+        event: &PenEvent,
+        pen_mode: Option<PenMode>,
+        now: Instant,
+    ) {
+        let pen_style = self.penholder.current_pen_style_w_override();
+        let Some(recorder) = self.event_recorder.as_mut() else {
+            return;
+        };
+        let mut event = event.clone();
+        let input_source = pen_event_element_mut(&mut event).map(|element| element.source);
+        recorder.record(
+            now,
+            pen_style,
+            RecordedInput::PenEvent {
+                event,
+                pen_mode,
+                input_source,
+            },
+        );
+    }
+
+    pub(super) fn record_shortcut_key(&mut self, shortcut_key: ShortcutKey, now: Instant) {
+        let pen_style = self.penholder.current_pen_style_w_override();
+        if let Some(recorder) = self.event_recorder.as_mut() {
+            recorder.record(now, pen_style, RecordedInput::ShortcutKey { shortcut_key });
+        }
+    }
+
+    /// Feed the recorded inputs through the same path as the inputs coming from the UI.
+    ///
+    /// The events are handled immediately, with their recorded times divided by the speed
+    /// instead of the wall time, so that replaying is deterministic.
+    /// The pen style is changed first when it differs from the recorded one,
+    /// because changes of the style that were made through the UI are not recorded.
+    pub fn replay_events(&mut self, log: &EventLog, speed: f64) -> anyhow::Result<WidgetFlags> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(anyhow::anyhow!("Invalid replay speed {speed}."));
+        }
+        let mut widget_flags = WidgetFlags::default();
+        let start = Instant::now();
+
+        for recorded in log.events.iter().cloned() {
+            let now = start + recorded.time.div_f64(speed);
+            if self.penholder.current_pen_style_w_override() != recorded.pen_style {
+                widget_flags |= self.change_pen_style(recorded.pen_style);
+            }
+            match recorded.input {
+                RecordedInput::PenEvent {
+                    mut event,
+                    pen_mode,
+                    input_source,
+                } => {
+                    if let (Some(element), Some(input_source)) =
+                        (pen_event_element_mut(&mut event), input_source)
+                    {
+                        element.source = input_source;
+                    }
+                    widget_flags |= self.handle_pen_event(event, pen_mode, now).1;
+                }
+                RecordedInput::ShortcutKey { shortcut_key } => {
+                    widget_flags |= self.handle_pressed_shortcut_key(shortcut_key, now).1;
+                }
+            }
+        }
+        Ok(widget_flags)
+    }
+}
+
+/// Helpers for regression tests that replay recorded event logs.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::engine::EngineSnapshot;
+    use std::hash::{Hash, Hasher};
+
+    /// The hash of the geometry and style of the strokes in their drawing order, and of their trash, selection and lock state.
+    ///
+    /// The creation and modification timestamps of the strokes are left out, because they are taken from the wall clock.
+    pub(crate) fn store_state_hash(engine: &Engine) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        let keys = engine.store.keys_sorted_chrono();
+        for (key, stroke) in keys.iter().zip(engine.store.get_strokes_arc(&keys)) {
+            key.hash(&mut hasher);
+            serde_json::to_vec(&stroke).unwrap().hash(&mut hasher);
+            engine.store.trashed(*key).hash(&mut hasher);
+            engine.store.selected(*key).hash(&mut hasher);
+            engine.store.locked(*key).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Load the document from the bytes of a rnote file into a new engine.
+    pub(crate) fn load_document(document: Vec<u8>) -> Engine {
+        let mut engine = Engine::default();
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(document)).unwrap();
+        let _ = engine.load_snapshot(snapshot);
+        engine
+    }
+
+    /// Load the initial document, replay the log over it and assert the hash of the final store state.
+    ///
+    /// Returns the engine, so that the test can continue from the replayed state.
+    pub(crate) fn assert_replayed_store_hash(
+        document: Vec<u8>,
+        log_path: &Path,
+        expected_hash: u64,
+    ) -> Engine {
+        let log = EventLog::load_from_file(log_path).unwrap();
+        let mut engine = load_document(document);
+        let _ = engine.replay_events(&log, 1.0).unwrap();
+        assert_eq!(store_state_hash(&engine), expected_hash);
+        engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+    use crate::pens::pensconfig::selectorconfig::SelectorStyle;
+    use crate::testsupport::{insert_rect, test_dir};
+    use p2d::bounding_volume::Aabb;
+    use std::collections::HashSet;
+
+    /// Drag with the primary button from the start to the end position, advancing the time with each event.
+    fn drag(
+        engine: &mut Engine,
+        now: &mut Instant,
+        start: na::Vector2<f64>,
+        end: na::Vector2<f64>,
+    ) {
+        for i in 0..=10 {
+            *now += Duration::from_millis(16);
+            let _ = engine.handle_pen_event(
+                PenEvent::down(
+                    Element::new(start.lerp(&end, i as f64 / 10.0), Element::PRESSURE_DEFAULT),
+                    HashSet::new(),
+                ),
+                None,
+                *now,
+            );
+        }
+        *now += Duration::from_millis(16);
+        let _ = engine.handle_pen_event(
+            PenEvent::up(Element::new(end, Element::PRESSURE_DEFAULT), HashSet::new()),
+            None,
+            *now,
+        );
+    }
+
+    fn stroke_bounds(engine: &Engine) -> Aabb {
+        let keys = engine.store.stroke_keys_as_rendered();
+        engine.store.bounds_for_strokes(&keys).unwrap()
+    }
+
+    #[test]
+    fn replayed_select_resize_undo() {
+        let dir = test_dir("eventrecorder", "select-resize-undo");
+        let mut engine = Engine::default();
+        engine.pens_config.selector_config.style = SelectorStyle::Rectangle;
+        let _ = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        let document =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+
+        // record selecting the stroke and dragging the bottom right resize node
+        let mut engine = load_document(document.clone());
+        let initial_bounds = stroke_bounds(&engine);
+        let mut now = Instant::now();
+        engine.start_recording_events();
+        let _ = engine.change_pen_style(PenStyle::Selector);
+        drag(
+            &mut engine,
+            &mut now,
+            na::vector![-20.0, -20.0],
+            na::vector![150.0, 100.0],
+        );
+        assert_eq!(engine.store.selection_keys_as_rendered().len(), 1);
+        let node_pos = initial_bounds.maxs.coords + na::vector![3.0, 3.0];
+        drag(&mut engine, &mut now, node_pos, node_pos * 2.0);
+        let log = engine.stop_recording_events().unwrap();
+        assert!(!engine.is_recording_events());
+        let resized_bounds = stroke_bounds(&engine);
+        assert!(resized_bounds.extents()[0] > initial_bounds.extents()[0] * 1.5);
+        let resized_hash = store_state_hash(&engine);
+        let _ = engine.undo(now);
+        let undone_hash = store_state_hash(&engine);
+
+        let log_path = dir.join("select-resize-undo.json");
+        log.save_to_file(&log_path).unwrap();
+        let mut replayed = assert_replayed_store_hash(document, &log_path, resized_hash);
+        assert_eq!(
+            replayed.penholder.current_pen_style_w_override(),
+            PenStyle::Selector
+        );
+        let _ = replayed.undo(Instant::now());
+        assert_eq!(store_state_hash(&replayed), undone_hash);
+        approx::assert_relative_eq!(
+            stroke_bounds(&replayed).maxs.coords,
+            initial_bounds.maxs.coords
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn replay_rejects_invalid_speed() {
+        let mut engine = Engine::default();
+        assert!(engine.replay_events(&EventLog::default(), 0.0).is_err());
+        assert!(engine
+            .replay_events(&EventLog::default(), f64::NAN)
+            .is_err());
+        assert!(engine.replay_events(&EventLog::default(), 2.0).is_ok());
+    }
+}
@@ -1,7 +1,11 @@
 // Imports
 use super::{Engine, EngineConfig, StrokeContent};
+use crate::document::format::MeasureUnit;
+use crate::document::DocumentMeta;
+use crate::fileformats::rnoteformat::maj0min9::RnoteThumbnail;
 use crate::fileformats::rnoteformat::RnoteFile;
 use crate::fileformats::{xoppformat, FileFormatSaver};
+use crate::render::Svg;
 use crate::CloneConfig;
 use anyhow::Context;
 use futures::channel::oneshot;
@@ -9,6 +13,7 @@ use rayon::prelude::*;
 use rnote_compose::transform::Transformable;
 use rnote_compose::SplitOrder;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
 use std::sync::Arc;
 use tracing::error;
 
@@ -226,6 +231,8 @@ pub enum SelectionExportFormat {
     Png,
     #[serde(rename = "jpeg")]
     Jpeg,
+    #[serde(rename = "pdf")]
+    Pdf,
 }
 
 impl Default for SelectionExportFormat {
@@ -240,6 +247,16 @@ impl SelectionExportFormat {
             SelectionExportFormat::Svg => String::from("svg"),
             SelectionExportFormat::Png => String::from("png"),
             SelectionExportFormat::Jpeg => String::from("jpg"),
+            SelectionExportFormat::Pdf => String::from("pdf"),
+        }
+    }
+
+    pub fn mime_type(self) -> String {
+        match self {
+            SelectionExportFormat::Svg => String::from(Svg::MIME_TYPE),
+            SelectionExportFormat::Png => String::from("image/png"),
+            SelectionExportFormat::Jpeg => String::from("image/jpeg"),
+            SelectionExportFormat::Pdf => String::from("application/pdf"),
         }
     }
 }
@@ -257,6 +274,52 @@ impl TryFrom<u32> for SelectionExportFormat {
     }
 }
 
+/// The physical dimensions of an exported selection.
+///
+/// Only applies to the vector formats, the size of bitmaps is always in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "selection_physical_size")]
+pub enum SelectionPhysicalSize {
+    /// Without physical units, the dimensions are in pixels.
+    #[serde(rename = "none")]
+    None,
+    /// True to scale, according to the DPI of the document.
+    #[serde(rename = "true_to_scale")]
+    TrueToScale,
+    /// Scaled to fit inside the size in mm, preserving the aspect ratio.
+    #[serde(rename = "fit_mm")]
+    FitMm { width: f64, height: f64 },
+}
+
+impl Default for SelectionPhysicalSize {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl SelectionPhysicalSize {
+    /// The size in mm of exported content with the extents in pixels.
+    ///
+    /// Returns None when it is exported without physical units or the extents are empty.
+    pub fn size_mm(&self, extents: na::Vector2<f64>, dpi: f64) -> Option<na::Vector2<f64>> {
+        if extents[0] <= 0.0 || extents[1] <= 0.0 {
+            return None;
+        }
+        match self {
+            Self::None => None,
+            Self::TrueToScale => Some(extents.map(|v| {
+                MeasureUnit::convert_measurement(v, MeasureUnit::Px, dpi, MeasureUnit::Mm, dpi)
+            })),
+            Self::FitMm { width, height } => {
+                if *width <= 0.0 || *height <= 0.0 {
+                    return None;
+                }
+                Some(extents * (width / extents[0]).min(height / extents[1]))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(default, rename = "selection_export_prefs")]
 pub struct SelectionExportPrefs {
@@ -281,6 +344,9 @@ pub struct SelectionExportPrefs {
     /// The margins of the export extending the bounds of the selection.
     #[serde(rename = "margin")]
     pub margin: f64,
+    /// The physical dimensions of the exported Svg or Pdf.
+    #[serde(rename = "physical_size")]
+    pub physical_size: SelectionPhysicalSize,
 }
 
 impl Default for SelectionExportPrefs {
@@ -293,6 +359,7 @@ impl Default for SelectionExportPrefs {
             bitmap_scalefactor: 1.8,
             jpeg_quality: 85,
             margin: 12.0,
+            physical_size: SelectionPhysicalSize::default(),
         }
     }
 }
@@ -318,21 +385,69 @@ impl CloneConfig for ExportPrefs {
     }
 }
 
+/// Options for saving the document as a .rnote file.
+#[derive(Debug, Clone, Copy)]
+pub struct RnoteSaveOptions {
+    /// Whether a thumbnail of the first page is embedded, for file managers to show as preview.
+    ///
+    /// Can be disabled to save faster.
+    pub embed_thumbnail: bool,
+}
+
+impl Default for RnoteSaveOptions {
+    fn default() -> Self {
+        Self {
+            embed_thumbnail: true,
+        }
+    }
+}
+
 impl Engine {
     /// The used image scale-factor for any strokes that are converted to bitmap images on export.
     pub const STROKE_EXPORT_IMAGE_SCALE: f64 = 1.8;
+    /// The maximum width and height of the thumbnail that is embedded in saved .rnote files, in pixels.
+    pub const THUMBNAIL_SIZE_MAX: f64 = 256.0;
 
     /// Save the current document as a .rnote file.
+    ///
+    /// Updates the modification time in the document metadata.
     pub fn save_as_rnote_bytes(
-        &self,
+        &mut self,
+        file_name: String,
+    ) -> oneshot::Receiver<anyhow::Result<Vec<u8>>> {
+        self.save_as_rnote_bytes_w_options(file_name, RnoteSaveOptions::default())
+    }
+
+    /// Save the current document as a .rnote file with the given options.
+    ///
+    /// Updates the modification time in the document metadata.
+    pub fn save_as_rnote_bytes_w_options(
+        &mut self,
         file_name: String,
+        options: RnoteSaveOptions,
     ) -> oneshot::Receiver<anyhow::Result<Vec<u8>>> {
         let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<Vec<u8>>>();
-        let engine_snapshot = self.take_snapshot();
+        self.document_meta.touch(DocumentMeta::now_timestamp());
+        let mut engine_snapshot = self.take_snapshot();
+        self.journal_begin_full_save(&mut engine_snapshot);
+        let thumbnail_content = options
+            .embed_thumbnail
+            .then(|| self.extract_thumbnail_content());
         rayon::spawn(move || {
             let result = || -> anyhow::Result<Vec<u8>> {
+                // A missing thumbnail is not worth failing the save
+                let thumbnail = thumbnail_content.and_then(|content| {
+                    match Self::gen_thumbnail(content) {
+                        Ok(thumbnail) => thumbnail,
+                        Err(e) => {
+                            error!("Generating the thumbnail failed while saving document as rnote bytes, Err: {e:?}");
+                            None
+                        }
+                    }
+                });
                 let rnote_file = RnoteFile {
                     engine_snapshot: ijson::to_value(&engine_snapshot)?,
+                    thumbnail,
                 };
                 rnote_file.save_as_bytes(&file_name)
             };
@@ -347,8 +462,12 @@ impl Engine {
 
     /// Extract the current engine configuration.
     pub fn extract_engine_config(&self) -> EngineConfig {
+        let mut document = self.document.clone_config();
+        // The background image and the Pdf underlay are part of the document, not of the config
+        document.background.image = None;
+        document.pdf_underlay = None;
         EngineConfig {
-            document: self.document.clone_config(),
+            document,
             pens_config: self.pens_config.clone_config(),
             penholder: self.penholder.clone_config(),
             import_prefs: self.import_prefs.clone_config(),
@@ -368,7 +487,42 @@ impl Engine {
                 self.bounds_w_content_extended()
                     .unwrap_or(self.document.bounds()),
             ))
-            .with_background(Some(self.document.background))
+            .with_background(Some(self.document.background.clone()))
+            .with_pdf_underlay(self.document.pdf_underlay.clone())
+    }
+
+    /// The content of the first page, which is shown in the thumbnail.
+    fn extract_thumbnail_content(&self) -> StrokeContent {
+        let bounds = self
+            .pages_bounds_w_content(SplitOrder::default())
+            .first()
+            .copied()
+            .unwrap_or(self.document.bounds());
+        StrokeContent::default()
+            .with_strokes(
+                self.store.get_strokes_arc(
+                    &self
+                        .store
+                        .stroke_keys_as_rendered_intersecting_bounds(bounds),
+                ),
+            )
+            .with_bounds(Some(bounds))
+            .with_background(Some(self.document.background.clone()))
+    }
+
+    /// Render the content into a png encoded thumbnail that fits into [Self::THUMBNAIL_SIZE_MAX].
+    fn gen_thumbnail(content: StrokeContent) -> anyhow::Result<Option<RnoteThumbnail>> {
+        let Some(svg) = content.gen_svg(true, true, false, 0.0)? else {
+            return Ok(None);
+        };
+        let size_max = svg.bounds.extents().max();
+        if size_max <= 0.0 {
+            return Ok(None);
+        }
+        let png_data = svg
+            .gen_image((Self::THUMBNAIL_SIZE_MAX / size_max).min(1.0))?
+            .into_encoded_bytes(image::ImageFormat::Png, None)?;
+        Ok(Some(RnoteThumbnail { png_data }))
     }
 
     pub fn extract_pages_content(&self, page_order: SplitOrder) -> Vec<StrokeContent> {
@@ -384,7 +538,8 @@ impl Engine {
                         ),
                     )
                     .with_bounds(Some(bounds))
-                    .with_background(Some(self.document.background))
+                    .with_background(Some(self.document.background.clone()))
+                    .with_pdf_underlay(self.document.pdf_underlay.clone())
             })
             .collect()
     }
@@ -397,7 +552,7 @@ impl Engine {
         Some(
             StrokeContent::default()
                 .with_strokes(self.store.get_strokes_arc(&selection_keys))
-                .with_background(Some(self.document.background)),
+                .with_background(Some(self.document.background.clone())),
         )
     }
 
@@ -440,6 +595,7 @@ impl Engine {
         let doc_export_prefs =
             doc_export_prefs_override.unwrap_or(self.export_prefs.doc_export_prefs);
         let doc_content = self.extract_document_content();
+        let svg_metadata = self.document_meta.gen_svg_metadata().unwrap_or_default();
 
         rayon::spawn(move || {
             let result = || -> anyhow::Result<Vec<u8>> {
@@ -453,7 +609,7 @@ impl Engine {
                     .ok_or(anyhow::anyhow!("Generating doc svg failed, returned None."))?;
                 Ok(rnote_compose::utils::add_xml_header(
                     rnote_compose::utils::wrap_svg_root(
-                        doc_svg.svg_data.as_str(),
+                        (svg_metadata + doc_svg.svg_data.as_str()).as_str(),
                         Some(doc_svg.bounds),
                         Some(doc_svg.bounds),
                         false,
@@ -480,8 +636,10 @@ impl Engine {
         let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<Vec<u8>>>();
         let doc_export_prefs =
             doc_export_prefs_override.unwrap_or(self.export_prefs.doc_export_prefs);
+        // The underlay pages are merged with their original vector content instead of being rasterized
         let pages_content = self.extract_pages_content(doc_export_prefs.page_order);
         let format_size = self.document.format.size();
+        let document_meta = self.document_meta.clone();
 
         rayon::spawn(move || {
             let result = || -> anyhow::Result<Vec<u8>> {
@@ -489,15 +647,7 @@ impl Engine {
                     cairo::PdfSurface::for_stream(format_size[0], format_size[1], Vec::<u8>::new())
                         .context("Creating Pdf target surface failed.")?;
 
-                target_surface
-                    .set_metadata(cairo::PdfMetadata::Title, title.as_str())
-                    .context("Set pdf surface title metadata failed.")?;
-                target_surface
-                    .set_metadata(
-                        cairo::PdfMetadata::CreateDate,
-                        crate::utils::now_formatted_string().as_str(),
-                    )
-                    .context("Set pdf surface date metadata failed.")?;
+                document_meta.apply_to_pdf_surface(&target_surface, title.as_str())?;
 
                 // New scope to avoid errors when flushing
                 {
@@ -704,6 +854,7 @@ impl Engine {
         let doc_pages_export_prefs =
             doc_pages_export_prefs_override.unwrap_or(self.export_prefs.doc_pages_export_prefs);
         let pages_content = self.extract_pages_content(doc_pages_export_prefs.page_order);
+        let svg_metadata = self.document_meta.gen_svg_metadata().unwrap_or_default();
 
         rayon::spawn(move || {
             let result = || -> anyhow::Result<Vec<Vec<u8>>> {
@@ -723,7 +874,7 @@ impl Engine {
                             ))?;
                         Ok(rnote_compose::utils::add_xml_header(
                             rnote_compose::utils::wrap_svg_root(
-                                page_svg.svg_data.as_str(),
+                                (svg_metadata.clone() + page_svg.svg_data.as_str()).as_str(),
                                 Some(page_svg.bounds),
                                 Some(page_svg.bounds),
                                 false,
@@ -756,6 +907,7 @@ impl Engine {
         let doc_pages_export_prefs =
             doc_pages_export_prefs_override.unwrap_or(self.export_prefs.doc_pages_export_prefs);
         let pages_contents = self.extract_pages_content(doc_pages_export_prefs.page_order);
+        let document_meta = self.document_meta.clone();
 
         rayon::spawn(move || {
             let result = || -> Result<Vec<Vec<u8>>, anyhow::Error> {
@@ -768,7 +920,7 @@ impl Engine {
                     .into_par_iter()
                     .enumerate()
                     .map(|(i, page_content)| {
-                        page_content
+                        let bytes = page_content
                             .gen_svg(
                                 doc_pages_export_prefs.with_background,
                                 doc_pages_export_prefs.with_pattern,
@@ -782,7 +934,12 @@ impl Engine {
                             .into_encoded_bytes(
                                 image_format,
                                 Some(doc_pages_export_prefs.jpeg_quality),
-                            )
+                            )?;
+                        if image_format == image::ImageFormat::Png {
+                            document_meta.embed_in_png(bytes)
+                        } else {
+                            Ok(bytes)
+                        }
                     })
                     .collect()
             };
@@ -809,6 +966,30 @@ impl Engine {
             SelectionExportFormat::Png | SelectionExportFormat::Jpeg => {
                 self.export_selection_as_bitmap_bytes(selection_export_prefs_override)
             }
+            SelectionExportFormat::Pdf => {
+                self.export_selection_as_pdf_bytes(selection_export_prefs_override)
+            }
+        }
+    }
+
+    /// Exports the current selection in the given format.
+    ///
+    /// Other than [Engine::export_selection], this returns an error when nothing is selected.
+    /// Used for copying the selection as image to the clipboard.
+    pub fn export_selection_w_format(
+        &self,
+        export_format: SelectionExportFormat,
+        selection_export_prefs_override: Option<SelectionExportPrefs>,
+    ) -> impl Future<Output = anyhow::Result<Vec<u8>>> {
+        let receiver = self.export_selection(Some(SelectionExportPrefs {
+            export_format,
+            ..selection_export_prefs_override.unwrap_or(self.export_prefs.selection_export_prefs)
+        }));
+
+        async move {
+            receiver.await??.ok_or_else(|| {
+                anyhow::anyhow!("Exporting selection failed, no strokes are selected.")
+            })
         }
     }
 
@@ -822,6 +1003,7 @@ impl Engine {
         let selection_export_prefs =
             selection_export_prefs_override.unwrap_or(self.export_prefs.selection_export_prefs);
         let content = self.extract_selection_content();
+        let dpi = self.document.format.dpi();
 
         rayon::spawn(move || {
             let result = || -> Result<Option<Vec<u8>>, anyhow::Error> {
@@ -837,19 +1019,11 @@ impl Engine {
                 else {
                     return Ok(None);
                 };
+                let size_mm = selection_export_prefs
+                    .physical_size
+                    .size_mm(svg.bounds.extents(), dpi);
 
-                Ok(Some(
-                    rnote_compose::utils::add_xml_header(
-                        rnote_compose::utils::wrap_svg_root(
-                            svg.svg_data.as_str(),
-                            Some(svg.bounds),
-                            Some(svg.bounds),
-                            false,
-                        )
-                        .as_str(),
-                    )
-                    .into_bytes(),
-                ))
+                Ok(Some(selection_svg_bytes(&svg, size_mm)))
             };
             if oneshot_sender.send(result()).is_err() {
                 error!("Sending result to receiver failed while exporting selection as Svg bytes. Receiver already dropped.");
@@ -887,7 +1061,7 @@ impl Engine {
                     return Ok(None);
                 };
                 let image_format = match selection_export_prefs.export_format {
-                    SelectionExportFormat::Svg => return Err(anyhow::anyhow!("Extracting bitmap image format from doc pages export prefs failed, not set to a bitmap format.")),
+                    SelectionExportFormat::Svg | SelectionExportFormat::Pdf => return Err(anyhow::anyhow!("Extracting bitmap image format from doc pages export prefs failed, not set to a bitmap format.")),
                     SelectionExportFormat::Png => image::ImageFormat::Png,
                     SelectionExportFormat::Jpeg => image::ImageFormat::Jpeg
                 };
@@ -907,4 +1081,101 @@ impl Engine {
 
         oneshot_receiver
     }
+
+    /// Export the selection as Pdf.
+    fn export_selection_as_pdf_bytes(
+        &self,
+        selection_export_prefs_override: Option<SelectionExportPrefs>,
+    ) -> oneshot::Receiver<Result<Option<Vec<u8>>, anyhow::Error>> {
+        let (oneshot_sender, oneshot_receiver) =
+            oneshot::channel::<anyhow::Result<Option<Vec<u8>>>>();
+        let selection_export_prefs =
+            selection_export_prefs_override.unwrap_or(self.export_prefs.selection_export_prefs);
+        let content = self.extract_selection_content();
+        let dpi = self.document.format.dpi();
+
+        rayon::spawn(move || {
+            let result = || -> Result<Option<Vec<u8>>, anyhow::Error> {
+                let Some(content) = content else {
+                    return Ok(None);
+                };
+                let Some(svg) = content.gen_svg(
+                    selection_export_prefs.with_background,
+                    selection_export_prefs.with_pattern,
+                    selection_export_prefs.optimize_printing,
+                    selection_export_prefs.margin,
+                )?
+                else {
+                    return Ok(None);
+                };
+                let size_mm = selection_export_prefs
+                    .physical_size
+                    .size_mm(svg.bounds.extents(), dpi);
+
+                Ok(Some(selection_pdf_bytes(&svg, size_mm)?))
+            };
+            if oneshot_sender.send(result()).is_err() {
+                error!("Sending result to receiver failed while exporting selection as Pdf bytes. Receiver already dropped.");
+            }
+        });
+
+        oneshot_receiver
+    }
+}
+
+/// The Svg file of an exported selection.
+///
+/// With the physical size in mm the dimensions of the root element are in mm, otherwise in pixels.
+fn selection_svg_bytes(svg: &Svg, size_mm: Option<na::Vector2<f64>>) -> Vec<u8> {
+    let svg_data = match size_mm {
+        Some(size_mm) => rnote_compose::utils::wrap_svg_root_w_size(
+            svg.svg_data.as_str(),
+            format!("{:.3}mm", size_mm[0]).as_str(),
+            format!("{:.3}mm", size_mm[1]).as_str(),
+            svg.bounds,
+        ),
+        None => rnote_compose::utils::wrap_svg_root(
+            svg.svg_data.as_str(),
+            Some(svg.bounds),
+            Some(svg.bounds),
+            false,
+        ),
+    };
+    rnote_compose::utils::add_xml_header(svg_data.as_str()).into_bytes()
+}
+
+/// The Pdf file of an exported selection, with a single page that has the size of the Svg.
+///
+/// With the physical size in mm the page is scaled to it, otherwise a pixel is mapped to a point
+/// like when exporting the document.
+fn selection_pdf_bytes(svg: &Svg, size_mm: Option<na::Vector2<f64>>) -> anyhow::Result<Vec<u8>> {
+    /// Pdf points per inch.
+    const PDF_POINTS_PER_INCH: f64 = 72.0;
+    let extents = svg.bounds.extents();
+    let page_size = match size_mm {
+        Some(size_mm) => size_mm * (PDF_POINTS_PER_INCH / MeasureUnit::AMOUNT_MM_IN_INCH),
+        None => extents,
+    };
+
+    let target_surface =
+        cairo::PdfSurface::for_stream(page_size[0], page_size[1], Vec::<u8>::new())
+            .context("Creating Pdf target surface failed.")?;
+    // New scope to avoid errors when flushing
+    {
+        let cairo_cx = cairo::Context::new(&target_surface)
+            .context("Creating new cairo context for pdf target surface failed.")?;
+        cairo_cx.scale(page_size[0] / extents[0], page_size[1] / extents[1]);
+        cairo_cx.translate(-svg.bounds.mins[0], -svg.bounds.mins[1]);
+        svg.draw_to_cairo(&cairo_cx)?;
+        cairo_cx.show_page().map_err(|e| {
+            anyhow::anyhow!("Showing page failed while exporting selection as pdf, Err: {e:?}")
+        })?;
+    }
+    let data = *target_surface
+        .finish_output_stream()
+        .map_err(|e| anyhow::anyhow!("Finishing outputstream failed, Err: {e:?}"))?
+        .downcast::<Vec<u8>>()
+        .map_err(|e| anyhow::anyhow!("Downcasting finished output stream failed, Err: {e:?}"))?;
+
+    Ok(data)
 }
@@ -1,6 +1,10 @@
 // Imports
-use super::{EngineConfig, EngineViewMut, StrokeContent};
+use super::{
+    ClipboardStrokes, EngineConfig, EngineSnapshot, EngineViewMut, PastePlacement, StrokeContent,
+};
 use crate::document::Layout;
+use crate::fileformats::rnoteformat::RnoteFile;
+use crate::fileformats::FileFormatLoader;
 use crate::pens::Pen;
 use crate::pens::PenStyle;
 use crate::store::chrono_comp::StrokeLayer;
@@ -8,7 +12,9 @@ use crate::store::StrokeKey;
 use crate::strokes::{resize::calculate_resize_ratio, resize::ImageSizeOption, Resize};
 use crate::strokes::{BitmapImage, Stroke, VectorImage};
 use crate::{CloneConfig, Engine, WidgetFlags};
+use anyhow::Context;
 use futures::channel::oneshot;
+use p2d::bounding_volume::BoundingVolume;
 use rnote_compose::ext::Vector2Ext;
 use rnote_compose::shapes::Shapeable;
 use serde::{Deserialize, Serialize};
@@ -148,6 +154,19 @@ impl CloneConfig for ImportPrefs {
 }
 
 impl Engine {
+    /// Extract the png encoded thumbnail from the bytes of a .rnote file, without loading its strokes.
+    ///
+    /// None if the file has no thumbnail or couldn't be read.
+    pub fn document_thumbnail(rnote_bytes: &[u8]) -> Option<Vec<u8>> {
+        match RnoteFile::load_thumbnail_from_bytes(rnote_bytes) {
+            Ok(thumbnail) => thumbnail,
+            Err(e) => {
+                error!("Extracting the thumbnail from rnote bytes failed, Err: {e:?}");
+                None
+            }
+        }
+    }
+
     /// Loads the engine config
     pub fn load_engine_config(
         &mut self,
@@ -156,7 +175,12 @@ impl Engine {
     ) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
+        // The config does not contain the background image and the Pdf underlay, they are kept with the strokes
+        let background_image = self.document.background.image.take();
+        let pdf_underlay = self.document.pdf_underlay.take();
         self.document = engine_config.document;
+        self.document.background.image = background_image;
+        self.document.pdf_underlay = pdf_underlay;
         self.pens_config = engine_config.pens_config;
         self.penholder = engine_config.penholder;
         self.import_prefs = engine_config.import_prefs;
@@ -384,7 +408,7 @@ impl Engine {
         // we need to always deselect all strokes. Even tough changing the pen style deselects too, it does only when
         // the pen is actually different.
         let all_strokes = self.store.stroke_keys_as_rendered();
-        self.store.set_selected_keys(&all_strokes, false);
+        self.store.set_selected_keys_keep_order(&all_strokes, false);
 
         if select {
             widget_flags |= self.change_pen_style(PenStyle::Selector);
@@ -411,7 +435,7 @@ impl Engine {
             self.store.set_selected_keys(&inserted, true);
         }
         widget_flags |= self.current_pen_update_state();
-        widget_flags |= self.store.record(Instant::now());
+        widget_flags |= self.record(Instant::now());
         widget_flags.resize = true;
         widget_flags.store_modified = true;
         widget_flags.refresh_ui = true;
@@ -425,7 +449,7 @@ impl Engine {
 
         // we need to always deselect all strokes. Even tough changing the pen style deselects too, but only when the pen is actually changed.
         let all_strokes = self.store.stroke_keys_as_rendered();
-        self.store.set_selected_keys(&all_strokes, false);
+        self.store.set_selected_keys_keep_order(&all_strokes, false);
 
         widget_flags |= self.change_pen_style(PenStyle::Typewriter);
 
@@ -444,7 +468,7 @@ impl Engine {
             );
         }
 
-        widget_flags |= self.store.record(Instant::now());
+        widget_flags = self.update_after_rejected(widget_flags) | self.record(Instant::now());
         widget_flags.redraw = true;
         widget_flags
     }
@@ -463,7 +487,7 @@ impl Engine {
         // we need to always deselect all strokes
         // even though changing the pen style deselects too, but only when the pen is actually different.
         let all_strokes = self.store.stroke_keys_as_rendered();
-        self.store.set_selected_keys(&all_strokes, false);
+        self.store.set_selected_keys_keep_order(&all_strokes, false);
         widget_flags |= self.change_pen_style(PenStyle::Selector);
 
         // calculate ratio
@@ -493,9 +517,125 @@ impl Engine {
             audioplayer: &mut self.audioplayer,
         });
 
-        widget_flags |= self.store.record(Instant::now());
+        widget_flags |= self.record(Instant::now());
         widget_flags.redraw = true;
 
         widget_flags
     }
+
+    /// Insert strokes from the rnote clipboard format, see [ClipboardStrokes].
+    ///
+    /// The strokes are placed according to the placement and are selected. Repeated pastes of the same payload
+    /// are cascaded, see [PastePlacement]. The camera is moved to the pasted strokes when they are outside of the viewport.
+    /// Returns an error if the payload could not be deserialized, e.g. when it was created by an incompatible version.
+    pub fn insert_clipboard_strokes(
+        &mut self,
+        bytes: &[u8],
+        placement: PastePlacement,
+    ) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let clipboard_strokes = ClipboardStrokes::from_bytes(bytes)?;
+        let Some(bounds) = clipboard_strokes.bounds() else {
+            return Ok(widget_flags);
+        };
+        let pos = self
+            .paste_cascade
+            .next_pos(bytes, placement, bounds.mins.coords);
+
+        // we need to always deselect all strokes
+        // even though changing the pen style deselects too, but only when the pen is actually different.
+        let all_strokes = self.store.stroke_keys_as_rendered();
+        self.store.set_selected_keys_keep_order(&all_strokes, false);
+        widget_flags |= self.change_pen_style(PenStyle::Selector);
+
+        // the strokes are inserted with new keys and are selected
+        let inserted_keys = self.store.insert_stroke_content(
+            StrokeContent::default().with_strokes(clipboard_strokes.strokes),
+            1.0,
+            pos,
+        );
+        self.store.update_geometry_for_strokes(&inserted_keys);
+        if let Some(pasted_bounds) = self.store.bounds_for_strokes(&inserted_keys) {
+            if !self.camera.viewport().contains(&pasted_bounds) {
+                widget_flags |= self
+                    .camera
+                    .set_viewport_center(pasted_bounds.center().coords);
+            }
+        }
+        self.store.regenerate_rendering_in_viewport_threaded(
+            self.tasks_tx.clone(),
+            false,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+
+        widget_flags |= self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now());
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.selection_changed = true;
+        widget_flags.redraw = true;
+
+        Ok(widget_flags)
+    }
+
+    /// Import the strokes of another .rnote file.
+    ///
+    /// The document config and the background of the file are ignored. The strokes keep their layers and order,
+    /// are inserted with new keys and are selected. They are offset so that the top-left of their bounds lands
+    /// at the target position, or centered in the viewport when it is None.
+    /// Older file versions are upgraded the same way as when loading the file.
+    pub fn import_rnote_file_as_strokes(
+        &mut self,
+        bytes: &[u8],
+        target_pos: Option<na::Vector2<f64>>,
+    ) -> anyhow::Result<(Vec<StrokeKey>, WidgetFlags)> {
+        let mut widget_flags = WidgetFlags::default();
+        let rnote_file =
+            RnoteFile::load_from_bytes(bytes).context("loading RnoteFile from bytes failed.")?;
+        let snapshot = ijson::from_value::<EngineSnapshot>(&rnote_file.engine_snapshot)
+            .context("deserializing EngineSnapshot failed.")?;
+        let strokes = snapshot.strokes_sorted_chrono();
+        let Some(bounds) = strokes
+            .iter()
+            .map(|(stroke, _)| stroke.bounds())
+            .reduce(|acc, bounds| acc.merged(&bounds))
+        else {
+            return Ok((vec![], widget_flags));
+        };
+        let pos = target_pos
+            .unwrap_or_else(|| self.camera.viewport().center().coords - bounds.half_extents());
+
+        // we need to always deselect all strokes
+        // even though changing the pen style deselects too, but only when the pen is actually different.
+        let all_strokes = self.store.stroke_keys_as_rendered();
+        self.store.set_selected_keys_keep_order(&all_strokes, false);
+        widget_flags |= self.change_pen_style(PenStyle::Selector);
+
+        let inserted_keys = strokes
+            .into_iter()
+            .map(|(stroke, layer)| self.store.insert_stroke(stroke, Some(layer)))
+            .collect::<Vec<StrokeKey>>();
+        self.store
+            .translate_strokes(&inserted_keys, pos - bounds.mins.coords);
+        self.store.update_geometry_for_strokes(&inserted_keys);
+        self.store.set_selected_keys(&inserted_keys, true);
+        self.store.regenerate_rendering_in_viewport_threaded(
+            self.tasks_tx.clone(),
+            false,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+
+        widget_flags |= self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now());
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.selection_changed = true;
+        widget_flags.redraw = true;
+
+        Ok((inserted_keys, widget_flags))
+    }
 }
@@ -0,0 +1,516 @@
+// Imports
+use super::{Engine, EngineSnapshot};
+use crate::document::DocumentMeta;
+use crate::fileformats::rnoteformat::journal::JournalRecord;
+use crate::store::StrokeKey;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+/// The journal state of the engine, tracking what was written since the last full save.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Journal {
+    /// None when the next save must be a full save.
+    state: Option<JournalState>,
+    /// The state of the full save that is currently being written.
+    pending: Option<JournalState>,
+}
+
+#[derive(Debug, Clone)]
+struct JournalState {
+    base_id: u64,
+    /// The snapshot of the state that was last written, either by the full save or by the latest record.
+    last_written: EngineSnapshot,
+    next_sequence: u32,
+    /// The number of strokes of the full save.
+    base_stroke_count: usize,
+    /// The number of entries of all records written since the full save.
+    journaled_entries: usize,
+}
+
+impl Journal {
+    /// The minimum number of journaled entries before a full save is required.
+    const COMPACTION_ENTRIES_MIN: usize = 100;
+    /// A full save is required when the journal holds more entries than this ratio of the strokes of the full save.
+    const COMPACTION_RATIO: f64 = 0.5;
+
+    /// Prepare a new journal for the full save of the snapshot, which is started once the save was written.
+    fn begin_full_save(&mut self, base_snapshot: EngineSnapshot) {
+        self.pending = Some(JournalState {
+            base_id: base_snapshot.journal_base_id,
+            base_stroke_count: base_snapshot.stroke_components.len(),
+            last_written: base_snapshot,
+            next_sequence: 0,
+            journaled_entries: 0,
+        });
+    }
+}
+
+/// The path of the journal sidecar file of the `.rnote` file at the given path.
+pub fn journal_file_path(rnote_file_path: impl AsRef<Path>) -> PathBuf {
+    let mut path = rnote_file_path.as_ref().as_os_str().to_owned();
+    path.push(".journal");
+    PathBuf::from(path)
+}
+
+/// The outcome of an incremental save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncrementalSave {
+    /// The changes were appended to the journal.
+    Appended,
+    /// Nothing was written, the document must be saved in full. The journal must be truncated afterwards.
+    ///
+    /// Either the document was not yet saved in full since it was loaded, or the journal grew too large.
+    FullSaveRequired,
+}
+
+impl Engine {
+    /// Generate a new journal base id for a full save, and prepare a new journal for it.
+    ///
+    /// The journal of the previous full save stays in use until [Engine::journal_full_save_finished] is called.
+    pub(crate) fn journal_begin_full_save(&mut self, snapshot: &mut EngineSnapshot) {
+        snapshot.journal_base_id = rand::random::<u64>().max(1);
+        self.journal.begin_full_save(snapshot.clone());
+    }
+
+    /// Start the journal of the last full save, must be called once it was written and the journal file was truncated.
+    ///
+    /// Does nothing when no full save was started since the last call.
+    pub fn journal_full_save_finished(&mut self) {
+        if let Some(pending) = self.journal.pending.take() {
+            self.journal.state = Some(pending);
+        }
+    }
+
+    /// Discard the journal, so that the next save must be a full save.
+    ///
+    /// Must be called when writing the bytes of an incremental save to the journal file failed.
+    pub fn journal_invalidate(&mut self) {
+        self.journal = Journal::default();
+    }
+
+    /// Append the changes since the last save to the journal of the last full save.
+    ///
+    /// The journal is the sidecar file of the saved `.rnote` file, which is truncated on every full save.
+    /// When [IncrementalSave::FullSaveRequired] is returned, a full save must be done instead.
+    pub fn save_incremental(
+        &mut self,
+        journal: &mut impl Write,
+    ) -> anyhow::Result<IncrementalSave> {
+        if self.journal.state.is_none() {
+            return Ok(IncrementalSave::FullSaveRequired);
+        }
+        self.document_meta.touch(DocumentMeta::now_timestamp());
+        let mut current = self.take_snapshot();
+        let Some(state) = self.journal.state.as_mut() else {
+            return Ok(IncrementalSave::FullSaveRequired);
+        };
+        current.journal_base_id = state.base_id;
+        let record = JournalRecord::from_diff(
+            state.base_id,
+            state.next_sequence,
+            &state.last_written,
+            &current,
+        );
+
+        let journaled_entries = state.journaled_entries + record.n_entries();
+        if journaled_entries as f64 > Self::journal_compaction_threshold(state.base_stroke_count) {
+            return Ok(IncrementalSave::FullSaveRequired);
+        }
+        let result = record
+            .to_bytes()
+            .and_then(|bytes| journal.write_all(&bytes).map_err(anyhow::Error::from));
+        if let Err(e) = result {
+            // The journal might be partially written, only a full save restores consistency
+            self.journal_invalidate();
+            return Err(e);
+        }
+        state.last_written = current;
+        state.next_sequence += 1;
+        state.journaled_entries = journaled_entries;
+        Ok(IncrementalSave::Appended)
+    }
+
+    fn journal_compaction_threshold(base_stroke_count: usize) -> f64 {
+        base_stroke_count.max(Journal::COMPACTION_ENTRIES_MIN) as f64 * Journal::COMPACTION_RATIO
+    }
+}
+
+impl JournalRecord {
+    /// The record of the changes from the last written snapshot to the current one.
+    fn from_diff(
+        base_id: u64,
+        sequence: u32,
+        last: &EngineSnapshot,
+        current: &EngineSnapshot,
+    ) -> Self {
+        let (upserted_strokes, removed_strokes) = diff_components(
+            last.stroke_components.iter(),
+            current.stroke_components.iter(),
+        );
+        // Only the components of the saved strokes, the other ones belong to trashed strokes
+        let (upserted_chrono_components, removed_chrono_components) = diff_components(
+            saved_components(last, last.chrono_components.iter()),
+            saved_components(current, current.chrono_components.iter()),
+        );
+        let (upserted_lock_components, removed_lock_components) = diff_components(
+            saved_components(last, last.lock_components.iter()),
+            saved_components(current, current.lock_components.iter()),
+        );
+        let background_image_changed = match (
+            &last.document.background.image,
+            &current.document.background.image,
+        ) {
+            (Some(last_image), Some(current_image)) => !Arc::ptr_eq(last_image, current_image),
+            (None, None) => false,
+            _ => true,
+        };
+        let pdf_underlay_changed =
+            match (&last.document.pdf_underlay, &current.document.pdf_underlay) {
+                (Some(last_underlay), Some(current_underlay)) => {
+                    !Arc::ptr_eq(last_underlay, current_underlay)
+                }
+                (None, None) => false,
+                _ => true,
+            };
+        let mut document = current.document.clone_config();
+        if !background_image_changed {
+            document.background.image = None;
+        }
+        if !pdf_underlay_changed {
+            document.pdf_underlay = None;
+        }
+
+        Self {
+            base_id,
+            sequence,
+            upserted_strokes,
+            removed_strokes,
+            upserted_chrono_components,
+            removed_chrono_components,
+            upserted_lock_components,
+            removed_lock_components,
+            chrono_counter: current.chrono_counter,
+            document,
+            background_image_changed,
+            pdf_underlay_changed,
+            camera: current.camera.clone_config(),
+            document_meta: current.document_meta.clone(),
+        }
+    }
+}
+
+/// The components that were added or replaced, and the keys of the removed ones.
+///
+/// Unchanged components share their allocation, so they are compared by pointer.
+fn diff_components<'a, T: 'a>(
+    last: impl IntoIterator<Item = (StrokeKey, &'a Arc<T>)>,
+    current: impl IntoIterator<Item = (StrokeKey, &'a Arc<T>)>,
+) -> (Vec<(StrokeKey, Arc<T>)>, Vec<StrokeKey>) {
+    let mut last = last.into_iter().collect::<HashMap<StrokeKey, &Arc<T>>>();
+    let upserted = current
+        .into_iter()
+        .filter(|(key, component)| {
+            !last
+                .remove(key)
+                .is_some_and(|last_component| Arc::ptr_eq(last_component, component))
+        })
+        .map(|(key, component)| (key, Arc::clone(component)))
+        .collect();
+    let mut removed = last.into_keys().collect::<Vec<StrokeKey>>();
+    removed.sort_unstable();
+    (upserted, removed)
+}
+
+fn saved_components<'a, T: 'a>(
+    snapshot: &'a EngineSnapshot,
+    components: impl Iterator<Item = (StrokeKey, &'a Arc<T>)> + 'a,
+) -> impl Iterator<Item = (StrokeKey, &'a Arc<T>)> + 'a {
+    components.filter(|(key, _)| snapshot.stroke_components.contains_key(*key))
+}
+
+impl EngineSnapshot {
+    /// Replay the records of the journal bytes over the snapshot of the base file.
+    ///
+    /// Replaying stops at the first record that is inconsistent with the previous ones,
+    /// so that the snapshot always reflects the state of an incremental save.
+    pub(crate) fn replay_journal(&mut self, journal_bytes: &[u8]) {
+        if self.journal_base_id == 0 {
+            warn!("Not replaying journal, the base file was not saved with journal support.");
+            return;
+        }
+        let records = match JournalRecord::load_all_from_bytes(journal_bytes) {
+            Ok((records, tail_error)) => {
+                if let Some(e) = tail_error {
+                    warn!("Journal contains an invalid record, replaying only the records before it, Err: {e:?}");
+                }
+                records
+            }
+            Err(e) => {
+                warn!("Loading journal failed, loading only the base file, Err: {e:?}");
+                return;
+            }
+        };
+
+        let base_keys = self
+            .stroke_components
+            .keys()
+            .collect::<HashSet<StrokeKey>>();
+        // The keys of the strokes that were added after the base file was saved can't be preserved
+        let mut mapped_keys = HashMap::<StrokeKey, StrokeKey>::new();
+        for (sequence, record) in records.into_iter().enumerate() {
+            if record.base_id != self.journal_base_id {
+                warn!(
+                    "Journal record {sequence} belongs to a different base file, stopping replay."
+                );
+                break;
+            }
+            if record.sequence as usize != sequence {
+                warn!(
+                    "Journal record {sequence} has the unexpected sequence {}, stopping replay.",
+                    record.sequence
+                );
+                break;
+            }
+            self.apply_journal_record(record, &base_keys, &mut mapped_keys);
+        }
+    }
+
+    fn apply_journal_record(
+        &mut self,
+        record: JournalRecord,
+        base_keys: &HashSet<StrokeKey>,
+        mapped_keys: &mut HashMap<StrokeKey, StrokeKey>,
+    ) {
+        let resolve = |mapped_keys: &HashMap<StrokeKey, StrokeKey>, key: StrokeKey| {
+            mapped_keys
+                .get(&key)
+                .copied()
+                .or_else(|| base_keys.contains(&key).then_some(key))
+        };
+
+        let stroke_components = Arc::make_mut(&mut self.stroke_components);
+        for key in record.removed_strokes {
+            if let Some(key) = resolve(mapped_keys, key) {
+                stroke_components.remove(key);
+            }
+        }
+        for (key, stroke) in record.upserted_strokes {
+            match resolve(mapped_keys, key).and_then(|key| stroke_components.get_mut(key)) {
+                Some(existing) => *existing = stroke,
+                None => {
+                    // New strokes, and removed strokes that were restored
+                    let new_key = stroke_components.insert(stroke);
+                    mapped_keys.insert(key, new_key);
+                }
+            }
+        }
+
+        let chrono_components = Arc::make_mut(&mut self.chrono_components);
+        for key in record.removed_chrono_components {
+            if let Some(key) = resolve(mapped_keys, key) {
+                chrono_components.remove(key);
+            }
+        }
+        for (key, chrono_comp) in record.upserted_chrono_components {
+            if let Some(key) = resolve(mapped_keys, key) {
+                chrono_components.insert(key, chrono_comp);
+            }
+        }
+        let lock_components = Arc::make_mut(&mut self.lock_components);
+        for key in record.removed_lock_components {
+            if let Some(key) = resolve(mapped_keys, key) {
+                lock_components.remove(key);
+            }
+        }
+        for (key, lock_comp) in record.upserted_lock_components {
+            if let Some(key) = resolve(mapped_keys, key) {
+                lock_components.insert(key, lock_comp);
+            }
+        }
+
+        let mut document = record.document;
+        if !record.background_image_changed {
+            document.background.image = self.document.background.image.take();
+        }
+        if !record.pdf_underlay_changed {
+            document.pdf_underlay = self.document.pdf_underlay.take();
+        }
+        self.document = document;
+        self.camera = record.camera;
+        self.document_meta = record.document_meta;
+        self.chrono_counter = record.chrono_counter;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::insert_rect;
+    use p2d::bounding_volume::Aabb;
+    use std::time::Instant;
+
+    fn square_at(x: f64) -> Aabb {
+        Aabb::new(na::point![x, 0.0], na::point![x + 50.0, 50.0])
+    }
+
+    fn save_full(engine: &mut Engine) -> Vec<u8> {
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+        engine.journal_full_save_finished();
+        bytes
+    }
+
+    fn load(bytes: Vec<u8>, journal: Option<Vec<u8>>) -> EngineSnapshot {
+        match journal {
+            Some(journal) => futures::executor::block_on(
+                EngineSnapshot::load_from_rnote_bytes_w_journal(bytes, journal),
+            ),
+            None => futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)),
+        }
+        .unwrap()
+    }
+
+    /// The state that is expected to be equal between the loaded snapshots, as json.
+    fn content(snapshot: &EngineSnapshot) -> serde_json::Value {
+        serde_json::json!({
+            "strokes": snapshot.strokes_sorted_chrono(),
+            "document": snapshot.document,
+            "chrono_counter": snapshot.chrono_counter,
+        })
+    }
+
+    #[test]
+    fn incremental_save_requires_full_save_first() {
+        let mut engine = Engine::default();
+        let mut journal = vec![];
+        assert_eq!(
+            engine.save_incremental(&mut journal).unwrap(),
+            IncrementalSave::FullSaveRequired
+        );
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn incremental_save_roundtrip() {
+        let mut engine = Engine::default();
+        let first = insert_rect(&mut engine.store, square_at(0.0));
+        let second = insert_rect(&mut engine.store, square_at(100.0));
+        engine.store.record(Instant::now());
+        let base = save_full(&mut engine);
+
+        let mut journal = vec![];
+        engine
+            .store
+            .translate_strokes(&[first], na::vector![10.0, 10.0]);
+        let added = insert_rect(&mut engine.store, square_at(200.0));
+        engine.store.record(Instant::now());
+        assert_eq!(
+            engine.save_incremental(&mut journal).unwrap(),
+            IncrementalSave::Appended
+        );
+        let first_record_len = journal.len();
+
+        engine.store.set_trashed(second, true);
+        engine
+            .store
+            .translate_strokes(&[added], na::vector![0.0, 100.0]);
+        insert_rect(&mut engine.store, square_at(300.0));
+        engine.store.record(Instant::now());
+        assert_eq!(
+            engine.save_incremental(&mut journal).unwrap(),
+            IncrementalSave::Appended
+        );
+        let after_first_record = load(base.clone(), Some(journal[..first_record_len].to_vec()));
+
+        let full = save_full(&mut engine);
+        let replayed = load(base.clone(), Some(journal.clone()));
+        assert_eq!(content(&replayed), content(&load(full, None)));
+        assert_eq!(replayed.stroke_components.len(), 3);
+
+        // a partially written record is ignored
+        let truncated = load(base.clone(), Some(journal[..journal.len() - 1].to_vec()));
+        assert_eq!(content(&truncated), content(&after_first_record));
+        assert_eq!(truncated.stroke_components.len(), 3);
+    }
+
+    #[test]
+    fn corrupted_journal_falls_back_to_base() {
+        let mut engine = Engine::default();
+        insert_rect(&mut engine.store, square_at(0.0));
+        engine.store.record(Instant::now());
+        let base = save_full(&mut engine);
+
+        let mut journal = vec![];
+        insert_rect(&mut engine.store, square_at(100.0));
+        engine.store.record(Instant::now());
+        engine.save_incremental(&mut journal).unwrap();
+
+        let mut corrupted = journal.clone();
+        corrupted[0] ^= 0xff;
+        assert_eq!(
+            content(&load(base.clone(), Some(corrupted))),
+            content(&load(base.clone(), None))
+        );
+
+        // a journal of another full save is not replayed
+        let other_base = save_full(&mut Engine::default());
+        assert_eq!(load(other_base, Some(journal)).stroke_components.len(), 0);
+    }
+
+    #[test]
+    fn unfinished_full_save_keeps_journal() {
+        let mut engine = Engine::default();
+        insert_rect(&mut engine.store, square_at(0.0));
+        engine.store.record(Instant::now());
+        let base = save_full(&mut engine);
+
+        // a full save that was never written
+        let _ = futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")));
+        let mut journal = vec![];
+        insert_rect(&mut engine.store, square_at(100.0));
+        engine.store.record(Instant::now());
+        assert_eq!(
+            engine.save_incremental(&mut journal).unwrap(),
+            IncrementalSave::Appended
+        );
+        assert_eq!(load(base, Some(journal)).stroke_components.len(), 2);
+    }
+
+    #[test]
+    fn incremental_save_requires_compaction() {
+        let mut engine = Engine::default();
+        save_full(&mut engine);
+
+        let mut journal = vec![];
+        for i in 0..Journal::COMPACTION_ENTRIES_MIN {
+            insert_rect(&mut engine.store, square_at(i as f64 * 100.0));
+        }
+        engine.store.record(Instant::now());
+        assert_eq!(
+            engine.save_incremental(&mut journal).unwrap(),
+            IncrementalSave::FullSaveRequired
+        );
+        assert!(journal.is_empty());
+
+        save_full(&mut engine);
+        insert_rect(&mut engine.store, square_at(-100.0));
+        engine.store.record(Instant::now());
+        assert_eq!(
+            engine.save_incremental(&mut journal).unwrap(),
+            IncrementalSave::Appended
+        );
+    }
+
+    #[test]
+    fn journal_file_path_appends_extension() {
+        assert_eq!(
+            journal_file_path("dir/test.rnote"),
+            PathBuf::from("dir/test.rnote.journal")
+        );
+    }
+}
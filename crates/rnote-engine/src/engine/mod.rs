@@ -1,27 +1,47 @@
 // Modules
+pub mod clipboardstrokes;
+pub mod eventrecorder;
 pub mod export;
 pub mod import;
+pub mod journal;
+pub mod operation;
+pub mod pdfunderlay;
+pub mod rasterize;
+pub mod recovery;
 pub mod rendering;
+pub mod search;
 pub mod snapshot;
+pub mod statistics;
 pub mod strokecontent;
 pub mod visual_debug;
 
 // Re-exports
+pub use clipboardstrokes::{ClipboardStrokes, PastePlacement};
+pub use eventrecorder::EventLog;
 pub use export::ExportPrefs;
 use futures::channel::mpsc::UnboundedReceiver;
 use futures::StreamExt;
 pub use import::ImportPrefs;
+pub use journal::IncrementalSave;
+pub use operation::{OperationEvent, OperationId};
+pub use search::TextMatch;
 pub use snapshot::EngineSnapshot;
+pub use statistics::DocumentStatistics;
 pub use strokecontent::StrokeContent;
 
 // Imports
-use crate::document::Layout;
-use crate::pens::{Pen, PenStyle};
-use crate::pens::{PenMode, PensConfig};
-use crate::store::render_comp::{self, RenderCompState};
-use crate::store::StrokeKey;
+use crate::document::background::{BackgroundImage, BackgroundImageOptions};
+use crate::document::{Bookmark, DocumentMeta, Layout, PdfUnderlay};
+use crate::pens::{CursorStyle, Pen, PenStyle, Selector};
+use crate::pens::{PenMode, PenSwitchContext, PensConfig};
+use crate::store::render_comp::{self, RenderCompState, RenderMode};
+use crate::store::{
+    ChronoComponent, ColorReplaceScope, SelectionStyleSummary, SelectionSummary, StateMarker,
+    StrokeKey, StyleUpdate, TrashedStrokeInfo,
+};
 use crate::strokes::content::GeneratedContentImages;
 use crate::strokes::textstroke::{TextAttribute, TextStyle};
+use crate::strokes::{Stroke, TextStroke};
 use crate::{render, AudioPlayer, CloneConfig, SelectionCollision, WidgetFlags};
 use crate::{Camera, Document, PenHolder, StrokeStore};
 use futures::channel::{mpsc, oneshot};
@@ -29,12 +49,14 @@ use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::eventresult::EventPropagation;
 use rnote_compose::ext::AabbExt;
 use rnote_compose::penevent::{PenEvent, ShortcutKey};
+use rnote_compose::style::smooth::SmoothOptions;
 use rnote_compose::{Color, SplitOrder};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Instant;
-use tracing::error;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
 
 /// An immutable view into the engine, excluding the penholder.
 #[derive(Debug)]
@@ -97,10 +119,47 @@ pub enum EngineTask {
         /// The generated images
         images: GeneratedContentImages,
     },
+    /// Replace the rendering of a page of the Pdf underlay.
+    UpdatePdfUnderlayPage {
+        /// The underlay the page was rendered from.
+        pdf_underlay: Arc<PdfUnderlay>,
+        /// The index of the page placement.
+        page: usize,
+        /// The image scale-factor the render task was using.
+        image_scale: f64,
+        /// The rendered page, None when rendering failed.
+        image: Option<render::Image>,
+    },
     /// Requests that the typewriter cursor should be blinked/toggled
     BlinkTypewriterCursor,
+    /// Requests that the selector nudges the camera again while the pen is held still close to the viewport edges
+    AutoNudge,
     /// Change the permanent zoom to the given value
     Zoom(f64),
+    /// Requests that the highlight of the search match is removed
+    ClearSearchHighlight,
+    /// Requests that the path of the laser pointer fades out further
+    LaserFade,
+    /// Requests that a recovery snapshot of the document is written, if it was modified since the last one
+    RecoverySnapshot,
+    /// Indicates that the last task of a batch of coalesced rendering tasks has finished,
+    /// so that the pending coalesced rendering request can be submitted.
+    CoalescedRenderingFinished,
+    /// Reports the progress of a long-running operation, see [EngineTaskSender::start_operation].
+    OperationProgress {
+        operation_id: OperationId,
+        /// The number of steps done.
+        done: usize,
+        /// The total number of steps.
+        total: usize,
+    },
+    /// Indicates that a long-running operation has finished.
+    OperationFinished { operation_id: OperationId },
+    /// Indicates that a long-running operation has failed.
+    OperationFailed {
+        operation_id: OperationId,
+        error: String,
+    },
     /// Indicates that the application is quitting. Sent to quit the handler which receives the tasks.
     Quit,
 }
@@ -126,11 +185,14 @@ pub struct EngineConfig {
 }
 
 #[derive(Debug, Clone)]
-pub struct EngineTaskSender(mpsc::UnboundedSender<EngineTask>);
+pub struct EngineTaskSender {
+    tx: mpsc::UnboundedSender<EngineTask>,
+    operations: operation::Operations,
+}
 
 impl EngineTaskSender {
     pub fn send(&self, task: EngineTask) {
-        if let Err(e) = self.0.unbounded_send(task) {
+        if let Err(e) = self.tx.unbounded_send(task) {
             let err = format!("{e:?}");
             error!(
                 "Failed to send engine task {:?}, Err: {err}",
@@ -171,22 +233,54 @@ pub struct Engine {
     pen_sounds: bool,
     #[serde(rename = "optimize_epd")]
     optimize_epd: bool,
+    #[serde(rename = "document_meta")]
+    document_meta: DocumentMeta,
 
     #[serde(skip)]
     audioplayer: Option<AudioPlayer>,
     #[serde(skip)]
     visual_debug: bool,
+    #[serde(skip)]
+    visual_debug_damage: bool,
+    #[serde(skip)]
+    render_mode: RenderMode,
+    /// Whether the strokes are drawn as outlines while the camera is dragged.
+    #[serde(skip)]
+    outlines_during_camera_drag: bool,
+    #[serde(skip)]
+    camera_drag_in_progress: bool,
+    /// The recent damage regions, drawn when the damage is visually debugged.
+    #[serde(skip)]
+    damage_history: VecDeque<Aabb>,
     // the task sender. Must not be modified, only cloned.
     #[serde(skip)]
     tasks_tx: EngineTaskSender,
     #[serde(skip)]
     tasks_rx: Option<EngineTaskReceiver>,
+    #[serde(skip)]
+    operation_subscribers: operation::OperationSubscribers,
+    #[serde(skip)]
+    recovery: recovery::Recovery,
+    #[serde(skip)]
+    journal: journal::Journal,
+    #[serde(skip)]
+    paste_cascade: clipboardstrokes::PasteCascade,
+    #[serde(skip)]
+    search_highlight: search::SearchHighlight,
+    #[serde(skip)]
+    event_recorder: Option<eventrecorder::EventRecorder>,
     // Background rendering
     #[serde(skip)]
     background_tile_image: Option<render::Image>,
     #[cfg(feature = "ui")]
     #[serde(skip)]
     background_rendernodes: Vec<gtk4::gsk::RenderNode>,
+    /// The texture of the background image, only recreated when the image changes.
+    #[cfg(feature = "ui")]
+    #[serde(skip)]
+    background_image_texture: Option<(Arc<BackgroundImage>, gtk4::gdk::MemoryTexture)>,
+    #[serde(skip)]
+    pdf_underlay_cache: pdfunderlay::PdfUnderlayCache,
     // Origin indicator rendering
     #[serde(skip)]
     origin_indicator_image: Option<render::Image>,
@@ -209,14 +303,32 @@ impl Default for Engine {
             export_prefs: ExportPrefs::default(),
             pen_sounds: false,
             optimize_epd: false,
+            document_meta: DocumentMeta::default(),
 
             audioplayer: None,
             visual_debug: false,
-            tasks_tx: EngineTaskSender(tasks_tx),
+            visual_debug_damage: false,
+            render_mode: RenderMode::default(),
+            outlines_during_camera_drag: false,
+            camera_drag_in_progress: false,
+            damage_history: VecDeque::default(),
+            tasks_tx: EngineTaskSender {
+                tx: tasks_tx,
+                operations: operation::Operations::default(),
+            },
             tasks_rx: Some(EngineTaskReceiver(tasks_rx)),
+            operation_subscribers: operation::OperationSubscribers::default(),
+            recovery: recovery::Recovery::default(),
+            journal: journal::Journal::default(),
+            paste_cascade: clipboardstrokes::PasteCascade::default(),
+            search_highlight: search::SearchHighlight::default(),
+            event_recorder: None,
             background_tile_image: None,
             #[cfg(feature = "ui")]
             background_rendernodes: Vec::default(),
+            #[cfg(feature = "ui")]
+            background_image_texture: None,
+            pdf_underlay_cache: pdfunderlay::PdfUnderlayCache::default(),
             origin_indicator_image: None,
             #[cfg(feature = "ui")]
             origin_indicator_rendernode: None,
@@ -226,6 +338,11 @@ impl Default for Engine {
 
 impl Engine {
     pub(crate) const STROKE_BOUNDS_INTERSECTION_TOLERANCE: f64 = 1e-3;
+    /// The padding around the content when zooming to fit it, in surface coordinates.
+    pub const ZOOM_TO_FIT_PADDING: f64 = 32.0;
+    /// The tolerance within which the selection is considered to already have the requested bounds,
+    /// in document coordinates.
+    pub(crate) const SELECTION_BOUNDS_TOLERANCE: f64 = 1e-3;
 
     pub fn engine_tasks_tx(&self) -> EngineTaskSender {
         self.tasks_tx.clone()
@@ -235,6 +352,27 @@ impl Engine {
         self.tasks_rx.take()
     }
 
+    /// Subscribe to the events of long-running operations, e.g. to display their progress.
+    ///
+    /// The events are published while handling the engine tasks with [Engine::handle_engine_task].
+    pub fn subscribe_operation_events(&mut self) -> UnboundedReceiver<OperationEvent> {
+        self.operation_subscribers.subscribe()
+    }
+
+    /// Cancel a running long-running operation.
+    ///
+    /// Strokes whose rendering was regenerated by the operation are flagged dirty again.
+    /// Returns false when the operation is not running (anymore).
+    pub fn cancel_operation(&mut self, operation_id: OperationId) -> bool {
+        if !self.tasks_tx.operations.cancel(operation_id) {
+            return false;
+        }
+        self.store.cancel_rendering_operation(operation_id);
+        self.operation_subscribers
+            .publish(OperationEvent::Cancelled { operation_id });
+        true
+    }
+
     #[allow(unused)]
     pub(crate) fn view(&self) -> EngineView {
         EngineView {
@@ -259,6 +397,140 @@ impl Engine {
         }
     }
 
+    /// The metadata of the document.
+    pub fn document_meta(&self) -> &DocumentMeta {
+        &self.document_meta
+    }
+
+    /// Set the title of the document.
+    pub fn set_document_title(&mut self, title: String) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if self.document_meta.title != title {
+            self.document_meta.title = title;
+            widget_flags.store_modified = true;
+        }
+        widget_flags
+    }
+
+    /// Set the author of the document.
+    pub fn set_document_author(&mut self, author: String) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if self.document_meta.author != author {
+            self.document_meta.author = author;
+            widget_flags.store_modified = true;
+        }
+        widget_flags
+    }
+
+    /// Set the tags of the document.
+    ///
+    /// Tags are trimmed, empty and duplicate tags are removed.
+    pub fn set_document_tags(&mut self, tags: Vec<String>) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let mut new_tags: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !new_tags.iter().any(|t| t == tag) {
+                new_tags.push(tag.to_string());
+            }
+        }
+        if self.document_meta.tags != new_tags {
+            self.document_meta.tags = new_tags;
+            widget_flags.store_modified = true;
+        }
+        widget_flags
+    }
+
+    /// The bookmarks of the document, in the order they were added.
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.document_meta.bookmarks
+    }
+
+    /// Bookmark the region of the current selection.
+    ///
+    /// The name is trimmed and must not be empty or already be used by another bookmark.
+    pub fn bookmark_selection(&mut self, name: String) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let name = self.bookmark_name_checked(name)?;
+        let bounds = self
+            .store
+            .bounds_for_strokes(&self.store.selection_keys_as_rendered())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Bookmarking the selection failed, no strokes are selected.")
+            })?;
+        self.document_meta
+            .bookmarks
+            .push(Bookmark::new(name, bounds));
+        widget_flags.store_modified = true;
+        Ok(widget_flags)
+    }
+
+    /// Rename the bookmark, keeping its position in the bookmarks.
+    ///
+    /// The new name is trimmed and must not be empty or already be used by another bookmark.
+    pub fn rename_bookmark(&mut self, name: &str, new_name: String) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        if self.document_meta.bookmark(name).is_none() {
+            return Err(anyhow::anyhow!(
+                "Renaming bookmark failed, no bookmark with name `{name}` exists."
+            ));
+        }
+        if new_name.trim() == name {
+            return Ok(widget_flags);
+        }
+        let new_name = self.bookmark_name_checked(new_name)?;
+        if let Some(bookmark) = self
+            .document_meta
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.name == name)
+        {
+            bookmark.name = new_name;
+            widget_flags.store_modified = true;
+        }
+        Ok(widget_flags)
+    }
+
+    /// Delete the bookmark.
+    pub fn delete_bookmark(&mut self, name: &str) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let n_bookmarks = self.document_meta.bookmarks.len();
+        self.document_meta
+            .bookmarks
+            .retain(|bookmark| bookmark.name != name);
+        if self.document_meta.bookmarks.len() != n_bookmarks {
+            widget_flags.store_modified = true;
+        } else {
+            warn!("Deleting bookmark failed, no bookmark with name `{name}` exists");
+        }
+        widget_flags
+    }
+
+    /// Zoom to fit the region of the bookmark into the viewport.
+    ///
+    /// The document is expanded to the viewport in layouts that are autoexpanding.
+    pub fn goto_bookmark(&mut self, name: &str) -> WidgetFlags {
+        let Some(bounds) = self.document_meta.bookmark(name).map(Bookmark::bounds) else {
+            warn!("Going to bookmark failed, no bookmark with name `{name}` exists");
+            return WidgetFlags::default();
+        };
+        self.zoom_to_bounds(bounds, Self::ZOOM_TO_FIT_PADDING)
+    }
+
+    /// The trimmed bookmark name, or an error when it is empty or already used.
+    fn bookmark_name_checked(&self, name: String) -> anyhow::Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Bookmark name is empty."));
+        }
+        if self.document_meta.bookmark(name).is_some() {
+            return Err(anyhow::anyhow!(
+                "A bookmark with name `{name}` already exists."
+            ));
+        }
+        Ok(name.to_string())
+    }
+
     /// Whether pen sounds are enabled.
     pub fn pen_sounds(&self) -> bool {
         self.pen_sounds
@@ -307,70 +579,188 @@ impl Engine {
         widget_flags
     }
 
-    /// Takes a snapshot of the current state.
-    pub fn take_snapshot(&self) -> EngineSnapshot {
-        let mut store_history_entry = self.store.create_history_entry();
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
 
-        // Remove all trashed strokes
-        let trashed_keys = store_history_entry
-            .trash_components
-            .iter()
-            .filter_map(|(key, trash_comp)| if trash_comp.trashed { Some(key) } else { None })
-            .collect::<Vec<StrokeKey>>();
+    /// Set how the strokes are drawn on the canvas. Exports always draw the strokes fully.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        self.render_mode = render_mode;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    pub fn outlines_during_camera_drag(&self) -> bool {
+        self.outlines_during_camera_drag
+    }
+
+    /// Set whether the strokes are drawn as outlines while the camera is dragged,
+    /// see [Engine::camera_drag_begin()].
+    pub fn set_outlines_during_camera_drag(
+        &mut self,
+        outlines_during_camera_drag: bool,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        self.outlines_during_camera_drag = outlines_during_camera_drag;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    /// The render mode the strokes are currently drawn with, switching to outlines during camera drags if enabled.
+    pub fn current_render_mode(&self) -> RenderMode {
+        if self.camera_drag_in_progress && self.outlines_during_camera_drag {
+            RenderMode::Outlines
+        } else {
+            self.render_mode
+        }
+    }
+
+    /// Called when the camera starts to be dragged, e.g. by touch or with the middle mouse button.
+    pub fn camera_drag_begin(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        self.camera_drag_in_progress = true;
+        widget_flags.redraw = self.outlines_during_camera_drag;
+        widget_flags
+    }
+
+    /// Called when the camera drag has finished. Updates the rendering for the new viewport.
+    pub fn camera_drag_end(&mut self) -> WidgetFlags {
+        let mut widget_flags = self.update_rendering_current_viewport();
+        self.camera_drag_in_progress = false;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    pub fn visual_debug_damage(&self) -> bool {
+        self.visual_debug_damage
+    }
+
+    /// Set whether the damage regions of the widget flags are visualized, see [Engine::record_damage].
+    pub fn set_visual_debug_damage(&mut self, visual_debug_damage: bool) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        self.visual_debug_damage = visual_debug_damage;
+        self.damage_history.clear();
+        widget_flags.redraw = true;
+        widget_flags
+    }
 
-        for key in trashed_keys {
-            Arc::make_mut(&mut store_history_entry.stroke_components).remove(key);
+    /// Record the damage region of the widget flags, to be drawn when the damage is visually debugged.
+    ///
+    /// Only the most recent regions are kept.
+    pub fn record_damage(&mut self, widget_flags: &WidgetFlags) {
+        const DAMAGE_HISTORY_LEN: usize = 8;
+
+        if !self.visual_debug_damage || !widget_flags.redraw {
+            return;
+        }
+        if let Some(damage) = widget_flags.damage {
+            if self.damage_history.len() >= DAMAGE_HISTORY_LEN {
+                self.damage_history.pop_front();
+            }
+            self.damage_history.push_back(damage);
         }
+    }
+
+    /// Whether the engine is in read-only mode, in which the strokes can't be modified.
+    pub fn read_only(&self) -> bool {
+        self.store.read_only()
+    }
 
-        EngineSnapshot {
-            document: self.document.clone_config(),
-            camera: self.camera.clone_config(),
-            stroke_components: Arc::clone(&store_history_entry.stroke_components),
-            chrono_components: Arc::clone(&store_history_entry.chrono_components),
-            chrono_counter: store_history_entry.chrono_counter,
+    /// Set the read-only mode, e.g. for presenting the document.
+    ///
+    /// Pens can then still select strokes and move the view, but operations that would modify the strokes,
+    /// including undo and redo, are rejected with [WidgetFlags::read_only_rejected] when they are recorded.
+    /// The mode is not saved in the document.
+    pub fn set_read_only(&mut self, read_only: bool) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if self.read_only() == read_only {
+            return widget_flags;
         }
+        // Finish the ongoing gesture first
+        widget_flags |= self.reinstall_pen_current_style();
+        self.penholder.set_read_only(read_only);
+        self.store.set_read_only(read_only);
+        widget_flags.hide_undo = Some(read_only || !self.can_undo());
+        widget_flags.hide_redo = Some(read_only || !self.can_redo());
+        widget_flags.refresh_ui = true;
+        widget_flags
+    }
+
+    /// Takes a snapshot of the current state.
+    pub fn take_snapshot(&self) -> EngineSnapshot {
+        EngineSnapshot::from_history_entry(
+            self.document.clone_config(),
+            self.camera.clone_config(),
+            self.document_meta.clone(),
+            &self.store.create_history_entry(),
+        )
     }
 
     /// Imports an engine snapshot. A save file should always be loaded with this method.
     pub fn load_snapshot(&mut self, snapshot: EngineSnapshot) -> WidgetFlags {
         self.document = snapshot.document.clone_config();
         self.camera = snapshot.camera.clone_config();
+        self.document_meta = snapshot.document_meta.clone();
+        // The strokes keys of the loaded snapshot are not those of a journal, so the next save must be a full save
+        self.journal = journal::Journal::default();
         let mut widget_flags = self.store.import_from_snapshot(&snapshot)
             | self.doc_resize_autoexpand()
             | self.current_pen_update_state()
             | self.background_rendering_regenerate()
             | self.update_content_rendering_current_viewport();
+        self.publish_document();
         widget_flags.refresh_ui = true;
         widget_flags.view_modified = true;
         widget_flags
     }
 
     /// Records the current store state and saves it as a history entry.
+    ///
+    /// In read-only mode the modifications are discarded instead, see [Engine::set_read_only].
     pub fn record(&mut self, now: Instant) -> WidgetFlags {
-        self.store.record(now)
+        let widget_flags = self.store.record(now);
+        self.update_after_rejected(widget_flags)
     }
 
     /// Update the state of the latest history entry with the current document state.
+    ///
+    /// In read-only mode the modifications are discarded instead, see [Engine::set_read_only].
     pub fn update_latest_history_entry(&mut self, now: Instant) -> WidgetFlags {
-        self.store.update_latest_history_entry(now)
+        let widget_flags = self.store.update_latest_history_entry(now);
+        self.update_after_rejected(widget_flags)
     }
 
     /// Undo the latest changes.
     pub fn undo(&mut self, now: Instant) -> WidgetFlags {
-        self.store.undo(now)
-            | self.doc_resize_autoexpand()
-            | self.current_pen_update_state()
-            | self.update_rendering_current_viewport()
+        self.store.undo(now) | self.update_after_history_change()
     }
 
     /// Redo the latest changes.
     pub fn redo(&mut self, now: Instant) -> WidgetFlags {
-        self.store.redo(now)
+        self.store.redo(now) | self.update_after_history_change()
+    }
+
+    /// Bring the document, the current pen and the rendering in line with the state imported from the history.
+    fn update_after_history_change(&mut self) -> WidgetFlags {
+        self.update_background_image_after_history_change()
+            | self.update_pdf_underlay_after_history_change()
             | self.doc_resize_autoexpand()
-            | self.current_pen_update_state()
+            | self.current_pen_update_state_after_history_change()
             | self.update_rendering_current_viewport()
     }
 
+    /// Bring the engine in line with the history again, when the widget flags report that modifications were
+    /// discarded because of the read-only mode.
+    ///
+    /// Needs to be called after pens recorded through the store directly.
+    fn update_after_rejected(&mut self, widget_flags: WidgetFlags) -> WidgetFlags {
+        if !widget_flags.read_only_rejected {
+            return widget_flags;
+        }
+        widget_flags | self.update_after_history_change()
+    }
+
     pub fn can_undo(&self) -> bool {
         self.store.can_undo()
     }
@@ -379,9 +769,47 @@ impl Engine {
         self.store.can_redo()
     }
 
+    /// The marker of the current state of the strokes.
+    ///
+    /// Should be retrieved when the document is saved and set with [Engine::set_saved_state_marker] once the save
+    /// succeeded.
+    pub fn state_marker(&self) -> StateMarker {
+        self.store.state_marker()
+    }
+
+    /// The marker of the state of the strokes that was last saved.
+    pub fn saved_state_marker(&self) -> StateMarker {
+        self.store.saved_state_marker()
+    }
+
+    /// Set the marker of the state that was saved successfully.
+    pub fn set_saved_state_marker(&mut self, state_marker: StateMarker) {
+        self.store.set_saved_state_marker(state_marker);
+    }
+
+    /// Whether the strokes differ from the saved state.
+    ///
+    /// Undoing back to the saved state reports no unsaved changes again.
+    /// Changes outside of the history, like to the document settings, are not considered.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.store.has_unsaved_changes()
+    }
+
+    /// The number of history steps between the current and the saved state.
+    pub fn changes_since_save(&self) -> usize {
+        self.store.changes_since_save()
+    }
+
     // Clears the entire engine.
     pub fn clear(&mut self) -> WidgetFlags {
-        self.store.clear() | self.current_pen_update_state() | self.return_to_origin(None)
+        self.journal = journal::Journal::default();
+        let mut widget_flags =
+            self.store.clear() | self.current_pen_update_state() | self.return_to_origin(None);
+        let pdf_underlay_removed = self.document.pdf_underlay.take().is_some();
+        if self.document.background.image.take().is_some() || pdf_underlay_removed {
+            widget_flags |= self.background_rendering_regenerate();
+        }
+        widget_flags
     }
 
     /// Handle a received task from tasks_rx.
@@ -441,6 +869,15 @@ impl Engine {
                     }
                 }
             }
+            EngineTask::UpdatePdfUnderlayPage {
+                pdf_underlay,
+                page,
+                image_scale,
+                image,
+            } => {
+                widget_flags |=
+                    self.handle_pdf_underlay_page_rendered(pdf_underlay, page, image_scale, image);
+            }
             EngineTask::AppendImagesToStroke { key, images } => {
                 self.store.append_rendering_images(key, images);
                 widget_flags.redraw = true;
@@ -451,6 +888,22 @@ impl Engine {
                     widget_flags.redraw = true;
                 }
             }
+            EngineTask::AutoNudge => {
+                let tasks_tx = self.engine_tasks_tx();
+                if let Pen::Selector(selector) = self.penholder.current_pen_mut() {
+                    widget_flags |= selector.handle_auto_nudge(
+                        Instant::now(),
+                        &mut EngineViewMut {
+                            tasks_tx,
+                            pens_config: &mut self.pens_config,
+                            document: &mut self.document,
+                            store: &mut self.store,
+                            camera: &mut self.camera,
+                            audioplayer: &mut self.audioplayer,
+                        },
+                    );
+                }
+            }
             EngineTask::Zoom(zoom) => {
                 widget_flags |= self.camera.zoom_temporarily_to(1.0) | self.camera.zoom_to(zoom);
 
@@ -460,6 +913,40 @@ impl Engine {
                     | self.background_rendering_regenerate()
                     | self.update_rendering_current_viewport();
             }
+            EngineTask::LaserFade => {
+                let tasks_tx = self.engine_tasks_tx();
+                if let Pen::Laser(laser) = self.penholder.current_pen_mut() {
+                    widget_flags |= laser.fade(
+                        Instant::now(),
+                        &EngineView {
+                            tasks_tx,
+                            pens_config: &self.pens_config,
+                            document: &self.document,
+                            store: &self.store,
+                            camera: &self.camera,
+                            audioplayer: &self.audioplayer,
+                        },
+                    );
+                }
+            }
+            EngineTask::ClearSearchHighlight => {
+                self.search_highlight.clear();
+                widget_flags.redraw = true;
+            }
+            EngineTask::RecoverySnapshot => {
+                let _ = self.write_recovery_snapshot();
+            }
+            EngineTask::CoalescedRenderingFinished => {
+                let tasks_tx = self.engine_tasks_tx();
+                self.store.regenerate_pending_rendering_coalesced(tasks_tx);
+            }
+            task @ (EngineTask::OperationProgress { .. }
+            | EngineTask::OperationFinished { .. }
+            | EngineTask::OperationFailed { .. }) => {
+                if let Some(event) = self.tasks_tx.operations.event_for_task(task) {
+                    self.operation_subscribers.publish(event);
+                }
+            }
             EngineTask::Quit => {
                 widget_flags |= self.set_active(false);
                 quit = true;
@@ -470,12 +957,15 @@ impl Engine {
     }
 
     /// Handle a pen event.
+    ///
+    /// When [EventPropagation::Proceed] is returned, the event was not consumed and the UI should pass it on.
     pub fn handle_pen_event(
         &mut self,
         event: PenEvent,
         pen_mode: Option<PenMode>,
         now: Instant,
     ) -> (EventPropagation, WidgetFlags) {
+        self.record_pen_event(&event, pen_mode, now);
         self.penholder.handle_pen_event(
             event,
             pen_mode,
@@ -497,6 +987,7 @@ impl Engine {
         shortcut_key: ShortcutKey,
         now: Instant,
     ) -> (EventPropagation, WidgetFlags) {
+        self.record_shortcut_key(shortcut_key, now);
         self.penholder.handle_pressed_shortcut_key(
             shortcut_key,
             now,
@@ -526,6 +1017,76 @@ impl Engine {
         )
     }
 
+    /// Switch to the pen with the given style, handing the context over to it.
+    ///
+    /// The switch is not recorded in the history.
+    pub fn request_pen_switch(
+        &mut self,
+        style: PenStyle,
+        context: PenSwitchContext,
+    ) -> WidgetFlags {
+        self.penholder.request_pen_switch(
+            style,
+            context,
+            &mut EngineViewMut {
+                tasks_tx: self.engine_tasks_tx(),
+                pens_config: &mut self.pens_config,
+                document: &mut self.document,
+                store: &mut self.store,
+                camera: &mut self.camera,
+                audioplayer: &mut self.audioplayer,
+            },
+        )
+    }
+
+    /// Save the config of the current pen as a preset with the name, replacing a preset with the same name.
+    pub fn save_preset(&mut self, name: String) -> anyhow::Result<()> {
+        let pen_style = self.penholder.current_pen_style_w_override();
+        self.pens_config.save_preset(name, pen_style)
+    }
+
+    /// Restore the pen config of the preset and switch to its pen.
+    pub fn apply_preset(&mut self, name: &str) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let pen_style = self.pens_config.apply_preset(name)?;
+        if pen_style != self.penholder.current_pen_style_w_override() {
+            widget_flags |= self.change_pen_style(pen_style) | self.change_pen_style_override(None);
+        } else {
+            widget_flags |= self.current_pen_update_state();
+        }
+        // the toolbars need to show the restored config
+        widget_flags.refresh_ui = true;
+        Ok(widget_flags)
+    }
+
+    /// Apply the preset at the position in [Engine::list_presets], e.g. when a number key is pressed.
+    ///
+    /// Only the first [PensConfig::PRESETS_WITH_POSITION_MAX] presets can be applied by their position.
+    pub fn apply_preset_at(&mut self, i: usize) -> anyhow::Result<WidgetFlags> {
+        let name = self
+            .pens_config
+            .presets()
+            .get(i)
+            .filter(|_| i < PensConfig::PRESETS_WITH_POSITION_MAX)
+            .map(|preset| preset.name.clone())
+            .ok_or_else(|| anyhow::anyhow!("No pen preset at position {i} exists."))?;
+        self.apply_preset(&name)
+    }
+
+    /// The names of the presets, in the order they were saved.
+    pub fn list_presets(&self) -> Vec<String> {
+        self.pens_config
+            .presets()
+            .iter()
+            .map(|preset| preset.name.clone())
+            .collect()
+    }
+
+    /// Delete the preset. Returns false when no preset with the name exists.
+    pub fn delete_preset(&mut self, name: &str) -> bool {
+        self.pens_config.delete_preset(name)
+    }
+
     /// Change the pen style (temporary) override.
     pub fn change_pen_style_override(
         &mut self,
@@ -559,6 +1120,64 @@ impl Engine {
         )
     }
 
+    /// Temporarily switch to the pen with the given style, see [PenHolder::push_pen_override].
+    pub fn push_pen_override(&mut self, style: PenStyle) -> WidgetFlags {
+        self.penholder.push_pen_override(
+            style,
+            &mut EngineViewMut {
+                tasks_tx: self.engine_tasks_tx(),
+                pens_config: &mut self.pens_config,
+                document: &mut self.document,
+                store: &mut self.store,
+                camera: &mut self.camera,
+                audioplayer: &mut self.audioplayer,
+            },
+        )
+    }
+
+    /// Restore the pen below the temporary pen override.
+    pub fn pop_pen_override(&mut self) -> WidgetFlags {
+        self.penholder.pop_pen_override(&mut EngineViewMut {
+            tasks_tx: self.engine_tasks_tx(),
+            pens_config: &mut self.pens_config,
+            document: &mut self.document,
+            store: &mut self.store,
+            camera: &mut self.camera,
+            audioplayer: &mut self.audioplayer,
+        })
+    }
+
+    /// Temporarily switch to the camera offset tool while the pan key is held down.
+    pub fn push_pan_override(&mut self) -> WidgetFlags {
+        self.penholder.push_pan_override(&mut EngineViewMut {
+            tasks_tx: self.engine_tasks_tx(),
+            pens_config: &mut self.pens_config,
+            document: &mut self.document,
+            store: &mut self.store,
+            camera: &mut self.camera,
+            audioplayer: &mut self.audioplayer,
+        })
+    }
+
+    /// Restore the pen from before the pan key was pressed.
+    pub fn release_pan_override(&mut self) -> WidgetFlags {
+        self.penholder.release_pan_override(&mut EngineViewMut {
+            tasks_tx: self.engine_tasks_tx(),
+            pens_config: &mut self.pens_config,
+            document: &mut self.document,
+            store: &mut self.store,
+            camera: &mut self.camera,
+            audioplayer: &mut self.audioplayer,
+        })
+    }
+
+    /// The cursor the UI should show for the current pen.
+    ///
+    /// Should be queried again when the widget flags indicate that it might have changed.
+    pub fn current_cursor(&self) -> CursorStyle {
+        self.penholder.current_cursor(&self.view())
+    }
+
     /// Reinstall the pen in the current style.
     pub fn reinstall_pen_current_style(&mut self) -> WidgetFlags {
         self.penholder
@@ -581,7 +1200,15 @@ impl Engine {
                 | self.update_content_rendering_current_viewport();
             widget_flags.view_modified = true;
         } else {
-            widget_flags |= self.clear_rendering() | self.penholder.deinit_current_pen();
+            widget_flags |= self.penholder.deinit_current_pen(&mut EngineViewMut {
+                tasks_tx: self.engine_tasks_tx(),
+                pens_config: &mut self.pens_config,
+                document: &mut self.document,
+                store: &mut self.store,
+                camera: &mut self.camera,
+                audioplayer: &mut self.audioplayer,
+            });
+            widget_flags |= self.clear_rendering();
         }
         widget_flags
     }
@@ -591,14 +1218,17 @@ impl Engine {
         let doc_bounds = self.document.bounds();
         let keys = self.store.stroke_keys_as_rendered();
 
-        let strokes_bounds = self.store.strokes_bounds(&keys);
+        let mut content_bounds = self.store.strokes_bounds(&keys);
+        if let Some(pdf_underlay) = &self.document.pdf_underlay {
+            content_bounds.extend(pdf_underlay.pages.iter().map(|page| page.bounds()));
+        }
 
         let pages_bounds = doc_bounds
             .split_extended_origin_aligned(self.document.format.size(), split_order)
             .into_iter()
             .filter(|page_bounds| {
-                // Filter the pages out that don't intersect with any stroke
-                strokes_bounds.iter().any(|stroke_bounds| {
+                // Filter the pages out that don't intersect with any stroke or underlay page
+                content_bounds.iter().any(|stroke_bounds| {
                     stroke_bounds.intersects_w_tolerance(
                         page_bounds,
                         Self::STROKE_BOUNDS_INTERSECTION_TOLERANCE,
@@ -640,6 +1270,35 @@ impl Engine {
         self.camera.zoom_w_timeout(zoom, self.tasks_tx.clone())
     }
 
+    /// Zoom so that the bounds fit into the viewport and center the viewport on them.
+    ///
+    /// `padding` is in surface coordinates. The zoom is clamped to the min and max zoom of the camera.
+    pub fn zoom_to_bounds(&mut self, bounds: Aabb, padding: f64) -> WidgetFlags {
+        self.camera
+            .zoom_to_bounds_w_timeout(bounds, padding, self.tasks_tx.clone())
+            | self.doc_expand_autoexpand()
+            | self.update_rendering_current_viewport()
+    }
+
+    /// Zoom to fit the current selection into the viewport.
+    ///
+    /// Does nothing when no strokes are selected.
+    pub fn zoom_to_selection(&mut self) -> WidgetFlags {
+        let Some(bounds) = self
+            .store
+            .bounds_for_strokes(&self.store.selection_keys_as_rendered())
+        else {
+            warn!("Zooming to the selection failed, no strokes are selected");
+            return WidgetFlags::default();
+        };
+        self.zoom_to_bounds(bounds, Self::ZOOM_TO_FIT_PADDING)
+    }
+
+    /// Zoom to fit the entire document into the viewport.
+    pub fn zoom_to_document(&mut self) -> WidgetFlags {
+        self.zoom_to_bounds(self.document.bounds(), Self::ZOOM_TO_FIT_PADDING)
+    }
+
     pub fn set_scale_factor(&mut self, scale_factor: f64) -> WidgetFlags {
         self.store
             .set_rendering_dirty_for_strokes(&self.store.stroke_keys_as_rendered());
@@ -713,6 +1372,7 @@ impl Engine {
     /// Document layout must be set to fixed-size.
     pub fn doc_remove_page_fixed_size(&mut self) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
+        let height = self.document.height;
         if self.document.remove_page_fixed_size() {
             self.store.set_trashed_keys(
                 &self
@@ -720,12 +1380,74 @@ impl Engine {
                     .keys_below_y(self.document.y + self.document.height),
                 true,
             );
-            widget_flags |= self.record(Instant::now()) | self.update_rendering_current_viewport();
+            widget_flags |= self.record(Instant::now());
+            // The page size is not part of the history
+            if widget_flags.read_only_rejected {
+                self.document.height = height;
+            }
+            widget_flags |= self.update_rendering_current_viewport();
             widget_flags.resize = true;
         }
         widget_flags
     }
 
+    /// Move the page at `from_idx` to `to_idx`, taking its strokes along. The pages in between move up or down by one.
+    ///
+    /// Strokes are part of the page that contains the larger share of their bounds.
+    /// Only possible in layouts with a fixed width, where the pages are stacked vertically.
+    pub fn move_page(&mut self, from_idx: usize, to_idx: usize) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let n_pages = self.document.n_vertical_pages();
+        if !self.document.layout.is_fixed_width()
+            || from_idx >= n_pages
+            || to_idx >= n_pages
+            || from_idx == to_idx
+        {
+            return widget_flags;
+        }
+        let format_height = self.document.format.height();
+
+        // the old page indices in their new order
+        let mut page_order = (0..n_pages).collect::<Vec<usize>>();
+        let moved = page_order.remove(from_idx);
+        page_order.insert(to_idx, moved);
+
+        let mut page_keys = vec![vec![]; n_pages];
+        for key in self.store.stroke_keys_as_rendered() {
+            let Some(bounds) = self.store.bounds_for_strokes(&[key]) else {
+                continue;
+            };
+            page_keys[self.document.vertical_page_idx_of_bounds(bounds)].push(key);
+        }
+
+        let mut moved_keys = vec![];
+        for (new_idx, &old_idx) in page_order.iter().enumerate() {
+            if new_idx == old_idx || page_keys[old_idx].is_empty() {
+                continue;
+            }
+            let offset = na::vector![0.0, (new_idx as f64 - old_idx as f64) * format_height];
+            self.store.translate_strokes(&page_keys[old_idx], offset);
+            self.store
+                .translate_strokes_images(&page_keys[old_idx], offset);
+            moved_keys.extend_from_slice(&page_keys[old_idx]);
+        }
+        if moved_keys.is_empty() {
+            return widget_flags;
+        }
+        self.store.update_geometry_for_strokes(&moved_keys);
+        self.store.regenerate_rendering_for_strokes(
+            &moved_keys,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+
+        widget_flags |= self.doc_resize_autoexpand() | self.record(Instant::now());
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
     /// Update the viewport offset of the camera, clamped to mins and maxs values depending on the document layout.
     ///
     /// Background and content rendering then need to be updated.
@@ -771,6 +1493,19 @@ impl Engine {
         })
     }
 
+    /// Update the state of the current pen after the store history has changed ( undo / redo ).
+    fn current_pen_update_state_after_history_change(&mut self) -> WidgetFlags {
+        self.penholder
+            .current_pen_update_state_after_history_change(&mut EngineViewMut {
+                tasks_tx: self.tasks_tx.clone(),
+                pens_config: &mut self.pens_config,
+                document: &mut self.document,
+                store: &mut self.store,
+                camera: &mut self.camera,
+                audioplayer: &mut self.audioplayer,
+            })
+    }
+
     /// Fetch clipboard content from the current pen.
     #[allow(clippy::type_complexity)]
     pub fn fetch_clipboard_content(
@@ -786,6 +1521,25 @@ impl Engine {
         })
     }
 
+    /// Fetch the selected strokes in the rnote clipboard format, see [ClipboardStrokes].
+    ///
+    /// Returns an empty payload when no strokes are selected.
+    pub fn fetch_clipboard_selection(&self) -> Vec<u8> {
+        let selection = self.store.selection_keys_as_rendered();
+        if selection.is_empty() {
+            return vec![];
+        }
+        let strokes = self.store.fetch_stroke_content(&selection).strokes;
+
+        match ClipboardStrokes::new(strokes).to_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Serializing the selection as clipboard strokes failed, Err: {e:?}");
+                vec![]
+            }
+        }
+    }
+
     /// Cut clipboard content from the current pen.
     #[allow(clippy::type_complexity)]
     pub fn cut_clipboard_content(
@@ -810,10 +1564,111 @@ impl Engine {
         }
     }
 
+    /// Set the background image of the document from the encoded image bytes, recording it in the history.
+    pub fn set_background_image(
+        &mut self,
+        bytes: &[u8],
+        options: BackgroundImageOptions,
+    ) -> anyhow::Result<WidgetFlags> {
+        let background_image = Arc::new(BackgroundImage::try_from_encoded_bytes(
+            bytes,
+            options,
+            self.document.format.dpi(),
+        )?);
+        self.document.background.image = Some(Arc::clone(&background_image));
+        self.store.set_background_image(Some(background_image));
+
+        let mut widget_flags = self.record(Instant::now()) | self.background_rendering_regenerate();
+        widget_flags.store_modified = true;
+        Ok(widget_flags)
+    }
+
+    /// Remove the background image of the document, recording it in the history.
+    pub fn clear_background_image(&mut self) -> WidgetFlags {
+        if self.document.background.image.take().is_none() {
+            return WidgetFlags::default();
+        }
+        self.store.set_background_image(None);
+
+        let mut widget_flags = self.record(Instant::now()) | self.background_rendering_regenerate();
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
+    /// Take over the background image from the store after navigating the history.
+    fn update_background_image_after_history_change(&mut self) -> WidgetFlags {
+        let background_image = self.store.background_image().cloned();
+        let unchanged = match (&self.document.background.image, &background_image) {
+            (Some(image), Some(history_image)) => Arc::ptr_eq(image, history_image),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return WidgetFlags::default();
+        }
+        self.document.background.image = background_image;
+        let mut widget_flags = self.background_rendering_regenerate();
+        widget_flags.refresh_ui = true;
+        widget_flags
+    }
+
     pub fn select_all_strokes(&mut self) -> WidgetFlags {
-        let widget_flags = self.change_pen_style(PenStyle::Selector);
+        let mut widget_flags = self.change_pen_style(PenStyle::Selector);
+        self.store.set_selected_keys(
+            &self
+                .store
+                .unlocked_keys(&self.store.stroke_keys_as_rendered()),
+            true,
+        );
+        widget_flags.selection_changed = true;
+        widget_flags
+            | self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport()
+    }
+
+    /// Select the strokes in the row of the document that contains the position and switch to the selector to modify them.
+    ///
+    /// The rows are the spaces between the horizontal lines of the background pattern, if it has them.
+    pub fn select_row_at(&mut self, pos: na::Vector2<f64>) -> WidgetFlags {
+        let keys = Selector::row_strokes(pos, &self.view());
+        self.select_created_strokes(&keys)
+    }
+
+    /// Select the `n` most recently created strokes and switch to the selector to modify them.
+    pub fn select_last_strokes(&mut self, n: usize) -> WidgetFlags {
+        let keys = self.store.last_created_keys(n);
+        self.select_created_strokes(&keys)
+    }
+
+    /// Select the strokes created since the given instant and switch to the selector to modify them.
+    pub fn select_strokes_since(&mut self, instant: Instant) -> WidgetFlags {
+        let keys = self.store.keys_created_since(instant);
+        self.select_created_strokes(&keys)
+    }
+
+    /// Select the strokes created since the pen style was last changed and switch to the selector to modify them.
+    ///
+    /// When the style was not changed yet, all strokes created during the current session are selected.
+    pub fn select_strokes_since_pen_style_change(&mut self) -> WidgetFlags {
+        let keys = match self.penholder.style_changed_at() {
+            Some(instant) => self.store.keys_created_since(instant),
+            None => self.store.last_created_keys(usize::MAX),
+        };
+        self.select_created_strokes(&keys)
+    }
+
+    fn select_created_strokes(&mut self, keys: &[StrokeKey]) -> WidgetFlags {
+        if keys.is_empty() {
+            return WidgetFlags::default();
+        }
+        // Changing the style deselects, so the new selection is set afterwards
+        let mut widget_flags = self.change_pen_style(PenStyle::Selector);
         self.store
-            .set_selected_keys(&self.store.stroke_keys_as_rendered(), true);
+            .set_selected_keys_keep_order(&self.store.selection_keys_as_rendered(), false);
+        self.store.set_selected_keys(keys, true);
+        widget_flags.selection_changed = true;
         widget_flags
             | self.current_pen_update_state()
             | self.doc_resize_autoexpand()
@@ -822,9 +1677,10 @@ impl Engine {
     }
 
     pub fn deselect_all_strokes(&mut self) -> WidgetFlags {
-        let widget_flags = self.change_pen_style(PenStyle::Selector);
+        let mut widget_flags = self.change_pen_style(PenStyle::Selector);
         self.store
-            .set_selected_keys(&self.store.selection_keys_as_rendered(), false);
+            .set_selected_keys_keep_order(&self.store.selection_keys_as_rendered(), false);
+        widget_flags.selection_changed = true;
         widget_flags
             | self.current_pen_update_state()
             | self.doc_resize_autoexpand()
@@ -832,6 +1688,114 @@ impl Engine {
             | self.update_rendering_current_viewport()
     }
 
+    /// Lock the selected strokes against selecting and erasing.
+    ///
+    /// Locked strokes can't stay selected, so the selection is dropped.
+    pub fn lock_selection(&mut self) -> WidgetFlags {
+        let selection = self.store.selection_keys_as_rendered();
+        if selection.is_empty() {
+            return WidgetFlags::default();
+        }
+        self.store.set_strokes_locked(&selection, true);
+        let mut widget_flags = self.current_pen_update_state()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport();
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
+    /// Unlock all locked strokes.
+    pub fn unlock_all_strokes(&mut self) -> WidgetFlags {
+        let locked = self.store.locked_keys_unordered();
+        if locked.is_empty() {
+            return WidgetFlags::default();
+        }
+        self.store.set_strokes_locked(&locked, false);
+        let mut widget_flags = self.record(Instant::now());
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
+    /// Insert text labels with the measured dimensions of the selected lines, rectangles and ellipses.
+    ///
+    /// The labels are regular text strokes and are selected together with their source strokes.
+    pub fn annotate_selection_measurements(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let selection = self.store.selection_keys_as_rendered();
+        let dpi = self.document.format.dpi();
+        let mut text_style = self.pens_config.typewriter_config.text_style.clone();
+        text_style.ranged_text_attributes.clear();
+        text_style.set_max_width(None);
+
+        let labels = selection
+            .iter()
+            .filter_map(|&key| match self.store.get_stroke_ref(key) {
+                Some(Stroke::ShapeStroke(shapestroke)) => shapestroke.measurement(),
+                _ => None,
+            })
+            .map(|(values, anchor)| {
+                let text = self
+                    .pens_config
+                    .shaper_config
+                    .format_measurement(&values, dpi);
+                Stroke::TextStroke(TextStroke::new(text, anchor, text_style.clone()))
+            })
+            .collect::<Vec<Stroke>>();
+        if labels.is_empty() {
+            return widget_flags;
+        }
+        let label_keys = labels
+            .into_iter()
+            .map(|label| self.store.insert_stroke(label, None))
+            .collect::<Vec<StrokeKey>>();
+
+        widget_flags |= self.change_pen_style(PenStyle::Selector);
+        self.store.set_selected_keys(&selection, true);
+        self.store.set_selected_keys(&label_keys, true);
+        self.store.update_geometry_for_strokes(&label_keys);
+        self.store.regenerate_rendering_for_strokes_threaded(
+            self.tasks_tx.clone(),
+            &label_keys,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+        widget_flags.selection_changed = true;
+        widget_flags.store_modified = true;
+        widget_flags.resize = true;
+
+        widget_flags
+            | self.current_pen_update_state()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport()
+    }
+
+    /// Insert a rectangle with the given options around the selection, inflated by the padding.
+    ///
+    /// The frame is drawn directly behind the selection, which stays selected.
+    /// Returns the key of the frame, None when nothing is selected.
+    pub fn frame_selection(
+        &mut self,
+        padding: f64,
+        options: SmoothOptions,
+    ) -> (Option<StrokeKey>, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let selection = self.store.selection_keys_as_rendered();
+        let Some(key) = Selector::insert_frame(&selection, padding, options, &mut self.view_mut())
+        else {
+            return (None, widget_flags);
+        };
+        widget_flags.store_modified = true;
+        widget_flags.resize = true;
+        widget_flags |= self.doc_resize_autoexpand()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport();
+
+        if widget_flags.read_only_rejected {
+            return (None, widget_flags);
+        }
+        (Some(key), widget_flags)
+    }
+
     pub fn select_with_bounds(
         &mut self,
         bounds: Aabb,
@@ -843,12 +1807,42 @@ impl Engine {
                 .store
                 .stroke_keys_as_rendered_intersecting_bounds(bounds),
         };
+        let select = self.store.unlocked_keys(&select);
         self.store.set_selected_keys(&select, true);
-        self.doc_resize_autoexpand()
+        let mut widget_flags = WidgetFlags::default();
+        widget_flags.selection_changed = !select.is_empty();
+        widget_flags
+            | self.doc_resize_autoexpand()
             | self.record(Instant::now())
             | self.update_rendering_current_viewport()
     }
 
+    /// Summarize the styles of the selected strokes.
+    ///
+    /// Returns None when nothing is selected.
+    pub fn selection_style_summary(&self) -> Option<SelectionStyleSummary> {
+        let selection = self.store.selection_keys_as_rendered();
+        if selection.is_empty() {
+            return None;
+        }
+        Some(self.store.selection_style_summary(&selection))
+    }
+
+    /// Sample the color of the topmost stroke at the given position, in document coordinates.
+    ///
+    /// Returns the stroke color, or the fill color for strokes that don't have one.
+    /// None if there is no stroke at the position.
+    pub fn sample_color_at(&self, pos: na::Vector2<f64>) -> Option<Color> {
+        let key = self.store.stroke_hitboxes_contain_coord(pos, true).pop()?;
+        let stroke = self.store.get_stroke_ref(key)?;
+        stroke.stroke_color().or_else(|| stroke.fill_color())
+    }
+
+    /// Summarize the number and kinds of the selected strokes.
+    pub fn selection_summary(&self) -> SelectionSummary {
+        self.store.selection_summary()
+    }
+
     pub fn duplicate_selection(&mut self) -> WidgetFlags {
         let new_selected = self.store.duplicate_selection();
         self.store.update_geometry_for_strokes(&new_selected);
@@ -858,6 +1852,100 @@ impl Engine {
             | self.update_rendering_current_viewport()
     }
 
+    /// Move and resize the selection so that its bounds match the given bounds.
+    ///
+    /// The extents are clamped to the same minimum as when resizing the selection interactively.
+    /// With `preserve_aspect` the selection is scaled uniformly to fit inside the new bounds, anchored at their top left corner.
+    /// Stroke widths are scaled according to the configured width scale policy.
+    ///
+    /// Errors when nothing is selected.
+    pub fn set_selection_bounds(
+        &mut self,
+        new_bounds: Aabb,
+        preserve_aspect: bool,
+    ) -> anyhow::Result<WidgetFlags> {
+        let selection_keys = self.store.selection_keys_as_rendered();
+        let Some(current_bounds) = self.store.bounds_for_strokes(&selection_keys) else {
+            return Err(anyhow::anyhow!(
+                "Setting selection bounds failed, nothing is selected."
+            ));
+        };
+        let new_bounds = Aabb::new_positive(new_bounds.mins, new_bounds.maxs);
+        let current_extents = current_bounds.extents();
+        let min_extents =
+            Selector::resize_min_extents(&self.store, &selection_keys, current_bounds);
+        let mut target_extents = new_bounds.extents().zip_map(&min_extents, f64::max);
+        if preserve_aspect {
+            let factors = target_extents.component_div(&current_extents);
+            let min_factor = min_extents
+                .component_div(&current_extents)
+                .iter()
+                .copied()
+                .filter(|f| f.is_finite())
+                .fold(0.0_f64, f64::max);
+            let factor = factors
+                .iter()
+                .copied()
+                .filter(|f| f.is_finite())
+                .fold(f64::INFINITY, f64::min)
+                .max(min_factor);
+            if factor.is_finite() {
+                target_extents = current_extents * factor;
+            }
+        }
+        let target_bounds = Aabb::new(new_bounds.mins, new_bounds.mins + target_extents);
+        let within_tolerance = |bounds: Aabb| {
+            (bounds.mins - target_bounds.mins).amax() < Self::SELECTION_BOUNDS_TOLERANCE
+                && (bounds.maxs - target_bounds.maxs).amax() < Self::SELECTION_BOUNDS_TOLERANCE
+        };
+        if within_tolerance(current_bounds) {
+            return Ok(WidgetFlags::default());
+        }
+
+        self.store.begin_width_scaling(
+            &selection_keys,
+            self.pens_config.selector_config.width_scale_policy,
+        );
+        // The stroke widths are part of the bounds but might not scale linearly with the policy,
+        // so the remaining difference is corrected in a few more passes.
+        let mut bounds = current_bounds;
+        for _ in 0..4 {
+            let scale = target_bounds
+                .extents()
+                .component_div(&bounds.extents())
+                .map(|s| if s.is_finite() && s > 0.0 { s } else { 1.0 });
+            self.store
+                .scale_strokes_with_pivot(&selection_keys, scale, bounds.mins.coords);
+            self.store
+                .scale_strokes_images_with_pivot(&selection_keys, scale, bounds.mins.coords);
+            let offset = target_bounds.mins - bounds.mins;
+            self.store.translate_strokes(&selection_keys, offset);
+            self.store.translate_strokes_images(&selection_keys, offset);
+            self.store.update_geometry_for_strokes(&selection_keys);
+
+            let Some(scaled_bounds) = self.store.bounds_for_strokes(&selection_keys) else {
+                break;
+            };
+            bounds = scaled_bounds;
+            if within_tolerance(bounds) {
+                break;
+            }
+        }
+        self.store.commit_width_scaling(&selection_keys);
+        self.store.update_geometry_for_strokes(&selection_keys);
+        self.store.regenerate_rendering_in_viewport_threaded(
+            self.tasks_tx.clone(),
+            false,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+
+        Ok(self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport())
+    }
+
     pub fn trash_selection(&mut self) -> WidgetFlags {
         let selection_keys = self.store.selection_keys_as_rendered();
         self.store.set_trashed_keys(&selection_keys, true);
@@ -867,6 +1955,47 @@ impl Engine {
             | self.update_rendering_current_viewport()
     }
 
+    /// Information about the trashed strokes, the most recently trashed first.
+    pub fn trashed_strokes_info(&self) -> Vec<TrashedStrokeInfo> {
+        self.store.trashed_strokes_info()
+    }
+
+    /// Restore the trashed strokes. They reappear where they were, on top of the other strokes.
+    pub fn restore_trashed(&mut self, keys: &[StrokeKey]) -> WidgetFlags {
+        let restored = self.store.restore_trashed(keys);
+        if restored.is_empty() {
+            return WidgetFlags::default();
+        }
+        self.store.update_geometry_for_strokes(&restored);
+        self.store.regenerate_rendering_for_strokes_threaded(
+            self.tasks_tx.clone(),
+            &restored,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+        let mut widget_flags = self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now());
+        widget_flags.store_modified = true;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    /// Permanently remove the strokes that were trashed longer ago than the given duration, or all when it is None.
+    ///
+    /// The history steps from before the purge still hold on to the strokes until they are dropped from the history.
+    pub fn purge_trashed(&mut self, older_than: Option<Duration>) -> WidgetFlags {
+        let purged = self
+            .store
+            .purge_trashed(older_than, ChronoComponent::now_timestamp());
+        if purged.is_empty() {
+            return WidgetFlags::default();
+        }
+        let mut widget_flags = self.record(Instant::now());
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
     pub fn nothing_selected(&self) -> bool {
         self.store.selection_keys_unordered().is_empty()
     }
@@ -885,6 +2014,188 @@ impl Engine {
             | self.update_content_rendering_current_viewport()
     }
 
+    /// Apply the style update to all selected strokes as a single history step.
+    ///
+    /// Returns the keys of the modified strokes.
+    pub fn change_selection_style(&mut self, update: StyleUpdate) -> (Vec<StrokeKey>, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let modified = self.store.change_selection_style(&update);
+        if modified.is_empty() {
+            return (modified, widget_flags);
+        }
+
+        self.store.update_geometry_for_strokes(&modified);
+        self.store.regenerate_rendering_for_strokes_threaded(
+            self.tasks_tx.clone(),
+            &modified,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+        widget_flags |= self.current_pen_update_state() | self.record(Instant::now());
+        widget_flags.redraw = true;
+        widget_flags.store_modified = true;
+
+        if widget_flags.read_only_rejected {
+            return (vec![], widget_flags);
+        }
+        (modified, widget_flags)
+    }
+
+    /// Replace the colors of the strokes that are perceptually within `tolerance` of the old color
+    /// as a single history step, e.g. to turn all red ink black for printing.
+    ///
+    /// Only the selected strokes are considered when `selection_only` is true.
+    ///
+    /// Returns the number of modified strokes.
+    pub fn replace_color(
+        &mut self,
+        old_color: Color,
+        new_color: Color,
+        tolerance: f64,
+        scope: ColorReplaceScope,
+        selection_only: bool,
+    ) -> (usize, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let modified =
+            self.store
+                .replace_color(old_color, new_color, tolerance, scope, selection_only);
+        if modified.is_empty() {
+            return (0, widget_flags);
+        }
+
+        self.store.regenerate_rendering_for_strokes_threaded(
+            self.tasks_tx.clone(),
+            &modified,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+        widget_flags |= self.current_pen_update_state() | self.record(Instant::now());
+        widget_flags.redraw = true;
+        widget_flags.store_modified = true;
+
+        if widget_flags.read_only_rejected {
+            return (0, widget_flags);
+        }
+        (modified.len(), widget_flags)
+    }
+
+    /// Set the opacity of the selected strokes.
+    ///
+    /// Clamped to a minimum of [crate::strokes::Stroke::OPACITY_MIN], so that strokes can't vanish irrecoverably.
+    pub fn set_selection_opacity(&mut self, opacity: f64) -> WidgetFlags {
+        self.store
+            .set_strokes_opacity(&self.store.selection_keys_as_rendered(), opacity)
+            | self.record(Instant::now())
+            | self.update_content_rendering_current_viewport()
+    }
+
+    /// Resize the image strokes of the given keys back to their natural size at the document DPI,
+    /// about their centers. Other strokes are left untouched.
+    pub fn reset_images_to_original_size(&mut self, keys: &[StrokeKey]) -> WidgetFlags {
+        let reset_keys = self.store.reset_images_to_original_size(keys);
+        if reset_keys.is_empty() {
+            return WidgetFlags::default();
+        }
+        self.store.update_geometry_for_strokes(&reset_keys);
+        self.store.regenerate_rendering_for_strokes_threaded(
+            self.tasks_tx.clone(),
+            &reset_keys,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+
+        let mut widget_flags = self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport();
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
+    /// Begin a two-finger pinch gesture on the selection.
+    ///
+    /// `center` is the gesture centroid in document coordinates.
+    /// Returns whether the gesture is applied to the selection.
+    /// If not, the gesture should control the camera instead.
+    pub fn selection_pinch_begin(&mut self, center: na::Vector2<f64>) -> (bool, WidgetFlags) {
+        if let Pen::Selector(selector) = self.penholder.current_pen_mut() {
+            selector.pinch_begin(
+                center,
+                &mut EngineViewMut {
+                    tasks_tx: self.tasks_tx.clone(),
+                    pens_config: &mut self.pens_config,
+                    document: &mut self.document,
+                    store: &mut self.store,
+                    camera: &mut self.camera,
+                    audioplayer: &mut self.audioplayer,
+                },
+            )
+        } else {
+            (false, WidgetFlags::default())
+        }
+    }
+
+    /// Update the pinch gesture on the selection with the accumulated scale and rotation angle since the gesture began.
+    pub fn selection_pinch_update(
+        &mut self,
+        scale: f64,
+        rotation_angle: f64,
+        center: na::Vector2<f64>,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if let Pen::Selector(selector) = self.penholder.current_pen_mut() {
+            widget_flags |= selector.pinch_update(
+                scale,
+                rotation_angle,
+                center,
+                &mut EngineViewMut {
+                    tasks_tx: self.tasks_tx.clone(),
+                    pens_config: &mut self.pens_config,
+                    document: &mut self.document,
+                    store: &mut self.store,
+                    camera: &mut self.camera,
+                    audioplayer: &mut self.audioplayer,
+                },
+            )
+        }
+        widget_flags
+    }
+
+    /// End the pinch gesture on the selection.
+    pub fn selection_pinch_end(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if let Pen::Selector(selector) = self.penholder.current_pen_mut() {
+            widget_flags |= selector.pinch_end(&mut EngineViewMut {
+                tasks_tx: self.tasks_tx.clone(),
+                pens_config: &mut self.pens_config,
+                document: &mut self.document,
+                store: &mut self.store,
+                camera: &mut self.camera,
+                audioplayer: &mut self.audioplayer,
+            })
+        }
+        self.update_after_rejected(widget_flags) | self.update_rendering_current_viewport()
+    }
+
+    /// Repeat the last committed translate, rotate, resize or shear gesture of the selector on the current selection.
+    ///
+    /// The transform is applied relative to the center of the selection, so that e.g. duplicating and
+    /// repeating the transform lays out equally spaced copies.
+    pub fn repeat_last_transform(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if let Pen::Selector(selector) = self.penholder.current_pen_mut() {
+            widget_flags |= selector.repeat_last_transform(&mut EngineViewMut {
+                tasks_tx: self.tasks_tx.clone(),
+                pens_config: &mut self.pens_config,
+                document: &mut self.document,
+                store: &mut self.store,
+                camera: &mut self.camera,
+                audioplayer: &mut self.audioplayer,
+            })
+        }
+        self.update_after_rejected(widget_flags)
+    }
+
     pub fn invert_selection_colors(&mut self) -> WidgetFlags {
         self.store
             .invert_color_brightness(&self.store.selection_keys_as_rendered())
@@ -1000,3 +2311,1234 @@ impl Engine {
         widget_flags
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::export::{
+        SelectionExportFormat, SelectionExportPrefs, SelectionPhysicalSize,
+    };
+    use crate::strokes::ShapeStroke;
+    use crate::testsupport::{engine_w_selected_rect, insert_rect};
+    use rnote_compose::shapes::Rectangle;
+    use rnote_compose::{Shape, Style};
+
+    fn selection_bounds(engine: &Engine) -> Aabb {
+        engine
+            .store
+            .bounds_for_strokes(&engine.store.selection_keys_as_rendered())
+            .unwrap()
+    }
+
+    #[test]
+    fn set_selection_bounds_maps_exactly() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![10.0, 20.0], na::point![110.0, 70.0]));
+        let start_bounds = selection_bounds(&engine);
+        let new_bounds = Aabb::new(na::point![200.0, 300.0], na::point![500.0, 350.0]);
+
+        let _ = engine.set_selection_bounds(new_bounds, false).unwrap();
+        let bounds = selection_bounds(&engine);
+        approx::assert_relative_eq!(
+            bounds.mins.coords,
+            new_bounds.mins.coords,
+            epsilon = Engine::SELECTION_BOUNDS_TOLERANCE
+        );
+        approx::assert_relative_eq!(
+            bounds.maxs.coords,
+            new_bounds.maxs.coords,
+            epsilon = Engine::SELECTION_BOUNDS_TOLERANCE
+        );
+
+        // Setting the same bounds again doesn't drift and doesn't add a history step
+        let _ = engine.set_selection_bounds(new_bounds, false).unwrap();
+        assert_eq!(selection_bounds(&engine), bounds);
+        let _ = engine.undo(Instant::now());
+        let keys = engine.store.stroke_keys_as_rendered();
+        approx::assert_relative_eq!(
+            engine.store.bounds_for_strokes(&keys).unwrap().mins.coords,
+            start_bounds.mins.coords
+        );
+    }
+
+    #[test]
+    fn set_selection_bounds_preserves_aspect() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]));
+        let start_extents = selection_bounds(&engine).extents();
+        let new_bounds = Aabb::new(na::point![0.0, 0.0], na::point![400.0, 400.0]);
+
+        let _ = engine.set_selection_bounds(new_bounds, true).unwrap();
+        let extents = selection_bounds(&engine).extents();
+        approx::assert_relative_eq!(
+            extents[0] / extents[1],
+            start_extents[0] / start_extents[1],
+            epsilon = 1e-3
+        );
+        assert!(extents[0] <= 400.0 + Engine::SELECTION_BOUNDS_TOLERANCE);
+    }
+
+    #[test]
+    fn set_selection_bounds_clamps_degenerate_bounds() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]));
+        let new_bounds = Aabb::new(na::point![20.0, 20.0], na::point![20.0, 20.0]);
+
+        let _ = engine.set_selection_bounds(new_bounds, false).unwrap();
+        let extents = selection_bounds(&engine).extents();
+        assert!(extents.iter().all(|e| e.is_finite() && *e > 0.0));
+        assert!(extents.min() >= Selector::RESIZE_MIN_EXTENTS - Engine::SELECTION_BOUNDS_TOLERANCE);
+    }
+
+    #[test]
+    fn set_selection_bounds_empty_selection() {
+        let mut engine = Engine::default();
+        let new_bounds = Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]);
+        assert!(engine.set_selection_bounds(new_bounds, false).is_err());
+    }
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(width, height))
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn image_original_size_survives_save_load() {
+        use crate::strokes::resize::ImageSizeOption;
+        use crate::strokes::BitmapImage;
+
+        let mut engine = Engine::default();
+        let bitmapimage = BitmapImage::from_image_bytes(
+            &png_bytes(40, 20),
+            na::vector![10.0, 10.0],
+            ImageSizeOption::ImposeSize(na::vector![200.0, 100.0]),
+        )
+        .unwrap();
+        assert_eq!(bitmapimage.original_pixel_size, Some((40, 20)));
+        let _ = engine
+            .store
+            .insert_stroke(Stroke::BitmapImage(bitmapimage), None);
+
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).unwrap();
+        let mut loaded = Engine::default();
+        let _ = loaded.load_snapshot(snapshot);
+
+        let keys = loaded.store.stroke_keys_as_rendered();
+        let Some(Stroke::BitmapImage(bitmapimage)) = loaded.store.get_stroke_ref(keys[0]) else {
+            panic!("the bitmap image was not loaded");
+        };
+        assert_eq!(bitmapimage.original_pixel_size, Some((40, 20)));
+        approx::assert_relative_eq!(bitmapimage.original_size(), na::vector![40.0, 20.0]);
+    }
+
+    #[test]
+    fn thumbnail_embedded_on_save() {
+        use crate::engine::export::RnoteSaveOptions;
+
+        let mut engine = engine_w_selected_rect(Aabb::new(
+            na::point![100.0, 100.0],
+            na::point![300.0, 200.0],
+        ));
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+
+        let png_data = Engine::document_thumbnail(&bytes).unwrap();
+        assert!(png_data.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let thumbnail =
+            image::load_from_memory_with_format(&png_data, image::ImageFormat::Png).unwrap();
+        // the thumbnail shows the first page, scaled down to fit
+        let page_size = engine.document.format.size();
+        let scale = Engine::THUMBNAIL_SIZE_MAX / page_size.max();
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (
+                (page_size[0] * scale).round() as u32,
+                (page_size[1] * scale).round() as u32
+            )
+        );
+        assert_eq!(
+            thumbnail.width().max(thumbnail.height()),
+            Engine::THUMBNAIL_SIZE_MAX as u32
+        );
+        // the file still loads as usual
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).unwrap();
+        assert_eq!(snapshot.stroke_components.len(), 1);
+
+        let bytes = futures::executor::block_on(engine.save_as_rnote_bytes_w_options(
+            String::from("test.rnote"),
+            RnoteSaveOptions {
+                embed_thumbnail: false,
+            },
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(Engine::document_thumbnail(&bytes).is_none());
+        assert!(futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).is_ok());
+    }
+
+    #[test]
+    fn replace_color() {
+        use crate::strokes::textstroke::TextStyle;
+        use crate::strokes::TextStroke;
+        use rnote_compose::style::smooth::SmoothOptions;
+
+        let red = Color::new(0.8, 0.1, 0.1, 1.0);
+        // e.g. slightly off after an import
+        let near_red = Color::new(0.81, 0.1, 0.11, 1.0);
+        let blue = Color::new(0.1, 0.2, 0.8, 1.0);
+        let black = Color::BLACK;
+
+        let mut engine = Engine::default();
+        let insert_colored_rect =
+            |engine: &mut Engine, stroke_color: Color, fill_color: Option<Color>| {
+                let mut style = Style::Smooth(SmoothOptions {
+                    stroke_color: Some(stroke_color),
+                    ..Default::default()
+                });
+                if let Some(fill_color) = fill_color {
+                    style.set_fill_color(fill_color);
+                }
+                engine.store.insert_stroke(
+                    Stroke::ShapeStroke(ShapeStroke::new(
+                        Shape::Rectangle(Rectangle::from_p2d_aabb(Aabb::new(
+                            na::point![0.0, 0.0],
+                            na::point![100.0, 100.0],
+                        ))),
+                        style,
+                    )),
+                    None,
+                )
+            };
+        let rect_a = insert_colored_rect(&mut engine, red, None);
+        let rect_b = insert_colored_rect(&mut engine, near_red, Some(red));
+        let rect_c = insert_colored_rect(&mut engine, blue, Some(near_red));
+        let text = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("red"),
+                na::vector![0.0, 200.0],
+                TextStyle {
+                    color: red,
+                    ..Default::default()
+                },
+            )),
+            None,
+        );
+        engine.store.record(Instant::now());
+        let colors = |engine: &Engine| {
+            [rect_a, rect_b, rect_c, text]
+                .map(|key| {
+                    let stroke = engine.store.get_stroke_ref(key).unwrap();
+                    (stroke.stroke_color(), stroke.fill_color())
+                })
+                .to_vec()
+        };
+        let original_colors = colors(&engine);
+
+        // without tolerance only the exact color is replaced
+        let (n_modified, widget_flags) =
+            engine.replace_color(red, black, 0.0, ColorReplaceScope::All, false);
+        assert_eq!(n_modified, 3);
+        assert!(widget_flags.store_modified);
+        assert_eq!(
+            colors(&engine),
+            vec![
+                (Some(black), None),
+                (Some(near_red), Some(black)),
+                (Some(blue), Some(near_red)),
+                (Some(black), None),
+            ]
+        );
+
+        // undo restores the exact original colors
+        let _ = engine.undo(Instant::now());
+        assert_eq!(colors(&engine), original_colors);
+
+        // the tolerance catches near identical colors, but not different ones
+        let (n_modified, _) =
+            engine.replace_color(red, black, 0.05, ColorReplaceScope::FillColors, false);
+        assert_eq!(n_modified, 2);
+        assert_eq!(
+            colors(&engine),
+            vec![
+                (Some(red), None),
+                (Some(near_red), Some(black)),
+                (Some(blue), Some(black)),
+                (Some(red), None),
+            ]
+        );
+
+        // restricted to the selection
+        engine.store.set_selected(rect_a, true);
+        let (n_modified, _) =
+            engine.replace_color(red, black, 0.05, ColorReplaceScope::StrokeColors, true);
+        assert_eq!(n_modified, 1);
+        assert_eq!(
+            engine.store.get_stroke_ref(text).unwrap().stroke_color(),
+            Some(red)
+        );
+    }
+
+    #[test]
+    fn background_image_survives_save_load_and_undo() {
+        use crate::document::background::BackgroundImageTiling;
+
+        let mut engine = Engine::default();
+        let options = BackgroundImageOptions {
+            dpi: 192.0,
+            tiling: BackgroundImageTiling::TileVertical,
+            opacity: 0.5,
+        };
+        let _ = engine
+            .set_background_image(&png_bytes(40, 20), options)
+            .unwrap();
+        let background_image = engine.document.background.image.clone().unwrap();
+        // at twice the DPI of the document the image is half its pixel size
+        approx::assert_relative_eq!(background_image.size, na::vector![20.0, 10.0]);
+        assert!(engine.can_undo());
+        let svg = engine
+            .document
+            .background
+            .gen_svg(engine.document.bounds(), false, false)
+            .unwrap();
+        assert!(svg.svg_data.contains("data:image/png;base64,"));
+        // the image is part of the document, not of the config
+        assert!(engine
+            .extract_engine_config()
+            .document
+            .background
+            .image
+            .is_none());
+
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).unwrap();
+        let mut loaded = Engine::default();
+        let _ = loaded.load_snapshot(snapshot);
+        let loaded_image = loaded
+            .document
+            .background
+            .image
+            .clone()
+            .expect("the background image was not loaded");
+        assert_eq!(
+            (
+                loaded_image.image.pixel_width,
+                loaded_image.image.pixel_height
+            ),
+            (40, 20)
+        );
+        assert_eq!(
+            loaded_image.options.tiling,
+            BackgroundImageTiling::TileVertical
+        );
+        approx::assert_relative_eq!(loaded_image.options.opacity, 0.5);
+        approx::assert_relative_eq!(loaded_image.size, background_image.size);
+        // the loaded image is the initial state of the history
+        assert!(!loaded.can_undo());
+        assert!(!loaded.has_unsaved_changes());
+
+        let _ = engine.clear_background_image();
+        assert!(engine.document.background.image.is_none());
+        let _ = engine.undo(Instant::now());
+        assert!(Arc::ptr_eq(
+            engine.document.background.image.as_ref().unwrap(),
+            &background_image
+        ));
+        let _ = engine.undo(Instant::now());
+        assert!(engine.document.background.image.is_none());
+        let _ = engine.redo(Instant::now());
+        assert!(engine.document.background.image.is_some());
+    }
+
+    #[test]
+    fn reset_images_to_original_size() {
+        use crate::strokes::resize::ImageSizeOption;
+        use crate::strokes::BitmapImage;
+
+        let mut engine = Engine::default();
+        let bitmapimage = BitmapImage::from_image_bytes(
+            &png_bytes(40, 20),
+            na::vector![100.0, 100.0],
+            ImageSizeOption::ImposeSize(na::vector![300.0, 50.0]),
+        )
+        .unwrap();
+        let image_key = engine
+            .store
+            .insert_stroke(Stroke::BitmapImage(bitmapimage), None);
+        let rect_bounds = Aabb::new(na::point![0.0, 0.0], na::point![30.0, 30.0]);
+        let rect_key = insert_rect(&mut engine.store, rect_bounds);
+        engine.store.record(Instant::now());
+        let image_bounds = engine.store.bounds_for_strokes(&[image_key]).unwrap();
+
+        let widget_flags = engine.reset_images_to_original_size(&[image_key, rect_key]);
+        assert!(widget_flags.store_modified);
+        let reset_bounds = engine.store.bounds_for_strokes(&[image_key]).unwrap();
+        approx::assert_relative_eq!(reset_bounds.extents(), na::vector![40.0, 20.0]);
+        approx::assert_relative_eq!(reset_bounds.center(), image_bounds.center());
+        // Other strokes are not modified
+        let rect_stroke_bounds = engine.store.bounds_for_strokes(&[rect_key]).unwrap();
+        approx::assert_relative_eq!(rect_stroke_bounds.mins, rect_bounds.mins, epsilon = 2.0);
+
+        // Reset in a single step
+        let _ = engine.undo(Instant::now());
+        let undone_bounds = engine.store.bounds_for_strokes(&[image_key]).unwrap();
+        approx::assert_relative_eq!(undone_bounds.extents(), image_bounds.extents());
+
+        // Strokes other than images don't record history
+        assert_eq!(
+            engine.reset_images_to_original_size(&[rect_key]),
+            WidgetFlags::default()
+        );
+    }
+
+    #[test]
+    fn move_pages_with_strokes() {
+        let mut engine = Engine::default();
+        engine.document.layout = Layout::FixedSize;
+        let h = engine.document.format.height();
+        engine.document.height = 4.0 * h;
+        let insert_page_rect = |engine: &mut Engine, min_y: f64, max_y: f64| {
+            insert_rect(
+                &mut engine.store,
+                Aabb::new(na::point![50.0, min_y], na::point![150.0, max_y]),
+            )
+        };
+        let page_keys = (0..4)
+            .map(|idx| {
+                let page_top = idx as f64 * h;
+                insert_page_rect(&mut engine, page_top + 100.0, page_top + 200.0)
+            })
+            .collect::<Vec<StrokeKey>>();
+        // The larger share of this stroke is on the second page
+        let straddling_key = insert_page_rect(&mut engine, 2.0 * h - 100.0, 2.0 * h + 50.0);
+        engine.store.record(Instant::now());
+
+        let min_y = |engine: &Engine, key| engine.store.bounds_for_strokes(&[key]).unwrap().mins[1];
+        let start_ys = page_keys
+            .iter()
+            .map(|&key| min_y(&engine, key))
+            .collect::<Vec<f64>>();
+        let straddling_start_y = min_y(&engine, straddling_key);
+        // `order` contains the original page indices in their current order
+        let assert_page_order = |engine: &Engine, order: [usize; 4]| {
+            for (idx, original_idx) in order.into_iter().enumerate() {
+                let offset = (idx as f64 - original_idx as f64) * h;
+                approx::assert_relative_eq!(
+                    min_y(engine, page_keys[original_idx]),
+                    start_ys[original_idx] + offset,
+                    epsilon = 1e-6
+                );
+                if original_idx == 1 {
+                    approx::assert_relative_eq!(
+                        min_y(engine, straddling_key),
+                        straddling_start_y + offset,
+                        epsilon = 1e-6
+                    );
+                }
+            }
+        };
+
+        assert!(engine.move_page(1, 3).store_modified);
+        assert_page_order(&engine, [0, 2, 3, 1]);
+        assert!(engine.move_page(3, 0).store_modified);
+        assert_page_order(&engine, [1, 0, 2, 3]);
+        approx::assert_relative_eq!(engine.document.height, 4.0 * h);
+
+        // Invalid moves don't modify the document
+        assert_eq!(engine.move_page(2, 4), WidgetFlags::default());
+        assert_eq!(engine.move_page(2, 2), WidgetFlags::default());
+        assert_page_order(&engine, [1, 0, 2, 3]);
+
+        // Every move is a single history step
+        let _ = engine.undo(Instant::now());
+        assert_page_order(&engine, [0, 2, 3, 1]);
+        let _ = engine.undo(Instant::now());
+        assert_page_order(&engine, [0, 1, 2, 3]);
+
+        // Pages can't be moved in layouts without a fixed width
+        engine.document.layout = Layout::Infinite;
+        assert_eq!(engine.move_page(0, 1), WidgetFlags::default());
+    }
+
+    fn engine_with_document_meta() -> Engine {
+        let mut engine = Engine::default();
+        assert!(
+            engine
+                .set_document_title(String::from("Lecture Notes"))
+                .store_modified
+        );
+        assert!(
+            engine
+                .set_document_author(String::from("Ada"))
+                .store_modified
+        );
+        assert!(
+            engine
+                .set_document_tags(vec![
+                    String::from(" math "),
+                    String::from(""),
+                    String::from("notes"),
+                    String::from("math"),
+                ])
+                .store_modified
+        );
+        assert!(
+            !engine
+                .set_document_author(String::from("Ada"))
+                .store_modified
+        );
+        engine
+    }
+
+    #[test]
+    fn document_meta_survives_save_load() {
+        let mut engine = engine_with_document_meta();
+        assert_eq!(
+            engine.document_meta().tags,
+            vec![String::from("math"), String::from("notes")]
+        );
+        assert_eq!(engine.document_meta().created, 0);
+
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+        let meta = engine.document_meta().clone();
+        assert!(meta.created > 0);
+        assert_eq!(meta.created, meta.modified);
+
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).unwrap();
+        let mut loaded = Engine::default();
+        let _ = loaded.load_snapshot(snapshot);
+        assert_eq!(loaded.document_meta(), &meta);
+
+        // saving again keeps the creation time
+        engine.document_meta.modified = 0;
+        let _ = futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")));
+        assert_eq!(engine.document_meta().created, meta.created);
+        assert!(engine.document_meta().modified >= meta.created);
+    }
+
+    #[test]
+    fn snapshots_without_document_meta_load() {
+        let engine = engine_with_document_meta();
+        let mut value = serde_json::to_value(engine.take_snapshot()).unwrap();
+        value.as_object_mut().unwrap().remove("document_meta");
+        let snapshot = serde_json::from_value::<EngineSnapshot>(value).unwrap();
+        assert_eq!(snapshot.document_meta, DocumentMeta::default());
+    }
+
+    #[test]
+    fn bookmarks_survive_save_load() {
+        let mut engine = engine_w_selected_rect(Aabb::new(
+            na::point![100.0, 200.0],
+            na::point![300.0, 250.0],
+        ));
+        let selection_bounds = selection_bounds(&engine);
+        assert!(engine.bookmark_selection(String::from("")).is_err());
+        assert!(
+            engine
+                .bookmark_selection(String::from(" Intro "))
+                .unwrap()
+                .store_modified
+        );
+        assert!(engine.bookmark_selection(String::from("Intro")).is_err());
+        let _ = engine.bookmark_selection(String::from("Proof")).unwrap();
+        let _ = engine.bookmark_selection(String::from("Lemma")).unwrap();
+        assert!(engine
+            .rename_bookmark("Proof", String::from("Lemma"))
+            .is_err());
+        let _ = engine
+            .rename_bookmark("Proof", String::from("Theorem"))
+            .unwrap();
+        assert!(engine.delete_bookmark("Lemma").store_modified);
+        assert!(!engine.delete_bookmark("Lemma").store_modified);
+
+        // the bookmark is positional, removing the strokes keeps it
+        let selection = engine.store.selection_keys_as_rendered();
+        engine.store.set_trashed_keys(&selection, true);
+        assert_eq!(
+            engine
+                .bookmarks()
+                .iter()
+                .map(|bookmark| bookmark.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["Intro", "Theorem"]
+        );
+
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).unwrap();
+        let mut loaded = Engine::default();
+        let _ = loaded.load_snapshot(snapshot);
+        assert_eq!(loaded.bookmarks(), engine.bookmarks());
+        let bounds = loaded.bookmarks()[0].bounds();
+        approx::assert_relative_eq!(bounds.mins, selection_bounds.mins, epsilon = 1e-3);
+        approx::assert_relative_eq!(bounds.maxs, selection_bounds.maxs, epsilon = 1e-3);
+
+        // documents saved before bookmarks existed load without them
+        let mut value = serde_json::to_value(engine.take_snapshot()).unwrap();
+        value["document_meta"]
+            .as_object_mut()
+            .unwrap()
+            .remove("bookmarks");
+        let snapshot = serde_json::from_value::<EngineSnapshot>(value).unwrap();
+        assert!(snapshot.document_meta.bookmarks.is_empty());
+    }
+
+    #[test]
+    fn goto_bookmark_outside_document_expands() {
+        // partially outside of the document to the left and the bottom
+        let bookmark_bounds = Aabb::new(na::point![-200.0, 1400.0], na::point![600.0, 1800.0]);
+        let mut engine = engine_w_selected_rect(bookmark_bounds);
+        engine.document.layout = Layout::Infinite;
+        let _ = engine.camera_set_size(na::vector![1000.0, 800.0]);
+        let _ = engine.bookmark_selection(String::from("far")).unwrap();
+        let selection = engine.store.selection_keys_as_rendered();
+        engine.store.set_trashed_keys(&selection, true);
+        assert!(engine.document.bounds().intersects(&bookmark_bounds));
+        assert!(!engine.document.bounds().contains(&bookmark_bounds));
+
+        let widget_flags = engine.goto_bookmark("far");
+        assert!(widget_flags.view_modified);
+        let bounds = engine.bookmarks()[0].bounds();
+        // the padding is on both sides and the width is limiting
+        approx::assert_relative_eq!(
+            engine.camera.total_zoom(),
+            (1000.0 - 2.0 * Engine::ZOOM_TO_FIT_PADDING) / bounds.extents()[0],
+            epsilon = 1e-6
+        );
+        approx::assert_relative_eq!(
+            engine.camera.viewport().center(),
+            bounds.center(),
+            epsilon = 1e-6
+        );
+        assert!(engine.camera.viewport().contains(&bounds));
+        assert!(engine.document.bounds().contains(&engine.camera.viewport()));
+
+        assert_eq!(engine.goto_bookmark("missing"), WidgetFlags::default());
+    }
+
+    #[test]
+    fn pdf_export_embeds_document_meta() {
+        use crate::engine::export::{DocExportFormat, DocExportPrefs};
+
+        let engine = engine_with_document_meta();
+        let bytes = futures::executor::block_on(engine.export_doc(
+            String::from("fallback"),
+            Some(DocExportPrefs {
+                export_format: DocExportFormat::Pdf,
+                ..Default::default()
+            }),
+        ))
+        .unwrap()
+        .unwrap();
+
+        let doc =
+            poppler::Document::from_bytes(&glib::Bytes::from(bytes.as_slice()), None).unwrap();
+        assert_eq!(doc.title().unwrap().as_str(), "Lecture Notes");
+        assert_eq!(doc.author().unwrap().as_str(), "Ada");
+        assert_eq!(doc.keywords().unwrap().as_str(), "math, notes");
+    }
+
+    /// The value of the attribute of the svg root element.
+    fn svg_root_attribute(svg: &str, name: &str) -> String {
+        let root = &svg[svg.find("<svg").unwrap()..];
+        let root = &root[..root.find('>').unwrap()];
+        let pattern = format!(" {name}=\"");
+        let start = root.find(&pattern).unwrap() + pattern.len();
+        root[start..].split('"').next().unwrap().to_string()
+    }
+
+    fn export_selection_svg(engine: &Engine, physical_size: SelectionPhysicalSize) -> String {
+        let bytes =
+            futures::executor::block_on(engine.export_selection(Some(SelectionExportPrefs {
+                export_format: SelectionExportFormat::Svg,
+                margin: 0.0,
+                physical_size,
+                ..Default::default()
+            })))
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn selection_svg_export_physical_size() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![10.0, 20.0], na::point![106.0, 68.0]));
+        let extents = engine
+            .extract_selection_content()
+            .unwrap()
+            .bounds()
+            .unwrap()
+            .extents();
+
+        // without physical units the dimensions are in pixels
+        let svg = export_selection_svg(&engine, SelectionPhysicalSize::None);
+        assert_eq!(
+            svg_root_attribute(&svg, "width"),
+            format!("{:.3}", extents[0])
+        );
+
+        for dpi in [96.0, 300.0] {
+            engine.document.format.set_dpi(dpi);
+            let svg = export_selection_svg(&engine, SelectionPhysicalSize::TrueToScale);
+            assert_eq!(
+                svg_root_attribute(&svg, "width"),
+                format!("{:.3}mm", extents[0] / dpi * 25.4)
+            );
+            assert_eq!(
+                svg_root_attribute(&svg, "height"),
+                format!("{:.3}mm", extents[1] / dpi * 25.4)
+            );
+        }
+
+        // the content is wider than high, so the width limits the scale
+        let svg = export_selection_svg(
+            &engine,
+            SelectionPhysicalSize::FitMm {
+                width: 50.0,
+                height: 50.0,
+            },
+        );
+        assert_eq!(svg_root_attribute(&svg, "width"), "50.000mm");
+        assert_eq!(
+            svg_root_attribute(&svg, "height"),
+            format!("{:.3}mm", extents[1] * 50.0 / extents[0])
+        );
+        assert_eq!(svg_root_attribute(&svg, "viewBox").split(' ').count(), 4);
+    }
+
+    #[test]
+    fn selection_pdf_export_physical_size() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![10.0, 20.0], na::point![106.0, 68.0]));
+        engine.document.format.set_dpi(96.0);
+        let extents = engine
+            .extract_selection_content()
+            .unwrap()
+            .bounds()
+            .unwrap()
+            .extents();
+        let bytes =
+            futures::executor::block_on(engine.export_selection(Some(SelectionExportPrefs {
+                export_format: SelectionExportFormat::Pdf,
+                margin: 0.0,
+                physical_size: SelectionPhysicalSize::TrueToScale,
+                ..Default::default()
+            })))
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+
+        let doc =
+            poppler::Document::from_bytes(&glib::Bytes::from(bytes.as_slice()), None).unwrap();
+        assert_eq!(doc.n_pages(), 1);
+        let (width, height) = doc.page(0).unwrap().size();
+        // in points
+        approx::assert_relative_eq!(width, extents[0] / 96.0 * 72.0, epsilon = 1e-3);
+        approx::assert_relative_eq!(height, extents[1] / 96.0 * 72.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn selection_export_w_format_roundtrip() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![10.0, 20.0], na::point![106.0, 68.0]));
+        let export = |engine: &Engine, export_format: SelectionExportFormat| {
+            futures::executor::block_on(engine.export_selection_w_format(
+                export_format,
+                Some(SelectionExportPrefs {
+                    margin: 0.0,
+                    ..Default::default()
+                }),
+            ))
+        };
+
+        // the same as exporting with the format in the prefs
+        let svg = String::from_utf8(export(&engine, SelectionExportFormat::Svg).unwrap()).unwrap();
+        assert_eq!(
+            svg,
+            export_selection_svg(&engine, SelectionPhysicalSize::None)
+        );
+        assert!(export(&engine, SelectionExportFormat::Pdf)
+            .unwrap()
+            .starts_with(b"%PDF"));
+        for (export_format, image_format) in [
+            (SelectionExportFormat::Png, image::ImageFormat::Png),
+            (SelectionExportFormat::Jpeg, image::ImageFormat::Jpeg),
+        ] {
+            let bytes = export(&engine, export_format).unwrap();
+            let image = image::load_from_memory_with_format(&bytes, image_format).unwrap();
+            assert!(image.width() > image.height());
+            assert!(image.height() > 0);
+        }
+
+        let selection_keys = engine.store.selection_keys_unordered();
+        engine.store.set_selected_keys(&selection_keys, false);
+        assert!(export(&engine, SelectionExportFormat::Png).is_err());
+    }
+
+    fn insert_rect_and_record(engine: &mut Engine, x: f64) {
+        insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![x, 0.0], na::point![x + 10.0, 10.0]),
+        );
+        let _ = engine.record(Instant::now());
+    }
+
+    fn save(engine: &mut Engine) {
+        let state_marker = engine.state_marker();
+        let _ = futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+            .unwrap()
+            .unwrap();
+        engine.set_saved_state_marker(state_marker);
+    }
+
+    #[test]
+    fn undo_to_save_is_clean() {
+        let mut engine = Engine::default();
+        assert!(!engine.has_unsaved_changes());
+        insert_rect_and_record(&mut engine, 0.0);
+        save(&mut engine);
+        assert!(!engine.has_unsaved_changes());
+
+        insert_rect_and_record(&mut engine, 20.0);
+        insert_rect_and_record(&mut engine, 40.0);
+        assert!(engine.has_unsaved_changes());
+        assert_eq!(engine.changes_since_save(), 2);
+
+        let _ = engine.undo(Instant::now());
+        assert_eq!(engine.changes_since_save(), 1);
+        let _ = engine.undo(Instant::now());
+        assert!(!engine.has_unsaved_changes());
+        assert_eq!(engine.changes_since_save(), 0);
+
+        // undoing past the save point is dirty again
+        let _ = engine.undo(Instant::now());
+        assert!(engine.has_unsaved_changes());
+        assert_eq!(engine.changes_since_save(), 1);
+        let _ = engine.redo(Instant::now());
+        assert!(!engine.has_unsaved_changes());
+    }
+
+    #[test]
+    fn edit_after_undo_past_save_is_dirty() {
+        let mut engine = Engine::default();
+        insert_rect_and_record(&mut engine, 0.0);
+        insert_rect_and_record(&mut engine, 20.0);
+        save(&mut engine);
+
+        let _ = engine.undo(Instant::now());
+        assert_eq!(engine.changes_since_save(), 1);
+        // the saved state is no longer reachable through redo
+        insert_rect_and_record(&mut engine, 40.0);
+        assert!(engine.has_unsaved_changes());
+        assert_eq!(engine.changes_since_save(), 2);
+        let _ = engine.undo(Instant::now());
+        assert!(engine.has_unsaved_changes());
+        assert_eq!(engine.changes_since_save(), 1);
+
+        // changes that are not recorded yet are also unsaved
+        save(&mut engine);
+        assert!(!engine.has_unsaved_changes());
+        insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![60.0, 0.0], na::point![70.0, 10.0]),
+        );
+        assert!(engine.has_unsaved_changes());
+    }
+
+    #[test]
+    fn read_only_rejects_modifications() {
+        use rnote_compose::penevent::KeyboardKey;
+        use rnote_compose::penpath::Element;
+        use std::collections::HashSet;
+
+        let store_state = |engine: &Engine| {
+            serde_json::to_string(&engine.take_snapshot().stroke_components).unwrap()
+        };
+        let drag = |engine: &mut Engine, from: na::Vector2<f64>, to: na::Vector2<f64>| {
+            let now = Instant::now();
+            let mut widget_flags = WidgetFlags::default();
+            for event in [
+                PenEvent::down(Element::new(from, 0.5), HashSet::new()),
+                PenEvent::down(Element::new(to, 0.5), HashSet::new()),
+                PenEvent::up(Element::new(to, 0.5), HashSet::new()),
+            ] {
+                widget_flags |= engine.handle_pen_event(event, None, now).1;
+            }
+            widget_flags
+        };
+
+        let mut engine = Engine::default();
+        insert_rect_and_record(&mut engine, 100.0);
+        insert_rect_and_record(&mut engine, 200.0);
+        let widget_flags = engine.set_read_only(true);
+        assert!(engine.read_only());
+        assert_eq!(widget_flags.hide_undo, Some(true));
+        let state = store_state(&engine);
+
+        let _ = engine.change_pen_style(PenStyle::Brush);
+        let widget_flags = drag(
+            &mut engine,
+            na::vector![10.0, 10.0],
+            na::vector![50.0, 50.0],
+        );
+        assert!(widget_flags.read_only_rejected);
+        assert_eq!(store_state(&engine), state);
+
+        // Selecting is still possible, translating the selection is not
+        let _ = engine.select_all_strokes();
+        assert_eq!(engine.store.selection_keys_unordered().len(), 2);
+        let widget_flags = drag(
+            &mut engine,
+            na::vector![105.0, 5.0],
+            na::vector![155.0, 55.0],
+        );
+        assert!(widget_flags.read_only_rejected);
+        assert_eq!(store_state(&engine), state);
+        assert_eq!(engine.store.selection_keys_unordered().len(), 2);
+
+        // The view can still be moved
+        let _ = engine.change_pen_style(PenStyle::Brush);
+        let offset = engine.camera.offset();
+        let _ = engine.handle_pen_event(
+            PenEvent::KeyPressed {
+                keyboard_key: KeyboardKey::NavDown,
+                modifier_keys: HashSet::new(),
+            },
+            None,
+            Instant::now(),
+        );
+        assert!(engine.camera.offset()[1] > offset[1]);
+
+        let _ = engine.set_read_only(false);
+        let _ = engine.undo(Instant::now());
+        assert_ne!(store_state(&engine), state);
+    }
+
+    #[test]
+    fn import_rnote_file_as_strokes_round_trip() {
+        let mut source = Engine::default();
+        for rect in [
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+            Aabb::new(na::point![20.0, 0.0], na::point![30.0, 20.0]),
+        ] {
+            insert_rect(&mut source.store, rect);
+        }
+        let source_bounds = source
+            .store
+            .bounds_for_strokes(&source.store.stroke_keys_as_rendered())
+            .unwrap();
+        let bytes =
+            futures::executor::block_on(source.save_as_rnote_bytes(String::from("stencils.rnote")))
+                .unwrap()
+                .unwrap();
+
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![50.0, 50.0]));
+        let (imported, widget_flags) = engine
+            .import_rnote_file_as_strokes(&bytes, Some(na::vector![100.0, 200.0]))
+            .unwrap();
+        assert!(widget_flags.store_modified);
+        assert_eq!(imported.len(), 2);
+        assert_eq!(engine.store.stroke_keys_as_rendered().len(), 3);
+        assert_eq!(engine.store.selection_keys_as_rendered().len(), 2);
+        // the imported strokes keep their order, on top of the existing ones
+        assert_eq!(&engine.store.keys_sorted_chrono()[1..], imported.as_slice());
+        let bounds = selection_bounds(&engine);
+        approx::assert_relative_eq!(bounds.mins.coords, na::vector![100.0, 200.0]);
+        approx::assert_relative_eq!(bounds.extents(), source_bounds.extents());
+
+        // a single history step
+        let _ = engine.undo(Instant::now());
+        assert_eq!(engine.store.stroke_keys_as_rendered().len(), 1);
+
+        assert!(engine
+            .import_rnote_file_as_strokes(b"not an rnote file", None)
+            .is_err());
+    }
+
+    #[test]
+    fn trashed_strokes_restore_and_purge() {
+        let rect = Aabb::new(na::point![10.0, 20.0], na::point![60.0, 50.0]);
+        let mut engine = engine_w_selected_rect(rect);
+        let key = engine.store.selection_keys_as_rendered()[0];
+        let _ = engine.change_selection_stroke_colors(Color::RED);
+        let stroke_json = |engine: &Engine| {
+            serde_json::to_value(engine.store.get_stroke_ref(key).unwrap()).unwrap()
+        };
+        let original = stroke_json(&engine);
+        let original_bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+
+        let _ = engine.trash_selection();
+        let info = engine.trashed_strokes_info();
+        assert_eq!(info.len(), 1);
+        assert_eq!(info[0].key, key);
+        assert_eq!(info[0].kind, crate::strokes::StrokeKind::ShapeStroke);
+        assert_eq!(info[0].bounds, original_bounds);
+        assert!(info[0].trashed_at > 0);
+
+        // geometry and style are restored exactly
+        let widget_flags = engine.restore_trashed(&[key]);
+        assert!(widget_flags.store_modified);
+        assert!(engine.trashed_strokes_info().is_empty());
+        assert_eq!(engine.store.stroke_keys_as_rendered(), vec![key]);
+        assert_eq!(stroke_json(&engine), original);
+        assert_eq!(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            original_bounds
+        );
+        // restoring is undoable
+        let _ = engine.undo(Instant::now());
+        assert_eq!(engine.trashed_strokes_info().len(), 1);
+        let _ = engine.redo(Instant::now());
+        assert!(engine.trashed_strokes_info().is_empty());
+
+        engine.store.set_selected(key, true);
+        let _ = engine.trash_selection();
+        // only strokes trashed longer ago are purged
+        let _ = engine.purge_trashed(Some(Duration::from_secs(3600)));
+        assert_eq!(engine.trashed_strokes_info().len(), 1);
+        let _ = engine.purge_trashed(None);
+        assert!(engine.trashed_strokes_info().is_empty());
+        assert!(engine.store.get_stroke_ref(key).is_none());
+        assert!(engine.store.trashed(key).is_none());
+    }
+
+    #[test]
+    fn preset_applied_and_persisted_with_engine_config() {
+        let mut engine = Engine::default();
+        let _ = engine.change_pen_style(PenStyle::Shaper);
+        engine
+            .pens_config
+            .shaper_config
+            .smooth_options
+            .set_stroke_width(7.0);
+        engine
+            .save_preset(String::from("dashed blue shaper"))
+            .unwrap();
+
+        let _ = engine.change_pen_style(PenStyle::Brush);
+        engine
+            .pens_config
+            .shaper_config
+            .smooth_options
+            .set_stroke_width(1.0);
+        let widget_flags = engine.apply_preset_at(0).unwrap();
+        assert!(widget_flags.refresh_ui);
+        assert_eq!(
+            engine.penholder.current_pen_style_w_override(),
+            PenStyle::Shaper
+        );
+        approx::assert_relative_eq!(
+            engine
+                .pens_config
+                .shaper_config
+                .smooth_options
+                .stroke_width(),
+            7.0
+        );
+        assert!(engine.apply_preset_at(1).is_err());
+
+        let config = engine.export_engine_config_as_json().unwrap();
+        let mut loaded = Engine::default();
+        let _ = loaded
+            .import_engine_config_from_json(&config, None)
+            .unwrap();
+        assert_eq!(loaded.list_presets(), ["dashed blue shaper"]);
+        assert!(loaded.delete_preset("dashed blue shaper"));
+        assert!(loaded.list_presets().is_empty());
+    }
+
+    #[test]
+    fn selection_style_summary_reported_on_select() {
+        let insert_colored_rect = |engine: &mut Engine, x: f64, stroke_color: Color| {
+            let mut smooth_options = SmoothOptions {
+                stroke_color: Some(stroke_color),
+                ..Default::default()
+            };
+            smooth_options.set_stroke_width(3.0);
+            smooth_options.set_fill_color(Some(Color::WHITE));
+            engine.store.insert_stroke(
+                Stroke::ShapeStroke(ShapeStroke::new(
+                    Shape::Rectangle(Rectangle::from_p2d_aabb(Aabb::new(
+                        na::point![x, 0.0],
+                        na::point![x + 10.0, 10.0],
+                    ))),
+                    Style::Smooth(smooth_options),
+                )),
+                None,
+            );
+            engine.store.record(Instant::now());
+        };
+
+        let mut engine = Engine::default();
+        insert_colored_rect(&mut engine, 0.0, Color::RED);
+        insert_colored_rect(&mut engine, 20.0, Color::RED);
+        assert!(engine.selection_style_summary().is_none());
+
+        let widget_flags = engine.select_all_strokes();
+        assert!(widget_flags.selection_changed);
+        assert_eq!(
+            engine.selection_style_summary(),
+            Some(SelectionStyleSummary {
+                stroke_color: Some(Color::RED),
+                fill_color: Some(Color::WHITE),
+                stroke_width: Some(3.0),
+                mixed: false,
+            })
+        );
+
+        insert_colored_rect(&mut engine, 40.0, Color::BLACK);
+        let widget_flags = engine.select_all_strokes();
+        assert!(widget_flags.selection_changed);
+        let summary = engine.selection_style_summary().unwrap();
+        assert_eq!(summary.stroke_color, None);
+        assert_eq!(summary.fill_color, Some(Color::WHITE));
+        assert_eq!(summary.stroke_width, Some(3.0));
+        assert!(summary.mixed);
+    }
+
+    #[test]
+    fn annotate_selection_measurements_inserts_labels() {
+        use crate::document::format::MeasureUnit;
+        use rnote_compose::shapes::Line;
+
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]));
+        let line_key = engine.store.insert_stroke(
+            Stroke::ShapeStroke(ShapeStroke::new(
+                Shape::Line(Line::new(na::vector![0.0, 100.0], na::vector![30.0, 140.0])),
+                Style::default(),
+            )),
+            None,
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(line_key, true);
+        engine.pens_config.shaper_config.measurement_unit = MeasureUnit::Px;
+        engine.pens_config.shaper_config.measurement_format = String::from("{value} {unit}");
+
+        let widget_flags = engine.annotate_selection_measurements();
+        assert!(widget_flags.store_modified);
+        assert!(widget_flags.selection_changed);
+        assert_eq!(
+            engine.penholder.current_pen_style_w_override(),
+            PenStyle::Selector
+        );
+
+        let mut labels = engine
+            .store
+            .selection_keys_as_rendered()
+            .into_iter()
+            .filter_map(|key| match engine.store.get_stroke_ref(key) {
+                Some(Stroke::TextStroke(textstroke)) => Some(textstroke.text.clone()),
+                _ => None,
+            })
+            .collect::<Vec<String>>();
+        labels.sort();
+        assert_eq!(labels, ["100.0 × 50.0 px", "50.0 px"]);
+        // the measured strokes stay selected together with the labels
+        assert_eq!(engine.store.selection_keys_unordered().len(), 4);
+
+        let _ = engine.undo(Instant::now());
+        assert_eq!(engine.store.stroke_keys_unordered().len(), 2);
+    }
+
+    #[test]
+    fn change_selection_style_single_history_step() {
+        use crate::store::StrokeWidthUpdate;
+
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]));
+        let rect_key = engine.store.selection_keys_unordered()[0];
+        let text_key = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("label"),
+                na::vector![0.0, 20.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+        engine.store.set_selected(text_key, true);
+        insert_rect_and_record(&mut engine, 50.0);
+        let n_strokes = engine.store.stroke_keys_unordered().len();
+        let stroke_width = engine
+            .store
+            .get_stroke_ref(rect_key)
+            .and_then(|stroke| stroke.stroke_width())
+            .unwrap();
+        let unselected_strokes = |engine: &Engine| {
+            engine
+                .store
+                .stroke_keys_unordered()
+                .into_iter()
+                .filter(|&key| engine.store.selected(key) == Some(false))
+                .map(|key| {
+                    serde_json::to_string(engine.store.get_stroke_ref(key).unwrap()).unwrap()
+                })
+                .collect::<Vec<String>>()
+        };
+        let unselected = unselected_strokes(&engine);
+
+        let (mut modified, widget_flags) = engine.change_selection_style(StyleUpdate {
+            stroke_color: Some(Color::RED),
+            fill_color: None,
+            stroke_width: Some(StrokeWidthUpdate::Multiply(2.0)),
+        });
+        modified.sort();
+        let mut expected = vec![rect_key, text_key];
+        expected.sort();
+        assert_eq!(modified, expected);
+        assert!(widget_flags.store_modified);
+        let Some(Stroke::ShapeStroke(rect)) = engine.store.get_stroke_ref(rect_key) else {
+            panic!("rectangle stroke is missing");
+        };
+        assert_eq!(rect.style.stroke_color(), Some(Color::RED));
+        approx::assert_relative_eq!(rect.style.stroke_width(), stroke_width * 2.0);
+        let Some(Stroke::TextStroke(text)) = engine.store.get_stroke_ref(text_key) else {
+            panic!("text stroke is missing");
+        };
+        assert_eq!(text.text_style.color, Color::RED);
+        assert_eq!(unselected_strokes(&engine), unselected);
+
+        // nothing to update, so no history step is recorded
+        let (modified, _) = engine.change_selection_style(StyleUpdate {
+            stroke_color: Some(Color::RED),
+            ..Default::default()
+        });
+        assert!(modified.is_empty());
+
+        let _ = engine.undo(Instant::now());
+        let Some(Stroke::ShapeStroke(rect)) = engine.store.get_stroke_ref(rect_key) else {
+            panic!("rectangle stroke is missing");
+        };
+        assert_ne!(rect.style.stroke_color(), Some(Color::RED));
+        approx::assert_relative_eq!(rect.style.stroke_width(), stroke_width);
+        assert_eq!(engine.store.stroke_keys_unordered().len(), n_strokes);
+    }
+}
@@ -0,0 +1,364 @@
+// Imports
+use super::{EngineTask, EngineTaskSender};
+use futures::channel::mpsc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Identifies a long-running operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OperationId(u64);
+
+/// An event of a long-running operation, received through [super::Engine::subscribe_operation_events].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperationEvent {
+    /// The operation has progressed. Sent with zero steps done when the operation starts,
+    /// and with strictly increasing steps done after that.
+    Progress {
+        operation_id: OperationId,
+        done: usize,
+        total: usize,
+    },
+    /// The operation has finished.
+    Finished { operation_id: OperationId },
+    /// The operation has failed.
+    Failed {
+        operation_id: OperationId,
+        error: String,
+    },
+    /// The operation was cancelled. No further events are sent for it.
+    Cancelled { operation_id: OperationId },
+}
+
+impl OperationEvent {
+    pub fn operation_id(&self) -> OperationId {
+        match self {
+            Self::Progress { operation_id, .. }
+            | Self::Finished { operation_id }
+            | Self::Failed { operation_id, .. }
+            | Self::Cancelled { operation_id } => *operation_id,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OperationEntry {
+    cancelled: Arc<AtomicBool>,
+    /// The steps done that were last reported to the subscribers.
+    reported_done: usize,
+}
+
+/// Keeps track of the running operations, shared by all clones of the [EngineTaskSender].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Operations {
+    next_id: Arc<AtomicU64>,
+    entries: Arc<Mutex<HashMap<OperationId, OperationEntry>>>,
+}
+
+impl Operations {
+    fn start(&self) -> (OperationId, Arc<AtomicBool>) {
+        let operation_id = OperationId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.lock_entries().insert(
+            operation_id,
+            OperationEntry {
+                cancelled: Arc::clone(&cancelled),
+                reported_done: 0,
+            },
+        );
+        (operation_id, cancelled)
+    }
+
+    /// Flag the operation as cancelled. Returns false when it is not running.
+    pub(crate) fn cancel(&self, operation_id: OperationId) -> bool {
+        let Some(entry) = self.lock_entries().remove(&operation_id) else {
+            return false;
+        };
+        entry.cancelled.store(true, Ordering::Release);
+        true
+    }
+
+    /// Convert a task of an operation into the event for the subscribers.
+    ///
+    /// Returns None for tasks of operations that are not running anymore and for outdated progress.
+    pub(crate) fn event_for_task(&self, task: EngineTask) -> Option<OperationEvent> {
+        let mut entries = self.lock_entries();
+        match task {
+            EngineTask::OperationProgress {
+                operation_id,
+                done,
+                total,
+            } => {
+                let entry = entries.get_mut(&operation_id)?;
+                // Steps can finish concurrently, so their progress may arrive out of order
+                if done < entry.reported_done || (done == entry.reported_done && done > 0) {
+                    return None;
+                }
+                entry.reported_done = done;
+                Some(OperationEvent::Progress {
+                    operation_id,
+                    done,
+                    total,
+                })
+            }
+            EngineTask::OperationFinished { operation_id } => {
+                entries.remove(&operation_id)?;
+                Some(OperationEvent::Finished { operation_id })
+            }
+            EngineTask::OperationFailed {
+                operation_id,
+                error,
+            } => {
+                entries.remove(&operation_id)?;
+                Some(OperationEvent::Failed {
+                    operation_id,
+                    error,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn lock_entries(&self) -> std::sync::MutexGuard<'_, HashMap<OperationId, OperationEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl EngineTaskSender {
+    /// Start a long-running operation with the given number of steps.
+    ///
+    /// The returned operation can be cloned and moved to the threads that do the work.
+    /// It is finished once all clones are dropped, unless it failed or was cancelled.
+    pub(crate) fn start_operation(&self, total: usize) -> Operation {
+        let (operation_id, cancelled) = self.operations.start();
+        self.send(EngineTask::OperationProgress {
+            operation_id,
+            done: 0,
+            total,
+        });
+        Operation(Arc::new(OperationInner {
+            operation_id,
+            total,
+            done: AtomicUsize::new(0),
+            failed: AtomicBool::new(false),
+            cancelled,
+            tasks_tx: self.clone(),
+        }))
+    }
+}
+
+/// A handle to a long-running operation, reporting its progress through the engine task channel.
+#[derive(Debug, Clone)]
+pub(crate) struct Operation(Arc<OperationInner>);
+
+#[derive(Debug)]
+struct OperationInner {
+    operation_id: OperationId,
+    total: usize,
+    done: AtomicUsize,
+    failed: AtomicBool,
+    cancelled: Arc<AtomicBool>,
+    tasks_tx: EngineTaskSender,
+}
+
+impl Operation {
+    pub(crate) fn id(&self) -> OperationId {
+        self.0.operation_id
+    }
+
+    /// Whether the operation was cancelled. The work should then be stopped as soon as possible.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Report that a step of the operation is done.
+    pub(crate) fn step_done(&self) {
+        let done = self.0.done.fetch_add(1, Ordering::AcqRel) + 1;
+        if self.is_cancelled() {
+            return;
+        }
+        self.0.tasks_tx.send(EngineTask::OperationProgress {
+            operation_id: self.0.operation_id,
+            done: done.min(self.0.total),
+            total: self.0.total,
+        });
+    }
+
+    /// Report that the operation has failed. The remaining steps are still expected to finish or to be stopped.
+    pub(crate) fn fail(&self, error: String) {
+        if self.0.failed.swap(true, Ordering::AcqRel) || self.is_cancelled() {
+            return;
+        }
+        self.0.tasks_tx.send(EngineTask::OperationFailed {
+            operation_id: self.0.operation_id,
+            error,
+        });
+    }
+}
+
+impl Drop for OperationInner {
+    fn drop(&mut self) {
+        if self.failed.load(Ordering::Acquire) || self.cancelled.load(Ordering::Acquire) {
+            return;
+        }
+        self.tasks_tx.send(EngineTask::OperationFinished {
+            operation_id: self.operation_id,
+        });
+    }
+}
+
+/// The subscribers to the events of long-running operations.
+#[derive(Debug, Default)]
+pub(crate) struct OperationSubscribers(Vec<mpsc::UnboundedSender<OperationEvent>>);
+
+impl OperationSubscribers {
+    pub(crate) fn subscribe(&mut self) -> mpsc::UnboundedReceiver<OperationEvent> {
+        let (tx, rx) = mpsc::unbounded();
+        self.0.push(tx);
+        rx
+    }
+
+    /// Send the event to all subscribers, dropping the ones that have gone away.
+    pub(crate) fn publish(&mut self, event: OperationEvent) {
+        self.0
+            .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineTaskReceiver;
+    use crate::Engine;
+
+    /// A fake long operation that does one step for every permit, until it is cancelled.
+    ///
+    /// Returns its id, the permit sender, the receiver of step acknowledgements and the worker, joining to the number of steps done.
+    fn spawn_fake_operation(
+        engine: &Engine,
+        total: usize,
+    ) -> (
+        OperationId,
+        std::sync::mpsc::Sender<()>,
+        std::sync::mpsc::Receiver<()>,
+        std::thread::JoinHandle<usize>,
+    ) {
+        let operation = engine.engine_tasks_tx().start_operation(total);
+        let operation_id = operation.id();
+        let (permit_tx, permit_rx) = std::sync::mpsc::channel::<()>();
+        let (ack_tx, ack_rx) = std::sync::mpsc::channel::<()>();
+        let worker = std::thread::spawn(move || {
+            let mut steps = 0;
+            while permit_rx.recv().is_ok() {
+                if operation.is_cancelled() {
+                    break;
+                }
+                operation.step_done();
+                steps += 1;
+                let _ = ack_tx.send(());
+            }
+            steps
+        });
+        (operation_id, permit_tx, ack_rx, worker)
+    }
+
+    fn handle_pending_tasks(engine: &mut Engine, tasks_rx: &mut EngineTaskReceiver) {
+        while let Ok(Some(task)) = tasks_rx.0.try_next() {
+            let _ = engine.handle_engine_task(task);
+        }
+    }
+
+    fn progress(operation_id: OperationId, done: usize, total: usize) -> OperationEvent {
+        OperationEvent::Progress {
+            operation_id,
+            done,
+            total,
+        }
+    }
+
+    #[test]
+    fn operation_progress_and_cancellation() {
+        let mut engine = Engine::default();
+        let mut tasks_rx = engine.take_engine_tasks_rx().unwrap();
+        let mut events = engine.subscribe_operation_events();
+        let mut received_events =
+            || std::iter::from_fn(|| events.try_next().ok().flatten()).collect::<Vec<_>>();
+
+        // Progress is reported in order and followed by the completion
+        let (operation_id, permit_tx, _ack_rx, worker) = spawn_fake_operation(&engine, 3);
+        for _ in 0..3 {
+            permit_tx.send(()).unwrap();
+        }
+        drop(permit_tx);
+        assert_eq!(worker.join().unwrap(), 3);
+        handle_pending_tasks(&mut engine, &mut tasks_rx);
+        assert_eq!(
+            received_events(),
+            vec![
+                progress(operation_id, 0, 3),
+                progress(operation_id, 1, 3),
+                progress(operation_id, 2, 3),
+                progress(operation_id, 3, 3),
+                OperationEvent::Finished { operation_id }
+            ]
+        );
+        assert!(!engine.cancel_operation(operation_id));
+
+        // Cancelling mid-way stops the operation and no completion is reported
+        let (operation_id, permit_tx, ack_rx, worker) = spawn_fake_operation(&engine, 10);
+        for _ in 0..4 {
+            permit_tx.send(()).unwrap();
+            ack_rx.recv().unwrap();
+        }
+        handle_pending_tasks(&mut engine, &mut tasks_rx);
+        assert!(engine.cancel_operation(operation_id));
+        for _ in 0..6 {
+            let _ = permit_tx.send(());
+        }
+        drop(permit_tx);
+        assert_eq!(worker.join().unwrap(), 4);
+        handle_pending_tasks(&mut engine, &mut tasks_rx);
+        assert_eq!(
+            received_events(),
+            vec![
+                progress(operation_id, 0, 10),
+                progress(operation_id, 1, 10),
+                progress(operation_id, 2, 10),
+                progress(operation_id, 3, 10),
+                progress(operation_id, 4, 10),
+                OperationEvent::Cancelled { operation_id }
+            ]
+        );
+        assert!(!engine.cancel_operation(operation_id));
+    }
+
+    #[test]
+    fn outdated_progress_is_dropped() {
+        let engine = Engine::default();
+        let operations = &engine.tasks_tx.operations;
+        let operation = engine.engine_tasks_tx().start_operation(4);
+        let operation_id = operation.id();
+        let task = |done| EngineTask::OperationProgress {
+            operation_id,
+            done,
+            total: 4,
+        };
+
+        assert_eq!(
+            operations.event_for_task(task(0)),
+            Some(progress(operation_id, 0, 4))
+        );
+        assert_eq!(
+            operations.event_for_task(task(2)),
+            Some(progress(operation_id, 2, 4))
+        );
+        // steps that finished concurrently can report their progress late
+        assert_eq!(operations.event_for_task(task(1)), None);
+        assert_eq!(operations.event_for_task(task(2)), None);
+        assert_eq!(
+            operations.event_for_task(EngineTask::OperationFinished { operation_id }),
+            Some(OperationEvent::Finished { operation_id })
+        );
+        assert_eq!(operations.event_for_task(task(3)), None);
+    }
+}
@@ -0,0 +1,499 @@
+// Imports
+use super::{Engine, EngineTask};
+use crate::document::{Layout, PdfUnderlay};
+use crate::store::render_comp::RENDER_IMAGE_SCALE_TOLERANCE;
+use crate::{render, WidgetFlags};
+use rnote_compose::ext::Vector2Ext;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::error;
+
+/// Identifies a rendering of a page of the underlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PageRenderKey {
+    /// The index of the page placement.
+    page: usize,
+    /// The image scale, in steps of [RENDER_IMAGE_SCALE_TOLERANCE].
+    scale: u32,
+}
+
+impl PageRenderKey {
+    fn new(page: usize, image_scale: f64) -> Self {
+        Self {
+            page,
+            scale: (image_scale / RENDER_IMAGE_SCALE_TOLERANCE).round() as u32,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PdfUnderlayCacheEntry {
+    key: PageRenderKey,
+    image: render::Image,
+    #[cfg(feature = "ui")]
+    rendernode: gtk4::gsk::RenderNode,
+}
+
+/// The rendered pages of the Pdf underlay, evicting the least recently used ones.
+#[derive(Debug, Default)]
+pub(crate) struct PdfUnderlayCache {
+    /// The underlay the pages were rendered from.
+    underlay: Option<Arc<PdfUnderlay>>,
+    /// Ordered from the least to the most recently used.
+    entries: VecDeque<PdfUnderlayCacheEntry>,
+    /// The pages that are currently rendered in a task.
+    pending: HashSet<PageRenderKey>,
+}
+
+impl PdfUnderlayCache {
+    /// The maximum number of rendered pages that are kept.
+    pub(crate) const CAPACITY: usize = 12;
+
+    fn is_for(&self, underlay: &Arc<PdfUnderlay>) -> bool {
+        self.underlay
+            .as_ref()
+            .is_some_and(|cache_underlay| Arc::ptr_eq(cache_underlay, underlay))
+    }
+
+    /// Drop the rendered pages when the underlay was replaced.
+    fn sync_underlay(&mut self, underlay: Option<&Arc<PdfUnderlay>>) {
+        let unchanged = match (&self.underlay, underlay) {
+            (Some(cache_underlay), Some(underlay)) => Arc::ptr_eq(cache_underlay, underlay),
+            (None, None) => true,
+            _ => false,
+        };
+        if !unchanged {
+            *self = Self {
+                underlay: underlay.cloned(),
+                ..Default::default()
+            };
+        }
+    }
+
+    /// Mark the rendering as most recently used. Returns false when it is not cached.
+    fn touch(&mut self, key: PageRenderKey) -> bool {
+        let Some(i) = self.entries.iter().position(|entry| entry.key == key) else {
+            return false;
+        };
+        if let Some(entry) = self.entries.remove(i) {
+            self.entries.push_back(entry);
+        }
+        true
+    }
+
+    fn insert(&mut self, entry: PdfUnderlayCacheEntry) {
+        self.entries.retain(|e| e.key != entry.key);
+        self.entries.push_back(entry);
+        while self.entries.len() > Self::CAPACITY {
+            self.entries.pop_front();
+        }
+    }
+
+    /// The rendering of the page with the key, or else the most recently used one of the page.
+    ///
+    /// A rendering with another image scale is drawn scaled until the current one is available.
+    #[cfg(feature = "ui")]
+    fn entry_for_page(&self, key: PageRenderKey) -> Option<&PdfUnderlayCacheEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.key.page == key.page)
+            })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn contains(&self, key: PageRenderKey) -> bool {
+        self.entries.iter().any(|entry| entry.key == key)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn n_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// The size of the data of the rendered pages, in bytes.
+    pub(crate) fn images_size(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.image.data.len())
+            .sum()
+    }
+}
+
+impl Engine {
+    /// Set the Pdf underlay of the document from the Pdf bytes, replacing the current one and recording it in the history.
+    ///
+    /// The pages are placed according to the Pdf import preferences.
+    pub fn set_pdf_underlay(&mut self, bytes: &[u8]) -> anyhow::Result<WidgetFlags> {
+        let mut widget_flags = WidgetFlags::default();
+        let pdf_import_prefs = self.import_prefs.pdf_import_prefs;
+        let pdf_underlay = PdfUnderlay::from_pdf_bytes(
+            bytes,
+            pdf_import_prefs,
+            na::Vector2::zeros(),
+            &self.document.format,
+        )?;
+
+        if pdf_import_prefs.adjust_document {
+            let max_size = pdf_underlay
+                .pages
+                .iter()
+                .map(|page| page.size)
+                .fold(na::Vector2::<f64>::zeros(), |acc, size| acc.maxs(&size));
+            self.document.format.set_width(max_size[0]);
+            self.document.format.set_height(max_size[1]);
+            widget_flags |= self.set_doc_layout(Layout::FixedSize);
+        }
+        let pdf_underlay = Arc::new(pdf_underlay);
+        self.document.pdf_underlay = Some(Arc::clone(&pdf_underlay));
+        self.store.set_pdf_underlay(Some(pdf_underlay));
+
+        widget_flags |= self.doc_resize_to_fit_content()
+            | self.record(Instant::now())
+            | self.background_rendering_regenerate();
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.refresh_ui = true;
+        Ok(widget_flags)
+    }
+
+    /// Remove the Pdf underlay of the document, recording it in the history.
+    pub fn clear_pdf_underlay(&mut self) -> WidgetFlags {
+        if self.document.pdf_underlay.take().is_none() {
+            return WidgetFlags::default();
+        }
+        self.store.set_pdf_underlay(None);
+
+        let mut widget_flags = self.doc_resize_to_fit_content()
+            | self.record(Instant::now())
+            | self.update_pdf_underlay_rendering_current_viewport();
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.refresh_ui = true;
+        widget_flags
+    }
+
+    /// Take over the Pdf underlay from the store after navigating the history.
+    pub(crate) fn update_pdf_underlay_after_history_change(&mut self) -> WidgetFlags {
+        let pdf_underlay = self.store.pdf_underlay().cloned();
+        let unchanged = match (&self.document.pdf_underlay, &pdf_underlay) {
+            (Some(underlay), Some(history_underlay)) => Arc::ptr_eq(underlay, history_underlay),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return WidgetFlags::default();
+        }
+        self.document.pdf_underlay = pdf_underlay;
+        // The pages are rendered by the viewport update that follows
+        let mut widget_flags = WidgetFlags::default();
+        widget_flags.resize = true;
+        widget_flags.refresh_ui = true;
+        widget_flags
+    }
+
+    /// Spawn render tasks for the pages of the Pdf underlay in the viewport
+    /// that are not yet rendered with the current image scale.
+    pub(crate) fn update_pdf_underlay_rendering_current_viewport(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let pdf_underlay = self.document.pdf_underlay.clone();
+        self.pdf_underlay_cache.sync_underlay(pdf_underlay.as_ref());
+        let Some(pdf_underlay) = pdf_underlay else {
+            return widget_flags;
+        };
+        let image_scale = self.camera.image_scale();
+
+        for page in pdf_underlay.pages_intersecting(self.camera.viewport()) {
+            let key = PageRenderKey::new(page, image_scale);
+            if self.pdf_underlay_cache.touch(key) || !self.pdf_underlay_cache.pending.insert(key) {
+                continue;
+            }
+            let tasks_tx = self.engine_tasks_tx();
+            let pdf_underlay = Arc::clone(&pdf_underlay);
+            rayon::spawn(move || {
+                let image = match pdf_underlay.gen_page_image(page, image_scale) {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        error!("Rendering page {page} of the Pdf underlay failed, Err: {e:?}");
+                        None
+                    }
+                };
+                tasks_tx.send(EngineTask::UpdatePdfUnderlayPage {
+                    pdf_underlay,
+                    page,
+                    image_scale,
+                    image,
+                });
+            });
+        }
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    /// Take over the rendered page of the Pdf underlay.
+    ///
+    /// Renderings of an underlay that was replaced in the meantime are discarded.
+    pub(crate) fn handle_pdf_underlay_page_rendered(
+        &mut self,
+        pdf_underlay: Arc<PdfUnderlay>,
+        page: usize,
+        image_scale: f64,
+        image: Option<render::Image>,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if !self.pdf_underlay_cache.is_for(&pdf_underlay) {
+            return widget_flags;
+        }
+        let key = PageRenderKey::new(page, image_scale);
+        self.pdf_underlay_cache.pending.remove(&key);
+        let Some(image) = image else {
+            // Retried when the viewport is updated the next time
+            return widget_flags;
+        };
+        #[cfg(feature = "ui")]
+        let rendernode = match image.to_rendernode() {
+            Ok(rendernode) => rendernode,
+            Err(e) => {
+                error!("Generating rendernode of Pdf underlay page {page} failed, Err: {e:?}");
+                return widget_flags;
+            }
+        };
+        self.pdf_underlay_cache.insert(PdfUnderlayCacheEntry {
+            key,
+            image,
+            #[cfg(feature = "ui")]
+            rendernode,
+        });
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    /// Draw the rendered pages of the Pdf underlay in the viewport.
+    #[cfg(feature = "ui")]
+    pub(crate) fn draw_pdf_underlay_to_gtk_snapshot(&self, snapshot: &gtk4::Snapshot) {
+        use crate::ext::GrapheneRectExt;
+        use gtk4::{graphene, prelude::*};
+
+        let Some(pdf_underlay) = &self.document.pdf_underlay else {
+            return;
+        };
+        if !self.pdf_underlay_cache.is_for(pdf_underlay) {
+            return;
+        }
+        let image_scale = self.camera.image_scale();
+
+        snapshot.push_clip(&graphene::Rect::from_p2d_aabb(self.document.bounds()));
+        for page in pdf_underlay.pages_intersecting(self.camera.viewport()) {
+            if let Some(entry) = self
+                .pdf_underlay_cache
+                .entry_for_page(PageRenderKey::new(page, image_scale))
+            {
+                snapshot.append_node(&entry.rendernode);
+            }
+        }
+        snapshot.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::import::PdfImportPageSpacing;
+    use crate::engine::EngineTaskReceiver;
+
+    /// A Pdf with the number of pages in A4 size.
+    fn pdf_bytes(n_pages: usize) -> Vec<u8> {
+        let surface = cairo::PdfSurface::for_stream(595.0, 842.0, Vec::<u8>::new()).unwrap();
+        {
+            let cx = cairo::Context::new(&surface).unwrap();
+            for i in 0..n_pages {
+                cx.set_source_rgb(0.0, 0.0, 1.0);
+                cx.rectangle(50.0, 50.0 + i as f64 * 10.0, 200.0, 100.0);
+                cx.fill().unwrap();
+                cx.show_page().unwrap();
+            }
+        }
+        *surface
+            .finish_output_stream()
+            .unwrap()
+            .downcast::<Vec<u8>>()
+            .unwrap()
+    }
+
+    /// Handle the render tasks until none is pending anymore.
+    fn handle_pending_render_tasks(engine: &mut Engine, tasks_rx: &mut EngineTaskReceiver) {
+        while engine.pdf_underlay_cache.n_pending() > 0 {
+            let task = futures::executor::block_on(tasks_rx.recv()).unwrap();
+            let _ = engine.handle_engine_task(task);
+        }
+    }
+
+    fn engine_with_underlay(n_pages: usize) -> (Engine, EngineTaskReceiver) {
+        let mut engine = Engine::default();
+        let tasks_rx = engine.take_engine_tasks_rx().unwrap();
+        engine.import_prefs.pdf_import_prefs.page_spacing =
+            PdfImportPageSpacing::OnePerDocumentPage;
+        engine.import_prefs.pdf_import_prefs.page_width_perc = 100.0;
+        let _ = engine.camera_set_size(na::vector![800.0, 600.0]);
+        let _ = engine.set_pdf_underlay(&pdf_bytes(n_pages)).unwrap();
+        (engine, tasks_rx)
+    }
+
+    #[test]
+    fn pdf_underlay_pages_rendered_lazily() {
+        let (mut engine, mut tasks_rx) = engine_with_underlay(20);
+        assert_eq!(
+            engine.document.pdf_underlay.as_ref().unwrap().pages.len(),
+            20
+        );
+        // the document grows to contain all pages
+        assert!(engine.document.bounds().maxs[1] >= engine.document.format.height() * 20.0);
+
+        handle_pending_render_tasks(&mut engine, &mut tasks_rx);
+        let image_scale = engine.camera.image_scale();
+        let visible = engine
+            .document
+            .pdf_underlay
+            .as_ref()
+            .unwrap()
+            .pages_intersecting(engine.camera.viewport());
+        assert!(!visible.is_empty() && visible.len() < 20);
+        // only the pages in the viewport are rendered
+        assert_eq!(engine.pdf_underlay_cache.len(), visible.len());
+        assert!(visible.iter().all(|&page| engine
+            .pdf_underlay_cache
+            .contains(PageRenderKey::new(page, image_scale))));
+
+        // visible pages that are already rendered are not rendered again
+        let _ = engine.update_rendering_current_viewport();
+        assert_eq!(engine.pdf_underlay_cache.n_pending(), 0);
+
+        // zooming renders the visible pages again with the new image scale
+        let _ = engine.camera.zoom_to(2.0);
+        let _ = engine.update_rendering_current_viewport();
+        assert!(engine.pdf_underlay_cache.n_pending() > 0);
+        handle_pending_render_tasks(&mut engine, &mut tasks_rx);
+        assert!(engine
+            .pdf_underlay_cache
+            .contains(PageRenderKey::new(visible[0], engine.camera.image_scale())));
+    }
+
+    #[test]
+    fn pdf_underlay_cache_evicts_least_recently_used() {
+        let (mut engine, mut tasks_rx) = engine_with_underlay(30);
+        handle_pending_render_tasks(&mut engine, &mut tasks_rx);
+        let image_scale = engine.camera.image_scale();
+        let page_height = engine.document.format.height();
+        let first_key = PageRenderKey::new(0, image_scale);
+        assert!(engine.pdf_underlay_cache.contains(first_key));
+
+        // scroll through all pages
+        for i in 1..30 {
+            let _ = engine.camera_set_offset_expand(na::vector![
+                0.0,
+                i as f64 * page_height * engine.camera.total_zoom()
+            ]);
+            let _ = engine.update_rendering_current_viewport();
+            handle_pending_render_tasks(&mut engine, &mut tasks_rx);
+            assert!(engine.pdf_underlay_cache.len() <= PdfUnderlayCache::CAPACITY);
+        }
+        assert_eq!(engine.pdf_underlay_cache.len(), PdfUnderlayCache::CAPACITY);
+        assert!(!engine.pdf_underlay_cache.contains(first_key));
+        assert!(engine
+            .pdf_underlay_cache
+            .contains(PageRenderKey::new(29, image_scale)));
+
+        // replacing the underlay drops the rendered pages
+        let _ = engine.clear_pdf_underlay();
+        assert_eq!(engine.pdf_underlay_cache.len(), 0);
+    }
+
+    #[test]
+    fn pdf_export_includes_underlay_pages() {
+        use crate::engine::export::{DocExportFormat, DocExportPrefs};
+
+        let (engine, _tasks_rx) = engine_with_underlay(3);
+        let bytes = futures::executor::block_on(engine.export_doc(
+            String::from("underlay"),
+            Some(DocExportPrefs {
+                export_format: DocExportFormat::Pdf,
+                ..Default::default()
+            }),
+        ))
+        .unwrap()
+        .unwrap();
+
+        let doc =
+            poppler::Document::from_bytes(&glib::Bytes::from(bytes.as_slice()), None).unwrap();
+        // the pages without strokes are exported as well
+        assert_eq!(doc.n_pages(), 3);
+    }
+
+    #[test]
+    fn pdf_underlay_change_is_undoable() {
+        let (mut engine, _tasks_rx) = engine_with_underlay(2);
+        let pdf_underlay = engine.document.pdf_underlay.clone().unwrap();
+        assert!(engine.can_undo());
+
+        let _ = engine.undo(Instant::now());
+        assert!(engine.document.pdf_underlay.is_none());
+        let _ = engine.redo(Instant::now());
+        assert!(engine
+            .document
+            .pdf_underlay
+            .as_ref()
+            .is_some_and(|underlay| Arc::ptr_eq(underlay, &pdf_underlay)));
+
+        let _ = engine.clear_pdf_underlay();
+        assert!(engine.document.pdf_underlay.is_none());
+        let _ = engine.undo(Instant::now());
+        assert!(engine.document.pdf_underlay.is_some());
+    }
+
+    #[test]
+    fn png_and_svg_exports_include_underlay() {
+        use crate::engine::export::{DocPagesExportFormat, DocPagesExportPrefs};
+
+        let (mut engine, _tasks_rx) = engine_with_underlay(1);
+        let export_page = |engine: &Engine, export_format| {
+            futures::executor::block_on(engine.export_doc_pages(Some(DocPagesExportPrefs {
+                export_format,
+                with_background: false,
+                bitmap_scalefactor: 1.0,
+                ..Default::default()
+            })))
+            .unwrap()
+            .unwrap()
+            .remove(0)
+        };
+        let has_blue_pixels = |png_bytes: &[u8]| {
+            image::load_from_memory(png_bytes)
+                .unwrap()
+                .to_rgba8()
+                .pixels()
+                .any(|p| p[2] > 200 && p[0] < 50 && p[1] < 50 && p[3] > 200)
+        };
+
+        let png = export_page(&engine, DocPagesExportFormat::Png);
+        let svg = export_page(&engine, DocPagesExportFormat::Svg);
+        assert!(has_blue_pixels(&png));
+
+        let _ = engine.clear_pdf_underlay();
+        assert!(!has_blue_pixels(&export_page(
+            &engine,
+            DocPagesExportFormat::Png
+        )));
+        // the underlay pages are embedded as vector content
+        assert!(svg.len() > export_page(&engine, DocPagesExportFormat::Svg).len());
+    }
+}
@@ -0,0 +1,155 @@
+// Imports
+use crate::document::Layout;
+use crate::store::chrono_comp::StrokeLayer;
+use crate::strokes::{BitmapImage, Stroke};
+use crate::{render, Drawable, Engine, WidgetFlags};
+use p2d::bounding_volume::BoundingVolume;
+use std::time::Instant;
+
+impl Engine {
+    /// Render the selected strokes into an image with the given density and put it into the background.
+    ///
+    /// The background itself can only hold a color and a pattern, so the image is inserted as a locked bitmap image
+    /// at the very back of the document layer. The original strokes are trashed and the selection is cleared,
+    /// all in one undo step, so undoing fully restores the vector strokes.
+    ///
+    /// For layouts with fixed page widths the image is clipped to the document bounds, for the infinite layouts
+    /// it covers the entire selection.
+    pub fn rasterize_selection_to_background(&mut self, dpi: f64) -> anyhow::Result<WidgetFlags> {
+        if !dpi.is_finite() || dpi <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "Rasterizing selection failed, invalid dpi: {dpi}."
+            ));
+        }
+        let selection_keys = self.store.selection_keys_as_rendered();
+        let Some(selection_bounds) = self.store.bounds_for_strokes(&selection_keys) else {
+            return Err(anyhow::anyhow!(
+                "Rasterizing selection failed, nothing is selected."
+            ));
+        };
+        let bounds = match self.document.layout {
+            Layout::FixedSize | Layout::ContinuousVertical => {
+                let doc_bounds = self.document.bounds();
+                if !doc_bounds.intersects(&selection_bounds) {
+                    return Err(anyhow::anyhow!(
+                        "Rasterizing selection failed, the selection is outside of the document."
+                    ));
+                }
+                doc_bounds.intersection(&selection_bounds).unwrap()
+            }
+            Layout::SemiInfinite | Layout::Infinite => selection_bounds,
+        };
+        let image_scale = dpi / self.document.format.dpi();
+
+        let strokes = self.store.get_strokes_arc(&selection_keys);
+        let image = render::Image::gen_with_piet(
+            |piet_cx| {
+                for stroke in strokes.iter() {
+                    stroke.draw(piet_cx, image_scale)?;
+                }
+                Ok(())
+            },
+            bounds,
+            image_scale,
+        )?;
+        let bitmapimage = BitmapImage {
+            rectangle: image.rect.clone(),
+            image,
+            ..Default::default()
+        };
+
+        self.store
+            .set_selected_keys_keep_order(&selection_keys, false);
+        self.store.set_trashed_keys(&selection_keys, true);
+        let key = self.store.insert_stroke(
+            Stroke::BitmapImage(bitmapimage),
+            Some(StrokeLayer::Document),
+        );
+        self.store.move_strokes_to_back(&[key]);
+        self.store.set_locked(key, true);
+        self.store.regenerate_rendering_for_stroke(
+            key,
+            self.camera.viewport(),
+            self.camera.image_scale(),
+        );
+
+        Ok(self.current_pen_update_state()
+            | self.doc_resize_autoexpand()
+            | self.record(Instant::now())
+            | self.update_rendering_current_viewport())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::engine_w_selected_rect;
+    use p2d::bounding_volume::Aabb;
+    use rnote_compose::shapes::Shapeable;
+
+    fn background_image_bounds(engine: &Engine) -> Aabb {
+        let keys = engine.store.stroke_keys_as_rendered();
+        assert_eq!(keys.len(), 1);
+        let Some(Stroke::BitmapImage(bitmapimage)) = engine.store.get_stroke_ref(keys[0]) else {
+            panic!("the selection was not replaced by a bitmap image");
+        };
+        assert_eq!(engine.store.locked(keys[0]), Some(true));
+        bitmapimage.bounds()
+    }
+
+    #[test]
+    fn rasterized_selection_is_undoable() {
+        let rect = Aabb::new(na::point![10.0, 20.0], na::point![110.0, 70.0]);
+        let mut engine = engine_w_selected_rect(rect);
+        engine.document.layout = Layout::Infinite;
+
+        let _ = engine.rasterize_selection_to_background(96.0).unwrap();
+        assert!(engine.store.selection_keys_unordered().is_empty());
+        let bounds = background_image_bounds(&engine);
+        assert!(bounds.contains(&rect));
+
+        let _ = engine.undo(Instant::now());
+        let keys = engine.store.stroke_keys_as_rendered();
+        assert_eq!(keys.len(), 1);
+        assert!(matches!(
+            engine.store.get_stroke_ref(keys[0]),
+            Some(Stroke::ShapeStroke(_))
+        ));
+    }
+
+    #[test]
+    fn rasterized_selection_clipped_to_fixed_pages() {
+        let doc_bounds = Engine::default().document.bounds();
+        let rect = Aabb::new(
+            na::point![doc_bounds.maxs[0] - 50.0, 20.0],
+            na::point![doc_bounds.maxs[0] + 150.0, 70.0],
+        );
+        let mut engine = engine_w_selected_rect(rect);
+        engine.document.layout = Layout::FixedSize;
+
+        let _ = engine.rasterize_selection_to_background(96.0).unwrap();
+        // The generated image is loosened by one unit
+        let bounds = background_image_bounds(&engine).tightened(1.0);
+        assert!(doc_bounds.contains(&bounds));
+        approx::assert_relative_eq!(bounds.maxs[0], doc_bounds.maxs[0], epsilon = 1e-6);
+
+        // Nothing left to rasterize outside of the document
+        let outside = Aabb::new(
+            na::point![doc_bounds.maxs[0] + 100.0, 20.0],
+            na::point![doc_bounds.maxs[0] + 150.0, 70.0],
+        );
+        let mut engine = engine_w_selected_rect(outside);
+        engine.document.layout = Layout::FixedSize;
+        assert!(engine.rasterize_selection_to_background(96.0).is_err());
+    }
+
+    #[test]
+    fn rasterized_selection_not_clipped_when_infinite() {
+        let rect = Aabb::new(na::point![-400.0, -300.0], na::point![-100.0, -50.0]);
+        let mut engine = engine_w_selected_rect(rect);
+        engine.document.layout = Layout::Infinite;
+
+        let _ = engine.rasterize_selection_to_background(192.0).unwrap();
+        assert!(background_image_bounds(&engine).contains(&rect));
+    }
+}
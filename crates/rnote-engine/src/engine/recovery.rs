@@ -0,0 +1,510 @@
+// Imports
+use super::{Engine, EngineSnapshot, EngineTask};
+use crate::document::DocumentMeta;
+use crate::fileformats::rnoteformat::RnoteFile;
+use crate::fileformats::{FileFormatLoader, FileFormatSaver};
+use crate::store::HistoryEntry;
+use crate::tasks::{PeriodicTaskHandle, PeriodicTaskResult};
+use crate::{Camera, CloneConfig, Document, WidgetFlags};
+use anyhow::Context;
+use futures::channel::oneshot;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, error};
+
+/// Periodically writes snapshots of the document into a recovery file, which can be loaded again after a crash.
+#[derive(Debug, Default)]
+pub(crate) struct Recovery {
+    /// The recovery file. No snapshots are written when not set.
+    file: Option<PathBuf>,
+    /// The file of the document, to determine whether the recovery file is newer than the last explicit save.
+    doc_file: Option<PathBuf>,
+    /// Whether the store was modified since the last snapshot.
+    dirty: bool,
+    /// Periodically sends [EngineTask::RecoverySnapshot] while it is alive.
+    task_handle: Option<PeriodicTaskHandle>,
+    /// The document settings for the published snapshot, see [Engine::published_snapshot].
+    published_document: Arc<Mutex<Arc<PublishedDocument>>>,
+    /// Whether the published snapshot is registered to be written by the crash recovery hook.
+    registered: bool,
+}
+
+/// The parts of a published snapshot that are not kept in the store.
+#[derive(Debug, Clone, Default)]
+struct PublishedDocument {
+    document: Document,
+    camera: Camera,
+    document_meta: DocumentMeta,
+    recovery_file: Option<PathBuf>,
+}
+
+/// A handle to the last recorded state of an engine, which can be read from any thread.
+///
+/// It is only updated when the state is recorded, so it can be used when the live engine might be inconsistent,
+/// like in a panic hook.
+#[derive(Debug, Clone)]
+pub struct PublishedSnapshot {
+    store_state: Arc<Mutex<Arc<HistoryEntry>>>,
+    document: Arc<Mutex<Arc<PublishedDocument>>>,
+}
+
+impl PublishedSnapshot {
+    /// The snapshot of the last recorded state.
+    ///
+    /// Never blocks, None when the state is published at the same time.
+    pub fn load(&self) -> Option<EngineSnapshot> {
+        let store_state = Arc::clone(&*self.store_state.try_lock().ok()?);
+        let document = Arc::clone(&*self.document.try_lock().ok()?);
+        Some(EngineSnapshot::from_history_entry(
+            document.document.clone_config(),
+            document.camera.clone_config(),
+            document.document_meta.clone(),
+            &store_state,
+        ))
+    }
+
+    /// Serialize the snapshot and write it into the recovery file of the engine.
+    ///
+    /// Blocks while serializing and writing. Errors when the engine doesn't have a recovery file.
+    pub fn write_recovery_file(&self) -> anyhow::Result<()> {
+        let file = self
+            .document
+            .try_lock()
+            .ok()
+            .and_then(|document| document.recovery_file.clone())
+            .ok_or_else(|| anyhow::anyhow!("No recovery file is set."))?;
+        let engine_snapshot = self
+            .load()
+            .ok_or_else(|| anyhow::anyhow!("Published snapshot is currently being updated."))?;
+        write_recovery_file(&file, &engine_snapshot)
+    }
+
+    fn downgrade(
+        &self,
+    ) -> (
+        Weak<Mutex<Arc<HistoryEntry>>>,
+        Weak<Mutex<Arc<PublishedDocument>>>,
+    ) {
+        (
+            Arc::downgrade(&self.store_state),
+            Arc::downgrade(&self.document),
+        )
+    }
+}
+
+/// The published snapshots of the engines that are still alive, written by the crash recovery hook.
+#[allow(clippy::type_complexity)]
+static CRASH_RECOVERY_SNAPSHOTS: Mutex<
+    Vec<(
+        Weak<Mutex<Arc<HistoryEntry>>>,
+        Weak<Mutex<Arc<PublishedDocument>>>,
+    )>,
+> = Mutex::new(Vec::new());
+
+/// Install a panic hook that writes the last recorded state of all engines with a recovery target into their recovery files.
+///
+/// The previously installed hook is called afterwards, so the panic then proceeds as usual.
+pub fn install_crash_recovery_hook() {
+    let prev_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // The panic might have happened while the registry was locked
+        if let Ok(snapshots) = CRASH_RECOVERY_SNAPSHOTS.try_lock() {
+            for (store_state, document) in snapshots.iter() {
+                let (Some(store_state), Some(document)) =
+                    (store_state.upgrade(), document.upgrade())
+                else {
+                    continue;
+                };
+                if let Err(e) = (PublishedSnapshot {
+                    store_state,
+                    document,
+                })
+                .write_recovery_file()
+                {
+                    error!("Writing crash recovery file failed, Err: {e:?}");
+                }
+            }
+        }
+        prev_hook(panic_info);
+    }));
+}
+
+impl Engine {
+    /// The file name prefix of recovery files.
+    pub const RECOVERY_FILE_PREFIX: &'static str = "recovery-";
+    /// Recovery files that weren't written to for this long are pruned.
+    pub const RECOVERY_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+    /// The recovery file in the given directory for the document file.
+    ///
+    /// The name is derived from the document file path, so the same document always maps to the same recovery file.
+    /// Documents without a file share one recovery file.
+    pub fn recovery_file_path(dir: impl AsRef<Path>, doc_file: Option<&Path>) -> PathBuf {
+        let name = match doc_file {
+            Some(doc_file) => format!("{:016x}", fnv1a_hash(doc_file.to_string_lossy().as_bytes())),
+            None => String::from("draft"),
+        };
+        dir.as_ref()
+            .join(format!("{}{name}.rnote", Self::RECOVERY_FILE_PREFIX))
+    }
+
+    /// Set the directory the recovery snapshots are written into and the file of the currently loaded document.
+    ///
+    /// Setting the directory to None stops writing recovery snapshots.
+    pub fn set_recovery_target(&mut self, dir: Option<PathBuf>, doc_file: Option<PathBuf>) {
+        self.recovery.file = dir.map(|dir| Self::recovery_file_path(dir, doc_file.as_deref()));
+        self.recovery.doc_file = doc_file;
+        self.publish_document();
+        if self.recovery.file.is_some() && !self.recovery.registered {
+            let mut snapshots = CRASH_RECOVERY_SNAPSHOTS
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            // Drop the snapshots of engines that are gone
+            snapshots.retain(|(store_state, _)| store_state.strong_count() > 0);
+            snapshots.push(self.published_snapshot().downgrade());
+            self.recovery.registered = true;
+        }
+    }
+
+    /// A handle to the last recorded state, which can be read from any thread.
+    ///
+    /// The strokes are published whenever the state is recorded, the document settings when
+    /// the store is flagged as modified with [Engine::mark_recovery_dirty] and when a snapshot is loaded.
+    pub fn published_snapshot(&self) -> PublishedSnapshot {
+        PublishedSnapshot {
+            store_state: self.store.published_state(),
+            document: Arc::clone(&self.recovery.published_document),
+        }
+    }
+
+    /// Publish the current document settings for the published snapshot.
+    pub(crate) fn publish_document(&mut self) {
+        let published = Arc::new(PublishedDocument {
+            document: self.document.clone_config(),
+            camera: self.camera.clone_config(),
+            document_meta: self.document_meta.clone(),
+            recovery_file: self.recovery.file.clone(),
+        });
+        *self
+            .recovery
+            .published_document
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = published;
+    }
+
+    /// Set the interval in which recovery snapshots are written. None or a zero interval turns them off.
+    pub fn set_recovery_interval(&mut self, interval: Option<Duration>) {
+        let Some(interval) = interval.filter(|i| !i.is_zero()) else {
+            self.recovery.task_handle = None;
+            return;
+        };
+        if let Some(task_handle) = self.recovery.task_handle.as_mut() {
+            if let Err(e) = task_handle.change_timeout(interval) {
+                error!("Changing the recovery snapshot interval failed, Err: {e:?}");
+            }
+            return;
+        }
+        let tasks_tx = self.engine_tasks_tx();
+        let task = move || -> PeriodicTaskResult {
+            tasks_tx.send(EngineTask::RecoverySnapshot);
+            PeriodicTaskResult::Continue
+        };
+        self.recovery.task_handle = Some(PeriodicTaskHandle::new(task, interval));
+    }
+
+    /// Flag that the store was modified, so that the next recovery snapshot is written.
+    ///
+    /// Should be called whenever `store_modified` is set in the widget flags.
+    pub fn mark_recovery_dirty(&mut self) {
+        self.recovery.dirty = true;
+        self.publish_document();
+    }
+
+    /// Write a recovery snapshot, if the store was modified since the last one.
+    ///
+    /// Serializing and writing happens on a worker thread, the returned receiver reports when it is finished.
+    /// Returns None when no snapshot is written.
+    pub(crate) fn write_recovery_snapshot(
+        &mut self,
+    ) -> Option<oneshot::Receiver<anyhow::Result<()>>> {
+        if !self.recovery.dirty {
+            return None;
+        }
+        let file = self.recovery.file.clone()?;
+        self.recovery.dirty = false;
+
+        let (oneshot_sender, oneshot_receiver) = oneshot::channel::<anyhow::Result<()>>();
+        let engine_snapshot = self.take_snapshot();
+        rayon::spawn(move || {
+            let result = || -> anyhow::Result<()> {
+                write_recovery_file(&file, &engine_snapshot)?;
+                if let Some(dir) = file.parent() {
+                    prune_recovery_files(dir, Engine::RECOVERY_MAX_AGE);
+                }
+                Ok(())
+            };
+            let result = result();
+            if let Err(e) = &result {
+                error!("Writing recovery snapshot failed, Err: {e:?}");
+            }
+            if oneshot_sender.send(result).is_err() {
+                debug!("Sending result to receiver failed while writing recovery snapshot. Receiver already dropped.");
+            }
+        });
+        Some(oneshot_receiver)
+    }
+
+    /// Whether a recovery file exists that is newer than the last explicit save of the document.
+    pub fn recovery_available(&self) -> bool {
+        let Some(recovery_modified) = self
+            .recovery
+            .file
+            .as_ref()
+            .and_then(|file| file.metadata().and_then(|m| m.modified()).ok())
+        else {
+            return false;
+        };
+        match self
+            .recovery
+            .doc_file
+            .as_ref()
+            .and_then(|file| file.metadata().and_then(|m| m.modified()).ok())
+        {
+            Some(doc_modified) => recovery_modified > doc_modified,
+            None => true,
+        }
+    }
+
+    /// Load the recovery file into the engine.
+    ///
+    /// This blocks while reading and deserializing and is meant to be called right after a document was opened.
+    /// The recovery file is kept until it is discarded with [Engine::discard_recovery].
+    pub fn load_recovery(&mut self) -> anyhow::Result<WidgetFlags> {
+        let file = self
+            .recovery
+            .file
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("No recovery file is set."))?;
+        let bytes = std::fs::read(&file)
+            .context(format!("Failed to read recovery file '{}'", file.display()))?;
+        let rnote_file =
+            RnoteFile::load_from_bytes(&bytes).context("loading RnoteFile from bytes failed.")?;
+        let engine_snapshot = ijson::from_value::<EngineSnapshot>(&rnote_file.engine_snapshot)
+            .context("deserializing EngineSnapshot failed.")?;
+
+        let mut widget_flags = self.load_snapshot(engine_snapshot);
+        // The recovered document differs from the one on disk
+        widget_flags.store_modified = true;
+        Ok(widget_flags)
+    }
+
+    /// Remove the recovery file, e.g. after the document was explicitly saved or the recovery was declined.
+    pub fn discard_recovery(&mut self) {
+        self.recovery.dirty = false;
+        let Some(file) = self.recovery.file.as_ref() else {
+            return;
+        };
+        if let Err(e) = std::fs::remove_file(file) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                error!(
+                    "Removing recovery file '{}' failed, Err: {e:?}",
+                    file.display()
+                );
+            }
+        }
+    }
+}
+
+/// Serialize the snapshot as rnote file and write it into the recovery file.
+fn write_recovery_file(file: &Path, engine_snapshot: &EngineSnapshot) -> anyhow::Result<()> {
+    let rnote_file = RnoteFile {
+        engine_snapshot: ijson::to_value(engine_snapshot)?,
+        thumbnail: None,
+    };
+    let file_name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let bytes = rnote_file.save_as_bytes(&file_name)?;
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).context(format!(
+            "Failed to create recovery directory '{}'",
+            dir.display()
+        ))?;
+    }
+    write_atomically(file, &bytes)
+}
+
+/// Write the bytes into a temporary file next to the given path and rename it over it,
+/// so the file is never left partially written.
+fn write_atomically(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    // distinguishes temporary files of snapshots that are written concurrently
+    static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Path '{}' has no file name.", path.display()))?
+        .to_string_lossy();
+    let temp_path = path.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let write = || -> anyhow::Result<()> {
+        let mut temp_file = std::fs::File::create(&temp_path).context(format!(
+            "Failed to create temporary file '{}'",
+            temp_path.display()
+        ))?;
+        temp_file.write_all(bytes)?;
+        temp_file.sync_all()?;
+        std::fs::rename(&temp_path, path).context(format!(
+            "Failed to rename temporary file to '{}'",
+            path.display()
+        ))
+    };
+    let result = write();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Remove recovery files and leftover temporary files in the directory that weren't modified for longer than the max age.
+fn prune_recovery_files(dir: &Path, max_age: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_recovery_file = name.starts_with(Engine::RECOVERY_FILE_PREFIX)
+            || (name.starts_with(&format!(".{}", Engine::RECOVERY_FILE_PREFIX))
+                && name.ends_with(".tmp"));
+        if !is_recovery_file {
+            continue;
+        }
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .map(|age| age > max_age)
+            .unwrap_or(false);
+        if expired {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                error!(
+                    "Pruning recovery file '{}' failed, Err: {e:?}",
+                    entry.path().display()
+                );
+            }
+        }
+    }
+}
+
+/// The 64bit FNV-1a hash, which in contrast to the std hashers is stable across releases.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::test_dir;
+
+    #[test]
+    fn snapshot_gated_by_dirty_flag() {
+        let dir = test_dir("recovery", "dirty");
+        let mut engine = Engine::default();
+        engine.set_recovery_target(Some(dir.clone()), None);
+
+        assert!(engine.write_recovery_snapshot().is_none());
+        assert!(!engine.recovery_available());
+
+        engine.mark_recovery_dirty();
+        let receiver = engine.write_recovery_snapshot().unwrap();
+        futures::executor::block_on(receiver).unwrap().unwrap();
+        assert!(engine.recovery_available());
+        // Not modified since the last snapshot
+        assert!(engine.write_recovery_snapshot().is_none());
+
+        let _ = engine.load_recovery().unwrap();
+        engine.discard_recovery();
+        assert!(!engine.recovery_available());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn atomic_write_replaces_file() {
+        let dir = test_dir("recovery", "atomic");
+        let path = dir.join("recovery-test.rnote");
+
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Failing to write leaves the previous file intact
+        assert!(write_atomically(&dir.join("missing").join("file.rnote"), b"third").is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn published_snapshot_follows_recorded_state() {
+        use crate::testsupport::insert_rect;
+        use p2d::bounding_volume::Aabb;
+        use std::time::Instant;
+
+        let dir = test_dir("recovery", "published");
+        let mut engine = Engine::default();
+        engine.set_recovery_target(Some(dir.clone()), None);
+        let published = engine.published_snapshot();
+        let rect = Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]);
+        let stroke_count =
+            |published: &PublishedSnapshot| published.load().unwrap().stroke_components.len();
+        assert_eq!(stroke_count(&published), 0);
+
+        insert_rect(&mut engine.store, rect);
+        let _ = engine.record(Instant::now());
+        assert_eq!(stroke_count(&published), 1);
+        // Changes that are not recorded yet are not published
+        insert_rect(&mut engine.store, rect);
+        assert_eq!(stroke_count(&published), 1);
+        let _ = engine.record(Instant::now());
+        assert_eq!(stroke_count(&published), 2);
+
+        // Can be written from another thread, like the one that panicked
+        let thread_published = published.clone();
+        std::thread::spawn(move || thread_published.write_recovery_file())
+            .join()
+            .unwrap()
+            .unwrap();
+        assert!(engine.recovery_available());
+        let _ = engine.load_recovery().unwrap();
+        assert_eq!(engine.store.stroke_keys_unordered().len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn recovery_file_path_stable() {
+        let doc_file = Path::new("/home/user/notes.rnote");
+        assert_eq!(
+            Engine::recovery_file_path("/tmp", Some(doc_file)),
+            Engine::recovery_file_path("/tmp", Some(doc_file))
+        );
+        assert_ne!(
+            Engine::recovery_file_path("/tmp", Some(doc_file)),
+            Engine::recovery_file_path("/tmp", None)
+        );
+    }
+}
@@ -11,7 +11,8 @@ impl Engine {
     ///
     /// If the background pattern or zoom has changed, the background pattern needs to be regenerated first.
     pub fn update_background_rendering_current_viewport(&mut self) -> WidgetFlags {
-        let mut widget_flags = WidgetFlags::default();
+        // The pages of the Pdf underlay are rendered lazily, when they become visible
+        let mut widget_flags = self.update_pdf_underlay_rendering_current_viewport();
 
         #[cfg(feature = "ui")]
         {
@@ -23,6 +24,27 @@ impl Engine {
             let viewport = self.camera.viewport();
             let mut rendernodes: Vec<gsk::RenderNode> = vec![];
 
+            // The background image is beneath the pattern tiles
+            if let Some((background_image, texture)) = &self.background_image_texture {
+                let image_nodes = background_image
+                    .tiles(viewport, self.document.bounds())
+                    .into_iter()
+                    .map(|tile_bounds| {
+                        gsk::TextureNode::new(texture, &graphene::Rect::from_p2d_aabb(tile_bounds))
+                            .upcast()
+                    })
+                    .collect::<Vec<gsk::RenderNode>>();
+                if !image_nodes.is_empty() {
+                    rendernodes.push(
+                        gsk::OpacityNode::new(
+                            gsk::ContainerNode::new(&image_nodes),
+                            background_image.options.opacity_sanitized() as f32,
+                        )
+                        .upcast(),
+                    );
+                }
+            }
+
             if let Some(image) = &self.background_tile_image {
                 // Only create the texture once, it is expensive
                 let new_texture = match image.to_memtexture() {
@@ -113,8 +135,10 @@ impl Engine {
         self.store.clear_rendering();
         self.background_tile_image.take();
         self.origin_indicator_image.take();
+        self.pdf_underlay_cache = Default::default();
         #[cfg(feature = "ui")]
         {
+            self.background_image_texture.take();
             self.background_rendernodes.clear();
             self.origin_indicator_rendernode.take();
         }
@@ -122,7 +146,8 @@ impl Engine {
         widget_flags
     }
 
-    /// Regenerate the background tile image, origin indicator and updates the background rendering.
+    /// Regenerate the background tile image, the background image texture, origin indicator
+    /// and updates the background rendering.
     pub fn background_rendering_regenerate(&mut self) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
         let image_scale = self.camera.image_scale();
@@ -138,6 +163,34 @@ impl Engine {
             }
         }
 
+        #[cfg(feature = "ui")]
+        {
+            use std::sync::Arc;
+
+            let texture_outdated = match (
+                &self.document.background.image,
+                &self.background_image_texture,
+            ) {
+                (Some(image), Some((texture_image, _))) => !Arc::ptr_eq(image, texture_image),
+                (None, None) => false,
+                _ => true,
+            };
+            if texture_outdated {
+                self.background_image_texture =
+                    self.document.background.image.as_ref().and_then(|image| {
+                        match image.image.to_memtexture() {
+                            Ok(texture) => Some((Arc::clone(image), texture)),
+                            Err(e) => {
+                                error!(
+                                "Generating memory-texture of background image failed, Err: {e:?}"
+                            );
+                                None
+                            }
+                        }
+                    });
+            }
+        }
+
         match gen_origin_indicator_image(scale_factor) {
             Ok(image) => {
                 self.origin_indicator_image = Some(image);
@@ -174,10 +227,18 @@ impl Engine {
         snapshot.transform(Some(&camera_transform));
         self.draw_document_shadow_to_gtk_snapshot(snapshot);
         self.draw_background_to_gtk_snapshot(snapshot)?;
+        self.draw_pdf_underlay_to_gtk_snapshot(snapshot);
         self.draw_format_borders_to_gtk_snapshot(snapshot)?;
+        self.draw_margin_guides_to_gtk_snapshot(snapshot)?;
         self.draw_origin_indicator_to_gtk_snapshot(snapshot)?;
-        self.store
-            .draw_strokes_to_gtk_snapshot(snapshot, doc_bounds, viewport);
+        self.store.draw_strokes_to_gtk_snapshot(
+            snapshot,
+            doc_bounds,
+            viewport,
+            self.current_render_mode(),
+            self.camera.total_zoom(),
+        );
+        self.draw_search_highlight_to_gtk_snapshot(snapshot);
         snapshot.restore();
         /*
                let cairo_cx = snapshot.append_cairo(&graphene::Rect::from_p2d_aabb(surface_bounds));
@@ -210,6 +271,16 @@ impl Engine {
 
             visual_debug::draw_statistics_to_gtk_snapshot(snapshot, self, surface_bounds)?;
         }
+        if self.visual_debug_damage {
+            snapshot.save();
+            snapshot.transform(Some(&camera_transform));
+            visual_debug::draw_damage_to_gtk_snapshot(
+                snapshot,
+                &self.damage_history,
+                self.camera.total_zoom(),
+            );
+            snapshot.restore();
+        }
 
         Ok(())
     }
@@ -326,6 +397,86 @@ impl Engine {
         Ok(())
     }
 
+    /// Draw the margin guides of the pages in the viewport as faint lines.
+    ///
+    /// They are only drawn on the canvas and not included in exports.
+    #[cfg(feature = "ui")]
+    fn draw_margin_guides_to_gtk_snapshot(&self, snapshot: &gtk4::Snapshot) -> anyhow::Result<()> {
+        use crate::ext::{GdkRGBAExt, GrapheneRectExt};
+        use gtk4::{gdk, graphene, prelude::*};
+        use p2d::bounding_volume::BoundingVolume;
+        use rnote_compose::ext::AabbExt;
+        use rnote_compose::SplitOrder;
+
+        const GUIDE_ALPHA_FACTOR: f64 = 0.4;
+        let margins = self.document.margins();
+        if margins.is_empty() {
+            return Ok(());
+        }
+        let guide_width = 1.0 / self.camera.total_zoom();
+        let viewport = self.camera.viewport();
+        let doc_bounds = self.document.bounds();
+        let mut guide_color = self.document.format.border_color;
+        guide_color.a *= GUIDE_ALPHA_FACTOR;
+        let guide_color = gdk::RGBA::from_compose_color(guide_color);
+
+        snapshot.push_clip(&graphene::Rect::from_p2d_aabb(doc_bounds));
+
+        for page_bounds in doc_bounds
+            .split_extended_origin_aligned(self.document.format.size(), SplitOrder::default())
+        {
+            if !page_bounds.intersects(&viewport) {
+                continue;
+            }
+            for x in margins.vertical_guides(page_bounds) {
+                snapshot.append_color(
+                    &guide_color,
+                    &graphene::Rect::from_p2d_aabb(Aabb::new(
+                        na::point![x - guide_width * 0.5, page_bounds.mins[1]],
+                        na::point![x + guide_width * 0.5, page_bounds.maxs[1]],
+                    )),
+                );
+            }
+            for y in margins.horizontal_guides(page_bounds) {
+                snapshot.append_color(
+                    &guide_color,
+                    &graphene::Rect::from_p2d_aabb(Aabb::new(
+                        na::point![page_bounds.mins[0], y - guide_width * 0.5],
+                        na::point![page_bounds.maxs[0], y + guide_width * 0.5],
+                    )),
+                );
+            }
+        }
+
+        snapshot.pop();
+
+        Ok(())
+    }
+
+    /// Draw the highlight of the search match that was scrolled to.
+    #[cfg(feature = "ui")]
+    fn draw_search_highlight_to_gtk_snapshot(&self, snapshot: &gtk4::Snapshot) {
+        use crate::engine::visual_debug;
+        use crate::ext::{GdkRGBAExt, GrapheneRectExt};
+        use gtk4::{gdk, graphene, prelude::*};
+
+        let Some(bounds) = self.search_highlight.bounds() else {
+            return;
+        };
+        let border_width = 1.5 / self.camera.total_zoom();
+
+        snapshot.append_color(
+            &gdk::RGBA::from_piet_color(color::GNOME_YELLOWS[1].with_a8(100)),
+            &graphene::Rect::from_p2d_aabb(bounds),
+        );
+        visual_debug::draw_bounds_to_gtk_snapshot(
+            bounds,
+            color::GNOME_ORANGES[3].into(),
+            snapshot,
+            border_width,
+        );
+    }
+
     /// Draw the document origin indicator cross.
     #[cfg(feature = "ui")]
     fn draw_origin_indicator_to_gtk_snapshot(
@@ -0,0 +1,161 @@
+// Imports
+use super::{Engine, EngineTask};
+use crate::store::StrokeKey;
+use crate::strokes::Stroke;
+use crate::tasks::OneOffTaskHandle;
+use crate::WidgetFlags;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use std::ops::Range;
+use std::time::Duration;
+
+/// A match of a text search in a text stroke.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMatch {
+    /// The key of the text stroke.
+    pub stroke_key: StrokeKey,
+    /// The byte range of the match in the text of the stroke.
+    pub char_range: Range<usize>,
+    /// The bounds of the matched text, in document coordinates.
+    pub bounds: Aabb,
+}
+
+/// The temporary highlight of the search match that was scrolled to.
+#[derive(Debug, Default)]
+pub(crate) struct SearchHighlight {
+    bounds: Option<Aabb>,
+    /// Sends [EngineTask::ClearSearchHighlight] once the highlight should disappear.
+    task_handle: Option<OneOffTaskHandle>,
+}
+
+impl SearchHighlight {
+    /// How long the match stays highlighted.
+    const TIMEOUT: Duration = Duration::from_millis(1500);
+
+    pub(crate) fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.bounds = None;
+        self.task_handle = None;
+    }
+}
+
+impl Engine {
+    /// The padding around the highlight of a search match, in surface coordinates.
+    const SEARCH_HIGHLIGHT_PADDING: f64 = 2.0;
+
+    /// Search the text of all text strokes that are not trashed.
+    ///
+    /// The matches are sorted by their position in the document, top to bottom and then left to right.
+    pub fn search_text(&self, query: &str, case_sensitive: bool) -> Vec<TextMatch> {
+        let mut matches = self
+            .store
+            .stroke_keys_as_rendered()
+            .into_iter()
+            .filter_map(|key| match self.store.get_stroke_ref(key) {
+                Some(Stroke::TextStroke(textstroke)) => Some((key, textstroke)),
+                _ => None,
+            })
+            .flat_map(|(key, textstroke)| {
+                textstroke
+                    .find_text(query, case_sensitive)
+                    .into_iter()
+                    .filter_map(move |char_range| {
+                        Some(TextMatch {
+                            stroke_key: key,
+                            bounds: textstroke.bounds_for_text_range(char_range.clone())?,
+                            char_range,
+                        })
+                    })
+            })
+            .collect::<Vec<TextMatch>>();
+        matches.sort_by(|first, second| {
+            first.bounds.mins[1]
+                .total_cmp(&second.bounds.mins[1])
+                .then(first.bounds.mins[0].total_cmp(&second.bounds.mins[0]))
+        });
+        matches
+    }
+
+    /// Center the viewport on the search match and highlight it for a short time.
+    pub fn scroll_to_match(&mut self, text_match: &TextMatch) -> WidgetFlags {
+        let mut widget_flags = self
+            .camera
+            .set_viewport_center(text_match.bounds.center().coords)
+            | self.doc_expand_autoexpand()
+            | self.update_rendering_current_viewport();
+
+        let tasks_tx = self.engine_tasks_tx();
+        self.search_highlight.bounds = Some(
+            text_match
+                .bounds
+                .loosened(Self::SEARCH_HIGHLIGHT_PADDING / self.camera.total_zoom()),
+        );
+        // Replacing the handle cancels the timeout of the previous highlight
+        self.search_highlight.task_handle = Some(OneOffTaskHandle::new(
+            move || tasks_tx.send(EngineTask::ClearSearchHighlight),
+            SearchHighlight::TIMEOUT,
+        ));
+        widget_flags.redraw = true;
+        widget_flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strokes::textstroke::TextStyle;
+    use crate::strokes::{Stroke, TextStroke};
+    use crate::Engine;
+
+    #[test]
+    fn search_multiline_text() {
+        let mut engine = Engine::default();
+        let text = String::from("First line\nsecond LINE, and a line");
+        let key = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                text.clone(),
+                na::vector![100.0, 50.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+        let trashed = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("line"),
+                na::vector![0.0, 0.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+        engine.store.set_trashed(trashed, true);
+
+        let matches = engine.search_text("line", false);
+        assert_eq!(matches.len(), 3);
+        assert!(matches.iter().all(|m| m.stroke_key == key));
+        assert!(matches
+            .iter()
+            .all(|m| text[m.char_range.clone()].eq_ignore_ascii_case("line")));
+        // the first match is on the first line, the others on the second line, left to right
+        assert!(matches[0].bounds.maxs[1] <= matches[1].bounds.mins[1] + 1e-6);
+        assert!(matches[1].bounds.maxs[0] <= matches[2].bounds.mins[0]);
+        assert!(matches[0].bounds.mins[0] > 100.0 && matches[0].bounds.mins[1] >= 50.0);
+        // the match covers only the substring
+        let stroke_bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+        assert!(matches[0].bounds.extents()[0] < stroke_bounds.extents()[0] * 0.5);
+
+        let matches = engine.search_text("LINE", true);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].char_range, 18..22);
+        assert!(engine.search_text("", false).is_empty());
+
+        let widget_flags = engine.scroll_to_match(&matches[0]);
+        assert!(widget_flags.redraw);
+        approx::assert_relative_eq!(
+            engine.camera.viewport().center(),
+            matches[0].bounds.center(),
+            epsilon = 1e-6
+        );
+        assert!(engine.search_highlight.bounds().is_some());
+    }
+}
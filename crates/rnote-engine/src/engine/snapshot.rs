@@ -1,8 +1,9 @@
 // Imports
-use crate::document::background;
+use crate::document::{background, DocumentMeta};
 use crate::engine::import::XoppImportPrefs;
 use crate::fileformats::{rnoteformat, xoppformat, FileFormatLoader};
-use crate::store::{ChronoComponent, StrokeKey};
+use crate::store::chrono_comp::StrokeLayer;
+use crate::store::{ChronoComponent, HistoryEntry, LockComponent, StrokeKey};
 use crate::strokes::Stroke;
 use crate::{Camera, Document, Engine};
 use anyhow::Context;
@@ -24,8 +25,17 @@ pub struct EngineSnapshot {
     pub stroke_components: Arc<HopSlotMap<StrokeKey, Arc<Stroke>>>,
     #[serde(rename = "chrono_components")]
     pub chrono_components: Arc<SecondaryMap<StrokeKey, Arc<ChronoComponent>>>,
+    #[serde(rename = "lock_components")]
+    pub lock_components: Arc<SecondaryMap<StrokeKey, Arc<LockComponent>>>,
     #[serde(rename = "chrono_counter")]
     pub chrono_counter: u32,
+    #[serde(rename = "document_meta")]
+    pub document_meta: DocumentMeta,
+    /// Identifies the saved file for journals of incremental saves that are based on it.
+    ///
+    /// Zero when no journal can be based on it.
+    #[serde(rename = "journal_base_id")]
+    pub journal_base_id: u64,
 }
 
 impl Default for EngineSnapshot {
@@ -35,12 +45,64 @@ impl Default for EngineSnapshot {
             camera: Camera::default(),
             stroke_components: Arc::new(HopSlotMap::with_key()),
             chrono_components: Arc::new(SecondaryMap::new()),
+            lock_components: Arc::new(SecondaryMap::new()),
             chrono_counter: 0,
+            document_meta: DocumentMeta::default(),
+            journal_base_id: 0,
         }
     }
 }
 
 impl EngineSnapshot {
+    /// A snapshot of the store state in the history entry, without the trashed strokes.
+    pub(crate) fn from_history_entry(
+        document: Document,
+        camera: Camera,
+        document_meta: DocumentMeta,
+        history_entry: &HistoryEntry,
+    ) -> Self {
+        let mut stroke_components = Arc::clone(&history_entry.stroke_components);
+        let trashed_keys = history_entry
+            .trash_components
+            .iter()
+            .filter_map(|(key, trash_comp)| if trash_comp.trashed { Some(key) } else { None })
+            .collect::<Vec<StrokeKey>>();
+        for key in trashed_keys {
+            Arc::make_mut(&mut stroke_components).remove(key);
+        }
+
+        Self {
+            document,
+            camera,
+            stroke_components,
+            chrono_components: Arc::clone(&history_entry.chrono_components),
+            lock_components: Arc::clone(&history_entry.lock_components),
+            chrono_counter: history_entry.chrono_counter,
+            document_meta,
+            journal_base_id: 0,
+        }
+    }
+
+    /// The strokes with their layers, in the order they are rendered.
+    ///
+    /// Strokes without a chrono component are placed on top, in their default layer.
+    pub(crate) fn strokes_sorted_chrono(&self) -> Vec<(Stroke, StrokeLayer)> {
+        let mut strokes = self
+            .stroke_components
+            .iter()
+            .map(|(key, stroke)| {
+                let chrono = self.chrono_components.get(key);
+                let order = chrono.map(|chrono| chrono.render_order());
+                let layer = chrono
+                    .map(|chrono| chrono.layer)
+                    .unwrap_or_else(|| stroke.extract_default_layer());
+                (order, (Stroke::clone(stroke), layer))
+            })
+            .collect::<Vec<_>>();
+        strokes.sort_by_key(|(order, _)| (order.is_none(), *order));
+        strokes.into_iter().map(|(_, stroke)| stroke).collect()
+    }
+
     /// Loads a snapshot from the bytes of a .rnote file.
     ///
     /// To import this snapshot into the current engine, use [`Engine::load_snapshot()`].
@@ -63,6 +125,38 @@ impl EngineSnapshot {
 
         snapshot_receiver.await?
     }
+
+    /// Loads a snapshot from the bytes of a .rnote file, replaying the journal of its incremental saves.
+    ///
+    /// Falls back to the state of the last consistent incremental save, or to the .rnote file alone
+    /// when the journal is invalid or belongs to a different .rnote file.
+    ///
+    /// To import this snapshot into the current engine, use [`Engine::load_snapshot()`].
+    pub async fn load_from_rnote_bytes_w_journal(
+        bytes: Vec<u8>,
+        journal_bytes: Vec<u8>,
+    ) -> anyhow::Result<Self> {
+        let (snapshot_sender, snapshot_receiver) = oneshot::channel::<anyhow::Result<Self>>();
+
+        rayon::spawn(move || {
+            let result = || -> anyhow::Result<Self> {
+                let rnote_file = rnoteformat::RnoteFile::load_from_bytes(&bytes)
+                    .context("loading RnoteFile from bytes failed.")?;
+                let mut snapshot: Self = ijson::from_value(&rnote_file.engine_snapshot)?;
+                snapshot.replay_journal(&journal_bytes);
+                Ok(snapshot)
+            };
+
+            if let Err(_data) = snapshot_sender.send(result()) {
+                error!(
+                    "Sending bytes result to receiver failed while loading rnote bytes with journal in. Receiver already dropped."
+                );
+            }
+        });
+
+        snapshot_receiver.await?
+    }
+
     /// Loads from the bytes of a Xournal++ .xopp file.
     ///
     /// To import this snapshot into the current engine, use [`Engine::load_snapshot()`].
@@ -0,0 +1,97 @@
+// Imports
+use super::Engine;
+use crate::document::Layout;
+use crate::store::StoreStatistics;
+use p2d::bounding_volume::Aabb;
+use std::io;
+use tracing::error;
+
+/// Statistics about the document, e.g. for a document properties dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentStatistics {
+    /// The statistics of the strokes.
+    pub store: StoreStatistics,
+    /// The bounds of the document.
+    pub document_bounds: Aabb,
+    /// The number of pages in layouts with a fixed page size. None in the other layouts.
+    pub pages: Option<usize>,
+    /// The estimated size of the current snapshot when serialized, in bytes, before compression.
+    pub serialized_size: usize,
+    /// The estimated memory of the rendered pages of the Pdf underlay, in bytes.
+    pub pdf_underlay_images_size: usize,
+}
+
+/// A writer that only counts the written bytes.
+#[derive(Debug, Default)]
+struct CountingWriter {
+    count: usize,
+}
+
+impl io::Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.count += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Engine {
+    /// Gather statistics about the document.
+    pub fn document_statistics(&self) -> DocumentStatistics {
+        let pages = match self.document.layout {
+            Layout::FixedSize | Layout::ContinuousVertical => {
+                Some(self.document.n_vertical_pages())
+            }
+            Layout::SemiInfinite | Layout::Infinite => None,
+        };
+        let mut writer = CountingWriter::default();
+        if let Err(e) = serde_json::to_writer(&mut writer, &self.take_snapshot()) {
+            error!("Serializing the snapshot failed while estimating its size, Err: {e:?}");
+        }
+        DocumentStatistics {
+            store: self.store.statistics(),
+            document_bounds: self.document.bounds(),
+            pages,
+            serialized_size: writer.count,
+            pdf_underlay_images_size: self.pdf_underlay_cache.images_size(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document::Layout;
+    use crate::strokes::textstroke::TextStyle;
+    use crate::strokes::{Stroke, TextStroke};
+    use crate::Engine;
+
+    #[test]
+    fn document_statistics() {
+        let mut engine = Engine::default();
+        engine.document.layout = Layout::FixedSize;
+        let _ = engine.doc_add_page_fixed_size();
+        let _ = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("text"),
+                na::vector![0.0, 0.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+
+        let statistics = engine.document_statistics();
+        assert_eq!(statistics.store.text_strokes, 1);
+        assert_eq!(statistics.pages, Some(2));
+        assert_eq!(statistics.document_bounds, engine.document.bounds());
+        assert_eq!(
+            statistics.serialized_size,
+            serde_json::to_vec(&engine.take_snapshot()).unwrap().len()
+        );
+
+        engine.document.layout = Layout::Infinite;
+        assert_eq!(engine.document_statistics().pages, None);
+    }
+}
@@ -1,12 +1,14 @@
 // Imports
-use crate::document::Background;
+use crate::document::{Background, PdfUnderlay};
 use crate::render::Svg;
-use crate::strokes::Stroke;
+use crate::strokes::{Content, Stroke};
 use crate::Drawable;
 use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::shapes::Shapeable;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::sync::Arc;
+use svg::node::element;
 use tracing::warn;
 
 /// Stroke content.
@@ -21,6 +23,9 @@ pub struct StrokeContent {
     pub bounds: Option<Aabb>,
     #[serde(rename = "background")]
     pub background: Option<Background>,
+    /// Drawn above the background with its original vector content.
+    #[serde(skip)]
+    pub pdf_underlay: Option<Arc<PdfUnderlay>>,
 }
 
 impl StrokeContent {
@@ -42,6 +47,11 @@ impl StrokeContent {
         self
     }
 
+    pub fn with_pdf_underlay(mut self, pdf_underlay: Option<Arc<PdfUnderlay>>) -> Self {
+        self.pdf_underlay = pdf_underlay;
+        self
+    }
+
     pub fn bounds(&self) -> Option<Aabb> {
         if self.bounds.is_some() {
             return self.bounds;
@@ -63,6 +73,9 @@ impl StrokeContent {
 
     /// Generate a Svg from the content.
     ///
+    /// Strokes that support it (for example shapes) are kept as native Svg elements,
+    /// the others are generated through cairo.
+    ///
     /// Moves the bounds to mins: [0.0, 0.0], maxs: extents.
     ///
     /// Returns Ok(None) if there is no content stored.
@@ -73,27 +86,153 @@ impl StrokeContent {
         optimize_printing: bool,
         margin: f64,
     ) -> anyhow::Result<Option<Svg>> {
-        let Some(bounds_loosened) = self.bounds().map(|b| b.loosened(margin)) else {
+        let Some(bounds) = self.bounds() else {
             return Ok(None);
         };
-        let mut svg = Svg::gen_with_cairo(
+        let bounds_loosened = bounds.loosened(margin);
+        let id_prefix = rnote_compose::utils::svg_random_id_prefix();
+        let image_bounds = self.image_bounds();
+
+        let mut content_group = element::Group::new();
+        if draw_background {
+            if let Some(background) = &self.background {
+                let background_svg =
+                    background.gen_svg(bounds_loosened, draw_pattern, optimize_printing)?;
+                content_group = content_group.add(svg::node::Blob::new(background_svg.svg_data));
+            }
+        }
+        if let Some(pdf_underlay) = &self.pdf_underlay {
+            if !pdf_underlay.pages_intersecting(bounds_loosened).is_empty() {
+                let underlay_svg = Self::gen_svg_through_cairo_in_place(
+                    |cairo_cx| pdf_underlay.draw_to_cairo(cairo_cx, bounds_loosened),
+                    bounds_loosened,
+                )?;
+                content_group = content_group.add(svg::node::Blob::new(underlay_svg.svg_data));
+            }
+        }
+
+        // Subsequent strokes that are not native are generated together, to keep the Svg small
+        let mut strokes_group = element::Group::new();
+        let mut cairo_run = Vec::new();
+        for stroke in self.strokes.iter() {
+            let stroke = Self::stroke_for_export(stroke, optimize_printing, &image_bounds);
+            if !stroke.gen_svg_is_native() {
+                cairo_run.push(stroke);
+                continue;
+            }
+            if !cairo_run.is_empty() {
+                let run_svg = Self::gen_strokes_svg_through_cairo(&std::mem::take(&mut cairo_run))?;
+                strokes_group = strokes_group.add(svg::node::Blob::new(run_svg.svg_data));
+            }
+            strokes_group = strokes_group.add(svg::node::Blob::new(stroke.gen_svg()?.svg_data));
+        }
+        if !cairo_run.is_empty() {
+            let run_svg = Self::gen_strokes_svg_through_cairo(&cairo_run)?;
+            strokes_group = strokes_group.add(svg::node::Blob::new(run_svg.svg_data));
+        }
+
+        let clip_id = format!("{id_prefix}_content_clip");
+        let clip_path = element::ClipPath::new().set("id", clip_id.as_str()).add(
+            element::Rectangle::new()
+                .set("x", bounds.mins[0])
+                .set("y", bounds.mins[1])
+                .set("width", bounds.extents()[0])
+                .set("height", bounds.extents()[1]),
+        );
+        content_group = content_group
+            .add(element::Definitions::new().add(clip_path))
+            .add(strokes_group.set("clip-path", format!("url(#{clip_id})")))
+            .set(
+                "transform",
+                format!(
+                    "translate({} {})",
+                    -bounds_loosened.mins[0], -bounds_loosened.mins[1]
+                ),
+            );
+
+        Ok(Some(Svg {
+            svg_data: rnote_compose::utils::svg_node_to_string(&content_group)?,
+            bounds: Aabb::new(na::point![0.0, 0.0], bounds_loosened.extents().into()),
+        }))
+    }
+
+    /// Generate the strokes through cairo, see [StrokeContent::gen_svg_through_cairo_in_place].
+    fn gen_strokes_svg_through_cairo(strokes: &[Cow<Stroke>]) -> anyhow::Result<Svg> {
+        let bounds = strokes
+            .iter()
+            .map(|s| s.bounds())
+            .fold(Aabb::new_invalid(), |acc, x| acc.merged(&x));
+        Self::gen_svg_through_cairo_in_place(
             |cairo_cx| {
-                self.draw_to_cairo(
-                    cairo_cx,
-                    draw_background,
-                    draw_pattern,
-                    optimize_printing,
-                    margin,
-                    1.0,
-                )
+                for stroke in strokes {
+                    stroke.draw_to_cairo(cairo_cx, 1.0)?;
+                }
+                Ok(())
             },
-            bounds_loosened,
-        )?;
-        // The simplification also moves the bounds to mins: [0.0, 0.0], maxs: extents
+            bounds,
+        )
+    }
+
+    /// Generate through cairo and simplify the result, so that its ids don't collide with other generated Svgs.
+    ///
+    /// The bounds are kept in place.
+    fn gen_svg_through_cairo_in_place<F>(draw_func: F, bounds: Aabb) -> anyhow::Result<Svg>
+    where
+        F: FnOnce(&cairo::Context) -> anyhow::Result<()>,
+    {
+        let mut svg = Svg::gen_with_cairo(draw_func, bounds)?;
         if let Err(e) = svg.simplify() {
             warn!("Simplifying Svg while generating StrokeContent Svg failed, Err: {e:?}");
+            return Ok(svg);
         };
-        Ok(Some(svg))
+        // The simplification moved the bounds to mins: [0.0, 0.0], so they need to be translated back
+        let group = element::Group::new()
+            .set(
+                "transform",
+                format!("translate({} {})", bounds.mins[0], bounds.mins[1]),
+            )
+            .add(svg::node::Blob::new(svg.svg_data));
+
+        Ok(Svg {
+            svg_data: rnote_compose::utils::svg_node_to_string(&group)?,
+            bounds,
+        })
+    }
+
+    /// The bounds of the images in the content.
+    fn image_bounds(&self) -> Vec<Aabb> {
+        self.strokes
+            .iter()
+            .filter_map(|stroke| match stroke.as_ref() {
+                Stroke::BitmapImage(image) => Some(image.rectangle.bounds()),
+                Stroke::VectorImage(image) => Some(image.rectangle.bounds()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The stroke as it should be exported, with the colors optimized for printing when enabled.
+    fn stroke_for_export<'a>(
+        stroke: &'a Stroke,
+        optimize_printing: bool,
+        image_bounds: &[Aabb],
+    ) -> Cow<'a, Stroke> {
+        let stroke_bounds = stroke.bounds();
+
+        if optimize_printing
+            && image_bounds
+                .iter()
+                .all(|bounds| !bounds.contains(&stroke_bounds))
+        {
+            // Using the stroke's bounds instead of hitboxes works for inclusion.
+            // If this is changed to intersection, all hitboxes must be checked individually.
+
+            let mut darkest_color_stroke = stroke.clone();
+            darkest_color_stroke.set_to_darkest_color();
+            Cow::Owned(darkest_color_stroke)
+        } else {
+            Cow::Borrowed(stroke)
+        }
     }
 
     pub fn draw_to_cairo(
@@ -129,6 +268,9 @@ impl StrokeContent {
                 )?;
             }
         }
+        if let Some(pdf_underlay) = &self.pdf_underlay {
+            pdf_underlay.draw_to_cairo(cairo_cx, bounds_loosened)?;
+        }
 
         cairo_cx.restore()?;
         cairo_cx.save()?;
@@ -140,34 +282,10 @@ impl StrokeContent {
         );
         cairo_cx.clip();
 
-        let image_bounds = self
-            .strokes
-            .iter()
-            .filter_map(|stroke| match stroke.as_ref() {
-                Stroke::BitmapImage(image) => Some(image.rectangle.bounds()),
-                Stroke::VectorImage(image) => Some(image.rectangle.bounds()),
-                _ => None,
-            })
-            .collect::<Vec<Aabb>>();
-
+        let image_bounds = self.image_bounds();
         for stroke in self.strokes.iter() {
-            let stroke_bounds = stroke.bounds();
-
-            if optimize_printing
-                && image_bounds
-                    .iter()
-                    .all(|bounds| !bounds.contains(&stroke_bounds))
-            {
-                // Using the stroke's bounds instead of hitboxes works for inclusion.
-                // If this is changed to intersection, all hitboxes must be checked individually.
-
-                let mut darkest_color_stroke = stroke.as_ref().clone();
-                darkest_color_stroke.set_to_darkest_color();
-
-                darkest_color_stroke.draw_to_cairo(cairo_cx, image_scale)?;
-            } else {
-                stroke.draw_to_cairo(cairo_cx, image_scale)?;
-            }
+            Self::stroke_for_export(stroke, optimize_printing, &image_bounds)
+                .draw_to_cairo(cairo_cx, image_scale)?;
         }
 
         cairo_cx.restore()?;
@@ -49,6 +49,12 @@ pub const COLOR_DOC_BOUNDS: Color = Color {
     b: 0.8,
     a: 1.0,
 };
+pub const COLOR_DAMAGE: Color = Color {
+    r: 1.0,
+    g: 0.4,
+    b: 0.0,
+    a: 1.0,
+};
 
 #[cfg(feature = "ui")]
 pub(crate) fn draw_bounds_to_gtk_snapshot(
@@ -241,3 +247,24 @@ pub(crate) fn draw_stroke_debug_to_gtk_snapshot(
 
     Ok(())
 }
+
+/// Draw the recent damage regions, the most recent one the most opaque.
+#[cfg(feature = "ui")]
+pub(crate) fn draw_damage_to_gtk_snapshot(
+    snapshot: &gtk4::Snapshot,
+    damage_history: &std::collections::VecDeque<p2d::bounding_volume::Aabb>,
+    total_zoom: f64,
+) {
+    let border_widths = 1.0 / total_zoom;
+
+    for (i, damage) in damage_history.iter().enumerate() {
+        let alpha = (i + 1) as f64 / damage_history.len() as f64;
+        let mut fill_color = COLOR_DAMAGE;
+        fill_color.a = 0.08 * alpha;
+        let mut border_color = COLOR_DAMAGE;
+        border_color.a = alpha;
+
+        draw_fill_to_gtk_snapshot(snapshot, *damage, fill_color);
+        draw_bounds_to_gtk_snapshot(*damage, border_color, snapshot, border_widths);
+    }
+}
@@ -0,0 +1,144 @@
+//! The journal of incremental saves of a `.rnote` file.
+//!
+//! The journal is a sidecar file of the `.rnote` file it is based on. It starts with a header,
+//! followed by the records that are appended with every incremental save.
+//! Each record is gzip compressed JSON, prefixed by its length as little endian `u32`.
+
+// Imports
+use super::{compress_to_gzip, decompress_from_gzip};
+use crate::document::DocumentMeta;
+use crate::store::{ChronoComponent, LockComponent, StrokeKey};
+use crate::strokes::Stroke;
+use crate::{Camera, Document};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Identifies journal files.
+const JOURNAL_MAGIC: &[u8] = b"RNOTEJNL";
+/// The version of the journal format. Journals of other versions are not loaded.
+const JOURNAL_FORMAT_VERSION: u32 = 1;
+/// The magic followed by the format version.
+const JOURNAL_HEADER_LEN: usize = JOURNAL_MAGIC.len() + 4;
+
+/// The changes of the document since the previous record, or since the base file for the first one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "journal_record")]
+pub(crate) struct JournalRecord {
+    /// The journal base id of the `.rnote` file the journal is based on.
+    #[serde(rename = "base_id")]
+    pub(crate) base_id: u64,
+    /// Counts up from zero, so that a missing record is detected.
+    #[serde(rename = "sequence")]
+    pub(crate) sequence: u32,
+    /// The strokes that were added or modified.
+    #[serde(rename = "upserted_strokes")]
+    pub(crate) upserted_strokes: Vec<(StrokeKey, Arc<Stroke>)>,
+    #[serde(rename = "removed_strokes")]
+    pub(crate) removed_strokes: Vec<StrokeKey>,
+    #[serde(rename = "upserted_chrono_components")]
+    pub(crate) upserted_chrono_components: Vec<(StrokeKey, Arc<ChronoComponent>)>,
+    #[serde(rename = "removed_chrono_components")]
+    pub(crate) removed_chrono_components: Vec<StrokeKey>,
+    #[serde(rename = "upserted_lock_components")]
+    pub(crate) upserted_lock_components: Vec<(StrokeKey, Arc<LockComponent>)>,
+    #[serde(rename = "removed_lock_components")]
+    pub(crate) removed_lock_components: Vec<StrokeKey>,
+    #[serde(rename = "chrono_counter")]
+    pub(crate) chrono_counter: u32,
+    /// The document, without the background image and the Pdf underlay when they are unchanged.
+    #[serde(rename = "document")]
+    pub(crate) document: Document,
+    #[serde(rename = "background_image_changed")]
+    pub(crate) background_image_changed: bool,
+    #[serde(rename = "pdf_underlay_changed")]
+    pub(crate) pdf_underlay_changed: bool,
+    #[serde(rename = "camera")]
+    pub(crate) camera: Camera,
+    #[serde(rename = "document_meta")]
+    pub(crate) document_meta: DocumentMeta,
+}
+
+impl JournalRecord {
+    /// The number of added, modified and removed components.
+    pub(crate) fn n_entries(&self) -> usize {
+        self.upserted_strokes.len()
+            + self.removed_strokes.len()
+            + self.upserted_chrono_components.len()
+            + self.removed_chrono_components.len()
+            + self.upserted_lock_components.len()
+            + self.removed_lock_components.len()
+    }
+
+    /// Encode the length prefixed record, preceded by the journal header if it is the first record.
+    pub(crate) fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let compressed = compress_to_gzip(
+            &serde_json::to_vec(self).context("Serializing JournalRecord failed.")?,
+        )
+        .context("compressing bytes failed.")?;
+        let len =
+            u32::try_from(compressed.len()).context("Journal record exceeds the maximum size.")?;
+
+        let mut bytes = Vec::with_capacity(JOURNAL_HEADER_LEN + 4 + compressed.len());
+        if self.sequence == 0 {
+            bytes.extend_from_slice(JOURNAL_MAGIC);
+            bytes.extend_from_slice(&JOURNAL_FORMAT_VERSION.to_le_bytes());
+        }
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&compressed);
+        Ok(bytes)
+    }
+
+    /// Decode the records of the journal bytes.
+    ///
+    /// Errors when the header is invalid. Otherwise the records up to the first invalid one are returned,
+    /// together with the error of the invalid record, e.g. when the journal was only partially written.
+    pub(crate) fn load_all_from_bytes(
+        bytes: &[u8],
+    ) -> anyhow::Result<(Vec<Self>, Option<anyhow::Error>)> {
+        if bytes.len() < JOURNAL_HEADER_LEN || !bytes.starts_with(JOURNAL_MAGIC) {
+            return Err(anyhow::anyhow!("Invalid journal header."));
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[JOURNAL_MAGIC.len()..JOURNAL_HEADER_LEN]);
+        let version = u32::from_le_bytes(version);
+        if version != JOURNAL_FORMAT_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported journal format version: {version}."
+            ));
+        }
+
+        let mut records = vec![];
+        let mut rest = &bytes[JOURNAL_HEADER_LEN..];
+        while !rest.is_empty() {
+            match Self::load_from_bytes(rest) {
+                Ok((record, len)) => {
+                    records.push(record);
+                    rest = &rest[len..];
+                }
+                Err(e) => return Ok((records, Some(e))),
+            }
+        }
+        Ok((records, None))
+    }
+
+    /// Decode the length prefixed record at the start of the bytes.
+    ///
+    /// Returns the record and the number of bytes it occupies.
+    fn load_from_bytes(bytes: &[u8]) -> anyhow::Result<(Self, usize)> {
+        let len_bytes = bytes
+            .get(..4)
+            .ok_or_else(|| anyhow::anyhow!("Journal record length is truncated."))?;
+        let mut len = [0; 4];
+        len.copy_from_slice(len_bytes);
+        let end = 4 + u32::from_le_bytes(len) as usize;
+        let compressed = bytes
+            .get(4..end)
+            .ok_or_else(|| anyhow::anyhow!("Journal record is truncated."))?;
+        let record = serde_json::from_slice::<Self>(
+            &decompress_from_gzip(compressed).context("decompressing bytes failed.")?,
+        )
+        .context("deserializing JournalRecord failed.")?;
+        Ok((record, end))
+    }
+}
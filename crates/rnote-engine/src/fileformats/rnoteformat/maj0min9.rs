@@ -8,6 +8,20 @@ pub struct RnoteFileMaj0Min9 {
     /// A snapshot of the engine.
     #[serde(rename = "engine_snapshot")]
     pub engine_snapshot: ijson::IValue,
+    /// A preview image of the document, for file managers to show.
+    ///
+    /// Optional, so that files without it stay compatible in both directions.
+    #[serde(default, rename = "thumbnail")]
+    pub thumbnail: Option<RnoteThumbnail>,
+}
+
+/// A preview image of the document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "rnote_thumbnail")]
+pub struct RnoteThumbnail {
+    /// The png encoded image, (de)serialized with base64 encoding.
+    #[serde(rename = "png_data", with = "rnote_compose::serialize::sliceu8_base64")]
+    pub png_data: Vec<u8>,
 }
 
 impl TryFrom<RnoteFileMaj0Min6> for RnoteFileMaj0Min9 {
@@ -23,6 +37,7 @@ impl TryFrom<RnoteFileMaj0Min6> for RnoteFileMaj0Min9 {
 
         Ok(Self {
             engine_snapshot: value.engine_snapshot,
+            thumbnail: None,
         })
     }
 }
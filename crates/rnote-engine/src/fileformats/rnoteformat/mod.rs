@@ -6,6 +6,7 @@
 //! Then [TryFrom] can be implemented to allow conversions and chaining from older to newer versions.
 
 // Modules
+pub(crate) mod journal;
 pub(crate) mod maj0min5patch8;
 pub(crate) mod maj0min5patch9;
 pub(crate) mod maj0min6;
@@ -15,7 +16,7 @@ pub(crate) mod maj0min9;
 use self::maj0min5patch8::RnoteFileMaj0Min5Patch8;
 use self::maj0min5patch9::RnoteFileMaj0Min5Patch9;
 use self::maj0min6::RnoteFileMaj0Min6;
-use self::maj0min9::RnoteFileMaj0Min9;
+use self::maj0min9::{RnoteFileMaj0Min9, RnoteThumbnail};
 use super::{FileFormatLoader, FileFormatSaver};
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
@@ -69,6 +70,23 @@ struct RnotefileWrapper {
     data: ijson::IValue,
 }
 
+/// Only the thumbnail of the rnote file.
+///
+/// All other fields, including the engine snapshot, are skipped while deserializing
+/// instead of being loaded.
+#[derive(Debug, Deserialize)]
+#[serde(rename = "rnotefile_wrapper")]
+struct RnotefileThumbnailWrapper {
+    #[serde(rename = "data")]
+    data: RnotefileThumbnailData,
+}
+
+#[derive(Debug, Deserialize)]
+struct RnotefileThumbnailData {
+    #[serde(default, rename = "thumbnail")]
+    thumbnail: Option<RnoteThumbnail>,
+}
+
 /// The Rnote file in the newest format version.
 ///
 /// This struct exists to allow for upgrading older versions before loading the file in.
@@ -76,6 +94,17 @@ pub type RnoteFile = RnoteFileMaj0Min9;
 
 impl RnoteFile {
     pub const SEMVER: &'static str = crate::utils::crate_version();
+
+    /// Load only the png encoded thumbnail from the bytes of a .rnote file.
+    ///
+    /// None if the file was saved without one, e.g. by older versions.
+    pub fn load_thumbnail_from_bytes(bytes: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let wrapper = serde_json::from_slice::<RnotefileThumbnailWrapper>(
+            &decompress_from_gzip(bytes).context("decompressing bytes failed.")?,
+        )
+        .context("deserializing RnotefileThumbnailWrapper from bytes failed.")?;
+        Ok(wrapper.data.thumbnail.map(|thumbnail| thumbnail.png_data))
+    }
 }
 
 impl FileFormatLoader for RnoteFile {
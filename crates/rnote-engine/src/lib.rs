@@ -12,6 +12,7 @@
 pub mod audioplayer;
 pub mod camera;
 pub mod cloneconfig;
+pub mod colorpalettes;
 pub mod document;
 pub mod drawable;
 pub mod engine;
@@ -24,6 +25,8 @@ pub mod snap;
 pub mod store;
 pub mod strokes;
 pub mod tasks;
+#[cfg(test)]
+pub(crate) mod testsupport;
 pub mod utils;
 pub mod widgetflags;
 
@@ -31,6 +34,7 @@ pub mod widgetflags;
 pub use audioplayer::AudioPlayer;
 pub use camera::Camera;
 pub use cloneconfig::CloneConfig;
+pub use colorpalettes::{ColorPalette, ColorPalettes};
 pub use document::Document;
 pub use drawable::Drawable;
 pub use drawable::DrawableOnDoc;
@@ -1,5 +1,6 @@
 // Imports
-use super::pensconfig::brushconfig::BrushStyle;
+use super::pensconfig::brushconfig::{BrushStyle, PressureSimulation};
+use super::stabilizer::Stabilizer;
 use super::PenBehaviour;
 use super::PenStyle;
 use crate::engine::{EngineView, EngineViewMut};
@@ -9,22 +10,228 @@ use crate::strokes::Stroke;
 use crate::{DrawableOnDoc, WidgetFlags};
 use p2d::bounding_volume::{Aabb, BoundingVolume};
 use piet::RenderContext;
+use rand::{Rng, SeedableRng};
 use rnote_compose::builders::buildable::{Buildable, BuilderCreator, BuilderProgress};
 use rnote_compose::builders::{
     PenPathBuilderType, PenPathCurvedBuilder, PenPathModeledBuilder, PenPathSimpleBuilder,
 };
+use rnote_compose::constraints::ConstraintRatio;
 use rnote_compose::eventresult::{EventPropagation, EventResult};
-use rnote_compose::penevent::{PenEvent, PenProgress};
+use rnote_compose::penevent::{InputSource, ModifierKey, PenEvent, PenProgress};
 use rnote_compose::penpath::{Element, Segment};
 use rnote_compose::Constraints;
+use std::collections::{HashSet, VecDeque};
 use std::time::Instant;
 
+/// Simulates the pressure of input that doesn't report it, for the duration of a stroke.
+#[derive(Debug)]
+struct PressureSimulator {
+    simulation: PressureSimulation,
+    /// The position and time of the last element that the speed was calculated from.
+    last: Option<(na::Vector2<f64>, Instant)>,
+    /// The recent speeds, in document coordinates per second.
+    speeds: VecDeque<f64>,
+    rng: rand_pcg::Pcg64,
+}
+
+impl PressureSimulator {
+    /// The number of recent speeds that are averaged.
+    const SPEED_WINDOW_LEN: usize = 5;
+
+    fn new(simulation: PressureSimulation) -> Self {
+        let seed = match simulation {
+            PressureSimulation::Random { seed, .. } => seed,
+            _ => 0,
+        };
+        Self {
+            simulation,
+            last: None,
+            speeds: VecDeque::with_capacity(Self::SPEED_WINDOW_LEN),
+            rng: rand_pcg::Pcg64::seed_from_u64(seed),
+        }
+    }
+
+    fn record_speed(&mut self, pos: na::Vector2<f64>, now: Instant) {
+        let Some((last_pos, last_time)) = self.last else {
+            self.last = Some((pos, now));
+            return;
+        };
+        let dt = now.saturating_duration_since(last_time).as_secs_f64();
+        // Elements of the same event can share the time, their distance is then added to the next speed
+        if dt <= 0.0 {
+            return;
+        }
+        if self.speeds.len() >= Self::SPEED_WINDOW_LEN {
+            self.speeds.pop_front();
+        }
+        self.speeds.push_back((pos - last_pos).magnitude() / dt);
+        self.last = Some((pos, now));
+    }
+
+    /// The average of the recent speeds.
+    fn smoothed_speed(&self) -> f64 {
+        if self.speeds.is_empty() {
+            return 0.0;
+        }
+        self.speeds.iter().sum::<f64>() / self.speeds.len() as f64
+    }
+
+    /// Replace the pressure of the element with the simulated one.
+    fn simulate(&mut self, mut element: Element, now: Instant) -> Element {
+        self.record_speed(element.pos, now);
+        let pressure = match self.simulation {
+            PressureSimulation::ConstantFull => 1.0,
+            PressureSimulation::SpeedBased { sensitivity } => {
+                PressureSimulation::pressure_for_speed(self.smoothed_speed(), sensitivity)
+            }
+            PressureSimulation::Random { variation, .. } => {
+                let variation = if variation.is_finite() {
+                    variation.clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                1.0 - variation * self.rng.gen::<f64>()
+            }
+        };
+        element.set_pressure_clamped(pressure);
+        element
+    }
+
+    fn simulate_event(&mut self, event: PenEvent, now: Instant) -> PenEvent {
+        match event {
+            PenEvent::Down {
+                element,
+                modifier_keys,
+                pen_button,
+            } => PenEvent::down(self.simulate(element, now), modifier_keys)
+                .with_pen_button(pen_button),
+            PenEvent::Up {
+                element,
+                modifier_keys,
+                pen_button,
+            } => {
+                PenEvent::up(self.simulate(element, now), modifier_keys).with_pen_button(pen_button)
+            }
+            event => event,
+        }
+    }
+}
+
+/// A straight line that the pen path is constrained to.
+#[derive(Debug, Clone, Copy)]
+struct StraightLine {
+    start: na::Vector2<f64>,
+    /// Is determined once the pen moved far enough away from the start.
+    direction: Option<na::Vector2<f64>>,
+}
+
+/// Constrains the pen path to a straight line while Shift is held, for the duration of a stroke.
+#[derive(Debug)]
+struct LineConstraint {
+    /// The last position of the pen path.
+    last_pos: na::Vector2<f64>,
+    /// Only present while Shift is held.
+    line: Option<StraightLine>,
+}
+
+impl LineConstraint {
+    /// The distance the pen needs to move away from the start of the line to determine its direction,
+    /// in surface coordinates.
+    const DIRECTION_THRESHOLD: f64 = 4.0;
+
+    fn new(start: na::Vector2<f64>) -> Self {
+        Self {
+            last_pos: start,
+            line: None,
+        }
+    }
+
+    /// The constraints snapping the line angle to multiples of 45°.
+    fn angle_snap_constraints() -> Constraints {
+        Constraints {
+            enabled: true,
+            ratios: HashSet::from([
+                ConstraintRatio::Horizontal,
+                ConstraintRatio::Vertical,
+                ConstraintRatio::OneToOne,
+            ]),
+        }
+    }
+
+    /// Project the position of the element onto the line while Shift is held.
+    ///
+    /// The line starts where the pen path was when Shift was pressed.
+    /// Its angle is snapped to multiples of 45° when Ctrl is held as well. The pressure of the element is kept.
+    fn constrain(
+        &mut self,
+        mut element: Element,
+        modifier_keys: &HashSet<ModifierKey>,
+        total_zoom: f64,
+    ) -> Element {
+        if !modifier_keys.contains(&ModifierKey::KeyboardShift) {
+            self.line = None;
+            self.last_pos = element.pos;
+            return element;
+        }
+        let line = self.line.get_or_insert(StraightLine {
+            start: self.last_pos,
+            direction: None,
+        });
+        if line.direction.is_none() {
+            let offset = element.pos - line.start;
+            if offset.magnitude() * total_zoom >= Self::DIRECTION_THRESHOLD {
+                let offset = if modifier_keys.contains(&ModifierKey::KeyboardCtrl) {
+                    Self::angle_snap_constraints().constrain(offset)
+                } else {
+                    offset
+                };
+                line.direction = Some(offset.normalize());
+            }
+        }
+        element.pos = match line.direction {
+            Some(direction) => line.start + direction * (element.pos - line.start).dot(&direction),
+            None => line.start,
+        };
+        self.last_pos = element.pos;
+        element
+    }
+
+    fn constrain_event(&mut self, event: PenEvent, total_zoom: f64) -> PenEvent {
+        match event {
+            PenEvent::Down {
+                element,
+                modifier_keys,
+                pen_button,
+            } => PenEvent::down(
+                self.constrain(element, &modifier_keys, total_zoom),
+                modifier_keys,
+            )
+            .with_pen_button(pen_button),
+            PenEvent::Up {
+                element,
+                modifier_keys,
+                pen_button,
+            } => PenEvent::up(
+                self.constrain(element, &modifier_keys, total_zoom),
+                modifier_keys,
+            )
+            .with_pen_button(pen_button),
+            event => event,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum BrushState {
     Idle,
     Drawing {
         path_builder: Box<dyn Buildable<Emit = Segment>>,
         current_stroke_key: StrokeKey,
+        /// Only present when the pressure of the input is simulated.
+        pressure_simulator: Option<PressureSimulator>,
+        /// Only present when the input is stabilized.
+        stabilizer: Option<Stabilizer>,
+        line_constraint: LineConstraint,
     },
 }
 
@@ -46,7 +253,7 @@ impl PenBehaviour for Brush {
         WidgetFlags::default()
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
         WidgetFlags::default()
     }
 
@@ -67,7 +274,14 @@ impl PenBehaviour for Brush {
         let mut widget_flags = WidgetFlags::default();
 
         let event_result = match (&mut self.state, event) {
-            (BrushState::Idle, PenEvent::Down { element, .. }) => {
+            (
+                BrushState::Idle,
+                PenEvent::Down {
+                    element,
+                    modifier_keys,
+                    ..
+                },
+            ) => {
                 if !element.filter_by_bounds(
                     engine_view
                         .document
@@ -82,6 +296,37 @@ impl PenBehaviour for Brush {
 
                     engine_view.pens_config.brush_config.new_style_seeds();
 
+                    let mut stabilizer =
+                        Stabilizer::new(engine_view.pens_config.brush_config.input_stabilization);
+                    let element = match stabilizer.as_mut() {
+                        Some(stabilizer) => {
+                            stabilizer.stabilize(element, engine_view.camera.total_zoom())
+                        }
+                        None => element,
+                    };
+                    let mut line_constraint = LineConstraint::new(element.pos);
+                    let element = line_constraint.constrain(
+                        element,
+                        &modifier_keys,
+                        engine_view.camera.total_zoom(),
+                    );
+
+                    // Real pressure of stylus input is never replaced
+                    let mut pressure_simulator = (engine_view
+                        .pens_config
+                        .brush_config
+                        .simulate_pressure_for_mouse
+                        && element.source == InputSource::Mouse)
+                        .then(|| {
+                            PressureSimulator::new(
+                                engine_view.pens_config.brush_config.pressure_simulation,
+                            )
+                        });
+                    let element = match pressure_simulator.as_mut() {
+                        Some(pressure_simulator) => pressure_simulator.simulate(element, now),
+                        None => element,
+                    };
+
                     let brushstroke = Stroke::BrushStroke(BrushStroke::new(
                         element,
                         engine_view
@@ -112,6 +357,9 @@ impl PenBehaviour for Brush {
                             now,
                         ),
                         current_stroke_key,
+                        pressure_simulator,
+                        stabilizer,
+                        line_constraint,
                     };
 
                     EventResult {
@@ -167,9 +415,24 @@ impl PenBehaviour for Brush {
                 BrushState::Drawing {
                     path_builder,
                     current_stroke_key,
+                    pressure_simulator,
+                    stabilizer,
+                    line_constraint,
                 },
                 pen_event,
             ) => {
+                let pen_event = match stabilizer.as_mut() {
+                    Some(stabilizer) => {
+                        stabilizer.stabilize_event(pen_event, engine_view.camera.total_zoom())
+                    }
+                    None => pen_event,
+                };
+                let pen_event =
+                    line_constraint.constrain_event(pen_event, engine_view.camera.total_zoom());
+                let pen_event = match pressure_simulator.as_mut() {
+                    Some(pressure_simulator) => pressure_simulator.simulate_event(pen_event, now),
+                    None => pen_event,
+                };
                 let builder_result =
                     path_builder.handle_event(pen_event, now, Constraints::default());
                 let handled = builder_result.handled;
@@ -336,3 +599,208 @@ fn new_builder(
         PenPathBuilderType::Modeled => Box::new(PenPathModeledBuilder::start(element, now)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pens::PenStyle;
+    use crate::Engine;
+    use approx::assert_relative_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn speed_pressure_clamped() {
+        assert_relative_eq!(PressureSimulation::pressure_for_speed(0.0, 1.0), 1.0);
+        assert_relative_eq!(
+            PressureSimulation::pressure_for_speed(PressureSimulation::SPEED_REFERENCE, 1.0),
+            0.5
+        );
+        assert_relative_eq!(
+            PressureSimulation::pressure_for_speed(1e9, 1.0),
+            PressureSimulation::PRESSURE_MIN
+        );
+        // invalid speeds and sensitivities don't reduce the pressure
+        assert_relative_eq!(PressureSimulation::pressure_for_speed(-100.0, 1.0), 1.0);
+        assert_relative_eq!(PressureSimulation::pressure_for_speed(f64::NAN, 1.0), 1.0);
+        assert_relative_eq!(PressureSimulation::pressure_for_speed(100.0, -1.0), 1.0);
+        assert_relative_eq!(
+            PressureSimulation::pressure_for_speed(100.0, f64::INFINITY),
+            1.0
+        );
+    }
+
+    #[test]
+    fn speed_pressure_smoothed() {
+        let mut simulator =
+            PressureSimulator::new(PressureSimulation::SpeedBased { sensitivity: 1.0 });
+        let start = Instant::now();
+        let mut pos = na::vector![0.0, 0.0];
+        let mut pressure = 0.0;
+        // steady at the reference speed
+        for i in 0..=10 {
+            pos[0] = i as f64 * 100.0;
+            pressure = simulator
+                .simulate(
+                    Element::new(pos, 0.0),
+                    start + Duration::from_millis(i * 100),
+                )
+                .pressure;
+        }
+        assert_relative_eq!(pressure, 0.5);
+
+        // a single jump only changes the pressure by a part of its own
+        pos[0] += 1000.0;
+        let spike_time = start + Duration::from_millis(1100);
+        let after_spike = simulator
+            .simulate(Element::new(pos, 0.0), spike_time)
+            .pressure;
+        assert!(after_spike < 0.5);
+        assert!(after_spike > PressureSimulation::pressure_for_speed(10_000.0, 1.0));
+
+        // elements without elapsed time don't produce infinite speeds
+        pos[0] += 100.0;
+        let same_time = simulator
+            .simulate(Element::new(pos, 0.0), spike_time)
+            .pressure;
+        assert_relative_eq!(same_time, after_spike);
+    }
+
+    #[test]
+    fn random_pressure_reproducible() {
+        let simulation = PressureSimulation::Random {
+            seed: 42,
+            variation: 0.5,
+        };
+        let now = Instant::now();
+        let pressures = |simulation| {
+            let mut simulator = PressureSimulator::new(simulation);
+            (0..20)
+                .map(|i| {
+                    simulator
+                        .simulate(Element::new(na::vector![i as f64, 0.0], 0.0), now)
+                        .pressure
+                })
+                .collect::<Vec<f64>>()
+        };
+        let first = pressures(simulation);
+        assert_eq!(first, pressures(simulation));
+        assert!(first.iter().all(|p| (0.5..=1.0).contains(p)));
+    }
+
+    #[test]
+    fn stylus_pressure_not_simulated() {
+        let mut engine = Engine::default();
+        engine.pens_config.brush_config.simulate_pressure_for_mouse = true;
+        engine.pens_config.brush_config.pressure_simulation = PressureSimulation::ConstantFull;
+        let _ = engine.change_pen_style(PenStyle::Brush);
+
+        let mut draw = |element: Element| {
+            let now = Instant::now();
+            for event in [
+                PenEvent::down(element, HashSet::new()),
+                PenEvent::up(element, HashSet::new()),
+            ] {
+                let _ = engine.handle_pen_event(event, None, now);
+            }
+            let key = *engine.store.keys_sorted_chrono().last().unwrap();
+            match engine.store.get_stroke_ref(key) {
+                Some(Stroke::BrushStroke(brushstroke)) => brushstroke.path.start.pressure,
+                _ => panic!("no brush stroke drawn"),
+            }
+        };
+        let element = Element::new(na::vector![10.0, 10.0], 0.3);
+        assert_relative_eq!(draw(element.with_source(InputSource::Stylus)), 0.3);
+        assert_relative_eq!(draw(element.with_source(InputSource::Mouse)), 1.0);
+    }
+
+    /// Draw a stroke with the Simple builder, holding the modifiers from the element at the index on.
+    ///
+    /// Returns the end positions of the segments, the Simple builder emits a segment for every element.
+    fn draw_path(
+        engine: &mut Engine,
+        path: &[na::Vector2<f64>],
+        modifiers_from: usize,
+        modifiers: &[ModifierKey],
+    ) -> Vec<na::Vector2<f64>> {
+        engine.pens_config.brush_config.builder_type = PenPathBuilderType::Simple;
+        let _ = engine.change_pen_style(PenStyle::Brush);
+        let now = Instant::now();
+        let (last, path) = path.split_last().unwrap();
+        let modifier_keys = |i: usize| {
+            if i >= modifiers_from {
+                HashSet::from_iter(modifiers.iter().copied())
+            } else {
+                HashSet::new()
+            }
+        };
+        for (i, pos) in path.iter().enumerate() {
+            let _ = engine.handle_pen_event(
+                PenEvent::down(Element::new(*pos, 0.5), modifier_keys(i)),
+                None,
+                now,
+            );
+        }
+        let _ = engine.handle_pen_event(
+            PenEvent::up(Element::new(*last, 0.5), modifier_keys(path.len())),
+            None,
+            now,
+        );
+        let key = *engine.store.keys_sorted_chrono().last().unwrap();
+        match engine.store.get_stroke_ref(key) {
+            Some(Stroke::BrushStroke(brushstroke)) => brushstroke
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.end().pos)
+                .collect(),
+            _ => panic!("no brush stroke drawn"),
+        }
+    }
+
+    fn wobbly_path(direction: na::Vector2<f64>) -> Vec<na::Vector2<f64>> {
+        let normal = na::vector![-direction[1], direction[0]];
+        (0..20)
+            .map(|i| direction * (i as f64 * 10.0) + normal * if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect()
+    }
+
+    fn assert_collinear(points: &[na::Vector2<f64>]) {
+        let start = points[0];
+        let direction = (points[points.len() - 1] - start).normalize();
+        for point in points {
+            assert_relative_eq!(direction.perp(&(point - start)), 0.0, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn shift_constrains_to_straight_line() {
+        let mut engine = Engine::default();
+        let path = wobbly_path(na::vector![1.0, 0.2].normalize());
+
+        let points = draw_path(&mut engine, &path, 0, &[ModifierKey::KeyboardShift]);
+        assert_eq!(points.len(), path.len());
+        assert_relative_eq!(points[0], path[0]);
+        assert_collinear(&points);
+
+        // pressing Shift mid-stroke only constrains the path from there on
+        let points = draw_path(&mut engine, &path, 10, &[ModifierKey::KeyboardShift]);
+        assert_eq!(points[..10], path[..10]);
+        assert_collinear(&points[9..]);
+
+        // Ctrl additionally snaps the angle
+        let path = wobbly_path(na::vector![1.0, 0.9].normalize());
+        let points = draw_path(
+            &mut engine,
+            &path,
+            0,
+            &[ModifierKey::KeyboardShift, ModifierKey::KeyboardCtrl],
+        );
+        assert_collinear(&points);
+        let offset = points[points.len() - 1] - points[0];
+        assert_relative_eq!(offset[0], offset[1], epsilon = 1e-9);
+
+        // unconstrained without Shift
+        let points = draw_path(&mut engine, &path, 0, &[]);
+        assert_eq!(points, path);
+    }
+}
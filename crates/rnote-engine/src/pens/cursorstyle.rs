@@ -0,0 +1,30 @@
+/// The cursor the UI should show for the current state of the pen.
+///
+/// The UI maps the styles to the cursors of the toolkit, [CursorStyle::Default] leaves its regular or drawing cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorStyle {
+    /// The regular cursor of the UI, or its drawing cursor while the pen is down.
+    Default,
+    /// Selecting a region or aiming at a position.
+    Crosshair,
+    /// Hovering over something that can be dragged.
+    Grab,
+    /// Dragging.
+    Grabbing,
+    /// Resizing from the top-left or the bottom-right corner.
+    ResizeNwSe,
+    /// Resizing from the top-right or the bottom-left corner.
+    ResizeNeSw,
+    /// Rotating.
+    Rotate,
+    /// Inserting or editing text.
+    Text,
+    /// Erasing.
+    Eraser,
+}
+
+impl Default for CursorStyle {
+    fn default() -> Self {
+        Self::Default
+    }
+}
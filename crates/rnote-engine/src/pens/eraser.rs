@@ -1,7 +1,7 @@
 // Imports
 use super::pensconfig::eraserconfig::EraserStyle;
 use super::PenBehaviour;
-use super::PenStyle;
+use super::{CursorStyle, PenStyle};
 use crate::engine::{EngineView, EngineViewMut};
 use crate::{DrawableOnDoc, WidgetFlags};
 use p2d::bounding_volume::{Aabb, BoundingVolume};
@@ -38,7 +38,7 @@ impl PenBehaviour for Eraser {
         WidgetFlags::default()
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
         WidgetFlags::default()
     }
 
@@ -46,6 +46,14 @@ impl PenBehaviour for Eraser {
         PenStyle::Eraser
     }
 
+    fn current_cursor(&self, _engine_view: &EngineView) -> CursorStyle {
+        match self.state {
+            // The drawing cursor is shown while erasing
+            EraserState::Down(_) => CursorStyle::Default,
+            EraserState::Up | EraserState::Proximity(_) => CursorStyle::Eraser,
+        }
+    }
+
     fn update_state(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
         WidgetFlags::default()
     }
@@ -220,18 +228,25 @@ impl DrawableOnDoc for Eraser {
 fn erase(element: Element, engine_view: &mut EngineViewMut) -> WidgetFlags {
     // the widget_flags.store_modified flag is set in the `.trash_..()` methods
     let mut widget_flags = WidgetFlags::default();
+    let selection = if engine_view.pens_config.eraser_config.restrict_to_selection {
+        engine_view.store.selection_keys_unordered()
+    } else {
+        vec![]
+    };
+    // Without a selection the eraser is not restricted
+    let restrict_to = (!selection.is_empty()).then_some(selection.as_slice());
 
     match &engine_view.pens_config.eraser_config.style {
         EraserStyle::TrashCollidingStrokes => {
             widget_flags |= engine_view.store.trash_colliding_strokes(
                 engine_view.pens_config.eraser_config.eraser_bounds(element),
-                engine_view.camera.viewport(),
+                restrict_to,
             );
         }
         EraserStyle::SplitCollidingStrokes => {
             let (modified_strokes, wf) = engine_view.store.split_colliding_strokes(
                 engine_view.pens_config.eraser_config.eraser_bounds(element),
-                engine_view.camera.viewport(),
+                restrict_to,
             );
             widget_flags |= wf;
 
@@ -241,7 +256,74 @@ fn erase(element: Element, engine_view: &mut EngineViewMut) -> WidgetFlags {
                 engine_view.camera.image_scale(),
             );
         }
+        EraserStyle::SplitStrokes => {
+            let (new_strokes, wf) = engine_view.store.cut_colliding_strokes(
+                engine_view.pens_config.eraser_config.eraser_bounds(element),
+                restrict_to,
+            );
+            widget_flags |= wf;
+
+            engine_view.store.regenerate_rendering_for_strokes(
+                &new_strokes,
+                engine_view.camera.viewport(),
+                engine_view.camera.image_scale(),
+            );
+        }
     }
 
     widget_flags
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::insert_line_stroke;
+    use crate::Engine;
+    use rnote_compose::shapes::Shapeable;
+    use std::collections::HashSet;
+
+    #[test]
+    fn erase_restricted_to_selection() {
+        let mut engine = Engine::default();
+        engine.pens_config.eraser_config.style = EraserStyle::SplitStrokes;
+        engine.pens_config.eraser_config.restrict_to_selection = true;
+        let selected = insert_line_stroke(&mut engine.store, 0.0);
+        let unselected = insert_line_stroke(&mut engine.store, 2.0);
+        let _ = engine.record(Instant::now());
+        let _ = engine.change_pen_style(PenStyle::Selector);
+        engine.store.set_selected_keys(&[selected], true);
+        let _ = engine.current_pen_update_state();
+
+        // the selection is kept when switching to the eraser
+        let _ = engine.change_pen_style(PenStyle::Eraser);
+        assert_eq!(engine.store.selection_keys_unordered(), vec![selected]);
+        let unselected_bounds = engine.store.get_stroke_ref(unselected).unwrap().bounds();
+
+        let element = Element::new(na::vector![30.0, 1.0], 0.5);
+        let now = Instant::now();
+        for event in [
+            PenEvent::down(element, HashSet::new()),
+            PenEvent::up(element, HashSet::new()),
+        ] {
+            let _ = engine.handle_pen_event(event, None, now);
+        }
+
+        // only the selected stroke is cut apart, its parts stay selected
+        assert_eq!(engine.store.trashed(selected), Some(true));
+        assert_eq!(engine.store.trashed(unselected), Some(false));
+        assert_eq!(
+            engine.store.get_stroke_ref(unselected).unwrap().bounds(),
+            unselected_bounds
+        );
+        let parts = engine.store.selection_keys_unordered();
+        assert_eq!(parts.len(), 2);
+        assert!(!parts.contains(&unselected));
+
+        let _ = engine.change_pen_style(PenStyle::Selector);
+        let mut selection = engine.store.selection_keys_unordered();
+        selection.sort();
+        let mut parts = parts;
+        parts.sort();
+        assert_eq!(selection, parts);
+    }
+}
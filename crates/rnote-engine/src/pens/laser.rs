@@ -0,0 +1,280 @@
+// Imports
+use super::PenBehaviour;
+use super::PenStyle;
+use crate::engine::{EngineTask, EngineView, EngineViewMut};
+use crate::tasks::{PeriodicTaskHandle, PeriodicTaskResult};
+use crate::{DrawableOnDoc, WidgetFlags};
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use piet::RenderContext;
+use rnote_compose::eventresult::{EventPropagation, EventResult};
+use rnote_compose::ext::Vector2Ext;
+use rnote_compose::penevent::{PenEvent, PenProgress};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+struct LaserPoint {
+    pos: na::Vector2<f64>,
+    time: Instant,
+    /// Whether the point starts a new path, because the pen was lifted before.
+    starts_path: bool,
+}
+
+/// A pointer for presentations, drawing the recent pen path as a bright stroke that fades out.
+///
+/// The path is never added to the document.
+#[derive(Debug, Default)]
+pub struct Laser {
+    points: VecDeque<LaserPoint>,
+    pen_down: bool,
+    /// Periodically sends [EngineTask::LaserFade] while the path is fading out.
+    fade_task_handle: Option<PeriodicTaskHandle>,
+}
+
+impl Laser {
+    /// The interval of the fade animation.
+    const FADE_INTERVAL: Duration = Duration::from_millis(16);
+
+    /// Remove the faded out points and redraw the remaining ones.
+    ///
+    /// Stops the fade animation when the path has faded out entirely.
+    pub(crate) fn fade(&mut self, now: Instant, engine_view: &EngineView) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let fade_duration = engine_view.pens_config.laser_config.fade_duration();
+        while self
+            .points
+            .front()
+            .is_some_and(|point| now.saturating_duration_since(point.time) >= fade_duration)
+        {
+            self.points.pop_front();
+        }
+        if self.points.is_empty() && !self.pen_down {
+            self.fade_task_handle = None;
+        }
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    fn push_point(&mut self, pos: na::Vector2<f64>, now: Instant, engine_view: &EngineViewMut) {
+        self.points.push_back(LaserPoint {
+            pos,
+            time: now,
+            starts_path: !self.pen_down,
+        });
+        if self.fade_task_handle.is_none() {
+            let tasks_tx = engine_view.tasks_tx.clone();
+            self.fade_task_handle = Some(PeriodicTaskHandle::new(
+                move || {
+                    tasks_tx.send(EngineTask::LaserFade);
+                    PeriodicTaskResult::Continue
+                },
+                Self::FADE_INTERVAL,
+            ));
+        }
+    }
+
+    /// The opacity of the point, fading out linearly over the fade duration.
+    fn opacity(point: &LaserPoint, now: Instant, fade_duration: Duration) -> f64 {
+        (1.0 - now.saturating_duration_since(point.time).as_secs_f64()
+            / fade_duration.as_secs_f64())
+        .clamp(0.0, 1.0)
+    }
+}
+
+impl PenBehaviour for Laser {
+    fn init(&mut self, _engine_view: &EngineView) -> WidgetFlags {
+        WidgetFlags::default()
+    }
+
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        self.points.clear();
+        self.pen_down = false;
+        self.fade_task_handle = None;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+
+    fn style(&self) -> PenStyle {
+        PenStyle::Laser
+    }
+
+    fn update_state(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
+        WidgetFlags::default()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: PenEvent,
+        now: Instant,
+        engine_view: &mut EngineViewMut,
+    ) -> (EventResult<PenProgress>, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+
+        let event_result = match event {
+            PenEvent::Down { element, .. } => {
+                self.push_point(element.pos, now, engine_view);
+                self.pen_down = true;
+                widget_flags.redraw = true;
+
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::InProgress,
+                }
+            }
+            PenEvent::Up { element, .. } => {
+                if self.pen_down {
+                    self.push_point(element.pos, now, engine_view);
+                }
+                self.pen_down = false;
+                widget_flags.redraw = true;
+
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::Finished,
+                }
+            }
+            PenEvent::Cancel => {
+                // The path keeps fading out
+                self.pen_down = false;
+
+                EventResult {
+                    handled: false,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::Finished,
+                }
+            }
+            PenEvent::Proximity { .. } | PenEvent::KeyPressed { .. } | PenEvent::Text { .. } => {
+                EventResult {
+                    handled: false,
+                    propagate: EventPropagation::Proceed,
+                    progress: if self.pen_down {
+                        PenProgress::InProgress
+                    } else {
+                        PenProgress::Idle
+                    },
+                }
+            }
+        };
+
+        (event_result, widget_flags)
+    }
+}
+
+impl DrawableOnDoc for Laser {
+    fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<Aabb> {
+        let width = engine_view.pens_config.laser_config.width / engine_view.camera.total_zoom();
+        self.points
+            .iter()
+            .map(|point| Aabb::from_half_extents(point.pos.into(), na::Vector2::repeat(width)))
+            .reduce(|acc, bounds| acc.merged(&bounds))
+    }
+
+    fn draw_on_doc(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        cx.save().map_err(|e| anyhow::anyhow!("{e:?}"))?;
+
+        let now = Instant::now();
+        let laser_config = &engine_view.pens_config.laser_config;
+        let fade_duration = laser_config.fade_duration();
+        let width = laser_config.width / engine_view.camera.total_zoom();
+        let stroke_style = piet::StrokeStyle::default()
+            .line_cap(piet::LineCap::Round)
+            .line_join(piet::LineJoin::Round);
+
+        for (start, end) in self.points.iter().zip(self.points.iter().skip(1)) {
+            if end.starts_path {
+                continue;
+            }
+            let mut color = laser_config.color;
+            color.a *= Self::opacity(start, now, fade_duration);
+            cx.stroke_styled(
+                kurbo::Line::new(start.pos.to_kurbo_point(), end.pos.to_kurbo_point()),
+                &piet::Color::from(color),
+                width,
+                &stroke_style,
+            );
+        }
+        if let Some(last) = self.points.back().filter(|_| self.pen_down) {
+            cx.fill(
+                kurbo::Circle::new(last.pos.to_kurbo_point(), width * 0.5),
+                &piet::Color::from(laser_config.color),
+            );
+        }
+
+        cx.restore().map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Engine;
+    use rnote_compose::penpath::Element;
+    use std::collections::HashSet;
+
+    #[test]
+    fn laser_path_fades_without_modifying_store() {
+        let mut engine = Engine::default();
+        let mut laser = Laser::default();
+        let start = Instant::now();
+        let mut widget_flags = WidgetFlags::default();
+
+        for (i, pos) in [
+            na::vector![0.0, 0.0],
+            na::vector![10.0, 0.0],
+            na::vector![20.0, 5.0],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (_, wf) = laser.handle_event(
+                PenEvent::down(Element::new(pos, 0.5), HashSet::new()),
+                start + Duration::from_millis(10 * i as u64),
+                &mut engine.view_mut(),
+            );
+            widget_flags |= wf;
+        }
+        let (_, wf) = laser.handle_event(
+            PenEvent::up(Element::new(na::vector![30.0, 5.0], 0.5), HashSet::new()),
+            start + Duration::from_millis(30),
+            &mut engine.view_mut(),
+        );
+        widget_flags |= wf;
+        assert!(!widget_flags.store_modified);
+        assert_eq!(engine.store.stroke_keys_unordered().len(), 0);
+        assert!(laser.bounds_on_doc(&engine.view()).is_some());
+
+        // fading continues after the pen was lifted, until the path has faded out
+        let fade_duration = engine.pens_config.laser_config.fade_duration();
+        let widget_flags = laser.fade(start + fade_duration, &engine.view());
+        assert!(!widget_flags.store_modified);
+        assert_eq!(laser.points.len(), 3);
+        assert!(laser.fade_task_handle.is_some());
+        let _ = laser.fade(start + fade_duration * 2, &engine.view());
+        assert!(laser.points.is_empty());
+        assert!(laser.fade_task_handle.is_none());
+        assert!(laser.bounds_on_doc(&engine.view()).is_none());
+    }
+
+    #[test]
+    fn deinit_clears_path() {
+        let mut engine = Engine::default();
+        let mut laser = Laser::default();
+        let _ = laser.handle_event(
+            PenEvent::down(Element::new(na::vector![0.0, 0.0], 0.5), HashSet::new()),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        let widget_flags = laser.deinit(&mut engine.view_mut());
+        assert!(widget_flags.redraw);
+        assert!(laser.points.is_empty());
+        assert!(laser.fade_task_handle.is_none());
+    }
+}
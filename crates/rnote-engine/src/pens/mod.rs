@@ -1,6 +1,8 @@
 // Modules
 pub mod brush;
+pub mod cursorstyle;
 pub mod eraser;
+pub mod laser;
 pub mod penbehaviour;
 pub mod penholder;
 pub mod penmode;
@@ -8,19 +10,23 @@ pub mod pensconfig;
 pub mod selector;
 pub mod shaper;
 pub mod shortcuts;
+pub mod stabilizer;
 pub mod tools;
 pub mod typewriter;
 
 // Re-exports
 pub use brush::Brush;
+pub use cursorstyle::CursorStyle;
 pub use eraser::Eraser;
+pub use laser::Laser;
 pub use penbehaviour::PenBehaviour;
-pub use penholder::PenHolder;
+pub use penholder::{PenHolder, PenSwitchContext};
 pub use penmode::PenMode;
 pub use pensconfig::PensConfig;
 pub use selector::Selector;
 pub use shaper::Shaper;
 pub use shortcuts::Shortcuts;
+pub use stabilizer::InputStabilization;
 pub use tools::Tools;
 pub use typewriter::Typewriter;
 
@@ -44,6 +50,7 @@ pub enum Pen {
     Eraser(Eraser),
     Selector(Selector),
     Tools(Tools),
+    Laser(Laser),
 }
 
 impl Default for Pen {
@@ -61,17 +68,19 @@ impl PenBehaviour for Pen {
             Pen::Eraser(eraser) => eraser.init(engine_view),
             Pen::Selector(selector) => selector.init(engine_view),
             Pen::Tools(tools) => tools.init(engine_view),
+            Pen::Laser(laser) => laser.init(engine_view),
         }
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
+    fn deinit(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
         match self {
-            Pen::Brush(brush) => brush.deinit(),
-            Pen::Shaper(shaper) => shaper.deinit(),
-            Pen::Typewriter(typewriter) => typewriter.deinit(),
-            Pen::Eraser(eraser) => eraser.deinit(),
-            Pen::Selector(selector) => selector.deinit(),
-            Pen::Tools(tools) => tools.deinit(),
+            Pen::Brush(brush) => brush.deinit(engine_view),
+            Pen::Shaper(shaper) => shaper.deinit(engine_view),
+            Pen::Typewriter(typewriter) => typewriter.deinit(engine_view),
+            Pen::Eraser(eraser) => eraser.deinit(engine_view),
+            Pen::Selector(selector) => selector.deinit(engine_view),
+            Pen::Tools(tools) => tools.deinit(engine_view),
+            Pen::Laser(laser) => laser.deinit(engine_view),
         }
     }
 
@@ -83,6 +92,7 @@ impl PenBehaviour for Pen {
             Pen::Eraser(eraser) => eraser.style(),
             Pen::Selector(selector) => selector.style(),
             Pen::Tools(tools) => tools.style(),
+            Pen::Laser(laser) => laser.style(),
         }
     }
 
@@ -94,6 +104,24 @@ impl PenBehaviour for Pen {
             Pen::Eraser(eraser) => eraser.update_state(engine_view),
             Pen::Selector(selector) => selector.update_state(engine_view),
             Pen::Tools(tools) => tools.update_state(engine_view),
+            Pen::Laser(laser) => laser.update_state(engine_view),
+        }
+    }
+
+    fn update_state_after_history_change(
+        &mut self,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        match self {
+            Pen::Brush(brush) => brush.update_state_after_history_change(engine_view),
+            Pen::Shaper(shaper) => shaper.update_state_after_history_change(engine_view),
+            Pen::Typewriter(typewriter) => {
+                typewriter.update_state_after_history_change(engine_view)
+            }
+            Pen::Eraser(eraser) => eraser.update_state_after_history_change(engine_view),
+            Pen::Selector(selector) => selector.update_state_after_history_change(engine_view),
+            Pen::Tools(tools) => tools.update_state_after_history_change(engine_view),
+            Pen::Laser(laser) => laser.update_state_after_history_change(engine_view),
         }
     }
 
@@ -110,6 +138,31 @@ impl PenBehaviour for Pen {
             Pen::Eraser(eraser) => eraser.handle_event(event, now, engine_view),
             Pen::Selector(selector) => selector.handle_event(event, now, engine_view),
             Pen::Tools(tools) => tools.handle_event(event, now, engine_view),
+            Pen::Laser(laser) => laser.handle_event(event, now, engine_view),
+        }
+    }
+
+    fn current_cursor(&self, engine_view: &EngineView) -> CursorStyle {
+        match self {
+            Pen::Brush(brush) => brush.current_cursor(engine_view),
+            Pen::Shaper(shaper) => shaper.current_cursor(engine_view),
+            Pen::Typewriter(typewriter) => typewriter.current_cursor(engine_view),
+            Pen::Eraser(eraser) => eraser.current_cursor(engine_view),
+            Pen::Selector(selector) => selector.current_cursor(engine_view),
+            Pen::Tools(tools) => tools.current_cursor(engine_view),
+            Pen::Laser(laser) => laser.current_cursor(engine_view),
+        }
+    }
+
+    fn take_pen_switch_request(&mut self) -> Option<(PenStyle, PenSwitchContext)> {
+        match self {
+            Pen::Brush(brush) => brush.take_pen_switch_request(),
+            Pen::Shaper(shaper) => shaper.take_pen_switch_request(),
+            Pen::Typewriter(typewriter) => typewriter.take_pen_switch_request(),
+            Pen::Eraser(eraser) => eraser.take_pen_switch_request(),
+            Pen::Selector(selector) => selector.take_pen_switch_request(),
+            Pen::Tools(tools) => tools.take_pen_switch_request(),
+            Pen::Laser(laser) => laser.take_pen_switch_request(),
         }
     }
 
@@ -124,6 +177,7 @@ impl PenBehaviour for Pen {
             Pen::Eraser(eraser) => eraser.fetch_clipboard_content(engine_view),
             Pen::Selector(selector) => selector.fetch_clipboard_content(engine_view),
             Pen::Tools(tools) => tools.fetch_clipboard_content(engine_view),
+            Pen::Laser(laser) => laser.fetch_clipboard_content(engine_view),
         }
     }
 
@@ -138,6 +192,7 @@ impl PenBehaviour for Pen {
             Pen::Eraser(eraser) => eraser.cut_clipboard_content(engine_view),
             Pen::Selector(selector) => selector.cut_clipboard_content(engine_view),
             Pen::Tools(tools) => tools.cut_clipboard_content(engine_view),
+            Pen::Laser(laser) => laser.cut_clipboard_content(engine_view),
         }
     }
 }
@@ -151,6 +206,7 @@ impl DrawableOnDoc for Pen {
             Pen::Eraser(eraser) => eraser.bounds_on_doc(engine_view),
             Pen::Selector(selector) => selector.bounds_on_doc(engine_view),
             Pen::Tools(tools) => tools.bounds_on_doc(engine_view),
+            Pen::Laser(laser) => laser.bounds_on_doc(engine_view),
         }
     }
 
@@ -166,6 +222,7 @@ impl DrawableOnDoc for Pen {
             Pen::Eraser(eraser) => eraser.draw_on_doc(cx, engine_view),
             Pen::Selector(selector) => selector.draw_on_doc(cx, engine_view),
             Pen::Tools(tools) => tools.draw_on_doc(cx, engine_view),
+            Pen::Laser(laser) => laser.draw_on_doc(cx, engine_view),
         }
     }
 }
@@ -198,6 +255,8 @@ pub enum PenStyle {
     Selector,
     #[serde(rename = "tools")]
     Tools,
+    #[serde(rename = "laser")]
+    Laser,
 }
 
 impl Default for PenStyle {
@@ -226,6 +285,7 @@ impl std::str::FromStr for PenStyle {
             "eraser" => Ok(Self::Eraser),
             "selector" => Ok(Self::Selector),
             "tools" => Ok(Self::Tools),
+            "laser" => Ok(Self::Laser),
             s => Err(anyhow::anyhow!(
                 "Creating PenStyle from &str failed, invalid name {s}"
             )),
@@ -242,6 +302,7 @@ impl Display for PenStyle {
             PenStyle::Eraser => write!(f, "eraser"),
             PenStyle::Selector => write!(f, "selector"),
             PenStyle::Tools => write!(f, "tools"),
+            PenStyle::Laser => write!(f, "laser"),
         }
     }
 }
@@ -255,6 +316,7 @@ impl PenStyle {
             Self::Eraser => String::from("pen-eraser-symbolic"),
             Self::Selector => String::from("pen-selector-symbolic"),
             Self::Tools => String::from("pen-tools-symbolic"),
+            Self::Laser => String::from("pen-laser-symbolic"),
         }
     }
 }
@@ -1,5 +1,6 @@
 // Imports
-use super::PenStyle;
+use super::penholder::PenSwitchContext;
+use super::{CursorStyle, PenStyle};
 use crate::engine::{EngineView, EngineViewMut};
 use crate::{DrawableOnDoc, WidgetFlags};
 use futures::channel::oneshot;
@@ -16,7 +17,7 @@ pub trait PenBehaviour: DrawableOnDoc {
     fn init(&mut self, _engine_view: &EngineView) -> WidgetFlags;
 
     /// Deinit the pen.
-    fn deinit(&mut self) -> WidgetFlags;
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags;
 
     // The pen style.
     fn style(&self) -> PenStyle;
@@ -24,6 +25,16 @@ pub trait PenBehaviour: DrawableOnDoc {
     /// Update the pen and pen config state with the state from the engine.
     fn update_state(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags;
 
+    /// Update the pen state after the store history has changed ( undo / redo ).
+    ///
+    /// Strokes the pen holds on to might have been removed, trashed or modified in the meantime.
+    fn update_state_after_history_change(
+        &mut self,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        self.update_state(engine_view)
+    }
+
     /// Handle a pen event.
     fn handle_event(
         &mut self,
@@ -32,6 +43,18 @@ pub trait PenBehaviour: DrawableOnDoc {
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags);
 
+    /// The cursor the UI should show for the current state of the pen.
+    fn current_cursor(&self, _engine_view: &EngineView) -> CursorStyle {
+        CursorStyle::Default
+    }
+
+    /// Take the switch to another pen that was requested while handling the last event.
+    ///
+    /// The pen holder then installs the requested pen and hands the context over to it.
+    fn take_pen_switch_request(&mut self) -> Option<(PenStyle, PenSwitchContext)> {
+        None
+    }
+
     /// Fetch clipboard content from the pen.
     ///
     /// The fetched content can be available in multiple formats,
@@ -2,12 +2,14 @@
 use super::penmode::PenModeState;
 use super::shortcuts::ShortcutMode;
 use super::{
-    Brush, Eraser, Pen, PenBehaviour, PenMode, PenStyle, Selector, Shaper, Shortcuts, Tools,
-    Typewriter,
+    Brush, CursorStyle, Eraser, Laser, Pen, PenBehaviour, PenMode, PenStyle, Selector, Shaper,
+    Shortcuts, Tools, Typewriter,
 };
 use crate::camera::NudgeDirection;
 use crate::engine::{EngineView, EngineViewMut};
+use crate::pens::pensconfig::toolsconfig::ToolStyle;
 use crate::pens::shortcuts::ShortcutAction;
+use crate::store::StrokeKey;
 use crate::widgetflags::WidgetFlags;
 use crate::{CloneConfig, DrawableOnDoc};
 use futures::channel::oneshot;
@@ -17,6 +19,7 @@ use rnote_compose::eventresult::EventPropagation;
 use rnote_compose::penevent::{KeyboardKey, ModifierKey, PenEvent, PenProgress, ShortcutKey};
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
+use tracing::warn;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BacklogPolicy {
@@ -25,6 +28,44 @@ pub enum BacklogPolicy {
     DisableBacklog,
 }
 
+/// The state that is handed over to the pen that is switched to with [PenHolder::request_pen_switch].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PenSwitchContext {
+    /// Nothing is handed over.
+    None,
+    /// Edit the text stroke with the typewriter.
+    ///
+    /// The cursor is placed at the position in document coordinates, or at the end of the text when it is None.
+    EditText {
+        key: StrokeKey,
+        pos: Option<na::Vector2<f64>>,
+    },
+    /// Select the strokes with the selector.
+    Select(Vec<StrokeKey>),
+}
+
+/// What pushed a temporary pen override.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PenOverrideSource {
+    /// Pushed with [PenHolder::push_pen_override].
+    Requested,
+    /// The eraser end of the stylus came into proximity.
+    EraserEnd,
+    /// The pan key is held down.
+    PanKey,
+}
+
+/// A temporary pen that is stacked on top of the pen of the current style.
+#[derive(Debug)]
+struct PenOverride {
+    style: PenStyle,
+    source: PenOverrideSource,
+    /// The pen below the override, restored when it is popped.
+    prev_pen: Pen,
+    /// The tool style that is restored when the override is popped, if it was replaced by it.
+    prev_tool_style: Option<ToolStyle>,
+}
+
 /// The Penholder holds the pens and related state and handles pen events.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default, rename = "penholder")]
@@ -45,6 +86,18 @@ pub struct PenHolder {
     toggle_pen_style: Option<PenStyle>,
     #[serde(skip)]
     prev_shortcut_key: Option<ShortcutKey>,
+    /// The instant the pen style was last changed.
+    #[serde(skip)]
+    style_changed_at: Option<Instant>,
+    /// Whether pen events are prevented from modifying the strokes, see [PenHolder::set_read_only].
+    #[serde(skip)]
+    read_only: bool,
+    /// Whether the events of the current gesture are discarded, because it was rejected in read-only mode.
+    #[serde(skip)]
+    read_only_rejecting: bool,
+    /// The temporary pen override, see [PenHolder::push_pen_override].
+    #[serde(skip)]
+    pen_override: Option<PenOverride>,
 }
 
 impl Default for PenHolder {
@@ -58,6 +111,10 @@ impl Default for PenHolder {
             progress: PenProgress::Idle,
             toggle_pen_style: None,
             prev_shortcut_key: None,
+            style_changed_at: None,
+            read_only: false,
+            read_only_rejecting: false,
+            pen_override: None,
         }
     }
 }
@@ -132,7 +189,36 @@ impl PenHolder {
 
     /// Get the current style, or the override if it is set.
     pub fn current_pen_style_w_override(&self) -> PenStyle {
-        self.pen_mode_state.current_style_w_override()
+        match &self.pen_override {
+            Some(pen_override) => pen_override.style,
+            None => self.pen_mode_state.current_style_w_override(),
+        }
+    }
+
+    /// The style of the temporary pen override, see [Self::push_pen_override].
+    pub fn pen_override(&self) -> Option<PenStyle> {
+        self.pen_override
+            .as_ref()
+            .map(|pen_override| pen_override.style)
+    }
+
+    /// The instant the pen style was last changed, if it was changed during the current session.
+    pub fn style_changed_at(&self) -> Option<Instant> {
+        self.style_changed_at
+    }
+
+    /// Whether pen events are prevented from modifying the strokes.
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Set whether pen events are prevented from modifying the strokes.
+    ///
+    /// In read-only mode only the selector and the tools that move the view handle pen events,
+    /// and they are rejected as soon as they would modify the strokes.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+        self.read_only_rejecting = false;
     }
 
     /// The current pen progress.
@@ -140,6 +226,11 @@ impl PenHolder {
         self.progress
     }
 
+    /// The cursor the UI should show for the current pen.
+    pub fn current_cursor(&self, engine_view: &EngineView) -> CursorStyle {
+        self.current_pen.current_cursor(engine_view)
+    }
+
     pub fn current_pen_ref(&mut self) -> &Pen {
         &self.current_pen
     }
@@ -162,6 +253,53 @@ impl PenHolder {
         widget_flags
     }
 
+    /// Switch to the pen with the given style and hand the context over to it.
+    ///
+    /// Unlike [Self::change_style] the switch also replaces a temporary style override.
+    /// It is not recorded in the history, the strokes are only deselected and selected again.
+    pub fn request_pen_switch(
+        &mut self,
+        style: PenStyle,
+        context: PenSwitchContext,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let all_strokes = engine_view.store.selection_keys_as_rendered();
+        engine_view
+            .store
+            .set_selected_keys_keep_order(&all_strokes, false);
+        widget_flags |= self.discard_pen_override(engine_view).1;
+        self.pen_mode_state.take_style_override();
+        self.pen_mode_state.set_style(style);
+        self.style_changed_at = Some(Instant::now());
+        self.toggle_pen_style = None;
+        self.prev_shortcut_key = None;
+        widget_flags |= self.reinstall_pen_current_style(engine_view);
+
+        match (&mut self.current_pen, context) {
+            (Pen::Typewriter(typewriter), PenSwitchContext::EditText { key, pos }) => {
+                widget_flags |= typewriter.edit_text_stroke(key, pos, engine_view);
+            }
+            (Pen::Selector(selector), PenSwitchContext::Select(keys)) => {
+                engine_view.store.set_selected_keys(&keys, true);
+                widget_flags |= selector.update_state(engine_view);
+                widget_flags.selection_changed = true;
+            }
+            (_, PenSwitchContext::None) => {}
+            (pen, context) => {
+                warn!(
+                    "Pen switch context {context:?} can't be handed over to pen with style {:?}",
+                    pen.style()
+                );
+            }
+        }
+        widget_flags.store_modified = true;
+        widget_flags.refresh_ui = true;
+
+        widget_flags
+    }
+
     /// Change the style override.
     pub fn change_style_override(
         &mut self,
@@ -171,12 +309,23 @@ impl PenHolder {
         let mut widget_flags = WidgetFlags::default();
 
         if self.pen_mode_state.style_override() != new_style_override {
+            let keep_selection = Self::keeps_selection(
+                self.current_pen_style_w_override(),
+                new_style_override.unwrap_or(self.pen_mode_state.style()),
+                &engine_view.as_im(),
+            );
             // Deselecting when changing the style override
             let all_strokes = engine_view.store.selection_keys_as_rendered();
-            engine_view.store.set_selected_keys(&all_strokes, false);
+            engine_view
+                .store
+                .set_selected_keys_keep_order(&all_strokes, false);
 
+            widget_flags |= self.discard_pen_override(engine_view).1;
             self.pen_mode_state.set_style_override(new_style_override);
             widget_flags |= self.reinstall_pen_current_style(engine_view);
+            if keep_selection {
+                widget_flags |= self.restore_selection(&all_strokes, engine_view);
+            }
             widget_flags.refresh_ui = true;
         }
 
@@ -195,17 +344,113 @@ impl PenHolder {
 
         if self.pen_mode_state.pen_mode() != new_pen_mode {
             self.pen_mode_state.set_pen_mode(new_pen_mode);
-            widget_flags |= self.reinstall_pen_current_style(engine_view);
+            // The pen of the pen mode is kept while the eraser end of the stylus is in use
+            match new_pen_mode {
+                PenMode::Eraser if self.pen_override.is_none() => {
+                    widget_flags |= self.push_pen_override_int(
+                        self.pen_mode_state.current_style_w_override(),
+                        PenOverrideSource::EraserEnd,
+                        None,
+                        engine_view,
+                    );
+                }
+                PenMode::Pen
+                    if self.pen_override_source() == Some(PenOverrideSource::EraserEnd) =>
+                {
+                    widget_flags |= self.pop_pen_override(engine_view);
+                }
+                _ => {
+                    widget_flags |= self.reinstall_pen_current_style(engine_view);
+                }
+            }
             widget_flags.refresh_ui = true;
         }
 
         widget_flags
     }
 
+    /// Temporarily switch to a pen with the given style, stacked on top of the pen of the current style.
+    ///
+    /// The pen style config is not altered. An in-flight gesture of the current pen is cancelled,
+    /// otherwise its state is kept and restored when the override is popped with [Self::pop_pen_override].
+    /// Explicitly changing the pen style ends the override.
+    ///
+    /// Nested overrides are rejected, nothing is changed when an override is already active.
+    pub fn push_pen_override(
+        &mut self,
+        style: PenStyle,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        self.push_pen_override_int(style, PenOverrideSource::Requested, None, engine_view)
+    }
+
+    /// Remove the temporary pen override and restore the pen below it.
+    ///
+    /// When the style was changed in the meantime, a new pen for the current style is installed instead.
+    pub fn pop_pen_override(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let (prev_pen, mut widget_flags) = self.discard_pen_override(engine_view);
+        let Some(prev_pen) = prev_pen else {
+            return widget_flags;
+        };
+
+        if prev_pen.style() == self.current_pen_style_w_override() {
+            self.current_pen = prev_pen;
+            // The strokes might have been modified by the override
+            widget_flags |= self.current_pen.update_state(engine_view);
+        } else {
+            let mut new_pen = new_pen(self.current_pen_style_w_override());
+            widget_flags |= new_pen.init(&engine_view.as_im()) | new_pen.update_state(engine_view);
+            self.current_pen = new_pen;
+        }
+        widget_flags |= self.handle_changed_pen_style();
+        self.progress = PenProgress::Idle;
+        widget_flags.refresh_ui = true;
+
+        widget_flags
+    }
+
+    /// Temporarily switch to the camera offset tool while the pan key is held down.
+    ///
+    /// Rejected like [Self::push_pen_override] when an override is already active.
+    pub fn push_pan_override(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        if self.pen_override.is_some() {
+            return WidgetFlags::default();
+        }
+        let prev_tool_style = Some(std::mem::replace(
+            &mut engine_view.pens_config.tools_config.style,
+            ToolStyle::OffsetCamera,
+        ));
+        self.push_pen_override_int(
+            PenStyle::Tools,
+            PenOverrideSource::PanKey,
+            prev_tool_style,
+            engine_view,
+        )
+    }
+
+    /// Restore the pen when the pan key is released.
+    ///
+    /// Does nothing when the current override wasn't pushed by the pan key.
+    pub fn release_pan_override(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        if self.pen_override_source() != Some(PenOverrideSource::PanKey) {
+            return WidgetFlags::default();
+        }
+        self.pop_pen_override(engine_view)
+    }
+
     pub fn current_pen_update_state(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
         self.current_pen.update_state(engine_view)
     }
 
+    /// Update the current pen state after the store history has changed.
+    pub fn current_pen_update_state_after_history_change(
+        &mut self,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        self.current_pen
+            .update_state_after_history_change(engine_view)
+    }
+
     /// Reinstall the pen for the current style.
     pub fn reinstall_pen_current_style(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
         // first cancel the current pen
@@ -223,11 +468,15 @@ impl PenHolder {
         widget_flags
     }
 
-    pub fn deinit_current_pen(&mut self) -> WidgetFlags {
-        self.current_pen_mut().deinit()
+    pub fn deinit_current_pen(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        self.current_pen_mut().deinit(engine_view)
     }
 
     /// Handle a pen event.
+    ///
+    /// The event is first dispatched to the current pen. If the pen did not handle it, the global handler
+    /// gets a chance to. The returned propagation tells the caller whether the event should be passed on,
+    /// e.g. to the application shortcuts.
     pub fn handle_pen_event(
         &mut self,
         event: PenEvent,
@@ -236,16 +485,32 @@ impl PenHolder {
         engine_view: &mut EngineViewMut,
     ) -> (EventPropagation, WidgetFlags) {
         let mut widget_flags = WidgetFlags::default();
+        let cursor = self.current_cursor(&engine_view.as_im());
 
         if let Some(pen_mode) = pen_mode {
             widget_flags |= self.change_pen_mode(pen_mode, engine_view);
         }
+        if self.read_only {
+            let (propagate, wf) = self.handle_pen_event_read_only(event, now, engine_view);
+            widget_flags |= wf;
+            // Undo and redo stay unavailable
+            widget_flags.hide_undo = Some(true);
+            widget_flags.hide_redo = Some(true);
+            widget_flags.update_cursor |= self.current_cursor(&engine_view.as_im()) != cursor;
+            widget_flags.redraw = true;
+            return (propagate, widget_flags);
+        }
 
         // Handle the event with the current pen
         let (mut event_result, wf) = self
             .current_pen
             .handle_event(event.clone(), now, engine_view);
-        widget_flags |= wf | self.handle_pen_progress(event_result.progress, engine_view);
+        widget_flags |= wf;
+        if let Some((style, context)) = self.current_pen.take_pen_switch_request() {
+            widget_flags |= self.request_pen_switch(style, context, engine_view);
+        } else {
+            widget_flags |= self.handle_pen_progress(event_result.progress, engine_view);
+        }
 
         if !event_result.handled {
             let (propagate, wf) = self.handle_pen_event_global(event, now, engine_view);
@@ -253,6 +518,7 @@ impl PenHolder {
             widget_flags |= wf;
         }
 
+        widget_flags.update_cursor |= self.current_cursor(&engine_view.as_im()) != cursor;
         // Always redraw after handling a pen event
         widget_flags.redraw = true;
 
@@ -339,19 +605,203 @@ impl PenHolder {
     ) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
-        if self.pen_mode_state.style() != new_style {
+        if self.pen_mode_state.style() != new_style || self.pen_override.is_some() {
+            let keep_selection = Self::keeps_selection(
+                self.current_pen_style_w_override(),
+                new_style,
+                &engine_view.as_im(),
+            );
             // Deselecting when changing the style
             let all_strokes = engine_view.store.selection_keys_as_rendered();
-            engine_view.store.set_selected_keys(&all_strokes, false);
+            engine_view
+                .store
+                .set_selected_keys_keep_order(&all_strokes, false);
 
+            widget_flags |= self.discard_pen_override(engine_view).1;
             self.pen_mode_state.set_style(new_style);
+            self.style_changed_at = Some(Instant::now());
             widget_flags |= self.reinstall_pen_current_style(engine_view);
+            if keep_selection {
+                widget_flags |= self.restore_selection(&all_strokes, engine_view);
+            }
             widget_flags.refresh_ui = true;
         }
 
         widget_flags
     }
 
+    fn pen_override_source(&self) -> Option<PenOverrideSource> {
+        self.pen_override
+            .as_ref()
+            .map(|pen_override| pen_override.source)
+    }
+
+    fn push_pen_override_int(
+        &mut self,
+        style: PenStyle,
+        source: PenOverrideSource,
+        prev_tool_style: Option<ToolStyle>,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if self.pen_override.is_some() {
+            return widget_flags;
+        }
+
+        // Only the in-flight state is cancelled, e.g. a half-drawn lasso of the selector
+        if self.progress == PenProgress::InProgress {
+            let (_, wf) =
+                self.current_pen
+                    .handle_event(PenEvent::Cancel, Instant::now(), engine_view);
+            widget_flags |= wf;
+        }
+        let mut new_pen = new_pen(style);
+        widget_flags |= new_pen.init(&engine_view.as_im()) | new_pen.update_state(engine_view);
+        let prev_pen = std::mem::replace(&mut self.current_pen, new_pen);
+        self.pen_override = Some(PenOverride {
+            style,
+            source,
+            prev_pen,
+            prev_tool_style,
+        });
+        widget_flags |= self.handle_changed_pen_style();
+        self.progress = PenProgress::Idle;
+        widget_flags.refresh_ui = true;
+
+        widget_flags
+    }
+
+    /// Cancel the pen of the override and remove it, returning the pen below it.
+    ///
+    /// Leaves the current pen in place, it needs to be replaced by the caller.
+    fn discard_pen_override(
+        &mut self,
+        engine_view: &mut EngineViewMut,
+    ) -> (Option<Pen>, WidgetFlags) {
+        let Some(pen_override) = self.pen_override.take() else {
+            return (None, WidgetFlags::default());
+        };
+        let (_, widget_flags) =
+            self.current_pen
+                .handle_event(PenEvent::Cancel, Instant::now(), engine_view);
+        if let Some(tool_style) = pen_override.prev_tool_style {
+            engine_view.pens_config.tools_config.style = tool_style;
+        }
+        (Some(pen_override.prev_pen), widget_flags)
+    }
+
+    /// Whether the selection is kept when switching between the pen styles.
+    ///
+    /// An eraser that is restricted to the selection keeps it when switching from and back to the selector.
+    fn keeps_selection(from: PenStyle, to: PenStyle, engine_view: &EngineView) -> bool {
+        engine_view.pens_config.eraser_config.restrict_to_selection
+            && matches!(
+                (from, to),
+                (PenStyle::Selector, PenStyle::Eraser) | (PenStyle::Eraser, PenStyle::Selector)
+            )
+    }
+
+    /// Select the strokes again after the pen was switched, which deselected them.
+    fn restore_selection(
+        &mut self,
+        selection: &[StrokeKey],
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        // The strokes that were trashed in the meantime are not selected again
+        let selection = selection
+            .iter()
+            .copied()
+            .filter(|&key| engine_view.store.trashed(key) == Some(false))
+            .collect::<Vec<StrokeKey>>();
+        engine_view.store.set_selected_keys(&selection, true);
+        widget_flags |= self.current_pen.update_state(engine_view);
+        widget_flags.selection_changed = true;
+        widget_flags
+    }
+
+    /// Handle a pen event in read-only mode.
+    ///
+    /// Pens that would always create or modify strokes don't receive events at all. The others are rejected
+    /// when they modified the strokes after all, in which case the modifications are discarded
+    /// and the rest of the gesture is ignored.
+    fn handle_pen_event_read_only(
+        &mut self,
+        event: PenEvent,
+        now: Instant,
+        engine_view: &mut EngineViewMut,
+    ) -> (EventPropagation, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let gesture_ended = matches!(event, PenEvent::Up { .. } | PenEvent::Cancel);
+
+        if self.read_only_rejecting {
+            if gesture_ended {
+                self.read_only_rejecting = false;
+            }
+            return (EventPropagation::Stop, widget_flags);
+        }
+
+        let handles_events = match self.current_pen_style_w_override() {
+            // Pointing at the document doesn't modify it
+            PenStyle::Selector | PenStyle::Laser => true,
+            PenStyle::Tools => !matches!(
+                engine_view.pens_config.tools_config.style,
+                ToolStyle::VerticalSpace
+            ),
+            PenStyle::Brush | PenStyle::Shaper | PenStyle::Typewriter | PenStyle::Eraser => false,
+        };
+        if !handles_events {
+            if matches!(event, PenEvent::Down { .. } | PenEvent::Text { .. }) {
+                widget_flags.read_only_rejected = true;
+                self.read_only_rejecting = !gesture_ended && matches!(event, PenEvent::Down { .. });
+                return (EventPropagation::Stop, widget_flags);
+            }
+            // Navigating the view is still possible
+            return self.handle_pen_event_global(event, now, engine_view);
+        }
+
+        let checkpoint = engine_view.store.checkpoint();
+        let (event_result, wf) = self
+            .current_pen
+            .handle_event(event.clone(), now, engine_view);
+        widget_flags |= wf;
+        let pen_switch_request = self.current_pen.take_pen_switch_request();
+
+        // Modifications the pen recorded were already discarded by the store
+        if widget_flags.read_only_rejected
+            || engine_view.store.modified_since_checkpoint(&checkpoint)
+        {
+            widget_flags |= self.reinstall_pen_current_style(engine_view);
+            widget_flags |= engine_view.store.restore_checkpoint(checkpoint)
+                | self.current_pen.update_state(engine_view);
+            engine_view.store.regenerate_rendering_in_viewport_threaded(
+                engine_view.tasks_tx.clone(),
+                false,
+                engine_view.camera.viewport(),
+                engine_view.camera.image_scale(),
+            );
+            widget_flags.store_modified = false;
+            widget_flags.read_only_rejected = true;
+            self.read_only_rejecting = !gesture_ended;
+            return (EventPropagation::Stop, widget_flags);
+        }
+
+        if let Some((style, context)) = pen_switch_request {
+            // Switching to a pen that can modify strokes is allowed, it only won't receive events
+            widget_flags |= self.request_pen_switch(style, context, engine_view);
+        } else {
+            widget_flags |= self.handle_pen_progress(event_result.progress, engine_view);
+        }
+        let mut propagate = event_result.propagate;
+        if !event_result.handled {
+            let (global_propagate, wf) = self.handle_pen_event_global(event, now, engine_view);
+            propagate |= global_propagate;
+            widget_flags |= wf;
+        }
+
+        (propagate, widget_flags)
+    }
+
     /// Handles the pen event in the global scope if the current pen has not handled it.
     ///
     /// Used to implement things like nudging the view, react to pressed buttons that weren't handled by th pen, ..
@@ -497,10 +947,12 @@ impl PenHolder {
             PenStyle::Eraser => BacklogPolicy::Limit(Duration::from_millis(33)),
             PenStyle::Selector => BacklogPolicy::Limit(Duration::from_millis(33)),
             PenStyle::Tools => BacklogPolicy::DisableBacklog,
+            PenStyle::Laser => BacklogPolicy::Limit(Duration::from_millis(8)),
         };
 
         // Enable text preprocessing for typewriter
         widget_flags.enable_text_preprocessing = Some(current_style == PenStyle::Typewriter);
+        widget_flags.update_cursor = true;
         widget_flags.redraw = true;
 
         widget_flags
@@ -533,5 +985,106 @@ fn new_pen(pen_style: PenStyle) -> Pen {
         PenStyle::Eraser => Pen::Eraser(Eraser::default()),
         PenStyle::Selector => Pen::Selector(Selector::default()),
         PenStyle::Tools => Pen::Tools(Tools::default()),
+        PenStyle::Laser => Pen::Laser(Laser::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::{down_event, insert_rect};
+    use crate::Engine;
+
+    fn engine_w_selection() -> (Engine, StrokeKey) {
+        let mut engine = Engine::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let _ = engine.select_all_strokes();
+        (engine, key)
+    }
+
+    #[test]
+    fn pen_override_restores_pen() {
+        let (mut engine, key) = engine_w_selection();
+
+        let _ = engine.push_pen_override(PenStyle::Eraser);
+        assert_eq!(
+            engine.penholder.current_pen_style_w_override(),
+            PenStyle::Eraser
+        );
+        // the pen style config is not altered
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Selector);
+        // nested overrides are rejected
+        let _ = engine.push_pen_override(PenStyle::Tools);
+        assert_eq!(engine.penholder.pen_override(), Some(PenStyle::Eraser));
+
+        let _ = engine.pop_pen_override();
+        assert_eq!(engine.penholder.pen_override(), None);
+        assert_eq!(
+            engine.penholder.current_pen_style_w_override(),
+            PenStyle::Selector
+        );
+        // the selector still modifies the selection
+        assert_eq!(engine.store.selected(key), Some(true));
+        assert!(engine.penholder.bounds_on_doc(&engine.view()).is_some());
+    }
+
+    #[test]
+    fn pen_override_cancels_in_flight_state() {
+        let mut engine = Engine::default();
+        let _ = engine.change_pen_style(PenStyle::Selector);
+        for pos in [na::vector![0.0, 0.0], na::vector![50.0, 50.0]] {
+            let _ = engine.handle_pen_event(down_event(pos), None, Instant::now());
+        }
+        assert_eq!(
+            engine.penholder.current_pen_progress(),
+            PenProgress::InProgress
+        );
+
+        let _ = engine.push_pan_override();
+        assert_eq!(
+            engine.pens_config.tools_config.style,
+            ToolStyle::OffsetCamera
+        );
+        let _ = engine.release_pan_override();
+        // the tool style is restored and the lasso is gone
+        assert_eq!(
+            engine.pens_config.tools_config.style,
+            ToolStyle::VerticalSpace
+        );
+        assert_eq!(
+            engine.penholder.current_pen_style_w_override(),
+            PenStyle::Selector
+        );
+        assert_eq!(engine.penholder.current_pen_progress(), PenProgress::Idle);
+        assert!(engine.penholder.bounds_on_doc(&engine.view()).is_none());
+    }
+
+    #[test]
+    fn eraser_end_overrides_pen() {
+        let (mut engine, key) = engine_w_selection();
+
+        let _ = engine.change_pen_mode(PenMode::Eraser);
+        assert_eq!(engine.penholder.pen_override(), Some(PenStyle::Eraser));
+        // releasing the pan key doesn't end an override it didn't push
+        let _ = engine.push_pan_override();
+        let _ = engine.release_pan_override();
+        assert_eq!(engine.penholder.pen_override(), Some(PenStyle::Eraser));
+
+        let _ = engine.change_pen_mode(PenMode::Pen);
+        assert_eq!(engine.penholder.pen_override(), None);
+        assert_eq!(engine.store.selected(key), Some(true));
+        assert!(engine.penholder.bounds_on_doc(&engine.view()).is_some());
+
+        // explicitly changing the style ends the override
+        let _ = engine.change_pen_mode(PenMode::Eraser);
+        let _ = engine.change_pen_style(PenStyle::Brush);
+        assert_eq!(engine.penholder.pen_override(), None);
+        assert_eq!(
+            engine.penholder.current_pen_style_w_override(),
+            PenStyle::Brush
+        );
     }
 }
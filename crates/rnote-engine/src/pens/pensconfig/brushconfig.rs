@@ -1,4 +1,5 @@
 // Imports
+use crate::pens::InputStabilization;
 use crate::store::chrono_comp::StrokeLayer;
 use rand::{Rng, SeedableRng};
 use rnote_compose::builders::PenPathBuilderType;
@@ -53,7 +54,7 @@ impl Default for MarkerOptions {
     fn default() -> Self {
         let mut options = SmoothOptions::default();
         options.pressure_curve = PressureCurve::Const;
-        options.stroke_width = 12.0;
+        options.set_stroke_width(12.0);
 
         Self(options)
     }
@@ -97,6 +98,60 @@ impl std::ops::DerefMut for SolidOptions {
     }
 }
 
+/// How pressure is simulated for input that doesn't report it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "pressure_simulation")]
+pub enum PressureSimulation {
+    /// Full pressure.
+    #[serde(rename = "constant_full")]
+    ConstantFull,
+    /// The pressure decreases the faster the input moves.
+    #[serde(rename = "speed_based")]
+    SpeedBased {
+        /// How strongly the pressure reacts to the speed.
+        #[serde(rename = "sensitivity", with = "rnote_compose::serialize::f64_dp3")]
+        sensitivity: f64,
+    },
+    /// The pressure varies randomly, reproducible through the seed.
+    #[serde(rename = "random")]
+    Random {
+        /// The seed, the same for every stroke.
+        #[serde(rename = "seed")]
+        seed: u64,
+        /// How much the pressure varies below full pressure, in the range [0.0 - 1.0].
+        #[serde(rename = "variation", with = "rnote_compose::serialize::f64_dp3")]
+        variation: f64,
+    },
+}
+
+impl Default for PressureSimulation {
+    fn default() -> Self {
+        Self::SpeedBased { sensitivity: 1.0 }
+    }
+}
+
+impl PressureSimulation {
+    /// The lowest simulated pressure, so that fast strokes don't vanish.
+    pub const PRESSURE_MIN: f64 = 0.1;
+    /// The speed in document coordinates per second, at which the pressure is halved with a sensitivity of 1.0.
+    pub const SPEED_REFERENCE: f64 = 1000.0;
+
+    /// The simulated pressure for the speed in document coordinates per second.
+    pub fn pressure_for_speed(speed: f64, sensitivity: f64) -> f64 {
+        let speed = if speed.is_finite() {
+            speed.max(0.0)
+        } else {
+            0.0
+        };
+        let sensitivity = if sensitivity.is_finite() {
+            sensitivity.max(0.0)
+        } else {
+            0.0
+        };
+        (1.0 / (1.0 + sensitivity * speed / Self::SPEED_REFERENCE)).clamp(Self::PRESSURE_MIN, 1.0)
+    }
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[serde(default, rename = "brush_config")]
 pub struct BrushConfig {
@@ -110,6 +165,13 @@ pub struct BrushConfig {
     pub solid_options: SolidOptions,
     #[serde(rename = "textured_options")]
     pub textured_options: TexturedOptions,
+    /// Whether pressure is simulated for mouse input, which doesn't report it.
+    #[serde(rename = "simulate_pressure_for_mouse")]
+    pub simulate_pressure_for_mouse: bool,
+    #[serde(rename = "pressure_simulation")]
+    pub pressure_simulation: PressureSimulation,
+    #[serde(rename = "input_stabilization")]
+    pub input_stabilization: InputStabilization,
 }
 
 impl BrushConfig {
@@ -149,3 +211,87 @@ impl BrushConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_monotone(pressure_curve: &PressureCurve) {
+        let mut prev = pressure_curve.factor(0.0);
+        for i in 1..=1000 {
+            let factor = pressure_curve.factor(i as f64 / 1000.0);
+            assert!(
+                (0.0..=1.0).contains(&factor),
+                "{pressure_curve:?}: {factor}"
+            );
+            assert!(factor >= prev - 1e-12, "{pressure_curve:?} reverses");
+            prev = factor;
+        }
+    }
+
+    #[test]
+    fn pressure_curves_monotone() {
+        let pressure_curves = (0..=7).map(|i| PressureCurve::try_from(i).unwrap()).chain([
+            PressureCurve::SCurve {
+                steepness: PressureCurve::SCURVE_STEEPNESS_MAX,
+            },
+            // overshoots with a plain cubic spline
+            PressureCurve::Custom {
+                points: vec![(0.0, 0.0), (0.1, 0.8), (0.2, 0.82), (1.0, 1.0)],
+            },
+            // not sorted and out of range
+            PressureCurve::Custom {
+                points: vec![(1.2, 0.9), (0.0, 0.3), (0.5, 0.2), (f64::NAN, 0.5)],
+            },
+        ]);
+        for pressure_curve in pressure_curves {
+            assert_monotone(&pressure_curve);
+        }
+    }
+
+    #[test]
+    fn pressure_curve_endpoints() {
+        for i in 1..=7 {
+            let pressure_curve = PressureCurve::try_from(i).unwrap();
+            approx::assert_relative_eq!(pressure_curve.factor(0.0), 0.0, epsilon = 1e-9);
+            approx::assert_relative_eq!(pressure_curve.factor(1.0), 1.0, epsilon = 1e-9);
+        }
+        approx::assert_relative_eq!(PressureCurve::Const.factor(0.0), 1.0);
+
+        // a minimum width floor
+        let floor = PressureCurve::Custom {
+            points: vec![(0.0, 0.25), (1.0, 1.0)],
+        };
+        approx::assert_relative_eq!(floor.apply(4.0, 0.0), 1.0);
+        approx::assert_relative_eq!(floor.factor(1.0), 1.0);
+        // outputs are clamped when the pressure is out of range
+        approx::assert_relative_eq!(floor.factor(-1.0), 0.25);
+        approx::assert_relative_eq!(floor.factor(2.0), 1.0);
+    }
+
+    #[test]
+    fn custom_pressure_curve_few_points() {
+        let no_points = PressureCurve::Custom { points: vec![] };
+        approx::assert_relative_eq!(no_points.factor(0.4), 0.4);
+        let one_point = PressureCurve::Custom {
+            points: vec![(0.5, 0.7)],
+        };
+        approx::assert_relative_eq!(one_point.factor(0.0), 0.7);
+        approx::assert_relative_eq!(one_point.factor(1.0), 0.7);
+    }
+
+    #[test]
+    fn pressure_curve_serde_compatible() {
+        let linear: PressureCurve = serde_json::from_str("\"linear\"").unwrap();
+        assert_eq!(linear, PressureCurve::Linear);
+
+        let custom = PressureCurve::Custom {
+            points: vec![(0.0, 0.1), (1.0, 1.0)],
+        };
+        let json = serde_json::to_string(&custom).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PressureCurve>(&json).unwrap(),
+            custom
+        );
+    }
+}
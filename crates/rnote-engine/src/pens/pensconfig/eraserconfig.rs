@@ -12,6 +12,9 @@ pub enum EraserStyle {
     TrashCollidingStrokes,
     #[serde(rename = "split_colliding_strokes")]
     SplitCollidingStrokes,
+    /// Cut brush strokes apart at the erased segments, so that the remaining parts are separate strokes.
+    #[serde(rename = "split_strokes")]
+    SplitStrokes,
 }
 
 impl Default for EraserStyle {
@@ -37,6 +40,11 @@ pub struct EraserConfig {
     pub width: f64,
     #[serde(rename = "style")]
     pub style: EraserStyle,
+    /// Only erase the selected strokes, when strokes are selected.
+    ///
+    /// The selection is then kept when switching between the selector and the eraser.
+    #[serde(rename = "restrict_to_selection")]
+    pub restrict_to_selection: bool,
 }
 
 impl Default for EraserConfig {
@@ -44,6 +52,7 @@ impl Default for EraserConfig {
         Self {
             width: Self::WIDTH_DEFAULT,
             style: EraserStyle::default(),
+            restrict_to_selection: false,
         }
     }
 }
@@ -0,0 +1,47 @@
+// Imports
+use rnote_compose::{color, Color};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, rename = "laser_config")]
+pub struct LaserConfig {
+    #[serde(rename = "color")]
+    pub color: Color,
+    /// The width of the laser path, in surface coordinates so that it doesn't change with the zoom.
+    #[serde(rename = "width", with = "rnote_compose::serialize::f64_dp3")]
+    pub width: f64,
+    /// How long the path takes to fade out, in seconds.
+    #[serde(rename = "fade_duration", with = "rnote_compose::serialize::f64_dp3")]
+    pub fade_duration: f64,
+}
+
+impl Default for LaserConfig {
+    fn default() -> Self {
+        Self {
+            color: Color::from(color::GNOME_REDS[2]),
+            width: Self::WIDTH_DEFAULT,
+            fade_duration: Self::FADE_DURATION_DEFAULT,
+        }
+    }
+}
+
+impl LaserConfig {
+    pub const WIDTH_MIN: f64 = 1.0;
+    pub const WIDTH_MAX: f64 = 50.0;
+    pub const WIDTH_DEFAULT: f64 = 6.0;
+    pub const FADE_DURATION_MIN: f64 = 0.1;
+    pub const FADE_DURATION_MAX: f64 = 10.0;
+    pub const FADE_DURATION_DEFAULT: f64 = 1.0;
+
+    /// The fade duration, clamped to the valid range.
+    pub(crate) fn fade_duration(&self) -> Duration {
+        let secs = if self.fade_duration.is_finite() {
+            self.fade_duration
+                .clamp(Self::FADE_DURATION_MIN, Self::FADE_DURATION_MAX)
+        } else {
+            Self::FADE_DURATION_DEFAULT
+        };
+        Duration::from_secs_f64(secs)
+    }
+}
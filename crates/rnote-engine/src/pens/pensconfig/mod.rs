@@ -1,7 +1,10 @@
 // Modules
 pub mod brushconfig;
 pub mod eraserconfig;
+pub mod laserconfig;
+pub mod penpresets;
 pub mod selectorconfig;
+pub mod selectorshortcuts;
 pub mod shaperconfig;
 pub mod toolsconfig;
 pub mod typewriterconfig;
@@ -9,7 +12,10 @@ pub mod typewriterconfig;
 // Re-exports
 pub use brushconfig::BrushConfig;
 pub use eraserconfig::EraserConfig;
+pub use laserconfig::LaserConfig;
+pub use penpresets::PenPreset;
 pub use selectorconfig::SelectorConfig;
+pub use selectorshortcuts::SelectorShortcuts;
 pub use shaperconfig::ShaperConfig;
 pub use toolsconfig::ToolsConfig;
 pub use typewriterconfig::TypewriterConfig;
@@ -34,6 +40,11 @@ pub struct PensConfig {
     pub selector_config: SelectorConfig,
     #[serde(default, rename = "tools_config")]
     pub tools_config: ToolsConfig,
+    #[serde(default, rename = "laser_config")]
+    pub laser_config: LaserConfig,
+    /// Persisted with the engine config in the app settings, not in the document.
+    #[serde(default, rename = "presets")]
+    presets: Vec<PenPreset>,
 }
 
 impl CloneConfig for PensConfig {
@@ -53,9 +64,15 @@ impl PensConfig {
     }
 
     pub fn set_all_fill_colors(&mut self, fill_color: Color) {
-        self.brush_config.marker_options.fill_color = Some(fill_color);
-        self.brush_config.solid_options.fill_color = Some(fill_color);
-        self.shaper_config.smooth_options.fill_color = Some(fill_color);
+        self.brush_config
+            .marker_options
+            .set_fill_color(Some(fill_color));
+        self.brush_config
+            .solid_options
+            .set_fill_color(Some(fill_color));
+        self.shaper_config
+            .smooth_options
+            .set_fill_color(Some(fill_color));
         self.shaper_config.rough_options.fill_color = Some(fill_color);
     }
 }
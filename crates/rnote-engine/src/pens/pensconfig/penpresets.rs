@@ -0,0 +1,236 @@
+// Imports
+use super::PensConfig;
+use crate::pens::PenStyle;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// A named snapshot of the config of a pen, to switch between often used settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "pen_preset")]
+pub struct PenPreset {
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "pen_style")]
+    pub pen_style: PenStyle,
+    /// The serialized config of the pen, e.g. the brush config for a brush preset.
+    #[serde(rename = "config")]
+    config: serde_json::Value,
+}
+
+impl Default for PenPreset {
+    fn default() -> Self {
+        Self {
+            name: String::default(),
+            pen_style: PenStyle::default(),
+            config: serde_json::Value::Null,
+        }
+    }
+}
+
+impl PensConfig {
+    /// The maximum number of presets that can be applied by their position, e.g. with the number keys.
+    pub const PRESETS_WITH_POSITION_MAX: usize = 9;
+
+    /// Save the current config of the pen as a preset.
+    ///
+    /// The name is trimmed and must not be empty. A preset with the same name is replaced, keeping its position.
+    pub fn save_preset(&mut self, name: String, pen_style: PenStyle) -> anyhow::Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow::anyhow!("Pen preset name is empty."));
+        }
+        let config = self
+            .serialize_pen_config(pen_style)
+            .context("Serializing the pen config of the preset failed.")?;
+        let preset = PenPreset {
+            name: name.to_string(),
+            pen_style,
+            config,
+        };
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        Ok(())
+    }
+
+    /// Restore the config of the pen of the preset. The configs of the other pens are left untouched.
+    ///
+    /// Returns the style of the pen of the preset.
+    pub fn apply_preset(&mut self, name: &str) -> anyhow::Result<PenStyle> {
+        let preset = self
+            .preset(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No pen preset with name `{name}` exists."))?;
+        self.deserialize_pen_config(preset.pen_style, preset.config)
+            .with_context(|| format!("Restoring the pen config of preset `{name}` failed."))?;
+        Ok(preset.pen_style)
+    }
+
+    pub fn preset(&self, name: &str) -> Option<&PenPreset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    /// The presets, in the order they were saved.
+    pub fn presets(&self) -> &[PenPreset] {
+        &self.presets
+    }
+
+    /// Delete the preset. Returns false when no preset with the name exists.
+    pub fn delete_preset(&mut self, name: &str) -> bool {
+        let len = self.presets.len();
+        self.presets.retain(|preset| preset.name != name);
+        self.presets.len() != len
+    }
+
+    fn serialize_pen_config(&self, pen_style: PenStyle) -> serde_json::Result<serde_json::Value> {
+        match pen_style {
+            PenStyle::Brush => serde_json::to_value(&self.brush_config),
+            PenStyle::Shaper => serde_json::to_value(&self.shaper_config),
+            PenStyle::Typewriter => serde_json::to_value(&self.typewriter_config),
+            PenStyle::Eraser => serde_json::to_value(&self.eraser_config),
+            PenStyle::Selector => serde_json::to_value(&self.selector_config),
+            PenStyle::Tools => serde_json::to_value(&self.tools_config),
+            PenStyle::Laser => serde_json::to_value(&self.laser_config),
+        }
+    }
+
+    /// Replace only the config of the pen, after it was deserialized successfully.
+    fn deserialize_pen_config(
+        &mut self,
+        pen_style: PenStyle,
+        config: serde_json::Value,
+    ) -> serde_json::Result<()> {
+        match pen_style {
+            PenStyle::Brush => self.brush_config = serde_json::from_value(config)?,
+            PenStyle::Shaper => self.shaper_config = serde_json::from_value(config)?,
+            PenStyle::Typewriter => self.typewriter_config = serde_json::from_value(config)?,
+            PenStyle::Eraser => self.eraser_config = serde_json::from_value(config)?,
+            PenStyle::Selector => self.selector_config = serde_json::from_value(config)?,
+            PenStyle::Tools => self.tools_config = serde_json::from_value(config)?,
+            PenStyle::Laser => self.laser_config = serde_json::from_value(config)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pens::pensconfig::brushconfig::BrushStyle;
+    use rnote_compose::style::PressureCurve;
+    use rnote_compose::Color;
+
+    fn brush_config_value(pens_config: &PensConfig) -> serde_json::Value {
+        pens_config.serialize_pen_config(PenStyle::Brush).unwrap()
+    }
+
+    #[test]
+    fn preset_restores_saved_config() {
+        let mut pens_config = PensConfig::default();
+        pens_config.brush_config.style = BrushStyle::Marker;
+        pens_config.brush_config.marker_options.stroke_color = Some(Color::RED);
+        pens_config
+            .brush_config
+            .marker_options
+            .set_stroke_width(24.0);
+        let pressure_curve = PressureCurve::Custom {
+            points: vec![(0.0, 0.2), (0.5, 0.6), (1.0, 1.0)],
+        };
+        pens_config.brush_config.solid_options.pressure_curve = pressure_curve.clone();
+        let textured_pressure_curve = PressureCurve::SCurve { steepness: 2.5 };
+        pens_config.brush_config.textured_options.pressure_curve = textured_pressure_curve.clone();
+        pens_config
+            .save_preset(String::from(" thick marker red "), PenStyle::Brush)
+            .unwrap();
+        let saved = brush_config_value(&pens_config);
+        assert_eq!(pens_config.presets()[0].name, "thick marker red");
+        // the nested pressure curves are part of the serialized config of the preset
+        let preset_config = &pens_config.presets()[0].config;
+        assert_eq!(
+            serde_json::from_value::<PressureCurve>(
+                preset_config["solid_options"]["pressure_curve"].clone()
+            )
+            .unwrap(),
+            pressure_curve
+        );
+        assert_eq!(
+            serde_json::from_value::<PressureCurve>(
+                preset_config["textured_options"]["pressure_curve"].clone()
+            )
+            .unwrap(),
+            textured_pressure_curve
+        );
+
+        pens_config.brush_config.style = BrushStyle::Solid;
+        pens_config
+            .brush_config
+            .marker_options
+            .set_stroke_width(2.0);
+        pens_config.brush_config.solid_options.pressure_curve = PressureCurve::Linear;
+        pens_config.brush_config.textured_options.pressure_curve = PressureCurve::Linear;
+        pens_config.laser_config.width = 20.0;
+        assert_ne!(brush_config_value(&pens_config), saved);
+
+        let pen_style = pens_config.apply_preset("thick marker red").unwrap();
+        assert_eq!(pen_style, PenStyle::Brush);
+        assert_eq!(brush_config_value(&pens_config), saved);
+        assert_eq!(
+            pens_config.brush_config.solid_options.pressure_curve,
+            pressure_curve
+        );
+        assert_eq!(
+            pens_config.brush_config.textured_options.pressure_curve,
+            textured_pressure_curve
+        );
+        // the configs of the other pens are left untouched
+        approx::assert_relative_eq!(pens_config.laser_config.width, 20.0);
+
+        // the presets survive saving and loading the pens config
+        let mut loaded =
+            serde_json::from_str::<PensConfig>(&serde_json::to_string(&pens_config).unwrap())
+                .unwrap();
+        loaded.brush_config.solid_options.pressure_curve = PressureCurve::Const;
+        loaded.brush_config.textured_options.pressure_curve = PressureCurve::Const;
+        loaded.apply_preset("thick marker red").unwrap();
+        assert_eq!(brush_config_value(&loaded), saved);
+        assert_eq!(
+            loaded.brush_config.solid_options.pressure_curve,
+            pressure_curve
+        );
+        assert_eq!(
+            loaded.brush_config.textured_options.pressure_curve,
+            textured_pressure_curve
+        );
+
+        assert!(pens_config.apply_preset("missing").is_err());
+        assert!(pens_config
+            .save_preset(String::from("  "), PenStyle::Brush)
+            .is_err());
+    }
+
+    #[test]
+    fn preset_replaced_and_deleted_by_name() {
+        let mut pens_config = PensConfig::default();
+        pens_config
+            .save_preset(String::from("fine"), PenStyle::Brush)
+            .unwrap();
+        pens_config
+            .save_preset(String::from("dashed"), PenStyle::Shaper)
+            .unwrap();
+        pens_config.brush_config.solid_options.set_stroke_width(1.0);
+        pens_config
+            .save_preset(String::from("fine"), PenStyle::Brush)
+            .unwrap();
+        let names = pens_config
+            .presets()
+            .iter()
+            .map(|preset| preset.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["fine", "dashed"]);
+
+        assert!(pens_config.delete_preset("fine"));
+        assert!(!pens_config.delete_preset("fine"));
+        assert_eq!(pens_config.presets().len(), 1);
+    }
+}
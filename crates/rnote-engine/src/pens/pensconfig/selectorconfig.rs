@@ -1,4 +1,5 @@
 // Imports
+use super::SelectorShortcuts;
 use serde::{Deserialize, Serialize};
 
 #[derive(
@@ -42,6 +43,128 @@ impl TryFrom<u32> for SelectorStyle {
     }
 }
 
+/// How the stroke widths are scaled when the selection is resized.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "width_scale_policy")]
+pub enum WidthScalePolicy {
+    /// Scale by the geometric mean of both axes.
+    #[serde(rename = "mean_axes")]
+    MeanAxes = 0,
+    /// Scale by the axis that is scaled the least.
+    #[serde(rename = "min_axis")]
+    MinAxis,
+    /// Scale by the axis that is scaled the most.
+    #[serde(rename = "max_axis")]
+    MaxAxis,
+    /// Keep the stroke widths.
+    #[serde(rename = "none")]
+    None,
+}
+
+impl Default for WidthScalePolicy {
+    fn default() -> Self {
+        Self::MeanAxes
+    }
+}
+
+impl TryFrom<u32> for WidthScalePolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "WidthScalePolicy try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+impl WidthScalePolicy {
+    /// The factor the stroke widths are scaled by for the given (possibly non-uniform) scale.
+    ///
+    /// Mirroring (a negative scale on an axis) does not change the widths.
+    pub fn width_scale(self, scale: na::Vector2<f64>) -> f64 {
+        match self {
+            // Using the geometric mean behaves the best when scaling non-uniformly.
+            Self::MeanAxes => (scale[0] * scale[1]).abs().sqrt(),
+            Self::MinAxis => scale[0].abs().min(scale[1].abs()),
+            Self::MaxAxis => scale[0].abs().max(scale[1].abs()),
+            Self::None => 1.0,
+        }
+    }
+}
+
+/// Which strokes are selected by a rectangle selection.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    num_derive::FromPrimitive,
+    num_derive::ToPrimitive,
+)]
+#[serde(rename = "rect_selection_mode")]
+pub enum RectSelectionMode {
+    /// Strokes that are fully contained in the rectangle.
+    #[serde(rename = "contained")]
+    Contained = 0,
+    /// Strokes that are touched by the rectangle.
+    #[serde(rename = "intersecting")]
+    Intersecting,
+    /// Contained when dragged from left to right, intersecting when dragged from right to left.
+    #[serde(rename = "direction_dependent")]
+    DirectionDependent,
+}
+
+impl Default for RectSelectionMode {
+    fn default() -> Self {
+        Self::Contained
+    }
+}
+
+impl TryFrom<u32> for RectSelectionMode {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        num_traits::FromPrimitive::from_u32(value).ok_or_else(|| {
+            anyhow::anyhow!(
+                "RectSelectionMode try_from::<u32>() for value {} failed",
+                value
+            )
+        })
+    }
+}
+
+impl RectSelectionMode {
+    /// Whether strokes that are touched by the rectangle dragged from the start to the end position are selected,
+    /// otherwise only fully contained strokes are.
+    pub fn selects_intersecting(self, start: na::Vector2<f64>, end: na::Vector2<f64>) -> bool {
+        match self {
+            Self::Contained => false,
+            Self::Intersecting => true,
+            Self::DirectionDependent => end[0] < start[0],
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default, rename = "selector_config")]
 pub struct SelectorConfig {
@@ -49,6 +172,29 @@ pub struct SelectorConfig {
     pub style: SelectorStyle,
     #[serde(rename = "resize_lock_aspectratio")]
     pub resize_lock_aspectratio: bool,
+    /// Whether dragging a resize corner across the opposite corner mirrors the selection,
+    /// instead of clamping it to the minimum size.
+    #[serde(rename = "resize_mirror")]
+    pub resize_mirror: bool,
+    /// How the stroke widths are scaled when resizing the selection.
+    #[serde(rename = "width_scale_policy")]
+    pub width_scale_policy: WidthScalePolicy,
+    /// The keyboard shortcuts.
+    #[serde(rename = "shortcuts")]
+    pub shortcuts: SelectorShortcuts,
+    /// The height of the row that is selected on backgrounds without horizontal lines, in document coordinates.
+    #[serde(rename = "row_band_height")]
+    pub row_band_height: f64,
+    /// Whether the center of the selection is snapped when translating, in addition to the nearest corner.
+    #[serde(rename = "snap_center_enabled")]
+    pub snap_center_enabled: bool,
+    /// Which strokes are selected by the rectangle selector style.
+    #[serde(rename = "rect_selection_mode")]
+    pub rect_selection_mode: RectSelectionMode,
+    /// Whether large selections are rendered once into a single image while they are translated or rotated,
+    /// instead of transforming the strokes on every pen event.
+    #[serde(rename = "drag_composite")]
+    pub drag_composite: bool,
 }
 
 impl Default for SelectorConfig {
@@ -56,6 +202,17 @@ impl Default for SelectorConfig {
         Self {
             style: SelectorStyle::default(),
             resize_lock_aspectratio: false,
+            resize_mirror: false,
+            width_scale_policy: WidthScalePolicy::default(),
+            shortcuts: SelectorShortcuts::default(),
+            row_band_height: Self::ROW_BAND_HEIGHT_DEFAULT,
+            snap_center_enabled: true,
+            rect_selection_mode: RectSelectionMode::default(),
+            drag_composite: true,
         }
     }
 }
+
+impl SelectorConfig {
+    pub const ROW_BAND_HEIGHT_DEFAULT: f64 = 32.0;
+}
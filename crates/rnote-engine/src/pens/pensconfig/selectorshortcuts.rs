@@ -0,0 +1,388 @@
+// Imports
+use rnote_compose::penevent::{KeyboardKey, ModifierKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// An action of the selector that can be triggered with a keyboard shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename = "selector_action")]
+pub enum SelectorAction {
+    /// Select all strokes in the viewport.
+    #[serde(rename = "select_all")]
+    SelectAll,
+    /// Select all strokes of the document.
+    #[serde(rename = "select_all_document")]
+    SelectAllDocument,
+    /// Select all strokes of the page at the viewport center.
+    #[serde(rename = "select_all_page")]
+    SelectAllPage,
+    /// Duplicate the selection.
+    #[serde(rename = "duplicate")]
+    Duplicate,
+    /// Lock the selection.
+    #[serde(rename = "lock")]
+    Lock,
+    /// Decrease the opacity of the selection.
+    #[serde(rename = "decrease_opacity")]
+    DecreaseOpacity,
+    /// Increase the opacity of the selection.
+    #[serde(rename = "increase_opacity")]
+    IncreaseOpacity,
+    /// Raise the selection by one step in the z-order.
+    #[serde(rename = "raise")]
+    Raise,
+    /// Lower the selection by one step in the z-order.
+    #[serde(rename = "lower")]
+    Lower,
+    /// Move the selection to the front.
+    #[serde(rename = "move_to_front")]
+    MoveToFront,
+    /// Move the selection to the back.
+    #[serde(rename = "move_to_back")]
+    MoveToBack,
+    /// Reorder the selection in the z-order by the creation time of the strokes, the oldest at the back.
+    #[serde(rename = "sort_chronologically")]
+    SortChronologically,
+    /// Insert a rectangle around the selection, behind it in the z-order.
+    #[serde(rename = "frame")]
+    Frame,
+    /// Trash the selection.
+    #[serde(rename = "delete")]
+    Delete,
+    /// Cancel the ongoing translate, rotate or resize gesture, otherwise cancel the selection.
+    #[serde(rename = "cancel")]
+    Cancel,
+    /// Zoom and scroll the camera to fit the selection.
+    #[serde(rename = "zoom_to_selection")]
+    ZoomToSelection,
+    /// Edit the control points of a single selected shape, or the text of a single selected text stroke.
+    #[serde(rename = "edit_nodes")]
+    EditNodes,
+    /// Rotate the selection clockwise by a small step.
+    #[serde(rename = "rotate_clockwise")]
+    RotateClockwise,
+    /// Rotate the selection counterclockwise by a small step.
+    #[serde(rename = "rotate_counterclockwise")]
+    RotateCounterclockwise,
+    /// Rotate the selection clockwise by a quarter turn.
+    #[serde(rename = "rotate_quarter_clockwise")]
+    RotateQuarterClockwise,
+    /// Rotate the selection counterclockwise by a quarter turn.
+    #[serde(rename = "rotate_quarter_counterclockwise")]
+    RotateQuarterCounterclockwise,
+    /// Repeat the last translate, rotate, resize or shear gesture on the selection.
+    #[serde(rename = "repeat_transform")]
+    RepeatTransform,
+    /// Add the strokes touching the selection to it.
+    #[serde(rename = "grow_selection")]
+    GrowSelection,
+}
+
+impl SelectorAction {
+    /// All actions, in the order they are looked up when bindings overlap.
+    pub const ALL: [Self; 23] = [
+        Self::SelectAll,
+        Self::SelectAllDocument,
+        Self::SelectAllPage,
+        Self::Duplicate,
+        Self::Lock,
+        Self::DecreaseOpacity,
+        Self::IncreaseOpacity,
+        Self::Raise,
+        Self::Lower,
+        Self::MoveToFront,
+        Self::MoveToBack,
+        Self::SortChronologically,
+        Self::Frame,
+        Self::Delete,
+        Self::Cancel,
+        Self::ZoomToSelection,
+        Self::EditNodes,
+        Self::RotateClockwise,
+        Self::RotateCounterclockwise,
+        Self::RotateQuarterClockwise,
+        Self::RotateQuarterCounterclockwise,
+        Self::RepeatTransform,
+        Self::GrowSelection,
+    ];
+}
+
+/// A key together with the modifiers that need to be pressed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename = "key_combo")]
+pub struct KeyCombo {
+    #[serde(rename = "key")]
+    pub key: KeyboardKey,
+    #[serde(rename = "modifiers")]
+    pub modifiers: Vec<ModifierKey>,
+}
+
+impl KeyCombo {
+    pub fn new(key: KeyboardKey, modifiers: impl IntoIterator<Item = ModifierKey>) -> Self {
+        Self {
+            key,
+            modifiers: modifiers.into_iter().collect(),
+        }
+    }
+
+    fn modifier_set(&self) -> HashSet<ModifierKey> {
+        self.modifiers.iter().copied().collect()
+    }
+
+    /// Whether the combo is triggered by the key, when all of its modifiers are pressed.
+    ///
+    /// Additional pressed modifiers are ignored.
+    pub fn matches(&self, key: KeyboardKey, modifier_keys: &HashSet<ModifierKey>) -> bool {
+        self.key == key && self.modifiers.iter().all(|m| modifier_keys.contains(m))
+    }
+
+    /// Whether both combos consist of the same key and modifiers.
+    pub fn same_as(&self, other: &Self) -> bool {
+        self.key == other.key && self.modifier_set() == other.modifier_set()
+    }
+}
+
+/// The keyboard shortcuts of the selector.
+///
+/// Every action can have multiple bindings, but a binding can only trigger a single action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename = "selector_shortcuts")]
+pub struct SelectorShortcuts {
+    #[serde(rename = "select_all")]
+    select_all: Vec<KeyCombo>,
+    #[serde(rename = "select_all_document")]
+    select_all_document: Vec<KeyCombo>,
+    #[serde(rename = "select_all_page")]
+    select_all_page: Vec<KeyCombo>,
+    #[serde(rename = "duplicate")]
+    duplicate: Vec<KeyCombo>,
+    #[serde(rename = "lock")]
+    lock: Vec<KeyCombo>,
+    #[serde(rename = "decrease_opacity")]
+    decrease_opacity: Vec<KeyCombo>,
+    #[serde(rename = "increase_opacity")]
+    increase_opacity: Vec<KeyCombo>,
+    #[serde(rename = "raise")]
+    raise: Vec<KeyCombo>,
+    #[serde(rename = "lower")]
+    lower: Vec<KeyCombo>,
+    #[serde(rename = "move_to_front")]
+    move_to_front: Vec<KeyCombo>,
+    #[serde(rename = "move_to_back")]
+    move_to_back: Vec<KeyCombo>,
+    #[serde(rename = "sort_chronologically")]
+    sort_chronologically: Vec<KeyCombo>,
+    #[serde(rename = "frame")]
+    frame: Vec<KeyCombo>,
+    #[serde(rename = "delete")]
+    delete: Vec<KeyCombo>,
+    #[serde(rename = "cancel")]
+    cancel: Vec<KeyCombo>,
+    #[serde(rename = "zoom_to_selection")]
+    zoom_to_selection: Vec<KeyCombo>,
+    #[serde(rename = "edit_nodes")]
+    edit_nodes: Vec<KeyCombo>,
+    #[serde(rename = "rotate_clockwise")]
+    rotate_clockwise: Vec<KeyCombo>,
+    #[serde(rename = "rotate_counterclockwise")]
+    rotate_counterclockwise: Vec<KeyCombo>,
+    #[serde(rename = "rotate_quarter_clockwise")]
+    rotate_quarter_clockwise: Vec<KeyCombo>,
+    #[serde(rename = "rotate_quarter_counterclockwise")]
+    rotate_quarter_counterclockwise: Vec<KeyCombo>,
+    #[serde(rename = "repeat_transform")]
+    repeat_transform: Vec<KeyCombo>,
+    #[serde(rename = "grow_selection")]
+    grow_selection: Vec<KeyCombo>,
+}
+
+impl Default for SelectorShortcuts {
+    fn default() -> Self {
+        use KeyboardKey::*;
+        use ModifierKey::{KeyboardAlt, KeyboardCtrl, KeyboardShift};
+
+        Self {
+            select_all: vec![KeyCombo::new(Unicode('a'), [KeyboardCtrl])],
+            // the key is reported in upper case while shift is pressed
+            select_all_document: vec![KeyCombo::new(Unicode('A'), [KeyboardCtrl, KeyboardShift])],
+            select_all_page: vec![KeyCombo::new(Unicode('a'), [KeyboardCtrl, KeyboardAlt])],
+            duplicate: vec![KeyCombo::new(Unicode('d'), [KeyboardCtrl])],
+            // Ctrl + L clears the document
+            lock: vec![KeyCombo::new(Unicode('L'), [KeyboardCtrl, KeyboardShift])],
+            decrease_opacity: vec![KeyCombo::new(Unicode('['), [])],
+            increase_opacity: vec![KeyCombo::new(Unicode(']'), [])],
+            raise: vec![KeyCombo::new(PageUp, [])],
+            lower: vec![KeyCombo::new(PageDown, [])],
+            move_to_front: vec![KeyCombo::new(PageUp, [KeyboardCtrl])],
+            move_to_back: vec![KeyCombo::new(PageDown, [KeyboardCtrl])],
+            // unbound by default
+            sort_chronologically: vec![],
+            frame: vec![KeyCombo::new(Unicode('b'), [KeyboardCtrl])],
+            delete: vec![KeyCombo::new(Delete, []), KeyCombo::new(BackSpace, [])],
+            cancel: vec![KeyCombo::new(Escape, [])],
+            zoom_to_selection: vec![KeyCombo::new(Unicode('f'), [])],
+            edit_nodes: vec![KeyCombo::new(CarriageReturn, [])],
+            rotate_clockwise: vec![KeyCombo::new(Unicode('r'), [])],
+            rotate_counterclockwise: vec![KeyCombo::new(Unicode('R'), [KeyboardShift])],
+            rotate_quarter_clockwise: vec![KeyCombo::new(Unicode('r'), [KeyboardCtrl])],
+            rotate_quarter_counterclockwise: vec![KeyCombo::new(
+                Unicode('R'),
+                [KeyboardCtrl, KeyboardShift],
+            )],
+            repeat_transform: vec![KeyCombo::new(Unicode('T'), [KeyboardCtrl, KeyboardShift])],
+            grow_selection: vec![KeyCombo::new(Unicode('+'), [])],
+        }
+    }
+}
+
+impl SelectorShortcuts {
+    /// The bindings of the action.
+    pub fn bindings(&self, action: SelectorAction) -> &[KeyCombo] {
+        match action {
+            SelectorAction::SelectAll => &self.select_all,
+            SelectorAction::SelectAllDocument => &self.select_all_document,
+            SelectorAction::SelectAllPage => &self.select_all_page,
+            SelectorAction::Duplicate => &self.duplicate,
+            SelectorAction::Lock => &self.lock,
+            SelectorAction::DecreaseOpacity => &self.decrease_opacity,
+            SelectorAction::IncreaseOpacity => &self.increase_opacity,
+            SelectorAction::Raise => &self.raise,
+            SelectorAction::Lower => &self.lower,
+            SelectorAction::MoveToFront => &self.move_to_front,
+            SelectorAction::MoveToBack => &self.move_to_back,
+            SelectorAction::SortChronologically => &self.sort_chronologically,
+            SelectorAction::Frame => &self.frame,
+            SelectorAction::Delete => &self.delete,
+            SelectorAction::Cancel => &self.cancel,
+            SelectorAction::ZoomToSelection => &self.zoom_to_selection,
+            SelectorAction::EditNodes => &self.edit_nodes,
+            SelectorAction::RotateClockwise => &self.rotate_clockwise,
+            SelectorAction::RotateCounterclockwise => &self.rotate_counterclockwise,
+            SelectorAction::RotateQuarterClockwise => &self.rotate_quarter_clockwise,
+            SelectorAction::RotateQuarterCounterclockwise => &self.rotate_quarter_counterclockwise,
+            SelectorAction::RepeatTransform => &self.repeat_transform,
+            SelectorAction::GrowSelection => &self.grow_selection,
+        }
+    }
+
+    fn bindings_mut(&mut self, action: SelectorAction) -> &mut Vec<KeyCombo> {
+        match action {
+            SelectorAction::SelectAll => &mut self.select_all,
+            SelectorAction::SelectAllDocument => &mut self.select_all_document,
+            SelectorAction::SelectAllPage => &mut self.select_all_page,
+            SelectorAction::Duplicate => &mut self.duplicate,
+            SelectorAction::Lock => &mut self.lock,
+            SelectorAction::DecreaseOpacity => &mut self.decrease_opacity,
+            SelectorAction::IncreaseOpacity => &mut self.increase_opacity,
+            SelectorAction::Raise => &mut self.raise,
+            SelectorAction::Lower => &mut self.lower,
+            SelectorAction::MoveToFront => &mut self.move_to_front,
+            SelectorAction::MoveToBack => &mut self.move_to_back,
+            SelectorAction::SortChronologically => &mut self.sort_chronologically,
+            SelectorAction::Frame => &mut self.frame,
+            SelectorAction::Delete => &mut self.delete,
+            SelectorAction::Cancel => &mut self.cancel,
+            SelectorAction::ZoomToSelection => &mut self.zoom_to_selection,
+            SelectorAction::EditNodes => &mut self.edit_nodes,
+            SelectorAction::RotateClockwise => &mut self.rotate_clockwise,
+            SelectorAction::RotateCounterclockwise => &mut self.rotate_counterclockwise,
+            SelectorAction::RotateQuarterClockwise => &mut self.rotate_quarter_clockwise,
+            SelectorAction::RotateQuarterCounterclockwise => {
+                &mut self.rotate_quarter_counterclockwise
+            }
+            SelectorAction::RepeatTransform => &mut self.repeat_transform,
+            SelectorAction::GrowSelection => &mut self.grow_selection,
+        }
+    }
+
+    /// Replace the bindings of the action.
+    ///
+    /// Errors when one of the bindings is already bound to another action, the shortcuts are then left unchanged.
+    pub fn set_bindings(
+        &mut self,
+        action: SelectorAction,
+        bindings: Vec<KeyCombo>,
+    ) -> anyhow::Result<()> {
+        for combo in bindings.iter() {
+            if let Some(conflicting) = SelectorAction::ALL
+                .into_iter()
+                .filter(|&other| other != action)
+                .find(|&other| self.bindings(other).iter().any(|c| c.same_as(combo)))
+            {
+                return Err(anyhow::anyhow!(
+                    "Binding {combo:?} for selector action {action:?} is already bound to {conflicting:?}"
+                ));
+            }
+        }
+        *self.bindings_mut(action) = bindings;
+        Ok(())
+    }
+
+    /// The action that is triggered by the key with the pressed modifiers.
+    ///
+    /// When multiple bindings match, the one requiring the most modifiers wins.
+    pub fn action_for(
+        &self,
+        key: KeyboardKey,
+        modifier_keys: &HashSet<ModifierKey>,
+    ) -> Option<SelectorAction> {
+        SelectorAction::ALL
+            .into_iter()
+            .flat_map(|action| {
+                self.bindings(action)
+                    .iter()
+                    .filter(|combo| combo.matches(key, modifier_keys))
+                    .map(move |combo| (action, combo.modifier_set().len()))
+            })
+            .fold(
+                None,
+                |best: Option<(SelectorAction, usize)>, (action, n)| match best {
+                    Some((_, best_n)) if best_n >= n => best,
+                    _ => Some((action, n)),
+                },
+            )
+            .map(|(action, _)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflicting_bindings_are_rejected() {
+        let mut shortcuts = SelectorShortcuts::default();
+        let ctrl_a = KeyCombo::new(KeyboardKey::Unicode('a'), [ModifierKey::KeyboardCtrl]);
+
+        assert!(shortcuts
+            .set_bindings(SelectorAction::Duplicate, vec![ctrl_a.clone()])
+            .is_err());
+        assert_eq!(
+            shortcuts.bindings(SelectorAction::Duplicate),
+            SelectorShortcuts::default().bindings(SelectorAction::Duplicate)
+        );
+        // rebinding the same action is fine
+        assert!(shortcuts
+            .set_bindings(SelectorAction::SelectAll, vec![ctrl_a])
+            .is_ok());
+    }
+
+    #[test]
+    fn most_specific_binding_wins() {
+        let shortcuts = SelectorShortcuts::default();
+        let ctrl = HashSet::from([ModifierKey::KeyboardCtrl]);
+
+        assert_eq!(
+            shortcuts.action_for(KeyboardKey::PageUp, &HashSet::new()),
+            Some(SelectorAction::Raise)
+        );
+        assert_eq!(
+            shortcuts.action_for(KeyboardKey::PageUp, &ctrl),
+            Some(SelectorAction::MoveToFront)
+        );
+        assert_eq!(
+            shortcuts.action_for(KeyboardKey::Unicode('d'), &HashSet::new()),
+            None
+        );
+    }
+}
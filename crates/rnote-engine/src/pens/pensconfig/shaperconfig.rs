@@ -1,9 +1,11 @@
 // Imports
+use crate::document::format::MeasureUnit;
+use crate::pens::InputStabilization;
 use rand::{Rng, SeedableRng};
 use rnote_compose::builders::ShapeBuilderType;
 use rnote_compose::constraints::ConstraintRatio;
 use rnote_compose::style::rough::RoughOptions;
-use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::style::smooth::{Fill, SmoothOptions};
 use rnote_compose::Constraints;
 use rnote_compose::Style;
 use serde::{Deserialize, Serialize};
@@ -44,10 +46,26 @@ pub struct ShaperConfig {
     pub style: ShaperStyle,
     #[serde(rename = "smooth_options")]
     pub smooth_options: SmoothOptions,
+    /// Whether the fill of the smooth style fades out as a linear gradient.
+    #[serde(rename = "smooth_fill_gradient")]
+    pub smooth_fill_gradient: bool,
+    /// The angle of the linear gradient fill, in radians.
+    #[serde(rename = "smooth_fill_gradient_angle")]
+    pub smooth_fill_gradient_angle: f64,
     #[serde(rename = "rough_options")]
     pub rough_options: RoughOptions,
     #[serde(rename = "constraints")]
     pub constraints: Constraints,
+    /// The format of measurement annotations. `{value}` and `{unit}` are replaced.
+    #[serde(rename = "measurement_format")]
+    pub measurement_format: String,
+    #[serde(rename = "measurement_unit")]
+    pub measurement_unit: MeasureUnit,
+    #[serde(rename = "input_stabilization")]
+    pub input_stabilization: InputStabilization,
+    /// Whether the outlines of smooth shapes follow the pressure of the input while drawing them.
+    #[serde(rename = "capture_pressure")]
+    pub capture_pressure: bool,
 }
 
 impl Default for ShaperConfig {
@@ -61,8 +79,14 @@ impl Default for ShaperConfig {
             builder_type: ShapeBuilderType::default(),
             style: ShaperStyle::default(),
             smooth_options: SmoothOptions::default(),
+            smooth_fill_gradient: false,
+            smooth_fill_gradient_angle: 0.0,
             rough_options: RoughOptions::default(),
             constraints,
+            measurement_format: String::from(Self::MEASUREMENT_FORMAT_DEFAULT),
+            measurement_unit: MeasureUnit::Mm,
+            input_stabilization: InputStabilization::default(),
+            capture_pressure: false,
         }
     }
 }
@@ -70,6 +94,7 @@ impl Default for ShaperConfig {
 impl ShaperConfig {
     pub const STROKE_WIDTH_MIN: f64 = 0.1;
     pub const STROKE_WIDTH_MAX: f64 = 500.0;
+    pub const MEASUREMENT_FORMAT_DEFAULT: &'static str = "{value} {unit}";
 
     /// A new seed for new shapes
     pub(crate) fn new_style_seeds(&mut self) {
@@ -77,10 +102,40 @@ impl ShaperConfig {
         self.rough_options.seed = seed;
     }
 
+    /// Format the measured values for a measurement annotation.
+    ///
+    /// The values are expected in document coordinates and are converted to the configured unit.
+    pub(crate) fn format_measurement(&self, values: &[f64], dpi: f64) -> String {
+        let value = values
+            .iter()
+            .map(|&v| {
+                let converted = MeasureUnit::convert_measurement(
+                    v,
+                    MeasureUnit::Px,
+                    dpi,
+                    self.measurement_unit,
+                    dpi,
+                );
+                format!("{converted:.1}")
+            })
+            .collect::<Vec<String>>()
+            .join(" × ");
+
+        self.measurement_format
+            .replace("{value}", &value)
+            .replace("{unit}", self.measurement_unit.abbreviation())
+    }
+
     pub(crate) fn gen_style_for_current_options(&self) -> Style {
         match &self.style {
             ShaperStyle::Smooth => {
-                let options = self.smooth_options.clone();
+                let mut options = self.smooth_options.clone();
+                if self.smooth_fill_gradient {
+                    // Relative to the bounds, new shapes need to map it with `Style::map_fill_to_bounds()`
+                    options.fill = options.fill_color().map(|c| {
+                        Fill::linear_fade_out_relative(c, self.smooth_fill_gradient_angle)
+                    });
+                }
 
                 Style::Smooth(options)
             }
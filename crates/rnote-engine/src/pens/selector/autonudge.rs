@@ -0,0 +1,112 @@
+// Imports
+use super::{ModifyState, Selector, SelectorState};
+use crate::engine::{EngineTask, EngineViewMut};
+use crate::pens::PenBehaviour;
+use crate::tasks::{PeriodicTaskHandle, PeriodicTaskResult};
+use crate::WidgetFlags;
+use rnote_compose::penevent::{ModifierKey, PenButton, PenEvent};
+use rnote_compose::penpath::Element;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Keeps nudging the camera while the pen is held still close to the viewport edges.
+#[derive(Debug, Clone)]
+pub(super) struct AutoNudge {
+    /// The last down element, with the position in surface coordinates.
+    ///
+    /// The position stays the same on the surface while the camera moves underneath it.
+    surface_element: Element,
+    modifier_keys: HashSet<ModifierKey>,
+    pen_button: PenButton,
+    /// Periodically sends [EngineTask::AutoNudge] while it is alive.
+    _task_handle: PeriodicTaskHandle,
+}
+
+impl Selector {
+    /// The strength of the ramped camera nudge while selecting and translating.
+    pub(super) const AUTO_NUDGE_STRENGTH: f64 = 1.0;
+    /// The interval in which the camera is nudged while the pen is held still.
+    const AUTO_NUDGE_INTERVAL: Duration = Duration::from_millis(16);
+
+    /// Start, update or stop the auto nudge after a pen event was handled.
+    ///
+    /// It is only active while selecting or translating and the pen is inside the nudge margin.
+    pub(super) fn update_auto_nudge(
+        &mut self,
+        element: Element,
+        modifier_keys: HashSet<ModifierKey>,
+        pen_button: PenButton,
+        engine_view: &EngineViewMut,
+    ) {
+        let nudging_state = matches!(
+            self.state,
+            SelectorState::Selecting { .. }
+                | SelectorState::ModifySelection {
+                    modify_state: ModifyState::Translate { .. },
+                    ..
+                }
+        );
+        if !nudging_state
+            || engine_view.camera.nudge_ramp_offset(element.pos) == na::Vector2::zeros()
+        {
+            self.auto_nudge = None;
+            return;
+        }
+
+        let surface_element = Element {
+            pos: engine_view
+                .camera
+                .transform()
+                .transform_point(&element.pos.into())
+                .coords,
+            ..element
+        };
+        match &mut self.auto_nudge {
+            Some(auto_nudge) => {
+                auto_nudge.surface_element = surface_element;
+                auto_nudge.modifier_keys = modifier_keys;
+                auto_nudge.pen_button = pen_button;
+            }
+            None => {
+                let tasks_tx = engine_view.tasks_tx.clone();
+                let task = move || -> PeriodicTaskResult {
+                    tasks_tx.send(EngineTask::AutoNudge);
+                    PeriodicTaskResult::Continue
+                };
+                self.auto_nudge = Some(AutoNudge {
+                    surface_element,
+                    modifier_keys,
+                    pen_button,
+                    _task_handle: PeriodicTaskHandle::new(task, Self::AUTO_NUDGE_INTERVAL),
+                });
+            }
+        }
+    }
+
+    /// Replays the last down event at the same surface position, which nudges the camera further
+    /// and extends the selecting path or moves the translated selection along with it.
+    pub(crate) fn handle_auto_nudge(
+        &mut self,
+        now: Instant,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let Some(auto_nudge) = &self.auto_nudge else {
+            return WidgetFlags::default();
+        };
+        let element = Element {
+            pos: engine_view
+                .camera
+                .transform()
+                .inverse()
+                .transform_point(&auto_nudge.surface_element.pos.into())
+                .coords,
+            ..auto_nudge.surface_element
+        };
+        let event = PenEvent::down(element, auto_nudge.modifier_keys.clone())
+            .with_pen_button(auto_nudge.pen_button);
+
+        let (_, mut widget_flags) = self.handle_event(event, now, engine_view);
+        widget_flags.redraw = true;
+        widget_flags
+    }
+}
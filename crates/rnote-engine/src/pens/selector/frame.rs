@@ -0,0 +1,86 @@
+// Imports
+use super::Selector;
+use crate::engine::EngineViewMut;
+use crate::store::StrokeKey;
+use crate::strokes::{ShapeStroke, Stroke};
+use p2d::bounding_volume::BoundingVolume;
+use rnote_compose::shapes::Rectangle;
+use rnote_compose::style::smooth::SmoothOptions;
+use rnote_compose::{Shape, Style};
+
+impl Selector {
+    /// The padding between the selection and the frame inserted with the shortcut.
+    pub(super) const FRAME_PADDING: f64 = 8.0;
+
+    /// Insert a rectangle around the strokes, inflated by the padding and drawn behind them.
+    ///
+    /// The strokes stay selected, the frame is not added to the selection. No history is recorded.
+    /// Returns the key of the frame, None when the strokes don't have bounds.
+    pub(crate) fn insert_frame(
+        selection: &[StrokeKey],
+        padding: f64,
+        options: SmoothOptions,
+        engine_view: &mut EngineViewMut,
+    ) -> Option<StrokeKey> {
+        let bounds = engine_view
+            .store
+            .bounds_for_strokes(selection)?
+            .loosened(padding.max(0.0));
+        let frame = Stroke::ShapeStroke(ShapeStroke::new(
+            Shape::Rectangle(Rectangle::from_p2d_aabb(bounds)),
+            Style::Smooth(options),
+        ));
+        let key = engine_view.store.insert_stroke(frame, None);
+        engine_view.store.move_strokes_behind(&[key], selection);
+        engine_view.store.update_geometry_for_strokes(&[key]);
+        engine_view.store.regenerate_rendering_for_strokes_threaded(
+            engine_view.tasks_tx.clone(),
+            &[key],
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testsupport::insert_rect;
+    use crate::Engine;
+    use p2d::bounding_volume::Aabb;
+    use rnote_compose::style::smooth::SmoothOptions;
+    use std::time::Instant;
+
+    #[test]
+    fn frame_is_drawn_behind_selection() {
+        let mut engine = Engine::default();
+        let rect_at = |x: f64| Aabb::new(na::point![x, 0.0], na::point![x + 10.0, 10.0]);
+        let first = insert_rect(&mut engine.store, rect_at(0.0));
+        let unselected = insert_rect(&mut engine.store, rect_at(20.0));
+        let last = insert_rect(&mut engine.store, rect_at(40.0));
+        engine.store.set_selected_keys(&[first, last], true);
+        engine.store.record(Instant::now());
+
+        let (frame, _) = engine.frame_selection(5.0, SmoothOptions::default());
+        let frame = frame.unwrap();
+        // selecting raised the strokes, the frame is directly behind the bottommost selected one
+        assert_eq!(
+            engine.store.keys_sorted_chrono(),
+            vec![unselected, frame, first, last]
+        );
+        let frame_bounds = engine.store.bounds_for_strokes(&[frame]).unwrap();
+        assert!(frame_bounds.mins[0] < -5.0 && frame_bounds.maxs[0] > 55.0);
+        assert_eq!(engine.store.selected(first), Some(true));
+        assert_eq!(engine.store.selected(frame), Some(false));
+
+        engine.undo(Instant::now());
+        assert!(engine.store.get_stroke_ref(frame).is_none());
+
+        // nothing to frame
+        let _ = engine.deselect_all_strokes();
+        assert!(engine
+            .frame_selection(5.0, SmoothOptions::default())
+            .0
+            .is_none());
+    }
+}
@@ -0,0 +1,117 @@
+// Imports
+use super::Selector;
+use crate::engine::EngineViewMut;
+use crate::store::{StrokeKey, StrokeStore};
+use crate::strokes::Stroke;
+use crate::WidgetFlags;
+use p2d::bounding_volume::Aabb;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The rotation of the selection that is accumulated by repeatedly pressing the rotate shortcuts.
+///
+/// The strokes are always rotated from their state before the first press by the accumulated angle,
+/// so that repeated presses don't add up floating point errors.
+#[derive(Clone, Debug)]
+pub(super) struct KeyRotation {
+    /// The strokes before the first press.
+    original: Vec<(StrokeKey, Arc<Stroke>)>,
+    /// The strokes after the last press, to detect whether they were modified otherwise in the meantime.
+    rotated: Vec<Arc<Stroke>>,
+    /// The center of the selection bounds before the first press.
+    center: na::Point2<f64>,
+    /// The accumulated rotation steps, in the range of a full turn.
+    steps: i32,
+}
+
+impl KeyRotation {
+    /// The angle of a single rotation step.
+    pub(super) const STEP_ANGLE: f64 = std::f64::consts::PI / 12.0;
+    /// The number of steps of a quarter turn.
+    pub(super) const QUARTER_TURN_STEPS: i32 = 6;
+    const FULL_TURN_STEPS: i32 = 4 * Self::QUARTER_TURN_STEPS;
+
+    fn new(selection: &[StrokeKey], selection_bounds: Aabb, store: &StrokeStore) -> Self {
+        Self {
+            original: selection
+                .iter()
+                .filter_map(|&key| Some((key, store.get_stroke_arc(key)?)))
+                .collect(),
+            rotated: vec![],
+            center: selection_bounds.center(),
+            steps: 0,
+        }
+    }
+
+    /// Whether the selection still consists of the strokes as they were left by the last press.
+    fn continues_with(&self, selection: &[StrokeKey], store: &StrokeStore) -> bool {
+        let current = store.get_strokes_arc(selection);
+        self.original.len() == selection.len()
+            && self.rotated.len() == current.len()
+            && self
+                .original
+                .iter()
+                .zip(selection)
+                .all(|((key, _), selected)| key == selected)
+            && self
+                .rotated
+                .iter()
+                .zip(current.iter())
+                .all(|(rotated, current)| Arc::ptr_eq(rotated, current))
+    }
+}
+
+impl Selector {
+    /// Rotate the selection by the number of rotation steps around the center of its bounds, recording history.
+    ///
+    /// Continues the rotation of the previous press when the selection was not modified in between.
+    pub(super) fn rotate_selection_by_steps(
+        key_rotation: &mut Option<KeyRotation>,
+        steps: i32,
+        selection: &[StrokeKey],
+        selection_bounds: &mut Aabb,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let mut rotation = match key_rotation.take() {
+            Some(rotation) if rotation.continues_with(selection, engine_view.store) => rotation,
+            _ => KeyRotation::new(selection, *selection_bounds, engine_view.store),
+        };
+        rotation.steps = (rotation.steps + steps).rem_euclid(KeyRotation::FULL_TURN_STEPS);
+
+        engine_view.store.replace_stroke_arcs(&rotation.original);
+        if rotation.steps != 0 {
+            engine_view.store.rotate_strokes(
+                selection,
+                f64::from(rotation.steps) * KeyRotation::STEP_ANGLE,
+                rotation.center,
+            );
+        }
+        engine_view.store.rotate_strokes_images(
+            selection,
+            f64::from(steps) * KeyRotation::STEP_ANGLE,
+            rotation.center,
+        );
+        engine_view.store.update_geometry_for_strokes(selection);
+        engine_view.store.regenerate_rendering_for_strokes_threaded(
+            engine_view.tasks_tx.clone(),
+            selection,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+            *selection_bounds = new_bounds;
+        }
+        rotation.rotated = engine_view.store.get_strokes_arc(selection);
+        *key_rotation = Some(rotation);
+
+        widget_flags |= engine_view
+            .document
+            .resize_autoexpand(engine_view.store, engine_view.camera)
+            | engine_view.store.record(Instant::now());
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+}
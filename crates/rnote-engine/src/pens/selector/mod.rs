@@ -1,29 +1,50 @@
 // Modules
+mod autonudge;
+mod frame;
+mod keyrotation;
+mod nodeedit;
 mod penevents;
+mod pinch;
+mod repeattransform;
+mod resizeinput;
+mod rowselect;
+mod tentative;
+mod textedit;
+mod zoomregion;
 
 // Imports
 use super::pensconfig::selectorconfig::SelectorStyle;
+use super::pensconfig::selectorshortcuts::SelectorAction;
 use super::PenBehaviour;
-use super::PenStyle;
+use super::PenSwitchContext;
+use super::{CursorStyle, PenStyle};
+use crate::document::Document;
 use crate::engine::{EngineView, EngineViewMut, StrokeContent};
 use crate::render::Svg;
 use crate::snap::SnapCorner;
-use crate::store::StrokeKey;
-use crate::strokes::Content;
+use crate::store::{StrokeKey, StrokeStore, TxId};
+use crate::strokes::{Content, Stroke};
 use crate::{Camera, DrawableOnDoc, Engine, WidgetFlags};
+use autonudge::AutoNudge;
 use futures::channel::oneshot;
+use keyrotation::KeyRotation;
 use kurbo::Shape;
+use nodeedit::DraggedNode;
 use p2d::bounding_volume::{Aabb, BoundingSphere, BoundingVolume};
 use p2d::query::PointQuery;
 use piet::RenderContext;
+use repeattransform::GestureTransform;
+use rnote_compose::eventresult::EventPropagation;
 use rnote_compose::ext::{AabbExt, Vector2Ext};
 use rnote_compose::penevent::{ModifierKey, PenEvent, PenProgress, PenState};
 use rnote_compose::penpath::Element;
 use rnote_compose::style::indicators;
 use rnote_compose::EventResult;
-use rnote_compose::{color, Color};
+use rnote_compose::{color, Color, SplitOrder};
 use std::collections::HashSet;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tentative::TentativeSelection;
+use textedit::Click;
 use tracing::error;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -34,6 +55,44 @@ pub(super) enum ResizeCorner {
     BottomRight,
 }
 
+impl ResizeCorner {
+    pub(super) const ALL: [Self; 4] = [
+        Self::TopLeft,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomRight,
+    ];
+
+    /// The cursor while resizing from the corner.
+    pub(super) fn cursor(self) -> CursorStyle {
+        match self {
+            Self::TopLeft | Self::BottomRight => CursorStyle::ResizeNwSe,
+            Self::TopRight | Self::BottomLeft => CursorStyle::ResizeNeSw,
+        }
+    }
+
+    /// Whether the corner is on the top edge of the bounds.
+    pub(super) fn is_top(self) -> bool {
+        matches!(self, Self::TopLeft | Self::TopRight)
+    }
+
+    /// The corner on the opposite side for every mirrored axis.
+    pub(super) fn mirrored(self, mirrored: [bool; 2]) -> Self {
+        let (left, top) = match self {
+            Self::TopLeft => (true, true),
+            Self::TopRight => (false, true),
+            Self::BottomLeft => (true, false),
+            Self::BottomRight => (false, false),
+        };
+        match (left != mirrored[0], top != mirrored[1]) {
+            (true, true) => Self::TopLeft,
+            (false, true) => Self::TopRight,
+            (true, false) => Self::BottomLeft,
+            (false, false) => Self::BottomRight,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(super) enum ModifyState {
     Up,
@@ -42,6 +101,10 @@ pub(super) enum ModifyState {
         start_pos: na::Vector2<f64>,
         current_pos: na::Vector2<f64>,
         snap_corner: SnapCorner,
+        /// The corner that engaged when snapping the last offset, either the snap corner or the center.
+        engaged_snap: SnapCorner,
+        /// The unit direction the translation is constrained to, while Shift is held.
+        constraint: Option<na::Vector2<f64>>,
     },
     Rotate {
         rotation_center: na::Point2<f64>,
@@ -53,7 +116,65 @@ pub(super) enum ModifyState {
         start_bounds: Aabb,
         start_pos: na::Vector2<f64>,
         last_rendered_bounds: Aabb,
+        /// The extents the selection can't be resized below, derived from the selected strokes.
+        min_extents: na::Vector2<f64>,
+        /// Whether the selection is currently mirrored about the pivot, for each axis.
+        ///
+        /// Only toggled when mirroring is enabled in the selector config and the dragged corner crosses the pivot.
+        mirrored: [bool; 2],
+        /// Whether the aspect ratio was locked on the last event.
+        ///
+        /// When it changes mid-drag the gesture is rebased onto the current position and bounds.
+        lock_aspectratio: bool,
+    },
+    /// Shearing the selection horizontally by dragging a resize node while holding Shift.
+    ///
+    /// The edge opposite to the dragged corner stays in place.
+    Shear {
+        from_corner: ResizeCorner,
+        start_bounds: Aabb,
+        start_pos: na::Vector2<f64>,
+        /// The horizontal shear-factor that is currently applied, relative to the start of the gesture.
+        current_shear: f64,
+    },
+    /// A two-finger touch gesture is scaling and rotating the selection about its centroid.
+    Pinch {
+        current_scale: f64,
+        current_rotation_angle: f64,
     },
+    /// The selection was finalized while the pen is still down (e.g. an auto-closed polygon),
+    /// the remaining down events are ignored until the pen is lifted.
+    AwaitPenUp,
+    /// Editing the control points of a single selected shape stroke.
+    EditNodes {
+        dragged: Option<DraggedNode>,
+        hover_pos: Option<na::Vector2<f64>>,
+        /// A node was removed with the current pen down, the remaining down events are ignored until the pen is lifted.
+        await_pen_up: bool,
+    },
+}
+
+/// The strokes that are selected when selecting all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum SelectAllScope {
+    /// The strokes intersecting the viewport.
+    Viewport,
+    /// All strokes of the document.
+    Document,
+    /// The strokes intersecting the page that contains the viewport center.
+    ///
+    /// Falls back to the viewport for layouts without a fixed width.
+    Page,
+}
+
+impl SelectAllScope {
+    pub(super) fn from_action(action: SelectorAction) -> Self {
+        match action {
+            SelectorAction::SelectAllDocument => Self::Document,
+            SelectorAction::SelectAllPage => Self::Page,
+            _ => Self::Viewport,
+        }
+    }
 }
 
 impl Default for ModifyState {
@@ -62,16 +183,42 @@ impl Default for ModifyState {
     }
 }
 
+/// Tracks whether the pen is held still after it went down to start selecting.
+///
+/// Checked on the following down events, which styluses report continuously while they are held on the surface.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct LongPress {
+    /// The position where the pen went down.
+    anchor: na::Vector2<f64>,
+    /// When the pen went down.
+    start: Instant,
+}
+
 #[derive(Clone, Debug)]
 pub(super) enum SelectorState {
     Idle,
     Selecting {
         path: Vec<Element>,
+        /// Only updated when selecting with a rectangle.
+        tentative: TentativeSelection,
+        /// Cleared once the pen moved too far away from where it went down.
+        long_press: Option<LongPress>,
+        /// Whether the path has moved away from its start far enough so that a polygon can be auto-closed.
+        ///
+        /// Updated with every added element, so the path doesn't need to be searched on every event.
+        left_start: bool,
+    },
+    /// Drawing a rectangle to zoom the camera to, in document coordinates.
+    ZoomRegion {
+        start: na::Vector2<f64>,
+        current: na::Vector2<f64>,
     },
     ModifySelection {
         modify_state: ModifyState,
         selection: Vec<StrokeKey>,
         selection_bounds: Aabb,
+        /// The rotation by gestures and keyboard input since the selection was created, in radians.
+        rotation_angle: f64,
     },
 }
 
@@ -84,12 +231,38 @@ impl Default for SelectorState {
 #[derive(Clone, Debug)]
 pub struct Selector {
     pub(super) state: SelectorState,
+    /// Overrides the configured selector style for the current selecting gesture.
+    pub(super) style_override: Option<SelectorStyle>,
+    /// The pending keyboard resize input, either a percentage, a `WxH` size or an `r` rotation in degrees.
+    pub(super) resize_input: Option<String>,
+    /// Keeps nudging the camera while the pen is held still close to the viewport edges.
+    pub(super) auto_nudge: Option<AutoNudge>,
+    /// The rotation accumulated with the rotate shortcuts.
+    pub(super) key_rotation: Option<KeyRotation>,
+    /// The last click of the pen, to detect double clicks.
+    pub(super) last_click: Option<Click>,
+    pub(super) pen_switch_request: Option<(PenStyle, PenSwitchContext)>,
+    /// The store transaction of the ongoing translate, rotate or resize gesture.
+    pub(super) transaction: Option<TxId>,
+    /// The originally selected strokes, while their duplicates are dragged out of them.
+    pub(super) copy_drag: Option<Vec<StrokeKey>>,
+    /// The transform applied during the ongoing gesture, recorded in the store once it is committed.
+    pub(super) gesture_transform: Option<GestureTransform>,
 }
 
 impl Default for Selector {
     fn default() -> Self {
         Self {
             state: SelectorState::default(),
+            style_override: None,
+            resize_input: None,
+            auto_nudge: None,
+            key_rotation: None,
+            last_click: None,
+            pen_switch_request: None,
+            transaction: None,
+            copy_drag: None,
+            gesture_transform: None,
         }
     }
 }
@@ -99,14 +272,88 @@ impl PenBehaviour for Selector {
         WidgetFlags::default()
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
-        WidgetFlags::default()
+    fn deinit(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        self.auto_nudge = None;
+        self.key_rotation = None;
+        self.last_click = None;
+
+        // An interrupted gesture is rolled back, so that its transaction isn't left open in the store
+        if let SelectorState::ModifySelection {
+            modify_state,
+            selection,
+            selection_bounds,
+            ..
+        } = &mut self.state
+        {
+            engine_view.store.discard_selection_composite();
+            if let Some(tx_id) = self.transaction.take() {
+                widget_flags |=
+                    Self::rollback_gesture(tx_id, selection, selection_bounds, engine_view);
+                *modify_state = ModifyState::Up;
+            }
+            if let Some(originals) = self.copy_drag.take() {
+                widget_flags |=
+                    Self::discard_copy_drag(originals, selection, selection_bounds, engine_view);
+            }
+        } else if let Some(tx_id) = self.transaction.take() {
+            engine_view.store.rollback_transaction(tx_id);
+        }
+        self.copy_drag = None;
+        self.gesture_transform = None;
+
+        widget_flags
     }
 
     fn style(&self) -> PenStyle {
         PenStyle::Selector
     }
 
+    fn current_cursor(&self, engine_view: &EngineView) -> CursorStyle {
+        match &self.state {
+            SelectorState::Idle
+            | SelectorState::Selecting { .. }
+            | SelectorState::ZoomRegion { .. } => CursorStyle::Crosshair,
+            SelectorState::ModifySelection {
+                modify_state,
+                selection_bounds,
+                ..
+            } => match modify_state {
+                ModifyState::Up => CursorStyle::Crosshair,
+                // The same hit-tests as when the pen goes down, so the cursor shows what will be modified
+                ModifyState::Hover(pos) => {
+                    if Self::rotate_node_sphere(*selection_bounds, engine_view.camera)
+                        .contains_local_point(&(*pos).into())
+                    {
+                        CursorStyle::Rotate
+                    } else if let Some(corner) =
+                        Self::resize_node_at(*pos, *selection_bounds, engine_view.camera)
+                    {
+                        corner.cursor()
+                    } else if selection_bounds.contains_local_point(&(*pos).into()) {
+                        CursorStyle::Grab
+                    } else {
+                        CursorStyle::Crosshair
+                    }
+                }
+                ModifyState::Translate { .. }
+                | ModifyState::Shear { .. }
+                | ModifyState::Pinch { .. } => CursorStyle::Grabbing,
+                ModifyState::Rotate { .. } => CursorStyle::Rotate,
+                ModifyState::Resize {
+                    from_corner,
+                    mirrored,
+                    ..
+                } => from_corner.mirrored(*mirrored).cursor(),
+                ModifyState::AwaitPenUp => CursorStyle::Default,
+                ModifyState::EditNodes {
+                    dragged: Some(_), ..
+                } => CursorStyle::Grabbing,
+                ModifyState::EditNodes { .. } => CursorStyle::Default,
+            },
+        }
+    }
+
     fn update_state(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
@@ -118,10 +365,60 @@ impl PenBehaviour for Selector {
                     modify_state: ModifyState::default(),
                     selection,
                     selection_bounds,
+                    rotation_angle: 0.0,
+                }
+            } else {
+                SelectorState::Idle
+            };
+        self.resize_input = None;
+
+        widget_flags.redraw = true;
+
+        widget_flags
+    }
+
+    fn update_state_after_history_change(
+        &mut self,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        // Re-validate the selection, the store only reports keys that still exist and are not trashed
+        let selection = engine_view.store.selection_keys_as_rendered();
+        let modify_state = match &self.state {
+            SelectorState::ModifySelection {
+                modify_state: modify_state @ ModifyState::Hover(_),
+                ..
+            } => *modify_state,
+            // Node editing is kept as long as the selection still supports it
+            SelectorState::ModifySelection {
+                modify_state: ModifyState::EditNodes { hover_pos, .. },
+                ..
+            } if Self::selection_control_points(&selection, engine_view.store).is_some() => {
+                ModifyState::EditNodes {
+                    dragged: None,
+                    hover_pos: *hover_pos,
+                    await_pen_up: false,
+                }
+            }
+            // Ongoing modifications refer to the bounds before the history change, so they are discarded
+            _ => ModifyState::default(),
+        };
+
+        self.state =
+            if let Some(selection_bounds) = engine_view.store.bounds_for_strokes(&selection) {
+                SelectorState::ModifySelection {
+                    modify_state,
+                    selection,
+                    selection_bounds,
+                    // The history change might have undone rotations, so the angle starts over
+                    rotation_angle: 0.0,
                 }
             } else {
                 SelectorState::Idle
             };
+        self.style_override = None;
+        self.resize_input = None;
 
         widget_flags.redraw = true;
 
@@ -134,26 +431,54 @@ impl PenBehaviour for Selector {
         now: Instant,
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
-        match event {
+        let result = match event {
             PenEvent::Down {
                 element,
                 modifier_keys,
-            } => self.handle_pen_event_down(element, modifier_keys, now, engine_view),
+                pen_button,
+            } => {
+                let result = self.handle_pen_event_down(
+                    element,
+                    modifier_keys.clone(),
+                    pen_button,
+                    now,
+                    engine_view,
+                );
+                self.update_auto_nudge(element, modifier_keys, pen_button, engine_view);
+                result
+            }
             PenEvent::Up {
                 element,
                 modifier_keys,
-            } => self.handle_pen_event_up(element, modifier_keys, now, engine_view),
+                ..
+            } => {
+                self.auto_nudge = None;
+                self.handle_pen_event_up(element, modifier_keys, now, engine_view)
+            }
             PenEvent::Proximity {
                 element,
                 modifier_keys,
-            } => self.handle_pen_event_proximity(element, modifier_keys, now, engine_view),
+                ..
+            } => {
+                self.auto_nudge = None;
+                self.handle_pen_event_proximity(element, modifier_keys, now, engine_view)
+            }
             PenEvent::KeyPressed {
                 keyboard_key,
                 modifier_keys,
             } => self.handle_pen_event_keypressed(keyboard_key, modifier_keys, now, engine_view),
             PenEvent::Text { text } => self.handle_pen_event_text(text, now, engine_view),
-            PenEvent::Cancel => self.handle_pen_event_cancel(now, engine_view),
-        }
+            PenEvent::Cancel => {
+                self.auto_nudge = None;
+                self.handle_pen_event_cancel(now, engine_view)
+            }
+        };
+        self.discard_stale_drag_composite(engine_view);
+        result
+    }
+
+    fn take_pen_switch_request(&mut self) -> Option<(PenStyle, PenSwitchContext)> {
+        self.pen_switch_request.take()
     }
 
     fn fetch_clipboard_content(
@@ -278,7 +603,7 @@ impl DrawableOnDoc for Selector {
 
         match &self.state {
             SelectorState::Idle => None,
-            SelectorState::Selecting { path } => {
+            SelectorState::Selecting { path, .. } => {
                 // Making sure bounds are always outside of coord + width
                 let mut path_iter = path.iter();
                 if let Some(first) = path_iter.next() {
@@ -295,14 +620,43 @@ impl DrawableOnDoc for Selector {
                         new_bounds.merge(&pos_bounds);
                     });
 
-                    Some(new_bounds.loosened(Self::SELECTING_SINGLE_CIRCLE_RADIUS / total_zoom))
+                    Some(new_bounds.loosened(
+                        Self::SELECTING_SINGLE_CIRCLE_RADIUS.max(Self::POLYGON_AUTO_CLOSE_RADIUS)
+                            / total_zoom,
+                    ))
                 } else {
                     None
                 }
             }
+            SelectorState::ZoomRegion { start, current } => Some(
+                Self::zoom_region_bounds(*start, *current)
+                    .loosened(Self::OUTLINE_STROKE_WIDTH / total_zoom),
+            ),
             SelectorState::ModifySelection {
-                selection_bounds, ..
-            } => Some(selection_bounds.extend_by(Self::RESIZE_NODE_SIZE / total_zoom)),
+                modify_state,
+                selection,
+                selection_bounds,
+                ..
+            } => {
+                let mut bounds = selection_bounds.extend_by(Self::RESIZE_NODE_SIZE / total_zoom);
+                if let ModifyState::EditNodes { .. } = modify_state {
+                    // Bezier control points can lie outside of the stroke bounds
+                    for control_point in
+                        Self::selection_control_points(selection, engine_view.store)
+                            .unwrap_or_default()
+                    {
+                        bounds.take_point(control_point.into());
+                    }
+                    bounds = bounds.loosened(Self::NODE_HANDLE_DIAMETER / total_zoom);
+                }
+                if self.resize_input.is_some() {
+                    // Leave room for the readout above the selection
+                    bounds.mins[1] -= Self::RESIZE_INPUT_READOUT_HEIGHT / total_zoom;
+                    bounds.maxs[0] = bounds.maxs[0]
+                        .max(bounds.mins[0] + Self::RESIZE_INPUT_READOUT_WIDTH / total_zoom);
+                }
+                Some(bounds)
+            }
         }
     }
 
@@ -316,21 +670,27 @@ impl DrawableOnDoc for Selector {
 
         match &self.state {
             SelectorState::Idle => {}
-            SelectorState::Selecting { path } => {
-                match engine_view.pens_config.selector_config.style {
+            SelectorState::Selecting {
+                path,
+                tentative,
+                left_start,
+                ..
+            } => {
+                match self
+                    .style_override
+                    .unwrap_or(engine_view.pens_config.selector_config.style)
+                {
                     SelectorStyle::Polygon => {
                         let mut bez_path = kurbo::BezPath::new();
                         let mut path_iter = path.iter();
 
-                        if let Some(first) = path_iter.next() {
+                        if let (Some(first), Some(last)) = (path_iter.next(), path.last()) {
                             bez_path.move_to(first.pos.to_kurbo_point());
 
                             for element in path_iter {
                                 bez_path.line_to(element.pos.to_kurbo_point());
                             }
 
-                            bez_path.close_path();
-
                             let mut stroke_style = piet::StrokeStyle::new();
                             stroke_style.set_dash_pattern(
                                 Self::SELECTING_DASH_PATTERN
@@ -339,25 +699,60 @@ impl DrawableOnDoc for Selector {
                                     .collect::<Vec<f64>>(),
                             );
 
-                            cx.fill(bez_path.clone(), &Self::SELECTION_FILL_COLOR);
+                            let mut closed_path = bez_path.clone();
+                            closed_path.close_path();
+                            cx.fill(closed_path, &Self::SELECTION_FILL_COLOR);
                             cx.stroke_styled(
                                 bez_path,
                                 &Self::SELECTION_OUTLINE_COLOR,
                                 Self::OUTLINE_STROKE_WIDTH / total_zoom,
                                 &stroke_style,
                             );
+
+                            // The implicit closing segment
+                            cx.stroke_styled(
+                                kurbo::Line::new(
+                                    last.pos.to_kurbo_point(),
+                                    first.pos.to_kurbo_point(),
+                                ),
+                                &Self::SELECTING_CLOSING_SEGMENT_COLOR,
+                                Self::OUTLINE_STROKE_WIDTH / total_zoom,
+                                &stroke_style,
+                            );
+
+                            // Indicate the area where the polygon gets auto-closed
+                            if *left_start {
+                                let auto_close_circle = kurbo::Circle::new(
+                                    first.pos.to_kurbo_point(),
+                                    Self::POLYGON_AUTO_CLOSE_RADIUS / total_zoom,
+                                );
+                                cx.fill(auto_close_circle, &Self::SELECTION_FILL_COLOR);
+                                cx.stroke(
+                                    auto_close_circle,
+                                    &Self::SELECTION_OUTLINE_COLOR,
+                                    Self::OUTLINE_STROKE_WIDTH / total_zoom,
+                                );
+                            }
                         }
                     }
                     SelectorStyle::Rectangle => {
+                        Self::draw_tentative_selection(cx, tentative, engine_view);
+
                         if let (Some(first), Some(last)) = (path.first(), path.last()) {
                             let select_rect = kurbo::Rect::from_points(
                                 first.pos.to_kurbo_point(),
                                 last.pos.to_kurbo_point(),
                             );
 
+                            let dash_pattern = if Self::rect_selects_intersecting(path, engine_view)
+                            {
+                                Self::SELECTING_INTERSECTING_DASH_PATTERN
+                            } else {
+                                Self::SELECTING_DASH_PATTERN
+                            };
                             let mut stroke_style = piet::StrokeStyle::new();
                             stroke_style.set_dash_pattern(
-                                Self::SELECTING_DASH_PATTERN
+                                dash_pattern
                                     .into_iter()
                                     .map(|x| x / total_zoom)
                                     .collect::<Vec<f64>>(),
@@ -413,6 +808,9 @@ impl DrawableOnDoc for Selector {
                     }
                 }
             }
+            SelectorState::ZoomRegion { start, current } => {
+                Self::draw_zoom_region(cx, *start, *current, engine_view.camera);
+            }
             SelectorState::ModifySelection {
                 modify_state,
                 selection,
@@ -426,14 +824,43 @@ impl DrawableOnDoc for Selector {
                     }
                 }
 
-                Self::draw_selection_overlay(
-                    cx,
-                    *selection_bounds,
-                    modify_state,
-                    engine_view.camera,
-                )?;
+                if let ModifyState::EditNodes {
+                    dragged, hover_pos, ..
+                } = modify_state
+                {
+                    Self::draw_node_edit(cx, selection, *dragged, *hover_pos, engine_view);
+                } else {
+                    Self::draw_selection_overlay(
+                        cx,
+                        *selection_bounds,
+                        modify_state,
+                        engine_view.camera,
+                    )?;
+                }
 
                 match modify_state {
+                    ModifyState::Translate {
+                        start_pos,
+                        engaged_snap,
+                        constraint,
+                        ..
+                    } => {
+                        if let Some(direction) = constraint {
+                            Self::draw_translate_constraint_guide(
+                                cx,
+                                *start_pos,
+                                *direction,
+                                engine_view.camera,
+                            );
+                        }
+                        if engine_view.document.snap_config().enabled() {
+                            Self::draw_snap_crosshair(
+                                cx,
+                                engaged_snap.pos_on_bounds(*selection_bounds),
+                                engine_view.camera,
+                            );
+                        }
+                    }
                     ModifyState::Rotate {
                         rotation_center,
                         start_rotation_angle,
@@ -449,6 +876,8 @@ impl DrawableOnDoc for Selector {
                     }
                     _ => {}
                 }
+
+                self.draw_resize_input(cx, engine_view)?;
             }
         }
 
@@ -462,78 +891,339 @@ impl Selector {
     const TRANSLATE_OFFSET_THRESHOLD: f64 = 1.414;
     /// The threshold angle (in radians) where above it the rotation is applied.
     const ROTATE_ANGLE_THRESHOLD: f64 = ((2.0 * std::f64::consts::PI) / 360.0) * 0.2;
+    /// The number of selected strokes from which the selection is dragged as a composite,
+    /// see [StrokeStore::begin_selection_composite].
+    const DRAG_COMPOSITE_MIN_STROKES: usize = 32;
+    /// The step by which the opacity of the selection is adjusted with the keyboard.
+    const OPACITY_STEP: f64 = 0.1;
     /// The outline stroke width when drawing a selection.
     const OUTLINE_STROKE_WIDTH: f64 = 2.0;
     /// The dash pattern while selecting.
     const SELECTING_DASH_PATTERN: [f64; 2] = [12.0, 6.0];
+    /// The dash pattern of the selecting rectangle when touched strokes are selected as well.
+    const SELECTING_INTERSECTING_DASH_PATTERN: [f64; 2] = [4.0, 4.0];
+    /// The half extents of the crosshair at the snapped point while translating, in surface coordinates.
+    const SNAP_CROSSHAIR_HALF_EXTENTS: f64 = 6.0;
     /// The radius of the circle when selecting in single mode.
     const SELECTING_SINGLE_CIRCLE_RADIUS: f64 = 4.0;
     /// Resize node size, in surface coordinates.
     const RESIZE_NODE_SIZE: na::Vector2<f64> = na::vector![18.0, 18.0];
     /// Rotate node diameter, in surface coordinates.
     const ROTATE_NODE_DIAMETER: f64 = 18.0;
+    /// The size the resize and rotate nodes are at least shrunk to for small selections, in surface coordinates.
+    const NODE_MIN_SIZE: f64 = 8.0;
+    /// The maximum share of the selection area on screen that the four resize nodes cover.
+    const RESIZE_NODES_MAX_AREA_SHARE: f64 = 0.3;
     /// The outline color when drawing a selection
     const SELECTION_OUTLINE_COLOR: piet::Color = color::GNOME_BRIGHTS[4].with_a8(240);
     /// The fill color when drawing a selection
     const SELECTION_FILL_COLOR: piet::Color = color::GNOME_BRIGHTS[2].with_a8(13);
-
-    fn add_to_select_path(style: SelectorStyle, path: &mut Vec<Element>, element: Element) {
+    /// The color of the guide line along the constrained direction while translating
+    const TRANSLATE_CONSTRAINT_GUIDE_COLOR: piet::Color = color::GNOME_BRIGHTS[4].with_a8(80);
+    /// The color of the implicit closing segment when selecting with a polygon
+    const SELECTING_CLOSING_SEGMENT_COLOR: piet::Color = color::GNOME_BRIGHTS[4].with_a8(120);
+    /// The radius around the start of the polygon path where it gets auto-closed, in surface coordinates.
+    const POLYGON_AUTO_CLOSE_RADIUS: f64 = 12.0;
+    /// The minimum area of a selecting polygon, in surface coordinates.
+    ///
+    /// Prevents accidental taps from creating degenerate selections.
+    const POLYGON_MIN_AREA: f64 = 64.0;
+    /// The duration the pen needs to be held still to select the stroke under it.
+    const LONG_PRESS_DURATION: Duration = Duration::from_millis(600);
+    /// The radius the pen can move while still being considered held still, in surface coordinates.
+    const LONG_PRESS_RADIUS: f64 = 8.0;
+    /// The minimum extents of a resized selection, in document coordinates.
+    pub(crate) const RESIZE_MIN_EXTENTS: f64 = 2.0;
+    /// The minimum distance between the vertices of the selecting path, in surface coordinates.
+    const SELECT_PATH_EPSILON: f64 = 2.0;
+    /// The maximum distance of a vertex from the line between its neighbours
+    /// for it to be collapsed, in surface coordinates.
+    const SELECT_PATH_COLLINEAR_TOLERANCE: f64 = 0.5;
+    /// The maximum number of vertices of the selecting path.
+    const SELECT_PATH_MAX_VERTICES: usize = 400;
+
+    fn add_to_select_path(
+        style: SelectorStyle,
+        path: &mut Vec<Element>,
+        element: Element,
+        total_zoom: f64,
+    ) {
         match style {
             SelectorStyle::Polygon | SelectorStyle::Single | SelectorStyle::IntersectingPath => {
-                path.push(element);
+                Self::add_to_decimated_path(path, element, total_zoom);
             }
             SelectorStyle::Rectangle => {
+                // Only the start and the latest position span the rectangle
+                path.truncate(1);
                 path.push(element);
+            }
+        }
+    }
 
-                if path.len() > 2 {
-                    path.resize(2, Element::default());
-                    path.insert(1, element);
-                }
+    /// Add the element to the path, decimating it while it is recorded.
+    ///
+    /// The last element of the path is always the latest element, the ones before it are the kept vertices.
+    /// Vertices that are too close to the previous one or that are nearly collinear with their neighbours are dropped,
+    /// and the path is thinned out when it exceeds the maximum number of vertices.
+    fn add_to_decimated_path(path: &mut Vec<Element>, element: Element, total_zoom: f64) {
+        let epsilon = Self::SELECT_PATH_EPSILON / total_zoom;
+        let tolerance = Self::SELECT_PATH_COLLINEAR_TOLERANCE / total_zoom;
+        let n = path.len();
+
+        if n >= 2 {
+            let latest = path[n - 1].pos;
+            if (latest - path[n - 2].pos).magnitude() < epsilon {
+                path.pop();
+            } else if n >= 3
+                && Self::nearly_collinear(path[n - 3].pos, path[n - 2].pos, latest, tolerance)
+            {
+                path.remove(n - 2);
             }
         }
+        path.push(element);
+
+        if path.len() > Self::SELECT_PATH_MAX_VERTICES {
+            // Drop every second vertex, but keep the start and the latest element
+            let last = path.len() - 1;
+            let mut i = 0;
+            path.retain(|_| {
+                let keep = i % 2 == 0 || i == last;
+                i += 1;
+                keep
+            });
+        }
+    }
+
+    /// Whether `mid` is within the tolerance of the line segment from `start` to `end`.
+    fn nearly_collinear(
+        start: na::Vector2<f64>,
+        mid: na::Vector2<f64>,
+        end: na::Vector2<f64>,
+        tolerance: f64,
+    ) -> bool {
+        let segment = end - start;
+        let offset = mid - start;
+        let projection = offset.dot(&segment);
+        // Reversals of the direction are never collapsed
+        if projection <= 0.0 || projection >= segment.magnitude_squared() {
+            return false;
+        }
+        segment.perp(&offset).abs() <= tolerance * segment.magnitude()
+    }
+
+    /// The area enclosed by the polygon path, implicitly closed from the last to the first element.
+    fn polygon_path_area(path: &[Element]) -> f64 {
+        if path.len() < 3 {
+            return 0.0;
+        }
+        let twice_area = path
+            .iter()
+            .zip(path.iter().cycle().skip(1))
+            .map(|(a, b)| a.pos[0] * b.pos[1] - b.pos[0] * a.pos[1])
+            .sum::<f64>();
+        twice_area.abs() * 0.5
+    }
+
+    /// Whether the polygon path is large enough to be used for selecting.
+    fn polygon_path_valid(path: &[Element], total_zoom: f64) -> bool {
+        path.len() >= 3
+            && Self::polygon_path_area(path) >= Self::POLYGON_MIN_AREA / total_zoom.powi(2)
+    }
+
+    /// Whether the position is far enough away from the start of the polygon path so that it can be auto-closed.
+    fn polygon_pos_left_start(path: &[Element], pos: na::Vector2<f64>, total_zoom: f64) -> bool {
+        path.first().is_some_and(|first| {
+            (pos - first.pos).magnitude() > 2.0 * Self::POLYGON_AUTO_CLOSE_RADIUS / total_zoom
+        })
+    }
+
+    /// Whether the rectangle spanned by the path selects the strokes it touches, otherwise only contained ones.
+    fn rect_selects_intersecting(path: &[Element], engine_view: &EngineView) -> bool {
+        let (Some(first), Some(last)) = (path.first(), path.last()) else {
+            return false;
+        };
+        engine_view
+            .pens_config
+            .selector_config
+            .rect_selection_mode
+            .selects_intersecting(first.pos, last.pos)
+    }
+
+    /// The keys of the strokes that are selected by the rectangle spanned by the path.
+    fn rect_selection_keys(path: &[Element], engine_view: &EngineView) -> Vec<StrokeKey> {
+        let (Some(first), Some(last)) = (path.first(), path.last()) else {
+            return vec![];
+        };
+        let aabb = Aabb::new_positive(first.pos.into(), last.pos.into());
+        if Self::rect_selects_intersecting(path, engine_view) {
+            engine_view
+                .store
+                .strokes_hitboxes_intersect_aabb(aabb, false)
+        } else {
+            engine_view
+                .store
+                .strokes_hitboxes_contained_in_aabb(aabb, false)
+        }
+    }
+
+    /// Whether the polygon path has returned to its start and should be auto-closed.
+    fn polygon_path_should_auto_close(path: &[Element], left_start: bool, total_zoom: f64) -> bool {
+        let (Some(first), Some(last)) = (path.first(), path.last()) else {
+            return false;
+        };
+        left_start
+            && (last.pos - first.pos).magnitude() < Self::POLYGON_AUTO_CLOSE_RADIUS / total_zoom
+            && Self::polygon_path_valid(path, total_zoom)
+    }
+
+    /// The scale of the resize and rotate nodes for the selection.
+    ///
+    /// The nodes have a constant size on screen, but are shrunk for small selections
+    /// so that the four resize nodes don't cover more than the maximum share of the selection area.
+    fn node_scale(selection_bounds: Aabb, total_zoom: f64) -> f64 {
+        let extents = selection_bounds.extents().map(|e| e.max(0.0)) * total_zoom;
+        let max_size = (Self::RESIZE_NODES_MAX_AREA_SHARE * extents[0] * extents[1] * 0.25).sqrt();
+        let min_scale = Self::NODE_MIN_SIZE / Self::RESIZE_NODE_SIZE[0];
+        (max_size / Self::RESIZE_NODE_SIZE[0]).clamp(min_scale, 1.0)
+    }
+
+    /// Whether the selection is smaller on screen than the four resize nodes combined.
+    ///
+    /// Then only the bottom right resize node and the rotate node are available.
+    fn nodes_reduced(selection_bounds: Aabb, total_zoom: f64) -> bool {
+        let node_size = Self::RESIZE_NODE_SIZE[0] * Self::node_scale(selection_bounds, total_zoom);
+        let extents = selection_bounds.extents() * total_zoom;
+        extents[0] < 2.0 * node_size || extents[1] < 2.0 * node_size
     }
 
     fn resize_node_bounds(position: ResizeCorner, selection_bounds: Aabb, camera: &Camera) -> Aabb {
         let total_zoom = camera.total_zoom();
-        match position {
-            ResizeCorner::TopLeft => Aabb::from_half_extents(
-                na::point![
-                    selection_bounds.mins[0] - Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom,
-                    selection_bounds.mins[1] - Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom
-                ],
-                Self::RESIZE_NODE_SIZE * 0.5 / total_zoom,
-            ),
-            ResizeCorner::TopRight => Aabb::from_half_extents(
-                na::point![
-                    selection_bounds.maxs[0] + Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom,
-                    selection_bounds.mins[1] - Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom
-                ],
-                Self::RESIZE_NODE_SIZE * 0.5 / total_zoom,
-            ),
-            ResizeCorner::BottomLeft => Aabb::from_half_extents(
-                na::point![
-                    selection_bounds.mins[0] - Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom,
-                    selection_bounds.maxs[1] + Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom
-                ],
-                Self::RESIZE_NODE_SIZE * 0.5 / total_zoom,
-            ),
-            ResizeCorner::BottomRight => Aabb::from_half_extents(
-                na::point![
-                    selection_bounds.maxs[0] + Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom,
-                    selection_bounds.maxs[1] + Self::RESIZE_NODE_SIZE[0] * 0.5 / total_zoom
-                ],
-                Self::RESIZE_NODE_SIZE * 0.5 / total_zoom,
-            ),
+        let half_extents =
+            Self::RESIZE_NODE_SIZE * Self::node_scale(selection_bounds, total_zoom) * 0.5
+                / total_zoom;
+        let center = match position {
+            ResizeCorner::TopLeft => selection_bounds.mins.coords - half_extents,
+            ResizeCorner::TopRight => na::vector![
+                selection_bounds.maxs[0] + half_extents[0],
+                selection_bounds.mins[1] - half_extents[1]
+            ],
+            ResizeCorner::BottomLeft => na::vector![
+                selection_bounds.mins[0] - half_extents[0],
+                selection_bounds.maxs[1] + half_extents[1]
+            ],
+            ResizeCorner::BottomRight => selection_bounds.maxs.coords + half_extents,
+        };
+        Aabb::from_half_extents(center.into(), half_extents)
+    }
+
+    /// The resize nodes that are available for the selection, with their bounds.
+    ///
+    /// Used for both drawing and hit-testing.
+    fn resize_nodes(selection_bounds: Aabb, camera: &Camera) -> Vec<(ResizeCorner, Aabb)> {
+        let corners: &[ResizeCorner] = if Self::nodes_reduced(selection_bounds, camera.total_zoom())
+        {
+            &[ResizeCorner::BottomRight]
+        } else {
+            &ResizeCorner::ALL
+        };
+        corners
+            .iter()
+            .map(|&corner| {
+                (
+                    corner,
+                    Self::resize_node_bounds(corner, selection_bounds, camera),
+                )
+            })
+            .collect()
+    }
+
+    /// The available resize node at the position.
+    fn resize_node_at(
+        pos: na::Vector2<f64>,
+        selection_bounds: Aabb,
+        camera: &Camera,
+    ) -> Option<ResizeCorner> {
+        Self::resize_nodes(selection_bounds, camera)
+            .into_iter()
+            .find(|(_, bounds)| bounds.contains_local_point(&pos.into()))
+            .map(|(corner, _)| corner)
+    }
+
+    /// The minimum extents when resizing the selection.
+    ///
+    /// Per axis the larger of twice the largest stroke width in the selection and a fixed minimum,
+    /// but never larger than the extents at the start of the resize.
+    pub(crate) fn resize_min_extents(
+        store: &StrokeStore,
+        selection: &[StrokeKey],
+        start_bounds: Aabb,
+    ) -> na::Vector2<f64> {
+        let max_stroke_width = selection
+            .iter()
+            .filter_map(|&key| store.get_stroke_ref(key).and_then(|s| s.stroke_width()))
+            .fold(0.0_f64, f64::max);
+        let min_extent = (max_stroke_width * 2.0).max(Self::RESIZE_MIN_EXTENTS);
+
+        start_bounds.extents().map(|e| min_extent.min(e.max(0.0)))
+    }
+
+    /// The new extents of the selection while resizing, clamped to the minimum extents.
+    ///
+    /// When the aspect ratio is locked, the clamp is applied uniformly to both axes.
+    /// With `mirror` the extents become negative on the axes where the dragged corner crossed the pivot,
+    /// otherwise they are clamped to the minimum extents.
+    fn resize_new_extents(
+        start_extents: na::Vector2<f64>,
+        offset_to_start: na::Vector2<f64>,
+        min_extents: na::Vector2<f64>,
+        lock_aspectratio: bool,
+        mirror: bool,
+    ) -> na::Vector2<f64> {
+        let clamp = |value: f64, min: f64| {
+            if mirror {
+                value.signum() * value.abs().max(min)
+            } else {
+                value.max(min)
+            }
+        };
+
+        if lock_aspectratio {
+            let start_mean = start_extents.mean();
+            let min_factor = min_extents
+                .component_div(&start_extents)
+                .iter()
+                .copied()
+                .filter(|f| f.is_finite())
+                .fold(0.0_f64, f64::max);
+            let factor = clamp(
+                (start_mean + offset_to_start.mean()) / start_mean,
+                min_factor,
+            );
+            start_extents * factor
+        } else {
+            (start_extents + offset_to_start).zip_map(&min_extents, clamp)
         }
     }
 
+    /// The rotate node at the right of the selection.
+    ///
+    /// When the nodes are reduced, it takes the place of the top right resize node.
     fn rotate_node_sphere(selection_bounds: Aabb, camera: &Camera) -> BoundingSphere {
         let total_zoom = camera.total_zoom();
-        let pos = na::point![
-            selection_bounds.maxs[0] + Self::RESIZE_NODE_SIZE[0] / (2.0 * total_zoom),
-            (selection_bounds.maxs[1] + selection_bounds.mins[1]) * 0.5
-        ];
-        BoundingSphere::new(pos, Self::ROTATE_NODE_DIAMETER * 0.5 / total_zoom)
+        let node_scale = Self::node_scale(selection_bounds, total_zoom);
+        let pos = if Self::nodes_reduced(selection_bounds, total_zoom) {
+            Self::resize_node_bounds(ResizeCorner::TopRight, selection_bounds, camera).center()
+        } else {
+            na::point![
+                selection_bounds.maxs[0]
+                    + Self::RESIZE_NODE_SIZE[0] * node_scale / (2.0 * total_zoom),
+                (selection_bounds.maxs[1] + selection_bounds.mins[1]) * 0.5
+            ]
+        };
+        BoundingSphere::new(
+            pos,
+            Self::ROTATE_NODE_DIAMETER * node_scale * 0.5 / total_zoom,
+        )
     }
 
     fn draw_selection_overlay(
@@ -558,73 +1248,23 @@ impl Selector {
             _ => PenState::Up,
         };
 
-        let resize_tl_node_bounds =
-            Self::resize_node_bounds(ResizeCorner::TopLeft, selection_bounds, camera);
-        let resize_tl_node_state = match modify_state {
-            ModifyState::Resize {
-                from_corner: ResizeCorner::TopLeft,
-                ..
-            } => PenState::Down,
-            ModifyState::Hover(pos) => {
-                if resize_tl_node_bounds.contains_local_point(&(*pos).into()) {
-                    PenState::Proximity
-                } else {
-                    PenState::Up
-                }
-            }
-            _ => PenState::Up,
-        };
-
-        let resize_tr_node_bounds =
-            Self::resize_node_bounds(ResizeCorner::TopRight, selection_bounds, camera);
-        let resize_tr_node_state = match modify_state {
-            ModifyState::Resize {
-                from_corner: ResizeCorner::TopRight,
-                ..
-            } => PenState::Down,
-            ModifyState::Hover(pos) => {
-                if resize_tr_node_bounds.contains_local_point(&(*pos).into()) {
-                    PenState::Proximity
-                } else {
-                    PenState::Up
-                }
-            }
-            _ => PenState::Up,
-        };
-
-        let resize_bl_node_bounds =
-            Self::resize_node_bounds(ResizeCorner::BottomLeft, selection_bounds, camera);
-        let resize_bl_node_state = match modify_state {
-            ModifyState::Resize {
-                from_corner: ResizeCorner::BottomLeft,
-                ..
-            } => PenState::Down,
-            ModifyState::Hover(pos) => {
-                if resize_bl_node_bounds.contains_local_point(&(*pos).into()) {
-                    PenState::Proximity
-                } else {
-                    PenState::Up
-                }
-            }
-            _ => PenState::Up,
-        };
-
-        let resize_br_node_bounds =
-            Self::resize_node_bounds(ResizeCorner::BottomRight, selection_bounds, camera);
-        let resize_br_node_state = match modify_state {
-            ModifyState::Resize {
-                from_corner: ResizeCorner::BottomRight,
-                ..
-            } => PenState::Down,
-            ModifyState::Hover(pos) => {
-                if resize_br_node_bounds.contains_local_point(&(*pos).into()) {
-                    PenState::Proximity
-                } else {
-                    PenState::Up
-                }
-            }
-            _ => PenState::Up,
-        };
+        let resize_nodes = Self::resize_nodes(selection_bounds, camera)
+            .into_iter()
+            .map(|(corner, bounds)| {
+                let state = match modify_state {
+                    ModifyState::Resize {
+                        from_corner,
+                        mirrored,
+                        ..
+                    } if from_corner.mirrored(*mirrored) == corner => PenState::Down,
+                    ModifyState::Hover(pos) if bounds.contains_local_point(&(*pos).into()) => {
+                        PenState::Proximity
+                    }
+                    _ => PenState::Up,
+                };
+                (bounds, state)
+            })
+            .collect::<Vec<(Aabb, PenState)>>();
 
         // Selection rect
         let selection_rect = selection_bounds.to_kurbo_rect();
@@ -632,38 +1272,11 @@ impl Selector {
         piet_cx.save().map_err(|e| anyhow::anyhow!("{e:?}"))?;
 
         let mut clip_path = kurbo::BezPath::new();
-        clip_path.extend(
-            indicators::rectangular_node_shape(
-                resize_tl_node_state,
-                resize_tl_node_bounds,
-                total_zoom,
-            )
-            .path_elements(0.1),
-        );
-        clip_path.extend(
-            indicators::rectangular_node_shape(
-                resize_tr_node_state,
-                resize_tr_node_bounds,
-                total_zoom,
-            )
-            .path_elements(0.1),
-        );
-        clip_path.extend(
-            indicators::rectangular_node_shape(
-                resize_bl_node_state,
-                resize_bl_node_bounds,
-                total_zoom,
-            )
-            .path_elements(0.1),
-        );
-        clip_path.extend(
-            indicators::rectangular_node_shape(
-                resize_br_node_state,
-                resize_br_node_bounds,
-                total_zoom,
-            )
-            .path_elements(0.1),
-        );
+        for (bounds, state) in resize_nodes.iter() {
+            clip_path.extend(
+                indicators::rectangular_node_shape(*state, *bounds, total_zoom).path_elements(0.1),
+            );
+        }
 
         clip_path.extend(
             indicators::circular_node_shape(rotate_node_state, rotate_node_sphere, total_zoom)
@@ -696,30 +1309,9 @@ impl Selector {
         indicators::draw_circular_node(piet_cx, rotate_node_state, rotate_node_sphere, total_zoom);
 
         // Resize Nodes
-        indicators::draw_rectangular_node(
-            piet_cx,
-            resize_tl_node_state,
-            resize_tl_node_bounds,
-            total_zoom,
-        );
-        indicators::draw_rectangular_node(
-            piet_cx,
-            resize_tr_node_state,
-            resize_tr_node_bounds,
-            total_zoom,
-        );
-        indicators::draw_rectangular_node(
-            piet_cx,
-            resize_bl_node_state,
-            resize_bl_node_bounds,
-            total_zoom,
-        );
-        indicators::draw_rectangular_node(
-            piet_cx,
-            resize_br_node_state,
-            resize_br_node_bounds,
-            total_zoom,
-        );
+        for (bounds, state) in resize_nodes {
+            indicators::draw_rectangular_node(piet_cx, state, bounds, total_zoom);
+        }
 
         piet_cx.restore().map_err(|e| anyhow::anyhow!("{e:?}"))?;
         Ok(())
@@ -775,38 +1367,404 @@ impl Selector {
         Ok(())
     }
 
-    fn select_all(
-        &mut self,
-        modifier_keys: HashSet<ModifierKey>,
-        engine_view: &mut EngineViewMut,
-        widget_flags: &mut WidgetFlags,
-    ) {
-        if modifier_keys.contains(&ModifierKey::KeyboardCtrl) {
-            // Select all keys
-            let all_strokes = engine_view.store.stroke_keys_as_rendered();
+    /// Constrain the offset of a translation to the nearest horizontal, vertical or diagonal direction.
+    ///
+    /// Returns the constrained offset and the unit direction, which is None for a zero offset.
+    fn constrain_translate_offset(
+        offset: na::Vector2<f64>,
+    ) -> (na::Vector2<f64>, Option<na::Vector2<f64>>) {
+        if offset.magnitude() == 0.0 {
+            return (offset, None);
+        }
+        const STEP: f64 = std::f64::consts::FRAC_PI_4;
+        let angle = (offset[1].atan2(offset[0]) / STEP).round() * STEP;
+        let direction = na::vector![angle.cos(), angle.sin()];
+        (direction * offset.dot(&direction), Some(direction))
+    }
 
-            if let Some(new_bounds) = engine_view.store.bounds_for_strokes(&all_strokes) {
-                engine_view.store.set_selected_keys(&all_strokes, true);
-                *widget_flags |= engine_view
-                    .document
-                    .resize_autoexpand(engine_view.store, engine_view.camera);
+    /// Draw a faint line through the translation start position along the constrained direction, spanning the viewport.
+    fn draw_translate_constraint_guide(
+        piet_cx: &mut impl RenderContext,
+        start_pos: na::Vector2<f64>,
+        direction: na::Vector2<f64>,
+        camera: &Camera,
+    ) {
+        let viewport = camera.viewport();
+        let half_length =
+            viewport.extents().magnitude() + (viewport.center().coords - start_pos).magnitude();
+        let guide = kurbo::Line::new(
+            (start_pos - direction * half_length).to_kurbo_point(),
+            (start_pos + direction * half_length).to_kurbo_point(),
+        );
+        piet_cx.stroke(
+            guide,
+            &Self::TRANSLATE_CONSTRAINT_GUIDE_COLOR,
+            1.0 / camera.total_zoom(),
+        );
+    }
 
-                self.state = SelectorState::ModifySelection {
-                    modify_state: ModifyState::default(),
-                    selection: all_strokes,
-                    selection_bounds: new_bounds,
-                };
+    /// Draw a small crosshair at the point of the selection that is snapped.
+    fn draw_snap_crosshair(
+        piet_cx: &mut impl RenderContext,
+        pos: na::Vector2<f64>,
+        camera: &Camera,
+    ) {
+        let total_zoom = camera.total_zoom();
+        let half_extents = Self::SNAP_CROSSHAIR_HALF_EXTENTS / total_zoom;
+        let mut crosshair = kurbo::BezPath::new();
+        crosshair.move_to((pos - na::vector![half_extents, 0.0]).to_kurbo_point());
+        crosshair.line_to((pos + na::vector![half_extents, 0.0]).to_kurbo_point());
+        crosshair.move_to((pos - na::vector![0.0, half_extents]).to_kurbo_point());
+        crosshair.line_to((pos + na::vector![0.0, half_extents]).to_kurbo_point());
+        piet_cx.stroke(
+            crosshair,
+            &Self::SELECTION_OUTLINE_COLOR,
+            Self::OUTLINE_STROKE_WIDTH / total_zoom,
+        );
+    }
+
+    /// Snap the offset the selection bounds are translated by.
+    ///
+    /// When enabled, the center of the bounds is snapped in addition to the snap corner, and the one that requires
+    /// the smaller correction is used. On ties the corner is used.
+    /// Returns the snapped offset and the corner that engaged.
+    fn snap_translate_offset(
+        bounds: Aabb,
+        snap_corner: SnapCorner,
+        offset: na::Vector2<f64>,
+        snap_center: bool,
+        document: &Document,
+    ) -> (na::Vector2<f64>, SnapCorner) {
+        let snapped = |corner: SnapCorner| {
+            let corner_pos = corner.pos_on_bounds(bounds);
+            document.snap_position(corner_pos + offset) - corner_pos
+        };
+        let corner_offset = snapped(snap_corner);
+        if !snap_center {
+            return (corner_offset, snap_corner);
+        }
+        let center_offset = snapped(SnapCorner::Center);
+        if (center_offset - offset).magnitude() < (corner_offset - offset).magnitude() {
+            (center_offset, SnapCorner::Center)
+        } else {
+            (corner_offset, snap_corner)
+        }
+    }
+
+    /// Filter the selection against the keys that still exist and are not trashed.
+    ///
+    /// Strokes can disappear while the selection is modified, for example when they are trashed by a background task.
+    /// Transitions to idle when none of the selected strokes are left.
+    /// Returns false if the selection became empty.
+    pub(super) fn validate_selection(&mut self, engine_view: &mut EngineViewMut) -> bool {
+        let SelectorState::ModifySelection {
+            selection,
+            selection_bounds,
+            ..
+        } = &mut self.state
+        else {
+            return true;
+        };
+        let (valid, vanished): (Vec<StrokeKey>, Vec<StrokeKey>) =
+            selection.iter().copied().partition(|&key| {
+                engine_view.store.get_stroke_ref(key).is_some()
+                    && !engine_view.store.trashed(key).unwrap_or(true)
+            });
+        if vanished.is_empty() {
+            return true;
+        }
+        engine_view.store.abort_width_scaling(&vanished);
+
+        match engine_view.store.bounds_for_strokes(&valid) {
+            Some(new_bounds) => {
+                *selection = valid;
+                *selection_bounds = new_bounds;
+                true
+            }
+            None => {
+                self.state = SelectorState::Idle;
+                self.resize_input = None;
+                self.auto_nudge = None;
+                false
+            }
+        }
+    }
+
+    /// The event result when the selection vanished, see [Selector::validate_selection].
+    pub(super) fn selection_vanished_result() -> (EventResult<PenProgress>, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        widget_flags.redraw = true;
+        widget_flags.selection_changed = true;
+        (
+            EventResult {
+                handled: true,
+                propagate: EventPropagation::Stop,
+                progress: PenProgress::Finished,
+            },
+            widget_flags,
+        )
+    }
+
+    /// Begin a store transaction when the selection started to be translated, rotated, resized or sheared.
+    pub(super) fn begin_gesture_transaction(&mut self, engine_view: &mut EngineViewMut) {
+        if self.transaction.is_none()
+            && matches!(
+                self.state,
+                SelectorState::ModifySelection {
+                    modify_state: ModifyState::Translate { .. }
+                        | ModifyState::Rotate { .. }
+                        | ModifyState::Resize { .. }
+                        | ModifyState::Shear { .. },
+                    ..
+                }
+            )
+        {
+            self.transaction = Some(engine_view.store.begin_transaction());
+            self.gesture_transform = None;
+        }
+    }
+
+    /// Render the selection into a composite when it started to be translated or rotated,
+    /// if it is large and enabled in the config.
+    pub(super) fn begin_drag_composite(&self, engine_view: &mut EngineViewMut) {
+        let SelectorState::ModifySelection {
+            modify_state: ModifyState::Translate { .. } | ModifyState::Rotate { .. },
+            selection,
+            ..
+        } = &self.state
+        else {
+            return;
+        };
+        if engine_view.pens_config.selector_config.drag_composite
+            && selection.len() >= Self::DRAG_COMPOSITE_MIN_STROKES
+            && engine_view.store.selection_composite().is_none()
+        {
+            engine_view
+                .store
+                .begin_selection_composite(selection, engine_view.camera.image_scale());
+        }
+    }
+
+    /// Discard the composite when the gesture it was rendered for ended otherwise than by committing it.
+    fn discard_stale_drag_composite(&self, engine_view: &mut EngineViewMut) {
+        if !matches!(
+            self.state,
+            SelectorState::ModifySelection {
+                modify_state: ModifyState::Translate { .. } | ModifyState::Rotate { .. },
+                ..
+            }
+        ) {
+            engine_view.store.discard_selection_composite();
+        }
+    }
 
-                widget_flags.store_modified = true;
-                widget_flags.deselect_color_setters = true;
+    /// Roll back the transaction of the gesture, restoring the selection as it was before the gesture began.
+    pub(super) fn rollback_gesture(
+        tx_id: TxId,
+        selection: &[StrokeKey],
+        selection_bounds: &mut Aabb,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        engine_view.store.abort_width_scaling(selection);
+        let restored = engine_view.store.rollback_transaction(tx_id);
+        engine_view.store.clear_pending_rendering_coalesced();
+        engine_view.store.regenerate_rendering_for_strokes_threaded(
+            engine_view.tasks_tx.clone(),
+            &restored,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+            *selection_bounds = new_bounds;
+        }
+        widget_flags |= engine_view
+            .document
+            .resize_autoexpand(engine_view.store, engine_view.camera);
+        widget_flags.redraw = true;
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
+    /// Remove the duplicates of a copy-drag that never moved, selecting the original strokes again.
+    pub(super) fn discard_copy_drag(
+        originals: Vec<StrokeKey>,
+        selection: &mut Vec<StrokeKey>,
+        selection_bounds: &mut Aabb,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        for &key in selection.iter() {
+            engine_view.store.remove_stroke(key);
+        }
+        engine_view.store.set_selected_keys(&originals, true);
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(&originals) {
+            *selection_bounds = new_bounds;
+        }
+        *selection = originals;
+        widget_flags.redraw = true;
+        widget_flags.store_modified = true;
+        widget_flags.selection_changed = true;
+        widget_flags
+    }
+
+    /// The region that needs to be redrawn after the selector was updated, given its bounds before the update.
+    ///
+    /// Only available while selecting with a path and while translating, rotating or resizing the selection.
+    /// Returns None when the whole surface needs to be redrawn, e.g. when the tentative selection or a guide
+    /// extends beyond the selector bounds.
+    pub(super) fn damage(
+        &self,
+        prev_bounds: Option<Aabb>,
+        engine_view: &EngineView,
+    ) -> Option<Aabb> {
+        match &self.state {
+            SelectorState::Selecting { tentative, .. } if tentative.keys.is_empty() => {}
+            SelectorState::ModifySelection {
+                modify_state:
+                    ModifyState::Translate {
+                        constraint: None, ..
+                    }
+                    | ModifyState::Rotate { .. }
+                    | ModifyState::Resize { .. }
+                    | ModifyState::Shear { .. },
+                ..
+            } => {}
+            _ => return None,
+        }
+        let bounds = self.bounds_on_doc(engine_view)?;
+        // The rotate node is placed next to the selection bounds
+        let damage = prev_bounds
+            .map(|prev_bounds| prev_bounds.merged(&bounds))
+            .unwrap_or(bounds)
+            .loosened(Self::ROTATE_NODE_DIAMETER / engine_view.camera.total_zoom());
+        Some(damage)
+    }
+
+    /// Update the long press with the current pen position.
+    ///
+    /// Returns the topmost stroke under the anchor once the pen was held still long enough.
+    /// The long press is then finished, as it is when the pen moved too far away from the anchor.
+    fn long_press_stroke(
+        long_press: &mut Option<LongPress>,
+        pos: na::Vector2<f64>,
+        now: Instant,
+        engine_view: &EngineViewMut,
+    ) -> Option<StrokeKey> {
+        let current = (*long_press)?;
+        if (pos - current.anchor).magnitude()
+            > Self::LONG_PRESS_RADIUS / engine_view.camera.total_zoom()
+        {
+            *long_press = None;
+            return None;
+        }
+        if now.saturating_duration_since(current.start) < Self::LONG_PRESS_DURATION {
+            return None;
+        }
+        *long_press = None;
+        engine_view
+            .store
+            .stroke_hitboxes_contain_coord(current.anchor, false)
+            .pop()
+    }
+
+    /// Whether the selection consists solely of image strokes.
+    fn selection_images_only(selection: &[StrokeKey], store: &StrokeStore) -> bool {
+        !selection.is_empty()
+            && store
+                .get_strokes_ref(selection)
+                .iter()
+                .all(|stroke| matches!(stroke, Stroke::BitmapImage(_) | Stroke::VectorImage(_)))
+    }
+
+    /// Whether the aspect ratio is locked when resizing the selection.
+    ///
+    /// Images get easily distorted, so their aspect ratio is locked unless Ctrl is pressed.
+    fn resize_lock_aspectratio(
+        selection: &[StrokeKey],
+        modifier_keys: &HashSet<ModifierKey>,
+        engine_view: &EngineViewMut,
+    ) -> bool {
+        let ctrl_pressed = modifier_keys.contains(&ModifierKey::KeyboardCtrl);
+        if Self::selection_images_only(selection, engine_view.store) {
+            !ctrl_pressed
+        } else {
+            engine_view
+                .pens_config
+                .selector_config
+                .resize_lock_aspectratio
+                || ctrl_pressed
+        }
+    }
+
+    /// The bounds of the page that contains the viewport center, for layouts with a fixed width.
+    fn viewport_center_page_bounds(engine_view: &EngineViewMut) -> Option<Aabb> {
+        if !engine_view.document.layout.is_fixed_width() {
+            return None;
+        }
+        let viewport_center = engine_view.camera.viewport_center();
+        engine_view
+            .document
+            .pages_bounds(SplitOrder::default())
+            .into_iter()
+            .find(|page_bounds| page_bounds.contains_local_point(&viewport_center.into()))
+    }
+
+    fn select_all(
+        &mut self,
+        scope: SelectAllScope,
+        engine_view: &mut EngineViewMut,
+        widget_flags: &mut WidgetFlags,
+    ) {
+        let keys = match scope {
+            SelectAllScope::Document => engine_view.store.stroke_keys_as_rendered(),
+            SelectAllScope::Page => match Self::viewport_center_page_bounds(engine_view) {
+                Some(page_bounds) => engine_view
+                    .store
+                    .stroke_keys_as_rendered_intersecting_bounds(page_bounds),
+                None => engine_view
+                    .store
+                    .stroke_keys_as_rendered_intersecting_bounds(engine_view.camera.viewport()),
+            },
+            SelectAllScope::Viewport => engine_view
+                .store
+                .stroke_keys_as_rendered_intersecting_bounds(engine_view.camera.viewport()),
+        };
+        // Select all keys in the scope, except the locked ones
+        let all_strokes = engine_view.store.unlocked_keys(&keys);
+
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(&all_strokes) {
+            if let SelectorState::ModifySelection { selection, .. } = &self.state {
+                // The previous selection might not be entirely inside the new scope
+                engine_view
+                    .store
+                    .set_selected_keys_keep_order(selection, false);
             }
+            engine_view.store.set_selected_keys(&all_strokes, true);
+            *widget_flags |= engine_view
+                .document
+                .resize_autoexpand(engine_view.store, engine_view.camera);
+
+            self.state = SelectorState::ModifySelection {
+                modify_state: ModifyState::default(),
+                selection: all_strokes,
+                selection_bounds: new_bounds,
+                rotation_angle: 0.0,
+            };
+
+            widget_flags.store_modified = true;
+            widget_flags.deselect_color_setters = true;
+            widget_flags.selection_changed = true;
         }
     }
 }
 
 fn cancel_selection(selection: &[StrokeKey], engine_view: &mut EngineViewMut) -> WidgetFlags {
     let mut widget_flags = WidgetFlags::default();
-    engine_view.store.set_selected_keys(selection, false);
+    engine_view
+        .store
+        .set_selected_keys_keep_order(selection, false);
     engine_view.store.update_geometry_for_strokes(selection);
     engine_view.store.regenerate_rendering_in_viewport_threaded(
         engine_view.tasks_tx.clone(),
@@ -818,5 +1776,2074 @@ fn cancel_selection(selection: &[StrokeKey], engine_view: &mut EngineViewMut) ->
     widget_flags |= engine_view.store.record(Instant::now());
     widget_flags.store_modified = true;
     widget_flags.resize = true;
+    widget_flags.selection_changed = true;
     widget_flags
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Layout;
+    use crate::pens::pensconfig::selectorconfig::{RectSelectionMode, WidthScalePolicy};
+    use crate::pens::pensconfig::selectorshortcuts::{KeyCombo, SelectorAction};
+    use crate::pens::Pen;
+    use crate::strokes::textstroke::TextStyle;
+    use crate::strokes::{ShapeStroke, Stroke, TextStroke};
+    use crate::testsupport::{assert_bounds_eq, down_event, engine_w_selected_rect, insert_rect};
+    use approx::assert_relative_eq;
+    use rnote_compose::penevent::{KeyboardKey, ModifierKey};
+    use rnote_compose::shapes::Shapeable;
+    use rnote_compose::{Shape, Style};
+
+    fn resize_selection(engine: &mut Engine, selector: &Selector, scale: f64) -> Aabb {
+        let SelectorState::ModifySelection {
+            selection,
+            selection_bounds,
+            ..
+        } = &selector.state
+        else {
+            panic!("selector is not modifying a selection");
+        };
+        engine.store.scale_strokes_with_pivot(
+            selection,
+            na::Vector2::repeat(scale),
+            selection_bounds.mins.coords,
+        );
+        engine.store.update_geometry_for_strokes(selection);
+        engine.store.record(Instant::now());
+        engine.store.bounds_for_strokes(selection).unwrap()
+    }
+
+    #[test]
+    fn selection_survives_undo() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        let initial_bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+
+        let resized_bounds = resize_selection(&mut engine, &selector, 2.0);
+        assert!(resized_bounds.extents()[0] > initial_bounds.extents()[0]);
+
+        engine.store.undo(Instant::now());
+        selector.update_state_after_history_change(&mut engine.view_mut());
+        let SelectorState::ModifySelection {
+            selection,
+            selection_bounds,
+            ..
+        } = &selector.state
+        else {
+            panic!("selection was not retained after undo");
+        };
+        assert_eq!(selection, &vec![key]);
+        assert_bounds_eq(*selection_bounds, initial_bounds);
+
+        // resizing again starts from the restored bounds
+        let resized_again_bounds = resize_selection(&mut engine, &selector, 2.0);
+        assert_bounds_eq(resized_again_bounds, resized_bounds);
+    }
+
+    fn pen_down(engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>) {
+        let _ = selector.handle_event(down_event(pos), Instant::now(), &mut engine.view_mut());
+    }
+
+    /// Handle a pen down or up event of the primary button at `pos` while holding the modifiers.
+    fn pen_event_w_modifiers(
+        engine: &mut Engine,
+        selector: &mut Selector,
+        pos: na::Vector2<f64>,
+        modifiers: &[ModifierKey],
+        up: bool,
+    ) {
+        let element = Element::new(pos, Element::PRESSURE_DEFAULT);
+        let modifier_keys = HashSet::from_iter(modifiers.iter().copied());
+        let event = if up {
+            PenEvent::up(element, modifier_keys)
+        } else {
+            PenEvent::down(element, modifier_keys)
+        };
+        let _ = selector.handle_event(event, Instant::now(), &mut engine.view_mut());
+    }
+
+    fn resize_state(selector: &Selector) -> (Aabb, na::Vector2<f64>) {
+        let SelectorState::ModifySelection {
+            modify_state: ModifyState::Resize { min_extents, .. },
+            selection_bounds,
+            ..
+        } = &selector.state
+        else {
+            panic!("selector is not resizing a selection");
+        };
+        (*selection_bounds, *min_extents)
+    }
+
+    #[test]
+    fn resize_stays_above_min_extents() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let SelectorState::ModifySelection {
+            selection_bounds: start_bounds,
+            ..
+        } = selector.state.clone()
+        else {
+            panic!("selector is not modifying a selection");
+        };
+
+        // start dragging the bottom right resize node
+        let total_zoom = engine.camera.total_zoom();
+        let start_pos = start_bounds.maxs.coords + Selector::RESIZE_NODE_SIZE * 0.5 / total_zoom;
+        pen_down(&mut engine, &mut selector, start_pos);
+        let (_, min_extents) = resize_state(&selector);
+        let stroke_width = engine
+            .store
+            .get_stroke_ref(key)
+            .and_then(|s| s.stroke_width())
+            .unwrap();
+        assert_relative_eq!(
+            min_extents[0],
+            (stroke_width * 2.0).max(Selector::RESIZE_MIN_EXTENTS)
+        );
+
+        // drag far past the pivot at the top left corner
+        let end_pos = start_pos - na::vector![1000.0, 1000.0];
+        let mut extents = Vec::new();
+        for i in 1..=20 {
+            pen_down(
+                &mut engine,
+                &mut selector,
+                start_pos.lerp(&end_pos, i as f64 / 20.0),
+            );
+            let (bounds, _) = resize_state(&selector);
+            assert!(bounds.extents()[0] >= min_extents[0] - 1e-6);
+            assert!(bounds.extents()[1] >= min_extents[1] - 1e-6);
+            extents.push(bounds.extents());
+        }
+        // stuck at the minimum instead of oscillating
+        let last_extents = *extents.last().unwrap();
+        assert_relative_eq!(last_extents[0], min_extents[0], epsilon = 1e-6);
+        assert_relative_eq!(last_extents[1], min_extents[1], epsilon = 1e-6);
+
+        // dragging back out recovers the original size
+        let mut prev_extents = last_extents;
+        for i in 1..=20 {
+            pen_down(
+                &mut engine,
+                &mut selector,
+                end_pos.lerp(&start_pos, i as f64 / 20.0),
+            );
+            let (bounds, _) = resize_state(&selector);
+            assert!(bounds.extents()[0] >= prev_extents[0] - 1e-6);
+            assert!(bounds.extents()[1] >= prev_extents[1] - 1e-6);
+            prev_extents = bounds.extents();
+        }
+        assert_relative_eq!(prev_extents[0], start_bounds.extents()[0], epsilon = 1e-6);
+        assert_relative_eq!(prev_extents[1], start_bounds.extents()[1], epsilon = 1e-6);
+    }
+
+    #[test]
+    fn resize_mirrors_across_pivot() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        engine.pens_config.selector_config.resize_mirror = true;
+
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let SelectorState::ModifySelection {
+            selection_bounds: start_bounds,
+            ..
+        } = selector.state.clone()
+        else {
+            panic!("selector is not modifying a selection");
+        };
+        let start_width = engine
+            .store
+            .get_stroke_ref(key)
+            .and_then(|s| s.stroke_width())
+            .unwrap();
+        let start_extents = start_bounds.extents();
+
+        // start dragging the bottom right resize node
+        let total_zoom = engine.camera.total_zoom();
+        let start_pos = start_bounds.maxs.coords + Selector::RESIZE_NODE_SIZE * 0.5 / total_zoom;
+        pen_down(&mut engine, &mut selector, start_pos);
+
+        // drag horizontally across the pivot at the left edge, crossing it repeatedly
+        let mirrored_pos = start_pos - na::vector![start_extents[0] * 2.0, 0.0];
+        for pos in [mirrored_pos, start_pos, mirrored_pos] {
+            for i in 1..=10 {
+                pen_down(
+                    &mut engine,
+                    &mut selector,
+                    start_pos.lerp(&pos, i as f64 / 10.0),
+                );
+            }
+        }
+        let SelectorState::ModifySelection {
+            modify_state:
+                ModifyState::Resize {
+                    from_corner,
+                    mirrored,
+                    ..
+                },
+            selection_bounds,
+            ..
+        } = selector.state.clone()
+        else {
+            panic!("selector is not resizing a selection");
+        };
+        assert_eq!(mirrored, [true, false]);
+        assert_eq!(from_corner.mirrored(mirrored), ResizeCorner::BottomLeft);
+        assert_bounds_eq(
+            selection_bounds,
+            Aabb::new(
+                na::point![
+                    start_bounds.mins[0] - start_extents[0],
+                    start_bounds.mins[1]
+                ],
+                na::point![start_bounds.mins[0], start_bounds.maxs[1]],
+            ),
+        );
+
+        let _ = selector.handle_event(
+            PenEvent::up(
+                Element::new(mirrored_pos, Element::PRESSURE_DEFAULT),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        // stroke widths stay positive and unchanged by the flip
+        assert_relative_eq!(
+            engine
+                .store
+                .get_stroke_ref(key)
+                .and_then(|s| s.stroke_width())
+                .unwrap(),
+            start_width,
+            epsilon = 1e-6
+        );
+
+        // a single undo reverts the flips and the scale
+        engine.store.undo(Instant::now());
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            start_bounds,
+        );
+    }
+
+    #[test]
+    fn toggling_lock_aspectratio_mid_resize_is_continuous() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        engine.pens_config.selector_config.resize_lock_aspectratio = false;
+
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let SelectorState::ModifySelection {
+            selection_bounds: start_bounds,
+            ..
+        } = selector.state.clone()
+        else {
+            panic!("selector is not modifying a selection");
+        };
+
+        // start dragging the bottom right resize node
+        let total_zoom = engine.camera.total_zoom();
+        let start_pos = start_bounds.maxs.coords + Selector::RESIZE_NODE_SIZE * 0.5 / total_zoom;
+        pen_down(&mut engine, &mut selector, start_pos);
+
+        let step = na::vector![10.0, 2.0];
+        let mut pos = start_pos;
+        let mut prev_bounds = start_bounds;
+        for i in 1..=40 {
+            // Ctrl is held during the second quarter, the config locks the aspect ratio in the last one
+            let modifier_keys = if (11..=20).contains(&i) {
+                HashSet::from([ModifierKey::KeyboardCtrl])
+            } else {
+                HashSet::new()
+            };
+            engine.pens_config.selector_config.resize_lock_aspectratio = i > 30;
+            pos += step;
+            let _ = selector.handle_event(
+                PenEvent::down(Element::new(pos, Element::PRESSURE_DEFAULT), modifier_keys),
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            let (bounds, _) = resize_state(&selector);
+            // the pivot stays in place
+            assert_relative_eq!(bounds.mins[0], start_bounds.mins[0], epsilon = 1e-6);
+            assert_relative_eq!(bounds.mins[1], start_bounds.mins[1], epsilon = 1e-6);
+            // the size never changes more than the pointer moved, also not when the lock changes
+            let size_change = (bounds.extents() - prev_bounds.extents()).magnitude();
+            assert!(
+                size_change <= step.magnitude() + 1e-6,
+                "size changed by {size_change} on event {i}"
+            );
+            prev_bounds = bounds;
+        }
+    }
+
+    #[test]
+    fn resize_lock_aspectratio_clamps_uniformly() {
+        let start_extents = na::vector![100.0, 50.0];
+        let min_extents = na::vector![4.0, 4.0];
+
+        for offset in [-60.0, -100.0, -1000.0, -1e6] {
+            let new_extents = Selector::resize_new_extents(
+                start_extents,
+                na::vector![offset, offset],
+                min_extents,
+                true,
+                false,
+            );
+            assert!(new_extents[0] >= min_extents[0] - 1e-9);
+            assert!(new_extents[1] >= min_extents[1] - 1e-9);
+            // the aspect ratio is preserved
+            assert_relative_eq!(new_extents[0] / new_extents[1], 2.0);
+        }
+        // stuck at the minimum when dragging further past the pivot
+        for offset in [-1000.0, -1e6] {
+            let new_extents = Selector::resize_new_extents(
+                start_extents,
+                na::vector![offset, offset],
+                min_extents,
+                true,
+                false,
+            );
+            assert_relative_eq!(new_extents[0], 8.0);
+            assert_relative_eq!(new_extents[1], 4.0);
+        }
+    }
+
+    #[test]
+    fn select_last_strokes_modifies_selection() {
+        let mut engine = Engine::default();
+        let keys = (0..3)
+            .map(|i| {
+                let offset = i as f64 * 200.0;
+                insert_rect(
+                    &mut engine.store,
+                    Aabb::new(na::point![offset, 0.0], na::point![offset + 100.0, 50.0]),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        engine.store.record(Instant::now());
+
+        let _ = engine.select_last_strokes(2);
+        let expected_bounds = engine.store.bounds_for_strokes(&keys[1..]).unwrap();
+        let Pen::Selector(selector) = engine.penholder.current_pen_ref() else {
+            panic!("current pen is not the selector");
+        };
+        let SelectorState::ModifySelection {
+            selection,
+            selection_bounds,
+            ..
+        } = &selector.state
+        else {
+            panic!("selector is not modifying the selection");
+        };
+        let mut selection = selection.clone();
+        selection.sort();
+        let mut expected = keys[1..].to_vec();
+        expected.sort();
+        assert_eq!(selection, expected);
+        assert_bounds_eq(*selection_bounds, expected_bounds);
+    }
+
+    #[test]
+    fn width_scale_policies() {
+        let scale = na::vector![2.0, 1.0];
+        for (policy, expected_factor) in [
+            (WidthScalePolicy::MeanAxes, std::f64::consts::SQRT_2),
+            (WidthScalePolicy::MinAxis, 1.0),
+            (WidthScalePolicy::MaxAxis, 2.0),
+            (WidthScalePolicy::None, 1.0),
+        ] {
+            let mut engine = Engine::default();
+            let key = insert_rect(
+                &mut engine.store,
+                Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+            );
+            let start_width = engine
+                .store
+                .get_stroke_ref(key)
+                .and_then(|stroke| stroke.stroke_width())
+                .unwrap();
+
+            engine.store.begin_width_scaling(&[key], policy);
+            // scale in two steps, like it is done while dragging
+            for _ in 0..2 {
+                engine.store.scale_strokes_with_pivot(
+                    &[key],
+                    scale.map(|s| s.sqrt()),
+                    na::Vector2::zeros(),
+                );
+            }
+            engine.store.commit_width_scaling(&[key]);
+
+            let width = engine
+                .store
+                .get_stroke_ref(key)
+                .and_then(|stroke| stroke.stroke_width())
+                .unwrap();
+            assert_relative_eq!(width, start_width * expected_factor, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn selection_survives_vanishing_keys_mid_gesture() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+
+        let keys = (0..2)
+            .map(|i| {
+                let offset = i as f64 * 200.0;
+                insert_rect(
+                    &mut engine.store,
+                    Aabb::new(na::point![offset, 0.0], na::point![offset + 100.0, 50.0]),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        engine.store.record(Instant::now());
+        engine.store.set_selected_keys(&keys, true);
+        selector.update_state(&mut engine.view_mut());
+        let SelectorState::ModifySelection {
+            selection_bounds: start_bounds,
+            ..
+        } = selector.state.clone()
+        else {
+            panic!("selector is not modifying a selection");
+        };
+
+        // start dragging the bottom right resize node
+        let total_zoom = engine.camera.total_zoom();
+        let start_pos = start_bounds.maxs.coords + Selector::RESIZE_NODE_SIZE * 0.5 / total_zoom;
+        pen_down(&mut engine, &mut selector, start_pos);
+        let _ = resize_state(&selector);
+
+        // a stroke disappears between the down and the following motion event
+        engine.store.remove_stroke(keys[1]);
+        pen_down(
+            &mut engine,
+            &mut selector,
+            start_pos + na::vector![10.0, 10.0],
+        );
+        let SelectorState::ModifySelection { selection, .. } = &selector.state else {
+            panic!("selector is not modifying the selection anymore");
+        };
+        assert_eq!(selection, &vec![keys[0]]);
+
+        // when the last stroke disappears the selector becomes idle
+        engine.store.set_trashed(keys[0], true);
+        let (event_result, _) = selector.handle_event(
+            PenEvent::down(
+                Element::new(
+                    start_pos + na::vector![20.0, 20.0],
+                    Element::PRESSURE_DEFAULT,
+                ),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        assert_eq!(event_result.progress, PenProgress::Finished);
+        assert!(matches!(selector.state, SelectorState::Idle));
+    }
+
+    #[test]
+    fn remapped_duplicate_shortcut() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine
+            .pens_config
+            .selector_config
+            .shortcuts
+            .set_bindings(
+                SelectorAction::Duplicate,
+                vec![KeyCombo::new(
+                    KeyboardKey::Unicode('u'),
+                    [ModifierKey::KeyboardCtrl],
+                )],
+            )
+            .unwrap();
+
+        let press = |engine: &mut Engine, selector: &mut Selector, keychar: char| {
+            engine
+                .store
+                .set_selected_keys(&engine.store.stroke_keys_unordered(), false);
+            engine.store.set_selected(key, true);
+            selector.update_state(&mut engine.view_mut());
+            let _ = selector.handle_event(
+                PenEvent::KeyPressed {
+                    keyboard_key: KeyboardKey::Unicode(keychar),
+                    modifier_keys: HashSet::from([ModifierKey::KeyboardCtrl]),
+                },
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            engine.store.stroke_keys_unordered().len()
+        };
+
+        // the old binding no longer duplicates
+        assert_eq!(press(&mut engine, &mut selector, 'd'), 1);
+        assert_eq!(press(&mut engine, &mut selector, 'u'), 2);
+    }
+
+    #[test]
+    fn unowned_keys_propagate_with_active_selection() {
+        let mut engine = Engine::default();
+
+        insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        let _ = engine.select_all_strokes();
+        assert_eq!(engine.store.selection_keys_unordered().len(), 1);
+
+        let (propagate, _) = engine.handle_pen_event(
+            PenEvent::KeyPressed {
+                keyboard_key: KeyboardKey::Unicode('+'),
+                modifier_keys: HashSet::new(),
+            },
+            None,
+            Instant::now(),
+        );
+        assert_eq!(propagate, EventPropagation::Proceed);
+        // the selection is kept
+        assert_eq!(engine.store.selection_keys_unordered().len(), 1);
+    }
+
+    fn key_press(engine: &mut Engine, selector: &mut Selector, keyboard_key: KeyboardKey) {
+        let _ = selector.handle_event(
+            PenEvent::KeyPressed {
+                keyboard_key,
+                modifier_keys: HashSet::new(),
+            },
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+    }
+
+    fn in_node_edit(selector: &Selector) -> bool {
+        matches!(
+            selector.state,
+            SelectorState::ModifySelection {
+                modify_state: ModifyState::EditNodes { .. },
+                ..
+            }
+        )
+    }
+
+    #[test]
+    fn node_edit_drags_control_point() {
+        use rnote_compose::shapes::{ControlPoints, CubicBezier};
+
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let cubbez = CubicBezier {
+            start: na::vector![0.0, 0.0],
+            cp1: na::vector![20.0, 80.0],
+            cp2: na::vector![80.0, 80.0],
+            end: na::vector![100.0, 0.0],
+        };
+        let key = engine.store.insert_stroke(
+            Stroke::ShapeStroke(ShapeStroke::new(
+                Shape::CubicBezier(cubbez),
+                Style::default(),
+            )),
+            None,
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+
+        key_press(&mut engine, &mut selector, KeyboardKey::CarriageReturn);
+        assert!(in_node_edit(&selector));
+
+        // drag the first control point in a few steps
+        let target = na::vector![40.0, 120.0];
+        pen_down(&mut engine, &mut selector, cubbez.cp1);
+        for i in 1..=4 {
+            pen_down(
+                &mut engine,
+                &mut selector,
+                cubbez.cp1.lerp(&target, i as f64 / 4.0),
+            );
+        }
+        let _ = selector.handle_event(
+            PenEvent::up(
+                Element::new(target, Element::PRESSURE_DEFAULT),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        assert!(in_node_edit(&selector));
+        let control_points = |engine: &Engine| {
+            let Some(Stroke::ShapeStroke(shapestroke)) = engine.store.get_stroke_ref(key) else {
+                panic!("stroke is not a shape stroke");
+            };
+            shapestroke.shape.control_points()
+        };
+        assert_relative_eq!(control_points(&engine)[1], target);
+        assert_relative_eq!(control_points(&engine)[0], cubbez.start);
+
+        // the drag is undone in a single step
+        engine.store.undo(Instant::now());
+        selector.update_state_after_history_change(&mut engine.view_mut());
+        assert_relative_eq!(control_points(&engine)[1], cubbez.cp1);
+        assert!(in_node_edit(&selector));
+
+        // escape only leaves node editing
+        key_press(&mut engine, &mut selector, KeyboardKey::Escape);
+        assert!(!in_node_edit(&selector));
+        assert_eq!(engine.store.selection_keys_unordered(), vec![key]);
+    }
+
+    #[test]
+    fn node_edit_not_offered_for_multiple_strokes() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        for x in [0.0, 200.0] {
+            engine.store.insert_stroke(
+                Stroke::ShapeStroke(ShapeStroke::new(
+                    Shape::Line(rnote_compose::shapes::Line {
+                        start: na::vector![x, 0.0],
+                        end: na::vector![x + 100.0, 50.0],
+                    }),
+                    Style::default(),
+                )),
+                None,
+            );
+        }
+        engine.store.record(Instant::now());
+        engine
+            .store
+            .set_selected_keys(&engine.store.stroke_keys_unordered(), true);
+        selector.update_state(&mut engine.view_mut());
+
+        key_press(&mut engine, &mut selector, KeyboardKey::CarriageReturn);
+        assert!(!in_node_edit(&selector));
+    }
+
+    #[test]
+    fn node_edit_inserts_and_removes_polygon_nodes() {
+        use rnote_compose::shapes::{ControlPoints, Polygon};
+
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let polygon = Polygon {
+            start: na::vector![0.0, 0.0],
+            path: vec![na::vector![200.0, 0.0], na::vector![200.0, 200.0]],
+        };
+        let key = engine.store.insert_stroke(
+            Stroke::ShapeStroke(ShapeStroke::new(Shape::Polygon(polygon), Style::default())),
+            None,
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        key_press(&mut engine, &mut selector, KeyboardKey::CarriageReturn);
+        assert!(in_node_edit(&selector));
+
+        let control_points = |engine: &Engine| {
+            let Some(Stroke::ShapeStroke(shapestroke)) = engine.store.get_stroke_ref(key) else {
+                panic!("stroke is not a shape stroke");
+            };
+            shapestroke.shape.control_points()
+        };
+        let pen_up = |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>| {
+            let _ = selector.handle_event(
+                PenEvent::up(Element::new(pos, Element::PRESSURE_DEFAULT), HashSet::new()),
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+        };
+        let ctrl_click = |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>| {
+            let _ = selector.handle_event(
+                PenEvent::down(
+                    Element::new(pos, Element::PRESSURE_DEFAULT),
+                    HashSet::from([ModifierKey::KeyboardCtrl]),
+                ),
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            pen_up(engine, selector, pos);
+        };
+
+        // dragging the midpoint handle of the first segment inserts a node there
+        let target = na::vector![100.0, -50.0];
+        pen_down(&mut engine, &mut selector, na::vector![100.0, 0.0]);
+        pen_down(&mut engine, &mut selector, target);
+        pen_up(&mut engine, &mut selector, target);
+        assert_eq!(control_points(&engine).len(), 4);
+        assert_relative_eq!(control_points(&engine)[1], target);
+
+        // the closing segment has a midpoint handle as well
+        pen_down(&mut engine, &mut selector, na::vector![100.0, 100.0]);
+        pen_up(&mut engine, &mut selector, na::vector![100.0, 100.0]);
+        assert_eq!(control_points(&engine).len(), 5);
+        assert_relative_eq!(control_points(&engine)[4], na::vector![100.0, 100.0]);
+
+        // ctrl-clicking removes nodes, but never below the minimum of a polygon
+        ctrl_click(&mut engine, &mut selector, target);
+        assert_eq!(control_points(&engine).len(), 4);
+        ctrl_click(&mut engine, &mut selector, na::vector![0.0, 0.0]);
+        assert_eq!(control_points(&engine).len(), 3);
+        ctrl_click(&mut engine, &mut selector, na::vector![200.0, 0.0]);
+        assert_eq!(control_points(&engine).len(), Polygon::MIN_POINTS);
+        assert!(in_node_edit(&selector));
+
+        // every insertion and removal is a single history step
+        engine.store.undo(Instant::now());
+        selector.update_state_after_history_change(&mut engine.view_mut());
+        assert_eq!(control_points(&engine).len(), 4);
+
+        // the edited shape survives a save and load round trip
+        let Some(stroke) = engine.store.get_stroke_ref(key) else {
+            panic!("stroke was removed");
+        };
+        let loaded: Stroke = serde_json::from_str(&serde_json::to_string(stroke).unwrap()).unwrap();
+        let Stroke::ShapeStroke(loaded) = loaded else {
+            panic!("loaded stroke is not a shape stroke");
+        };
+        assert_eq!(loaded.shape.control_points(), control_points(&engine));
+    }
+
+    #[test]
+    fn shift_constrains_translation() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let rect = Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]);
+        let key = insert_rect(&mut engine.store, rect);
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let initial_bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+
+        let drag = |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>, shift| {
+            let modifier_keys = if shift {
+                HashSet::from([ModifierKey::KeyboardShift])
+            } else {
+                HashSet::new()
+            };
+            let _ = selector.handle_event(
+                PenEvent::down(Element::new(pos, Element::PRESSURE_DEFAULT), modifier_keys),
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            let SelectorState::ModifySelection {
+                modify_state: ModifyState::Translate { constraint, .. },
+                selection_bounds,
+                ..
+            } = &selector.state
+            else {
+                panic!("selector is not translating a selection");
+            };
+            (*selection_bounds, *constraint)
+        };
+
+        pen_down(&mut engine, &mut selector, na::vector![50.0, 25.0]);
+        // mostly horizontal, constrained to the x axis
+        let (bounds, constraint) = drag(&mut engine, &mut selector, na::vector![150.0, 35.0], true);
+        assert_relative_eq!(constraint.unwrap(), na::vector![1.0, 0.0]);
+        assert_bounds_eq(bounds, initial_bounds.translate(na::vector![100.0, 0.0]));
+
+        // close to the diagonal
+        let (bounds, constraint) =
+            drag(&mut engine, &mut selector, na::vector![140.0, 105.0], true);
+        assert!(constraint.is_some());
+        assert_bounds_eq(bounds, initial_bounds.translate(na::vector![85.0, 85.0]));
+
+        // releasing shift follows the pen again, relative to the start position
+        let (bounds, constraint) =
+            drag(&mut engine, &mut selector, na::vector![150.0, 35.0], false);
+        assert!(constraint.is_none());
+        assert_bounds_eq(bounds, initial_bounds.translate(na::vector![100.0, 10.0]));
+    }
+
+    #[test]
+    fn translate_damages_previous_and_new_bounds() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let rect = Aabb::new(na::point![200.0, 200.0], na::point![300.0, 250.0]);
+        let key = insert_rect(&mut engine.store, rect);
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let initial_bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+
+        pen_down(&mut engine, &mut selector, na::vector![250.0, 225.0]);
+        let (_, widget_flags) = selector.handle_event(
+            PenEvent::down(
+                Element::new(na::vector![280.0, 240.0], Element::PRESSURE_DEFAULT),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        let new_bounds = selection_bounds(&selector);
+        assert_bounds_eq(
+            new_bounds,
+            initial_bounds.translate(na::vector![30.0, 15.0]),
+        );
+
+        assert!(widget_flags.redraw);
+        let damage = widget_flags.damage.unwrap();
+        assert!(damage.contains(&initial_bounds));
+        assert!(damage.contains(&new_bounds));
+        // the damage stays local to the selection
+        assert!(damage.extents()[0] < initial_bounds.extents()[0] * 3.0);
+    }
+
+    #[test]
+    fn select_all_scopes() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        engine.camera = Camera::default().with_size(na::vector![800.0, 600.0]);
+        engine.document.layout = Layout::FixedSize;
+        engine.document.height = engine.document.format.height() * 2.0;
+
+        let square_at = |mins: na::Point2<f64>| Aabb::new(mins, mins + na::vector![50.0, 50.0]);
+        let in_viewport = insert_rect(&mut engine.store, square_at(na::point![10.0, 10.0]));
+        let on_first_page = insert_rect(&mut engine.store, square_at(na::point![900.0, 700.0]));
+        let second_page_y = engine.document.format.height() + 100.0;
+        let on_second_page = insert_rect(
+            &mut engine.store,
+            square_at(na::point![10.0, second_page_y]),
+        );
+        engine.store.record(Instant::now());
+
+        let select_all = |engine: &mut Engine,
+                          selector: &mut Selector,
+                          keyboard_key: KeyboardKey,
+                          modifier_keys: &[ModifierKey]| {
+            let (_, widget_flags) = selector.handle_event(
+                PenEvent::KeyPressed {
+                    keyboard_key,
+                    modifier_keys: modifier_keys.iter().copied().collect(),
+                },
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            let mut selected = engine.store.selection_keys_unordered();
+            selected.sort();
+            (selected, widget_flags)
+        };
+        let sorted = |mut keys: Vec<StrokeKey>| {
+            keys.sort();
+            keys
+        };
+
+        let (selected, _) = select_all(
+            &mut engine,
+            &mut selector,
+            KeyboardKey::Unicode('a'),
+            &[ModifierKey::KeyboardCtrl],
+        );
+        assert_eq!(selected, vec![in_viewport]);
+
+        let (selected, _) = select_all(
+            &mut engine,
+            &mut selector,
+            KeyboardKey::Unicode('a'),
+            &[ModifierKey::KeyboardCtrl, ModifierKey::KeyboardAlt],
+        );
+        assert_eq!(selected, sorted(vec![in_viewport, on_first_page]));
+
+        let (selected, _) = select_all(
+            &mut engine,
+            &mut selector,
+            KeyboardKey::Unicode('A'),
+            &[ModifierKey::KeyboardCtrl, ModifierKey::KeyboardShift],
+        );
+        assert_eq!(
+            selected,
+            sorted(vec![in_viewport, on_first_page, on_second_page])
+        );
+
+        // narrowing the scope again deselects the strokes outside of it
+        let (selected, _) = select_all(
+            &mut engine,
+            &mut selector,
+            KeyboardKey::Unicode('a'),
+            &[ModifierKey::KeyboardCtrl],
+        );
+        assert_eq!(selected, vec![in_viewport]);
+        let SelectorState::ModifySelection { selection, .. } = &selector.state else {
+            panic!("selector is not modifying a selection");
+        };
+        assert_eq!(selection, &vec![in_viewport]);
+
+        // nothing in the viewport
+        let mut engine_empty = Engine::default();
+        let mut selector = Selector::default();
+        let _ = insert_rect(
+            &mut engine_empty.store,
+            square_at(na::point![2000.0, 2000.0]),
+        );
+        engine_empty.store.record(Instant::now());
+        let (selected, widget_flags) = select_all(
+            &mut engine_empty,
+            &mut selector,
+            KeyboardKey::Unicode('a'),
+            &[ModifierKey::KeyboardCtrl],
+        );
+        assert!(selected.is_empty());
+        assert!(matches!(selector.state, SelectorState::Idle));
+        assert_eq!(widget_flags, WidgetFlags::default());
+    }
+
+    #[test]
+    fn long_press_selects_stroke_under_pen() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+
+        let down =
+            |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>, now: Instant| {
+                let _ = selector.handle_event(
+                    PenEvent::down(Element::new(pos, Element::PRESSURE_DEFAULT), HashSet::new()),
+                    now,
+                    &mut engine.view_mut(),
+                );
+            };
+        let start = Instant::now();
+        let held = start + Selector::LONG_PRESS_DURATION;
+
+        // Held still over the stroke
+        down(&mut engine, &mut selector, na::vector![0.5, 25.0], start);
+        down(&mut engine, &mut selector, na::vector![2.0, 25.0], held);
+        let SelectorState::ModifySelection {
+            modify_state: ModifyState::Translate { .. },
+            selection,
+            ..
+        } = &selector.state
+        else {
+            panic!("long press did not select the stroke");
+        };
+        assert_eq!(selection, &vec![key]);
+        assert_eq!(engine.store.selected(key), Some(true));
+
+        // Moving too far away continues selecting normally
+        let mut selector = Selector::default();
+        engine.store.set_selected(key, false);
+        down(&mut engine, &mut selector, na::vector![0.5, 25.0], start);
+        down(&mut engine, &mut selector, na::vector![40.0, 25.0], start);
+        down(&mut engine, &mut selector, na::vector![0.5, 25.0], held);
+        assert!(matches!(
+            selector.state,
+            SelectorState::Selecting {
+                long_press: None,
+                ..
+            }
+        ));
+
+        // Nothing under the pen continues selecting normally
+        let mut selector = Selector::default();
+        down(&mut engine, &mut selector, na::vector![300.0, 300.0], start);
+        down(&mut engine, &mut selector, na::vector![300.0, 300.0], held);
+        assert!(matches!(selector.state, SelectorState::Selecting { .. }));
+        assert_eq!(engine.store.selected(key), Some(false));
+    }
+
+    #[test]
+    fn polygon_auto_closes_at_start() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+
+        // Staying near the start doesn't close the polygon
+        pen_down(&mut engine, &mut selector, na::vector![-20.0, -20.0]);
+        pen_down(&mut engine, &mut selector, na::vector![-15.0, -20.0]);
+        assert!(matches!(
+            selector.state,
+            SelectorState::Selecting {
+                left_start: false,
+                ..
+            }
+        ));
+
+        // Returning to the start after going around the stroke selects it while the pen is still down
+        for pos in [
+            na::vector![120.0, -20.0],
+            na::vector![120.0, 70.0],
+            na::vector![-20.0, 70.0],
+            na::vector![-19.0, -19.0],
+        ] {
+            pen_down(&mut engine, &mut selector, pos);
+        }
+        let SelectorState::ModifySelection {
+            modify_state: ModifyState::AwaitPenUp,
+            selection,
+            ..
+        } = &selector.state
+        else {
+            panic!("polygon was not auto-closed");
+        };
+        assert_eq!(selection, &vec![key]);
+        assert_eq!(engine.store.selected(key), Some(true));
+    }
+
+    fn selection_bounds(selector: &Selector) -> Aabb {
+        let SelectorState::ModifySelection {
+            selection_bounds, ..
+        } = &selector.state
+        else {
+            panic!("selector is not modifying a selection");
+        };
+        *selection_bounds
+    }
+
+    #[test]
+    fn ctrl_shift_drag_draws_zoom_region() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let ctrl_shift = [ModifierKey::KeyboardCtrl, ModifierKey::KeyboardShift];
+
+        pen_event_w_modifiers(
+            &mut engine,
+            &mut selector,
+            na::vector![0.0, 0.0],
+            &ctrl_shift,
+            false,
+        );
+        pen_event_w_modifiers(
+            &mut engine,
+            &mut selector,
+            na::vector![200.0, 100.0],
+            &ctrl_shift,
+            false,
+        );
+        let SelectorState::ZoomRegion { start, current } = selector.state else {
+            panic!("selector is not drawing a zoom region");
+        };
+        assert_eq!(start, na::vector![0.0, 0.0]);
+        assert_eq!(current, na::vector![200.0, 100.0]);
+
+        pen_event_w_modifiers(
+            &mut engine,
+            &mut selector,
+            na::vector![200.0, 100.0],
+            &ctrl_shift,
+            true,
+        );
+        assert!(matches!(selector.state, SelectorState::Idle));
+
+        // Ctrl alone still selects rows
+        pen_event_w_modifiers(
+            &mut engine,
+            &mut selector,
+            na::vector![0.0, 0.0],
+            &[ModifierKey::KeyboardCtrl],
+            false,
+        );
+        assert!(!matches!(selector.state, SelectorState::ZoomRegion { .. }));
+    }
+
+    #[test]
+    fn ctrl_drag_duplicates_selection() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]));
+        let key = engine.store.selection_keys_as_rendered()[0];
+        let mut selector = Selector::default();
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+        let ctrl_event =
+            |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>, up: bool| {
+                pen_event_w_modifiers(engine, selector, pos, &[ModifierKey::KeyboardCtrl], up)
+            };
+
+        // a click without dragging doesn't leave a copy behind
+        ctrl_event(&mut engine, &mut selector, na::vector![50.0, 25.0], false);
+        ctrl_event(&mut engine, &mut selector, na::vector![50.0, 25.0], true);
+        assert_eq!(engine.store.stroke_keys_as_rendered(), vec![key]);
+        assert_eq!(engine.store.selection_keys_as_rendered(), vec![key]);
+
+        // dragging moves the duplicate, the original stays in place
+        ctrl_event(&mut engine, &mut selector, na::vector![50.0, 25.0], false);
+        ctrl_event(&mut engine, &mut selector, na::vector![150.0, 75.0], false);
+        ctrl_event(&mut engine, &mut selector, na::vector![150.0, 75.0], true);
+        let keys = engine.store.stroke_keys_as_rendered();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(engine.store.selected(key), Some(false));
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            start_bounds,
+        );
+        assert_bounds_eq(
+            selection_bounds(&selector),
+            start_bounds.translate(na::vector![100.0, 50.0]),
+        );
+
+        // the duplication and the move are a single history step
+        engine.store.undo(Instant::now());
+        assert_eq!(engine.store.stroke_keys_as_rendered(), vec![key]);
+    }
+
+    #[test]
+    fn shift_drag_on_resize_node_shears() {
+        let mut engine =
+            engine_w_selected_rect(Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]));
+        let key = engine.store.selection_keys_as_rendered()[0];
+        let mut selector = Selector::default();
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+        let shift_event =
+            |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>, up: bool| {
+                pen_event_w_modifiers(engine, selector, pos, &[ModifierKey::KeyboardShift], up)
+            };
+
+        // drag the bottom right resize node horizontally by the height of the selection, shearing by 45°
+        let total_zoom = engine.camera.total_zoom();
+        let start_pos = start_bounds.maxs.coords + Selector::RESIZE_NODE_SIZE * 0.5 / total_zoom;
+        shift_event(&mut engine, &mut selector, start_pos, false);
+        assert!(matches!(
+            selector.state,
+            SelectorState::ModifySelection {
+                modify_state: ModifyState::Shear { .. },
+                ..
+            }
+        ));
+        for i in 1..=4 {
+            let offset = start_bounds.extents()[1] * i as f64 / 4.0;
+            shift_event(
+                &mut engine,
+                &mut selector,
+                start_pos + na::vector![offset, 0.0],
+                false,
+            );
+        }
+        shift_event(
+            &mut engine,
+            &mut selector,
+            start_pos + na::vector![start_bounds.extents()[1], 0.0],
+            true,
+        );
+
+        let Some(Stroke::ShapeStroke(shapestroke)) = engine.store.get_stroke_ref(key) else {
+            panic!("stroke is not a shape stroke");
+        };
+        // the top edge of the selection is the pivot, so the bottom edge moves by its distance to it
+        let pivot_offset = -start_bounds.mins[1];
+        let shape_bounds = shapestroke.shape.bounds();
+        assert_relative_eq!(shape_bounds.mins[0], pivot_offset, epsilon = 1e-6);
+        assert_relative_eq!(shape_bounds.maxs[0], 150.0 + pivot_offset, epsilon = 1e-6);
+        assert_relative_eq!(shape_bounds.extents()[1], 50.0, epsilon = 1e-6);
+        assert!(shapestroke.shape.hitboxes().iter().all(|hitbox| {
+            hitbox.mins[0] >= shape_bounds.mins[0] - 1e-6
+                && hitbox.maxs[0] <= shape_bounds.maxs[0] + 1e-6
+        }));
+        let bounds = selection_bounds(&selector);
+        assert_relative_eq!(
+            bounds.extents()[0],
+            start_bounds.extents()[0] + 50.0,
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            bounds.extents()[1],
+            start_bounds.extents()[1],
+            epsilon = 1e-6
+        );
+        assert!(!engine.store.can_redo() && engine.store.can_undo());
+
+        engine.store.undo(Instant::now());
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            start_bounds,
+        );
+    }
+
+    #[test]
+    fn repeat_last_transform() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+        assert!(engine.store.last_selection_transform().is_none());
+
+        // translate the selection with the pen
+        for pos in [na::vector![50.0, 25.0], na::vector![150.0, 75.0]] {
+            pen_down(&mut engine, &mut selector, pos);
+        }
+        let _ = selector.handle_event(
+            PenEvent::up(
+                Element::new(na::vector![150.0, 75.0], Element::PRESSURE_DEFAULT),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        assert_bounds_eq(
+            selection_bounds(&selector),
+            start_bounds.translate(na::vector![100.0, 50.0]),
+        );
+
+        for i in 2..=4 {
+            let _ = selector.handle_event(
+                PenEvent::KeyPressed {
+                    keyboard_key: KeyboardKey::Unicode('T'),
+                    modifier_keys: HashSet::from([
+                        ModifierKey::KeyboardCtrl,
+                        ModifierKey::KeyboardShift,
+                    ]),
+                },
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            let expected = start_bounds.translate(na::vector![100.0, 50.0] * i as f64);
+            assert_bounds_eq(selection_bounds(&selector), expected);
+            assert_bounds_eq(engine.store.bounds_for_strokes(&[key]).unwrap(), expected);
+        }
+
+        // a composite rotation and translation is repeated as a whole, relative to the selection center
+        let bounds = selection_bounds(&selector);
+        let mut gesture_transform = None;
+        GestureTransform::accumulate(&mut gesture_transform, bounds, |transform| {
+            transform.append_rotation_wrt_point_mut(std::f64::consts::FRAC_PI_2, bounds.center())
+        });
+        GestureTransform::accumulate(&mut gesture_transform, bounds, |transform| {
+            transform.append_translation_mut(na::vector![200.0, 0.0])
+        });
+        engine
+            .store
+            .set_last_selection_transform(gesture_transform.unwrap().relative());
+        for i in 1..=3 {
+            let _ = selector.repeat_last_transform(&mut engine.view_mut());
+            let center = bounds.center() + na::vector![200.0, 0.0] * i as f64;
+            let half_extents = if i % 2 == 0 {
+                bounds.half_extents()
+            } else {
+                na::vector![bounds.half_extents()[1], bounds.half_extents()[0]]
+            };
+            let new_bounds = selection_bounds(&selector);
+            assert_relative_eq!(new_bounds.center(), center, epsilon = 1e-6);
+            assert_relative_eq!(new_bounds.half_extents(), half_extents, epsilon = 1e-6);
+        }
+        assert_eq!(engine.store.selection_keys_as_rendered(), vec![key]);
+
+        // the transform is cleared together with the document
+        let _ = engine.clear();
+        assert!(engine.store.last_selection_transform().is_none());
+    }
+
+    #[test]
+    fn cursor_follows_hover_target() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        assert_eq!(
+            selector.current_cursor(&engine.view()),
+            CursorStyle::Crosshair
+        );
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let bounds = selection_bounds(&selector);
+        let hover = |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>| {
+            let _ = selector.handle_event(
+                PenEvent::proximity(Element::new(pos, Element::PRESSURE_DEFAULT), HashSet::new()),
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+            selector.current_cursor(&engine.view())
+        };
+
+        assert_eq!(
+            hover(&mut engine, &mut selector, na::vector![50.0, 25.0]),
+            CursorStyle::Grab
+        );
+        let top_left = Selector::resize_node_bounds(ResizeCorner::TopLeft, bounds, &engine.camera)
+            .center()
+            .coords;
+        assert_eq!(
+            hover(&mut engine, &mut selector, top_left),
+            CursorStyle::ResizeNwSe
+        );
+        let bottom_left =
+            Selector::resize_node_bounds(ResizeCorner::BottomLeft, bounds, &engine.camera)
+                .center()
+                .coords;
+        assert_eq!(
+            hover(&mut engine, &mut selector, bottom_left),
+            CursorStyle::ResizeNeSw
+        );
+        let rotate_node = Selector::rotate_node_sphere(bounds, &engine.camera)
+            .center()
+            .coords;
+        assert_eq!(
+            hover(&mut engine, &mut selector, rotate_node),
+            CursorStyle::Rotate
+        );
+
+        pen_down(&mut engine, &mut selector, na::vector![50.0, 25.0]);
+        assert_eq!(
+            selector.current_cursor(&engine.view()),
+            CursorStyle::Grabbing
+        );
+    }
+
+    #[test]
+    fn select_path_is_decimated() {
+        let mut engine = Engine::default();
+        let center = na::vector![500.0, 500.0];
+        for (x, y) in [
+            (490.0, 490.0),
+            (350.0, 500.0),
+            (600.0, 420.0),
+            (900.0, 500.0),
+            (100.0, 100.0),
+        ] {
+            insert_rect(
+                &mut engine.store,
+                Aabb::new(na::point![x, y], na::point![x + 20.0, y + 20.0]),
+            );
+        }
+
+        // a wobbly circle of radius 300, recorded with 10k events
+        let raw = (0..10_000)
+            .map(|i| {
+                let angle = i as f64 / 10_000.0 * std::f64::consts::TAU;
+                let radius = 300.0 + 5.0 * (angle * 200.0).sin();
+                Element::new(center + na::vector![angle.cos(), angle.sin()] * radius, 0.5)
+            })
+            .collect::<Vec<Element>>();
+        let mut path = vec![];
+        for element in raw.iter() {
+            Selector::add_to_select_path(SelectorStyle::Polygon, &mut path, *element, 1.0);
+            assert_eq!(path.last().unwrap().pos, element.pos);
+            assert!(path.len() <= Selector::SELECT_PATH_MAX_VERTICES);
+        }
+        assert_eq!(path.first().unwrap().pos, raw[0].pos);
+        assert!(path.len() > 3);
+
+        let mut decimated_keys = engine
+            .store
+            .strokes_hitboxes_contained_in_path_polygon(&path, false);
+        let mut raw_keys = engine
+            .store
+            .strokes_hitboxes_contained_in_path_polygon(&raw, false);
+        decimated_keys.sort_unstable();
+        raw_keys.sort_unstable();
+        assert_eq!(raw_keys.len(), 3);
+        assert_eq!(decimated_keys, raw_keys);
+    }
+
+    #[test]
+    fn rotate_selection_with_keyboard() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+
+        let rotate = |engine: &mut Engine, selector: &mut Selector, ctrl: bool, shift: bool| {
+            let mut modifier_keys = HashSet::new();
+            if ctrl {
+                modifier_keys.insert(ModifierKey::KeyboardCtrl);
+            }
+            if shift {
+                modifier_keys.insert(ModifierKey::KeyboardShift);
+            }
+            let _ = selector.handle_event(
+                PenEvent::KeyPressed {
+                    keyboard_key: KeyboardKey::Unicode(if shift { 'R' } else { 'r' }),
+                    modifier_keys,
+                },
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+        };
+
+        // A quarter turn swaps the extents around the center
+        rotate(&mut engine, &mut selector, true, false);
+        let quarter_bounds = selection_bounds(&selector);
+        assert_relative_eq!(
+            quarter_bounds.extents(),
+            na::vector![start_bounds.extents()[1], start_bounds.extents()[0]],
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            quarter_bounds.center(),
+            start_bounds.center(),
+            epsilon = 1e-9
+        );
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            quarter_bounds,
+        );
+
+        // Four quarter turns return to the start without drift
+        for _ in 0..3 {
+            rotate(&mut engine, &mut selector, true, false);
+        }
+        assert_bounds_eq(selection_bounds(&selector), start_bounds);
+
+        // Small steps in both directions and mixed with quarter turns
+        for _ in 0..5 {
+            rotate(&mut engine, &mut selector, false, false);
+        }
+        assert!(selection_bounds(&selector).extents()[1] > start_bounds.extents()[1]);
+        rotate(&mut engine, &mut selector, true, true);
+        for _ in 0..7 {
+            rotate(&mut engine, &mut selector, false, true);
+        }
+        rotate(&mut engine, &mut selector, true, false);
+        rotate(&mut engine, &mut selector, false, false);
+        rotate(&mut engine, &mut selector, false, false);
+        assert_bounds_eq(selection_bounds(&selector), start_bounds);
+
+        // Every press is a single history step
+        let _ = engine.undo(Instant::now());
+        selector.update_state_after_history_change(&mut engine.view_mut());
+        let undone_bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+        assert!(undone_bounds.extents()[1] > start_bounds.extents()[1]);
+        // Rotating after undoing starts from the restored strokes
+        rotate(&mut engine, &mut selector, false, false);
+        let bounds = selection_bounds(&selector);
+        assert_relative_eq!(bounds.mins, start_bounds.mins, epsilon = 1e-9);
+        assert_relative_eq!(bounds.maxs, start_bounds.maxs, epsilon = 1e-9);
+    }
+
+    fn engine_pen_event(engine: &mut Engine, event: PenEvent) {
+        let _ = engine.handle_pen_event(event, None, Instant::now());
+    }
+
+    fn engine_click(engine: &mut Engine, pos: na::Vector2<f64>) {
+        let element = Element::new(pos, Element::PRESSURE_DEFAULT);
+        engine_pen_event(engine, PenEvent::down(element, HashSet::new()));
+        engine_pen_event(engine, PenEvent::up(element, HashSet::new()));
+    }
+
+    fn engine_key_press(engine: &mut Engine, keyboard_key: KeyboardKey) {
+        engine_pen_event(
+            engine,
+            PenEvent::KeyPressed {
+                keyboard_key,
+                modifier_keys: HashSet::new(),
+            },
+        );
+    }
+
+    fn text_stroke_text(engine: &Engine, key: StrokeKey) -> String {
+        let Some(Stroke::TextStroke(textstroke)) = engine.store.get_stroke_ref(key) else {
+            panic!("stroke is not a text stroke");
+        };
+        textstroke.text.clone()
+    }
+
+    #[test]
+    fn text_stroke_handed_over_to_typewriter() {
+        let mut engine = Engine::default();
+        let key = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("text"),
+                na::vector![0.0, 0.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+        engine.store.record(Instant::now());
+        let _ = engine.select_all_strokes();
+        let bounds = engine.store.bounds_for_strokes(&[key]).unwrap();
+
+        // Double clicking the selected text stroke switches to the typewriter
+        engine_click(&mut engine, bounds.center().coords);
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Selector);
+        engine_click(&mut engine, bounds.center().coords);
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Typewriter);
+        assert_eq!(engine.store.selected(key), Some(false));
+
+        // The typewriter edits the handed over stroke
+        engine_key_press(&mut engine, KeyboardKey::Unicode('s'));
+        assert_ne!(text_stroke_text(&engine, key), "text");
+        assert_eq!(text_stroke_text(&engine, key).len(), 5);
+
+        // Escape returns to the selector with the stroke selected again
+        engine_key_press(&mut engine, KeyboardKey::Escape);
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Selector);
+        assert_eq!(engine.store.selected(key), Some(true));
+        let Pen::Selector(selector) = engine.penholder.current_pen_ref() else {
+            panic!("current pen is not the selector");
+        };
+        assert!(matches!(
+            &selector.state,
+            SelectorState::ModifySelection { selection, .. } if selection == &vec![key]
+        ));
+
+        // Pressing Enter switches as well, the cursor is placed at the end of the text
+        engine_key_press(&mut engine, KeyboardKey::CarriageReturn);
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Typewriter);
+        engine_key_press(&mut engine, KeyboardKey::Unicode('!'));
+        assert!(text_stroke_text(&engine, key).ends_with('!'));
+        engine_key_press(&mut engine, KeyboardKey::Escape);
+
+        // The typed text is merged into the history entry of the inserted stroke,
+        // so undoing once only leaves the stroke behind if one of the switches was recorded
+        let _ = engine.undo(Instant::now());
+        assert!(engine.store.get_stroke_ref(key).is_none());
+    }
+
+    #[test]
+    fn only_single_text_strokes_are_handed_over() {
+        let mut engine = Engine::default();
+        let shape_key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        let text_key = engine.store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("text"),
+                na::vector![20.0, 10.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+        engine.store.record(Instant::now());
+        let _ = engine.select_all_strokes();
+        let text_bounds = engine.store.bounds_for_strokes(&[text_key]).unwrap();
+
+        engine_click(&mut engine, text_bounds.center().coords);
+        engine_click(&mut engine, text_bounds.center().coords);
+        engine_key_press(&mut engine, KeyboardKey::CarriageReturn);
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Selector);
+        assert_eq!(engine.store.selected(shape_key), Some(true));
+        assert_eq!(engine.store.selected(text_key), Some(true));
+    }
+
+    #[test]
+    fn deinit_rolls_back_gesture() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+
+        let translate_pos = start_bounds.center().coords;
+        pen_down(&mut engine, &mut selector, translate_pos);
+        pen_down(
+            &mut engine,
+            &mut selector,
+            translate_pos + na::vector![40.0, 30.0],
+        );
+        assert!(selector.transaction.is_some());
+        let widget_flags = selector.deinit(&mut engine.view_mut());
+        assert!(widget_flags.store_modified);
+        assert!(selector.transaction.is_none());
+        assert_bounds_eq(selection_bounds(&selector), start_bounds);
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            start_bounds,
+        );
+        assert_eq!(engine.store.selected(key), Some(true));
+
+        // the interrupted gesture is not recorded, the only history is the insertion
+        let _ = engine.store.undo(Instant::now());
+        assert!(!engine.store.can_undo());
+    }
+
+    #[test]
+    fn escape_rolls_back_gestures() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+        let stroke_width = |engine: &Engine| {
+            engine
+                .store
+                .get_stroke_ref(key)
+                .and_then(|stroke| stroke.stroke_width())
+                .unwrap()
+        };
+        let start_width = stroke_width(&engine);
+        let pen_up = |engine: &mut Engine, selector: &mut Selector, pos: na::Vector2<f64>| {
+            let _ = selector.handle_event(
+                PenEvent::up(Element::new(pos, Element::PRESSURE_DEFAULT), HashSet::new()),
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+        };
+
+        // resize from the bottom right node, then translate, both cancelled mid-gesture
+        let total_zoom = engine.camera.total_zoom();
+        let resize_pos = start_bounds.maxs.coords + Selector::RESIZE_NODE_SIZE * 0.5 / total_zoom;
+        pen_down(&mut engine, &mut selector, resize_pos);
+        pen_down(
+            &mut engine,
+            &mut selector,
+            resize_pos + na::vector![60.0, 20.0],
+        );
+        pen_down(
+            &mut engine,
+            &mut selector,
+            resize_pos + na::vector![-30.0, 40.0],
+        );
+        assert!(selector.transaction.is_some());
+        key_press(&mut engine, &mut selector, KeyboardKey::Escape);
+        assert!(selector.transaction.is_none());
+        assert_bounds_eq(selection_bounds(&selector), start_bounds);
+        assert_relative_eq!(stroke_width(&engine), start_width);
+        // further motion until the pen is lifted is ignored
+        pen_down(
+            &mut engine,
+            &mut selector,
+            resize_pos + na::vector![90.0, 90.0],
+        );
+        assert_bounds_eq(selection_bounds(&selector), start_bounds);
+        pen_up(
+            &mut engine,
+            &mut selector,
+            resize_pos + na::vector![90.0, 90.0],
+        );
+
+        let translate_pos = start_bounds.center().coords;
+        pen_down(&mut engine, &mut selector, translate_pos);
+        pen_down(
+            &mut engine,
+            &mut selector,
+            translate_pos + na::vector![40.0, 30.0],
+        );
+        key_press(&mut engine, &mut selector, KeyboardKey::Escape);
+        pen_up(
+            &mut engine,
+            &mut selector,
+            translate_pos + na::vector![40.0, 30.0],
+        );
+        assert_bounds_eq(selection_bounds(&selector), start_bounds);
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            start_bounds,
+        );
+        assert_eq!(engine.store.selected(key), Some(true));
+
+        // cancelled gestures are not recorded, the only history is the insertion
+        let _ = engine.store.undo(Instant::now());
+        assert!(!engine.store.can_undo());
+
+        // completed gestures are recorded with the transaction being committed
+        let _ = engine.store.redo(Instant::now());
+        selector.update_state(&mut engine.view_mut());
+        pen_down(&mut engine, &mut selector, translate_pos);
+        pen_down(
+            &mut engine,
+            &mut selector,
+            translate_pos + na::vector![40.0, 30.0],
+        );
+        pen_up(
+            &mut engine,
+            &mut selector,
+            translate_pos + na::vector![40.0, 30.0],
+        );
+        assert!(selector.transaction.is_none());
+        assert_relative_eq!(
+            selection_bounds(&selector).mins,
+            start_bounds.mins + na::vector![40.0, 30.0],
+            epsilon = 1e-9
+        );
+        let _ = engine.store.undo(Instant::now());
+        assert_bounds_eq(
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+            start_bounds,
+        );
+    }
+
+    #[test]
+    fn nodes_are_screen_constant_and_shrink_for_small_selections() {
+        let nodes_area = |selection_bounds: Aabb, camera: &Camera| {
+            Selector::resize_nodes(selection_bounds, camera)
+                .iter()
+                .map(|(_, bounds)| bounds.volume() * camera.total_zoom().powi(2))
+                .sum::<f64>()
+        };
+
+        // the size on screen is independent of the zoom
+        let selection_bounds = Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]);
+        for zoom in [1.0, 6.0] {
+            let camera = Camera::default().with_zoom(zoom);
+            let nodes = Selector::resize_nodes(selection_bounds, &camera);
+            assert_eq!(nodes.len(), 4);
+            for (_, bounds) in nodes {
+                assert_relative_eq!(bounds.extents() * zoom, Selector::RESIZE_NODE_SIZE);
+            }
+            assert_relative_eq!(
+                Selector::rotate_node_sphere(selection_bounds, &camera).radius() * zoom,
+                Selector::ROTATE_NODE_DIAMETER * 0.5
+            );
+        }
+
+        // shrunk to cover at most the maximum share of the selection area
+        let camera = Camera::default();
+        let selection_bounds = Aabb::new(na::point![0.0, 0.0], na::point![60.0, 60.0]);
+        assert_eq!(Selector::resize_nodes(selection_bounds, &camera).len(), 4);
+        assert!(
+            nodes_area(selection_bounds, &camera)
+                <= selection_bounds.volume() * Selector::RESIZE_NODES_MAX_AREA_SHARE + 1e-9
+        );
+
+        // tiny selections only get the bottom right resize node and the rotate node, which don't overlap
+        let camera = Camera::default().with_zoom(6.0);
+        let selection_bounds = Aabb::new(na::point![0.0, 0.0], na::point![2.0, 2.0]);
+        let nodes = Selector::resize_nodes(selection_bounds, &camera);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].0, ResizeCorner::BottomRight);
+        let rotate_node_sphere = Selector::rotate_node_sphere(selection_bounds, &camera);
+        assert!(
+            nodes[0]
+                .1
+                .distance_to_local_point(rotate_node_sphere.center(), true)
+                > rotate_node_sphere.radius()
+        );
+        // leaving the selection itself free for translating
+        let center = selection_bounds.center().coords;
+        assert!(Selector::resize_node_at(center, selection_bounds, &camera).is_none());
+        assert!(!rotate_node_sphere.contains_local_point(&center.into()));
+
+        // thin selections are reduced as well
+        let selection_bounds = Aabb::new(na::point![0.0, 0.0], na::point![200.0, 0.0]);
+        assert_eq!(Selector::resize_nodes(selection_bounds, &camera).len(), 1);
+    }
+
+    #[test]
+    fn translate_snaps_corner_or_center() {
+        let mut document = Document::default();
+        document.set_snap_enabled(true);
+        document.set_snap_to_background_pattern(false);
+        document.set_snap_spacing(na::vector![10.0, 10.0]);
+        let offset = na::vector![0.0, 0.0];
+
+        // both the corner and the center are 3.0 off the grid, the corner wins the tie
+        let bounds = Aabb::new(na::point![103.0, 103.0], na::point![123.0, 123.0]);
+        let (snapped, engaged) =
+            Selector::snap_translate_offset(bounds, SnapCorner::TopLeft, offset, true, &document);
+        assert_eq!(engaged, SnapCorner::TopLeft);
+        assert_relative_eq!(snapped, na::vector![-3.0, -3.0]);
+
+        // the center needs the smaller correction
+        let bounds = Aabb::new(na::point![103.0, 103.0], na::point![118.0, 118.0]);
+        let (snapped, engaged) =
+            Selector::snap_translate_offset(bounds, SnapCorner::TopLeft, offset, true, &document);
+        assert_eq!(engaged, SnapCorner::Center);
+        assert_relative_eq!(snapped, na::vector![-0.5, -0.5]);
+
+        // only the corner is snapped when center snapping is disabled
+        let (snapped, engaged) =
+            Selector::snap_translate_offset(bounds, SnapCorner::TopLeft, offset, false, &document);
+        assert_eq!(engaged, SnapCorner::TopLeft);
+        assert_relative_eq!(snapped, na::vector![-3.0, -3.0]);
+    }
+
+    /// Drag a selecting rectangle from the start to the end position over a single rectangle stroke.
+    ///
+    /// Returns whether the stroke was selected.
+    fn rect_select_by_drag(
+        mode: RectSelectionMode,
+        start: na::Vector2<f64>,
+        end: na::Vector2<f64>,
+    ) -> bool {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        engine.pens_config.selector_config.style = SelectorStyle::Rectangle;
+        engine.pens_config.selector_config.rect_selection_mode = mode;
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+
+        pen_down(&mut engine, &mut selector, start);
+        pen_down(&mut engine, &mut selector, end);
+        let _ = selector.handle_event(
+            PenEvent::up(Element::new(end, Element::PRESSURE_DEFAULT), HashSet::new()),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        engine.store.selected(key) == Some(true)
+    }
+
+    #[test]
+    fn rect_selection_modes() {
+        let covering = (na::vector![-20.0, -20.0], na::vector![120.0, 70.0]);
+        let touching = (na::vector![50.0, 20.0], na::vector![150.0, 70.0]);
+        let touching_reversed = (touching.1, touching.0);
+
+        for (start, end) in [covering, touching, touching_reversed] {
+            assert!(rect_select_by_drag(
+                RectSelectionMode::Intersecting,
+                start,
+                end
+            ));
+        }
+        assert!(rect_select_by_drag(
+            RectSelectionMode::Contained,
+            covering.0,
+            covering.1
+        ));
+        assert!(!rect_select_by_drag(
+            RectSelectionMode::Contained,
+            touching_reversed.0,
+            touching_reversed.1
+        ));
+        // touching strokes are only selected when dragging from right to left
+        assert!(!rect_select_by_drag(
+            RectSelectionMode::DirectionDependent,
+            touching.0,
+            touching.1
+        ));
+        assert!(rect_select_by_drag(
+            RectSelectionMode::DirectionDependent,
+            touching_reversed.0,
+            touching_reversed.1
+        ));
+    }
+
+    #[test]
+    fn tentative_selection_previewed_while_selecting() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        engine.pens_config.selector_config.style = SelectorStyle::Rectangle;
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        let tentative_keys = |selector: &Selector| {
+            let SelectorState::Selecting { tentative, .. } = &selector.state else {
+                panic!("selector is not selecting");
+            };
+            tentative.keys.clone()
+        };
+
+        pen_down(&mut engine, &mut selector, na::vector![-20.0, -20.0]);
+        pen_down(&mut engine, &mut selector, na::vector![50.0, 20.0]);
+        assert!(tentative_keys(&selector).is_empty());
+        pen_down(&mut engine, &mut selector, na::vector![120.0, 70.0]);
+        assert_eq!(tentative_keys(&selector), vec![key]);
+        // only previewed, the stroke is not selected yet
+        assert_eq!(engine.store.selected(key), Some(false));
+        pen_down(&mut engine, &mut selector, na::vector![50.0, 20.0]);
+        assert!(tentative_keys(&selector).is_empty());
+
+        // the query is skipped as long as the rectangle moved less than the threshold
+        let path = |last: na::Vector2<f64>| {
+            [
+                Element::new(na::vector![-20.0, -20.0], Element::PRESSURE_DEFAULT),
+                Element::new(last, Element::PRESSURE_DEFAULT),
+            ]
+        };
+        let mut tentative = TentativeSelection::default();
+        assert!(Selector::update_tentative_selection(
+            &path(na::vector![120.0, 70.0]),
+            &mut tentative,
+            &engine.view()
+        ));
+        assert_eq!(tentative.keys, vec![key]);
+        assert!(!Selector::update_tentative_selection(
+            &path(na::vector![121.0, 71.0]),
+            &mut tentative,
+            &engine.view()
+        ));
+        assert!(Selector::update_tentative_selection(
+            &path(na::vector![50.0, 20.0]),
+            &mut tentative,
+            &engine.view()
+        ));
+        assert!(tentative.keys.is_empty());
+    }
+
+    #[test]
+    fn rotation_input_rotates_to_absolute_angle() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        selector.update_state(&mut engine.view_mut());
+        let rotation_angle = |selector: &Selector| {
+            let SelectorState::ModifySelection { rotation_angle, .. } = &selector.state else {
+                panic!("selector is not modifying a selection");
+            };
+            *rotation_angle
+        };
+        let stroke_angle = |engine: &Engine| {
+            let Some(Stroke::ShapeStroke(ShapeStroke {
+                shape: Shape::Rectangle(rectangle),
+                ..
+            })) = engine.store.get_stroke_ref(key)
+            else {
+                panic!("stroke is not a rectangle");
+            };
+            let matrix = rectangle.transform.affine.matrix();
+            matrix[(1, 0)].atan2(matrix[(0, 0)])
+        };
+
+        // rotate by gesture around the center by about 33 degrees
+        let bounds = selection_bounds(&selector);
+        let center = bounds.center().coords;
+        let node_pos = Selector::rotate_node_sphere(bounds, &engine.camera)
+            .center()
+            .coords;
+        let radius = (node_pos - center).magnitude();
+        let gesture_angle = 33.0_f64.to_radians();
+        pen_down(&mut engine, &mut selector, node_pos);
+        let end_pos = center + na::vector![gesture_angle.cos(), gesture_angle.sin()] * radius;
+        pen_down(&mut engine, &mut selector, end_pos);
+        let _ = selector.handle_event(
+            PenEvent::up(
+                Element::new(end_pos, Element::PRESSURE_DEFAULT),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        assert_relative_eq!(rotation_angle(&selector), gesture_angle, epsilon = 1e-6);
+        assert_relative_eq!(stroke_angle(&engine), gesture_angle, epsilon = 1e-6);
+
+        for keyboard_key in [
+            KeyboardKey::Unicode('r'),
+            KeyboardKey::Unicode('4'),
+            KeyboardKey::Unicode('5'),
+            KeyboardKey::CarriageReturn,
+        ] {
+            key_press(&mut engine, &mut selector, keyboard_key);
+        }
+        assert!(selector.resize_input.is_none());
+        let target_angle = 45.0_f64.to_radians();
+        assert_relative_eq!(rotation_angle(&selector), target_angle, epsilon = 1e-6);
+        assert_relative_eq!(stroke_angle(&engine), target_angle, epsilon = 1e-6);
+        assert_bounds_eq(
+            selection_bounds(&selector),
+            engine.store.bounds_for_strokes(&[key]).unwrap(),
+        );
+    }
+
+    #[test]
+    fn large_selection_dragged_as_composite() {
+        let mut engine = Engine::default();
+        let mut selector = Selector::default();
+        // a grid of rectangles, large enough to be dragged as a composite
+        let keys = (0..Selector::DRAG_COMPOSITE_MIN_STROKES * 2)
+            .map(|i| {
+                let mins = na::point![(i % 8) as f64 * 20.0, (i / 8) as f64 * 20.0];
+                insert_rect(
+                    &mut engine.store,
+                    Aabb::new(mins, mins + na::vector![10.0, 10.0]),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        engine.store.record(Instant::now());
+        engine.store.set_selected_keys(&keys, true);
+        selector.update_state(&mut engine.view_mut());
+        let start_bounds = selection_bounds(&selector);
+        let strokes = engine.store.get_strokes_arc(&keys);
+
+        let start_pos = start_bounds.center().coords;
+        pen_down(&mut engine, &mut selector, start_pos);
+        for i in 1..=20 {
+            pen_down(
+                &mut engine,
+                &mut selector,
+                start_pos + na::vector![i as f64 * 5.0, 0.0],
+            );
+        }
+        // only the composite is moved during the drag, it was rendered once
+        assert!(engine
+            .store
+            .get_strokes_arc(&keys)
+            .iter()
+            .zip(strokes.iter())
+            .all(|(stroke, start_stroke)| std::sync::Arc::ptr_eq(stroke, start_stroke)));
+        assert_eq!(
+            engine
+                .store
+                .selection_composite()
+                .map(|composite| composite.renders()),
+            Some(1)
+        );
+        let end_bounds = start_bounds.translate(na::vector![100.0, 0.0]);
+        assert_bounds_eq(selection_bounds(&selector), end_bounds);
+
+        let _ = selector.handle_event(
+            PenEvent::up(
+                Element::new(
+                    start_pos + na::vector![100.0, 0.0],
+                    Element::PRESSURE_DEFAULT,
+                ),
+                HashSet::new(),
+            ),
+            Instant::now(),
+            &mut engine.view_mut(),
+        );
+        // the strokes are transformed once the drag has finished
+        assert!(engine.store.selection_composite().is_none());
+        assert_bounds_eq(engine.store.bounds_for_strokes(&keys).unwrap(), end_bounds);
+        assert_bounds_eq(selection_bounds(&selector), end_bounds);
+    }
+}
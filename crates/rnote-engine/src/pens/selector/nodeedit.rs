@@ -0,0 +1,259 @@
+// Imports
+use super::{ModifyState, Selector, SelectorState};
+use crate::engine::{EngineView, EngineViewMut};
+use crate::store::{StrokeKey, StrokeStore};
+use crate::strokes::Stroke;
+use crate::{Camera, WidgetFlags};
+use p2d::bounding_volume::{Aabb, BoundingSphere};
+use p2d::query::PointQuery;
+use rnote_compose::penevent::PenState;
+use rnote_compose::shapes::{ControlPoints, Shape};
+use rnote_compose::style::indicators;
+use std::time::Instant;
+
+/// A control point that is dragged while editing nodes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct DraggedNode {
+    /// The index of the control point.
+    pub(super) idx: usize,
+    /// The offset from the pen position to the control point when the drag started.
+    pub(super) grab_offset: na::Vector2<f64>,
+    /// Whether the control point was moved since the drag started.
+    pub(super) moved: bool,
+}
+
+impl Selector {
+    /// The diameter of the control point handles when editing nodes, in surface coordinates.
+    pub(super) const NODE_HANDLE_DIAMETER: f64 = 14.0;
+    /// The diameter of the handles at the segment midpoints which insert a node when clicked, in surface coordinates.
+    pub(super) const NODE_INSERT_HANDLE_DIAMETER: f64 = 10.0;
+
+    /// The control points of the selection, if it consists of a single shape stroke that supports node editing.
+    pub(super) fn selection_control_points(
+        selection: &[StrokeKey],
+        store: &StrokeStore,
+    ) -> Option<Vec<na::Vector2<f64>>> {
+        let [key] = selection else {
+            return None;
+        };
+        let Some(Stroke::ShapeStroke(shapestroke)) = store.get_stroke_ref(*key) else {
+            return None;
+        };
+        let control_points = shapestroke.shape.control_points();
+        (!control_points.is_empty()).then_some(control_points)
+    }
+
+    /// The segment midpoints of the selection, if it consists of a single polyline or polygon shape stroke.
+    pub(super) fn selection_segment_midpoints(
+        selection: &[StrokeKey],
+        store: &StrokeStore,
+    ) -> Vec<na::Vector2<f64>> {
+        let [key] = selection else {
+            return vec![];
+        };
+        match store.get_stroke_ref(*key) {
+            Some(Stroke::ShapeStroke(shapestroke)) => match &shapestroke.shape {
+                Shape::Polyline(polyline) => polyline.segment_midpoints(),
+                Shape::Polygon(polygon) => polygon.segment_midpoints(),
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    fn node_handle_sphere(pos: na::Vector2<f64>, camera: &Camera) -> BoundingSphere {
+        BoundingSphere::new(
+            pos.into(),
+            Self::NODE_HANDLE_DIAMETER * 0.5 / camera.total_zoom(),
+        )
+    }
+
+    fn node_insert_handle_sphere(pos: na::Vector2<f64>, camera: &Camera) -> BoundingSphere {
+        BoundingSphere::new(
+            pos.into(),
+            Self::NODE_INSERT_HANDLE_DIAMETER * 0.5 / camera.total_zoom(),
+        )
+    }
+
+    /// Enter node editing. Only possible for a selection of a single shape stroke that is currently not modified.
+    ///
+    /// Returns whether node editing was entered.
+    pub(super) fn enter_node_edit(&mut self, engine_view: &EngineViewMut) -> bool {
+        let SelectorState::ModifySelection {
+            modify_state: modify_state @ (ModifyState::Up | ModifyState::Hover(_)),
+            selection,
+            ..
+        } = &mut self.state
+        else {
+            return false;
+        };
+        if Self::selection_control_points(selection, engine_view.store).is_none() {
+            return false;
+        }
+        let hover_pos = match modify_state {
+            ModifyState::Hover(pos) => Some(*pos),
+            _ => None,
+        };
+        *modify_state = ModifyState::EditNodes {
+            dragged: None,
+            hover_pos,
+            await_pen_up: false,
+        };
+        true
+    }
+
+    /// The index of the control point of the selection whose handle contains the position.
+    pub(super) fn node_at_pos(
+        selection: &[StrokeKey],
+        pos: na::Vector2<f64>,
+        engine_view: &EngineView,
+    ) -> Option<usize> {
+        Self::selection_control_points(selection, engine_view.store)?
+            .into_iter()
+            .position(|control_point| {
+                Self::node_handle_sphere(control_point, engine_view.camera)
+                    .contains_local_point(&pos.into())
+            })
+    }
+
+    /// The index and midpoint of the segment of the selection whose insert handle contains the position.
+    pub(super) fn segment_at_pos(
+        selection: &[StrokeKey],
+        pos: na::Vector2<f64>,
+        engine_view: &EngineView,
+    ) -> Option<(usize, na::Vector2<f64>)> {
+        Self::selection_segment_midpoints(selection, engine_view.store)
+            .into_iter()
+            .enumerate()
+            .find(|(_, midpoint)| {
+                Self::node_insert_handle_sphere(*midpoint, engine_view.camera)
+                    .contains_local_point(&pos.into())
+            })
+    }
+
+    /// Insert a node into the segment of the stroke at the position and update its rendering.
+    ///
+    /// The inserted node has the index `segment_idx + 1`. Returns the new bounds of the stroke.
+    pub(super) fn insert_node(
+        key: StrokeKey,
+        segment_idx: usize,
+        pos: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+    ) -> Option<Aabb> {
+        if !engine_view.store.insert_shape_vertex(key, segment_idx, pos) {
+            return None;
+        }
+        engine_view.store.regenerate_rendering_for_stroke(
+            key,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        engine_view.store.bounds_for_strokes(&[key])
+    }
+
+    /// Remove the node of the stroke and record the change.
+    ///
+    /// Returns the new bounds of the stroke, None when the node can't be removed.
+    pub(super) fn remove_node(
+        key: StrokeKey,
+        idx: usize,
+        engine_view: &mut EngineViewMut,
+        widget_flags: &mut WidgetFlags,
+    ) -> Option<Aabb> {
+        if !engine_view.store.remove_shape_vertex(key, idx) {
+            return None;
+        }
+        *widget_flags |= Self::record_node_edit(engine_view);
+        engine_view.store.bounds_for_strokes(&[key])
+    }
+
+    /// Move the control point of the stroke to the position and update its geometry and rendering.
+    ///
+    /// Returns the new bounds of the stroke.
+    pub(super) fn move_node(
+        key: StrokeKey,
+        idx: usize,
+        pos: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+    ) -> Option<Aabb> {
+        let Some(Stroke::ShapeStroke(shapestroke)) = engine_view.store.get_stroke_mut(key) else {
+            return None;
+        };
+        shapestroke.shape.set_control_point(idx, pos);
+        engine_view.store.update_geometry_for_stroke(key);
+        engine_view.store.regenerate_rendering_for_stroke(
+            key,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        engine_view.store.bounds_for_strokes(&[key])
+    }
+
+    /// Finish dragging a control point, recording the change when the control point was moved.
+    pub(super) fn finish_node_drag(
+        dragged: DraggedNode,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        if !dragged.moved {
+            return WidgetFlags::default();
+        }
+        Self::record_node_edit(engine_view)
+    }
+
+    fn record_node_edit(engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        widget_flags |= engine_view
+            .document
+            .resize_autoexpand(engine_view.store, engine_view.camera);
+        engine_view.store.regenerate_rendering_in_viewport_threaded(
+            engine_view.tasks_tx.clone(),
+            false,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        widget_flags |= engine_view.store.record(Instant::now());
+        widget_flags.store_modified = true;
+        widget_flags
+    }
+
+    /// Draw the handles of the control points while editing nodes.
+    pub(super) fn draw_node_edit(
+        cx: &mut impl piet::RenderContext,
+        selection: &[StrokeKey],
+        dragged: Option<DraggedNode>,
+        hover_pos: Option<na::Vector2<f64>>,
+        engine_view: &EngineView,
+    ) {
+        let Some(control_points) = Self::selection_control_points(selection, engine_view.store)
+        else {
+            return;
+        };
+        let total_zoom = engine_view.camera.total_zoom();
+
+        // Insert handles are hidden while dragging, the midpoints are moving along
+        if dragged.is_none() {
+            for midpoint in Self::selection_segment_midpoints(selection, engine_view.store) {
+                let handle_sphere = Self::node_insert_handle_sphere(midpoint, engine_view.camera);
+                let node_state = match hover_pos {
+                    Some(pos) if handle_sphere.contains_local_point(&pos.into()) => {
+                        PenState::Proximity
+                    }
+                    _ => PenState::Up,
+                };
+                indicators::draw_circular_node(cx, node_state, handle_sphere, total_zoom);
+            }
+        }
+
+        for (idx, control_point) in control_points.into_iter().enumerate() {
+            let handle_sphere = Self::node_handle_sphere(control_point, engine_view.camera);
+            let node_state = match (dragged, hover_pos) {
+                (Some(dragged), _) if dragged.idx == idx => PenState::Down,
+                (None, Some(pos)) if handle_sphere.contains_local_point(&pos.into()) => {
+                    PenState::Proximity
+                }
+                _ => PenState::Up,
+            };
+            indicators::draw_circular_node(cx, node_state, handle_sphere, total_zoom);
+        }
+    }
+}
@@ -1,67 +1,219 @@
 // Imports
-use super::{ModifyState, ResizeCorner, Selector, SelectorState};
+use super::keyrotation::KeyRotation;
+use super::nodeedit::DraggedNode;
+use super::repeattransform::GestureTransform;
+use super::tentative::TentativeSelection;
+use super::textedit::Click;
+use super::{LongPress, ModifyState, ResizeCorner, SelectAllScope, Selector, SelectorState};
 use crate::engine::EngineViewMut;
 use crate::pens::pensconfig::selectorconfig::SelectorStyle;
+use crate::pens::pensconfig::selectorshortcuts::SelectorAction;
 use crate::snap::SnapCorner;
 use crate::store::StrokeKey;
-use crate::{DrawableOnDoc, WidgetFlags};
+use crate::{DrawableOnDoc, Engine, WidgetFlags};
 use p2d::bounding_volume::Aabb;
 use p2d::query::PointQuery;
 use rnote_compose::eventresult::{EventPropagation, EventResult};
 use rnote_compose::ext::{AabbExt, Vector2Ext};
-use rnote_compose::penevent::{KeyboardKey, ModifierKey, PenProgress};
+use rnote_compose::penevent::{KeyboardKey, ModifierKey, PenButton, PenProgress};
 use rnote_compose::penpath::Element;
 use std::collections::HashSet;
 use std::time::Instant;
+use tracing::error;
 
 impl Selector {
     pub(super) fn handle_pen_event_down(
         &mut self,
         element: Element,
         modifier_keys: HashSet<ModifierKey>,
-        _now: Instant,
+        pen_button: PenButton,
+        now: Instant,
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
+        if !self.validate_selection(engine_view) {
+            return Self::selection_vanished_result();
+        }
         let mut widget_flags = WidgetFlags::default();
+        let prev_bounds = self.bounds_on_doc(&engine_view.as_im());
+        let selecting_style = self
+            .style_override
+            .unwrap_or(engine_view.pens_config.selector_config.style);
+        // A pending keyboard resize input is discarded once the selection is modified with the pen
+        if self.resize_input.take().is_some() {
+            widget_flags.redraw = true;
+        }
 
         let event_result = match &mut self.state {
+            SelectorState::Idle
+                if modifier_keys.contains(&ModifierKey::KeyboardCtrl)
+                    && modifier_keys.contains(&ModifierKey::KeyboardShift) =>
+            {
+                // Holding Ctrl and Shift draws a region to zoom to instead of selecting
+                self.state = SelectorState::ZoomRegion {
+                    start: element.pos,
+                    current: element.pos,
+                };
+
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::InProgress,
+                }
+            }
+            SelectorState::Idle if modifier_keys.contains(&ModifierKey::KeyboardCtrl) => {
+                // Holding Ctrl selects the row of strokes under the pen
+                let progress = if self.select_row(element.pos, engine_view, &mut widget_flags) {
+                    PenProgress::InProgress
+                } else {
+                    PenProgress::Idle
+                };
+
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress,
+                }
+            }
             SelectorState::Idle => {
                 // Deselect on start
                 let selection_keys = engine_view.store.selection_keys_as_rendered();
                 if !selection_keys.is_empty() {
-                    engine_view.store.set_selected_keys(&selection_keys, false);
+                    engine_view
+                        .store
+                        .set_selected_keys_keep_order(&selection_keys, false);
                     widget_flags.store_modified = true;
+                    widget_flags.selection_changed = true;
                 }
 
+                self.last_click = Some(Click::new(element.pos, now));
+                // The barrel button selects a single stroke for this gesture, regardless of the configured style
+                self.style_override = pen_button
+                    .is_barrel_button()
+                    .then_some(SelectorStyle::Single);
+
                 self.state = SelectorState::Selecting {
                     path: vec![element],
+                    tentative: TentativeSelection::default(),
+                    long_press: Some(LongPress {
+                        anchor: element.pos,
+                        start: now,
+                    }),
+                    left_start: false,
+                };
+
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::InProgress,
+                }
+            }
+            SelectorState::Selecting {
+                path,
+                tentative,
+                long_press,
+                left_start,
+            } => {
+                // Holding the pen still selects the stroke under it, ready to be translated
+                if let Some((key, new_bounds)) =
+                    Self::long_press_stroke(long_press, element.pos, now, engine_view)
+                        .and_then(|key| Some((key, engine_view.store.bounds_for_strokes(&[key])?)))
+                {
+                    engine_view.store.set_selected(key, true);
+                    widget_flags.store_modified = true;
+                    widget_flags.deselect_color_setters = true;
+                    widget_flags.selection_changed = true;
+
+                    let snap_corner = SnapCorner::determine_from_bounds(new_bounds, element.pos);
+                    self.state = SelectorState::ModifySelection {
+                        modify_state: ModifyState::Translate {
+                            start_pos: element.pos,
+                            current_pos: element.pos,
+                            snap_corner,
+                            engaged_snap: snap_corner,
+                            constraint: None,
+                        },
+                        selection: vec![key],
+                        selection_bounds: new_bounds,
+                        rotation_angle: 0.0,
+                    };
+                    self.begin_gesture_transaction(engine_view);
+
+                    return (
+                        EventResult {
+                            handled: true,
+                            propagate: EventPropagation::Stop,
+                            progress: PenProgress::InProgress,
+                        },
+                        widget_flags,
+                    );
+                }
+
+                let total_zoom = engine_view.camera.total_zoom();
+                *left_start =
+                    *left_start || Self::polygon_pos_left_start(path, element.pos, total_zoom);
+                Self::add_to_select_path(selecting_style, path, element, total_zoom);
+                if selecting_style == SelectorStyle::Rectangle
+                    && Self::update_tentative_selection(path, tentative, &engine_view.as_im())
+                {
+                    widget_flags.redraw = true;
+                }
+
+                // auto-close the polygon when the pen returns to the start
+                let auto_close_selection = if selecting_style == SelectorStyle::Polygon
+                    && Self::polygon_path_should_auto_close(path, *left_start, total_zoom)
+                {
+                    // Only queried once when closing, the strokes are looked up in the key tree with the path bounds
+                    engine_view
+                        .store
+                        .strokes_hitboxes_contained_in_path_polygon(path, false)
+                } else {
+                    vec![]
                 };
 
+                if let Some(new_bounds) =
+                    engine_view.store.bounds_for_strokes(&auto_close_selection)
+                {
+                    engine_view
+                        .store
+                        .set_selected_keys(&auto_close_selection, true);
+                    widget_flags.store_modified = true;
+                    widget_flags.deselect_color_setters = true;
+                    widget_flags.selection_changed = true;
+
+                    // Change to the modify state, but ignore the following down events until the pen is lifted
+                    self.state = SelectorState::ModifySelection {
+                        modify_state: ModifyState::AwaitPenUp,
+                        selection: auto_close_selection,
+                        selection_bounds: new_bounds,
+                        rotation_angle: 0.0,
+                    };
+                } else {
+                    // possibly nudge camera
+                    widget_flags |= engine_view.camera.nudge_w_pos(
+                        element.pos,
+                        engine_view.document,
+                        Some(Self::AUTO_NUDGE_STRENGTH),
+                    );
+                    widget_flags |= engine_view
+                        .document
+                        .expand_autoexpand(engine_view.camera, engine_view.store);
+                    engine_view.store.regenerate_rendering_in_viewport_threaded(
+                        engine_view.tasks_tx.clone(),
+                        false,
+                        engine_view.camera.viewport(),
+                        engine_view.camera.image_scale(),
+                    );
+                }
+
                 EventResult {
                     handled: true,
                     propagate: EventPropagation::Stop,
                     progress: PenProgress::InProgress,
                 }
             }
-            SelectorState::Selecting { path } => {
-                Self::add_to_select_path(
-                    engine_view.pens_config.selector_config.style,
-                    path,
-                    element,
-                );
-                // possibly nudge camera
-                widget_flags |= engine_view
-                    .camera
-                    .nudge_w_pos(element.pos, engine_view.document);
-                widget_flags |= engine_view
-                    .document
-                    .expand_autoexpand(engine_view.camera, engine_view.store);
-                engine_view.store.regenerate_rendering_in_viewport_threaded(
-                    engine_view.tasks_tx.clone(),
-                    false,
-                    engine_view.camera.viewport(),
-                    engine_view.camera.image_scale(),
-                );
+            SelectorState::ZoomRegion { current, .. } => {
+                *current = element.pos;
+                widget_flags.redraw = true;
 
                 EventResult {
                     handled: true,
@@ -73,6 +225,7 @@ impl Selector {
                 modify_state,
                 selection,
                 selection_bounds,
+                rotation_angle: selection_rotation_angle,
             } => {
                 let mut progress = PenProgress::InProgress;
 
@@ -82,19 +235,36 @@ impl Selector {
                         // while pressing Shift, we add it to the selection
                         let key_to_add = engine_view
                             .store
-                            .stroke_hitboxes_contain_coord(
-                                engine_view.camera.viewport(),
-                                element.pos,
-                            )
-                            .pop();
-
-                        if (engine_view.pens_config.selector_config.style == SelectorStyle::Single
-                            || modifier_keys.contains(&ModifierKey::KeyboardShift))
-                            && key_to_add
-                                .and_then(|key| engine_view.store.selected(key).map(|s| !s))
-                                .unwrap_or(false)
-                        {
-                            let key_to_add = key_to_add.unwrap();
+                            .stroke_hitboxes_contain_coord(element.pos, false)
+                            .pop()
+                            .filter(|&key| engine_view.store.selected(key) == Some(false))
+                            .filter(|_| {
+                                engine_view.pens_config.selector_config.style
+                                    == SelectorStyle::Single
+                                    || modifier_keys.contains(&ModifierKey::KeyboardShift)
+                            });
+
+                        // Double clicking a single selected text stroke edits it with the typewriter
+                        let double_click = self
+                            .last_click
+                            .replace(Click::new(element.pos, now))
+                            .is_some_and(|click| {
+                                click.is_double_click(
+                                    element.pos,
+                                    now,
+                                    engine_view.camera.total_zoom(),
+                                )
+                            });
+                        let edit_text = double_click
+                            && selection_bounds.contains_local_point(&element.pos.into())
+                            && Self::selected_text_stroke(selection, engine_view.store).is_some();
+
+                        if edit_text {
+                            self.request_text_edit(Some(element.pos), engine_view);
+                            widget_flags.selection_changed = true;
+
+                            progress = PenProgress::Finished;
+                        } else if let Some(key_to_add) = key_to_add {
                             engine_view.store.set_selected(key_to_add, true);
                             selection.push(key_to_add);
                             if let Some(new_bounds) =
@@ -102,6 +272,7 @@ impl Selector {
                             {
                                 *selection_bounds = new_bounds;
                             }
+                            widget_flags.selection_changed = true;
                         } else if Self::rotate_node_sphere(*selection_bounds, engine_view.camera)
                             .contains_local_point(&element.pos.into())
                         {
@@ -117,59 +288,64 @@ impl Selector {
                                 current_rotation_angle: rotation_angle,
                             };
                             // clicking on one of the resize nodes at the corners
-                        } else if Self::resize_node_bounds(
-                            ResizeCorner::TopLeft,
-                            *selection_bounds,
-                            engine_view.camera,
-                        )
-                        .contains_local_point(&element.pos.into())
-                        {
-                            *modify_state = ModifyState::Resize {
-                                from_corner: ResizeCorner::TopLeft,
-                                start_bounds: *selection_bounds,
-                                start_pos: element.pos,
-                                last_rendered_bounds: *selection_bounds,
-                            }
-                        } else if Self::resize_node_bounds(
-                            ResizeCorner::TopRight,
-                            *selection_bounds,
-                            engine_view.camera,
-                        )
-                        .contains_local_point(&element.pos.into())
-                        {
-                            *modify_state = ModifyState::Resize {
-                                from_corner: ResizeCorner::TopRight,
-                                start_bounds: *selection_bounds,
-                                start_pos: element.pos,
-                                last_rendered_bounds: *selection_bounds,
-                            }
-                        } else if Self::resize_node_bounds(
-                            ResizeCorner::BottomLeft,
-                            *selection_bounds,
-                            engine_view.camera,
-                        )
-                        .contains_local_point(&element.pos.into())
+                        } else if let Some(from_corner) =
+                            Self::resize_node_at(element.pos, *selection_bounds, engine_view.camera)
+                                .filter(|_| modifier_keys.contains(&ModifierKey::KeyboardShift))
                         {
-                            *modify_state = ModifyState::Resize {
-                                from_corner: ResizeCorner::BottomLeft,
+                            // dragging a resize node while holding Shift shears the selection
+                            *modify_state = ModifyState::Shear {
+                                from_corner,
                                 start_bounds: *selection_bounds,
                                 start_pos: element.pos,
-                                last_rendered_bounds: *selection_bounds,
-                            }
-                        } else if Self::resize_node_bounds(
-                            ResizeCorner::BottomRight,
-                            *selection_bounds,
-                            engine_view.camera,
-                        )
-                        .contains_local_point(&element.pos.into())
+                                current_shear: 0.0,
+                            };
+                        } else if let Some(from_corner) =
+                            Self::resize_node_at(element.pos, *selection_bounds, engine_view.camera)
                         {
+                            engine_view.store.begin_width_scaling(
+                                selection,
+                                engine_view.pens_config.selector_config.width_scale_policy,
+                            );
                             *modify_state = ModifyState::Resize {
-                                from_corner: ResizeCorner::BottomRight,
+                                from_corner,
                                 start_bounds: *selection_bounds,
                                 start_pos: element.pos,
                                 last_rendered_bounds: *selection_bounds,
+                                min_extents: Self::resize_min_extents(
+                                    engine_view.store,
+                                    selection,
+                                    *selection_bounds,
+                                ),
+                                mirrored: [false; 2],
+                                lock_aspectratio: Self::resize_lock_aspectratio(
+                                    selection,
+                                    &modifier_keys,
+                                    engine_view,
+                                ),
                             }
                         } else if selection_bounds.contains_local_point(&element.pos.into()) {
+                            if pen_button.is_barrel_button()
+                                || modifier_keys.contains(&ModifierKey::KeyboardCtrl)
+                            {
+                                // dragging with the barrel button or while holding Ctrl duplicates the selection
+                                // and drags the duplicates, leaving the originals in place
+                                let originals = std::mem::replace(
+                                    selection,
+                                    engine_view
+                                        .store
+                                        .duplicate_selection_w_offset(na::Vector2::zeros()),
+                                );
+                                engine_view.store.update_geometry_for_strokes(selection);
+                                if let Some(new_bounds) =
+                                    engine_view.store.bounds_for_strokes(selection)
+                                {
+                                    *selection_bounds = new_bounds;
+                                }
+                                self.copy_drag = Some(originals);
+                                widget_flags.store_modified = true;
+                                widget_flags.selection_changed = true;
+                            }
+
                             let snap_corner =
                                 SnapCorner::determine_from_bounds(*selection_bounds, element.pos);
 
@@ -178,61 +354,92 @@ impl Selector {
                                 start_pos: element.pos,
                                 current_pos: element.pos,
                                 snap_corner,
+                                engaged_snap: snap_corner,
+                                constraint: None,
                             };
                         } else {
                             // when clicking outside the selection bounds, reset
-                            engine_view.store.set_selected_keys(selection, false);
+                            engine_view
+                                .store
+                                .set_selected_keys_keep_order(selection, false);
                             self.state = SelectorState::Idle;
+                            widget_flags.selection_changed = true;
 
                             progress = PenProgress::Finished;
                         }
                     }
                     ModifyState::Translate {
-                        start_pos: _,
+                        start_pos,
                         current_pos,
                         snap_corner,
+                        engaged_snap,
+                        constraint,
                     } => {
-                        let snap_corner_pos = match snap_corner {
-                            SnapCorner::TopLeft => selection_bounds.mins.coords,
-                            SnapCorner::TopRight => {
-                                na::vector![selection_bounds.maxs[0], selection_bounds.mins[1]]
-                            }
-                            SnapCorner::BottomLeft => {
-                                na::vector![selection_bounds.mins[0], selection_bounds.maxs[1]]
-                            }
-                            SnapCorner::BottomRight => selection_bounds.maxs.coords,
+                        // The constrained position is always derived from the start position,
+                        // so releasing Shift returns to free movement right away
+                        let target_pos = if modifier_keys.contains(&ModifierKey::KeyboardShift) {
+                            let (constrained_offset, direction) =
+                                Self::constrain_translate_offset(element.pos - *start_pos);
+                            *constraint = direction;
+                            *start_pos + constrained_offset
+                        } else {
+                            *constraint = None;
+                            element.pos
                         };
+                        let target_offset = target_pos - *current_pos;
 
-                        let offset = engine_view
-                            .document
-                            .snap_position(snap_corner_pos + (element.pos - *current_pos))
-                            - snap_corner_pos;
+                        let (snapped_offset, new_engaged_snap) = Self::snap_translate_offset(
+                            *selection_bounds,
+                            *snap_corner,
+                            target_offset,
+                            engine_view.pens_config.selector_config.snap_center_enabled,
+                            engine_view.document,
+                        );
+                        *engaged_snap = new_engaged_snap;
+                        // only snap along the constrained direction, so the selection stays on it
+                        let offset = match constraint {
+                            Some(direction) => {
+                                *direction * direction.dot(&snapped_offset)
+                                    + (target_offset - *direction * direction.dot(&target_offset))
+                            }
+                            None => snapped_offset,
+                        };
 
                         if offset.magnitude()
                             > Self::TRANSLATE_OFFSET_THRESHOLD / engine_view.camera.total_zoom()
                         {
                             // move selection
-                            engine_view.store.translate_strokes(selection, offset);
-                            engine_view
-                                .store
-                                .translate_strokes_images(selection, offset);
+                            GestureTransform::accumulate(
+                                &mut self.gesture_transform,
+                                *selection_bounds,
+                                |transform| transform.append_translation_mut(offset),
+                            );
+                            if !engine_view.store.translate_selection_composite(offset) {
+                                engine_view.store.translate_strokes(selection, offset);
+                                engine_view
+                                    .store
+                                    .translate_strokes_images(selection, offset);
+                            }
                             *selection_bounds = selection_bounds.translate(offset);
                             *current_pos += offset;
                         }
 
                         // possibly nudge camera
-                        widget_flags |= engine_view
-                            .camera
-                            .nudge_w_pos(element.pos, engine_view.document);
+                        widget_flags |= engine_view.camera.nudge_w_pos(
+                            element.pos,
+                            engine_view.document,
+                            Some(Self::AUTO_NUDGE_STRENGTH),
+                        );
                         widget_flags |= engine_view
                             .document
                             .expand_autoexpand(engine_view.camera, engine_view.store);
-                        engine_view.store.regenerate_rendering_in_viewport_threaded(
-                            engine_view.tasks_tx.clone(),
-                            false,
-                            engine_view.camera.viewport(),
-                            engine_view.camera.image_scale(),
-                        );
+                        engine_view
+                            .store
+                            .regenerate_rendering_in_viewport_threaded_coalesced(
+                                engine_view.tasks_tx.clone(),
+                                engine_view.camera.viewport(),
+                                engine_view.camera.image_scale(),
+                            );
                     }
                     ModifyState::Rotate {
                         rotation_center,
@@ -246,23 +453,42 @@ impl Selector {
                         let angle_delta = new_rotation_angle - *current_rotation_angle;
 
                         if angle_delta.abs() > Self::ROTATE_ANGLE_THRESHOLD {
-                            engine_view.store.rotate_strokes(
-                                selection,
-                                angle_delta,
-                                *rotation_center,
-                            );
-                            engine_view.store.rotate_strokes_images(
-                                selection,
-                                angle_delta,
-                                *rotation_center,
+                            GestureTransform::accumulate(
+                                &mut self.gesture_transform,
+                                *selection_bounds,
+                                |transform| {
+                                    transform.append_rotation_wrt_point_mut(
+                                        angle_delta,
+                                        *rotation_center,
+                                    )
+                                },
                             );
+                            if !engine_view
+                                .store
+                                .rotate_selection_composite(angle_delta, *rotation_center)
+                            {
+                                engine_view.store.rotate_strokes(
+                                    selection,
+                                    angle_delta,
+                                    *rotation_center,
+                                );
+                                engine_view.store.rotate_strokes_images(
+                                    selection,
+                                    angle_delta,
+                                    *rotation_center,
+                                );
+                            }
 
-                            if let Some(new_bounds) =
-                                engine_view.store.bounds_for_strokes(selection)
+                            if let Some(new_bounds) = engine_view
+                                .store
+                                .selection_composite()
+                                .map(|composite| composite.bounds())
+                                .or_else(|| engine_view.store.bounds_for_strokes(selection))
                             {
                                 *selection_bounds = new_bounds;
                             }
                             *current_rotation_angle = new_rotation_angle;
+                            *selection_rotation_angle += angle_delta;
                         }
                     }
                     ModifyState::Resize {
@@ -270,12 +496,22 @@ impl Selector {
                         start_bounds,
                         start_pos,
                         last_rendered_bounds,
+                        min_extents,
+                        mirrored,
+                        lock_aspectratio: last_lock_aspectratio,
                     } => {
-                        let lock_aspectratio = engine_view
-                            .pens_config
-                            .selector_config
-                            .resize_lock_aspectratio
-                            || modifier_keys.contains(&ModifierKey::KeyboardCtrl);
+                        let lock_aspectratio =
+                            Self::resize_lock_aspectratio(selection, &modifier_keys, engine_view);
+                        if lock_aspectratio != *last_lock_aspectratio {
+                            // The offsets are computed under different rules once the lock changes,
+                            // so the gesture continues from the current state to avoid a jump.
+                            // The dragged corner of the current bounds is the mirrored one.
+                            *from_corner = from_corner.mirrored(*mirrored);
+                            *mirrored = [false; 2];
+                            *start_bounds = *selection_bounds;
+                            *start_pos = element.pos;
+                            *last_lock_aspectratio = lock_aspectratio;
+                        }
                         let snap_corner_pos = match from_corner {
                             ResizeCorner::TopLeft => start_bounds.mins.coords,
                             ResizeCorner::TopRight => na::vector![
@@ -317,34 +553,77 @@ impl Selector {
                             }
                             ResizeCorner::BottomRight => offset_to_start,
                         };
-                        if lock_aspectratio {
-                            let start_extents = start_bounds.extents();
-                            let start_mean = start_extents.mean();
-                            let offset_mean = offset_to_start.mean();
-                            offset_to_start = start_extents * (offset_mean / start_mean);
+                        let new_extents = Self::resize_new_extents(
+                            start_bounds.extents(),
+                            offset_to_start,
+                            *min_extents,
+                            lock_aspectratio,
+                            engine_view.pens_config.selector_config.resize_mirror,
+                        );
+                        // flip the axes where the dragged corner crossed the pivot since the last event
+                        let new_mirrored = [new_extents[0] < 0.0, new_extents[1] < 0.0];
+                        let flip = na::vector![
+                            if new_mirrored[0] != mirrored[0] {
+                                -1.0
+                            } else {
+                                1.0
+                            },
+                            if new_mirrored[1] != mirrored[1] {
+                                -1.0
+                            } else {
+                                1.0
+                            }
+                        ];
+                        let scale = new_extents
+                            .abs()
+                            .component_div(&selection_bounds.extents())
+                            .component_mul(&flip);
+                        if !scale.iter().all(|s| s.is_finite() && *s != 0.0) {
+                            error!(
+                                "Resizing selection failed, invalid scale {scale:?} for selection bounds {selection_bounds:?}. Aborting the resize."
+                            );
+                            engine_view.store.commit_width_scaling(selection);
+                            *modify_state = ModifyState::AwaitPenUp;
+                            return (
+                                EventResult {
+                                    handled: true,
+                                    propagate: EventPropagation::Stop,
+                                    progress: PenProgress::InProgress,
+                                },
+                                widget_flags,
+                            );
                         }
-                        let min_extents = na::Vector2::<f64>::from_element(2.0f64)
-                            / engine_view.camera.total_zoom();
-                        let scale = (start_bounds.extents() + offset_to_start)
-                            .maxs(&min_extents)
-                            .component_div(&selection_bounds.extents());
 
                         // resize strokes
+                        GestureTransform::accumulate(
+                            &mut self.gesture_transform,
+                            *selection_bounds,
+                            |transform| {
+                                transform.append_translation_mut(-pivot);
+                                transform.append_scale_mut(scale);
+                                transform.append_translation_mut(pivot);
+                            },
+                        );
                         engine_view
                             .store
                             .scale_strokes_with_pivot(selection, scale, pivot);
                         engine_view
                             .store
                             .scale_strokes_images_with_pivot(selection, scale, pivot);
-                        *selection_bounds = selection_bounds
-                            .translate(-pivot)
-                            .scale_non_uniform(scale)
-                            .translate(pivot);
+                        // the mirrored bounds end up on the other side of the pivot
+                        *selection_bounds = Aabb::new_positive(
+                            (pivot + (selection_bounds.mins.coords - pivot).component_mul(&scale))
+                                .into(),
+                            (pivot + (selection_bounds.maxs.coords - pivot).component_mul(&scale))
+                                .into(),
+                        );
+                        *mirrored = new_mirrored;
 
                         // possibly nudge camera
-                        widget_flags |= engine_view
-                            .camera
-                            .nudge_w_pos(element.pos, engine_view.document);
+                        widget_flags |=
+                            engine_view
+                                .camera
+                                .nudge_w_pos(element.pos, engine_view.document, None);
                         widget_flags |= engine_view
                             .document
                             .expand_autoexpand(engine_view.camera, engine_view.store);
@@ -368,14 +647,175 @@ impl Selector {
                                 )
                                 .copied()
                                 .collect();
-                            engine_view.store.regenerate_rendering_for_strokes(
-                                &selection_in_viewport,
-                                engine_view.camera.viewport(),
-                                engine_view.camera.image_scale(),
-                            );
+                            engine_view
+                                .store
+                                .set_rendering_dirty_for_strokes(&selection_in_viewport);
+                            engine_view
+                                .store
+                                .regenerate_rendering_in_viewport_threaded_coalesced(
+                                    engine_view.tasks_tx.clone(),
+                                    engine_view.camera.viewport(),
+                                    engine_view.camera.image_scale(),
+                                );
                             *last_rendered_bounds = *selection_bounds;
                         }
                     }
+                    ModifyState::Shear {
+                        from_corner,
+                        start_bounds,
+                        start_pos,
+                        current_shear,
+                    } => {
+                        // the pivot is on the edge opposite to the dragged corner
+                        let (pivot, corner_offset) = if from_corner.is_top() {
+                            (start_bounds.maxs, -start_bounds.extents()[1])
+                        } else {
+                            (start_bounds.mins, start_bounds.extents()[1])
+                        };
+                        let new_shear = (element.pos[0] - start_pos[0]) / corner_offset;
+                        let shear_delta = new_shear - *current_shear;
+
+                        if new_shear.is_finite() && shear_delta != 0.0 {
+                            GestureTransform::accumulate(
+                                &mut self.gesture_transform,
+                                *selection_bounds,
+                                |transform| {
+                                    transform.append_shear_wrt_point_mut(
+                                        na::vector![shear_delta, 0.0],
+                                        pivot,
+                                    )
+                                },
+                            );
+                            engine_view.store.shear_strokes(
+                                selection,
+                                na::vector![shear_delta, 0.0],
+                                pivot,
+                            );
+                            engine_view.store.shear_strokes_images(
+                                selection,
+                                na::vector![shear_delta, 0.0],
+                                pivot,
+                            );
+                            if let Some(new_bounds) =
+                                engine_view.store.bounds_for_strokes(selection)
+                            {
+                                *selection_bounds = new_bounds;
+                            }
+                            *current_shear = new_shear;
+                        }
+
+                        // possibly nudge camera
+                        widget_flags |=
+                            engine_view
+                                .camera
+                                .nudge_w_pos(element.pos, engine_view.document, None);
+                        widget_flags |= engine_view
+                            .document
+                            .expand_autoexpand(engine_view.camera, engine_view.store);
+                    }
+                    ModifyState::EditNodes {
+                        dragged,
+                        hover_pos,
+                        await_pen_up,
+                    } => {
+                        *hover_pos = None;
+                        match dragged {
+                            Some(dragged) => {
+                                if let Some(&key) = selection.first() {
+                                    let node_pos = engine_view
+                                        .document
+                                        .snap_position(element.pos + dragged.grab_offset);
+                                    if let Some(new_bounds) =
+                                        Self::move_node(key, dragged.idx, node_pos, engine_view)
+                                    {
+                                        *selection_bounds = new_bounds;
+                                        dragged.moved = true;
+                                    }
+                                }
+
+                                // possibly nudge camera
+                                widget_flags |= engine_view.camera.nudge_w_pos(
+                                    element.pos,
+                                    engine_view.document,
+                                    Some(Self::AUTO_NUDGE_STRENGTH),
+                                );
+                                widget_flags |= engine_view
+                                    .document
+                                    .expand_autoexpand(engine_view.camera, engine_view.store);
+                            }
+                            None if *await_pen_up => {}
+                            None => {
+                                let control_point =
+                                    Self::node_at_pos(selection, element.pos, &engine_view.as_im())
+                                        .and_then(|idx| {
+                                            Self::selection_control_points(
+                                                selection,
+                                                engine_view.store,
+                                            )?
+                                            .get(idx)
+                                            .map(|&control_point| (idx, control_point))
+                                        });
+
+                                if let Some((idx, control_point)) = control_point {
+                                    if modifier_keys.contains(&ModifierKey::KeyboardCtrl) {
+                                        // ctrl-clicking on one of the control point handles removes it
+                                        if let Some(&key) = selection.first() {
+                                            if let Some(new_bounds) = Self::remove_node(
+                                                key,
+                                                idx,
+                                                engine_view,
+                                                &mut widget_flags,
+                                            ) {
+                                                *selection_bounds = new_bounds;
+                                            }
+                                        }
+                                        *await_pen_up = true;
+                                    } else {
+                                        // clicking on one of the control point handles
+                                        *dragged = Some(DraggedNode {
+                                            idx,
+                                            grab_offset: control_point - element.pos,
+                                            moved: false,
+                                        });
+                                    }
+                                } else if let Some((segment_idx, midpoint)) = Self::segment_at_pos(
+                                    selection,
+                                    element.pos,
+                                    &engine_view.as_im(),
+                                ) {
+                                    // clicking on one of the segment midpoint handles inserts a node,
+                                    // which is then dragged and recorded when the pen is lifted
+                                    if let Some(&key) = selection.first() {
+                                        if let Some(new_bounds) = Self::insert_node(
+                                            key,
+                                            segment_idx,
+                                            midpoint,
+                                            engine_view,
+                                        ) {
+                                            *selection_bounds = new_bounds;
+                                            *dragged = Some(DraggedNode {
+                                                idx: segment_idx + 1,
+                                                grab_offset: midpoint - element.pos,
+                                                moved: true,
+                                            });
+                                        }
+                                    }
+                                } else if !selection_bounds
+                                    .contains_local_point(&element.pos.into())
+                                {
+                                    // when clicking outside the selection bounds, reset
+                                    engine_view
+                                        .store
+                                        .set_selected_keys_keep_order(selection, false);
+                                    self.state = SelectorState::Idle;
+                                    widget_flags.selection_changed = true;
+
+                                    progress = PenProgress::Finished;
+                                }
+                            }
+                        }
+                    }
+                    ModifyState::Pinch { .. } | ModifyState::AwaitPenUp => {}
                 }
 
                 widget_flags.store_modified = true;
@@ -387,6 +827,18 @@ impl Selector {
                 }
             }
         };
+        self.begin_gesture_transaction(engine_view);
+        self.begin_drag_composite(engine_view);
+        // Moving the camera requires redrawing the entire surface
+        if !widget_flags.view_modified {
+            if let Some(damage) = self.damage(prev_bounds, &engine_view.as_im()) {
+                widget_flags |= WidgetFlags {
+                    redraw: true,
+                    damage: Some(damage),
+                    ..Default::default()
+                };
+            }
+        }
 
         (event_result, widget_flags)
     }
@@ -398,8 +850,15 @@ impl Selector {
         _now: Instant,
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
+        if !self.validate_selection(engine_view) {
+            return Self::selection_vanished_result();
+        }
         let mut widget_flags = WidgetFlags::default();
         let selector_bounds = self.bounds_on_doc(&engine_view.as_im());
+        let selecting_style = self
+            .style_override
+            .take()
+            .unwrap_or(engine_view.pens_config.selector_config.style);
 
         let event_result = match &mut self.state {
             SelectorState::Idle => EventResult {
@@ -407,41 +866,27 @@ impl Selector {
                 propagate: EventPropagation::Proceed,
                 progress: PenProgress::Idle,
             },
-            SelectorState::Selecting { path } => {
+            SelectorState::Selecting { path, .. } => {
                 let mut progress = PenProgress::Finished;
 
-                let new_selection = match engine_view.pens_config.selector_config.style {
+                let new_selection = match selecting_style {
                     SelectorStyle::Polygon => {
-                        if path.len() >= 3 {
+                        if Self::polygon_path_valid(path, engine_view.camera.total_zoom()) {
                             engine_view
                                 .store
-                                .strokes_hitboxes_contained_in_path_polygon(
-                                    path,
-                                    engine_view.camera.viewport(),
-                                )
+                                .strokes_hitboxes_contained_in_path_polygon(path, false)
                         } else {
                             vec![]
                         }
                     }
                     SelectorStyle::Rectangle => {
-                        if let (Some(first), Some(last)) = (path.first(), path.last()) {
-                            let aabb = Aabb::new_positive(first.pos.into(), last.pos.into());
-                            engine_view.store.strokes_hitboxes_contained_in_aabb(
-                                aabb,
-                                engine_view.camera.viewport(),
-                            )
-                        } else {
-                            vec![]
-                        }
+                        Self::rect_selection_keys(path, &engine_view.as_im())
                     }
                     SelectorStyle::Single => {
                         if let Some(key) = path.last().and_then(|last| {
                             engine_view
                                 .store
-                                .stroke_hitboxes_contain_coord(
-                                    engine_view.camera.viewport(),
-                                    last.pos,
-                                )
+                                .stroke_hitboxes_contain_coord(last.pos, false)
                                 .pop()
                         }) {
                             vec![key]
@@ -451,10 +896,9 @@ impl Selector {
                     }
                     SelectorStyle::IntersectingPath => {
                         if path.len() >= 3 {
-                            engine_view.store.strokes_hitboxes_intersect_path(
-                                path,
-                                engine_view.camera.viewport(),
-                            )
+                            engine_view
+                                .store
+                                .strokes_hitboxes_intersect_path(path, false)
                         } else {
                             vec![]
                         }
@@ -464,6 +908,7 @@ impl Selector {
                     engine_view.store.set_selected_keys(&new_selection, true);
                     widget_flags.store_modified = true;
                     widget_flags.deselect_color_setters = true;
+                    widget_flags.selection_changed = true;
 
                     if let Some(new_bounds) = engine_view.store.bounds_for_strokes(&new_selection) {
                         // Change to the modify state
@@ -471,6 +916,7 @@ impl Selector {
                             modify_state: ModifyState::default(),
                             selection: new_selection,
                             selection_bounds: new_bounds,
+                            rotation_angle: 0.0,
                         };
                         progress = PenProgress::InProgress;
                     }
@@ -482,19 +928,58 @@ impl Selector {
                     progress,
                 }
             }
+            SelectorState::ZoomRegion { start, .. } => {
+                let start = *start;
+                self.state = SelectorState::Idle;
+                widget_flags |= Self::zoom_to_region(start, element.pos, engine_view);
+                widget_flags.redraw = true;
+
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::Finished,
+                }
+            }
             SelectorState::ModifySelection {
                 modify_state,
                 selection,
                 selection_bounds,
+                ..
             } => {
+                let copy_drag = self.copy_drag.take();
                 match modify_state {
+                    ModifyState::Translate {
+                        start_pos,
+                        current_pos,
+                        ..
+                    } if copy_drag.is_some() && start_pos == current_pos => {
+                        // The duplicates were never dragged out, so a stray click doesn't leave a hidden copy behind
+                        engine_view.store.discard_selection_composite();
+                        if let Some(tx_id) = self.transaction.take() {
+                            engine_view.store.rollback_transaction(tx_id);
+                        }
+                        if let Some(originals) = copy_drag {
+                            widget_flags |= Self::discard_copy_drag(
+                                originals,
+                                selection,
+                                selection_bounds,
+                                engine_view,
+                            );
+                        }
+                    }
                     ModifyState::Translate { .. }
                     | ModifyState::Rotate { .. }
-                    | ModifyState::Resize { .. } => {
+                    | ModifyState::Resize { .. }
+                    | ModifyState::Shear { .. }
+                    | ModifyState::Pinch { .. } => {
+                        engine_view.store.commit_selection_composite();
+                        engine_view.store.commit_width_scaling(selection);
                         engine_view.store.update_geometry_for_strokes(selection);
                         widget_flags |= engine_view
                             .document
                             .resize_autoexpand(engine_view.store, engine_view.camera);
+                        // the final state is always rendered, regardless of coalesced requests still in flight
+                        engine_view.store.clear_pending_rendering_coalesced();
                         engine_view.store.regenerate_rendering_in_viewport_threaded(
                             engine_view.tasks_tx.clone(),
                             false,
@@ -508,20 +993,43 @@ impl Selector {
                         // We would need to update bounds held in the modify state, but since we transition into either
                         // the up or hover state anyway that is not actually needed.
 
-                        widget_flags |= engine_view.store.record(Instant::now());
+                        if let Some(gesture_transform) = self.gesture_transform.take() {
+                            engine_view
+                                .store
+                                .set_last_selection_transform(gesture_transform.relative());
+                        }
+                        widget_flags |= match self.transaction.take() {
+                            Some(tx_id) => {
+                                engine_view.store.commit_transaction(tx_id, Instant::now())
+                            }
+                            None => engine_view.store.record(Instant::now()),
+                        };
                         widget_flags.store_modified = true;
                     }
+                    ModifyState::EditNodes {
+                        dragged,
+                        hover_pos,
+                        await_pen_up,
+                    } => {
+                        *await_pen_up = false;
+                        if let Some(dragged) = dragged.take() {
+                            widget_flags |= Self::finish_node_drag(dragged, engine_view);
+                        }
+                        *hover_pos = Some(element.pos);
+                    }
                     _ => {}
                 }
 
-                *modify_state = if selector_bounds
-                    .map(|b| b.contains_local_point(&element.pos.into()))
-                    .unwrap_or(false)
-                {
-                    ModifyState::Hover(element.pos)
-                } else {
-                    ModifyState::Up
-                };
+                if !matches!(modify_state, ModifyState::EditNodes { .. }) {
+                    *modify_state = if selector_bounds
+                        .map(|b| b.contains_local_point(&element.pos.into()))
+                        .unwrap_or(false)
+                    {
+                        ModifyState::Hover(element.pos)
+                    } else {
+                        ModifyState::Up
+                    };
+                }
 
                 EventResult {
                     handled: true,
@@ -541,6 +1049,9 @@ impl Selector {
         _now: Instant,
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
+        if !self.validate_selection(engine_view) {
+            return Self::selection_vanished_result();
+        }
         let widget_flags = WidgetFlags::default();
         let selector_bounds = self.bounds_on_doc(&engine_view.as_im());
 
@@ -550,20 +1061,24 @@ impl Selector {
                 propagate: EventPropagation::Proceed,
                 progress: PenProgress::Idle,
             },
-            SelectorState::Selecting { .. } => EventResult {
+            SelectorState::Selecting { .. } | SelectorState::ZoomRegion { .. } => EventResult {
                 handled: true,
                 propagate: EventPropagation::Stop,
                 progress: PenProgress::InProgress,
             },
             SelectorState::ModifySelection { modify_state, .. } => {
-                *modify_state = if selector_bounds
-                    .map(|b| b.contains_local_point(&element.pos.into()))
-                    .unwrap_or(false)
-                {
-                    ModifyState::Hover(element.pos)
+                if let ModifyState::EditNodes { hover_pos, .. } = modify_state {
+                    *hover_pos = Some(element.pos);
                 } else {
-                    ModifyState::Up
-                };
+                    *modify_state = if selector_bounds
+                        .map(|b| b.contains_local_point(&element.pos.into()))
+                        .unwrap_or(false)
+                    {
+                        ModifyState::Hover(element.pos)
+                    } else {
+                        ModifyState::Up
+                    };
+                }
                 EventResult {
                     handled: true,
                     propagate: EventPropagation::Stop,
@@ -582,12 +1097,38 @@ impl Selector {
         _now: Instant,
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
+        if !self.validate_selection(engine_view) {
+            return Self::selection_vanished_result();
+        }
         let mut widget_flags = WidgetFlags::default();
 
+        if let Some(event_result) = self.handle_resize_input_keypressed(
+            keyboard_key,
+            &modifier_keys,
+            engine_view,
+            &mut widget_flags,
+        ) {
+            return (event_result, widget_flags);
+        }
+
+        let action = engine_view
+            .pens_config
+            .selector_config
+            .shortcuts
+            .action_for(keyboard_key, &modifier_keys);
+
         let event_result = match &mut self.state {
-            SelectorState::Idle => match keyboard_key {
-                KeyboardKey::Unicode('a') => {
-                    self.select_all(modifier_keys, engine_view, &mut widget_flags);
+            SelectorState::Idle | SelectorState::Selecting { .. } => match action {
+                Some(
+                    action @ (SelectorAction::SelectAll
+                    | SelectorAction::SelectAllDocument
+                    | SelectorAction::SelectAllPage),
+                ) => {
+                    self.select_all(
+                        SelectAllScope::from_action(action),
+                        engine_view,
+                        &mut widget_flags,
+                    );
                     EventResult {
                         handled: true,
                         propagate: EventPropagation::Stop,
@@ -600,13 +1141,14 @@ impl Selector {
                     progress: PenProgress::InProgress,
                 },
             },
-            SelectorState::Selecting { .. } => match keyboard_key {
-                KeyboardKey::Unicode('a') => {
-                    self.select_all(modifier_keys, engine_view, &mut widget_flags);
+            SelectorState::ZoomRegion { .. } => match action {
+                Some(SelectorAction::Cancel) => {
+                    self.state = SelectorState::Idle;
+                    widget_flags.redraw = true;
                     EventResult {
                         handled: true,
                         propagate: EventPropagation::Stop,
-                        progress: PenProgress::InProgress,
+                        progress: PenProgress::Finished,
                     }
                 }
                 _ => EventResult {
@@ -615,64 +1157,294 @@ impl Selector {
                     progress: PenProgress::InProgress,
                 },
             },
-            SelectorState::ModifySelection { selection, .. } => {
-                match keyboard_key {
-                    KeyboardKey::Unicode('a') => {
-                        self.select_all(modifier_keys, engine_view, &mut widget_flags);
+            SelectorState::ModifySelection {
+                modify_state,
+                selection,
+                selection_bounds,
+                rotation_angle,
+            } => match action {
+                Some(SelectorAction::Cancel)
+                    if matches!(modify_state, ModifyState::EditNodes { .. }) =>
+                {
+                    // Only leaves node editing, the selection is kept
+                    if let ModifyState::EditNodes {
+                        dragged: Some(dragged),
+                        ..
+                    } = modify_state
+                    {
+                        widget_flags |= Self::finish_node_drag(*dragged, engine_view);
+                    }
+                    *modify_state = ModifyState::Up;
+                    widget_flags.redraw = true;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::EditNodes) => {
+                    if self.enter_node_edit(engine_view) {
+                        widget_flags.redraw = true;
                         EventResult {
                             handled: true,
                             propagate: EventPropagation::Stop,
                             progress: PenProgress::InProgress,
                         }
-                    }
-                    KeyboardKey::Unicode('d') => {
-                        //Duplicate selection
-                        if modifier_keys.contains(&ModifierKey::KeyboardCtrl) {
-                            let duplicated = engine_view.store.duplicate_selection();
-                            engine_view.store.update_geometry_for_strokes(&duplicated);
-                            engine_view.store.regenerate_rendering_for_strokes_threaded(
-                                engine_view.tasks_tx.clone(),
-                                &duplicated,
-                                engine_view.camera.viewport(),
-                                engine_view.camera.image_scale(),
-                            );
-
-                            widget_flags |= engine_view.store.record(Instant::now());
-                            widget_flags.resize = true;
-                            widget_flags.store_modified = true;
-                        }
+                    } else if self.request_text_edit(None, engine_view) {
+                        // A single text stroke is edited with the typewriter instead
+                        widget_flags.selection_changed = true;
                         EventResult {
                             handled: true,
                             propagate: EventPropagation::Stop,
                             progress: PenProgress::Finished,
                         }
-                    }
-                    KeyboardKey::Delete | KeyboardKey::BackSpace => {
-                        engine_view.store.set_trashed_keys(selection, true);
-                        widget_flags |= super::cancel_selection(selection, engine_view);
-                        self.state = SelectorState::Idle;
+                    } else {
+                        // Multi-stroke selections and other strokes than shapes don't offer node editing
                         EventResult {
-                            handled: true,
-                            propagate: EventPropagation::Stop,
-                            progress: PenProgress::Finished,
+                            handled: false,
+                            propagate: EventPropagation::Proceed,
+                            progress: PenProgress::InProgress,
                         }
                     }
-                    KeyboardKey::Escape => {
-                        widget_flags |= super::cancel_selection(selection, engine_view);
-                        self.state = SelectorState::Idle;
-                        EventResult {
-                            handled: true,
-                            propagate: EventPropagation::Stop,
-                            progress: PenProgress::Finished,
+                }
+                Some(
+                    action @ (SelectorAction::SelectAll
+                    | SelectorAction::SelectAllDocument
+                    | SelectorAction::SelectAllPage),
+                ) => {
+                    self.select_all(
+                        SelectAllScope::from_action(action),
+                        engine_view,
+                        &mut widget_flags,
+                    );
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::Duplicate) => {
+                    let duplicated = engine_view.store.duplicate_selection();
+                    engine_view.store.update_geometry_for_strokes(&duplicated);
+                    engine_view.store.regenerate_rendering_for_strokes_threaded(
+                        engine_view.tasks_tx.clone(),
+                        &duplicated,
+                        engine_view.camera.viewport(),
+                        engine_view.camera.image_scale(),
+                    );
+
+                    widget_flags |= engine_view.store.record(Instant::now());
+                    widget_flags.resize = true;
+                    widget_flags.store_modified = true;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::Finished,
+                    }
+                }
+                Some(SelectorAction::Frame) => {
+                    let options = engine_view.pens_config.shaper_config.smooth_options.clone();
+                    if Self::insert_frame(selection, Self::FRAME_PADDING, options, engine_view)
+                        .is_some()
+                    {
+                        widget_flags |= engine_view.store.record(Instant::now());
+                        widget_flags.resize = true;
+                        widget_flags.store_modified = true;
+                    }
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::Lock) => {
+                    // Lock the selection, locked strokes can't stay selected
+                    engine_view.store.set_strokes_locked(selection, true);
+                    widget_flags |= super::cancel_selection(selection, engine_view);
+                    self.state = SelectorState::Idle;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::Finished,
+                    }
+                }
+                Some(
+                    action @ (SelectorAction::DecreaseOpacity | SelectorAction::IncreaseOpacity),
+                ) => {
+                    let offset = if action == SelectorAction::IncreaseOpacity {
+                        Self::OPACITY_STEP
+                    } else {
+                        -Self::OPACITY_STEP
+                    };
+                    widget_flags |= engine_view.store.adjust_strokes_opacity(selection, offset);
+                    engine_view.store.regenerate_rendering_for_strokes_threaded(
+                        engine_view.tasks_tx.clone(),
+                        selection,
+                        engine_view.camera.viewport(),
+                        engine_view.camera.image_scale(),
+                    );
+                    widget_flags |= engine_view.store.record(Instant::now());
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(
+                    action @ (SelectorAction::Raise
+                    | SelectorAction::Lower
+                    | SelectorAction::MoveToFront
+                    | SelectorAction::MoveToBack
+                    | SelectorAction::SortChronologically),
+                ) => {
+                    // Change the z-order of the selection
+                    match action {
+                        SelectorAction::Raise => engine_view.store.raise_strokes(selection),
+                        SelectorAction::Lower => engine_view.store.lower_strokes(selection),
+                        SelectorAction::MoveToFront => {
+                            engine_view.store.move_strokes_to_front(selection)
                         }
+                        SelectorAction::MoveToBack => {
+                            engine_view.store.move_strokes_to_back(selection)
+                        }
+                        _ => engine_view.store.sort_strokes_by_creation(selection),
                     }
-                    _ => EventResult {
-                        handled: false,
-                        propagate: EventPropagation::Proceed,
+                    // The rendering order is updated with the next redraw
+                    widget_flags |= engine_view.store.record(Instant::now());
+                    widget_flags.store_modified = true;
+                    widget_flags.redraw = true;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
                         progress: PenProgress::InProgress,
-                    },
+                    }
                 }
-            }
+                Some(SelectorAction::Delete) => {
+                    engine_view.store.set_trashed_keys(selection, true);
+                    widget_flags |= super::cancel_selection(selection, engine_view);
+                    self.state = SelectorState::Idle;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::Finished,
+                    }
+                }
+                Some(SelectorAction::Cancel) if self.transaction.is_some() => {
+                    // Cancels the ongoing gesture, the selection is kept
+                    engine_view.store.discard_selection_composite();
+                    if let Some(tx_id) = self.transaction.take() {
+                        widget_flags |=
+                            Self::rollback_gesture(tx_id, selection, selection_bounds, engine_view);
+                    }
+                    if let Some(originals) = self.copy_drag.take() {
+                        widget_flags |= Self::discard_copy_drag(
+                            originals,
+                            selection,
+                            selection_bounds,
+                            engine_view,
+                        );
+                    }
+                    // The pen is still down, it is only lifted before the selection can be modified again
+                    *modify_state = ModifyState::AwaitPenUp;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::Cancel) => {
+                    widget_flags |= super::cancel_selection(selection, engine_view);
+                    self.state = SelectorState::Idle;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::Finished,
+                    }
+                }
+                Some(
+                    action @ (SelectorAction::RotateClockwise
+                    | SelectorAction::RotateCounterclockwise
+                    | SelectorAction::RotateQuarterClockwise
+                    | SelectorAction::RotateQuarterCounterclockwise),
+                ) if matches!(modify_state, ModifyState::Up | ModifyState::Hover(_)) => {
+                    let steps = match action {
+                        SelectorAction::RotateClockwise => 1,
+                        SelectorAction::RotateCounterclockwise => -1,
+                        SelectorAction::RotateQuarterClockwise => KeyRotation::QUARTER_TURN_STEPS,
+                        _ => -KeyRotation::QUARTER_TURN_STEPS,
+                    };
+                    widget_flags |= Self::rotate_selection_by_steps(
+                        &mut self.key_rotation,
+                        steps,
+                        selection,
+                        selection_bounds,
+                        engine_view,
+                    );
+                    *rotation_angle += f64::from(steps) * KeyRotation::STEP_ANGLE;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::RepeatTransform)
+                    if matches!(modify_state, ModifyState::Up | ModifyState::Hover(_)) =>
+                {
+                    widget_flags |= Self::repeat_transform(
+                        selection,
+                        selection_bounds,
+                        rotation_angle,
+                        engine_view,
+                    );
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::GrowSelection)
+                    if matches!(modify_state, ModifyState::Up | ModifyState::Hover(_)) =>
+                {
+                    let grown = engine_view.store.grow_selection(Some(1));
+                    if !grown.is_empty() {
+                        selection.extend(grown);
+                        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+                            *selection_bounds = new_bounds;
+                        }
+                        widget_flags.store_modified = true;
+                        widget_flags.deselect_color_setters = true;
+                        widget_flags.selection_changed = true;
+                        widget_flags.redraw = true;
+                    }
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                Some(SelectorAction::ZoomToSelection) => {
+                    widget_flags |= engine_view.camera.zoom_to_bounds_w_timeout(
+                        *selection_bounds,
+                        Engine::ZOOM_TO_FIT_PADDING,
+                        engine_view.tasks_tx.clone(),
+                    ) | engine_view
+                        .document
+                        .expand_autoexpand(engine_view.camera, engine_view.store);
+                    widget_flags.redraw = true;
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::InProgress,
+                    }
+                }
+                // Keys the selector doesn't own are passed on, so that the application shortcuts
+                // (e.g. zooming) keep working while a selection is active.
+                None => EventResult {
+                    handled: false,
+                    propagate: EventPropagation::Proceed,
+                    progress: PenProgress::InProgress,
+                },
+            },
         };
 
         (event_result, widget_flags)
@@ -684,6 +1456,8 @@ impl Selector {
         _now: Instant,
         _engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
+        // The keyboard resize input is only taken from the key press events,
+        // the committed text of the same keystrokes would insert it twice.
         let widget_flags = WidgetFlags::default();
 
         let event_result = match &mut self.state {
@@ -692,7 +1466,7 @@ impl Selector {
                 propagate: EventPropagation::Proceed,
                 progress: PenProgress::Idle,
             },
-            SelectorState::Selecting { .. } => EventResult {
+            SelectorState::Selecting { .. } | SelectorState::ZoomRegion { .. } => EventResult {
                 handled: false,
                 propagate: EventPropagation::Proceed,
                 progress: PenProgress::InProgress,
@@ -712,6 +1486,9 @@ impl Selector {
         _now: Instant,
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
+        if !self.validate_selection(engine_view) {
+            return Self::selection_vanished_result();
+        }
         let mut widget_flags = WidgetFlags::default();
 
         let event_result = match &mut self.state {
@@ -720,15 +1497,45 @@ impl Selector {
                 propagate: EventPropagation::Proceed,
                 progress: PenProgress::Idle,
             },
+            SelectorState::ZoomRegion { .. } => {
+                self.state = SelectorState::Idle;
+                widget_flags.redraw = true;
+                EventResult {
+                    handled: true,
+                    propagate: EventPropagation::Stop,
+                    progress: PenProgress::Finished,
+                }
+            }
             SelectorState::Selecting { .. } => {
                 self.state = SelectorState::Idle;
+                self.style_override = None;
                 EventResult {
                     handled: true,
                     propagate: EventPropagation::Stop,
                     progress: PenProgress::Finished,
                 }
             }
-            SelectorState::ModifySelection { selection, .. } => {
+            SelectorState::ModifySelection {
+                selection,
+                selection_bounds,
+                ..
+            } => {
+                engine_view.store.discard_selection_composite();
+                match self.transaction.take() {
+                    Some(tx_id) => {
+                        widget_flags |=
+                            Self::rollback_gesture(tx_id, selection, selection_bounds, engine_view);
+                    }
+                    None => engine_view.store.commit_width_scaling(selection),
+                }
+                if let Some(originals) = self.copy_drag.take() {
+                    widget_flags |= Self::discard_copy_drag(
+                        originals,
+                        selection,
+                        selection_bounds,
+                        engine_view,
+                    );
+                }
                 widget_flags |= super::cancel_selection(selection, engine_view);
                 self.state = SelectorState::Idle;
                 EventResult {
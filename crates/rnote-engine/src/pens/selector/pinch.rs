@@ -0,0 +1,177 @@
+// Imports
+use super::repeattransform::GestureTransform;
+use super::{ModifyState, Selector, SelectorState};
+use crate::engine::EngineViewMut;
+use crate::WidgetFlags;
+use p2d::query::PointQuery;
+use std::time::Instant;
+
+impl Selector {
+    /// Begin a two-finger pinch gesture.
+    ///
+    /// `center` is the gesture centroid in document coordinates.
+    /// Returns whether the gesture is applied to the selection, which is the case when it starts inside the selection bounds.
+    pub(crate) fn pinch_begin(
+        &mut self,
+        center: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+    ) -> (bool, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        if !self.validate_selection(engine_view) {
+            widget_flags.redraw = true;
+            widget_flags.selection_changed = true;
+            return (false, widget_flags);
+        }
+
+        let SelectorState::ModifySelection {
+            modify_state: modify_state @ (ModifyState::Up | ModifyState::Hover(_)),
+            selection,
+            selection_bounds,
+            ..
+        } = &mut self.state
+        else {
+            return (false, widget_flags);
+        };
+        if !selection_bounds.contains_local_point(&center.into()) {
+            return (false, widget_flags);
+        }
+
+        engine_view.store.begin_width_scaling(
+            selection,
+            engine_view.pens_config.selector_config.width_scale_policy,
+        );
+        *modify_state = ModifyState::Pinch {
+            current_scale: 1.0,
+            current_rotation_angle: 0.0,
+        };
+        self.gesture_transform = None;
+        widget_flags.redraw = true;
+
+        (true, widget_flags)
+    }
+
+    /// Update the pinch gesture.
+    ///
+    /// `scale` and `rotation_angle` are the accumulated values since the gesture began,
+    /// `center` is the current gesture centroid in document coordinates.
+    pub(crate) fn pinch_update(
+        &mut self,
+        scale: f64,
+        rotation_angle: f64,
+        center: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        if !self.validate_selection(engine_view) {
+            widget_flags.redraw = true;
+            widget_flags.selection_changed = true;
+            return widget_flags;
+        }
+
+        let SelectorState::ModifySelection {
+            modify_state:
+                ModifyState::Pinch {
+                    current_scale,
+                    current_rotation_angle,
+                },
+            selection,
+            selection_bounds,
+            rotation_angle: selection_rotation_angle,
+        } = &mut self.state
+        else {
+            return widget_flags;
+        };
+
+        let angle_delta = rotation_angle - *current_rotation_angle;
+        if angle_delta.abs() > Self::ROTATE_ANGLE_THRESHOLD {
+            GestureTransform::accumulate(
+                &mut self.gesture_transform,
+                *selection_bounds,
+                |transform| transform.append_rotation_wrt_point_mut(angle_delta, center.into()),
+            );
+            engine_view
+                .store
+                .rotate_strokes(selection, angle_delta, center.into());
+            engine_view
+                .store
+                .rotate_strokes_images(selection, angle_delta, center.into());
+            *current_rotation_angle = rotation_angle;
+            *selection_rotation_angle += angle_delta;
+        }
+
+        let scale_delta = scale / *current_scale;
+        let min_extents = 2.0 / engine_view.camera.total_zoom();
+        if (selection_bounds.extents() * scale_delta).min() > min_extents {
+            let scale_delta = na::Vector2::repeat(scale_delta);
+            GestureTransform::accumulate(
+                &mut self.gesture_transform,
+                *selection_bounds,
+                |transform| {
+                    transform.append_translation_mut(-center);
+                    transform.append_scale_mut(scale_delta);
+                    transform.append_translation_mut(center);
+                },
+            );
+            engine_view
+                .store
+                .scale_strokes_with_pivot(selection, scale_delta, center);
+            engine_view
+                .store
+                .scale_strokes_images_with_pivot(selection, scale_delta, center);
+            *current_scale = scale;
+        }
+
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+            *selection_bounds = new_bounds;
+        }
+        widget_flags |= engine_view
+            .document
+            .expand_autoexpand(engine_view.camera, engine_view.store);
+        widget_flags.store_modified = true;
+        widget_flags.redraw = true;
+
+        widget_flags
+    }
+
+    /// End the pinch gesture, committing the geometry and recording the changes in the history.
+    pub(crate) fn pinch_end(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        let SelectorState::ModifySelection {
+            modify_state: modify_state @ ModifyState::Pinch { .. },
+            selection,
+            selection_bounds,
+            ..
+        } = &mut self.state
+        else {
+            return widget_flags;
+        };
+
+        engine_view.store.commit_width_scaling(selection);
+        engine_view.store.update_geometry_for_strokes(selection);
+        widget_flags |= engine_view
+            .document
+            .resize_autoexpand(engine_view.store, engine_view.camera);
+        engine_view.store.regenerate_rendering_in_viewport_threaded(
+            engine_view.tasks_tx.clone(),
+            false,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+            *selection_bounds = new_bounds;
+        }
+        *modify_state = ModifyState::Up;
+        if let Some(gesture_transform) = self.gesture_transform.take() {
+            engine_view
+                .store
+                .set_last_selection_transform(gesture_transform.relative());
+        }
+
+        widget_flags |= engine_view.store.record(Instant::now());
+        widget_flags.store_modified = true;
+        widget_flags.redraw = true;
+
+        widget_flags
+    }
+}
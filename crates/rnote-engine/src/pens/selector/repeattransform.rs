@@ -0,0 +1,141 @@
+// Imports
+use super::{ModifyState, Selector, SelectorState};
+use crate::engine::EngineViewMut;
+use crate::store::StrokeKey;
+use crate::WidgetFlags;
+use p2d::bounding_volume::Aabb;
+use rnote_compose::Transform;
+use std::time::Instant;
+use tracing::error;
+
+/// The transform that is applied to the selection during a gesture, accumulated from its individual steps.
+///
+/// Once the gesture is committed it is recorded in the store, so that it can be repeated on other selections.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct GestureTransform {
+    /// The center of the selection bounds before the gesture.
+    origin: na::Point2<f64>,
+    transform: Transform,
+}
+
+impl GestureTransform {
+    /// Accumulate a step of the gesture.
+    ///
+    /// A new gesture transform is started from the selection bounds before the step if there is none yet.
+    pub(super) fn accumulate(
+        gesture_transform: &mut Option<Self>,
+        selection_bounds: Aabb,
+        step: impl FnOnce(&mut Transform),
+    ) {
+        let gesture_transform = gesture_transform.get_or_insert_with(|| Self {
+            origin: selection_bounds.center(),
+            transform: Transform::default(),
+        });
+        step(&mut gesture_transform.transform);
+    }
+
+    /// The transform relative to the center of the selection bounds before the gesture.
+    pub(super) fn relative(&self) -> Transform {
+        Transform::new(
+            na::Translation2::from(-self.origin.coords)
+                * self.transform.affine
+                * na::Translation2::from(self.origin.coords),
+        )
+    }
+}
+
+impl Selector {
+    /// Repeat the last committed transform gesture on the current selection, recording history.
+    ///
+    /// Only applied while the selection is not modified otherwise.
+    pub(crate) fn repeat_last_transform(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let SelectorState::ModifySelection {
+            modify_state: ModifyState::Up | ModifyState::Hover(_),
+            selection,
+            selection_bounds,
+            rotation_angle,
+        } = &mut self.state
+        else {
+            return WidgetFlags::default();
+        };
+        Self::repeat_transform(selection, selection_bounds, rotation_angle, engine_view)
+    }
+
+    /// Apply the last committed transform relative to the center of the selection bounds.
+    pub(super) fn repeat_transform(
+        selection: &[StrokeKey],
+        selection_bounds: &mut Aabb,
+        rotation_angle: &mut f64,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let Some(relative) = engine_view.store.last_selection_transform() else {
+            return widget_flags;
+        };
+        let center = selection_bounds.center().coords;
+        let affine =
+            na::Translation2::from(center) * relative.affine * na::Translation2::from(-center);
+
+        // Decomposed into a scale, a horizontal shear and a rotation around the origin, followed by a translation
+        let matrix = affine.matrix();
+        let (a, b, c, d) = (
+            matrix[(0, 0)],
+            matrix[(0, 1)],
+            matrix[(1, 0)],
+            matrix[(1, 1)],
+        );
+        let offset = na::vector![matrix[(0, 2)], matrix[(1, 2)]];
+        let scale_x = a.hypot(c);
+        let angle = c.atan2(a);
+        let (sin, cos) = angle.sin_cos();
+        let sheared = cos * b + sin * d;
+        let scale_y = cos * d - sin * b;
+        let scale = na::vector![scale_x, scale_y];
+        let shear = sheared / scale_y;
+        if !(scale.iter().all(|s| s.is_finite() && *s != 0.0) && shear.is_finite()) {
+            error!(
+                "Repeating the last transform failed, the transform {relative:?} is degenerate."
+            );
+            return widget_flags;
+        }
+        let origin = na::Point2::origin();
+
+        if scale != na::Vector2::repeat(1.0) {
+            engine_view.store.begin_width_scaling(
+                selection,
+                engine_view.pens_config.selector_config.width_scale_policy,
+            );
+            engine_view.store.scale_strokes(selection, scale);
+            engine_view.store.commit_width_scaling(selection);
+        }
+        if shear != 0.0 {
+            engine_view
+                .store
+                .shear_strokes(selection, na::vector![shear, 0.0], origin);
+        }
+        if angle != 0.0 {
+            engine_view.store.rotate_strokes(selection, angle, origin);
+        }
+        engine_view.store.translate_strokes(selection, offset);
+        engine_view.store.update_geometry_for_strokes(selection);
+        engine_view.store.regenerate_rendering_for_strokes_threaded(
+            engine_view.tasks_tx.clone(),
+            selection,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+            *selection_bounds = new_bounds;
+        }
+        *rotation_angle += angle;
+
+        widget_flags |= engine_view
+            .document
+            .resize_autoexpand(engine_view.store, engine_view.camera)
+            | engine_view.store.record(Instant::now());
+        widget_flags.resize = true;
+        widget_flags.store_modified = true;
+        widget_flags.redraw = true;
+        widget_flags
+    }
+}
@@ -0,0 +1,382 @@
+// Imports
+use super::{ModifyState, Selector, SelectorState};
+use crate::engine::{EngineView, EngineViewMut};
+use crate::WidgetFlags;
+use p2d::bounding_volume::Aabb;
+use piet::{RenderContext, Text, TextLayoutBuilder};
+use rnote_compose::eventresult::{EventPropagation, EventResult};
+use rnote_compose::ext::Vector2Ext;
+use rnote_compose::penevent::{KeyboardKey, ModifierKey, PenProgress};
+use std::collections::HashSet;
+use std::time::Instant;
+
+/// A parsed keyboard resize or rotation input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ResizeInput {
+    /// Scale uniformly by the percentage of the current size.
+    Percentage(f64),
+    /// Resize the bounds to the size, in document coordinates.
+    Size(na::Vector2<f64>),
+    /// Rotate to the absolute angle in degrees, relative to the selection when it was created.
+    ///
+    /// Positive angles rotate clockwise.
+    Rotation(f64),
+}
+
+impl ResizeInput {
+    /// Parse the input buffer.
+    ///
+    /// Accepts a percentage like "150" or "150%", a size like "200x100"
+    /// and a rotation like "r45" or "r-30°".
+    pub(super) fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(angle) = input.strip_prefix(['r', 'R']) {
+            let angle = angle
+                .trim()
+                .trim_end_matches('°')
+                .trim()
+                .parse::<f64>()
+                .ok()?;
+            angle.is_finite().then_some(Self::Rotation(angle))
+        } else if let Some((width, height)) = input.split_once(['x', 'X']) {
+            let width = width.trim().parse::<f64>().ok()?;
+            let height = height.trim().parse::<f64>().ok()?;
+            (width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0)
+                .then_some(Self::Size(na::vector![width, height]))
+        } else {
+            let percentage = input.trim_end_matches('%').trim().parse::<f64>().ok()?;
+            (percentage.is_finite() && percentage > 0.0).then_some(Self::Percentage(percentage))
+        }
+    }
+
+    /// The scale that needs to be applied to the bounds to fulfill the input.
+    pub(super) fn scale_for_bounds(&self, bounds: Aabb) -> na::Vector2<f64> {
+        match self {
+            Self::Rotation(_) => na::Vector2::repeat(1.0),
+            Self::Percentage(percentage) => na::Vector2::repeat(percentage / 100.0),
+            Self::Size(size) => {
+                let extents = bounds.extents();
+                na::vector![
+                    if extents[0] > 0.0 {
+                        size[0] / extents[0]
+                    } else {
+                        1.0
+                    },
+                    if extents[1] > 0.0 {
+                        size[1] / extents[1]
+                    } else {
+                        1.0
+                    }
+                ]
+            }
+        }
+    }
+}
+
+impl Selector {
+    /// The font size of the resize input readout, in surface coordinates.
+    const RESIZE_INPUT_FONT_SIZE: f64 = 14.0;
+    /// The space reserved for the readout above the selection, in surface coordinates.
+    pub(super) const RESIZE_INPUT_READOUT_HEIGHT: f64 = 64.0;
+    /// The minimum width reserved for the readout, in surface coordinates.
+    pub(super) const RESIZE_INPUT_READOUT_WIDTH: f64 = 160.0;
+
+    /// Whether the selector currently accepts keyboard resize input.
+    pub(super) fn accepts_resize_input(&self) -> bool {
+        matches!(
+            self.state,
+            SelectorState::ModifySelection {
+                modify_state: ModifyState::Up | ModifyState::Hover(_),
+                ..
+            }
+        )
+    }
+
+    /// Append the text of a pressed key to the resize input buffer.
+    ///
+    /// A new input can only be started with a digit or with 'r' for a rotation,
+    /// so that other shortcuts keep working.
+    /// Returns whether the text was consumed.
+    pub(super) fn push_resize_input(&mut self, text: &str) -> bool {
+        if !self.accepts_resize_input() || text.is_empty() {
+            return false;
+        }
+        let valid = |c: char| c.is_ascii_digit() || matches!(c, '.' | 'x' | 'X' | '%' | '-' | '°');
+        match &mut self.resize_input {
+            Some(input) => {
+                if !text.chars().all(valid) {
+                    return false;
+                }
+                input.push_str(text);
+            }
+            None => {
+                let rotation = text.strip_prefix('r');
+                let starts_input =
+                    rotation.is_some() || text.starts_with(|c: char| c.is_ascii_digit());
+                if !starts_input || !rotation.unwrap_or(text).chars().all(valid) {
+                    return false;
+                }
+                self.resize_input = Some(text.to_string());
+            }
+        }
+        true
+    }
+
+    /// Handle a key press for the keyboard resize input.
+    ///
+    /// Returns None if the key is not related to the resize input and should be handled otherwise.
+    pub(super) fn handle_resize_input_keypressed(
+        &mut self,
+        keyboard_key: KeyboardKey,
+        modifier_keys: &HashSet<ModifierKey>,
+        engine_view: &mut EngineViewMut,
+        widget_flags: &mut WidgetFlags,
+    ) -> Option<EventResult<PenProgress>> {
+        if modifier_keys.contains(&ModifierKey::KeyboardCtrl)
+            || modifier_keys.contains(&ModifierKey::KeyboardAlt)
+        {
+            return None;
+        }
+        match keyboard_key {
+            KeyboardKey::Unicode(c) => {
+                let starts_rotation = c == 'r' && self.resize_input.is_none();
+                if !self.push_resize_input(c.encode_utf8(&mut [0; 4])) {
+                    return None;
+                }
+                if starts_rotation {
+                    // The rotate shortcut still handles the key
+                    widget_flags.redraw = true;
+                    return None;
+                }
+            }
+            KeyboardKey::CarriageReturn | KeyboardKey::Linefeed if self.resize_input.is_some() => {
+                *widget_flags |= self.apply_resize_input(engine_view);
+            }
+            KeyboardKey::BackSpace if self.resize_input.is_some() => {
+                if let Some(input) = &mut self.resize_input {
+                    input.pop();
+                    if input.is_empty() {
+                        self.resize_input = None;
+                    }
+                }
+            }
+            KeyboardKey::Escape if self.resize_input.is_some() => {
+                // Only cancels the pending input, the selection is kept
+                self.resize_input = None;
+            }
+            _ => return None,
+        }
+        widget_flags.redraw = true;
+
+        Some(EventResult {
+            handled: true,
+            propagate: EventPropagation::Stop,
+            progress: PenProgress::InProgress,
+        })
+    }
+
+    /// Apply the pending resize or rotation input to the selection about its center
+    /// and record it in the history.
+    ///
+    /// The input is cleared, regardless whether it was valid.
+    pub(super) fn apply_resize_input(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        widget_flags.redraw = true;
+
+        let Some(resize_input) = self
+            .resize_input
+            .take()
+            .and_then(|i| ResizeInput::parse(&i))
+        else {
+            return widget_flags;
+        };
+        let SelectorState::ModifySelection {
+            selection,
+            selection_bounds,
+            rotation_angle,
+            ..
+        } = &mut self.state
+        else {
+            return widget_flags;
+        };
+
+        if let ResizeInput::Rotation(angle) = resize_input {
+            let target_angle = angle.to_radians();
+            let angle_delta = target_angle - *rotation_angle;
+            let center = selection_bounds.center();
+            engine_view
+                .store
+                .rotate_strokes(selection, angle_delta, center);
+            engine_view
+                .store
+                .rotate_strokes_images(selection, angle_delta, center);
+            *rotation_angle = target_angle;
+        } else {
+            let scale = resize_input.scale_for_bounds(*selection_bounds);
+            let pivot = selection_bounds.center().coords;
+            let min_extents = 2.0 / engine_view.camera.total_zoom();
+            if (selection_bounds.extents().component_mul(&scale)).min() < min_extents {
+                return widget_flags;
+            }
+
+            engine_view.store.begin_width_scaling(
+                selection,
+                engine_view.pens_config.selector_config.width_scale_policy,
+            );
+            engine_view
+                .store
+                .scale_strokes_with_pivot(selection, scale, pivot);
+            engine_view
+                .store
+                .scale_strokes_images_with_pivot(selection, scale, pivot);
+            engine_view.store.commit_width_scaling(selection);
+        }
+        engine_view.store.update_geometry_for_strokes(selection);
+        engine_view.store.regenerate_rendering_in_viewport_threaded(
+            engine_view.tasks_tx.clone(),
+            false,
+            engine_view.camera.viewport(),
+            engine_view.camera.image_scale(),
+        );
+        if let Some(new_bounds) = engine_view.store.bounds_for_strokes(selection) {
+            *selection_bounds = new_bounds;
+        }
+        widget_flags |= engine_view
+            .document
+            .resize_autoexpand(engine_view.store, engine_view.camera);
+
+        widget_flags |= engine_view.store.record(Instant::now());
+        widget_flags.store_modified = true;
+
+        widget_flags
+    }
+
+    /// Draw the pending resize input above the selection.
+    ///
+    /// A rotation input is followed by the current rotation of the selection.
+    pub(super) fn draw_resize_input(
+        &self,
+        cx: &mut piet_cairo::CairoRenderContext,
+        engine_view: &EngineView,
+    ) -> anyhow::Result<()> {
+        let (
+            Some(resize_input),
+            SelectorState::ModifySelection {
+                selection_bounds,
+                rotation_angle,
+                ..
+            },
+        ) = (&self.resize_input, &self.state)
+        else {
+            return Ok(());
+        };
+        let total_zoom = engine_view.camera.total_zoom();
+
+        let readout = match ResizeInput::parse(resize_input) {
+            Some(ResizeInput::Percentage(_)) if !resize_input.ends_with('%') => {
+                format!("{resize_input}%")
+            }
+            _ if resize_input.starts_with(['r', 'R']) => format!(
+                "{resize_input}  ({:.1}°)",
+                rotation_angle.to_degrees().rem_euclid(360.0)
+            ),
+            _ => resize_input.clone(),
+        };
+        let text_layout = cx
+            .text()
+            .new_text_layout(readout)
+            .text_color(Self::SELECTION_OUTLINE_COLOR)
+            .font(
+                piet::FontFamily::MONOSPACE,
+                Self::RESIZE_INPUT_FONT_SIZE / total_zoom,
+            )
+            .build()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        let pos = na::vector![
+            selection_bounds.mins[0],
+            selection_bounds.mins[1]
+                - (Self::RESIZE_NODE_SIZE[1] + Self::RESIZE_INPUT_FONT_SIZE * 1.5) / total_zoom
+        ];
+        cx.draw_text(&text_layout, pos.to_kurbo_point());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pens::PenBehaviour;
+    use crate::testsupport::insert_rect;
+    use crate::Engine;
+    use rnote_compose::penevent::PenEvent;
+
+    #[test]
+    fn resize_input_taken_from_key_presses_only() {
+        let mut engine = Engine::default();
+        let key = insert_rect(
+            &mut engine.store,
+            Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]),
+        );
+        engine.store.record(Instant::now());
+        engine.store.set_selected(key, true);
+        let mut selector = Selector::default();
+        selector.update_state(&mut engine.view_mut());
+        let start_extents = engine.store.bounds_for_strokes(&[key]).unwrap().extents();
+        let press = |engine: &mut Engine, selector: &mut Selector, keyboard_key: KeyboardKey| {
+            let _ = selector.handle_event(
+                PenEvent::KeyPressed {
+                    keyboard_key,
+                    modifier_keys: HashSet::new(),
+                },
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+        };
+
+        for c in ['1', '5', '0'] {
+            press(&mut engine, &mut selector, KeyboardKey::Unicode(c));
+            // the text that is committed for the same keystroke is not inserted again
+            let _ = selector.handle_event(
+                PenEvent::Text {
+                    text: c.to_string(),
+                },
+                Instant::now(),
+                &mut engine.view_mut(),
+            );
+        }
+        assert_eq!(selector.resize_input.as_deref(), Some("150"));
+
+        press(&mut engine, &mut selector, KeyboardKey::CarriageReturn);
+        assert!(selector.resize_input.is_none());
+        let extents = engine.store.bounds_for_strokes(&[key]).unwrap().extents();
+        // the stroke width is not necessarily scaled with the shape
+        approx::assert_relative_eq!(extents[0], start_extents[0] * 1.5, epsilon = 5.0);
+        approx::assert_relative_eq!(extents[1], start_extents[1] * 1.5, epsilon = 5.0);
+    }
+
+    #[test]
+    fn parse_resize_input() {
+        assert_eq!(
+            ResizeInput::parse("150"),
+            Some(ResizeInput::Percentage(150.0))
+        );
+        assert_eq!(
+            ResizeInput::parse("50.5%"),
+            Some(ResizeInput::Percentage(50.5))
+        );
+        assert_eq!(
+            ResizeInput::parse("200x100"),
+            Some(ResizeInput::Size(na::vector![200.0, 100.0]))
+        );
+        assert_eq!(
+            ResizeInput::parse("r-30°"),
+            Some(ResizeInput::Rotation(-30.0))
+        );
+        assert_eq!(ResizeInput::parse("r"), None);
+        assert_eq!(ResizeInput::parse("-50"), None);
+        assert_eq!(ResizeInput::parse("0"), None);
+        assert_eq!(ResizeInput::parse("200x"), None);
+        assert_eq!(ResizeInput::parse("1.2.3"), None);
+    }
+}
@@ -0,0 +1,172 @@
+// Imports
+use super::{ModifyState, Selector, SelectorState};
+use crate::document::Background;
+use crate::engine::{EngineView, EngineViewMut};
+use crate::store::StrokeKey;
+use crate::WidgetFlags;
+use p2d::bounding_volume::Aabb;
+
+impl Selector {
+    /// The vertical band `(top, bottom)` of the row that contains the y coordinate.
+    ///
+    /// On backgrounds with horizontal lines the row is the space between two lines,
+    /// otherwise it is a band of the default height centered on the coordinate.
+    pub(super) fn row_band_at(y: f64, background: &Background, default_height: f64) -> (f64, f64) {
+        match background.line_spacing() {
+            Some(spacing) => {
+                let top = (y / spacing).floor() * spacing;
+                (top, top + spacing)
+            }
+            None => (y - default_height * 0.5, y + default_height * 0.5),
+        }
+    }
+
+    /// Whether more than half of the height of the bounds is inside the band.
+    ///
+    /// Strokes that only slightly cross the lines of the row, like descenders, are still considered to be in it.
+    pub(super) fn bounds_in_row_band(bounds: Aabb, (top, bottom): (f64, f64)) -> bool {
+        let height = bounds.maxs[1] - bounds.mins[1];
+        if height <= 0.0 {
+            return bounds.mins[1] >= top && bounds.mins[1] <= bottom;
+        }
+        let overlap = bounds.maxs[1].min(bottom) - bounds.mins[1].max(top);
+        overlap > height * 0.5
+    }
+
+    /// The unlocked strokes in the row that contains the position.
+    pub(crate) fn row_strokes(pos: na::Vector2<f64>, engine_view: &EngineView) -> Vec<StrokeKey> {
+        let band = Self::row_band_at(
+            pos[1],
+            &engine_view.document.background,
+            engine_view.pens_config.selector_config.row_band_height,
+        );
+        let doc_bounds = engine_view.document.bounds();
+        let band_bounds = Aabb::new(
+            na::point![doc_bounds.mins[0], band.0],
+            na::point![doc_bounds.maxs[0], band.1],
+        );
+        let keys = engine_view
+            .store
+            .stroke_keys_as_rendered_intersecting_bounds(band_bounds)
+            .into_iter()
+            .filter(|&key| {
+                engine_view
+                    .store
+                    .bounds_for_strokes(&[key])
+                    .is_some_and(|bounds| Self::bounds_in_row_band(bounds, band))
+            })
+            .collect::<Vec<StrokeKey>>();
+        engine_view.store.unlocked_keys(&keys)
+    }
+
+    /// Select the strokes in the row that contains the position, replacing the current selection.
+    ///
+    /// Returns whether strokes were selected.
+    pub(super) fn select_row(
+        &mut self,
+        pos: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+        widget_flags: &mut WidgetFlags,
+    ) -> bool {
+        let row_strokes = Self::row_strokes(pos, &engine_view.as_im());
+        let Some(new_bounds) = engine_view.store.bounds_for_strokes(&row_strokes) else {
+            return false;
+        };
+        if let SelectorState::ModifySelection { selection, .. } = &self.state {
+            engine_view
+                .store
+                .set_selected_keys_keep_order(selection, false);
+        }
+        engine_view.store.set_selected_keys(&row_strokes, true);
+
+        // The pen is still down, it is only lifted before the selection can be modified
+        self.state = SelectorState::ModifySelection {
+            modify_state: ModifyState::AwaitPenUp,
+            selection: row_strokes,
+            selection_bounds: new_bounds,
+            rotation_angle: 0.0,
+        };
+
+        widget_flags.store_modified = true;
+        widget_flags.deselect_color_setters = true;
+        widget_flags.selection_changed = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::background::PatternStyle;
+    use crate::pens::PenStyle;
+    use crate::testsupport::insert_rect;
+    use crate::Engine;
+    use approx::assert_relative_eq;
+    use std::time::Instant;
+
+    fn lines_background(spacing: f64) -> Background {
+        Background {
+            pattern: PatternStyle::Lines,
+            pattern_size: na::vector![spacing, spacing],
+            ..Default::default()
+        }
+    }
+
+    fn bounds(top: f64, bottom: f64) -> Aabb {
+        Aabb::new(na::point![10.0, top], na::point![60.0, bottom])
+    }
+
+    #[test]
+    fn row_band() {
+        let background = lines_background(32.0);
+        let (top, bottom) = Selector::row_band_at(50.0, &background, 100.0);
+        assert_relative_eq!(top, 32.0);
+        assert_relative_eq!(bottom, 64.0);
+        // positions on a line belong to the row below it
+        let (top, bottom) = Selector::row_band_at(64.0, &background, 100.0);
+        assert_relative_eq!(top, 64.0);
+        assert_relative_eq!(bottom, 96.0);
+
+        // without horizontal lines the default height is centered on the position
+        let background = Background {
+            pattern: PatternStyle::Dots,
+            ..Default::default()
+        };
+        let (top, bottom) = Selector::row_band_at(50.0, &background, 20.0);
+        assert_relative_eq!(top, 40.0);
+        assert_relative_eq!(bottom, 60.0);
+    }
+
+    #[test]
+    fn row_band_overlap() {
+        let band = (32.0, 64.0);
+        assert!(Selector::bounds_in_row_band(bounds(38.0, 62.0), band));
+        // descenders that dip below the line
+        assert!(Selector::bounds_in_row_band(bounds(36.0, 72.0), band));
+        // ascenders that reach above the line
+        assert!(Selector::bounds_in_row_band(bounds(26.0, 60.0), band));
+        // strokes that mostly belong to the neighbouring rows
+        assert!(!Selector::bounds_in_row_band(bounds(20.0, 40.0), band));
+        assert!(!Selector::bounds_in_row_band(bounds(58.0, 90.0), band));
+        assert!(!Selector::bounds_in_row_band(bounds(70.0, 90.0), band));
+        // horizontal strokes without height
+        assert!(Selector::bounds_in_row_band(bounds(48.0, 48.0), band));
+        assert!(!Selector::bounds_in_row_band(bounds(70.0, 70.0), band));
+    }
+
+    #[test]
+    fn select_row_at() {
+        let mut engine = Engine::default();
+        engine.document.background = lines_background(32.0);
+        let word = insert_rect(&mut engine.store, bounds(40.0, 60.0));
+        let descender = insert_rect(&mut engine.store, bounds(44.0, 70.0));
+        let next_row = insert_rect(&mut engine.store, bounds(68.0, 92.0));
+        engine.store.record(Instant::now());
+
+        let _ = engine.select_row_at(na::vector![100.0, 50.0]);
+        assert_eq!(engine.penholder.current_pen_style(), PenStyle::Selector);
+        assert_eq!(engine.store.selected(word), Some(true));
+        assert_eq!(engine.store.selected(descender), Some(true));
+        assert_eq!(engine.store.selected(next_row), Some(false));
+    }
+}
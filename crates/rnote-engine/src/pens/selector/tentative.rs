@@ -0,0 +1,76 @@
+// Imports
+use super::Selector;
+use crate::engine::EngineView;
+use crate::store::StrokeKey;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use piet::RenderContext;
+use rnote_compose::color;
+use rnote_compose::ext::AabbExt;
+use rnote_compose::penpath::Element;
+
+/// The strokes that would be selected when the pen is lifted while selecting with a rectangle.
+///
+/// Only used as preview, the selected flags in the store are not touched.
+#[derive(Debug, Clone, Default)]
+pub(super) struct TentativeSelection {
+    pub(super) keys: Vec<StrokeKey>,
+    /// The rectangle the keys were last queried with.
+    rect: Option<Aabb>,
+}
+
+impl Selector {
+    /// The distance a corner of the selecting rectangle needs to move for the tentative selection to be updated,
+    /// in surface coordinates.
+    const TENTATIVE_SELECTION_UPDATE_THRESHOLD: f64 = 4.0;
+    /// The fill color of the bounds of tentatively selected strokes.
+    const TENTATIVE_SELECTION_FILL_COLOR: piet::Color = color::GNOME_BLUES[2].with_a8(28);
+
+    /// Update the tentative selection for the current rectangle selecting path.
+    ///
+    /// The query is skipped when the rectangle hasn't changed meaningfully since the last update.
+    /// Returns true if the tentative selection was updated.
+    pub(super) fn update_tentative_selection(
+        path: &[Element],
+        tentative: &mut TentativeSelection,
+        engine_view: &EngineView,
+    ) -> bool {
+        let (Some(first), Some(last)) = (path.first(), path.last()) else {
+            return false;
+        };
+        let rect = Aabb::new_positive(first.pos.into(), last.pos.into());
+        let threshold =
+            Self::TENTATIVE_SELECTION_UPDATE_THRESHOLD / engine_view.camera.total_zoom();
+        if let Some(prev_rect) = tentative.rect {
+            if (rect.mins - prev_rect.mins).amax() < threshold
+                && (rect.maxs - prev_rect.maxs).amax() < threshold
+            {
+                return false;
+            }
+        }
+
+        tentative.keys = Self::rect_selection_keys(path, engine_view);
+        tentative.rect = Some(rect);
+        true
+    }
+
+    /// Draw a subtle highlight around the bounds of the tentatively selected strokes.
+    pub(super) fn draw_tentative_selection(
+        cx: &mut impl RenderContext,
+        tentative: &TentativeSelection,
+        engine_view: &EngineView,
+    ) {
+        let total_zoom = engine_view.camera.total_zoom();
+
+        for key in tentative.keys.iter() {
+            let Some(bounds) = engine_view.store.bounds_for_strokes(&[*key]) else {
+                continue;
+            };
+            cx.fill(
+                bounds
+                    .loosened(Self::OUTLINE_STROKE_WIDTH / total_zoom)
+                    .to_kurbo_rect(),
+                &Self::TENTATIVE_SELECTION_FILL_COLOR,
+            );
+        }
+    }
+}
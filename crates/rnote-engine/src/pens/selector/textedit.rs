@@ -0,0 +1,83 @@
+// Imports
+use super::{ModifyState, Selector, SelectorState};
+use crate::engine::EngineViewMut;
+use crate::pens::{PenStyle, PenSwitchContext};
+use crate::store::{StrokeKey, StrokeStore};
+use crate::strokes::Stroke;
+use std::time::{Duration, Instant};
+
+/// A click of the pen, to detect double clicks.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Click {
+    pos: na::Vector2<f64>,
+    at: Instant,
+}
+
+impl Click {
+    pub(super) fn new(pos: na::Vector2<f64>, at: Instant) -> Self {
+        Self { pos, at }
+    }
+
+    /// Whether the click at the position forms a double click together with this click.
+    pub(super) fn is_double_click(
+        &self,
+        pos: na::Vector2<f64>,
+        at: Instant,
+        total_zoom: f64,
+    ) -> bool {
+        at.saturating_duration_since(self.at) <= Selector::DOUBLE_CLICK_DURATION
+            && (pos - self.pos).magnitude() <= Selector::DOUBLE_CLICK_RADIUS / total_zoom
+    }
+}
+
+impl Selector {
+    /// The maximum duration between the clicks of a double click.
+    const DOUBLE_CLICK_DURATION: Duration = Duration::from_millis(400);
+    /// The radius the second click of a double click can be away from the first, in surface coordinates.
+    const DOUBLE_CLICK_RADIUS: f64 = 8.0;
+
+    /// The key of the text stroke when the selection consists of exactly one.
+    pub(super) fn selected_text_stroke(
+        selection: &[StrokeKey],
+        store: &StrokeStore,
+    ) -> Option<StrokeKey> {
+        match selection {
+            [key] if matches!(store.get_stroke_ref(*key), Some(Stroke::TextStroke(_))) => {
+                Some(*key)
+            }
+            _ => None,
+        }
+    }
+
+    /// Hand the selected text stroke over to the typewriter for editing, with the cursor placed at the position.
+    ///
+    /// Only possible for a selection of a single text stroke that is currently not modified.
+    /// The selection is relinquished without recording history. Returns whether the switch was requested.
+    pub(super) fn request_text_edit(
+        &mut self,
+        pos: Option<na::Vector2<f64>>,
+        engine_view: &mut EngineViewMut,
+    ) -> bool {
+        let SelectorState::ModifySelection {
+            modify_state: ModifyState::Up | ModifyState::Hover(_),
+            selection,
+            ..
+        } = &self.state
+        else {
+            return false;
+        };
+        let Some(key) = Self::selected_text_stroke(selection, engine_view.store) else {
+            return false;
+        };
+        engine_view
+            .store
+            .set_selected_keys_keep_order(selection, false);
+        self.pen_switch_request = Some((
+            PenStyle::Typewriter,
+            PenSwitchContext::EditText { key, pos },
+        ));
+        self.state = SelectorState::Idle;
+        self.last_click = None;
+        true
+    }
+}
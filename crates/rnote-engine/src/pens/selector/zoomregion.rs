@@ -0,0 +1,82 @@
+// Imports
+use super::Selector;
+use crate::engine::EngineViewMut;
+use crate::{Camera, WidgetFlags};
+use p2d::bounding_volume::Aabb;
+use piet::RenderContext;
+use rnote_compose::color;
+use rnote_compose::ext::AabbExt;
+
+impl Selector {
+    /// The outline color of the zoom region rectangle.
+    const ZOOM_REGION_OUTLINE_COLOR: piet::Color = color::GNOME_ORANGES[3].with_a8(240);
+    /// The fill color of the zoom region rectangle.
+    const ZOOM_REGION_FILL_COLOR: piet::Color = color::GNOME_ORANGES[1].with_a8(13);
+    /// The dash pattern of the zoom region rectangle, in surface coordinates.
+    const ZOOM_REGION_DASH_PATTERN: [f64; 2] = [4.0, 4.0];
+    /// Zoom regions smaller than this on both axes are treated as a click, in surface coordinates.
+    const ZOOM_REGION_CLICK_THRESHOLD: f64 = 8.0;
+    /// The zoom factor applied when clicking instead of drawing a zoom region.
+    const ZOOM_REGION_CLICK_STEP: f64 = 1.5;
+
+    pub(super) fn zoom_region_bounds(start: na::Vector2<f64>, current: na::Vector2<f64>) -> Aabb {
+        Aabb::new_positive(start.into(), current.into())
+    }
+
+    /// Zoom and scroll the camera so that the region fills the viewport.
+    ///
+    /// A tiny region is treated as a click, zooming in by a fixed step centered at the click position.
+    pub(super) fn zoom_to_region(
+        start: na::Vector2<f64>,
+        end: na::Vector2<f64>,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let total_zoom = engine_view.camera.total_zoom();
+        let region = Self::zoom_region_bounds(start, end);
+        let region_extents = region.extents();
+
+        let (new_zoom, new_center) = if (region_extents * total_zoom)
+            .iter()
+            .all(|&e| e < Self::ZOOM_REGION_CLICK_THRESHOLD)
+        {
+            (total_zoom * Self::ZOOM_REGION_CLICK_STEP, end)
+        } else {
+            (
+                engine_view.camera.zoom_fitting_bounds(region, 0.0),
+                region.center().coords,
+            )
+        };
+        let new_zoom = new_zoom.clamp(Camera::ZOOM_MIN, Camera::ZOOM_MAX);
+
+        engine_view
+            .camera
+            .zoom_w_timeout(new_zoom, engine_view.tasks_tx.clone())
+            | engine_view.camera.set_viewport_center(new_center)
+    }
+
+    pub(super) fn draw_zoom_region(
+        cx: &mut impl RenderContext,
+        start: na::Vector2<f64>,
+        current: na::Vector2<f64>,
+        camera: &Camera,
+    ) {
+        let total_zoom = camera.total_zoom();
+        let region_rect = Self::zoom_region_bounds(start, current).to_kurbo_rect();
+
+        let mut stroke_style = piet::StrokeStyle::new();
+        stroke_style.set_dash_pattern(
+            Self::ZOOM_REGION_DASH_PATTERN
+                .into_iter()
+                .map(|x| x / total_zoom)
+                .collect::<Vec<f64>>(),
+        );
+
+        cx.fill(region_rect, &Self::ZOOM_REGION_FILL_COLOR);
+        cx.stroke_styled(
+            region_rect,
+            &Self::ZOOM_REGION_OUTLINE_COLOR,
+            Self::OUTLINE_STROKE_WIDTH / total_zoom,
+            &stroke_style,
+        );
+    }
+}
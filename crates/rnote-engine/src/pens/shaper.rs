@@ -1,4 +1,5 @@
 // Imports
+use super::stabilizer::Stabilizer;
 use super::PenBehaviour;
 use super::PenStyle;
 use crate::engine::{EngineView, EngineViewMut};
@@ -16,7 +17,8 @@ use rnote_compose::builders::{
 use rnote_compose::eventresult::{EventPropagation, EventResult};
 use rnote_compose::penevent::{KeyboardKey, ModifierKey, PenEvent, PenProgress};
 use rnote_compose::penpath::Element;
-use rnote_compose::Shape;
+use rnote_compose::shapes::Shapeable;
+use rnote_compose::{Shape, Style};
 use std::time::Instant;
 
 #[derive(Debug)]
@@ -24,6 +26,10 @@ enum ShaperState {
     Idle,
     BuildShape {
         builder: Box<dyn Buildable<Emit = Shape>>,
+        /// Only present when the input is stabilized.
+        stabilizer: Option<Stabilizer>,
+        /// The pressures of the down events, for the width profile of the outline.
+        pressures: Vec<f64>,
     },
 }
 
@@ -45,7 +51,7 @@ impl PenBehaviour for Shaper {
         WidgetFlags::default()
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
         WidgetFlags::default()
     }
 
@@ -69,12 +75,23 @@ impl PenBehaviour for Shaper {
             (ShaperState::Idle, PenEvent::Down { element, .. }) => {
                 engine_view.pens_config.shaper_config.new_style_seeds();
 
+                let mut stabilizer =
+                    Stabilizer::new(engine_view.pens_config.shaper_config.input_stabilization);
+                let element = match stabilizer.as_mut() {
+                    Some(stabilizer) => {
+                        stabilizer.stabilize(element, engine_view.camera.total_zoom())
+                    }
+                    None => element,
+                };
+
                 self.state = ShaperState::BuildShape {
                     builder: new_builder(
                         engine_view.pens_config.shaper_config.builder_type,
                         element,
                         now,
                     ),
+                    stabilizer,
+                    pressures: captured_pressure(element).into_iter().collect(),
                 };
 
                 EventResult {
@@ -97,7 +114,23 @@ impl PenBehaviour for Shaper {
                     progress: PenProgress::Finished,
                 }
             }
-            (ShaperState::BuildShape { builder }, event) => {
+            (
+                ShaperState::BuildShape {
+                    builder,
+                    stabilizer,
+                    pressures,
+                },
+                event,
+            ) => {
+                let event = match stabilizer.as_mut() {
+                    Some(stabilizer) => {
+                        stabilizer.stabilize_event(event, engine_view.camera.total_zoom())
+                    }
+                    None => event,
+                };
+                if let PenEvent::Down { element, .. } = &event {
+                    pressures.extend(captured_pressure(*element));
+                }
                 // Use Ctrl to temporarily enable/disable constraints when the switch is off/on
                 let mut constraints = engine_view.pens_config.shaper_config.constraints.clone();
                 constraints.enabled = match event {
@@ -126,11 +159,18 @@ impl PenBehaviour for Shaper {
                             .pens_config
                             .shaper_config
                             .gen_style_for_current_options();
+                        // The following shapes start with the pressures after the emitted ones
+                        let pressures = std::mem::take(pressures);
+                        if engine_view.pens_config.shaper_config.capture_pressure {
+                            style.set_width_profile_from_pressures(&pressures);
+                        }
                         let shapes_emitted = !shapes.is_empty();
 
                         for shape in shapes {
+                            let mut shape_style = style.clone();
+                            shape_style.map_fill_to_bounds(shape.bounds());
                             let key = engine_view.store.insert_stroke(
-                                Stroke::ShapeStroke(ShapeStroke::new(shape, style.clone())),
+                                Stroke::ShapeStroke(ShapeStroke::new(shape, shape_style)),
                                 None,
                             );
                             style.advance_seed();
@@ -152,11 +192,16 @@ impl PenBehaviour for Shaper {
                             .pens_config
                             .shaper_config
                             .gen_style_for_current_options();
+                        if engine_view.pens_config.shaper_config.capture_pressure {
+                            style.set_width_profile_from_pressures(pressures);
+                        }
 
                         let shapes_emitted = !shapes.is_empty();
                         for shape in shapes {
+                            let mut shape_style = style.clone();
+                            shape_style.map_fill_to_bounds(shape.bounds());
                             let key = engine_view.store.insert_stroke(
-                                Stroke::ShapeStroke(ShapeStroke::new(shape, style.clone())),
+                                Stroke::ShapeStroke(ShapeStroke::new(shape, shape_style)),
                                 None,
                             );
                             style.advance_seed();
@@ -206,14 +251,18 @@ impl PenBehaviour for Shaper {
 
 impl DrawableOnDoc for Shaper {
     fn bounds_on_doc(&self, engine_view: &EngineView) -> Option<Aabb> {
-        let style = engine_view
+        let mut style = engine_view
             .pens_config
             .shaper_config
             .gen_style_for_current_options();
+        // Gradient fills are relative to the bounds of the finished shape, so the fill is previewed flat.
+        if let Style::Smooth(options) = &mut style {
+            options.set_fill_color(options.fill_color());
+        }
 
         match &self.state {
             ShaperState::Idle => None,
-            ShaperState::BuildShape { builder } => {
+            ShaperState::BuildShape { builder, .. } => {
                 builder.bounds(&style, engine_view.camera.total_zoom())
             }
         }
@@ -225,14 +274,18 @@ impl DrawableOnDoc for Shaper {
         engine_view: &EngineView,
     ) -> anyhow::Result<()> {
         cx.save().map_err(|e| anyhow::anyhow!("{e:?}"))?;
-        let style = engine_view
+        let mut style = engine_view
             .pens_config
             .shaper_config
             .gen_style_for_current_options();
+        // Gradient fills are relative to the bounds of the finished shape, so the fill is previewed flat.
+        if let Style::Smooth(options) = &mut style {
+            options.set_fill_color(options.fill_color());
+        }
 
         match &self.state {
             ShaperState::Idle => {}
-            ShaperState::BuildShape { builder } => {
+            ShaperState::BuildShape { builder, .. } => {
                 builder.draw_styled(cx, &style, engine_view.camera.total_zoom())
             }
         }
@@ -242,6 +295,14 @@ impl DrawableOnDoc for Shaper {
     }
 }
 
+/// The pressure of the element, when it is reported by the input device.
+fn captured_pressure(element: Element) -> Option<f64> {
+    element
+        .source
+        .reports_pressure()
+        .then_some(element.pressure)
+}
+
 fn new_builder(
     builder_type: ShapeBuilderType,
     element: Element,
@@ -0,0 +1,217 @@
+// Imports
+use rnote_compose::penevent::PenEvent;
+use rnote_compose::penpath::Element;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How the positions of the pen input are stabilized, to reduce jitter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename = "input_stabilization")]
+pub enum InputStabilization {
+    /// The input is used as is.
+    #[default]
+    #[serde(rename = "none")]
+    None,
+    /// The positions are averaged over the recent input.
+    #[serde(rename = "moving_average")]
+    MovingAverage {
+        /// The number of recent positions that are averaged.
+        #[serde(rename = "window")]
+        window: usize,
+    },
+    /// The drawn position trails the pen as if pulled on a string,
+    /// only following once the pen is further away than the radius.
+    #[serde(rename = "pull_string")]
+    PullString {
+        /// The length of the string, in surface coordinates.
+        #[serde(rename = "radius", with = "rnote_compose::serialize::f64_dp3")]
+        radius: f64,
+    },
+}
+
+impl InputStabilization {
+    pub const WINDOW_MAX: usize = 64;
+    pub const RADIUS_MAX: f64 = 200.0;
+
+    /// The window, clamped to the valid range.
+    fn window_sanitized(window: usize) -> usize {
+        window.clamp(1, Self::WINDOW_MAX)
+    }
+
+    /// The radius, clamped to the valid range.
+    fn radius_sanitized(radius: f64) -> f64 {
+        if radius.is_finite() {
+            radius.clamp(0.0, Self::RADIUS_MAX)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Stabilizes the positions of the pen input for the duration of a stroke.
+#[derive(Debug)]
+pub(crate) struct Stabilizer {
+    stabilization: InputStabilization,
+    /// The recent raw positions, for the moving average.
+    recent: VecDeque<na::Vector2<f64>>,
+    /// The last stabilized position.
+    last: Option<na::Vector2<f64>>,
+}
+
+impl Stabilizer {
+    /// Returns None when the input is not stabilized.
+    pub(crate) fn new(stabilization: InputStabilization) -> Option<Self> {
+        let capacity = match stabilization {
+            InputStabilization::None => return None,
+            InputStabilization::MovingAverage { window } => {
+                InputStabilization::window_sanitized(window)
+            }
+            InputStabilization::PullString { .. } => 0,
+        };
+        Some(Self {
+            stabilization,
+            recent: VecDeque::with_capacity(capacity),
+            last: None,
+        })
+    }
+
+    /// Replace the position of the element with the stabilized one.
+    ///
+    /// The radius of the pull string is scaled by the zoom, so that it stays the same on the surface.
+    pub(crate) fn stabilize(&mut self, mut element: Element, total_zoom: f64) -> Element {
+        let pos = match self.stabilization {
+            InputStabilization::None => element.pos,
+            InputStabilization::MovingAverage { window } => {
+                if self.recent.len() >= InputStabilization::window_sanitized(window) {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(element.pos);
+                self.recent.iter().sum::<na::Vector2<f64>>() / self.recent.len() as f64
+            }
+            InputStabilization::PullString { radius } => match self.last {
+                None => element.pos,
+                Some(last) => {
+                    let radius =
+                        InputStabilization::radius_sanitized(radius) / total_zoom.max(f64::EPSILON);
+                    let offset = element.pos - last;
+                    let distance = offset.magnitude();
+                    if distance <= radius {
+                        last
+                    } else {
+                        last + offset * ((distance - radius) / distance)
+                    }
+                }
+            },
+        };
+        self.last = Some(pos);
+        element.pos = pos;
+        element
+    }
+
+    /// Stabilize the element of down events.
+    ///
+    /// The element of up events is kept at the raw position, so that strokes end where the pen was lifted.
+    pub(crate) fn stabilize_event(&mut self, event: PenEvent, total_zoom: f64) -> PenEvent {
+        match event {
+            PenEvent::Down {
+                element,
+                modifier_keys,
+                pen_button,
+            } => PenEvent::down(self.stabilize(element, total_zoom), modifier_keys)
+                .with_pen_button(pen_button),
+            PenEvent::Up {
+                element,
+                modifier_keys,
+                pen_button,
+            } => {
+                // Builders that take multiple presses continue from the raw position
+                self.recent.clear();
+                self.recent.push_back(element.pos);
+                self.last = Some(element.pos);
+                PenEvent::up(element, modifier_keys).with_pen_button(pen_button)
+            }
+            event => event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    /// A horizontal line with vertical jitter.
+    fn noisy_path() -> Vec<Element> {
+        let mut rng = rand_pcg::Pcg64::seed_from_u64(42);
+        (0..200)
+            .map(|i| Element::new(na::vector![i as f64, rng.gen_range(-3.0..3.0)], 0.5))
+            .collect()
+    }
+
+    fn variance(elements: &[Element]) -> f64 {
+        let mean = elements.iter().map(|e| e.pos[1]).sum::<f64>() / elements.len() as f64;
+        elements
+            .iter()
+            .map(|e| (e.pos[1] - mean).powi(2))
+            .sum::<f64>()
+            / elements.len() as f64
+    }
+
+    fn stabilize_path(stabilization: InputStabilization, path: &[Element]) -> Vec<Element> {
+        let mut stabilizer = Stabilizer::new(stabilization).unwrap();
+        let (last, path) = path.split_last().unwrap();
+        let mut stabilized = path
+            .iter()
+            .map(|element| {
+                let PenEvent::Down { element, .. } =
+                    stabilizer.stabilize_event(PenEvent::down(*element, HashSet::new()), 2.0)
+                else {
+                    unreachable!()
+                };
+                element
+            })
+            .collect::<Vec<Element>>();
+        let PenEvent::Up { element, .. } =
+            stabilizer.stabilize_event(PenEvent::up(*last, HashSet::new()), 2.0)
+        else {
+            unreachable!()
+        };
+        stabilized.push(element);
+        stabilized
+    }
+
+    #[test]
+    fn stabilization_reduces_jitter() {
+        let path = noisy_path();
+        let raw_variance = variance(&path);
+
+        for stabilization in [
+            InputStabilization::MovingAverage { window: 8 },
+            InputStabilization::PullString { radius: 8.0 },
+        ] {
+            let stabilized = stabilize_path(stabilization, &path);
+            assert_eq!(stabilized.len(), path.len());
+            assert!(
+                variance(&stabilized) < raw_variance * 0.5,
+                "{stabilization:?} does not reduce the jitter"
+            );
+            // the stroke ends exactly where the pen was lifted
+            assert_eq!(stabilized.last().unwrap().pos, path.last().unwrap().pos);
+        }
+        assert!(Stabilizer::new(InputStabilization::None).is_none());
+    }
+
+    #[test]
+    fn pull_string_trails_by_radius() {
+        let mut stabilizer =
+            Stabilizer::new(InputStabilization::PullString { radius: 10.0 }).unwrap();
+        let start = stabilizer.stabilize(Element::new(na::vector![0.0, 0.0], 0.5), 2.0);
+        assert_eq!(start.pos, na::vector![0.0, 0.0]);
+        // within the radius of 5.0 in document coordinates at a zoom of 2.0
+        let inside = stabilizer.stabilize(Element::new(na::vector![4.0, 0.0], 0.5), 2.0);
+        assert_eq!(inside.pos, na::vector![0.0, 0.0]);
+        let pulled = stabilizer.stabilize(Element::new(na::vector![20.0, 0.0], 0.5), 2.0);
+        approx::assert_relative_eq!(pulled.pos, na::vector![15.0, 0.0]);
+    }
+}
@@ -282,7 +282,7 @@ impl PenBehaviour for Tools {
         WidgetFlags::default()
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
         WidgetFlags::default()
     }
 
@@ -413,9 +413,10 @@ impl PenBehaviour for Tools {
                         }
 
                         // possibly nudge camera
-                        widget_flags |= engine_view
-                            .camera
-                            .nudge_w_pos(element.pos, engine_view.document);
+                        widget_flags |=
+                            engine_view
+                                .camera
+                                .nudge_w_pos(element.pos, engine_view.document, None);
                         widget_flags |= engine_view
                             .document
                             .expand_autoexpand(engine_view.camera, engine_view.store);
@@ -4,10 +4,11 @@ mod penevents;
 // Imports
 use super::pensconfig::TypewriterConfig;
 use super::PenBehaviour;
-use super::PenStyle;
+use super::PenSwitchContext;
+use super::{CursorStyle, PenStyle};
 use crate::engine::{EngineTask, EngineView, EngineViewMut};
 use crate::store::StrokeKey;
-use crate::strokes::textstroke::{RangedTextAttribute, TextAttribute, TextStyle};
+use crate::strokes::textstroke::{FontStyle, RangedTextAttribute, TextAttribute, TextStyle};
 use crate::strokes::{Stroke, TextStroke};
 use crate::{AudioPlayer, Camera, DrawableOnDoc, WidgetFlags};
 use futures::channel::oneshot;
@@ -62,6 +63,10 @@ pub struct Typewriter {
     state: TypewriterState,
     blink_task_handle: Option<crate::tasks::PeriodicTaskHandle>,
     cursor_visible: bool,
+    /// Whether the edited text stroke was handed over from the selector,
+    /// pressing Escape then switches back to the selector with the stroke selected.
+    return_to_selector: bool,
+    pen_switch_request: Option<(PenStyle, PenSwitchContext)>,
 }
 
 impl Default for Typewriter {
@@ -70,6 +75,8 @@ impl Default for Typewriter {
             state: TypewriterState::Idle,
             blink_task_handle: None,
             cursor_visible: true,
+            return_to_selector: false,
+            pen_switch_request: None,
         }
     }
 }
@@ -271,7 +278,7 @@ impl PenBehaviour for Typewriter {
         WidgetFlags::default()
     }
 
-    fn deinit(&mut self) -> WidgetFlags {
+    fn deinit(&mut self, _engine_view: &mut EngineViewMut) -> WidgetFlags {
         self.blink_task_handle = None;
         WidgetFlags::default()
     }
@@ -280,6 +287,10 @@ impl PenBehaviour for Typewriter {
         PenStyle::Typewriter
     }
 
+    fn current_cursor(&self, _engine_view: &EngineView) -> CursorStyle {
+        CursorStyle::Text
+    }
+
     fn update_state(&mut self, engine_view: &mut EngineViewMut) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
@@ -360,14 +371,17 @@ impl PenBehaviour for Typewriter {
             PenEvent::Down {
                 element,
                 modifier_keys,
+                ..
             } => self.handle_pen_event_down(element, modifier_keys, now, engine_view),
             PenEvent::Up {
                 element,
                 modifier_keys,
+                ..
             } => self.handle_pen_event_up(element, modifier_keys, now, engine_view),
             PenEvent::Proximity {
                 element,
                 modifier_keys,
+                ..
             } => self.handle_pen_event_proximity(element, modifier_keys, now, engine_view),
             PenEvent::KeyPressed {
                 keyboard_key,
@@ -380,6 +394,10 @@ impl PenBehaviour for Typewriter {
         (event_result, widget_flags)
     }
 
+    fn take_pen_switch_request(&mut self) -> Option<(PenStyle, PenSwitchContext)> {
+        self.pen_switch_request.take()
+    }
+
     fn fetch_clipboard_content(
         &self,
         engine_view: &EngineView,
@@ -552,6 +570,41 @@ impl Typewriter {
     /// The outline color when drawing a text box outline
     const TEXT_OUTLINE_COLOR: piet::Color = color::GNOME_BRIGHTS[4].with_a8(240);
 
+    /// Start editing the text stroke that was handed over from the selector.
+    ///
+    /// The cursor is placed at the position, or at the end of the text when it is None.
+    pub(crate) fn edit_text_stroke(
+        &mut self,
+        stroke_key: StrokeKey,
+        pos: Option<na::Vector2<f64>>,
+        engine_view: &mut EngineViewMut,
+    ) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+        let Some(Stroke::TextStroke(textstroke)) = engine_view.store.get_stroke_ref(stroke_key)
+        else {
+            return widget_flags;
+        };
+        let cursor = pos
+            .and_then(|pos| textstroke.get_cursor_for_global_coord(pos).ok())
+            .unwrap_or_else(|| {
+                GraphemeCursor::new(textstroke.text.len(), textstroke.text.len(), true)
+            });
+
+        engine_view.store.update_chrono_to_last(stroke_key);
+        self.state = TypewriterState::Modifying {
+            modify_state: ModifyState::Up,
+            stroke_key,
+            cursor,
+            pen_down: false,
+        };
+        self.return_to_selector = true;
+        self.reset_blink();
+
+        widget_flags |= self.update_state(engine_view);
+        widget_flags.refresh_ui = true;
+        widget_flags
+    }
+
     pub(crate) fn toggle_cursor_visibility(&mut self) {
         self.cursor_visible = !self.cursor_visible;
     }
@@ -789,6 +842,16 @@ impl Typewriter {
         widget_flags
     }
 
+    /// The text attribute that is toggled on the selection by the keyboard shortcut Ctrl and the key.
+    pub fn shortcut_text_attribute(keychar: char) -> Option<TextAttribute> {
+        match keychar {
+            'b' => Some(TextAttribute::FontWeight(piet::FontWeight::BOLD.to_raw())),
+            'i' => Some(TextAttribute::Style(FontStyle::Italic)),
+            'u' => Some(TextAttribute::Underline(true)),
+            _ => None,
+        }
+    }
+
     pub(crate) fn toggle_text_attribute_current_selection(
         &mut self,
         text_attribute: TextAttribute,
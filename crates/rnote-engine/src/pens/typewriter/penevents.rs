@@ -1,7 +1,7 @@
 // Imports
 use super::{ModifyState, Typewriter, TypewriterState};
 use crate::engine::EngineViewMut;
-use crate::pens::PenBehaviour;
+use crate::pens::{PenBehaviour, PenStyle, PenSwitchContext};
 use crate::strokes::{Stroke, TextStroke};
 use crate::{DrawableOnDoc, StrokeStore, WidgetFlags};
 use rnote_compose::eventresult::{EventPropagation, EventResult};
@@ -32,7 +32,7 @@ impl Typewriter {
 
                 if let Some(&stroke_key) = engine_view
                     .store
-                    .stroke_hitboxes_contain_coord(engine_view.camera.viewport(), element.pos)
+                    .stroke_hitboxes_contain_coord(element.pos, false)
                     .last()
                 {
                     // When clicked on a textstroke, we start modifying it
@@ -72,9 +72,10 @@ impl Typewriter {
                 }
 
                 // possibly nudge camera
-                widget_flags |= engine_view
-                    .camera
-                    .nudge_w_pos(element.pos, engine_view.document);
+                widget_flags |=
+                    engine_view
+                        .camera
+                        .nudge_w_pos(element.pos, engine_view.document, None);
                 widget_flags |= engine_view
                     .document
                     .expand_autoexpand(engine_view.camera, engine_view.store);
@@ -258,9 +259,11 @@ impl Typewriter {
                             }
 
                             // possibly nudge camera
-                            widget_flags |= engine_view
-                                .camera
-                                .nudge_w_pos(element.pos, engine_view.document);
+                            widget_flags |= engine_view.camera.nudge_w_pos(
+                                element.pos,
+                                engine_view.document,
+                                None,
+                            );
                             widget_flags |= engine_view
                                 .document
                                 .expand_autoexpand(engine_view.camera, engine_view.store);
@@ -484,10 +487,34 @@ impl Typewriter {
         engine_view: &mut EngineViewMut,
     ) -> (EventResult<PenProgress>, WidgetFlags) {
         let mut widget_flags = WidgetFlags::default();
+        let mut toggle_text_attribute = None;
 
         let text_width = engine_view.pens_config.typewriter_config.text_width();
         let mut text_style = engine_view.pens_config.typewriter_config.text_style.clone();
 
+        if let TypewriterState::Modifying { stroke_key, .. } = &self.state {
+            if self.return_to_selector
+                && keyboard_key == KeyboardKey::Escape
+                && modifier_keys.is_empty()
+            {
+                // Return to the selector the text stroke was handed over from
+                self.pen_switch_request = Some((
+                    PenStyle::Selector,
+                    PenSwitchContext::Select(vec![*stroke_key]),
+                ));
+                self.state = TypewriterState::Idle;
+
+                return (
+                    EventResult {
+                        handled: true,
+                        propagate: EventPropagation::Stop,
+                        progress: PenProgress::Finished,
+                    },
+                    widget_flags,
+                );
+            }
+        }
+
         let event_result = match &mut self.state {
             TypewriterState::Idle => EventResult {
                 handled: false,
@@ -851,6 +878,13 @@ impl Typewriter {
                                         textstroke
                                             .update_selection_entire_text(cursor, selection_cursor);
                                         *finished = true;
+                                    } else if let Some(text_attribute) = modifier_keys
+                                        .contains(&ModifierKey::KeyboardCtrl)
+                                        .then(|| Self::shortcut_text_attribute(keychar))
+                                        .flatten()
+                                    {
+                                        // Toggled once the state is not borrowed anymore
+                                        toggle_text_attribute = Some(text_attribute);
                                     } else {
                                         textstroke.replace_text_between_selection_cursors(
                                             cursor,
@@ -1043,6 +1077,10 @@ impl Typewriter {
             }
         };
 
+        if let Some(text_attribute) = toggle_text_attribute {
+            widget_flags |=
+                self.toggle_text_attribute_current_selection(text_attribute, engine_view);
+        }
         self.reset_blink();
 
         (event_result, widget_flags)
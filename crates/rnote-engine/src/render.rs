@@ -186,6 +186,10 @@ impl Transformable for Image {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.rect.scale(scale)
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.rect.shear(shear, pivot)
+    }
 }
 
 impl Image {
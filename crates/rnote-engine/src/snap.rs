@@ -7,10 +7,14 @@ pub enum SnapCorner {
     TopRight,
     BottomLeft,
     BottomRight,
+    /// The center of the bounds.
+    Center,
 }
 
 impl SnapCorner {
     /// Determine the corner for the position to snap to depending on to which corner of the bounds it is closest to.
+    ///
+    /// Never determines the center.
     pub fn determine_from_bounds(bounds: Aabb, pos: na::Vector2<f64>) -> Self {
         let dist_left = (pos[0] - bounds.mins[0]).abs();
         let dist_right = (pos[0] - bounds.maxs[0]).abs();
@@ -27,4 +31,15 @@ impl SnapCorner {
             (false, false) => Self::BottomRight,
         }
     }
+
+    /// The position of the corner on the bounds.
+    pub fn pos_on_bounds(self, bounds: Aabb) -> na::Vector2<f64> {
+        match self {
+            Self::TopLeft => bounds.mins.coords,
+            Self::TopRight => na::vector![bounds.maxs[0], bounds.mins[1]],
+            Self::BottomLeft => na::vector![bounds.mins[0], bounds.maxs[1]],
+            Self::BottomRight => bounds.maxs.coords,
+            Self::Center => bounds.center().coords,
+        }
+    }
 }
@@ -1,10 +1,14 @@
 // Imports
 use super::{StrokeKey, StrokeStore};
+use crate::strokes::Stroke;
 use p2d::bounding_volume::Aabb;
 use rayon::slice::ParallelSliceMut;
 use serde::{Deserialize, Serialize};
+use slotmap::HopSlotMap;
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Instant;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq)]
 #[serde(rename = "stroke_layer")]
@@ -68,6 +72,16 @@ pub struct ChronoComponent {
     t: u32,
     #[serde(rename = "layer")]
     pub layer: StrokeLayer,
+    /// When the stroke was created, as unix timestamp in milliseconds.
+    ///
+    /// None for strokes loaded from files that predate the timestamps.
+    #[serde(rename = "created", skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    /// When the stroke was last modified, as unix timestamp in milliseconds.
+    ///
+    /// Updated when the modification is recorded in the history.
+    #[serde(rename = "modified", skip_serializing_if = "Option::is_none")]
+    pub modified: Option<i64>,
 }
 
 impl Default for ChronoComponent {
@@ -75,13 +89,32 @@ impl Default for ChronoComponent {
         Self {
             t: 0,
             layer: StrokeLayer::default(),
+            created: None,
+            modified: None,
         }
     }
 }
 
 impl ChronoComponent {
+    /// A new component for a stroke that is created now.
     pub(crate) fn new(t: u32, layer: StrokeLayer) -> Self {
-        Self { t, layer }
+        let now = Self::now_timestamp();
+        Self {
+            t,
+            layer,
+            created: Some(now),
+            modified: Some(now),
+        }
+    }
+
+    /// The order in which the strokes are rendered, by layer first and then by the chrono time.
+    pub(crate) fn render_order(&self) -> (StrokeLayer, u32) {
+        (self.layer, self.t)
+    }
+
+    /// The current time as unix timestamp in milliseconds.
+    pub(crate) fn now_timestamp() -> i64 {
+        chrono::Utc::now().timestamp_millis()
     }
 }
 
@@ -94,6 +127,181 @@ impl StrokeStore {
         }
     }
 
+    /// Move the strokes for the given keys to the front, so that they are drawn last.
+    ///
+    /// The relative order within the moved strokes is preserved.
+    pub(crate) fn move_strokes_to_front(&mut self, keys: &[StrokeKey]) {
+        let moved = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        let (moved_keys, mut order): (Vec<StrokeKey>, Vec<StrokeKey>) = self
+            .keys_sorted_chrono()
+            .into_iter()
+            .partition(|key| moved.contains(key));
+        order.extend(moved_keys);
+        self.renumber_chrono(&order);
+    }
+
+    /// Move the strokes for the given keys to the back, so that they are drawn first.
+    ///
+    /// The relative order within the moved strokes is preserved.
+    pub(crate) fn move_strokes_to_back(&mut self, keys: &[StrokeKey]) {
+        let moved = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        let (mut order, not_moved_keys): (Vec<StrokeKey>, Vec<StrokeKey>) = self
+            .keys_sorted_chrono()
+            .into_iter()
+            .partition(|key| moved.contains(key));
+        order.extend(not_moved_keys);
+        self.renumber_chrono(&order);
+    }
+
+    /// Move the strokes for the given keys directly behind the bottommost of the strokes for the other given keys.
+    ///
+    /// The relative order within the moved strokes is preserved.
+    /// Nothing is moved when none of the other strokes exist.
+    pub(crate) fn move_strokes_behind(&mut self, keys: &[StrokeKey], behind: &[StrokeKey]) {
+        let moved = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        let behind = behind.iter().copied().collect::<HashSet<StrokeKey>>();
+        let (moved_keys, mut order): (Vec<StrokeKey>, Vec<StrokeKey>) = self
+            .keys_sorted_chrono()
+            .into_iter()
+            .partition(|key| moved.contains(key));
+        let Some(i) = order.iter().position(|key| behind.contains(key)) else {
+            return;
+        };
+        order.splice(i..i, moved_keys);
+        self.renumber_chrono(&order);
+    }
+
+    /// Raise the strokes for the given keys by one step, above the next stroke in the same layer.
+    ///
+    /// The relative order within the raised strokes is preserved.
+    pub(crate) fn raise_strokes(&mut self, keys: &[StrokeKey]) {
+        let moved = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        let mut order = self.keys_sorted_chrono();
+
+        for i in (0..order.len().saturating_sub(1)).rev() {
+            if moved.contains(&order[i])
+                && !moved.contains(&order[i + 1])
+                && self.same_layer(order[i], order[i + 1])
+            {
+                order.swap(i, i + 1);
+            }
+        }
+        self.renumber_chrono(&order);
+    }
+
+    /// Lower the strokes for the given keys by one step, below the previous stroke in the same layer.
+    ///
+    /// The relative order within the lowered strokes is preserved.
+    pub(crate) fn lower_strokes(&mut self, keys: &[StrokeKey]) {
+        let moved = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        let mut order = self.keys_sorted_chrono();
+
+        for i in 1..order.len() {
+            if moved.contains(&order[i])
+                && !moved.contains(&order[i - 1])
+                && self.same_layer(order[i], order[i - 1])
+            {
+                order.swap(i, i - 1);
+            }
+        }
+        self.renumber_chrono(&order);
+    }
+
+    /// Reorder the strokes for the given keys by their creation time, the oldest drawn first.
+    ///
+    /// The strokes take the places in the z-order that the given strokes occupied,
+    /// so their order relative to the other strokes is preserved.
+    /// Strokes without a creation time are treated as the oldest.
+    pub(crate) fn sort_strokes_by_creation(&mut self, keys: &[StrokeKey]) {
+        let sorted = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        let mut order = self.keys_sorted_chrono();
+        let slots = order
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| sorted.contains(key))
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+        let mut by_creation = slots.iter().map(|&i| order[i]).collect::<Vec<StrokeKey>>();
+        // stable, so strokes created at the same time keep their order
+        by_creation.sort_by_key(|&key| self.chrono_components.get(key).and_then(|c| c.created));
+        for (&slot, key) in slots.iter().zip(by_creation) {
+            order[slot] = key;
+        }
+        self.renumber_chrono(&order);
+    }
+
+    /// The keys of the strokes that were created within the time range, ordered by their creation.
+    ///
+    /// The range is given as unix timestamps in milliseconds, including the start and excluding the end.
+    /// Trashed strokes and strokes without a creation time are excluded.
+    pub(crate) fn strokes_in_time_range(&self, start: i64, end: i64) -> Vec<StrokeKey> {
+        let mut keys = self
+            .chrono_components
+            .iter()
+            .filter_map(|(key, chrono_comp)| Some((key, chrono_comp.created?, chrono_comp.t)))
+            .filter(|&(key, created, _)| {
+                (start..end).contains(&created)
+                    && self.stroke_components.contains_key(key)
+                    && !self.trashed(key).unwrap_or(true)
+            })
+            .collect::<Vec<(StrokeKey, i64, u32)>>();
+        keys.sort_unstable_by_key(|&(_, created, t)| (created, t));
+        keys.into_iter().map(|(key, _, _)| key).collect()
+    }
+
+    /// Update the modification time of the strokes that were modified compared to the history entry.
+    pub(super) fn touch_modified_strokes(
+        &mut self,
+        history_entry_stroke_components: &HopSlotMap<StrokeKey, Arc<Stroke>>,
+        now: i64,
+    ) {
+        let modified = self
+            .stroke_components
+            .iter()
+            .filter(|(key, stroke)| {
+                history_entry_stroke_components
+                    .get(*key)
+                    .is_some_and(|prev| !Arc::ptr_eq(prev, stroke))
+            })
+            .map(|(key, _)| key)
+            .collect::<Vec<StrokeKey>>();
+        if modified.is_empty() {
+            return;
+        }
+        let chrono_components = Arc::make_mut(&mut self.chrono_components);
+        for key in modified {
+            if let Some(chrono_comp) = chrono_components.get_mut(key) {
+                Arc::make_mut(chrono_comp).modified = Some(now);
+            }
+        }
+    }
+
+    fn same_layer(&self, first: StrokeKey, second: StrokeKey) -> bool {
+        match (
+            self.chrono_components.get(first),
+            self.chrono_components.get(second),
+        ) {
+            (Some(first), Some(second)) => first.layer == second.layer,
+            _ => false,
+        }
+    }
+
+    /// Reassign the chrono components so that the strokes are drawn in the given order.
+    ///
+    /// The layers are left untouched, so they still take precedence over the given order.
+    fn renumber_chrono(&mut self, order: &[StrokeKey]) {
+        let chrono_components = Arc::make_mut(&mut self.chrono_components);
+        let mut t = 0;
+
+        for &key in order {
+            if let Some(chrono_comp) = chrono_components.get_mut(key) {
+                t += 1;
+                Arc::make_mut(chrono_comp).t = t;
+            }
+        }
+        self.chrono_counter = t;
+    }
+
     /// Returns the keys in chronological order, as in first: gets drawn first, last: gets drawn last.
     pub(crate) fn keys_sorted_chrono(&self) -> Vec<StrokeKey> {
         let chrono_components = &self.chrono_components;
@@ -166,4 +374,50 @@ impl StrokeStore {
 
         keys
     }
+
+    /// The selectable keys of the strokes inserted during the current session, ordered by their creation,
+    /// as in first: created first, last: created last.
+    ///
+    /// Trashed and locked strokes are excluded.
+    fn selectable_keys_sorted_creation(&self) -> Vec<StrokeKey> {
+        let mut keys = self
+            .creation_instants
+            .iter()
+            .filter(|&(key, _)| {
+                self.stroke_components.contains_key(key)
+                    && !self.trashed(key).unwrap_or(true)
+                    && !self.locked(key).unwrap_or(false)
+            })
+            .map(|(key, &instant)| (key, instant))
+            .collect::<Vec<(StrokeKey, Instant)>>();
+
+        keys.sort_unstable_by(|(first_key, first_instant), (second_key, second_instant)| {
+            first_instant.cmp(second_instant).then_with(|| {
+                self.chrono_components
+                    .get(*first_key)
+                    .map(|c| c.t)
+                    .cmp(&self.chrono_components.get(*second_key).map(|c| c.t))
+            })
+        });
+
+        keys.into_iter().map(|(key, _)| key).collect()
+    }
+
+    /// The keys of the `n` most recently created selectable strokes.
+    pub(crate) fn last_created_keys(&self, n: usize) -> Vec<StrokeKey> {
+        let keys = self.selectable_keys_sorted_creation();
+        keys[keys.len().saturating_sub(n)..].to_vec()
+    }
+
+    /// The keys of the selectable strokes created at or after the given instant.
+    pub(crate) fn keys_created_since(&self, instant: Instant) -> Vec<StrokeKey> {
+        self.selectable_keys_sorted_creation()
+            .into_iter()
+            .filter(|&key| {
+                self.creation_instants
+                    .get(key)
+                    .is_some_and(|&created| created >= instant)
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,291 @@
+// Imports
+use super::render_comp::RENDER_IMAGE_SCALE_TOLERANCE;
+use super::{StrokeKey, StrokeStore};
+use crate::render;
+use p2d::bounding_volume::Aabb;
+use rnote_compose::Transform;
+use std::collections::HashSet;
+use tracing::error;
+
+/// The strokes of a dragged selection, rendered once into a single image.
+///
+/// While the composite is active the strokes are left untouched and hidden from the normal draw path.
+/// Instead the image is drawn with the accumulated transform of the drag,
+/// so that the work per drag event doesn't depend on the number of dragged strokes.
+#[derive(Debug, Clone)]
+pub(crate) struct SelectionComposite {
+    keys: Vec<StrokeKey>,
+    hidden: HashSet<StrokeKey>,
+    /// The bounds of the strokes when the drag started.
+    bounds: Aabb,
+    /// The image scale the composite was requested with.
+    ///
+    /// The image might be rendered with a lower scale so that it doesn't exceed [SelectionComposite::PIXELS_MAX].
+    image_scale: f64,
+    rendering: CompositeRendering,
+    /// The accumulated translations and rotations of the drag.
+    isometry: na::Isometry2<f64>,
+    /// How often the image was rendered.
+    renders: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CompositeRendering {
+    image: render::Image,
+    #[cfg(feature = "ui")]
+    rendernode: gtk4::gsk::RenderNode,
+}
+
+impl SelectionComposite {
+    /// The maximum number of pixels of the image.
+    const PIXELS_MAX: f64 = 4096.0 * 2048.0;
+
+    /// The transform from the positions of the strokes to where they are drawn.
+    pub(crate) fn transform(&self) -> Transform {
+        Transform::new_w_isometry(self.isometry)
+    }
+
+    /// The bounds of the transformed strokes.
+    pub(crate) fn bounds(&self) -> Aabb {
+        self.transform().transform_aabb(self.bounds)
+    }
+
+    pub(crate) fn keys(&self) -> &[StrokeKey] {
+        &self.keys
+    }
+
+    /// Whether the stroke is drawn by the composite.
+    pub(crate) fn hides(&self, key: StrokeKey) -> bool {
+        self.hidden.contains(&key)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn renders(&self) -> usize {
+        self.renders
+    }
+
+    /// The size of the data of the image, in bytes.
+    pub(crate) fn image_size(&self) -> usize {
+        self.rendering.image.data.len()
+    }
+
+    #[cfg(feature = "ui")]
+    pub(crate) fn draw_to_gtk_snapshot(&self, snapshot: &gtk4::Snapshot) {
+        use gtk4::prelude::*;
+
+        snapshot.save();
+        snapshot.transform(Some(&crate::utils::transform_to_gsk(&self.transform())));
+        snapshot.append_node(&self.rendering.rendernode);
+        snapshot.restore();
+    }
+}
+
+impl StrokeStore {
+    /// Render the strokes into a composite that is dragged instead of the strokes,
+    /// until it is committed with [StrokeStore::commit_selection_composite].
+    ///
+    /// Replaces a composite that is still active.
+    pub(crate) fn begin_selection_composite(&mut self, keys: &[StrokeKey], image_scale: f64) {
+        self.selection_composite = None;
+        let Some(bounds) = self.bounds_for_strokes(keys) else {
+            return;
+        };
+        match self.render_selection_composite(keys, bounds, image_scale) {
+            Ok(rendering) => {
+                self.selection_composite = Some(SelectionComposite {
+                    keys: keys.to_vec(),
+                    hidden: keys.iter().copied().collect(),
+                    bounds,
+                    image_scale,
+                    rendering,
+                    isometry: na::Isometry2::identity(),
+                    renders: 1,
+                });
+            }
+            Err(e) => {
+                // The strokes are dragged by themselves instead
+                error!("Rendering the selection composite failed, Err: {e:?}");
+            }
+        }
+    }
+
+    fn render_selection_composite(
+        &self,
+        keys: &[StrokeKey],
+        bounds: Aabb,
+        image_scale: f64,
+    ) -> anyhow::Result<CompositeRendering> {
+        let extents = bounds.extents();
+        let pixels = extents[0] * extents[1] * image_scale * image_scale;
+        let image_scale = if pixels > SelectionComposite::PIXELS_MAX {
+            image_scale * (SelectionComposite::PIXELS_MAX / pixels).sqrt()
+        } else {
+            image_scale
+        };
+        let keys = self.keys_sorted_chrono_of(keys);
+        let image = render::Image::gen_with_piet(
+            |piet_cx| self.draw_keys_immediate(&keys, piet_cx, image_scale),
+            bounds,
+            image_scale,
+        )?;
+        Ok(CompositeRendering {
+            #[cfg(feature = "ui")]
+            rendernode: image.to_rendernode()?,
+            image,
+        })
+    }
+
+    /// The strokes of the keys in the order they are rendered.
+    fn keys_sorted_chrono_of(&self, keys: &[StrokeKey]) -> Vec<StrokeKey> {
+        let keys = keys.iter().copied().collect::<HashSet<StrokeKey>>();
+        self.keys_sorted_chrono()
+            .into_iter()
+            .filter(|key| keys.contains(key))
+            .collect()
+    }
+
+    pub(crate) fn selection_composite(&self) -> Option<&SelectionComposite> {
+        self.selection_composite.as_ref()
+    }
+
+    /// Translate the active composite.
+    ///
+    /// Returns false when there is no composite, the strokes then need to be translated themselves.
+    pub(crate) fn translate_selection_composite(&mut self, offset: na::Vector2<f64>) -> bool {
+        let Some(composite) = self.selection_composite.as_mut() else {
+            return false;
+        };
+        composite
+            .isometry
+            .append_translation_mut(&na::Translation2::from(offset));
+        true
+    }
+
+    /// Rotate the active composite around the center.
+    ///
+    /// Returns false when there is no composite, the strokes then need to be rotated themselves.
+    pub(crate) fn rotate_selection_composite(
+        &mut self,
+        angle: f64,
+        center: na::Point2<f64>,
+    ) -> bool {
+        let Some(composite) = self.selection_composite.as_mut() else {
+            return false;
+        };
+        composite
+            .isometry
+            .append_rotation_wrt_point_mut(&na::UnitComplex::new(angle), &center);
+        true
+    }
+
+    /// Render the active composite again when the image scale changed, e.g. when zooming during the drag.
+    pub(crate) fn refresh_selection_composite(&mut self, image_scale: f64) {
+        let Some(composite) = self.selection_composite.as_ref() else {
+            return;
+        };
+        if (composite.image_scale - image_scale).abs() <= RENDER_IMAGE_SCALE_TOLERANCE {
+            return;
+        }
+        match self.render_selection_composite(&composite.keys, composite.bounds, image_scale) {
+            Ok(rendering) => {
+                if let Some(composite) = self.selection_composite.as_mut() {
+                    composite.image_scale = image_scale;
+                    composite.rendering = rendering;
+                    composite.renders += 1;
+                }
+            }
+            Err(e) => {
+                // The outdated image is still drawn, scaled
+                error!("Rendering the selection composite again failed, Err: {e:?}");
+            }
+        }
+    }
+
+    /// Drop the active composite and apply its transform to the strokes.
+    ///
+    /// The images of the strokes are transformed as well, the caller is responsible for regenerating the rendering.
+    /// Returns the keys of the transformed strokes.
+    pub(crate) fn commit_selection_composite(&mut self) -> Vec<StrokeKey> {
+        let Some(composite) = self.selection_composite.take() else {
+            return vec![];
+        };
+        // The isometry is a rotation around the origin, followed by a translation
+        let angle = composite.isometry.rotation.angle();
+        let offset = composite.isometry.translation.vector;
+        if angle != 0.0 {
+            self.rotate_strokes(&composite.keys, angle, na::Point2::origin());
+            self.rotate_strokes_images(&composite.keys, angle, na::Point2::origin());
+        }
+        if offset != na::Vector2::zeros() {
+            self.translate_strokes(&composite.keys, offset);
+            self.translate_strokes_images(&composite.keys, offset);
+        }
+        composite.keys
+    }
+
+    /// Drop the active composite without modifying the strokes, e.g. when the drag was cancelled.
+    pub(crate) fn discard_selection_composite(&mut self) {
+        self.selection_composite = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::insert_rect;
+    use approx::assert_relative_eq;
+    use std::sync::Arc;
+
+    fn assert_point_eq(a: na::Point2<f64>, b: na::Point2<f64>) {
+        assert_relative_eq!(a[0], b[0], epsilon = 1e-6);
+        assert_relative_eq!(a[1], b[1], epsilon = 1e-6);
+    }
+
+    #[test]
+    fn composite_commit_applies_transform() {
+        let mut store = StrokeStore::default();
+        let keys = (0..4)
+            .map(|i| {
+                insert_rect(
+                    &mut store,
+                    Aabb::new(
+                        na::point![i as f64 * 20.0, 0.0],
+                        na::point![i as f64 * 20.0 + 10.0, 10.0],
+                    ),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        let bounds = store.bounds_for_strokes(&keys).unwrap();
+        let center = bounds.center();
+
+        store.begin_selection_composite(&keys, 1.0);
+        let strokes = store.get_strokes_arc(&keys);
+        for _ in 0..10 {
+            assert!(store.translate_selection_composite(na::vector![5.0, 0.0]));
+        }
+        let rotation_center = center + na::vector![50.0, 0.0];
+        assert!(store.rotate_selection_composite(std::f64::consts::PI, rotation_center));
+        store.refresh_selection_composite(1.0);
+        // the strokes stay untouched during the drag
+        assert!(store
+            .get_strokes_arc(&keys)
+            .iter()
+            .zip(strokes.iter())
+            .all(|(a, b)| Arc::ptr_eq(a, b)));
+        assert_eq!(store.selection_composite().unwrap().renders(), 1);
+        let composite_bounds = store.selection_composite().unwrap().bounds();
+
+        // zooming renders the composite again
+        store.refresh_selection_composite(2.0);
+        assert_eq!(store.selection_composite().unwrap().renders(), 2);
+
+        assert_eq!(store.commit_selection_composite().len(), keys.len());
+        assert!(store.selection_composite().is_none());
+        store.update_geometry_for_strokes(&keys);
+        let committed_bounds = store.bounds_for_strokes(&keys).unwrap();
+        // translated by 50 to the rotation center, then rotated by half a turn around it
+        assert_point_eq(committed_bounds.center(), center + na::vector![50.0, 0.0]);
+        assert_point_eq(committed_bounds.mins, composite_bounds.mins);
+        assert_point_eq(committed_bounds.maxs, composite_bounds.maxs);
+    }
+}
@@ -10,19 +10,26 @@ type KeyTreeObject = GeomWithData<rstar::primitives::Rectangle<[f64; 2]>, Stroke
 /// A Rtree with [StrokeKey]'s as associated data.
 ///
 /// Used for faster spatial queries.
-pub(super) struct KeyTree(rstar::RTree<KeyTreeObject, rstar::DefaultParams>);
+pub(super) struct KeyTree {
+    tree: rstar::RTree<KeyTreeObject, rstar::DefaultParams>,
+    /// The bounds every key was inserted with, so that its tree object can be located without scanning the tree.
+    bounds: slotmap::SecondaryMap<StrokeKey, Aabb>,
+}
 
 impl KeyTree {
     /// Insert a new tree object with the given [StrokeKey] and bounds.
+    ///
+    /// An already present tree object for the key is replaced.
     pub(crate) fn insert_with_key(&mut self, key: StrokeKey, bounds: Aabb) {
-        self.0.insert(new_keytree_object(key, bounds));
+        self.remove_with_key(key);
+        self.tree.insert(new_keytree_object(key, bounds));
+        self.bounds.insert(key, bounds);
     }
 
     /// Removes the [KeyTreeObject] for the given key.
     pub(crate) fn remove_with_key(&mut self, key: StrokeKey) -> Option<KeyTreeObject> {
-        let object_to_remove = self.0.iter().find(|&object| object.data == key)?.to_owned();
-
-        self.0.remove(&object_to_remove)
+        let bounds = self.bounds.remove(key)?;
+        self.tree.remove(&new_keytree_object(key, bounds))
     }
 
     /// Update the Tree with new bounds for the given key.
@@ -35,7 +42,7 @@ impl KeyTree {
 
     /// Return the keys that intersect with the given bounds.
     pub(crate) fn keys_intersecting_bounds(&self, bounds: Aabb) -> Vec<StrokeKey> {
-        self.0
+        self.tree
             .locate_in_envelope_intersecting(&rstar::AABB::from_corners(
                 [bounds.mins[0], bounds.mins[1]],
                 [bounds.maxs[0], bounds.maxs[1]],
@@ -46,7 +53,7 @@ impl KeyTree {
 
     /// Return the keys that are completely contained in the given bounds.
     pub(crate) fn keys_in_bounds(&self, bounds: Aabb) -> Vec<StrokeKey> {
-        self.0
+        self.tree
             .locate_in_envelope(&rstar::AABB::from_corners(
                 [bounds.mins[0], bounds.mins[1]],
                 [bounds.maxs[0], bounds.maxs[1]],
@@ -57,12 +64,19 @@ impl KeyTree {
 
     /// Rebuild the entire rtree from the given Vec of (key, bounds).
     pub(crate) fn rebuild_from_vec(&mut self, strokes: Vec<(StrokeKey, Aabb)>) {
+        self.bounds = strokes.iter().copied().collect();
         let objects = strokes
             .into_iter()
             .map(|(key, bounds)| new_keytree_object(key, bounds))
             .collect();
 
-        self.0 = rstar::RTree::bulk_load(objects);
+        self.tree = rstar::RTree::bulk_load(objects);
+    }
+
+    /// The number of tree objects.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.tree.size()
     }
 
     ///  Clear the entire tree.
@@ -0,0 +1,75 @@
+// Imports
+use super::{StrokeKey, StrokeStore};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename = "lock_component")]
+pub struct LockComponent {
+    #[serde(rename = "locked")]
+    pub locked: bool,
+}
+
+impl Default for LockComponent {
+    fn default() -> Self {
+        Self { locked: false }
+    }
+}
+
+/// Systems that are related to locking.
+///
+/// Locked strokes are ignored by the hit-testing used for selecting and erasing, but are rendered and exported normally.
+impl StrokeStore {
+    /// Rebuild the slotmap with the keys returned from the stroke components.
+    ///
+    /// Existing lock components are retained, missing ones are inserted unlocked.
+    pub(crate) fn rebuild_lock_components_slotmap(&mut self) {
+        let lock_components = Arc::make_mut(&mut self.lock_components);
+        lock_components.retain(|key, _| self.stroke_components.contains_key(key));
+        self.stroke_components.keys().for_each(|key| {
+            if !lock_components.contains_key(key) {
+                lock_components.insert(key, Arc::new(LockComponent::default()));
+            }
+        });
+    }
+
+    pub(crate) fn locked(&self, key: StrokeKey) -> Option<bool> {
+        self.lock_components.get(key).map(|l| l.locked)
+    }
+
+    pub(crate) fn set_locked(&mut self, key: StrokeKey, locked: bool) {
+        if let Some(lock_comp) = Arc::make_mut(&mut self.lock_components)
+            .get_mut(key)
+            .map(Arc::make_mut)
+        {
+            lock_comp.locked = locked;
+        }
+    }
+
+    /// Lock or unlock the strokes.
+    ///
+    /// Locked strokes can't stay selected, so they get deselected.
+    pub(crate) fn set_strokes_locked(&mut self, keys: &[StrokeKey], locked: bool) {
+        keys.iter().for_each(|&key| {
+            if locked {
+                self.set_selected_keep_order(key, false);
+            }
+            self.set_locked(key, locked);
+        });
+    }
+
+    /// Filter out the locked keys.
+    pub(crate) fn unlocked_keys(&self, keys: &[StrokeKey]) -> Vec<StrokeKey> {
+        keys.iter()
+            .copied()
+            .filter(|&key| !self.locked(key).unwrap_or(false))
+            .collect()
+    }
+
+    pub(crate) fn locked_keys_unordered(&self) -> Vec<StrokeKey> {
+        self.stroke_components
+            .keys()
+            .filter(|&key| self.locked(key).unwrap_or(false))
+            .collect()
+    }
+}
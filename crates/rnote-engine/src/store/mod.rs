@@ -1,35 +1,77 @@
 // Modules
 pub mod chrono_comp;
+pub mod composite;
 pub mod keytree;
+pub mod lock_comp;
 pub mod render_comp;
 pub mod selection_comp;
+pub mod statistics;
 pub mod stroke_comp;
 pub mod trash_comp;
 
 // Re-exports
 pub use chrono_comp::ChronoComponent;
 use keytree::KeyTree;
+pub use lock_comp::LockComponent;
 pub use render_comp::RenderComponent;
-pub use selection_comp::SelectionComponent;
-pub use trash_comp::TrashComponent;
+use selection_comp::SelectionCounters;
+pub use selection_comp::{
+    SelectionComponent, SelectionStyleSummary, SelectionSummary, StrokeWidthUpdate, StyleUpdate,
+};
+pub use statistics::StoreStatistics;
+pub use stroke_comp::ColorReplaceScope;
+use stroke_comp::WidthScaling;
+pub use trash_comp::{TrashComponent, TrashedStrokeInfo};
 
 // Imports
 use self::chrono_comp::StrokeLayer;
+use crate::document::background::BackgroundImage;
+use crate::document::PdfUnderlay;
 use crate::engine::EngineSnapshot;
 use crate::strokes::Stroke;
 use crate::WidgetFlags;
 use rnote_compose::shapes::Shapeable;
+use rnote_compose::Transform;
 use serde::{Deserialize, Serialize};
 use slotmap::{HopSlotMap, SecondaryMap};
-use std::collections::VecDeque;
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use tracing::debug;
+use tracing::{debug, warn};
 
 slotmap::new_key_type! {
     pub struct StrokeKey;
 }
 
+/// Identifies a transaction of the store, see [StrokeStore::begin_transaction].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxId(u64);
+
+/// Identifies a state of the document in the history, see [StrokeStore::state_marker].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateMarker {
+    /// The marker of the history entry the state is anchored at.
+    entry: u64,
+    /// The history steps from the anchor entry to the state.
+    steps: usize,
+}
+
+/// An ongoing transaction, holding the stroke components as they were when it began.
+#[derive(Debug, Clone)]
+struct Transaction {
+    id: TxId,
+    stroke_components: Arc<HopSlotMap<StrokeKey, Arc<Stroke>>>,
+}
+
+/// The strokes and their history at some point, see [StrokeStore::checkpoint].
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+    state: HistoryEntry,
+    history: VecDeque<HistoryEntry>,
+    live_index: usize,
+    saved_state_marker: StateMarker,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename = "history_entry")]
 pub struct HistoryEntry {
@@ -39,8 +81,29 @@ pub struct HistoryEntry {
     pub trash_components: Arc<SecondaryMap<StrokeKey, Arc<TrashComponent>>>,
     #[serde(rename = "chrono_components")]
     pub chrono_components: Arc<SecondaryMap<StrokeKey, Arc<ChronoComponent>>>,
+    #[serde(rename = "lock_components")]
+    pub lock_components: Arc<SecondaryMap<StrokeKey, Arc<LockComponent>>>,
     #[serde(rename = "chrono_counter")]
     pub chrono_counter: u32,
+    /// The keys that were selected when the entry was recorded.
+    ///
+    /// Not part of the document state, so it is neither saved nor considered when
+    /// comparing the current state with the history.
+    #[serde(skip)]
+    pub selection_keys: Arc<Vec<StrokeKey>>,
+    /// Uniquely identifies the entry within the history of the store, assigned when it is recorded.
+    #[serde(skip)]
+    pub state_marker: u64,
+    /// The background image of the document when the entry was recorded.
+    ///
+    /// Not serialized, because it is saved with the document.
+    #[serde(skip)]
+    pub background_image: Option<Arc<BackgroundImage>>,
+    /// The Pdf underlay of the document when the entry was recorded.
+    ///
+    /// Not serialized, because it is saved with the document.
+    #[serde(skip)]
+    pub pdf_underlay: Option<Arc<PdfUnderlay>>,
 }
 
 impl Default for HistoryEntry {
@@ -49,8 +112,13 @@ impl Default for HistoryEntry {
             stroke_components: Arc::new(HopSlotMap::with_key()),
             trash_components: Arc::new(SecondaryMap::new()),
             chrono_components: Arc::new(SecondaryMap::new()),
+            lock_components: Arc::new(SecondaryMap::new()),
 
             chrono_counter: 0,
+            selection_keys: Arc::new(Vec::new()),
+            state_marker: 0,
+            background_image: None,
+            pdf_underlay: None,
         }
     }
 }
@@ -62,6 +130,7 @@ impl Default for HistoryEntry {
 ///     * 'trash_components': Holds state whether the strokes are trashed
 ///     * 'selection_components': Holds state whether the strokes are selected
 ///     * 'chrono_components': Holds state about the chronological ordering
+///     * 'lock_components': Holds state whether the strokes are locked against selecting and erasing
 ///     * 'render_components': Holds state about the rendering.
 ///
 /// The systems are implemented as methods on StrokesStore, loosely categorized to the different components (but often modify others as well).
@@ -79,13 +148,30 @@ pub struct StrokeStore {
     selection_components: Arc<SecondaryMap<StrokeKey, Arc<SelectionComponent>>>,
     #[serde(rename = "chrono_components")]
     chrono_components: Arc<SecondaryMap<StrokeKey, Arc<ChronoComponent>>>,
+    #[serde(rename = "lock_components")]
+    lock_components: Arc<SecondaryMap<StrokeKey, Arc<LockComponent>>>,
     /// Incrementing counter for chrono_components.
     ///
     /// Value must be kept equal to the [ChronoComponent] of the newest inserted or modified stroke.
     #[serde(rename = "chrono_counter")]
     chrono_counter: u32,
+    /// The background image of the document, tracked here so that changing it is part of the history.
+    ///
+    /// Must be kept equal to the image of the document background.
+    #[serde(skip)]
+    background_image: Option<Arc<BackgroundImage>>,
+    /// The Pdf underlay of the document, tracked here so that changing it is part of the history.
+    ///
+    /// Must be kept equal to the underlay of the document.
+    #[serde(skip)]
+    pdf_underlay: Option<Arc<PdfUnderlay>>,
     #[serde(skip)]
     render_components: SecondaryMap<StrokeKey, RenderComponent>,
+    /// The instants the strokes were inserted into the store.
+    ///
+    /// Only tracked for the current session, loaded strokes don't have one.
+    #[serde(skip)]
+    creation_instants: SecondaryMap<StrokeKey, Instant>,
     #[serde(skip)]
     history: VecDeque<HistoryEntry>,
     /// The index of the current live document in the history stack.
@@ -96,6 +182,39 @@ pub struct StrokeStore {
     /// Needs to be updated with `update_with_key()` when strokes changed their geometry or position!
     #[serde(skip)]
     key_tree: KeyTree,
+    /// The stroke widths before an ongoing interactive scaling, see [StrokeStore::begin_width_scaling].
+    #[serde(skip)]
+    width_scaling: HashMap<StrokeKey, WidthScaling>,
+    /// The transform of the last committed selector gesture, see [StrokeStore::last_selection_transform].
+    #[serde(skip)]
+    last_selection_transform: Option<Transform>,
+    /// The counters of the selected strokes, see [StrokeStore::selection_summary].
+    #[serde(skip)]
+    selection_counters: SelectionCounters,
+    /// Coalesces the rendering requests of continuous gestures.
+    #[serde(skip)]
+    render_coalescer: render_comp::RenderCoalescer,
+    /// The rendering of the dragged selection, see [StrokeStore::begin_selection_composite].
+    #[serde(skip)]
+    selection_composite: Option<composite::SelectionComposite>,
+    /// The ongoing transaction, see [StrokeStore::begin_transaction].
+    #[serde(skip)]
+    transaction: Option<Transaction>,
+    #[serde(skip)]
+    next_tx_id: u64,
+    /// The state that was last saved, see [StrokeStore::set_saved_state_marker].
+    #[serde(skip)]
+    saved_state_marker: StateMarker,
+    #[serde(skip)]
+    next_state_marker: u64,
+    /// The live history entry, published whenever it changes so that it can be read from other threads.
+    ///
+    /// Never contains unrecorded modifications, see [StrokeStore::published_state].
+    #[serde(skip)]
+    published_state: Arc<Mutex<Arc<HistoryEntry>>>,
+    /// Whether modifications are rejected when they are recorded, see [StrokeStore::set_read_only].
+    #[serde(skip)]
+    read_only: bool,
 }
 
 impl Default for StrokeStore {
@@ -105,15 +224,30 @@ impl Default for StrokeStore {
             trash_components: Arc::new(SecondaryMap::new()),
             selection_components: Arc::new(SecondaryMap::new()),
             chrono_components: Arc::new(SecondaryMap::new()),
+            lock_components: Arc::new(SecondaryMap::new()),
             render_components: SecondaryMap::new(),
+            creation_instants: SecondaryMap::new(),
 
             // Start off with state in the history
             history: VecDeque::from(vec![HistoryEntry::default()]),
             live_index: 0,
 
             key_tree: KeyTree::default(),
+            width_scaling: HashMap::new(),
+            last_selection_transform: None,
+            selection_counters: SelectionCounters::default(),
+            render_coalescer: render_comp::RenderCoalescer::default(),
+            selection_composite: None,
+            transaction: None,
+            next_tx_id: 0,
+            saved_state_marker: StateMarker { entry: 0, steps: 0 },
+            next_state_marker: 1,
+            published_state: Arc::new(Mutex::new(Arc::new(HistoryEntry::default()))),
 
             chrono_counter: 0,
+            background_image: None,
+            pdf_underlay: None,
+            read_only: false,
         }
     }
 }
@@ -131,11 +265,15 @@ impl StrokeStore {
         widget_flags |= self.clear();
         self.stroke_components = Arc::clone(&snapshot.stroke_components);
         self.chrono_components = Arc::clone(&snapshot.chrono_components);
+        self.lock_components = Arc::clone(&snapshot.lock_components);
         self.chrono_counter = snapshot.chrono_counter;
+        self.background_image = snapshot.document.background.image.clone();
+        self.pdf_underlay = snapshot.document.pdf_underlay.clone();
 
         self.update_geometry_for_strokes(&self.keys_unordered());
         self.rebuild_selection_components_slotmap();
         self.rebuild_trash_components_slotmap();
+        self.rebuild_lock_components_slotmap();
         self.rebuild_render_components_slotmap();
         self.rebuild_rtree();
         widget_flags |= self.clear_history(self.create_history_entry());
@@ -158,7 +296,18 @@ impl StrokeStore {
         Arc::ptr_eq(&self.stroke_components, &history_entry.stroke_components)
             && Arc::ptr_eq(&self.trash_components, &history_entry.trash_components)
             && Arc::ptr_eq(&self.chrono_components, &history_entry.chrono_components)
+            && Arc::ptr_eq(&self.lock_components, &history_entry.lock_components)
             && self.chrono_counter == history_entry.chrono_counter
+            && match (&self.background_image, &history_entry.background_image) {
+                (Some(image), Some(entry_image)) => Arc::ptr_eq(image, entry_image),
+                (None, None) => true,
+                _ => false,
+            }
+            && match (&self.pdf_underlay, &history_entry.pdf_underlay) {
+                (Some(underlay), Some(entry_underlay)) => Arc::ptr_eq(underlay, entry_underlay),
+                (None, None) => true,
+                _ => false,
+            }
     }
 
     /// Create a history entry from the current state.
@@ -167,30 +316,101 @@ impl StrokeStore {
             stroke_components: Arc::clone(&self.stroke_components),
             trash_components: Arc::clone(&self.trash_components),
             chrono_components: Arc::clone(&self.chrono_components),
+            lock_components: Arc::clone(&self.lock_components),
             chrono_counter: self.chrono_counter,
+            selection_keys: Arc::new(self.selection_keys_unordered()),
+            // assigned when the entry is recorded
+            state_marker: 0,
+            background_image: self.background_image.clone(),
+            pdf_underlay: self.pdf_underlay.clone(),
         }
     }
 
     /// Import the given history entry and replaces the current state with it.
+    ///
+    /// The current selection is retained for the strokes that are still present and not trashed,
+    /// if none of them are left the selection that was snapshotted in the history entry is restored.
     fn import_history_entry(&mut self, history_entry: HistoryEntry) {
+        let prev_selection = self.selection_keys_unordered();
+
         self.stroke_components = Arc::clone(&history_entry.stroke_components);
         self.trash_components = Arc::clone(&history_entry.trash_components);
         self.chrono_components = Arc::clone(&history_entry.chrono_components);
+        self.lock_components = Arc::clone(&history_entry.lock_components);
         self.chrono_counter = history_entry.chrono_counter;
+        self.background_image = history_entry.background_image.clone();
+        self.pdf_underlay = history_entry.pdf_underlay.clone();
+        // The composite shows the strokes from before the import
+        self.selection_composite = None;
 
         // Since we don't store the rtree in the history, we need to rebuild it.
         self.rebuild_rtree();
         self.rebuild_selection_components_slotmap();
+        let is_valid = |store: &Self, key: StrokeKey| {
+            store.stroke_components.contains_key(key) && !store.trashed(key).unwrap_or(true)
+        };
+        let mut selection = prev_selection
+            .into_iter()
+            .filter(|&key| is_valid(self, key))
+            .collect::<Vec<StrokeKey>>();
+        if selection.is_empty() {
+            selection = history_entry
+                .selection_keys
+                .iter()
+                .copied()
+                .filter(|&key| is_valid(self, key))
+                .collect();
+        }
+        self.set_selected_keys_keep_order(&selection, true);
         // Rebuild but retain the render components for the strokes that are found in the history entry.
         // This ensures that we are able to continue displaying the strokes after undo/redo while they are rerendered.
         self.rebuild_retain_valid_keys_render_components();
+        self.width_scaling.clear();
+        self.transaction = None;
 
         let all_strokes = self.stroke_keys_unordered();
         self.set_rendering_dirty_for_strokes(&all_strokes);
     }
 
+    /// Whether modifications are rejected when they are recorded.
+    pub(crate) fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Set whether modifications are rejected when they are recorded.
+    ///
+    /// In read-only mode recording discards the modifications since the live history entry instead
+    /// and reports them with [WidgetFlags::read_only_rejected], undo and redo are rejected as well.
+    /// Selecting strokes then doesn't raise them, so that it is not a modification.
+    pub(crate) fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Discard the modifications since the live history entry, in read-only mode.
+    ///
+    /// The current selection is retained for the strokes that are still present.
+    fn reject_unrecorded_modifications(&mut self) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        if let Some(live) = self.history.get(self.live_index) {
+            if !self.eq_w_history_entry(live) {
+                let live = live.clone();
+                self.import_history_entry(live);
+                widget_flags.read_only_rejected = true;
+            }
+        }
+
+        widget_flags.hide_undo = Some(true);
+        widget_flags.hide_redo = Some(true);
+
+        widget_flags
+    }
+
     /// Record the current state and save it in the history.
     pub(crate) fn record(&mut self, _now: Instant) -> WidgetFlags {
+        if self.read_only {
+            return self.reject_unrecorded_modifications();
+        }
         let mut widget_flags = WidgetFlags::default();
 
         if self
@@ -199,22 +419,33 @@ impl StrokeStore {
             .map(|last| !self.eq_w_history_entry(last))
             .unwrap_or(true)
         {
+            self.touch_modified_strokes_since_live_entry();
             // as soon as the current state is recorded, remove the future
-            self.history.truncate(self.live_index + 1);
+            self.truncate_history_future();
 
-            let current = self.create_history_entry();
+            let mut current = self.create_history_entry();
+            current.state_marker = self.new_state_marker();
             self.history.push_back(current);
             self.live_index += 1;
 
             // truncate history if necessary
             while self.history.len() > Self::HISTORY_MAX_LEN {
-                self.history.pop_front();
+                if let Some(front) = self.history.pop_front() {
+                    // The saved state is then reached through the new oldest entry
+                    if front.state_marker == self.saved_state_marker.entry {
+                        self.saved_state_marker = StateMarker {
+                            entry: self.history[0].state_marker,
+                            steps: self.saved_state_marker.steps + 1,
+                        };
+                    }
+                }
                 self.live_index -= 1;
             }
         } else {
             debug!("State has not changed, no need to record.");
         }
 
+        self.publish_live_state();
         widget_flags.hide_undo = Some(!self.can_undo());
         widget_flags.hide_redo = Some(!self.can_redo());
 
@@ -223,6 +454,9 @@ impl StrokeStore {
 
     /// Update the state of the latest history entry with the current document state.
     pub(crate) fn update_latest_history_entry(&mut self, _now: Instant) -> WidgetFlags {
+        if self.read_only {
+            return self.reject_unrecorded_modifications();
+        }
         let mut widget_flags = WidgetFlags::default();
 
         if self
@@ -231,27 +465,278 @@ impl StrokeStore {
             .map(|last| !self.eq_w_history_entry(last))
             .unwrap_or(true)
         {
+            self.touch_modified_strokes_since_live_entry();
             // as soon as the current state is recorded, remove the future
-            self.history.truncate(self.live_index + 1);
+            self.truncate_history_future();
 
-            let current = self.create_history_entry();
+            let mut current = self.create_history_entry();
+            current.state_marker = self.new_state_marker();
+            // The replaced entry is a step away from the one that replaces it
+            if self.history[self.live_index].state_marker == self.saved_state_marker.entry {
+                self.saved_state_marker = StateMarker {
+                    entry: current.state_marker,
+                    steps: self.saved_state_marker.steps + 1,
+                };
+            }
             self.history[self.live_index] = current;
         } else {
             debug!("State has not changed, no need to update history with current state.");
         }
 
+        self.publish_live_state();
         widget_flags.hide_undo = Some(!self.can_undo());
         widget_flags.hide_redo = Some(!self.can_redo());
 
         widget_flags
     }
 
+    /// Update the modification time of the strokes that were modified since the current history entry.
+    fn touch_modified_strokes_since_live_entry(&mut self) {
+        let Some(live_stroke_components) = self
+            .history
+            .get(self.live_index)
+            .map(|entry| Arc::clone(&entry.stroke_components))
+        else {
+            return;
+        };
+        self.touch_modified_strokes(&live_stroke_components, ChronoComponent::now_timestamp());
+    }
+
+    /// Publish the live history entry, so that it is readable through [StrokeStore::published_state].
+    fn publish_live_state(&self) {
+        let Some(live) = self.history.get(self.live_index) else {
+            return;
+        };
+        let live = Arc::new(live.clone());
+        match self.published_state.lock() {
+            Ok(mut published) => *published = live,
+            // Only the store writes while holding the lock, so the entry is intact
+            Err(poisoned) => *poisoned.into_inner() = live,
+        }
+    }
+
+    /// The handle to the published live history entry.
+    ///
+    /// It is updated whenever the state is recorded or the history is navigated,
+    /// so it always holds a consistent state, even when the store itself is in the middle of a modification.
+    pub(crate) fn published_state(&self) -> Arc<Mutex<Arc<HistoryEntry>>> {
+        Arc::clone(&self.published_state)
+    }
+
+    fn new_state_marker(&mut self) -> u64 {
+        let marker = self.next_state_marker;
+        self.next_state_marker += 1;
+        marker
+    }
+
+    /// Remove the entries after the current one from the history.
+    ///
+    /// When the saved state is removed with them, it is anchored at the current entry from where it branched off.
+    fn truncate_history_future(&mut self) {
+        if let Some(saved_index) = self.history_index_of(self.saved_state_marker.entry) {
+            if saved_index > self.live_index {
+                self.saved_state_marker = StateMarker {
+                    entry: self.history[self.live_index].state_marker,
+                    steps: self.saved_state_marker.steps + (saved_index - self.live_index),
+                };
+            }
+        }
+        self.history.truncate(self.live_index + 1);
+    }
+
+    fn history_index_of(&self, state_marker: u64) -> Option<usize> {
+        self.history
+            .iter()
+            .position(|entry| entry.state_marker == state_marker)
+    }
+
+    /// The marker of the current state.
+    ///
+    /// When the current state has not been recorded yet, it is considered a step away from the current history entry.
+    pub(crate) fn state_marker(&self) -> StateMarker {
+        let current = &self.history[self.live_index];
+        StateMarker {
+            entry: current.state_marker,
+            steps: usize::from(!self.eq_w_history_entry(current)),
+        }
+    }
+
+    /// The marker of the state that was last saved.
+    ///
+    /// The initial state of the history is considered saved.
+    pub(crate) fn saved_state_marker(&self) -> StateMarker {
+        self.saved_state_marker
+    }
+
+    /// Set the marker of the state that was saved, as retrieved with [StrokeStore::state_marker] when it was saved.
+    pub(crate) fn set_saved_state_marker(&mut self, state_marker: StateMarker) {
+        self.saved_state_marker = state_marker;
+    }
+
+    /// The number of history steps between the current and the saved state.
+    pub(crate) fn changes_since_save(&self) -> usize {
+        let current = self.state_marker();
+        let Some(saved_index) = self.history_index_of(self.saved_state_marker.entry) else {
+            // The anchor of the saved state is always kept in the history, but be safe
+            return self.history.len() + current.steps;
+        };
+        self.live_index.abs_diff(saved_index) + self.saved_state_marker.steps + current.steps
+    }
+
+    /// Whether the current state differs from the saved state.
+    pub(crate) fn has_unsaved_changes(&self) -> bool {
+        self.changes_since_save() > 0
+    }
+
+    /// Begin a transaction for a gesture that modifies the strokes incrementally.
+    ///
+    /// The stroke components are snapshotted the same way a history entry is, so this is cheap.
+    /// The transaction must be finished with either [StrokeStore::commit_transaction] or
+    /// [StrokeStore::rollback_transaction]. Beginning a new one replaces a transaction that is still ongoing.
+    pub(crate) fn begin_transaction(&mut self) -> TxId {
+        if let Some(transaction) = &self.transaction {
+            debug!(
+                "Beginning a new transaction while transaction {:?} is still ongoing, replacing it.",
+                transaction.id
+            );
+        }
+        let id = TxId(self.next_tx_id);
+        self.next_tx_id = self.next_tx_id.wrapping_add(1);
+        self.transaction = Some(Transaction {
+            id,
+            stroke_components: Arc::clone(&self.stroke_components),
+        });
+        id
+    }
+
+    /// Whether the transaction is the ongoing one.
+    pub(crate) fn transaction_ongoing(&self, id: TxId) -> bool {
+        self.transaction
+            .as_ref()
+            .map(|transaction| transaction.id == id)
+            .unwrap_or(false)
+    }
+
+    /// Commit the transaction, recording the current state in the history.
+    pub(crate) fn commit_transaction(&mut self, id: TxId, now: Instant) -> WidgetFlags {
+        if self.transaction_ongoing(id) {
+            self.transaction = None;
+        } else {
+            warn!("Committing transaction {id:?} that is not ongoing.");
+        }
+        self.record(now)
+    }
+
+    /// Roll back the transaction, restoring the strokes that were modified since it began.
+    ///
+    /// Strokes that were inserted during the transaction are kept.
+    /// Returns the keys of the restored strokes, which then need to update their rendering.
+    pub(crate) fn rollback_transaction(&mut self, id: TxId) -> Vec<StrokeKey> {
+        if !self.transaction_ongoing(id) {
+            warn!("Rolling back transaction {id:?} that is not ongoing.");
+            return vec![];
+        }
+        let Some(transaction) = self.transaction.take() else {
+            return vec![];
+        };
+        let snapshot = transaction.stroke_components;
+        if Arc::ptr_eq(&self.stroke_components, &snapshot) {
+            return vec![];
+        }
+
+        let touched = snapshot
+            .iter()
+            .filter(|(key, stroke)| {
+                self.stroke_components
+                    .get(*key)
+                    .map(|current| !Arc::ptr_eq(current, stroke))
+                    .unwrap_or(false)
+            })
+            .map(|(key, _)| key)
+            .collect::<Vec<StrokeKey>>();
+        if self.stroke_components.len() == snapshot.len()
+            && self
+                .stroke_components
+                .keys()
+                .all(|key| snapshot.contains_key(key))
+        {
+            // Restoring the snapshotted components themselves makes the state equal to the history again
+            self.stroke_components = snapshot;
+        } else {
+            let stroke_components = Arc::make_mut(&mut self.stroke_components);
+            for &key in &touched {
+                if let (Some(current), Some(stroke)) =
+                    (stroke_components.get_mut(key), snapshot.get(key))
+                {
+                    *current = Arc::clone(stroke);
+                }
+            }
+        }
+        self.width_scaling.clear();
+
+        for &key in &touched {
+            if let Some(stroke) = self.stroke_components.get(key) {
+                self.key_tree.update_with_key(key, stroke.bounds());
+            }
+            self.set_rendering_dirty(key);
+        }
+        touched
+    }
+
+    /// Take a checkpoint of the strokes and the history, which is cheap.
+    ///
+    /// Used to guarantee that an operation leaves the strokes untouched, see [StrokeStore::restore_checkpoint].
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            state: self.create_history_entry(),
+            history: self.history.clone(),
+            live_index: self.live_index,
+            saved_state_marker: self.saved_state_marker,
+        }
+    }
+
+    /// Whether the strokes or the history were modified since the checkpoint was taken.
+    ///
+    /// Changes of the selection are not considered modifications.
+    pub(crate) fn modified_since_checkpoint(&self, checkpoint: &Checkpoint) -> bool {
+        !self.eq_w_history_entry(&checkpoint.state)
+            || self.live_index != checkpoint.live_index
+            || self.history.len() != checkpoint.history.len()
+            || self.history.iter().zip(checkpoint.history.iter()).any(
+                |(entry, checkpoint_entry)| entry.state_marker != checkpoint_entry.state_marker,
+            )
+    }
+
+    /// Restore the strokes and the history of the checkpoint, discarding all modifications since it was taken.
+    ///
+    /// The current selection is retained for the strokes that are still present.
+    /// The strokes then need to update their rendering.
+    pub(crate) fn restore_checkpoint(&mut self, checkpoint: Checkpoint) -> WidgetFlags {
+        let mut widget_flags = WidgetFlags::default();
+
+        self.history = checkpoint.history;
+        self.live_index = checkpoint.live_index;
+        self.saved_state_marker = checkpoint.saved_state_marker;
+        self.import_history_entry(checkpoint.state);
+
+        self.publish_live_state();
+        widget_flags.hide_undo = Some(!self.can_undo());
+        widget_flags.hide_redo = Some(!self.can_redo());
+        widget_flags.store_modified = true;
+
+        widget_flags
+    }
+
     /// Undo the latest changes.
     ///
     /// Should only be called from inside the engine undo wrapper function.
     pub(crate) fn undo(&mut self, _now: Instant) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
+        if self.read_only {
+            widget_flags.read_only_rejected = true;
+            return widget_flags;
+        }
         if !self.can_undo() {
             return widget_flags;
         }
@@ -260,6 +745,7 @@ impl StrokeStore {
         self.import_history_entry(prev);
         self.live_index -= 1;
 
+        self.publish_live_state();
         widget_flags.hide_undo = Some(!self.can_undo());
         widget_flags.hide_redo = Some(!self.can_redo());
         widget_flags.store_modified = true;
@@ -273,6 +759,10 @@ impl StrokeStore {
     pub(crate) fn redo(&mut self, _now: Instant) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
+        if self.read_only {
+            widget_flags.read_only_rejected = true;
+            return widget_flags;
+        }
         if !self.can_redo() {
             return widget_flags;
         }
@@ -281,6 +771,7 @@ impl StrokeStore {
         self.import_history_entry(next);
         self.live_index += 1;
 
+        self.publish_live_state();
         widget_flags.hide_undo = Some(!self.can_undo());
         widget_flags.hide_redo = Some(!self.can_redo());
         widget_flags.store_modified = true;
@@ -296,12 +787,40 @@ impl StrokeStore {
         self.live_index < self.history.len() - 1
     }
 
+    /// The background image of the document, as it is tracked in the history.
+    pub(crate) fn background_image(&self) -> Option<&Arc<BackgroundImage>> {
+        self.background_image.as_ref()
+    }
+
+    /// Set the background image of the document. Needs to be recorded to become part of the history.
+    pub(crate) fn set_background_image(&mut self, background_image: Option<Arc<BackgroundImage>>) {
+        self.background_image = background_image;
+    }
+
+    /// The Pdf underlay of the document, as it is tracked in the history.
+    pub(crate) fn pdf_underlay(&self) -> Option<&Arc<PdfUnderlay>> {
+        self.pdf_underlay.as_ref()
+    }
+
+    /// Set the Pdf underlay of the document. Needs to be recorded to become part of the history.
+    pub(crate) fn set_pdf_underlay(&mut self, pdf_underlay: Option<Arc<PdfUnderlay>>) {
+        self.pdf_underlay = pdf_underlay;
+    }
+
     /// Clear the history.
-    pub(crate) fn clear_history(&mut self, initial_state: HistoryEntry) -> WidgetFlags {
+    ///
+    /// The initial state then is considered saved.
+    pub(crate) fn clear_history(&mut self, mut initial_state: HistoryEntry) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
+        initial_state.state_marker = self.new_state_marker();
+        self.saved_state_marker = StateMarker {
+            entry: initial_state.state_marker,
+            steps: 0,
+        };
         self.history = VecDeque::from(vec![initial_state]);
         self.live_index = 0;
+        self.publish_live_state();
 
         widget_flags.hide_undo = Some(true);
         widget_flags.hide_redo = Some(true);
@@ -333,8 +852,10 @@ impl StrokeStore {
             key,
             Arc::new(ChronoComponent::new(self.chrono_counter, layer)),
         );
+        Arc::make_mut(&mut self.lock_components).insert(key, Arc::new(LockComponent::default()));
         self.render_components
             .insert(key, RenderComponent::default());
+        self.creation_instants.insert(key, Instant::now());
 
         key
     }
@@ -345,7 +866,11 @@ impl StrokeStore {
         Arc::make_mut(&mut self.trash_components).remove(key);
         Arc::make_mut(&mut self.selection_components).remove(key);
         Arc::make_mut(&mut self.chrono_components).remove(key);
+        Arc::make_mut(&mut self.lock_components).remove(key);
         self.render_components.remove(key);
+        self.creation_instants.remove(key);
+        self.width_scaling.remove(&key);
+        self.selection_counters.remove(key);
 
         self.key_tree.remove_with_key(key);
         Arc::make_mut(&mut self.stroke_components)
@@ -359,13 +884,308 @@ impl StrokeStore {
         Arc::make_mut(&mut self.trash_components).clear();
         Arc::make_mut(&mut self.selection_components).clear();
         Arc::make_mut(&mut self.chrono_components).clear();
+        Arc::make_mut(&mut self.lock_components).clear();
 
         self.chrono_counter = 0;
+        self.background_image = None;
+        self.pdf_underlay = None;
         let widget_flags = self.clear_history(HistoryEntry::default());
 
         self.render_components.clear();
+        self.creation_instants.clear();
         self.key_tree.clear();
+        self.width_scaling.clear();
+        self.last_selection_transform = None;
+        self.selection_counters.clear();
+        self.selection_composite = None;
+        self.transaction = None;
 
         widget_flags
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::{assert_bounds_eq, insert_rect};
+    use approx::assert_relative_eq;
+    use p2d::bounding_volume::Aabb;
+
+    fn set_timestamps(store: &mut StrokeStore, key: StrokeKey, created: i64, modified: i64) {
+        let chrono_comp = Arc::make_mut(
+            Arc::make_mut(&mut store.chrono_components)
+                .get_mut(key)
+                .unwrap(),
+        );
+        chrono_comp.created = Some(created);
+        chrono_comp.modified = Some(modified);
+    }
+
+    #[test]
+    fn rollback_after_scale_and_translate() {
+        let mut store = StrokeStore::default();
+        let moved = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let untouched = insert_rect(
+            &mut store,
+            Aabb::new(na::point![100.0, 100.0], na::point![120.0, 110.0]),
+        );
+        store.record(Instant::now());
+        let moved_bounds = store.bounds_for_strokes(&[moved]).unwrap();
+        let untouched_stroke = store.get_stroke_arc(untouched).unwrap();
+
+        let tx = store.begin_transaction();
+        // the incremental updates of a gesture
+        for _ in 0..3 {
+            store.scale_strokes_with_pivot(&[moved], na::vector![1.5, 0.5], na::vector![0.0, 0.0]);
+            store.translate_strokes(&[moved], na::vector![7.0, -3.0]);
+            store.update_geometry_for_strokes(&[moved]);
+        }
+        let restored = store.rollback_transaction(tx);
+
+        assert_eq!(restored, vec![moved]);
+        assert_bounds_eq(store.bounds_for_strokes(&[moved]).unwrap(), moved_bounds);
+        assert!(Arc::ptr_eq(
+            &store.get_stroke_arc(untouched).unwrap(),
+            &untouched_stroke
+        ));
+        // the index follows the restored bounds
+        assert_eq!(
+            store.stroke_keys_as_rendered_intersecting_bounds(Aabb::new(
+                na::point![1.0, 1.0],
+                na::point![2.0, 2.0]
+            )),
+            vec![moved]
+        );
+        // the state equals the history again, so nothing new is recorded
+        store.record(Instant::now());
+        assert!(store.can_undo());
+        store.undo(Instant::now());
+        assert!(!store.can_undo());
+    }
+
+    #[test]
+    fn rollback_keeps_strokes_inserted_during_transaction() {
+        let mut store = StrokeStore::default();
+        let moved = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        store.record(Instant::now());
+        let moved_bounds = store.bounds_for_strokes(&[moved]).unwrap();
+
+        let tx = store.begin_transaction();
+        store.translate_strokes(&[moved], na::vector![50.0, 50.0]);
+        store.update_geometry_for_strokes(&[moved]);
+        let inserted = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![5.0, 5.0]),
+        );
+        assert_eq!(store.rollback_transaction(tx), vec![moved]);
+
+        assert_bounds_eq(store.bounds_for_strokes(&[moved]).unwrap(), moved_bounds);
+        assert!(store.get_stroke_ref(inserted).is_some());
+    }
+
+    #[test]
+    fn commit_transaction_records() {
+        let mut store = StrokeStore::default();
+        let key = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        store.record(Instant::now());
+        let bounds = store.bounds_for_strokes(&[key]).unwrap();
+
+        let tx = store.begin_transaction();
+        store.translate_strokes(&[key], na::vector![5.0, 0.0]);
+        store.update_geometry_for_strokes(&[key]);
+        store.commit_transaction(tx, Instant::now());
+        assert!(!store.transaction_ongoing(tx));
+        // committed transactions can't be rolled back anymore
+        assert!(store.rollback_transaction(tx).is_empty());
+        assert_relative_eq!(
+            store.bounds_for_strokes(&[key]).unwrap().mins[0],
+            bounds.mins[0] + 5.0
+        );
+
+        store.undo(Instant::now());
+        assert_bounds_eq(store.bounds_for_strokes(&[key]).unwrap(), bounds);
+    }
+
+    #[test]
+    fn only_the_ongoing_transaction_is_rolled_back() {
+        let mut store = StrokeStore::default();
+        let key = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        store.record(Instant::now());
+        let bounds = store.bounds_for_strokes(&[key]).unwrap();
+
+        let replaced = store.begin_transaction();
+        let tx = store.begin_transaction();
+        assert_ne!(replaced, tx);
+        store.translate_strokes(&[key], na::vector![5.0, 0.0]);
+        store.update_geometry_for_strokes(&[key]);
+        assert!(store.rollback_transaction(replaced).is_empty());
+        assert!(store.transaction_ongoing(tx));
+        assert_eq!(store.rollback_transaction(tx), vec![key]);
+        assert_bounds_eq(store.bounds_for_strokes(&[key]).unwrap(), bounds);
+    }
+
+    #[test]
+    fn chrono_timestamps_serde() {
+        let chrono_comp = ChronoComponent::new(3, StrokeLayer::UserLayer(0));
+        let json = serde_json::to_string(&chrono_comp).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ChronoComponent>(&json).unwrap(),
+            chrono_comp
+        );
+
+        // components of older files don't have timestamps
+        let json = serde_json::to_string(&ChronoComponent::default()).unwrap();
+        assert!(!json.contains("created") && !json.contains("modified"));
+        let loaded = serde_json::from_str::<ChronoComponent>(&json).unwrap();
+        assert_eq!(loaded.created, None);
+        assert_eq!(loaded.modified, None);
+    }
+
+    #[test]
+    fn duplicates_get_fresh_creation_time() {
+        let mut store = StrokeStore::default();
+        let key = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        set_timestamps(&mut store, key, 1000, 1000);
+        store.record(Instant::now());
+
+        store.set_selected_keys(&[key], true);
+        let duplicated = store.duplicate_selection_w_offset(na::vector![20.0, 0.0]);
+        assert_eq!(duplicated.len(), 1);
+        let created = store.chrono_components.get(duplicated[0]).unwrap().created;
+        assert!(created.is_some_and(|created| created > 1000));
+        assert_eq!(
+            store.chrono_components.get(key).unwrap().created,
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn modification_time_follows_history() {
+        let mut store = StrokeStore::default();
+        let key = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let untouched = insert_rect(
+            &mut store,
+            Aabb::new(na::point![50.0, 0.0], na::point![60.0, 10.0]),
+        );
+        set_timestamps(&mut store, key, 1000, 1000);
+        set_timestamps(&mut store, untouched, 1000, 1000);
+        store.record(Instant::now());
+
+        store.translate_strokes(&[key], na::vector![5.0, 0.0]);
+        store.update_geometry_for_strokes(&[key]);
+        store.record(Instant::now());
+        let modified = store.chrono_components.get(key).unwrap().modified;
+        assert!(modified.is_some_and(|modified| modified > 1000));
+        assert_eq!(
+            store.chrono_components.get(key).unwrap().created,
+            Some(1000)
+        );
+        assert_eq!(
+            store.chrono_components.get(untouched).unwrap().modified,
+            Some(1000)
+        );
+
+        store.undo(Instant::now());
+        assert_eq!(
+            store.chrono_components.get(key).unwrap().modified,
+            Some(1000)
+        );
+        store.redo(Instant::now());
+        assert_eq!(store.chrono_components.get(key).unwrap().modified, modified);
+    }
+
+    #[test]
+    fn sort_by_creation_and_time_range() {
+        let mut store = StrokeStore::default();
+        let a = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let b = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let c = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let unsorted = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        set_timestamps(&mut store, a, 2, 2);
+        set_timestamps(&mut store, b, 3, 3);
+        set_timestamps(&mut store, c, 1, 1);
+        set_timestamps(&mut store, unsorted, 0, 0);
+
+        store.sort_strokes_by_creation(&[a, b, c]);
+        // the stroke that isn't sorted keeps its place in front
+        assert_eq!(store.keys_sorted_chrono(), vec![c, a, b, unsorted]);
+
+        assert_eq!(store.strokes_in_time_range(1, 3), vec![c, a]);
+        store.set_trashed_keys(&[c], true);
+        assert_eq!(store.strokes_in_time_range(0, 10), vec![unsorted, a, b]);
+    }
+
+    #[test]
+    fn read_only_rejects_recorded_modifications() {
+        let mut store = StrokeStore::default();
+        let a = insert_rect(
+            &mut store,
+            Aabb::new(na::point![0.0, 0.0], na::point![10.0, 10.0]),
+        );
+        let b = insert_rect(
+            &mut store,
+            Aabb::new(na::point![50.0, 0.0], na::point![60.0, 10.0]),
+        );
+        store.record(Instant::now());
+        store.set_read_only(true);
+        let state = store.create_history_entry();
+
+        // Selecting is not a modification and doesn't raise the stroke
+        store.set_selected(a, true);
+        assert!(!store.record(Instant::now()).read_only_rejected);
+        assert_eq!(store.keys_sorted_chrono(), vec![a, b]);
+
+        store.set_trashed_keys(&[a], true);
+        let widget_flags = store.record(Instant::now());
+        assert!(widget_flags.read_only_rejected);
+        assert_eq!(widget_flags.hide_undo, Some(true));
+        assert_eq!(store.trashed(a), Some(false));
+        assert_eq!(store.selection_keys_as_rendered(), vec![a]);
+
+        let duplicated = store.duplicate_selection();
+        assert!(
+            store
+                .update_latest_history_entry(Instant::now())
+                .read_only_rejected
+        );
+        assert!(store.get_stroke_ref(duplicated[0]).is_none());
+
+        assert!(store.undo(Instant::now()).read_only_rejected);
+        assert!(store.eq_w_history_entry(&state));
+        assert!(store.can_undo());
+
+        store.set_read_only(false);
+        assert!(!store.undo(Instant::now()).read_only_rejected);
+        assert!(!store.eq_w_history_entry(&state));
+    }
+}
@@ -1,17 +1,39 @@
 // Imports
 use super::{Stroke, StrokeKey, StrokeStore};
-use crate::engine::{EngineTask, EngineTaskSender};
+use crate::engine::{EngineTask, EngineTaskSender, OperationId};
 use crate::strokes::content::GeneratedContentImages;
 use crate::strokes::Content;
 use crate::{render, Drawable};
 use p2d::bounding_volume::{Aabb, BoundingVolume};
-use rnote_compose::ext::AabbExt;
-use rnote_compose::shapes::Shapeable;
+use rnote_compose::ext::{AabbExt, Vector2Ext};
+use rnote_compose::shapes::{Rectangle, Shapeable};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 use tracing::error;
 
 /// The tolerance where check between scale-factors are considered "equal".
 pub(crate) const RENDER_IMAGE_SCALE_TOLERANCE: f64 = 0.01;
 
+/// How the strokes are drawn on the canvas.
+///
+/// Exports always draw the strokes fully.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The cached renderings of the strokes.
+    #[default]
+    Full,
+    /// Only the outlines of the strokes, stroked with a thin uniform line, bypassing the cached renderings.
+    ///
+    /// Images are drawn as crossed rectangles.
+    Outlines,
+}
+
+impl RenderMode {
+    /// The width of the outlines, in surface coordinates.
+    const OUTLINE_WIDTH: f64 = 1.0;
+    const OUTLINE_COLOR: piet::Color = rnote_compose::color::GNOME_BLUES[4];
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RenderCompState {
     Complete,
@@ -32,6 +54,12 @@ pub struct RenderComponent {
     pub(super) images: Vec<render::Image>,
     #[cfg(feature = "ui")]
     pub(super) rendernodes: Vec<gtk4::gsk::RenderNode>,
+    /// Incremented when a rendering task is started or the rendering becomes dirty.
+    ///
+    /// Rendering tasks compare it with the generation they were started with and bail out when they are stale.
+    pub(super) generation: Arc<AtomicU64>,
+    /// The operation of the rendering task that is currently rendering the stroke.
+    pub(super) operation: Option<OperationId>,
 }
 
 impl Default for RenderComponent {
@@ -41,6 +69,121 @@ impl Default for RenderComponent {
             images: vec![],
             #[cfg(feature = "ui")]
             rendernodes: vec![],
+            generation: Arc::new(AtomicU64::new(0)),
+            operation: None,
+        }
+    }
+}
+
+impl RenderComponent {
+    /// Start a new generation, returning the token that the rendering task checks for staleness.
+    fn next_generation(&self) -> GenerationToken {
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        GenerationToken {
+            counter: Arc::clone(&self.generation),
+            generation,
+        }
+    }
+
+    /// The size of the data of the cached images, in bytes.
+    ///
+    /// The render nodes share the data with the images.
+    pub(crate) fn images_size(&self) -> usize {
+        self.images.iter().map(|image| image.data.len()).sum()
+    }
+}
+
+/// The generation a rendering task was started with.
+#[derive(Debug, Clone)]
+struct GenerationToken {
+    counter: Arc<AtomicU64>,
+    generation: u64,
+}
+
+impl GenerationToken {
+    /// Whether a newer task was started or the rendering became dirty in the meantime.
+    fn is_stale(&self) -> bool {
+        self.counter.load(Ordering::Acquire) != self.generation
+    }
+}
+
+/// A stroke that is rendered by a task of a rendering operation.
+#[derive(Debug)]
+struct RenderJob {
+    key: StrokeKey,
+    stroke: Arc<Stroke>,
+    generation: GenerationToken,
+    task_guard: Option<RenderTaskGuard>,
+}
+
+/// A request to regenerate the rendering in the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RenderRequest {
+    pub(crate) viewport: Aabb,
+    pub(crate) image_scale: f64,
+}
+
+/// Coalesces requests to regenerate the rendering in the viewport, so that at most one batch of rendering tasks is in flight.
+///
+/// Requests that arrive while a batch is in flight replace each other, the latest one is submitted once the batch finished.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RenderCoalescer {
+    /// The number of rendering tasks of the submitted batch that are still running.
+    in_flight: Arc<AtomicUsize>,
+    /// The latest request that arrived while a batch was in flight.
+    pending: Option<RenderRequest>,
+}
+
+impl RenderCoalescer {
+    /// Returns the request if it should be submitted right away, otherwise it becomes the pending request.
+    pub(crate) fn request(&mut self, request: RenderRequest) -> Option<RenderRequest> {
+        if self.in_flight.load(Ordering::Acquire) > 0 {
+            self.pending = Some(request);
+            None
+        } else {
+            self.pending = None;
+            Some(request)
+        }
+    }
+
+    /// Take the pending request, once the batch in flight has finished.
+    pub(crate) fn take_pending(&mut self) -> Option<RenderRequest> {
+        if self.in_flight.load(Ordering::Acquire) > 0 {
+            return None;
+        }
+        self.pending.take()
+    }
+
+    /// Discard the pending request.
+    pub(crate) fn clear_pending(&mut self) {
+        self.pending = None;
+    }
+
+    /// Register a rendering task of the submitted batch, which is kept in flight until the returned guard is dropped.
+    ///
+    /// When the last task of the batch finishes, [EngineTask::CoalescedRenderingFinished] is sent if a sender is given.
+    pub(crate) fn task_guard(&self, tasks_tx: Option<EngineTaskSender>) -> RenderTaskGuard {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        RenderTaskGuard {
+            in_flight: Arc::clone(&self.in_flight),
+            tasks_tx,
+        }
+    }
+}
+
+/// Keeps a batch of coalesced rendering tasks in flight while it is alive.
+#[derive(Debug)]
+pub(crate) struct RenderTaskGuard {
+    in_flight: Arc<AtomicUsize>,
+    tasks_tx: Option<EngineTaskSender>,
+}
+
+impl Drop for RenderTaskGuard {
+    fn drop(&mut self) {
+        if self.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(tasks_tx) = self.tasks_tx.take() {
+                tasks_tx.send(EngineTask::CoalescedRenderingFinished);
+            }
         }
     }
 }
@@ -81,6 +224,8 @@ impl StrokeStore {
     pub(crate) fn set_rendering_dirty(&mut self, key: StrokeKey) {
         if let Some(render_comp) = self.render_components.get_mut(key) {
             render_comp.state = RenderCompState::Dirty;
+            // tasks that are still rendering the old state are stale
+            render_comp.generation.fetch_add(1, Ordering::AcqRel);
         }
     }
 
@@ -196,11 +341,18 @@ impl StrokeStore {
 
             // indicates that a task is now started rendering the stroke
             render_comp.state = RenderCompState::BusyRenderingInTask;
+            let generation = render_comp.next_generation();
 
             // Spawn a new thread for image rendering
-            rayon::spawn(
-                move || match stroke.gen_images(viewport_extended, image_scale) {
+            rayon::spawn(move || {
+                if generation.is_stale() {
+                    return;
+                }
+                match stroke.gen_images(viewport_extended, image_scale) {
                     Ok(images) => {
+                        if generation.is_stale() {
+                            return;
+                        }
                         tasks_tx.send(EngineTask::UpdateStrokeWithImages {
                             key,
                             images,
@@ -212,25 +364,115 @@ impl StrokeStore {
                             "Generating images of stroke failed while regenerating stroke rendering, stroke key {key:?} , Err: {e:?}"
                         );
                     }
-                },
-            );
+                }
+            });
         }
     }
 
+    /// Regenerate the rendering of the strokes in a single operation, reporting its progress.
+    ///
+    /// Returns the id of the operation, None when no stroke needed to be rendered.
     pub(crate) fn regenerate_rendering_for_strokes_threaded(
         &mut self,
         tasks_tx: EngineTaskSender,
         keys: &[StrokeKey],
         viewport: Aabb,
         image_scale: f64,
-    ) {
+    ) -> Option<OperationId> {
+        let mut jobs = Vec::with_capacity(keys.len());
         for &key in keys {
-            self.regenerate_rendering_for_stroke_threaded(
-                tasks_tx.clone(),
-                key,
-                viewport,
-                image_scale,
-            );
+            if let (Some(render_comp), Some(stroke)) = (
+                self.render_components.get_mut(key),
+                self.stroke_components.get(key),
+            ) {
+                if render_comp.state == RenderCompState::BusyRenderingInTask {
+                    continue;
+                }
+                // indicates that a task is now started rendering the stroke
+                render_comp.state = RenderCompState::BusyRenderingInTask;
+                jobs.push(RenderJob {
+                    key,
+                    stroke: Arc::clone(stroke),
+                    generation: render_comp.next_generation(),
+                    task_guard: None,
+                });
+            }
+        }
+        let viewport_extended =
+            viewport.extend_by(viewport.extents() * render::VIEWPORT_EXTENTS_MARGIN_FACTOR);
+        self.spawn_rendering_operation(tasks_tx, jobs, viewport_extended, image_scale)
+    }
+
+    /// Spawn the rendering tasks of the jobs as one operation.
+    fn spawn_rendering_operation(
+        &mut self,
+        tasks_tx: EngineTaskSender,
+        jobs: Vec<RenderJob>,
+        viewport_extended: Aabb,
+        image_scale: f64,
+    ) -> Option<OperationId> {
+        if jobs.is_empty() {
+            return None;
+        }
+        let operation = tasks_tx.start_operation(jobs.len());
+        for job in jobs.iter() {
+            if let Some(render_comp) = self.render_components.get_mut(job.key) {
+                render_comp.operation = Some(operation.id());
+            }
+        }
+
+        for job in jobs {
+            let tasks_tx = tasks_tx.clone();
+            let operation = operation.clone();
+
+            // Spawn a new thread for image rendering
+            rayon::spawn(move || {
+                let RenderJob {
+                    key,
+                    stroke,
+                    generation,
+                    task_guard,
+                } = job;
+                // keeps the coalesced batch in flight until the task finished
+                let _task_guard = task_guard;
+                if generation.is_stale() || operation.is_cancelled() {
+                    return;
+                }
+                match stroke.gen_images(viewport_extended, image_scale) {
+                    Ok(images) => {
+                        if generation.is_stale() || operation.is_cancelled() {
+                            return;
+                        }
+                        tasks_tx.send(EngineTask::UpdateStrokeWithImages {
+                            key,
+                            images,
+                            image_scale,
+                        });
+                    }
+                    Err(e) => {
+                        error!(
+                            "Generating stroke images failed while regenerating rendering in viewport `{viewport_extended:?}`, stroke key: {key:?}, Err: {e:?}"
+                        );
+                        operation.fail(format!("Generating images of stroke {key:?} failed, {e}"));
+                    }
+                }
+                operation.step_done();
+            });
+        }
+        Some(operation.id())
+    }
+
+    /// Flag the strokes that are still rendered by the tasks of the cancelled operation as dirty,
+    /// so that their outdated tasks bail out.
+    pub(crate) fn cancel_rendering_operation(&mut self, operation_id: OperationId) {
+        for (_key, render_comp) in self.render_components.iter_mut() {
+            if render_comp.state == RenderCompState::BusyRenderingInTask
+                && render_comp.operation == Some(operation_id)
+            {
+                render_comp.state = RenderCompState::Dirty;
+                render_comp.operation = None;
+                render_comp.generation.fetch_add(1, Ordering::AcqRel);
+            }
         }
     }
 
@@ -241,18 +483,79 @@ impl StrokeStore {
         force_regenerate: bool,
         viewport: Aabb,
         image_scale: f64,
+    ) -> Option<OperationId> {
+        self.regenerate_rendering_in_viewport_threaded_int(
+            tasks_tx,
+            force_regenerate,
+            viewport,
+            image_scale,
+            false,
+        )
+    }
+
+    /// Regenerate the rendering in the viewport like [StrokeStore::regenerate_rendering_in_viewport_threaded],
+    /// but coalesced with other coalesced requests.
+    ///
+    /// Meant for continuous gestures where a request is made for every pen event.
+    /// While a batch of rendering tasks is in flight, the request is postponed until the batch finished,
+    /// only the latest postponed request is then submitted.
+    pub(crate) fn regenerate_rendering_in_viewport_threaded_coalesced(
+        &mut self,
+        tasks_tx: EngineTaskSender,
+        viewport: Aabb,
+        image_scale: f64,
     ) {
+        if let Some(request) = self.render_coalescer.request(RenderRequest {
+            viewport,
+            image_scale,
+        }) {
+            self.regenerate_rendering_in_viewport_threaded_int(
+                tasks_tx,
+                false,
+                request.viewport,
+                request.image_scale,
+                true,
+            );
+        }
+    }
+
+    /// Submit the pending coalesced rendering request, once the batch in flight has finished.
+    pub(crate) fn regenerate_pending_rendering_coalesced(&mut self, tasks_tx: EngineTaskSender) {
+        if let Some(request) = self.render_coalescer.take_pending() {
+            self.regenerate_rendering_in_viewport_threaded_coalesced(
+                tasks_tx,
+                request.viewport,
+                request.image_scale,
+            );
+        }
+    }
+
+    /// Discard the pending coalesced rendering request, e.g. when the gesture has finished
+    /// and the rendering is regenerated uncoalesced.
+    pub(crate) fn clear_pending_rendering_coalesced(&mut self) {
+        self.render_coalescer.clear_pending();
+    }
+
+    fn regenerate_rendering_in_viewport_threaded_int(
+        &mut self,
+        tasks_tx: EngineTaskSender,
+        force_regenerate: bool,
+        viewport: Aabb,
+        image_scale: f64,
+        coalesced: bool,
+    ) -> Option<OperationId> {
+        self.refresh_selection_composite(image_scale);
         let keys = self.render_components.keys().collect::<Vec<StrokeKey>>();
+        let viewport_extended =
+            viewport.extend_by(viewport.extents() * render::VIEWPORT_EXTENTS_MARGIN_FACTOR);
+        let mut jobs = vec![];
 
         for key in keys {
             if let (Some(stroke), Some(render_comp)) = (
                 self.stroke_components.get(key),
                 self.render_components.get_mut(key),
             ) {
-                let tasks_tx = tasks_tx.clone();
                 let stroke_bounds = stroke.bounds();
-                let viewport_extended =
-                    viewport.extend_by(viewport.extents() * render::VIEWPORT_EXTENTS_MARGIN_FACTOR);
 
                 // skip and clear image buffer if stroke is not in viewport
                 if !viewport_extended.intersects(&stroke_bounds) {
@@ -293,27 +596,17 @@ impl StrokeStore {
 
                 // indicates that a task has now started to render the stroke
                 render_comp.state = RenderCompState::BusyRenderingInTask;
-                let stroke = stroke.clone();
-
-                // Spawn a new thread for image rendering
-                rayon::spawn(
-                    move || match stroke.gen_images(viewport_extended, image_scale) {
-                        Ok(images) => {
-                            tasks_tx.send(EngineTask::UpdateStrokeWithImages {
-                                key,
-                                images,
-                                image_scale,
-                            });
-                        }
-                        Err(e) => {
-                            error!(
-                                "Generating stroke images failed stroke while regenerating rendering in viewport `{viewport:?}`, stroke key: {key:?}, Err: {e:?}"
-                            );
-                        }
-                    },
-                );
+                jobs.push(RenderJob {
+                    key,
+                    stroke: Arc::clone(stroke),
+                    generation: render_comp.next_generation(),
+                    task_guard: coalesced
+                        .then(|| self.render_coalescer.task_guard(Some(tasks_tx.clone()))),
+                });
             }
         }
+
+        self.spawn_rendering_operation(tasks_tx, jobs, viewport_extended, image_scale)
     }
 
     /// Clear all rendering for all strokes.
@@ -325,6 +618,7 @@ impl StrokeStore {
             }
             render_comp.images = vec![];
             render_comp.state = RenderCompState::Dirty;
+            render_comp.generation.fetch_add(1, Ordering::AcqRel);
         }
     }
 
@@ -483,6 +777,8 @@ impl StrokeStore {
         snapshot: &gtk4::Snapshot,
         doc_bounds: Aabb,
         viewport: Aabb,
+        render_mode: RenderMode,
+        total_zoom: f64,
     ) {
         use crate::ext::{GdkRGBAExt, GrapheneRectExt};
         use gtk4::{gdk, graphene, prelude::*};
@@ -490,7 +786,22 @@ impl StrokeStore {
 
         snapshot.push_clip(&graphene::Rect::from_p2d_aabb(doc_bounds));
 
+        if render_mode == RenderMode::Outlines {
+            let cairo_cx = snapshot.append_cairo(&graphene::Rect::from_p2d_aabb(viewport));
+            let mut piet_cx = piet_cairo::CairoRenderContext::new(&cairo_cx);
+            self.draw_outlines_immediate(&mut piet_cx, viewport, total_zoom);
+            if let Err(e) = piet::RenderContext::finish(&mut piet_cx) {
+                error!("Finishing piet context failed while drawing stroke outlines, Err: {e:?}");
+            }
+            snapshot.pop();
+            return;
+        }
+
+        let composite = self.selection_composite();
         for key in self.stroke_keys_as_rendered_intersecting_bounds(viewport) {
+            if composite.is_some_and(|composite| composite.hides(key)) {
+                continue;
+            }
             if let (Some(stroke), Some(render_comp)) = (
                 self.stroke_components.get(key),
                 self.render_components.get(key),
@@ -514,6 +825,10 @@ impl StrokeStore {
                 }
             }
         }
+        // the dragged selection is drawn on top
+        if let Some(composite) = composite {
+            composite.draw_to_gtk_snapshot(snapshot);
+        }
 
         snapshot.pop();
     }
@@ -557,6 +872,42 @@ impl StrokeStore {
         }
     }
 
+    /// Draw the outlines of all strokes intersecting the viewport on the [piet::RenderContext].
+    ///
+    /// Immediate without any cached images, see [RenderMode::Outlines].
+    #[cfg(feature = "ui")]
+    pub(crate) fn draw_outlines_immediate(
+        &self,
+        piet_cx: &mut impl piet::RenderContext,
+        viewport: Aabb,
+        total_zoom: f64,
+    ) {
+        let width = RenderMode::OUTLINE_WIDTH / total_zoom;
+        let composite = self.selection_composite();
+        // The strokes of the dragged selection are drawn where the composite is
+        let composite_keys = composite
+            .map(|composite| composite.keys().to_vec())
+            .unwrap_or_default();
+        let keys = self
+            .stroke_keys_as_rendered_intersecting_bounds(viewport)
+            .into_iter()
+            .filter(|&key| !composite.is_some_and(|composite| composite.hides(key)));
+        for key in keys.chain(composite_keys) {
+            let Some(stroke) = self.stroke_components.get(key) else {
+                continue;
+            };
+            let mut outline = match stroke.as_ref() {
+                Stroke::BitmapImage(bitmapimage) => crossed_rectangle_path(&bitmapimage.rectangle),
+                Stroke::VectorImage(vectorimage) => crossed_rectangle_path(&vectorimage.rectangle),
+                stroke => stroke.outline_path(),
+            };
+            if let Some(composite) = composite.filter(|composite| composite.hides(key)) {
+                outline.apply_affine(composite.transform().to_kurbo());
+            }
+            piet_cx.stroke(outline, &RenderMode::OUTLINE_COLOR, width);
+        }
+    }
+
     /// Draw bounds, positions, etc. for all strokes for visual debugging purposes.
     #[cfg(feature = "ui")]
     pub(crate) fn draw_debug_to_gtk_snapshot(
@@ -651,3 +1002,152 @@ impl StrokeStore {
         Ok(())
     }
 }
+
+/// The outline of the rectangle, crossed by its diagonals.
+fn crossed_rectangle_path(rectangle: &Rectangle) -> kurbo::BezPath {
+    let half_extents = rectangle.cuboid.half_extents;
+    let corner = |x: f64, y: f64| {
+        rectangle
+            .transform
+            .transform_point(na::point![x * half_extents[0], y * half_extents[1]])
+            .coords
+            .to_kurbo_point()
+    };
+    let mut path = rectangle.outline_path();
+    path.move_to(corner(-1.0, -1.0));
+    path.line_to(corner(1.0, 1.0));
+    path.move_to(corner(1.0, -1.0));
+    path.line_to(corner(-1.0, 1.0));
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(i: usize) -> RenderRequest {
+        RenderRequest {
+            viewport: Aabb::new(
+                na::point![i as f64, 0.0],
+                na::point![i as f64 + 100.0, 100.0],
+            ),
+            image_scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn coalescer_renders_fewer_and_latest() {
+        let mut coalescer = RenderCoalescer::default();
+        let mut rendered = Vec::new();
+        let mut in_flight = Vec::new();
+
+        // Every submitted batch consists of a few tasks that take as long as four requests to finish
+        for i in 0..100 {
+            if i % 4 == 0 {
+                in_flight.clear();
+                if let Some(pending) = coalescer.take_pending() {
+                    rendered.push(pending);
+                    in_flight = (0..3).map(|_| coalescer.task_guard(None)).collect();
+                }
+            }
+            if let Some(submitted) = coalescer.request(request(i)) {
+                rendered.push(submitted);
+                in_flight = (0..3).map(|_| coalescer.task_guard(None)).collect();
+            }
+        }
+        in_flight.clear();
+        if let Some(pending) = coalescer.take_pending() {
+            rendered.push(pending);
+        }
+
+        assert!(rendered.len() <= 100 / 4 + 1);
+        assert_eq!(rendered.last(), Some(&request(99)));
+        assert!(coalescer.take_pending().is_none());
+    }
+
+    #[test]
+    fn coalescer_pending_held_while_in_flight() {
+        let mut coalescer = RenderCoalescer::default();
+        assert_eq!(coalescer.request(request(0)), Some(request(0)));
+
+        let guard = coalescer.task_guard(None);
+        assert!(coalescer.request(request(1)).is_none());
+        assert!(coalescer.request(request(2)).is_none());
+        assert!(coalescer.take_pending().is_none());
+        drop(guard);
+        assert_eq!(coalescer.take_pending(), Some(request(2)));
+
+        let _guard = coalescer.task_guard(None);
+        assert!(coalescer.request(request(3)).is_none());
+        coalescer.clear_pending();
+        drop(_guard);
+        assert!(coalescer.take_pending().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "ui")]
+    fn outlines_drawn_without_fills() {
+        use crate::strokes::ShapeStroke;
+        use rnote_compose::shapes::{Ellipse, Shape};
+        use rnote_compose::style::smooth::SmoothOptions;
+        use rnote_compose::{Color, Style, Transform};
+
+        let mut store = StrokeStore::default();
+        let mut options = SmoothOptions::default();
+        options.set_fill_color(Some(Color::RED));
+        store.insert_stroke(
+            Stroke::ShapeStroke(ShapeStroke::new(
+                Shape::Ellipse(Ellipse {
+                    radii: na::vector![40.0, 30.0],
+                    transform: Transform::new_w_isometry(na::Isometry2::new(
+                        na::vector![50.0, 50.0],
+                        0.0,
+                    )),
+                }),
+                Style::Smooth(options),
+            )),
+            None,
+        );
+        let bounds = Aabb::new(na::point![0.0, 0.0], na::point![100.0, 100.0]);
+        let render = |draw: &dyn Fn(&mut piet_cairo::CairoRenderContext)| {
+            render::Image::gen_with_piet(
+                |piet_cx| {
+                    draw(piet_cx);
+                    Ok(())
+                },
+                bounds,
+                1.0,
+            )
+            .unwrap()
+        };
+        let alpha_at = |image: &render::Image, x: u32, y: u32| {
+            image.data[((y * image.pixel_width + x) * 4 + 3) as usize]
+        };
+
+        let full = render(&|piet_cx| store.draw_strokes_immediate(piet_cx, bounds, bounds, 1.0));
+        let outlines = render(&|piet_cx| store.draw_outlines_immediate(piet_cx, bounds, 1.0));
+        assert!(alpha_at(&full, 50, 50) > 0);
+        // the outline is drawn, the fill is not
+        assert!(outlines
+            .data
+            .iter()
+            .skip(3)
+            .step_by(4)
+            .any(|alpha| *alpha > 0));
+        assert!((5..15).any(|x| alpha_at(&outlines, x, 50) > 0));
+        assert_eq!(alpha_at(&outlines, 50, 50), 0);
+        assert_eq!(alpha_at(&outlines, 30, 50), 0);
+    }
+
+    #[test]
+    fn stale_generation() {
+        let render_comp = RenderComponent::default();
+        let first = render_comp.next_generation();
+        assert!(!first.is_stale());
+        let second = render_comp.next_generation();
+        assert!(first.is_stale());
+        assert!(!second.is_stale());
+        render_comp.generation.fetch_add(1, Ordering::AcqRel);
+        assert!(second.is_stale());
+    }
+}
@@ -3,8 +3,11 @@ use super::render_comp::RenderCompState;
 use super::{StrokeKey, StrokeStore};
 use crate::strokes::content::GeneratedContentImages;
 use crate::strokes::Stroke;
-use p2d::bounding_volume::Aabb;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use rnote_compose::shapes::Shapeable;
+use rnote_compose::{Color, Transform};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -20,11 +23,161 @@ impl Default for SelectionComponent {
     }
 }
 
+/// A summary of the styles of the selected strokes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SelectionStyleSummary {
+    /// The stroke color shared by all selected strokes that have one.
+    ///
+    /// None when the colors are mixed or none of the strokes has a stroke color.
+    pub stroke_color: Option<Color>,
+    /// The fill color shared by all selected strokes that have one.
+    ///
+    /// None when the colors are mixed or none of the strokes has a fill color.
+    pub fill_color: Option<Color>,
+    /// The stroke width shared by all selected strokes that have one.
+    ///
+    /// None when the widths are mixed or none of the strokes has a stroke width.
+    pub stroke_width: Option<f64>,
+    /// Whether the selected strokes have differing stroke colors, fill colors or stroke widths.
+    pub mixed: bool,
+}
+
+/// The number and kinds of the selected strokes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SelectionSummary {
+    /// The total number of selected strokes.
+    pub count: usize,
+    /// The number of selected brush strokes.
+    pub n_brush: usize,
+    /// The number of selected shapes.
+    pub n_shape: usize,
+    /// The number of selected text strokes.
+    pub n_text: usize,
+    /// The number of selected vector and bitmap images.
+    pub n_image: usize,
+    /// The bounds of the selected strokes. None if nothing is selected.
+    pub bounds: Option<Aabb>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SelectedKind {
+    Brush,
+    Shape,
+    Text,
+    Image,
+}
+
+impl SelectedKind {
+    fn new(stroke: &Stroke) -> Self {
+        match stroke {
+            Stroke::BrushStroke(_) => Self::Brush,
+            Stroke::ShapeStroke(_) => Self::Shape,
+            Stroke::TextStroke(_) => Self::Text,
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => Self::Image,
+        }
+    }
+}
+
+/// Incrementally updated counters of the selected, not trashed strokes.
+///
+/// Kept in sync with the selection and trash components so that summarizing the selection
+/// doesn't need to iterate the entire store.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SelectionCounters {
+    keys: HashMap<StrokeKey, SelectedKind>,
+    n_brush: usize,
+    n_shape: usize,
+    n_text: usize,
+    n_image: usize,
+}
+
+impl SelectionCounters {
+    fn counter_mut(&mut self, kind: SelectedKind) -> &mut usize {
+        match kind {
+            SelectedKind::Brush => &mut self.n_brush,
+            SelectedKind::Shape => &mut self.n_shape,
+            SelectedKind::Text => &mut self.n_text,
+            SelectedKind::Image => &mut self.n_image,
+        }
+    }
+
+    fn insert(&mut self, key: StrokeKey, kind: SelectedKind) {
+        if let Some(prev) = self.keys.insert(key, kind) {
+            *self.counter_mut(prev) -= 1;
+        }
+        *self.counter_mut(kind) += 1;
+    }
+
+    pub(crate) fn remove(&mut self, key: StrokeKey) {
+        if let Some(kind) = self.keys.remove(&key) {
+            *self.counter_mut(kind) -= 1;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// How the stroke width is updated by a [StyleUpdate].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StrokeWidthUpdate {
+    /// Replace the stroke width.
+    Absolute(f64),
+    /// Multiply the current stroke width.
+    Multiply(f64),
+}
+
+impl StrokeWidthUpdate {
+    /// The new stroke width. None if it would not be positive.
+    pub fn apply(&self, stroke_width: f64) -> Option<f64> {
+        let new_width = match self {
+            Self::Absolute(width) => *width,
+            Self::Multiply(factor) => stroke_width * factor,
+        };
+        (new_width.is_finite() && new_width > 0.0).then_some(new_width)
+    }
+}
+
+/// An update of the style of strokes. Fields that are None are left untouched.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StyleUpdate {
+    /// The new stroke color. Also applied to the text color of text strokes.
+    pub stroke_color: Option<Color>,
+    /// The new fill color.
+    pub fill_color: Option<Color>,
+    /// The stroke width update.
+    pub stroke_width: Option<StrokeWidthUpdate>,
+}
+
 /// Systems that are related to selecting.
 impl StrokeStore {
+    /// Apply the style update to all selected strokes.
+    ///
+    /// Returns the keys of the modified strokes, which then need to update their geometry and rendering.
+    pub(crate) fn change_selection_style(&mut self, update: &StyleUpdate) -> Vec<StrokeKey> {
+        let selection = self.selection_keys_as_rendered();
+
+        selection
+            .into_iter()
+            .filter(|&key| {
+                let modified = Arc::make_mut(&mut self.stroke_components)
+                    .get_mut(key)
+                    .map(Arc::make_mut)
+                    .map(|stroke| stroke.apply_style_update(update))
+                    .unwrap_or(false);
+                if modified {
+                    self.set_rendering_dirty(key);
+                }
+                modified
+            })
+            .collect()
+    }
+
     /// Rebuild the slotmap with empty selection components with the keys returned from the stroke components.
     pub(crate) fn rebuild_selection_components_slotmap(&mut self) {
         self.selection_components = Arc::new(slotmap::SecondaryMap::new());
+        self.selection_counters.clear();
         self.stroke_components.keys().for_each(|key| {
             Arc::make_mut(&mut self.selection_components)
                 .insert(key, Arc::new(SelectionComponent::default()));
@@ -44,14 +197,60 @@ impl StrokeStore {
     }
 
     /// Set if the stroke is currently selected.
+    ///
+    /// Moves the stroke to the front of the render order, except in read-only mode where that would be a modification.
     pub(crate) fn set_selected(&mut self, key: StrokeKey, selected: bool) {
-        if let Some(selection_comp) = Arc::make_mut(&mut self.selection_components)
+        if self.set_selected_keep_order(key, selected) && !self.read_only {
+            self.update_chrono_to_last(key);
+        }
+    }
+
+    /// Set if the stroke is currently selected, without changing its position in the render order.
+    ///
+    /// Used when deselecting and when restoring the selection from the history,
+    /// otherwise strokes that were sent to the back or lowered would be raised to the front again.
+    /// Returns false if the stroke doesn't have a selection component.
+    pub(crate) fn set_selected_keep_order(&mut self, key: StrokeKey, selected: bool) -> bool {
+        let exists = if let Some(selection_comp) = Arc::make_mut(&mut self.selection_components)
             .get_mut(key)
             .map(Arc::make_mut)
         {
             selection_comp.selected = selected;
+            true
+        } else {
+            false
+        };
+        self.update_selection_counters(key);
+        exists
+    }
 
-            self.update_chrono_to_last(key);
+    /// Update the selection counters for the stroke after its selected or trashed state has changed.
+    pub(crate) fn update_selection_counters(&mut self, key: StrokeKey) {
+        match self.stroke_components.get(key) {
+            Some(stroke)
+                if self.selected(key).unwrap_or(false) && !self.trashed(key).unwrap_or(false) =>
+            {
+                self.selection_counters
+                    .insert(key, SelectedKind::new(stroke));
+            }
+            _ => self.selection_counters.remove(key),
+        }
+    }
+
+    /// Summarize the number and kinds of the selected strokes.
+    ///
+    /// Only iterates the selected strokes for the bounds, the counts are tracked incrementally.
+    pub(crate) fn selection_summary(&self) -> SelectionSummary {
+        let counters = &self.selection_counters;
+        let keys = counters.keys.keys().copied().collect::<Vec<StrokeKey>>();
+
+        SelectionSummary {
+            count: keys.len(),
+            n_brush: counters.n_brush,
+            n_shape: counters.n_shape,
+            n_text: counters.n_text,
+            n_image: counters.n_image,
+            bounds: self.bounds_for_strokes(&keys),
         }
     }
 
@@ -61,6 +260,48 @@ impl StrokeStore {
         })
     }
 
+    /// Set if the strokes for the given keys are selected, without changing their position in the render order.
+    pub(crate) fn set_selected_keys_keep_order(&mut self, keys: &[StrokeKey], selected: bool) {
+        keys.iter().for_each(|&key| {
+            self.set_selected_keep_order(key, selected);
+        })
+    }
+
+    /// Summarize the styles of the strokes for the given keys.
+    pub(crate) fn selection_style_summary(&self, keys: &[StrokeKey]) -> SelectionStyleSummary {
+        /// Returns the shared value, or None and sets `mixed` if the values differ.
+        fn shared<T: PartialEq + Copy>(
+            values: impl Iterator<Item = T>,
+            mixed: &mut bool,
+        ) -> Option<T> {
+            let mut shared = None;
+            for value in values {
+                match shared {
+                    None => shared = Some(value),
+                    Some(s) if s != value => {
+                        *mixed = true;
+                        return None;
+                    }
+                    Some(_) => {}
+                }
+            }
+            shared
+        }
+
+        let strokes = self.get_strokes_ref(keys);
+        let mut mixed = false;
+        let stroke_color = shared(strokes.iter().filter_map(|s| s.stroke_color()), &mut mixed);
+        let fill_color = shared(strokes.iter().filter_map(|s| s.fill_color()), &mut mixed);
+        let stroke_width = shared(strokes.iter().filter_map(|s| s.stroke_width()), &mut mixed);
+
+        SelectionStyleSummary {
+            stroke_color,
+            fill_color,
+            stroke_width,
+            mixed,
+        }
+    }
+
     pub(crate) fn selection_keys_unordered(&self) -> Vec<StrokeKey> {
         self.stroke_components
             .keys()
@@ -92,12 +333,83 @@ impl StrokeStore {
         self.bounds_for_strokes(&self.selection_keys_unordered())
     }
 
+    /// Grow the selection by the strokes whose hitboxes intersect the hitboxes of a selected stroke.
+    ///
+    /// Repeats for the given number of iterations, growing from the strokes added in the previous one,
+    /// or until no more strokes are touched when `None`. Trashed and locked strokes are skipped.
+    ///
+    /// Returns the keys of the newly selected strokes.
+    pub(crate) fn grow_selection(&mut self, iterations: Option<usize>) -> Vec<StrokeKey> {
+        let mut grown = vec![];
+        let mut frontier = self.selection_keys_unordered();
+        let mut iteration = 0;
+
+        while !frontier.is_empty() && iterations.map_or(true, |n| iteration < n) {
+            let mut touched = HashSet::new();
+            for key in frontier {
+                let Some(stroke) = self.stroke_components.get(key) else {
+                    continue;
+                };
+                let hitboxes = stroke.hitboxes();
+                touched.extend(
+                    self.key_tree
+                        .keys_intersecting_bounds(stroke.bounds())
+                        .into_iter()
+                        .filter(|&candidate| {
+                            !self.selected(candidate).unwrap_or(true)
+                                && !self.trashed(candidate).unwrap_or(false)
+                                && !self.locked(candidate).unwrap_or(false)
+                        })
+                        .filter(|&candidate| {
+                            self.stroke_components.get(candidate).is_some_and(|other| {
+                                other.hitboxes().into_iter().any(|other_hitbox| {
+                                    hitboxes
+                                        .iter()
+                                        .any(|hitbox| hitbox.intersects(&other_hitbox))
+                                })
+                            })
+                        })
+                        .collect::<Vec<StrokeKey>>(),
+                );
+            }
+            frontier = touched.into_iter().collect();
+            self.set_selected_keys(&frontier, true);
+            grown.extend_from_slice(&frontier);
+            iteration += 1;
+        }
+        grown
+    }
+
+    /// The transform of the last committed selector gesture, relative to the center of the selection bounds before it.
+    ///
+    /// Kept across selection changes, but cleared together with the strokes of the document.
+    pub(crate) fn last_selection_transform(&self) -> Option<Transform> {
+        self.last_selection_transform
+    }
+
+    pub(crate) fn set_last_selection_transform(&mut self, transform: Transform) {
+        self.last_selection_transform = Some(transform);
+    }
+
     /// Duplicate the selected keys.
     ///
     /// The returned, duplicated strokes then need to update their geometry and rendering.
     pub(crate) fn duplicate_selection(&mut self) -> Vec<StrokeKey> {
+        // Offsetting the new selected stroke to make the duplication apparent
+        self.duplicate_selection_w_offset(Stroke::IMPORT_OFFSET_DEFAULT)
+    }
+
+    /// Duplicate the selected keys and translate the duplicates by the given offset.
+    ///
+    /// The duplicated strokes are selected, the originals get deselected.
+    ///
+    /// The caller must update the geometry and rendering for the new strokes, if needed.
+    pub(crate) fn duplicate_selection_w_offset(
+        &mut self,
+        offset: na::Vector2<f64>,
+    ) -> Vec<StrokeKey> {
         let old_selected = self.selection_keys_as_rendered();
-        self.set_selected_keys(&old_selected, false);
+        self.set_selected_keys_keep_order(&old_selected, false);
 
         let new_selected = old_selected
             .iter()
@@ -125,10 +437,194 @@ impl StrokeStore {
             })
             .collect::<Vec<StrokeKey>>();
 
-        // Offsetting the new selected stroke to make the duplication apparent
-        self.translate_strokes(&new_selected, Stroke::IMPORT_OFFSET_DEFAULT);
-        self.translate_strokes_images(&new_selected, Stroke::IMPORT_OFFSET_DEFAULT);
+        if offset != na::Vector2::zeros() {
+            self.translate_strokes(&new_selected, offset);
+            self.translate_strokes_images(&new_selected, offset);
+        }
 
         new_selected
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineSnapshot;
+    use crate::strokes::textstroke::TextStyle;
+    use crate::strokes::TextStroke;
+    use crate::testsupport::insert_rect;
+    use crate::Engine;
+    use std::time::Instant;
+
+    fn recounted_summary(store: &StrokeStore) -> SelectionSummary {
+        let keys = store.selection_keys_unordered();
+        let strokes = store.get_strokes_ref(&keys);
+        let n_kind = |f: fn(&Stroke) -> bool| strokes.iter().filter(|s| f(s)).count();
+
+        SelectionSummary {
+            count: keys.len(),
+            n_brush: n_kind(|s| matches!(s, Stroke::BrushStroke(_))),
+            n_shape: n_kind(|s| matches!(s, Stroke::ShapeStroke(_))),
+            n_text: n_kind(|s| matches!(s, Stroke::TextStroke(_))),
+            n_image: n_kind(|s| matches!(s, Stroke::VectorImage(_) | Stroke::BitmapImage(_))),
+            bounds: store.bounds_for_strokes(&keys),
+        }
+    }
+
+    #[test]
+    fn selection_counters_stay_consistent() {
+        let mut store = StrokeStore::default();
+        let mut keys = (0..3)
+            .map(|i| {
+                let offset = i as f64 * 200.0;
+                insert_rect(
+                    &mut store,
+                    Aabb::new(na::point![offset, 0.0], na::point![offset + 100.0, 50.0]),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        keys.push(store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("text"),
+                na::vector![0.0, 200.0],
+                TextStyle::default(),
+            )),
+            None,
+        ));
+        store.record(Instant::now());
+
+        let assert_consistent = |store: &StrokeStore| {
+            assert_eq!(store.selection_summary(), recounted_summary(store));
+        };
+        assert_consistent(&store);
+
+        store.set_selected_keys(&keys, true);
+        assert_consistent(&store);
+        assert_eq!(store.selection_summary().n_shape, 3);
+        assert_eq!(store.selection_summary().n_text, 1);
+
+        store.set_selected(keys[0], false);
+        // selecting twice must not count twice
+        store.set_selected(keys[1], true);
+        assert_consistent(&store);
+
+        store.set_trashed(keys[1], true);
+        assert_consistent(&store);
+        store.set_trashed(keys[1], false);
+        assert_consistent(&store);
+
+        store.set_trashed_keys(&keys[2..], true);
+        assert_consistent(&store);
+        assert_eq!(store.selection_summary().count, 1);
+
+        store.remove_stroke(keys[1]);
+        assert_consistent(&store);
+
+        store.set_selected(keys[0], true);
+        store.record(Instant::now());
+        store.undo(Instant::now());
+        assert_consistent(&store);
+        store.redo(Instant::now());
+        assert_consistent(&store);
+    }
+
+    #[test]
+    fn deselecting_preserves_render_order() {
+        let mut engine = Engine::default();
+        let keys = (0..3)
+            .map(|i| {
+                let offset = i as f64 * 200.0;
+                insert_rect(
+                    &mut engine.store,
+                    Aabb::new(na::point![offset, 0.0], na::point![offset + 100.0, 50.0]),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        engine.store.record(Instant::now());
+
+        let bounds_mins_x = keys
+            .iter()
+            .map(|&key| engine.store.bounds_for_strokes(&[key]).unwrap().mins[0])
+            .collect::<Vec<f64>>();
+
+        // selecting raises the stroke to the front
+        engine.store.set_selected(keys[1], true);
+        assert_eq!(
+            engine.store.stroke_keys_as_rendered(),
+            vec![keys[0], keys[2], keys[1]]
+        );
+        engine.store.move_strokes_to_back(&keys[1..2]);
+        let reordered = vec![keys[1], keys[0], keys[2]];
+        assert_eq!(engine.store.stroke_keys_as_rendered(), reordered);
+
+        // deselecting doesn't undo the reordering
+        engine
+            .store
+            .set_selected_keys_keep_order(&keys[1..2], false);
+        assert_eq!(engine.store.stroke_keys_as_rendered(), reordered);
+        engine.store.record(Instant::now());
+
+        // the order survives saving and loading
+        let rendered_bounds = |store: &StrokeStore| {
+            store
+                .stroke_keys_as_rendered()
+                .into_iter()
+                .map(|key| store.bounds_for_strokes(&[key]).unwrap().mins[0])
+                .collect::<Vec<f64>>()
+        };
+        let bytes =
+            futures::executor::block_on(engine.save_as_rnote_bytes(String::from("test.rnote")))
+                .unwrap()
+                .unwrap();
+        let snapshot =
+            futures::executor::block_on(EngineSnapshot::load_from_rnote_bytes(bytes)).unwrap();
+        let mut loaded = Engine::default();
+        let _ = loaded.load_snapshot(snapshot);
+        assert_eq!(
+            rendered_bounds(&loaded.store),
+            vec![bounds_mins_x[1], bounds_mins_x[0], bounds_mins_x[2]]
+        );
+        assert_eq!(
+            rendered_bounds(&loaded.store),
+            rendered_bounds(&engine.store)
+        );
+    }
+
+    #[test]
+    fn grow_selection_along_chain() {
+        let mut store = StrokeStore::default();
+        // overlapping rectangles, each one crossing the outline of the next
+        let chain = (0..4)
+            .map(|i| {
+                let x = i as f64 * 150.0;
+                insert_rect(
+                    &mut store,
+                    Aabb::new(na::point![x, 0.0], na::point![x + 200.0, 100.0]),
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        // inside the first rectangle, its bounds intersect but not its hitboxes
+        let inner = insert_rect(
+            &mut store,
+            Aabb::new(na::point![40.0, 30.0], na::point![70.0, 60.0]),
+        );
+        let locked = insert_rect(
+            &mut store,
+            Aabb::new(na::point![600.0, 50.0], na::point![700.0, 150.0]),
+        );
+        store.set_locked(locked, true);
+        store.set_selected(chain[0], true);
+
+        assert_eq!(store.grow_selection(Some(1)), vec![chain[1]]);
+        let mut grown = store.grow_selection(None);
+        grown.sort();
+        let mut expected = chain[2..].to_vec();
+        expected.sort();
+        assert_eq!(grown, expected);
+        assert!(!store.selected(inner).unwrap());
+        assert!(!store.selected(locked).unwrap());
+        // the fixpoint is reached
+        assert!(store.grow_selection(None).is_empty());
+        assert_eq!(store.selection_summary().count, 4);
+    }
+}
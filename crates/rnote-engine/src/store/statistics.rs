@@ -0,0 +1,130 @@
+// Imports
+use super::StrokeStore;
+use crate::strokes::Stroke;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+use rnote_compose::shapes::Shapeable;
+
+/// Statistics about the strokes in the store.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreStatistics {
+    /// The number of strokes that are not trashed.
+    pub strokes: usize,
+    /// The number of brush strokes that are not trashed.
+    pub brush_strokes: usize,
+    /// The number of shape strokes that are not trashed.
+    pub shape_strokes: usize,
+    /// The number of text strokes that are not trashed.
+    pub text_strokes: usize,
+    /// The number of bitmap and vector images that are not trashed.
+    pub images: usize,
+    /// The number of segments of the pen paths of the brush strokes that are not trashed.
+    pub pen_path_segments: usize,
+    /// The number of trashed strokes.
+    pub trashed_strokes: usize,
+    /// The estimated memory of the cached render images, in bytes.
+    pub render_images_size: usize,
+    /// The bounds of the strokes that are not trashed. None when there are none.
+    pub content_bounds: Option<Aabb>,
+}
+
+impl StrokeStore {
+    /// Gather statistics about the strokes, without cloning them.
+    pub fn statistics(&self) -> StoreStatistics {
+        let mut statistics = StoreStatistics::default();
+        for (key, stroke) in self.stroke_components.iter() {
+            if self.trashed(key).unwrap_or(false) {
+                statistics.trashed_strokes += 1;
+                continue;
+            }
+            statistics.strokes += 1;
+            match stroke.as_ref() {
+                Stroke::BrushStroke(brushstroke) => {
+                    statistics.brush_strokes += 1;
+                    statistics.pen_path_segments += brushstroke.path.segments.len();
+                }
+                Stroke::ShapeStroke(_) => statistics.shape_strokes += 1,
+                Stroke::TextStroke(_) => statistics.text_strokes += 1,
+                Stroke::VectorImage(_) | Stroke::BitmapImage(_) => statistics.images += 1,
+            }
+            let bounds = stroke.bounds();
+            statistics.content_bounds = Some(
+                statistics
+                    .content_bounds
+                    .map_or(bounds, |content_bounds| content_bounds.merged(&bounds)),
+            );
+        }
+        statistics.render_images_size = self
+            .render_components
+            .values()
+            .map(|render_comp| render_comp.images_size())
+            .sum::<usize>()
+            + self
+                .selection_composite()
+                .map_or(0, |composite| composite.image_size());
+        statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strokes::textstroke::TextStyle;
+    use crate::strokes::{BrushStroke, TextStroke};
+    use crate::testsupport::insert_rect;
+    use rnote_compose::penpath::{Element, Segment};
+    use rnote_compose::Style;
+
+    #[test]
+    fn statistics_per_kind() {
+        let mut store = StrokeStore::default();
+        let mut brush_keys = vec![];
+        for i in 0..2 {
+            let mut brushstroke =
+                BrushStroke::new(Element::new(na::vector![0.0, 0.0], 0.5), Style::default());
+            brushstroke.extend_w_segments((1..=3 + i).map(|j| Segment::LineTo {
+                end: Element::new(na::vector![10.0 * j as f64, 5.0], 0.5),
+            }));
+            brush_keys.push(store.insert_stroke(Stroke::BrushStroke(brushstroke), None));
+        }
+        let _ = insert_rect(
+            &mut store,
+            Aabb::new(na::point![100.0, 100.0], na::point![200.0, 300.0]),
+        );
+        let _ = store.insert_stroke(
+            Stroke::TextStroke(TextStroke::new(
+                String::from("text"),
+                na::vector![-50.0, 0.0],
+                TextStyle::default(),
+            )),
+            None,
+        );
+
+        let statistics = store.statistics();
+        assert_eq!(statistics.strokes, 4);
+        assert_eq!(statistics.brush_strokes, 2);
+        assert_eq!(statistics.shape_strokes, 1);
+        assert_eq!(statistics.text_strokes, 1);
+        assert_eq!(statistics.images, 0);
+        assert_eq!(statistics.pen_path_segments, 3 + 4);
+        assert_eq!(statistics.trashed_strokes, 0);
+        let content_bounds = statistics.content_bounds.unwrap();
+        assert!(content_bounds.mins[0] <= -50.0 && content_bounds.maxs[1] >= 300.0);
+
+        // trashing moves the stroke into the trashed bucket
+        store.set_trashed(brush_keys[1], true);
+        let statistics = store.statistics();
+        assert_eq!(statistics.strokes, 3);
+        assert_eq!(statistics.brush_strokes, 1);
+        assert_eq!(statistics.pen_path_segments, 3);
+        assert_eq!(statistics.trashed_strokes, 1);
+        store.set_trashed(brush_keys[1], false);
+        assert_eq!(store.statistics().brush_strokes, 2);
+
+        // the cached images are accounted for
+        assert_eq!(statistics.render_images_size, 0);
+        let viewport = Aabb::new(na::point![-100.0, -100.0], na::point![500.0, 500.0]);
+        let keys = store.stroke_keys_unordered();
+        store.regenerate_rendering_for_strokes(&keys, viewport, 1.0);
+        assert!(store.statistics().render_images_size > 0);
+    }
+}
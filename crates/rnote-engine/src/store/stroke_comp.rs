@@ -2,18 +2,30 @@
 use super::render_comp::RenderCompState;
 use super::StrokeKey;
 use crate::engine::StrokeContent;
+use crate::pens::pensconfig::selectorconfig::WidthScalePolicy;
 use crate::strokes::{Content, Stroke};
 use crate::{StrokeStore, WidgetFlags};
 use geo::intersects::Intersects;
 use geo::prelude::Contains;
 use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::penpath::Element;
-use rnote_compose::shapes::Shapeable;
+use rnote_compose::shapes::{Shape, Shapeable};
 use rnote_compose::transform::Transformable;
 use rnote_compose::Color;
 use std::sync::Arc;
 use tracing::error;
 
+/// Transient state of a stroke during an interactive scaling.
+///
+/// The stroke width is always derived from the width before the scaling started,
+/// so that repeated incremental scaling does not accumulate errors.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WidthScaling {
+    start_width: f64,
+    scale: f64,
+    policy: WidthScalePolicy,
+}
+
 /// Systems that are related to the stroke components.
 impl StrokeStore {
     /// Gets a immutable reference to a stroke.
@@ -29,7 +41,6 @@ impl StrokeStore {
     }
 
     /// Gets the stroke by cloning the Arc that is wrapped around it.
-    #[allow(unused)]
     pub(crate) fn get_stroke_arc(&self, key: StrokeKey) -> Option<Arc<Stroke>> {
         self.stroke_components.get(key).cloned()
     }
@@ -233,6 +244,18 @@ impl StrokeStore {
         });
     }
 
+    /// Replace the strokes with the given ones, e.g. to restore an earlier state of them.
+    ///
+    /// Keys that are not in the store are ignored. The strokes then need to update their rendering.
+    pub(crate) fn replace_stroke_arcs(&mut self, strokes: &[(StrokeKey, Arc<Stroke>)]) {
+        for (key, stroke) in strokes {
+            if let Some(current) = Arc::make_mut(&mut self.stroke_components).get_mut(*key) {
+                *current = Arc::clone(stroke);
+                self.key_tree.update_with_key(*key, stroke.bounds());
+            }
+        }
+    }
+
     /// Rotate the stroke by the given angle (in radians) around the center.
     ///
     /// Strokes then need to update their rendering.
@@ -256,6 +279,26 @@ impl StrokeStore {
         });
     }
 
+    /// Shear the strokes by the shear-factors around the pivot.
+    ///
+    /// Strokes then need to update their rendering.
+    pub(crate) fn shear_strokes(
+        &mut self,
+        keys: &[StrokeKey],
+        shear: na::Vector2<f64>,
+        pivot: na::Point2<f64>,
+    ) {
+        keys.iter().for_each(|&key| {
+            if let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
+                .get_mut(key)
+                .map(Arc::make_mut)
+            {
+                stroke.shear(shear, pivot);
+                self.key_tree.update_with_key(key, stroke.bounds());
+            }
+        });
+    }
+
     /// Change the stroke and text color for the given keys.
     ///
     /// The strokes then need to update their rendering.
@@ -363,6 +406,175 @@ impl StrokeStore {
         widget_flags
     }
 
+    /// Set the opacity of the given keys.
+    ///
+    /// Replaces the alpha of the stroke, text and fill colors, images have their opacity replaced.
+    /// The opacity is clamped to a minimum of [Stroke::OPACITY_MIN].
+    ///
+    /// The strokes then need to update their rendering.
+    pub(crate) fn set_strokes_opacity(&mut self, keys: &[StrokeKey], opacity: f64) -> WidgetFlags {
+        self.update_strokes_opacity(keys, |_| opacity)
+    }
+
+    /// Insert a vertex at the position into the segment of a polyline or polygon shape stroke.
+    ///
+    /// The vertex becomes the point following the segment start. Returns whether it was inserted.
+    ///
+    /// The stroke then needs to update its rendering.
+    pub(crate) fn insert_shape_vertex(
+        &mut self,
+        key: StrokeKey,
+        segment_idx: usize,
+        pos: na::Vector2<f64>,
+    ) -> bool {
+        let Some(Stroke::ShapeStroke(shapestroke)) = self.get_stroke_mut(key) else {
+            return false;
+        };
+        let n_segments = match &shapestroke.shape {
+            Shape::Polyline(polyline) => polyline.segment_midpoints().len(),
+            Shape::Polygon(polygon) => polygon.segment_midpoints().len(),
+            _ => return false,
+        };
+        if segment_idx >= n_segments {
+            return false;
+        }
+        match &mut shapestroke.shape {
+            Shape::Polyline(polyline) => polyline.insert_point(segment_idx + 1, pos),
+            Shape::Polygon(polygon) => polygon.insert_point(segment_idx + 1, pos),
+            _ => return false,
+        }
+        self.update_geometry_for_stroke(key);
+        self.set_rendering_dirty(key);
+        true
+    }
+
+    /// Remove the vertex of a polyline or polygon shape stroke.
+    ///
+    /// Rejected when a polyline would be left with fewer than two or a polygon with fewer than three points.
+    /// Returns whether it was removed.
+    ///
+    /// The stroke then needs to update its rendering.
+    pub(crate) fn remove_shape_vertex(&mut self, key: StrokeKey, vertex_idx: usize) -> bool {
+        let Some(Stroke::ShapeStroke(shapestroke)) = self.get_stroke_mut(key) else {
+            return false;
+        };
+        let removed = match &mut shapestroke.shape {
+            Shape::Polyline(polyline) => polyline.remove_point(vertex_idx).is_some(),
+            Shape::Polygon(polygon) => polygon.remove_point(vertex_idx).is_some(),
+            _ => false,
+        };
+        if removed {
+            self.update_geometry_for_stroke(key);
+            self.set_rendering_dirty(key);
+        }
+        removed
+    }
+
+    /// Resize the image strokes of the given keys to their original size about their centers.
+    ///
+    /// Returns the keys of the image strokes, they then need to update their geometry and rendering.
+    pub(crate) fn reset_images_to_original_size(&mut self, keys: &[StrokeKey]) -> Vec<StrokeKey> {
+        keys.iter()
+            .copied()
+            .filter(|&key| {
+                let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
+                    .get_mut(key)
+                    .map(Arc::make_mut)
+                else {
+                    return false;
+                };
+                match stroke {
+                    Stroke::BitmapImage(bitmapimage) => bitmapimage.reset_to_original_size(),
+                    Stroke::VectorImage(vectorimage) => vectorimage.reset_to_original_size(),
+                    _ => return false,
+                }
+                self.set_rendering_dirty(key);
+                true
+            })
+            .collect()
+    }
+
+    /// Replace the colors of the non-trashed strokes that are perceptually within `tolerance` of the old color.
+    ///
+    /// Only the selected strokes are considered when `selection_only` is true. Locked strokes are skipped.
+    /// See [Color::perceptual_distance] for the scale of the tolerance.
+    ///
+    /// Returns the keys of the modified strokes, which then need to update their rendering.
+    pub(crate) fn replace_color(
+        &mut self,
+        old_color: Color,
+        new_color: Color,
+        tolerance: f64,
+        scope: ColorReplaceScope,
+        selection_only: bool,
+    ) -> Vec<StrokeKey> {
+        let keys = if selection_only {
+            self.selection_keys_as_rendered()
+        } else {
+            self.stroke_keys_as_rendered()
+        };
+        let matches = |color: Color| color.perceptual_distance(old_color) <= tolerance;
+
+        keys.into_iter()
+            .filter(|&key| {
+                if self.locked(key).unwrap_or(false) {
+                    return false;
+                }
+                let modified = Arc::make_mut(&mut self.stroke_components)
+                    .get_mut(key)
+                    .map(Arc::make_mut)
+                    .map(|stroke| stroke.replace_color(matches, new_color, scope))
+                    .unwrap_or(false);
+                if modified {
+                    self.set_rendering_dirty(key);
+                }
+                modified
+            })
+            .collect()
+    }
+
+    /// Adjust the opacity of the given keys by adding the given (possibly negative) offset.
+    ///
+    /// The opacity is clamped to a minimum of [Stroke::OPACITY_MIN].
+    ///
+    /// The strokes then need to update their rendering.
+    pub(crate) fn adjust_strokes_opacity(
+        &mut self,
+        keys: &[StrokeKey],
+        offset: f64,
+    ) -> WidgetFlags {
+        self.update_strokes_opacity(keys, |alpha| alpha + offset)
+    }
+
+    fn update_strokes_opacity<F>(&mut self, keys: &[StrokeKey], update: F) -> WidgetFlags
+    where
+        F: Fn(f64) -> f64,
+    {
+        let mut widget_flags = WidgetFlags::default();
+
+        if keys.is_empty() {
+            return widget_flags;
+        }
+
+        keys.iter().for_each(|&key| {
+            if let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
+                .get_mut(key)
+                .map(Arc::make_mut)
+            {
+                let stroke_modified = stroke.update_opacity(&update);
+
+                if stroke_modified {
+                    self.set_rendering_dirty(key);
+                }
+            }
+        });
+
+        widget_flags.redraw = true;
+        widget_flags.store_modified = true;
+
+        widget_flags
+    }
+
     /// Rotate the stroke rendering images.
     ///
     /// The strokes then need to update their rendering.
@@ -393,6 +605,36 @@ impl StrokeStore {
         });
     }
 
+    /// Shear the stroke rendering images.
+    ///
+    /// The strokes then need to update their rendering.
+    pub(crate) fn shear_strokes_images(
+        &mut self,
+        keys: &[StrokeKey],
+        shear: na::Vector2<f64>,
+        pivot: na::Point2<f64>,
+    ) {
+        keys.iter().for_each(|&key| {
+            if let Some(render_comp) = self.render_components.get_mut(key) {
+                render_comp.state = RenderCompState::Dirty;
+
+                for image in render_comp.images.iter_mut() {
+                    image.shear(shear, pivot);
+                }
+
+                #[cfg(feature = "ui")]
+                match crate::render::Image::images_to_rendernodes(&render_comp.images) {
+                    Ok(rendernodes) => {
+                        render_comp.rendernodes = rendernodes;
+                    }
+                    Err(e) => error!(
+                        "Generating rendernodes from images failed while shearing stroke images, Err: {e:?}"
+                    ),
+                }
+            }
+        });
+    }
+
     /// Scale the strokes with the factor.
     ///
     /// The strokes then need to update their rendering.
@@ -405,12 +647,72 @@ impl StrokeStore {
                 {
                     // rotate the stroke geometry
                     stroke.scale(scale);
+                    if let Some(width_scaling) = self.width_scaling.get_mut(&key) {
+                        width_scaling.scale *= width_scaling.policy.width_scale(scale);
+                        stroke.set_stroke_width(width_scaling.start_width * width_scaling.scale);
+                    }
                     self.key_tree.update_with_key(key, stroke.bounds());
                 }
             }
         });
     }
 
+    /// Begin an interactive scaling of the strokes.
+    ///
+    /// Until the scaling is committed or aborted, the stroke widths are derived from the widths
+    /// at the start of the scaling instead of being scaled incrementally, according to the policy.
+    pub(crate) fn begin_width_scaling(&mut self, keys: &[StrokeKey], policy: WidthScalePolicy) {
+        for &key in keys {
+            let Some(start_width) = self
+                .stroke_components
+                .get(key)
+                .and_then(|stroke| stroke.stroke_width())
+            else {
+                continue;
+            };
+            self.width_scaling.insert(
+                key,
+                WidthScaling {
+                    start_width,
+                    scale: 1.0,
+                    policy,
+                },
+            );
+        }
+    }
+
+    /// Commit the interactive scaling of the strokes, settling the widths derived with the policy.
+    pub(crate) fn commit_width_scaling(&mut self, keys: &[StrokeKey]) {
+        for key in keys {
+            let Some(width_scaling) = self.width_scaling.remove(key) else {
+                continue;
+            };
+            if let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
+                .get_mut(*key)
+                .map(Arc::make_mut)
+            {
+                stroke.set_stroke_width(width_scaling.start_width * width_scaling.scale);
+            }
+        }
+    }
+
+    /// Abort the interactive scaling of the strokes, restoring the widths from before the scaling.
+    ///
+    /// The strokes then need to update their geometry and rendering.
+    pub(crate) fn abort_width_scaling(&mut self, keys: &[StrokeKey]) {
+        for key in keys {
+            let Some(width_scaling) = self.width_scaling.remove(key) else {
+                continue;
+            };
+            if let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
+                .get_mut(*key)
+                .map(Arc::make_mut)
+            {
+                stroke.set_stroke_width(width_scaling.start_width);
+            }
+        }
+    }
+
     /// Scale the stroke rendering images.
     ///
     /// The strokes then need to update their rendering.
@@ -465,164 +767,95 @@ impl StrokeStore {
     }
 
     /// Return the keys for stroke whose hitboxes are contained in the given polygon path.
+    ///
+    /// Locked strokes are skipped unless `include_locked` is true.
     pub(crate) fn strokes_hitboxes_contained_in_path_polygon(
-        &mut self,
+        &self,
         path: &[Element],
-        viewport: Aabb,
+        include_locked: bool,
     ) -> Vec<StrokeKey> {
-        let mut bounds = viewport;
-        for p in path {
-            bounds.take_point(p.pos.into());
-        }
-
-        let path_polygon = {
-            let selector_path_points = path
-                .iter()
-                .map(|element| geo::Coord {
-                    x: element.pos[0],
-                    y: element.pos[1],
-                })
-                .collect::<Vec<geo::Coord<f64>>>();
-
-            geo::Polygon::new(selector_path_points.into(), vec![])
-        };
-
-        self.keys_sorted_chrono_intersecting_bounds(bounds)
-            .into_iter()
-            .filter_map(|key| {
-                // skip if stroke is trashed
-                if self.trashed(key)? {
-                    return None;
-                }
-
-                let stroke = self.stroke_components.get(key)?;
-                let stroke_bounds = stroke.bounds();
-
-                if path_polygon.contains(&crate::utils::p2d_aabb_to_geo_polygon(stroke_bounds)) {
-                    return Some(key);
-                } else if path_polygon
-                    .intersects(&crate::utils::p2d_aabb_to_geo_polygon(stroke_bounds))
-                {
-                    for &hitbox_elem in stroke.hitboxes().iter() {
-                        if !path_polygon
-                            .contains(&crate::utils::p2d_aabb_to_geo_polygon(hitbox_elem))
-                        {
-                            return None;
-                        }
-                    }
-
-                    return Some(key);
-                }
-
-                None
-            })
-            .collect()
+        self.keys_hit_by(&HitQuery::contained_in_path_polygon(path), include_locked)
     }
 
     /// Return the keys for strokes whose hitboxes intersect in the given path.
+    ///
+    /// Locked strokes are skipped unless `include_locked` is true.
     pub(crate) fn strokes_hitboxes_intersect_path(
-        &mut self,
+        &self,
         path: &[Element],
-        viewport: Aabb,
+        include_locked: bool,
     ) -> Vec<StrokeKey> {
-        let mut bounds = viewport;
-        for p in path {
-            bounds.take_point(p.pos.into());
-        }
-
-        let path_linestring = {
-            let selector_path_points = path
-                .iter()
-                .map(|element| geo::Coord {
-                    x: element.pos[0],
-                    y: element.pos[1],
-                })
-                .collect::<Vec<geo::Coord<f64>>>();
-
-            geo::LineString::new(selector_path_points)
-        };
-
-        self.keys_sorted_chrono_intersecting_bounds(bounds)
-            .into_iter()
-            .filter_map(|key| {
-                // skip if stroke is trashed
-                if self.trashed(key)? {
-                    return None;
-                }
-
-                let stroke = self.stroke_components.get(key)?;
-                let stroke_bounds = stroke.bounds();
-
-                if path_linestring.intersects(&crate::utils::p2d_aabb_to_geo_polygon(stroke_bounds))
-                {
-                    for &hitbox_elem in stroke.hitboxes().iter() {
-                        if path_linestring
-                            .intersects(&crate::utils::p2d_aabb_to_geo_polygon(hitbox_elem))
-                        {
-                            return Some(key);
-                        }
-                    }
-                }
-
-                None
-            })
-            .collect()
+        self.keys_hit_by(&HitQuery::intersects_path(path), include_locked)
     }
 
     /// Return the keys for strokes whose hitboxes are contained in the given Aabb.
+    ///
+    /// Locked strokes are skipped unless `include_locked` is true.
     pub(crate) fn strokes_hitboxes_contained_in_aabb(
-        &mut self,
+        &self,
         aabb: Aabb,
-        viewport: Aabb,
+        include_locked: bool,
     ) -> Vec<StrokeKey> {
-        self.keys_sorted_chrono_intersecting_bounds(viewport.merged(&aabb))
-            .into_iter()
-            .filter_map(|key| {
-                // skip if stroke is trashed
-                if self.trashed(key)? {
-                    return None;
-                }
-
-                let stroke = self.stroke_components.get(key)?;
-                let stroke_bounds = stroke.bounds();
-
-                if aabb.contains(&stroke_bounds) {
-                    return Some(key);
-                } else if aabb.intersects(&stroke_bounds) {
-                    for &hitbox_elem in stroke.hitboxes().iter() {
-                        if !aabb.contains(&hitbox_elem) {
-                            return None;
-                        }
-                    }
-
-                    return Some(key);
-                }
+        self.keys_hit_by(&HitQuery::ContainedInAabb(aabb), include_locked)
+    }
 
-                None
-            })
-            .collect()
+    /// Return the keys for strokes where at least one of their hitboxes intersects the given Aabb.
+    ///
+    /// Locked strokes are skipped unless `include_locked` is true.
+    pub(crate) fn strokes_hitboxes_intersect_aabb(
+        &self,
+        aabb: Aabb,
+        include_locked: bool,
+    ) -> Vec<StrokeKey> {
+        self.keys_hit_by(&HitQuery::IntersectsAabb(aabb), include_locked)
     }
 
     /// Return the keys for strokes where the given coord is inside at least one of their hitboxes.
+    ///
+    /// Locked strokes are skipped unless `include_locked` is true.
     pub(crate) fn stroke_hitboxes_contain_coord(
         &self,
-        viewport: Aabb,
         coord: na::Vector2<f64>,
+        include_locked: bool,
     ) -> Vec<StrokeKey> {
-        let mut bounds = viewport;
-        bounds.take_point(coord.into());
+        self.keys_hit_by(&HitQuery::ContainsCoord(coord), include_locked)
+    }
+
+    /// The keys of the strokes that are hit by the query, in the order that they should be rendered.
+    ///
+    /// Only the strokes whose bounds intersect the bounds of the query are looked up in the key tree
+    /// and then tested exactly against their hitboxes.
+    fn keys_hit_by(&self, query: &HitQuery, include_locked: bool) -> Vec<StrokeKey> {
+        let Some(bounds) = query.bounds() else {
+            return vec![];
+        };
+        self.filter_keys_hit_by(
+            self.keys_sorted_chrono_intersecting_bounds(bounds),
+            query,
+            include_locked,
+        )
+    }
 
-        self.stroke_keys_as_rendered_intersecting_bounds(bounds)
+    /// Filter the candidate keys to the ones of strokes that are hit by the query,
+    /// skipping trashed strokes and locked ones unless `include_locked` is true.
+    fn filter_keys_hit_by(
+        &self,
+        candidates: Vec<StrokeKey>,
+        query: &HitQuery,
+        include_locked: bool,
+    ) -> Vec<StrokeKey> {
+        candidates
             .into_iter()
             .filter(|&key| {
-                if let Some(stroke) = self.stroke_components.get(key) {
-                    stroke
-                        .hitboxes()
-                        .into_iter()
-                        .any(|hitbox| hitbox.contains_local_point(&coord.into()))
-                } else {
-                    false
+                if self.trashed(key).unwrap_or(false)
+                    || (!include_locked && self.locked(key).unwrap_or(false))
+                {
+                    return false;
                 }
+                self.stroke_components
+                    .get(key)
+                    .map(|stroke| query.hits(stroke))
+                    .unwrap_or(false)
             })
             .collect()
     }
@@ -700,7 +933,7 @@ impl StrokeStore {
         let strokes = keys
             .iter()
             .filter_map(|k| {
-                self.set_selected(*k, false);
+                self.set_selected_keep_order(*k, false);
                 self.set_trashed(*k, true);
                 self.stroke_components.get(*k).cloned()
             })
@@ -749,3 +982,280 @@ impl StrokeStore {
             .collect()
     }
 }
+
+/// Which colors of the strokes are replaced by [StrokeStore::replace_color].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorReplaceScope {
+    /// The stroke colors, including the colors of text.
+    StrokeColors,
+    /// The fill colors, including the stops of gradients.
+    FillColors,
+    /// Both stroke and fill colors.
+    #[default]
+    All,
+}
+
+impl ColorReplaceScope {
+    pub(crate) fn stroke_colors(self) -> bool {
+        matches!(self, Self::StrokeColors | Self::All)
+    }
+
+    pub(crate) fn fill_colors(self) -> bool {
+        matches!(self, Self::FillColors | Self::All)
+    }
+}
+
+/// An exact hit test against the hitboxes of strokes.
+#[derive(Debug, Clone)]
+enum HitQuery {
+    /// All hitboxes are contained in the polygon.
+    ContainedInPolygon {
+        polygon: geo::Polygon<f64>,
+        bounds: Option<Aabb>,
+    },
+    /// At least one of the hitboxes intersects the line string.
+    IntersectsLineString {
+        linestring: geo::LineString<f64>,
+        bounds: Option<Aabb>,
+    },
+    /// All hitboxes are contained in the Aabb.
+    ContainedInAabb(Aabb),
+    /// At least one of the hitboxes intersects the Aabb.
+    IntersectsAabb(Aabb),
+    /// At least one of the hitboxes contains the coord.
+    ContainsCoord(na::Vector2<f64>),
+}
+
+impl HitQuery {
+    fn contained_in_path_polygon(path: &[Element]) -> Self {
+        Self::ContainedInPolygon {
+            polygon: geo::Polygon::new(Self::path_coords(path).into(), vec![]),
+            bounds: Self::path_bounds(path),
+        }
+    }
+
+    fn intersects_path(path: &[Element]) -> Self {
+        Self::IntersectsLineString {
+            linestring: geo::LineString::new(Self::path_coords(path)),
+            bounds: Self::path_bounds(path),
+        }
+    }
+
+    fn path_coords(path: &[Element]) -> Vec<geo::Coord<f64>> {
+        path.iter()
+            .map(|element| geo::Coord {
+                x: element.pos[0],
+                y: element.pos[1],
+            })
+            .collect()
+    }
+
+    fn path_bounds(path: &[Element]) -> Option<Aabb> {
+        let (first, rest) = path.split_first()?;
+        let mut bounds = Aabb::new(first.pos.into(), first.pos.into());
+        for element in rest {
+            bounds.take_point(element.pos.into());
+        }
+        Some(bounds)
+    }
+
+    /// The bounds that the bounds of a stroke need to intersect for it to possibly be hit.
+    ///
+    /// None when no stroke can be hit.
+    fn bounds(&self) -> Option<Aabb> {
+        match self {
+            Self::ContainedInPolygon { bounds, .. } | Self::IntersectsLineString { bounds, .. } => {
+                *bounds
+            }
+            Self::ContainedInAabb(aabb) | Self::IntersectsAabb(aabb) => Some(*aabb),
+            Self::ContainsCoord(coord) => Some(Aabb::new((*coord).into(), (*coord).into())),
+        }
+    }
+
+    fn hits(&self, stroke: &Stroke) -> bool {
+        let stroke_bounds = stroke.bounds();
+        match self {
+            Self::ContainedInPolygon { polygon, .. } => {
+                let stroke_bounds_polygon = crate::utils::p2d_aabb_to_geo_polygon(stroke_bounds);
+                if polygon.contains(&stroke_bounds_polygon) {
+                    true
+                } else if polygon.intersects(&stroke_bounds_polygon) {
+                    stroke.hitboxes().into_iter().all(|hitbox| {
+                        polygon.contains(&crate::utils::p2d_aabb_to_geo_polygon(hitbox))
+                    })
+                } else {
+                    false
+                }
+            }
+            Self::IntersectsLineString { linestring, .. } => {
+                linestring.intersects(&crate::utils::p2d_aabb_to_geo_polygon(stroke_bounds))
+                    && stroke.hitboxes().into_iter().any(|hitbox| {
+                        linestring.intersects(&crate::utils::p2d_aabb_to_geo_polygon(hitbox))
+                    })
+            }
+            Self::ContainedInAabb(aabb) => {
+                if aabb.contains(&stroke_bounds) {
+                    true
+                } else if aabb.intersects(&stroke_bounds) {
+                    stroke
+                        .hitboxes()
+                        .into_iter()
+                        .all(|hitbox| aabb.contains(&hitbox))
+                } else {
+                    false
+                }
+            }
+            Self::IntersectsAabb(aabb) => {
+                aabb.intersects(&stroke_bounds)
+                    && stroke
+                        .hitboxes()
+                        .into_iter()
+                        .any(|hitbox| aabb.intersects(&hitbox))
+            }
+            Self::ContainsCoord(coord) => stroke
+                .hitboxes()
+                .into_iter()
+                .any(|hitbox| hitbox.contains_local_point(&(*coord).into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strokes::ShapeStroke;
+    use rnote_compose::ext::AabbExt;
+    use rnote_compose::shapes::{Line, Rectangle};
+    use rnote_compose::Style;
+    use std::collections::HashSet;
+    use std::time::Instant;
+
+    /// A small deterministic pseudo random number generator, so that failures are reproducible.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next_f64(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn pos(&mut self, extents: f64) -> na::Vector2<f64> {
+            na::vector![self.next_f64() * extents, self.next_f64() * extents]
+        }
+
+        fn path(&mut self, extents: f64) -> Vec<Element> {
+            let center = self.pos(extents);
+            let radius = self.next_f64() * extents * 0.2;
+            (0..8)
+                .map(|i| {
+                    let angle = std::f64::consts::TAU * f64::from(i) / 8.0;
+                    let r = radius * (0.5 + self.next_f64() * 0.5);
+                    Element::new(
+                        center + na::vector![angle.cos(), angle.sin()] * r,
+                        Element::PRESSURE_DEFAULT,
+                    )
+                })
+                .collect()
+        }
+    }
+
+    fn insert_random_strokes(store: &mut StrokeStore, rng: &mut Lcg, n: usize, extents: f64) {
+        for i in 0..n {
+            let start = rng.pos(extents);
+            let end = start + rng.pos(extents * 0.02);
+            let shape = if i % 3 == 0 {
+                Shape::Rectangle(Rectangle::from_p2d_aabb(Aabb::new_positive(
+                    start.into(),
+                    end.into(),
+                )))
+            } else {
+                Shape::Line(Line::new(start, end))
+            };
+            store.insert_stroke(
+                Stroke::ShapeStroke(ShapeStroke::new(shape, Style::default())),
+                None,
+            );
+        }
+    }
+
+    fn brute_force(store: &StrokeStore, query: &HitQuery, include_locked: bool) -> Vec<StrokeKey> {
+        store.filter_keys_hit_by(store.keys_sorted_chrono(), query, include_locked)
+    }
+
+    fn key_set(keys: Vec<StrokeKey>) -> HashSet<StrokeKey> {
+        keys.into_iter().collect()
+    }
+
+    #[test]
+    fn indexed_hit_queries_match_brute_force() {
+        const EXTENTS: f64 = 4000.0;
+        let mut rng = Lcg(0x5eed);
+        let mut store = StrokeStore::default();
+        insert_random_strokes(&mut store, &mut rng, 2000, EXTENTS);
+        store.record(Instant::now());
+
+        // Modify the strokes in all ways that move their bounds, the index needs to follow along
+        let keys = store.keys_unordered();
+        for (i, &key) in keys.iter().enumerate() {
+            match i % 7 {
+                0 => store.translate_strokes(&[key], rng.pos(200.0)),
+                1 => store.rotate_strokes(&[key], rng.next_f64(), rng.pos(EXTENTS).into()),
+                2 => store.scale_strokes(&[key], na::vector![1.5, 0.5]),
+                3 => store.set_trashed(key, true),
+                4 => store.set_locked(key, true),
+                5 => {
+                    if let Some(Stroke::ShapeStroke(shapestroke)) = store.get_stroke_mut(key) {
+                        shapestroke.shape.translate(rng.pos(100.0));
+                    }
+                    store.update_geometry_for_stroke(key);
+                }
+                _ => {}
+            }
+        }
+        store.record(Instant::now());
+        // The index is rebuilt when the history is restored
+        store.undo(Instant::now());
+        store.redo(Instant::now());
+        assert_eq!(store.key_tree.len(), store.keys_unordered().len());
+
+        for i in 0..200 {
+            let include_locked = i % 2 == 0;
+            let aabb = Aabb::new_positive(rng.pos(EXTENTS).into(), rng.pos(EXTENTS).into());
+            let coord = rng.pos(EXTENTS);
+            let path = rng.path(EXTENTS);
+
+            let expected = brute_force(&store, &HitQuery::ContainedInAabb(aabb), include_locked);
+            assert_eq!(
+                key_set(store.strokes_hitboxes_contained_in_aabb(aabb, include_locked)),
+                key_set(expected)
+            );
+            let expected = brute_force(&store, &HitQuery::IntersectsAabb(aabb), include_locked);
+            assert_eq!(
+                key_set(store.strokes_hitboxes_intersect_aabb(aabb, include_locked)),
+                key_set(expected)
+            );
+            let expected = brute_force(&store, &HitQuery::ContainsCoord(coord), include_locked);
+            assert_eq!(
+                key_set(store.stroke_hitboxes_contain_coord(coord, include_locked)),
+                key_set(expected)
+            );
+            let expected = brute_force(
+                &store,
+                &HitQuery::contained_in_path_polygon(&path),
+                include_locked,
+            );
+            assert_eq!(
+                key_set(store.strokes_hitboxes_contained_in_path_polygon(&path, include_locked)),
+                key_set(expected)
+            );
+            let expected = brute_force(&store, &HitQuery::intersects_path(&path), include_locked);
+            assert_eq!(
+                key_set(store.strokes_hitboxes_intersect_path(&path, include_locked)),
+                key_set(expected)
+            );
+        }
+    }
+}
@@ -1,27 +1,45 @@
 // Imports
 use super::chrono_comp::StrokeLayer;
-use super::{StrokeKey, StrokeStore};
-use crate::strokes::{BrushStroke, Stroke};
+use super::{ChronoComponent, StrokeKey, StrokeStore};
+use crate::strokes::{BrushStroke, Stroke, StrokeKind};
 use crate::WidgetFlags;
 use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::shapes::Shapeable;
 use rnote_compose::PenPath;
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(default, rename = "trash_component")]
 pub struct TrashComponent {
     #[serde(rename = "trashed")]
     pub trashed: bool,
+    /// When the stroke was trashed, as unix timestamp in milliseconds.
+    #[serde(rename = "trashed_at")]
+    pub trashed_at: Option<i64>,
 }
 
 impl Default for TrashComponent {
     fn default() -> Self {
-        Self { trashed: false }
+        Self {
+            trashed: false,
+            trashed_at: None,
+        }
     }
 }
 
+/// Information about a trashed stroke, to browse the trashed strokes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrashedStrokeInfo {
+    pub key: StrokeKey,
+    pub kind: StrokeKind,
+    pub bounds: Aabb,
+    /// When the stroke was trashed, as unix timestamp in milliseconds.
+    pub trashed_at: i64,
+}
+
 /// Systems that are related to trashing.
 impl StrokeStore {
     /// Rebuild the slotmap with empty trash components with the keys returned from the stroke components.
@@ -48,14 +66,20 @@ impl StrokeStore {
             .get_mut(key)
             .map(Arc::make_mut)
         {
+            if trash && !trash_comp.trashed {
+                trash_comp.trashed_at = Some(ChronoComponent::now_timestamp());
+            } else if !trash {
+                trash_comp.trashed_at = None;
+            }
             trash_comp.trashed = trash;
             self.update_chrono_to_last(key);
         }
+        self.update_selection_counters(key);
     }
 
     pub(crate) fn set_trashed_keys(&mut self, keys: &[StrokeKey], trash: bool) {
         keys.iter().for_each(|&key| {
-            self.set_selected(key, false);
+            self.set_selected_keep_order(key, false);
             self.set_trashed(key, trash);
             self.update_chrono_to_last(key);
         });
@@ -68,6 +92,67 @@ impl StrokeStore {
             .collect()
     }
 
+    /// Information about the trashed strokes, the most recently trashed first.
+    pub(crate) fn trashed_strokes_info(&self) -> Vec<TrashedStrokeInfo> {
+        let mut info = self
+            .trash_components
+            .iter()
+            .filter(|(_, trash_comp)| trash_comp.trashed)
+            .filter_map(|(key, trash_comp)| {
+                let stroke = self.stroke_components.get(key)?;
+                Some(TrashedStrokeInfo {
+                    key,
+                    kind: stroke.kind(),
+                    bounds: stroke.bounds(),
+                    trashed_at: trash_comp.trashed_at.unwrap_or_default(),
+                })
+            })
+            .collect::<Vec<TrashedStrokeInfo>>();
+        info.sort_by(|first, second| second.trashed_at.cmp(&first.trashed_at));
+        info
+    }
+
+    /// Un-trash the strokes, ignoring the keys of strokes that are not trashed.
+    ///
+    /// Returns the keys of the restored strokes, which then need to update their geometry and rendering.
+    pub(crate) fn restore_trashed(&mut self, keys: &[StrokeKey]) -> Vec<StrokeKey> {
+        let restored = keys
+            .iter()
+            .copied()
+            .filter(|&key| self.trashed(key).unwrap_or(false))
+            .collect::<Vec<StrokeKey>>();
+        self.set_trashed_keys(&restored, false);
+        restored
+    }
+
+    /// Permanently remove the strokes that were trashed longer ago than the given duration, or all when it is None.
+    ///
+    /// `now` is the current time as unix timestamp in milliseconds. Returns the keys of the removed strokes.
+    pub(crate) fn purge_trashed(
+        &mut self,
+        older_than: Option<Duration>,
+        now: i64,
+    ) -> Vec<StrokeKey> {
+        let trashed_before = older_than.map(|older_than| {
+            now.saturating_sub(older_than.as_millis().try_into().unwrap_or(i64::MAX))
+        });
+        let purged = self
+            .trash_components
+            .iter()
+            .filter(|(_, trash_comp)| {
+                trash_comp.trashed
+                    && trashed_before
+                        .map(|before| trash_comp.trashed_at.unwrap_or_default() <= before)
+                        .unwrap_or(true)
+            })
+            .map(|(key, _)| key)
+            .collect::<Vec<StrokeKey>>();
+        for &key in purged.iter() {
+            self.remove_stroke(key);
+        }
+        purged
+    }
+
     /// Removes all trashed strokes permanently from the store.
     #[allow(unused)]
     pub(crate) fn remove_trashed_strokes(&mut self) -> Vec<Stroke> {
@@ -77,15 +162,36 @@ impl StrokeStore {
             .collect()
     }
 
+    /// The unlocked strokes that might collide with the eraser bounds.
+    ///
+    /// When restricted to keys, only those are considered.
+    fn eraser_candidates(
+        &self,
+        eraser_bounds: Aabb,
+        restrict_to: Option<&[StrokeKey]>,
+    ) -> Vec<StrokeKey> {
+        let keys =
+            self.unlocked_keys(&self.stroke_keys_as_rendered_intersecting_bounds(eraser_bounds));
+        match restrict_to {
+            Some(restrict_to) => keys
+                .into_iter()
+                .filter(|key| restrict_to.contains(key))
+                .collect(),
+            None => keys,
+        }
+    }
+
     /// Trash strokes that collide with the given bounds.
+    ///
+    /// When restricted to keys, only those are trashed.
     pub(crate) fn trash_colliding_strokes(
         &mut self,
         eraser_bounds: Aabb,
-        viewport: Aabb,
+        restrict_to: Option<&[StrokeKey]>,
     ) -> WidgetFlags {
         let mut widget_flags = WidgetFlags::default();
 
-        self.stroke_keys_as_rendered_intersecting_bounds(viewport)
+        self.eraser_candidates(eraser_bounds, restrict_to)
             .into_iter()
             .for_each(|key| {
                 let mut trash_current_stroke = false;
@@ -123,19 +229,21 @@ impl StrokeStore {
     /// Remove colliding stroke segments with the given bounds.
     /// The stroke is then split. Strokes that don't have segments are trashed completely.
     ///
+    /// When restricted to keys, only those are split and the created strokes are selected.
+    ///
     /// Returns the keys of all created or modified strokes.
     ///
     /// The returned strokes need to update their rendering.
     pub(crate) fn split_colliding_strokes(
         &mut self,
         eraser_bounds: Aabb,
-        viewport: Aabb,
+        restrict_to: Option<&[StrokeKey]>,
     ) -> (Vec<StrokeKey>, WidgetFlags) {
         let mut widget_flags = WidgetFlags::default();
         let mut modified_keys = vec![];
 
         let new_strokes = self
-            .stroke_keys_as_rendered_intersecting_bounds(viewport)
+            .eraser_candidates(eraser_bounds, restrict_to)
             .into_iter()
             .flat_map(|key| {
                 let Some(stroke) = Arc::make_mut(&mut self.stroke_components)
@@ -235,12 +343,15 @@ impl StrokeStore {
             })
             .collect::<Vec<(Stroke, StrokeLayer)>>();
 
-        modified_keys.append(
-            &mut new_strokes
-                .into_iter()
-                .map(|(new_stroke, layer)| self.insert_stroke(new_stroke, Some(layer)))
-                .collect(),
-        );
+        let mut new_keys = new_strokes
+            .into_iter()
+            .map(|(new_stroke, layer)| self.insert_stroke(new_stroke, Some(layer)))
+            .collect::<Vec<StrokeKey>>();
+        if restrict_to.is_some() {
+            // the parts of the split strokes stay selected
+            self.set_selected_keys(&new_keys, true);
+        }
+        modified_keys.append(&mut new_keys);
 
         if !modified_keys.is_empty() {
             widget_flags.store_modified = true;
@@ -249,4 +360,197 @@ impl StrokeStore {
 
         (modified_keys, widget_flags)
     }
+
+    /// Cut brush strokes apart at the segments colliding with the given bounds, see [StrokeStore::split_stroke_at_ranges].
+    /// Other colliding shape strokes and brush strokes without segments are trashed completely.
+    /// When restricted to keys, only those are cut and the inserted strokes are selected.
+    ///
+    /// Returns the keys of the inserted strokes, which need to update their rendering.
+    pub(crate) fn cut_colliding_strokes(
+        &mut self,
+        eraser_bounds: Aabb,
+        restrict_to: Option<&[StrokeKey]>,
+    ) -> (Vec<StrokeKey>, WidgetFlags) {
+        let mut widget_flags = WidgetFlags::default();
+        let mut new_keys = vec![];
+
+        for key in self.eraser_candidates(eraser_bounds, restrict_to) {
+            let Some(stroke) = self.stroke_components.get(key).cloned() else {
+                continue;
+            };
+            if !eraser_bounds.intersects(&stroke.bounds()) {
+                continue;
+            }
+
+            match stroke.as_ref() {
+                Stroke::BrushStroke(brushstroke) if !brushstroke.path.segments.is_empty() => {
+                    let hits = brushstroke
+                        .path
+                        .hittest(&eraser_bounds, brushstroke.style.stroke_width() * 0.5);
+                    if hits.is_empty() {
+                        continue;
+                    }
+                    new_keys.append(&mut self.split_stroke_at_ranges(key, &hit_ranges(&hits)));
+                }
+                Stroke::BrushStroke(_) | Stroke::ShapeStroke(_) => {
+                    if stroke
+                        .hitboxes()
+                        .iter()
+                        .any(|hitbox| eraser_bounds.intersects(hitbox))
+                    {
+                        self.set_trashed(key, true);
+                    } else {
+                        continue;
+                    }
+                }
+                // Ignore other strokes when trashing with the Eraser
+                Stroke::TextStroke(_) | Stroke::VectorImage(_) | Stroke::BitmapImage(_) => continue,
+            }
+
+            widget_flags.store_modified = true;
+            widget_flags.resize = true;
+        }
+        if restrict_to.is_some() {
+            // the parts of the cut strokes stay selected
+            self.set_selected_keys(&new_keys, true);
+        }
+
+        (new_keys, widget_flags)
+    }
+
+    /// Split the brush stroke by removing the segments in the ranges of segment indices.
+    ///
+    /// The remaining parts of the pen path are inserted as new strokes with the style and layer of the original stroke,
+    /// which then is trashed. Parts without a segment are dropped.
+    /// Returns the keys of the inserted strokes, or an empty vec when the stroke is not a brush stroke
+    /// or no segments are removed.
+    pub(crate) fn split_stroke_at_ranges(
+        &mut self,
+        key: StrokeKey,
+        ranges: &[Range<usize>],
+    ) -> Vec<StrokeKey> {
+        let Some(Stroke::BrushStroke(brushstroke)) =
+            self.stroke_components.get(key).map(|s| s.as_ref())
+        else {
+            return vec![];
+        };
+        let Some(layer) = self.chrono_components.get(key).map(|c| c.layer) else {
+            return vec![];
+        };
+        let path = &brushstroke.path;
+        let n_segments = path.segments.len();
+        let mut removed = vec![false; n_segments];
+        for range in ranges {
+            removed[range.start.min(n_segments)..range.end.min(n_segments)].fill(true);
+        }
+        if !removed.contains(&true) {
+            return vec![];
+        }
+
+        let mut parts = vec![];
+        let mut part_start = 0;
+        for i in 0..=n_segments {
+            if i == n_segments || removed[i] {
+                if i > part_start {
+                    // the part starts at the end of the segment before it
+                    let start = if part_start == 0 {
+                        path.start
+                    } else {
+                        path.segments[part_start - 1].end()
+                    };
+                    parts.push(Stroke::BrushStroke(BrushStroke::from_penpath(
+                        PenPath::new_w_segments(start, path.segments[part_start..i].to_vec()),
+                        brushstroke.style.clone(),
+                    )));
+                }
+                part_start = i + 1;
+            }
+        }
+
+        self.set_trashed(key, true);
+        parts
+            .into_iter()
+            .map(|part| self.insert_stroke(part, Some(layer)))
+            .collect()
+    }
+}
+
+/// Coalesce sorted segment indices into ranges of consecutive indices.
+fn hit_ranges(hits: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for &hit in hits {
+        match ranges.last_mut() {
+            Some(last) if last.end == hit => last.end = hit + 1,
+            _ => ranges.push(hit..hit + 1),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsupport::insert_line_stroke;
+
+    fn brushstroke_path(store: &StrokeStore, key: StrokeKey) -> PenPath {
+        match store.get_stroke_ref(key) {
+            Some(Stroke::BrushStroke(brushstroke)) => brushstroke.path.clone(),
+            _ => panic!("stroke is not a brush stroke"),
+        }
+    }
+
+    fn path_xs(path: &PenPath) -> Vec<f64> {
+        std::iter::once(path.start)
+            .chain(path.segments.iter().map(|s| s.end()))
+            .map(|e| e.pos[0])
+            .collect()
+    }
+
+    #[test]
+    fn split_stroke_at_ranges() {
+        let mut store = StrokeStore::default();
+        let key = insert_line_stroke(&mut store, 0.0);
+
+        // removing the segments 2 and 3 leaves the elements 0..=2 and 4..=6
+        let new_keys = store.split_stroke_at_ranges(key, &[2..4]);
+        assert_eq!(new_keys.len(), 2);
+        assert_eq!(store.trashed(key), Some(true));
+        let first = brushstroke_path(&store, new_keys[0]);
+        let second = brushstroke_path(&store, new_keys[1]);
+        assert_eq!(path_xs(&first), vec![0.0, 10.0, 20.0]);
+        assert_eq!(path_xs(&second), vec![40.0, 50.0, 60.0]);
+        // the widths of the elements are kept
+        approx::assert_relative_eq!(second.start.pressure, 0.4);
+        approx::assert_relative_eq!(second.segments[1].end().pressure, 0.6);
+    }
+
+    #[test]
+    fn split_stroke_at_ranges_drops_empty_parts() {
+        let mut store = StrokeStore::default();
+        let key = insert_line_stroke(&mut store, 0.0);
+
+        // the parts before the first and after the last range don't have segments
+        let new_keys = store.split_stroke_at_ranges(key, &[0..1, 3..4, 5..6]);
+        assert_eq!(new_keys.len(), 2);
+        assert_eq!(
+            path_xs(&brushstroke_path(&store, new_keys[0])),
+            vec![10.0, 20.0, 30.0]
+        );
+        assert_eq!(
+            path_xs(&brushstroke_path(&store, new_keys[1])),
+            vec![40.0, 50.0]
+        );
+
+        // nothing is changed when no segments are removed
+        let key = insert_line_stroke(&mut store, 0.0);
+        assert!(store.split_stroke_at_ranges(key, &[]).is_empty());
+        assert!(store.split_stroke_at_ranges(key, &[8..10]).is_empty());
+        assert_eq!(store.trashed(key), Some(false));
+    }
+
+    #[test]
+    fn hit_indices_coalesce_into_ranges() {
+        assert_eq!(hit_ranges(&[1, 2, 3, 6, 8, 9]), vec![1..4, 6..7, 8..10]);
+        assert!(hit_ranges(&[]).is_empty());
+    }
 }
@@ -1,5 +1,5 @@
 // Imports
-use super::resize::{calculate_resize_ratio, ImageSizeOption};
+use super::resize::{calculate_resize_ratio, resize_rectangle_about_center, ImageSizeOption};
 use super::{Content, Stroke};
 use crate::document::Format;
 use crate::engine::import::{PdfImportPageSpacing, PdfImportPrefs};
@@ -29,6 +29,14 @@ pub struct BitmapImage {
     pub image: render::Image,
     #[serde(rename = "rectangle")]
     pub rectangle: Rectangle,
+    /// The opacity of the image, ranging [0.0, 1.0].
+    #[serde(rename = "opacity", with = "rnote_compose::serialize::f64_dp3")]
+    pub opacity: f64,
+    /// The pixel dimensions of the image when it was imported.
+    ///
+    /// None for images that weren't imported from an image file, or were imported before it was recorded.
+    #[serde(rename = "original_pixel_size")]
+    pub original_pixel_size: Option<(u32, u32)>,
 }
 
 impl Default for BitmapImage {
@@ -36,6 +44,8 @@ impl Default for BitmapImage {
         Self {
             image: render::Image::default(),
             rectangle: Rectangle::default(),
+            opacity: 1.0,
+            original_pixel_size: None,
         }
     }
 }
@@ -51,11 +61,23 @@ impl Drawable for BitmapImage {
         cx.save().map_err(|e| anyhow::anyhow!("{e:?}"))?;
         cx.transform(self.rectangle.transform.affine.to_kurbo());
 
+        // The image data is premultiplied, so all channels need to be scaled to apply the opacity
+        let data: std::borrow::Cow<[u8]> = if self.opacity < 1.0 {
+            std::borrow::Cow::Owned(
+                self.image
+                    .data
+                    .iter()
+                    .map(|&c| (c as f64 * self.opacity).round() as u8)
+                    .collect::<Vec<u8>>(),
+            )
+        } else {
+            std::borrow::Cow::Borrowed(&self.image.data)
+        };
         let piet_image = cx
             .make_image(
                 self.image.pixel_width as usize,
                 self.image.pixel_height as usize,
-                &self.image.data,
+                &data,
                 piet_image_format,
             )
             .map_err(|e| {
@@ -95,6 +117,10 @@ impl Transformable for BitmapImage {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.rectangle.scale(scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.rectangle.shear(shear, pivot);
+    }
 }
 
 impl BitmapImage {
@@ -123,7 +149,30 @@ impl BitmapImage {
             cuboid: p2d::shape::Cuboid::new(size * 0.5),
             transform,
         };
-        Ok(Self { image, rectangle })
+        Ok(Self {
+            original_pixel_size: Some((image.pixel_width, image.pixel_height)),
+            image,
+            rectangle,
+            ..Default::default()
+        })
+    }
+
+    /// The natural size of the image, with one image pixel per document unit.
+    ///
+    /// Falls back to the dimensions of the image data when the original pixel size isn't known.
+    pub fn original_size(&self) -> na::Vector2<f64> {
+        let (width, height) = self
+            .original_pixel_size
+            .unwrap_or((self.image.pixel_width, self.image.pixel_height));
+        na::vector![f64::from(width), f64::from(height)]
+    }
+
+    /// Resize the image to its natural size about its center.
+    pub fn reset_to_original_size(&mut self) {
+        let size = self.original_size();
+        if size[0] > 0.0 && size[1] > 0.0 {
+            resize_rectangle_about_center(&mut self.rectangle, size);
+        }
     }
 
     pub fn from_pdf_bytes(
@@ -251,10 +251,14 @@ impl Transformable for BrushStroke {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.path.scale(scale);
         // Using the geometric mean behaves the best when scaling non-uniformly.
-        let scale_scalar = (scale[0] * scale[1]).sqrt();
+        let scale_scalar = (scale[0] * scale[1]).abs().sqrt();
         self.style
             .set_stroke_width(self.style.stroke_width() * scale_scalar);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.path.shear(shear, pivot);
+    }
 }
 
 impl BrushStroke {
@@ -33,6 +33,13 @@ where
         render::Svg::gen_with_cairo(|cx| self.draw_to_cairo(cx, 1.0), bounds)
     }
 
+    /// Whether [Content::gen_svg()] generates native Svg elements instead of going through cairo.
+    ///
+    /// Native Svg can be merged with other Svg as is, when exporting.
+    fn gen_svg_is_native(&self) -> bool {
+        false
+    }
+
     /// Generate bitmap images for rendering in the app.
     ///
     /// A larger `image_scale` value renders them in a higher than native resolution (usually set as the camera zoom).
@@ -14,6 +14,6 @@ pub use brushstroke::BrushStroke;
 pub use content::Content;
 pub use resize::Resize;
 pub use shapestroke::ShapeStroke;
-pub use stroke::Stroke;
+pub use stroke::{Stroke, StrokeKind};
 pub use textstroke::TextStroke;
 pub use vectorimage::VectorImage;
@@ -1,3 +1,7 @@
+// Imports
+use rnote_compose::shapes::Rectangle;
+use rnote_compose::transform::Transform;
+
 /// Enum that lists the different options for sizing the image
 ///
 /// Either respect the original image size (in pixel or dimensions)
@@ -111,3 +115,22 @@ pub fn calculate_resize_ratio(
         .fold(1.0f64, |acc, x| acc.min(*x.0))
         .max(1e-15f64) //force the value to be positive as a zero would make transforms crash
 }
+
+/// Resize the rectangle to the given size about its center, keeping its rotation and mirroring.
+///
+/// Any other scaling or shearing of the rectangle transform is discarded.
+pub(crate) fn resize_rectangle_about_center(rectangle: &mut Rectangle, size: na::Vector2<f64>) {
+    let matrix = rectangle.transform.affine.matrix();
+    let center = rectangle.transform.translation_part();
+    let angle = matrix[(1, 0)].atan2(matrix[(0, 0)]);
+    let determinant = matrix[(0, 0)] * matrix[(1, 1)] - matrix[(0, 1)] * matrix[(1, 0)];
+
+    let mut transform = Transform::default();
+    if determinant < 0.0 {
+        transform.append_scale_mut(na::vector![1.0, -1.0]);
+    }
+    transform.append_rotation_wrt_point_mut(angle, na::Point2::origin());
+    transform.append_translation_mut(center);
+    rectangle.cuboid.half_extents = size.abs() * 0.5;
+    rectangle.transform = transform;
+}
@@ -1,7 +1,7 @@
 // Imports
 use super::Content;
-use crate::{strokes::content, Drawable};
-use p2d::bounding_volume::{Aabb, BoundingVolume};
+use crate::{render, strokes::content, Drawable};
+use p2d::bounding_volume::Aabb;
 use rnote_compose::ext::AabbExt;
 use rnote_compose::shapes::Shape;
 use rnote_compose::shapes::Shapeable;
@@ -23,6 +23,27 @@ pub struct ShapeStroke {
 }
 
 impl Content for ShapeStroke {
+    /// Shapes in the smooth style are kept as native Svg elements, the other styles are generated through cairo.
+    fn gen_svg(&self) -> Result<render::Svg, anyhow::Error> {
+        let bounds = self.bounds();
+        match &self.style {
+            Style::Smooth(options) if options.width_profile.is_none() => Ok(render::Svg {
+                svg_data: rnote_compose::utils::svg_node_to_string(
+                    &self.shape.to_svg_element(options),
+                )?,
+                bounds,
+            }),
+            // Variable width outlines are exported as the filled outlines they are drawn with
+            Style::Smooth(_) | Style::Rough(_) | Style::Textured(_) => {
+                render::Svg::gen_with_cairo(|cx| self.draw_to_cairo(cx, 1.0), bounds)
+            }
+        }
+    }
+
+    fn gen_svg_is_native(&self) -> bool {
+        matches!(&self.style, Style::Smooth(options) if options.width_profile.is_none())
+    }
+
     fn draw_highlight(
         &self,
         cx: &mut impl piet::RenderContext,
@@ -87,17 +108,24 @@ impl Shapeable for ShapeStroke {
 impl Transformable for ShapeStroke {
     fn translate(&mut self, offset: na::Vector2<f64>) {
         self.shape.translate(offset);
+        self.style.translate(offset);
     }
     fn rotate(&mut self, angle: f64, center: na::Point2<f64>) {
         self.shape.rotate(angle, center);
+        self.style.rotate(angle, center);
     }
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.shape.scale(scale);
+        self.style.scale(scale);
         // Using the geometric mean behaves the best when scaling non-uniformly.
-        let scale_scalar = (scale[0] * scale[1]).sqrt();
+        let scale_scalar = (scale[0] * scale[1]).abs().sqrt();
         self.style
             .set_stroke_width(self.style.stroke_width() * scale_scalar);
     }
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.shape.shear(shear, pivot);
+        self.style.shear(shear, pivot);
+    }
 }
 
 impl ShapeStroke {
@@ -112,13 +140,295 @@ impl ShapeStroke {
         shapestroke
     }
 
-    fn gen_hitboxes_int(&self) -> Vec<Aabb> {
-        let width = self.style.stroke_width();
+    /// The measured dimensions of the shape and where a label for them should be anchored.
+    ///
+    /// Lines are measured by their length, rectangles and ellipses by their width and height.
+    /// Returns None for shapes that can't be measured.
+    pub fn measurement(&self) -> Option<(Vec<f64>, na::Vector2<f64>)> {
+        match &self.shape {
+            Shape::Line(line) => Some((vec![line.length()], line.midpoint())),
+            Shape::Rectangle(rectangle) => {
+                let size = rectangle.size();
+                Some((
+                    vec![size[0], size[1]],
+                    rectangle.transform.translation_part(),
+                ))
+            }
+            Shape::Ellipse(ellipse) => {
+                let diameters = ellipse.diameters();
+                Some((
+                    vec![diameters[0], diameters[1]],
+                    ellipse.transform.translation_part(),
+                ))
+            }
+            _ => None,
+        }
+    }
 
+    fn gen_hitboxes_int(&self) -> Vec<Aabb> {
         self.shape
+            .hitboxes_w_stroke_width(self.style.stroke_width())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::StrokeKey;
+    use crate::strokes::Stroke;
+    use crate::StrokeStore;
+    use approx::assert_relative_eq;
+    use rnote_compose::shapes::{Ellipse, Line, Polygon, Polyline, Rectangle};
+    use rnote_compose::style::smooth::{Fill, SmoothOptions};
+    use rnote_compose::style::PressureCurve;
+    use rnote_compose::Color;
+
+    fn smooth_style(stroke_width: f64) -> Style {
+        let mut options = SmoothOptions::default();
+        options.set_stroke_width(stroke_width);
+        Style::Smooth(options)
+    }
+
+    fn hits(shapestroke: &ShapeStroke, pos: na::Vector2<f64>) -> bool {
+        shapestroke
             .hitboxes()
-            .into_iter()
-            .map(|hitbox| hitbox.loosened(width * 0.5))
-            .collect()
+            .iter()
+            .any(|hitbox| hitbox.contains_local_point(&pos.into()))
+    }
+
+    #[test]
+    fn diagonal_line_hitboxes_follow_ink() {
+        for stroke_width in [1.0, 4.0, 20.0] {
+            let start = na::vector![0.0, 0.0];
+            let end = na::vector![1000.0, 1000.0];
+            let shapestroke = ShapeStroke::new(
+                Shape::Line(Line::new(start, end)),
+                smooth_style(stroke_width),
+            );
+            let normal = na::vector![1.0, -1.0].normalize();
+
+            for i in 0..=100 {
+                let on_ink = start.lerp(&end, f64::from(i) / 100.0);
+                assert!(hits(&shapestroke, on_ink));
+                assert!(!hits(&shapestroke, on_ink + normal * stroke_width * 2.0));
+                assert!(!hits(&shapestroke, on_ink - normal * stroke_width * 2.0));
+            }
+        }
+    }
+
+    #[test]
+    fn polygon_closing_edge_hitboxes_follow_ink() {
+        let stroke_width = 2.0;
+        let shapestroke = ShapeStroke::new(
+            Shape::Polygon(Polygon {
+                start: na::vector![0.0, 0.0],
+                path: vec![na::vector![500.0, 0.0], na::vector![500.0, 500.0]],
+            }),
+            smooth_style(stroke_width),
+        );
+        let normal = na::vector![1.0, -1.0].normalize();
+        let on_closing_edge = na::vector![250.0, 250.0];
+
+        assert!(hits(&shapestroke, on_closing_edge));
+        assert!(!hits(
+            &shapestroke,
+            on_closing_edge - normal * stroke_width * 2.0
+        ));
+    }
+
+    #[test]
+    fn many_segments_hit_query() {
+        let stroke_width = 2.0;
+        let mut store = StrokeStore::default();
+        let keys = (0..3000)
+            .map(|i| {
+                let offset = na::vector![f64::from(i % 50) * 100.0, f64::from(i / 50) * 100.0];
+                store.insert_stroke(
+                    Stroke::ShapeStroke(ShapeStroke::new(
+                        Shape::Line(Line::new(offset, offset + na::vector![80.0, 80.0])),
+                        smooth_style(stroke_width),
+                    )),
+                    None,
+                )
+            })
+            .collect::<Vec<StrokeKey>>();
+        let n_hitboxes = keys
+            .iter()
+            .map(|key| store.get_stroke_ref(*key).unwrap().hitboxes().len())
+            .sum::<usize>();
+        // The subdivision stays proportional to the length over the stroke width
+        assert!(n_hitboxes <= keys.len() * 40);
+
+        let eraser_bounds =
+            Aabb::from_half_extents(na::point![740.0, 740.0], na::vector![1.0, 1.0]);
+        let widget_flags = store.trash_colliding_strokes(eraser_bounds, None);
+        assert!(widget_flags.store_modified);
+        assert_eq!(store.trashed_keys_unordered(), vec![keys[7 * 50 + 7]]);
+    }
+
+    #[test]
+    fn width_profile_sampling() {
+        let mut options = SmoothOptions::default();
+        assert_relative_eq!(options.width_factor_at(0.3), 1.0);
+
+        options.pressure_curve = PressureCurve::Linear;
+        options.set_width_profile_from_pressures(&[0.2, 0.6, 1.0]);
+        assert_eq!(
+            options.width_profile.as_deref(),
+            Some([(0.0, 0.2), (0.5, 0.6), (1.0, 1.0)].as_slice())
+        );
+        assert_relative_eq!(options.width_factor_at(0.25), 0.4);
+        assert_relative_eq!(options.width_factor_at(0.75), 0.8);
+        // clamped to the factors at the ends
+        assert_relative_eq!(options.width_factor_at(-1.0), 0.2);
+        assert_relative_eq!(options.width_factor_at(2.0), 1.0);
+
+        // long inputs are resampled
+        let pressures = (0..1000)
+            .map(|i| f64::from(i) / 999.0)
+            .collect::<Vec<f64>>();
+        options.set_width_profile_from_pressures(&pressures);
+        let width_profile = options.width_profile.clone().unwrap();
+        assert_eq!(width_profile.len(), SmoothOptions::WIDTH_PROFILE_POINTS_MAX);
+        assert!(width_profile.windows(2).all(|w| w[0].0 < w[1].0));
+        for t in [0.0, 0.33, 0.5, 0.9, 1.0] {
+            assert_relative_eq!(options.width_factor_at(t), t, epsilon = 1e-9);
+        }
+
+        options.set_width_profile_from_pressures(&[]);
+        assert!(options.width_profile.is_none());
+    }
+
+    fn render_alpha(shapestroke: &ShapeStroke) -> Vec<u8> {
+        let image = render::Image::gen_with_piet(
+            |piet_cx| shapestroke.draw(piet_cx, 1.0),
+            shapestroke.bounds(),
+            1.0,
+        )
+        .unwrap();
+        image.data.iter().skip(3).step_by(4).copied().collect()
+    }
+
+    #[test]
+    fn constant_width_profile_matches_stroked_outline() {
+        let shapes = [
+            Shape::Rectangle(Rectangle::from_p2d_aabb(Aabb::new(
+                na::point![0.0, 0.0],
+                na::point![120.0, 80.0],
+            ))),
+            Shape::Ellipse(Ellipse {
+                radii: na::vector![60.0, 40.0],
+                transform: rnote_compose::Transform::new_w_isometry(na::Isometry2::new(
+                    na::vector![70.0, 50.0],
+                    0.0,
+                )),
+            }),
+            Shape::Line(Line::new(na::vector![0.0, 0.0], na::vector![150.0, 60.0])),
+            Shape::Polyline(Polyline {
+                start: na::vector![0.0, 0.0],
+                path: vec![na::vector![80.0, 40.0], na::vector![160.0, 0.0]],
+            }),
+        ];
+        for shape in shapes {
+            let style = smooth_style(8.0);
+            let mut profiled_style = style.clone();
+            if let Style::Smooth(options) = &mut profiled_style {
+                options.width_profile = Some(vec![(0.0, 1.0), (1.0, 1.0)]);
+            }
+            let stroked = render_alpha(&ShapeStroke::new(shape.clone(), style));
+            let profiled_shapestroke = ShapeStroke::new(shape.clone(), profiled_style);
+            let profiled = render_alpha(&profiled_shapestroke);
+            assert!(!profiled_shapestroke.gen_svg_is_native());
+
+            assert_eq!(stroked.len(), profiled.len());
+            // Only the antialiasing at the edges differs
+            let n_differing = stroked
+                .iter()
+                .zip(profiled.iter())
+                .filter(|(a, b)| a.abs_diff(**b) > 64)
+                .count();
+            assert!(
+                n_differing * 100 < stroked.len(),
+                "{shape:?} differs in {n_differing} of {} pixels",
+                stroked.len()
+            );
+
+            // thinner outlines have less ink
+            let mut thin_style = smooth_style(8.0);
+            if let Style::Smooth(options) = &mut thin_style {
+                options.width_profile = Some(vec![(0.0, 0.25), (1.0, 0.5)]);
+            }
+            let thin = render_alpha(&ShapeStroke::new(shape, thin_style));
+            let ink = |alpha: &[u8]| alpha.iter().map(|a| u64::from(*a)).sum::<u64>();
+            assert!(ink(&thin) < ink(&stroked) * 3 / 4);
+        }
+    }
+
+    #[test]
+    fn former_fill_color_loaded_as_solid_fill() {
+        let mut options = SmoothOptions::default();
+        options.set_fill_color(Some(Color::RED));
+        let shapestroke = ShapeStroke::new(
+            Shape::Rectangle(Rectangle::from_p2d_aabb(Aabb::new(
+                na::point![0.0, 0.0],
+                na::point![100.0, 50.0],
+            ))),
+            Style::Smooth(options),
+        );
+
+        // documents saved before gradient fills only had the flat `fill_color`
+        let mut value = serde_json::to_value(&shapestroke).unwrap();
+        let smooth = value["style"]["smooth"].as_object_mut().unwrap();
+        assert!(smooth.remove("fill").is_some());
+        smooth.insert(
+            String::from("fill_color"),
+            serde_json::to_value(Color::RED).unwrap(),
+        );
+        let loaded = serde_json::from_value::<ShapeStroke>(value.clone()).unwrap();
+        let Style::Smooth(loaded_options) = &loaded.style else {
+            panic!("the loaded style is not smooth");
+        };
+        assert_eq!(loaded_options.fill, Some(Fill::Solid(Color::RED)));
+
+        value["style"]["smooth"]["fill_color"] = serde_json::Value::Null;
+        let loaded = serde_json::from_value::<ShapeStroke>(value).unwrap();
+        assert_eq!(loaded.style.fill_color(), None);
+    }
+
+    #[test]
+    fn gradient_fill_follows_resize_and_rotation() {
+        let bounds = Aabb::new(na::point![10.0, 20.0], na::point![110.0, 70.0]);
+        let mut options = SmoothOptions::default();
+        options.fill =
+            Some(Fill::linear_fade_out_relative(Color::RED, 0.0).mapped_to_bounds(bounds));
+        let mut shapestroke = ShapeStroke::new(
+            Shape::Rectangle(Rectangle::from_p2d_aabb(bounds)),
+            Style::Smooth(options),
+        );
+
+        shapestroke.scale(na::vector![2.0, 3.0]);
+        shapestroke.rotate(std::f64::consts::FRAC_PI_2, na::point![40.0, 30.0]);
+        shapestroke.translate(na::vector![-5.0, 15.0]);
+
+        // the gradient still runs from the middle of the left to the middle of the right edge
+        let Shape::Rectangle(rectangle) = &shapestroke.shape else {
+            panic!("the shape is not a rectangle");
+        };
+        let half_extents = rectangle.cuboid.half_extents;
+        let left_mid = rectangle
+            .transform
+            .transform_point(na::point![-half_extents[0], 0.0]);
+        let right_mid = rectangle
+            .transform
+            .transform_point(na::point![half_extents[0], 0.0]);
+        let Style::Smooth(SmoothOptions {
+            fill: Some(Fill::LinearGradient { start, end, .. }),
+            ..
+        }) = &shapestroke.style
+        else {
+            panic!("the gradient fill is missing");
+        };
+        assert_relative_eq!(*start, left_mid.coords, epsilon = 1e-9);
+        assert_relative_eq!(*end, right_mid.coords, epsilon = 1e-9);
     }
 }
@@ -3,10 +3,12 @@ use super::bitmapimage::BitmapImage;
 use super::brushstroke::BrushStroke;
 use super::content::GeneratedContentImages;
 use super::shapestroke::ShapeStroke;
+use super::textstroke::TextAttribute;
 use super::vectorimage::VectorImage;
 use super::{Content, TextStroke};
 use crate::fileformats::xoppformat::{self, XoppColor};
 use crate::store::chrono_comp::StrokeLayer;
+use crate::store::{ColorReplaceScope, StyleUpdate};
 use crate::{render, Engine};
 use crate::{utils, Drawable};
 use p2d::bounding_volume::Aabb;
@@ -18,8 +20,19 @@ use rnote_compose::transform::Transform;
 use rnote_compose::transform::Transformable;
 use rnote_compose::{Color, PenPath, Style};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use tracing::error;
 
+/// The kind of a stroke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StrokeKind {
+    BrushStroke,
+    ShapeStroke,
+    TextStroke,
+    VectorImage,
+    BitmapImage,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename = "stroke")]
 pub enum Stroke {
@@ -46,6 +59,16 @@ impl Content for Stroke {
         }
     }
 
+    fn gen_svg_is_native(&self) -> bool {
+        match self {
+            Stroke::BrushStroke(brushstroke) => brushstroke.gen_svg_is_native(),
+            Stroke::ShapeStroke(shapestroke) => shapestroke.gen_svg_is_native(),
+            Stroke::TextStroke(textstroke) => textstroke.gen_svg_is_native(),
+            Stroke::VectorImage(vectorimage) => vectorimage.gen_svg_is_native(),
+            Stroke::BitmapImage(bitmapimage) => bitmapimage.gen_svg_is_native(),
+        }
+    }
+
     fn gen_images(
         &self,
         viewport: Aabb,
@@ -199,11 +222,43 @@ impl Transformable for Stroke {
             }
         }
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        match self {
+            Self::BrushStroke(brushstroke) => {
+                brushstroke.shear(shear, pivot);
+            }
+            Self::ShapeStroke(shapestroke) => {
+                shapestroke.shear(shear, pivot);
+            }
+            Self::TextStroke(textstroke) => {
+                textstroke.shear(shear, pivot);
+            }
+            Self::VectorImage(vectorimage) => {
+                vectorimage.shear(shear, pivot);
+            }
+            Self::BitmapImage(bitmapimage) => {
+                bitmapimage.shear(shear, pivot);
+            }
+        }
+    }
 }
 
 impl Stroke {
     /// The default offset in surface coords when importing a stroke.
     pub const IMPORT_OFFSET_DEFAULT: na::Vector2<f64> = na::vector![32.0, 32.0];
+    /// The minimum opacity a stroke can be set to, so that it can't vanish irrecoverably.
+    pub const OPACITY_MIN: f64 = 0.05;
+
+    pub fn kind(&self) -> StrokeKind {
+        match self {
+            Stroke::BrushStroke(_) => StrokeKind::BrushStroke,
+            Stroke::ShapeStroke(_) => StrokeKind::ShapeStroke,
+            Stroke::TextStroke(_) => StrokeKind::TextStroke,
+            Stroke::VectorImage(_) => StrokeKind::VectorImage,
+            Stroke::BitmapImage(_) => StrokeKind::BitmapImage,
+        }
+    }
 
     pub fn extract_default_layer(&self) -> StrokeLayer {
         match self {
@@ -226,11 +281,9 @@ impl Stroke {
                         .set_stroke_color(color.to_inverted_brightness_color());
                 }
 
-                if let Some(color) = brush_stroke.style.fill_color() {
-                    brush_stroke
-                        .style
-                        .set_fill_color(color.to_inverted_brightness_color());
-                }
+                brush_stroke
+                    .style
+                    .map_fill_colors(|color| color.to_inverted_brightness_color());
 
                 true
             }
@@ -241,11 +294,9 @@ impl Stroke {
                         .set_stroke_color(color.to_inverted_brightness_color());
                 }
 
-                if let Some(color) = shape_stroke.style.fill_color() {
-                    shape_stroke
-                        .style
-                        .set_fill_color(color.to_inverted_brightness_color());
-                }
+                shape_stroke
+                    .style
+                    .map_fill_colors(|color| color.to_inverted_brightness_color());
 
                 true
             }
@@ -260,6 +311,237 @@ impl Stroke {
         }
     }
 
+    /// The stroke width of the style, if the stroke has one.
+    pub fn stroke_width(&self) -> Option<f64> {
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => Some(style.stroke_width()),
+            Stroke::TextStroke(_) | Stroke::VectorImage(_) | Stroke::BitmapImage(_) => None,
+        }
+    }
+
+    /// Set the stroke width of the style, if the stroke has one.
+    pub fn set_stroke_width(&mut self, stroke_width: f64) {
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => {
+                style.set_stroke_width(stroke_width)
+            }
+            Stroke::TextStroke(_) | Stroke::VectorImage(_) | Stroke::BitmapImage(_) => {}
+        }
+    }
+
+    /// The stroke color of the style, if the stroke has one.
+    ///
+    /// For text strokes this is the text color.
+    pub fn stroke_color(&self) -> Option<Color> {
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => style.stroke_color(),
+            Stroke::TextStroke(text_stroke) => Some(text_stroke.text_style.color),
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => None,
+        }
+    }
+
+    /// The fill color of the style, if the stroke has one.
+    pub fn fill_color(&self) -> Option<Color> {
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => style.fill_color(),
+            Stroke::TextStroke(_) | Stroke::VectorImage(_) | Stroke::BitmapImage(_) => None,
+        }
+    }
+
+    /// Apply the style update, skipping the parts that don't apply to the stroke.
+    ///
+    /// Returns true if the stroke was modified and needs to update its geometry and rendering.
+    pub fn apply_style_update(&mut self, update: &StyleUpdate) -> bool {
+        let mut modified = false;
+
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => {
+                if let Some(color) = update.stroke_color {
+                    if style.stroke_color() != Some(color) {
+                        style.set_stroke_color(color);
+                        modified = true;
+                    }
+                }
+                if let Some(color) = update.fill_color {
+                    if style.fill_color() != Some(color) && !matches!(style, Style::Textured(_)) {
+                        style.set_fill_color(color);
+                        modified = true;
+                    }
+                }
+                if let Some(new_width) = update
+                    .stroke_width
+                    .and_then(|u| u.apply(style.stroke_width()))
+                {
+                    if new_width != style.stroke_width() {
+                        style.set_stroke_width(new_width);
+                        modified = true;
+                    }
+                }
+            }
+            Stroke::TextStroke(text_stroke) => {
+                if let Some(color) = update.stroke_color {
+                    if text_stroke.text_style.color != color {
+                        text_stroke.text_style.color = color;
+                        modified = true;
+                    }
+                }
+            }
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => {}
+        }
+
+        modified
+    }
+
+    /// Update the opacity of the stroke by applying `update` to the alpha of all its colors.
+    ///
+    /// The resulting alpha is clamped to [Stroke::OPACITY_MIN, 1.0]. Colors that are not set are left untouched.
+    /// Images have their own opacity which is updated instead.
+    ///
+    /// Returns true if the stroke was modified and needs to update its rendering.
+    pub fn update_opacity<F>(&mut self, update: F) -> bool
+    where
+        F: Fn(f64) -> f64,
+    {
+        let update_alpha = |alpha: f64| update(alpha).clamp(Self::OPACITY_MIN, 1.0);
+        let update_color = |color: Color| Color {
+            a: update_alpha(color.a),
+            ..color
+        };
+
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => {
+                let mut modified = false;
+
+                if let Some(color) = style.stroke_color() {
+                    let new_color = update_color(color);
+                    if new_color != color {
+                        style.set_stroke_color(new_color);
+                        modified = true;
+                    }
+                }
+
+                let fill_modified = Cell::new(false);
+                style.map_fill_colors(|color| {
+                    let new_color = update_color(color);
+                    if new_color != color {
+                        fill_modified.set(true);
+                    }
+                    new_color
+                });
+                modified |= fill_modified.get();
+
+                modified
+            }
+            Stroke::TextStroke(text_stroke) => {
+                let old_color = text_stroke.text_style.color;
+                text_stroke.text_style.color = update_color(old_color);
+                let mut modified = text_stroke.text_style.color != old_color;
+
+                for attr in text_stroke.text_style.ranged_text_attributes.iter_mut() {
+                    if let TextAttribute::TextColor(color) = &mut attr.attribute {
+                        let new_color = update_color(*color);
+                        if new_color != *color {
+                            *color = new_color;
+                            modified = true;
+                        }
+                    }
+                }
+
+                modified
+            }
+            Stroke::VectorImage(vectorimage) => {
+                let old_opacity = vectorimage.opacity;
+                vectorimage.opacity = update_alpha(old_opacity);
+                vectorimage.opacity != old_opacity
+            }
+            Stroke::BitmapImage(bitmapimage) => {
+                let old_opacity = bitmapimage.opacity;
+                bitmapimage.opacity = update_alpha(old_opacity);
+                bitmapimage.opacity != old_opacity
+            }
+        }
+    }
+
+    /// Replace the colors of the stroke in the scope that match with the new color.
+    ///
+    /// Gradient stops and ranged text colors are matched individually. Images are never modified.
+    ///
+    /// Returns true if the stroke was modified and needs to update its rendering.
+    pub fn replace_color<F>(
+        &mut self,
+        matches: F,
+        new_color: Color,
+        scope: ColorReplaceScope,
+    ) -> bool
+    where
+        F: Fn(Color) -> bool,
+    {
+        let replace = |color: Color| {
+            if color != new_color && matches(color) {
+                new_color
+            } else {
+                color
+            }
+        };
+
+        match self {
+            Stroke::BrushStroke(BrushStroke { style, .. })
+            | Stroke::ShapeStroke(ShapeStroke { style, .. }) => {
+                let mut modified = false;
+
+                if scope.stroke_colors() {
+                    if let Some(color) = style.stroke_color() {
+                        let replaced = replace(color);
+                        if replaced != color {
+                            style.set_stroke_color(replaced);
+                            modified = true;
+                        }
+                    }
+                }
+                if scope.fill_colors() {
+                    let fill_modified = Cell::new(false);
+                    style.map_fill_colors(|color| {
+                        let replaced = replace(color);
+                        if replaced != color {
+                            fill_modified.set(true);
+                        }
+                        replaced
+                    });
+                    modified |= fill_modified.get();
+                }
+
+                modified
+            }
+            Stroke::TextStroke(text_stroke) => {
+                if !scope.stroke_colors() {
+                    return false;
+                }
+                let old_color = text_stroke.text_style.color;
+                text_stroke.text_style.color = replace(old_color);
+                let mut modified = text_stroke.text_style.color != old_color;
+
+                for attr in text_stroke.text_style.ranged_text_attributes.iter_mut() {
+                    if let TextAttribute::TextColor(color) = &mut attr.attribute {
+                        let replaced = replace(*color);
+                        if replaced != *color {
+                            *color = replaced;
+                            modified = true;
+                        }
+                    }
+                }
+
+                modified
+            }
+            Stroke::VectorImage(_) | Stroke::BitmapImage(_) => false,
+        }
+    }
+
     /// Set all colors of the stroke to their darkest variant.
     ///
     /// Returns true if the stroke was modified and needs to update its rendering.
@@ -272,9 +554,9 @@ impl Stroke {
                         .set_stroke_color(color.to_darkest_color());
                 }
 
-                if let Some(color) = brush_stroke.style.fill_color() {
-                    brush_stroke.style.set_fill_color(color.to_darkest_color());
-                }
+                brush_stroke
+                    .style
+                    .map_fill_colors(|color| color.to_darkest_color());
 
                 true
             }
@@ -285,9 +567,9 @@ impl Stroke {
                         .set_stroke_color(color.to_darkest_color());
                 }
 
-                if let Some(color) = shape_stroke.style.fill_color() {
-                    shape_stroke.style.set_fill_color(color.to_darkest_color());
-                }
+                shape_stroke
+                    .style
+                    .map_fill_colors(|color| color.to_darkest_color());
 
                 true
             }
@@ -367,7 +649,7 @@ impl Stroke {
             widths = (0..coords.len()).map(|_| 1.0).collect();
         };
 
-        smooth_options.stroke_width = stroke_width;
+        smooth_options.set_stroke_width(stroke_width);
 
         let penpath = PenPath::try_from_elements(
             coords
@@ -424,7 +706,12 @@ impl Stroke {
         };
         let image = render::Image::try_from_encoded_bytes(&bytes)?;
 
-        Ok(Stroke::BitmapImage(BitmapImage { image, rectangle }))
+        Ok(Stroke::BitmapImage(BitmapImage {
+            original_pixel_size: Some((image.pixel_width, image.pixel_height)),
+            image,
+            rectangle,
+            opacity: 1.0,
+        }))
     }
 
     pub fn into_xopp(self, current_dpi: f64) -> Option<xoppformat::XoppStrokeType> {
@@ -433,7 +720,7 @@ impl Stroke {
                 let (stroke_width, color): (f64, XoppColor) = match &brushstroke.style {
                     // Return early if color is None
                     Style::Smooth(options) => (
-                        options.stroke_width,
+                        options.stroke_width(),
                         crate::utils::xoppcolor_from_color(options.stroke_color?),
                     ),
                     Style::Rough(options) => (
@@ -673,3 +960,156 @@ impl Stroke {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn smooth_options(stroke_color: Option<Color>, fill_color: Option<Color>) -> SmoothOptions {
+        let mut options = SmoothOptions::default();
+        options.set_fill_color(fill_color);
+        options.stroke_color = stroke_color;
+        options
+    }
+
+    fn brushstroke_w_colors(stroke_color: Option<Color>, fill_color: Option<Color>) -> Stroke {
+        Stroke::BrushStroke(BrushStroke::new(
+            Element::new(na::vector![0.0, 0.0], 0.5),
+            Style::Smooth(smooth_options(stroke_color, fill_color)),
+        ))
+    }
+
+    #[test]
+    fn opacity_clamped_to_min() {
+        let mut stroke = brushstroke_w_colors(Some(Color::BLACK), Some(Color::RED));
+
+        assert!(stroke.update_opacity(|_| 0.0));
+        let Stroke::BrushStroke(brushstroke) = &stroke else {
+            unreachable!()
+        };
+        assert_relative_eq!(
+            brushstroke.style.stroke_color().unwrap().a,
+            Stroke::OPACITY_MIN
+        );
+        assert_relative_eq!(
+            brushstroke.style.fill_color().unwrap().a,
+            Stroke::OPACITY_MIN
+        );
+
+        // already at the minimum, nothing changes
+        assert!(!stroke.update_opacity(|a| a - 0.1));
+    }
+
+    #[test]
+    fn opacity_unset_colors_untouched() {
+        let mut stroke = brushstroke_w_colors(None, None);
+
+        assert!(!stroke.update_opacity(|_| 0.5));
+        assert!(!stroke.update_opacity(|_| 0.5));
+        let Stroke::BrushStroke(brushstroke) = &stroke else {
+            unreachable!()
+        };
+        assert!(brushstroke.style.stroke_color().is_none());
+        assert!(brushstroke.style.fill_color().is_none());
+    }
+
+    #[test]
+    fn smooth_shapes_export_as_svg_primitives() {
+        use rnote_compose::shapes::{CubicBezier, Ellipse, Line, Shape};
+        use std::f64::consts::PI;
+
+        let gen_svg_data = |shape: Shape| {
+            let stroke = Stroke::ShapeStroke(ShapeStroke::new(
+                shape,
+                Style::Smooth(smooth_options(Some(Color::BLACK), Some(Color::RED))),
+            ));
+            assert!(stroke.gen_svg_is_native());
+            stroke.gen_svg().unwrap().svg_data
+        };
+
+        let mut rectangle =
+            Rectangle::from_p2d_aabb(Aabb::new(na::point![0.0, 0.0], na::point![100.0, 50.0]));
+        rectangle.rotate(PI / 4.0, na::point![50.0, 25.0]);
+        let rect_svg = gen_svg_data(Shape::Rectangle(rectangle));
+        assert!(rect_svg.contains("<rect"));
+        assert!(rect_svg.contains("transform="));
+
+        let ellipse = Ellipse {
+            radii: na::vector![20.0, 10.0],
+            transform: Transform::new_w_isometry(na::Isometry2::new(na::vector![50.0, 50.0], 0.0)),
+        };
+        assert!(gen_svg_data(Shape::Ellipse(ellipse)).contains("<ellipse"));
+
+        let line = Line::new(na::vector![0.0, 0.0], na::vector![10.0, 10.0]);
+        let line_svg = gen_svg_data(Shape::Line(line));
+        assert!(line_svg.contains("<line"));
+        // lines are never filled
+        assert!(line_svg.contains("fill=\"none\""));
+
+        let cubbez = CubicBezier {
+            start: na::vector![0.0, 0.0],
+            cp1: na::vector![10.0, 0.0],
+            cp2: na::vector![10.0, 10.0],
+            end: na::vector![0.0, 10.0],
+        };
+        assert!(gen_svg_data(Shape::CubicBezier(cubbez)).contains("<path"));
+    }
+
+    /// Replace the values of all fields with the given name.
+    fn replace_json_field(value: &mut serde_json::Value, name: &str, new: &serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, field) in map.iter_mut() {
+                    if key == name {
+                        *field = new.clone();
+                    } else {
+                        replace_json_field(field, name, new);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => {
+                for value in values {
+                    replace_json_field(value, name, new);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn invalid_smooth_stroke_widths_repaired_on_load() {
+        let stroke = brushstroke_w_colors(Some(Color::BLACK), None);
+        let value = serde_json::to_value(&stroke).unwrap();
+
+        // non-finite numbers end up as null in the saved file
+        let mut nan_value = value.clone();
+        replace_json_field(&mut nan_value, "stroke_width", &serde_json::Value::Null);
+        let loaded = serde_json::from_value::<Stroke>(nan_value).unwrap();
+        assert_relative_eq!(
+            loaded.stroke_width().unwrap(),
+            SmoothOptions::STROKE_WIDTH_DEFAULT
+        );
+
+        let mut huge_value = value;
+        replace_json_field(&mut huge_value, "stroke_width", &serde_json::json!(1e9));
+        let loaded = serde_json::from_value::<Stroke>(huge_value).unwrap();
+        assert_relative_eq!(
+            loaded.stroke_width().unwrap(),
+            SmoothOptions::STROKE_WIDTH_MAX
+        );
+    }
+
+    #[test]
+    fn smooth_stroke_width_setter_validates() {
+        let mut options = SmoothOptions::default();
+        options.set_stroke_width(f64::NAN);
+        assert_relative_eq!(options.stroke_width(), SmoothOptions::STROKE_WIDTH_DEFAULT);
+        options.set_stroke_width(0.0);
+        assert_relative_eq!(options.stroke_width(), SmoothOptions::STROKE_WIDTH_MIN);
+        options.set_stroke_width(f64::INFINITY);
+        assert_relative_eq!(options.stroke_width(), SmoothOptions::STROKE_WIDTH_MIN);
+        options.set_stroke_width(12.0);
+        assert_relative_eq!(options.stroke_width(), 12.0);
+    }
+}
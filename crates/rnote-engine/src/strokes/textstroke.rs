@@ -1,9 +1,9 @@
 // Imports
 use super::Content;
-use crate::{Camera, Drawable};
+use crate::{render, Camera, Drawable};
 use itertools::Itertools;
 use kurbo::Shape;
-use p2d::bounding_volume::Aabb;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
 use piet::{RenderContext, TextLayout, TextLayoutBuilder};
 use rnote_compose::ext::{AabbExt, Affine2Ext, Vector2Ext};
 use rnote_compose::shapes::Shapeable;
@@ -14,7 +14,7 @@ use std::ops::Range;
 use tracing::error;
 use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename = "font_style")]
 pub enum FontStyle {
     #[serde(rename = "regular")]
@@ -82,7 +82,7 @@ impl From<TextAlignment> for piet::TextAlignment {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename = "text_attribute")]
 pub enum TextAttribute {
     /// The font family.
@@ -169,7 +169,7 @@ impl TextAttribute {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename = "ranged_text_attribute")]
 pub struct RangedTextAttribute {
     #[serde(rename = "range")]
@@ -465,6 +465,10 @@ impl Transformable for TextStroke {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.transform.append_scale_mut(scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.transform.append_shear_wrt_point_mut(shear, pivot);
+    }
 }
 
 impl Shapeable for TextStroke {
@@ -519,6 +523,102 @@ impl Shapeable for TextStroke {
 }
 
 impl Content for TextStroke {
+    /// Text is kept as native Svg text, with the ranged attributes as styled tspans.
+    fn gen_svg(&self) -> Result<render::Svg, anyhow::Error> {
+        let text_layout = self
+            .text_style
+            .build_text_layout(&mut piet_cairo::CairoText::new(), self.text.clone())?;
+        let mut ranged_text_attributes = self.text_style.ranged_text_attributes.clone();
+        ranged_text_attributes.sort_by_key(|attr| attr.range.start);
+
+        let mut w = xmlwriter::XmlWriter::new(xmlwriter::Options {
+            use_single_quote: false,
+            indent: xmlwriter::Indent::None,
+            attributes_indent: xmlwriter::Indent::None,
+        });
+        w.start_element("g");
+        w.write_attribute("transform", &self.transform.to_svg_transform_attr_str());
+        w.start_element("text");
+        w.write_attribute("xml:space", "preserve");
+        let base_attrs = [
+            TextAttribute::FontFamily(self.text_style.font_family.clone()),
+            TextAttribute::FontSize(self.text_style.font_size),
+            TextAttribute::FontWeight(self.text_style.font_weight),
+            TextAttribute::Style(self.text_style.font_style),
+            TextAttribute::TextColor(self.text_style.color),
+        ];
+        for (name, value) in svg_attrs_for_text_attrs(base_attrs.iter()) {
+            w.write_attribute(name, &value);
+        }
+
+        for line in 0..text_layout.line_count() {
+            let Some(line_metric) = text_layout.line_metric(line) else {
+                continue;
+            };
+            let line_start = line_metric.start_offset;
+            let line_end = line_start
+                + self.text[line_start..line_metric.end_offset]
+                    .trim_end_matches(['\n', '\r'])
+                    .len();
+            if line_start == line_end {
+                continue;
+            }
+            w.start_element("tspan");
+            w.write_attribute_fmt(
+                "x",
+                format_args!(
+                    "{:.3}",
+                    text_layout.hit_test_text_position(line_start).point.x
+                ),
+            );
+            w.write_attribute_fmt(
+                "y",
+                format_args!("{:.3}", line_metric.y_offset + line_metric.baseline),
+            );
+
+            // Split the line where attributes start or end
+            let mut boundaries = ranged_text_attributes
+                .iter()
+                .flat_map(|attr| [attr.range.start, attr.range.end])
+                .filter(|pos| *pos > line_start && *pos < line_end)
+                .chain([line_start, line_end])
+                .collect::<Vec<usize>>();
+            boundaries.sort_unstable();
+            boundaries.dedup();
+            for (start, end) in boundaries.iter().zip(boundaries.iter().skip(1)) {
+                let Some(segment) = self.text.get(*start..*end) else {
+                    continue;
+                };
+                let svg_attrs = svg_attrs_for_text_attrs(
+                    ranged_text_attributes
+                        .iter()
+                        .filter(|attr| attr.range.start <= *start && attr.range.end >= *end)
+                        .map(|attr| &attr.attribute),
+                );
+                if svg_attrs.is_empty() {
+                    w.write_text(segment);
+                } else {
+                    w.start_element("tspan");
+                    for (name, value) in svg_attrs {
+                        w.write_attribute(name, &value);
+                    }
+                    w.write_text(segment);
+                    w.end_element();
+                }
+            }
+            w.end_element();
+        }
+
+        Ok(render::Svg {
+            svg_data: w.end_document(),
+            bounds: self.bounds(),
+        })
+    }
+
+    fn gen_svg_is_native(&self) -> bool {
+        true
+    }
+
     fn update_geometry(&mut self) {}
 }
 
@@ -552,6 +652,81 @@ impl TextStroke {
         &self.text[range]
     }
 
+    /// The byte ranges of the non-overlapping occurrences of the query in the text.
+    pub fn find_text(&self, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+        if query.is_empty() {
+            return vec![];
+        }
+        if case_sensitive {
+            return self
+                .text
+                .match_indices(query)
+                .map(|(start, matched)| start..start + matched.len())
+                .collect();
+        }
+
+        let query = query
+            .chars()
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<char>>();
+        // The end of the match starting at the beginning of the text, if there is one
+        let match_end = |text: &str| -> Option<usize> {
+            let mut query_chars = query.iter();
+            for (i, c) in text.char_indices() {
+                for lowercase in c.to_lowercase() {
+                    if query_chars.next() != Some(&lowercase) {
+                        return None;
+                    }
+                }
+                if query_chars.len() == 0 {
+                    return Some(i + c.len_utf8());
+                }
+            }
+            None
+        };
+        let mut matches = vec![];
+        let mut next_start = 0;
+        for (start, _) in self.text.char_indices() {
+            if start < next_start {
+                continue;
+            }
+            if let Some(len) = match_end(&self.text[start..]) {
+                matches.push(start..start + len);
+                next_start = start + len;
+            }
+        }
+        matches
+    }
+
+    /// The bounds of the text in the byte range, in document coordinates.
+    ///
+    /// Covers all lines that the range spans. None when the range is empty or not on char boundaries.
+    pub fn bounds_for_text_range(&self, range: Range<usize>) -> Option<Aabb> {
+        if range.is_empty()
+            || range.end > self.text.len()
+            || !self.text.is_char_boundary(range.start)
+            || !self.text.is_char_boundary(range.end)
+        {
+            return None;
+        }
+        let text_layout = match self
+            .text_style
+            .build_text_layout(&mut piet_cairo::CairoText::new(), self.text.clone())
+        {
+            Ok(text_layout) => text_layout,
+            Err(e) => {
+                error!("Building text layout failed while calculating the bounds for a text range, Err: {e:?}");
+                return None;
+            }
+        };
+
+        text_layout
+            .rects_for_range(range)
+            .into_iter()
+            .map(|rect| self.transform.transform_aabb(Aabb::from_kurbo_rect(rect)))
+            .reduce(|acc, bounds| acc.merged(&bounds))
+    }
+
     /// Get a cursor matching best for the given coordinate.
     ///
     /// `coord` must be in global coordinate space.
@@ -583,7 +758,7 @@ impl TextStroke {
         self.text.insert_str(cursor.cur_cursor(), text);
 
         // translate the text attributes
-        self.translate_attrs_after_cursor(cursor.cur_cursor(), text.len() as i32);
+        self.translate_attrs_for_insertion(cursor.cur_cursor(), text.len());
 
         *cursor = GraphemeCursor::new(cursor.cur_cursor() + text.len(), self.text.len(), true);
     }
@@ -596,10 +771,7 @@ impl TextStroke {
                 self.text.replace_range(prev_pos..cur_pos, "");
 
                 // translate the text attributes
-                self.translate_attrs_after_cursor(
-                    prev_pos,
-                    prev_pos as i32 - cur_pos as i32 + "".len() as i32,
-                );
+                self.translate_attrs_for_removal(prev_pos..cur_pos);
             }
 
             // New text length, new cursor
@@ -615,10 +787,7 @@ impl TextStroke {
                 self.text.replace_range(cur_pos..next_pos, "");
 
                 // translate the text attributes
-                self.translate_attrs_after_cursor(
-                    cur_pos,
-                    -(next_pos as i32 - cur_pos as i32) + "".len() as i32,
-                );
+                self.translate_attrs_for_removal(cur_pos..next_pos);
             }
 
             // New text length, new cursor
@@ -634,10 +803,7 @@ impl TextStroke {
             self.text.replace_range(prev_pos..cur_pos, "");
 
             // translate the text attributes
-            self.translate_attrs_after_cursor(
-                prev_pos,
-                prev_pos as i32 - cur_pos as i32 + "".len() as i32,
-            );
+            self.translate_attrs_for_removal(prev_pos..cur_pos);
 
             // New text length, new cursor
             *cursor = GraphemeCursor::new(prev_pos, self.text.len(), true);
@@ -652,10 +818,7 @@ impl TextStroke {
             self.text.replace_range(cur_pos..next_pos, "");
 
             // translate the text attributes
-            self.translate_attrs_after_cursor(
-                cur_pos,
-                -(next_pos as i32 - cur_pos as i32) + "".len() as i32,
-            );
+            self.translate_attrs_for_removal(cur_pos..next_pos);
 
             // New text length, new cursor
             *cursor = GraphemeCursor::new(cur_pos, self.text.len(), true);
@@ -679,6 +842,10 @@ impl TextStroke {
 
         self.text.replace_range(cursor_range.clone(), replace_text);
 
+        // translate the text attributes
+        self.translate_attrs_for_removal(cursor_range.clone());
+        self.translate_attrs_for_insertion(cursor_range.start, replace_text.len());
+
         *cursor = GraphemeCursor::new(
             cursor_range.start + replace_text.len(),
             self.text.len(),
@@ -689,52 +856,60 @@ impl TextStroke {
             self.text.len(),
             true,
         );
+    }
 
-        self.translate_attrs_after_cursor(
-            cursor.cur_cursor(),
-            -(cursor_range.end as i32 - cursor_range.start as i32) + replace_text.len() as i32,
-        );
+    /// Translate the ranged text attributes for the text that was inserted at the position.
+    ///
+    /// Attributes that contain or end at the position are extended,
+    /// so that text typed at the end of an attribute continues its style.
+    fn translate_attrs_for_insertion(&mut self, pos: usize, len: usize) {
+        for attr in self.text_style.ranged_text_attributes.iter_mut() {
+            if attr.range.start >= pos {
+                attr.range.start += len;
+                attr.range.end += len;
+            } else if attr.range.end >= pos {
+                attr.range.end += len;
+            }
+        }
     }
 
-    /// Translate the ranged text attributes after the given cursor.
+    /// Translate the ranged text attributes for the text that was removed in the range.
     ///
-    /// Overlapping ranges are extended / shrunk
-    fn translate_attrs_after_cursor(&mut self, from_pos: usize, offset: i32) {
+    /// Overlapping attributes are shrunk and removed when they became empty.
+    fn translate_attrs_for_removal(&mut self, range: Range<usize>) {
+        let translate = |pos: usize| {
+            if pos >= range.end {
+                pos - range.len()
+            } else {
+                pos.min(range.start)
+            }
+        };
         for attr in self.text_style.ranged_text_attributes.iter_mut() {
-            if attr.range.start > from_pos {
-                if offset >= 0 {
-                    attr.range.start = attr
-                        .range
-                        .start
-                        .saturating_add(offset.unsigned_abs() as usize);
-                    attr.range.end = attr
-                        .range
-                        .end
-                        .saturating_add(offset.unsigned_abs() as usize);
-                } else {
-                    attr.range.start = attr
-                        .range
-                        .start
-                        .saturating_sub(offset.unsigned_abs() as usize);
-                    attr.range.end = attr
-                        .range
-                        .end
-                        .saturating_sub(offset.unsigned_abs() as usize);
-                }
-            } else if attr.range.end > from_pos {
-                if offset >= 0 {
-                    attr.range.end = attr
-                        .range
-                        .end
-                        .saturating_add(offset.unsigned_abs() as usize);
-                } else {
-                    attr.range.end = attr
-                        .range
-                        .end
-                        .saturating_sub(offset.unsigned_abs() as usize);
-                }
+            attr.range = translate(attr.range.start)..translate(attr.range.end);
+        }
+        self.normalize_attrs();
+    }
+
+    /// Remove empty attributes and merge equal attributes that overlap or are adjacent.
+    fn normalize_attrs(&mut self) {
+        let mut normalized: Vec<RangedTextAttribute> =
+            Vec::with_capacity(self.text_style.ranged_text_attributes.len());
+        for attr in std::mem::take(&mut self.text_style.ranged_text_attributes)
+            .into_iter()
+            .filter(|attr| !attr.range.is_empty())
+            .sorted_by_key(|attr| attr.range.start)
+        {
+            if let Some(prev) = normalized
+                .iter_mut()
+                .rev()
+                .find(|prev| prev.attribute == attr.attribute && prev.range.end >= attr.range.start)
+            {
+                prev.range.end = prev.range.end.max(attr.range.end);
+            } else {
+                normalized.push(attr);
             }
         }
+        self.text_style.ranged_text_attributes = normalized;
     }
 
     /// Remove all attributes in the given range.
@@ -819,6 +994,7 @@ impl TextStroke {
         }
 
         self.text_style.ranged_text_attributes = non_matching_attrs;
+        self.normalize_attrs();
     }
 
     pub fn update_selection_entire_text(
@@ -983,6 +1159,58 @@ impl TextStroke {
     }
 }
 
+/// The Svg presentation attributes for the text attributes.
+///
+/// Later attributes override earlier ones of the same variant.
+fn svg_attrs_for_text_attrs<'a>(
+    text_attributes: impl Iterator<Item = &'a TextAttribute>,
+) -> Vec<(&'static str, String)> {
+    let mut svg_attrs: Vec<(&'static str, String)> = vec![];
+    let mut set_attr = |name: &'static str, value: String| {
+        if let Some(attr) = svg_attrs.iter_mut().find(|(n, _)| *n == name) {
+            attr.1 = value;
+        } else {
+            svg_attrs.push((name, value));
+        }
+    };
+    let mut underline = None;
+    let mut strikethrough = None;
+    for text_attribute in text_attributes {
+        match text_attribute {
+            TextAttribute::FontFamily(font_family) => set_attr("font-family", font_family.clone()),
+            TextAttribute::FontSize(font_size) => set_attr("font-size", format!("{font_size:.3}")),
+            TextAttribute::FontWeight(font_weight) => {
+                set_attr("font-weight", font_weight.to_string())
+            }
+            TextAttribute::TextColor(color) => set_attr("fill", color.to_css_color_attr()),
+            TextAttribute::Style(font_style) => set_attr(
+                "font-style",
+                String::from(match font_style {
+                    FontStyle::Regular => "normal",
+                    FontStyle::Italic => "italic",
+                }),
+            ),
+            TextAttribute::Underline(u) => underline = Some(*u),
+            TextAttribute::Strikethrough(s) => strikethrough = Some(*s),
+        }
+    }
+    if underline.is_some() || strikethrough.is_some() {
+        let decorations = [(underline, "underline"), (strikethrough, "line-through")]
+            .into_iter()
+            .filter_map(|(enabled, decoration)| enabled.unwrap_or(false).then_some(decoration))
+            .collect::<Vec<&str>>();
+        set_attr(
+            "text-decoration",
+            if decorations.is_empty() {
+                String::from("none")
+            } else {
+                decorations.join(" ")
+            },
+        );
+    }
+    svg_attrs
+}
+
 fn get_intersecting_attrs_for_range(
     range: &Range<usize>,
     ranged_text_attributes: Vec<RangedTextAttribute>,
@@ -1024,3 +1252,111 @@ fn remove_intersecting_attrs_in_range(
         .filter(|attr| !attr.range.is_empty())
         .collect::<Vec<RangedTextAttribute>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOLD: TextAttribute = TextAttribute::FontWeight(700);
+
+    /// "Hello world", with "world" in bold.
+    fn textstroke_w_bold_word() -> TextStroke {
+        let mut textstroke = TextStroke::new(
+            String::from("Hello world"),
+            na::vector![0.0, 0.0],
+            TextStyle::default(),
+        );
+        textstroke.toggle_attrs_for_range(6..11, BOLD);
+        textstroke
+    }
+
+    fn ranges(textstroke: &TextStroke) -> Vec<Range<usize>> {
+        textstroke
+            .text_style
+            .ranged_text_attributes
+            .iter()
+            .map(|attr| attr.range.clone())
+            .collect()
+    }
+
+    fn insert_at(textstroke: &mut TextStroke, pos: usize, text: &str) {
+        let mut cursor = GraphemeCursor::new(pos, textstroke.text.len(), true);
+        textstroke.insert_text_after_cursor(text, &mut cursor);
+    }
+
+    fn replace_between(textstroke: &mut TextStroke, range: Range<usize>, text: &str) {
+        let mut cursor = GraphemeCursor::new(range.start, textstroke.text.len(), true);
+        let mut selection_cursor = GraphemeCursor::new(range.end, textstroke.text.len(), true);
+        textstroke.replace_text_between_selection_cursors(&mut cursor, &mut selection_cursor, text);
+    }
+
+    #[test]
+    fn attrs_maintained_on_insertion() {
+        // inside
+        let mut textstroke = textstroke_w_bold_word();
+        insert_at(&mut textstroke, 8, "XY");
+        assert_eq!(ranges(&textstroke), vec![6..13]);
+
+        // before, and at the start of the range
+        let mut textstroke = textstroke_w_bold_word();
+        insert_at(&mut textstroke, 2, "XY");
+        assert_eq!(ranges(&textstroke), vec![8..13]);
+        insert_at(&mut textstroke, 8, "Z");
+        assert_eq!(ranges(&textstroke), vec![9..14]);
+        assert_eq!(textstroke.get_text_slice_for_range(9..14), "world");
+
+        // at the end the style is continued
+        let mut textstroke = textstroke_w_bold_word();
+        insert_at(&mut textstroke, 11, "!");
+        assert_eq!(ranges(&textstroke), vec![6..12]);
+    }
+
+    #[test]
+    fn attrs_maintained_on_replacement_across_range() {
+        let mut textstroke = textstroke_w_bold_word();
+        replace_between(&mut textstroke, 4..8, "Z");
+        assert_eq!(textstroke.text, "HellZrld");
+        assert_eq!(ranges(&textstroke), vec![5..8]);
+        assert_eq!(textstroke.get_text_slice_for_range(5..8), "rld");
+
+        // the range is removed when its text is removed entirely
+        replace_between(&mut textstroke, 4..8, "");
+        assert_eq!(textstroke.text, "Hell");
+        assert!(ranges(&textstroke).is_empty());
+    }
+
+    #[test]
+    fn attrs_split_and_merged() {
+        let mut textstroke = textstroke_w_bold_word();
+        // toggling inside splits the range
+        textstroke.toggle_attrs_for_range(7..9, BOLD);
+        let mut split = ranges(&textstroke);
+        split.sort_by_key(|range| range.start);
+        assert_eq!(split, vec![6..7, 9..11]);
+        // and toggling again merges it
+        textstroke.toggle_attrs_for_range(7..9, BOLD);
+        assert_eq!(ranges(&textstroke), vec![6..11]);
+
+        // removing the text between equal attributes merges them
+        textstroke.toggle_attrs_for_range(0..2, BOLD);
+        replace_between(&mut textstroke, 2..6, "");
+        assert_eq!(ranges(&textstroke), vec![0..7]);
+        assert_eq!(textstroke.text, "Heworld");
+    }
+
+    #[test]
+    fn svg_export_styled_tspans() {
+        let mut textstroke = textstroke_w_bold_word();
+        textstroke.toggle_attrs_for_range(0..5, TextAttribute::Underline(true));
+        textstroke.text.push_str("\n<next>");
+        let svg = textstroke.gen_svg().unwrap();
+        assert!(textstroke.gen_svg_is_native());
+        assert!(svg.svg_data.starts_with("<g transform="));
+        assert!(svg
+            .svg_data
+            .contains(r#"<tspan text-decoration="underline">Hello</tspan> <tspan font-weight="700">world</tspan>"#));
+        assert!(svg.svg_data.contains("&lt;next"));
+        // one tspan per line, the second line placed below the first
+        assert_eq!(svg.svg_data.matches(" y=\"").count(), 2);
+    }
+}
@@ -1,6 +1,6 @@
 // Imports
 use super::content::GeneratedContentImages;
-use super::resize::{calculate_resize_ratio, ImageSizeOption};
+use super::resize::{calculate_resize_ratio, resize_rectangle_about_center, ImageSizeOption};
 use super::{Content, Stroke};
 use crate::document::Format;
 use crate::engine::import::{PdfImportPageSpacing, PdfImportPrefs};
@@ -31,6 +31,9 @@ pub struct VectorImage {
     pub intrinsic_size: na::Vector2<f64>,
     #[serde(rename = "rectangle")]
     pub rectangle: Rectangle,
+    /// The opacity of the image, ranging [0.0, 1.0].
+    #[serde(rename = "opacity", with = "rnote_compose::serialize::f64_dp3")]
+    pub opacity: f64,
 }
 
 impl Default for VectorImage {
@@ -39,6 +42,7 @@ impl Default for VectorImage {
             svg_data: String::default(),
             intrinsic_size: na::Vector2::zeros(),
             rectangle: Rectangle::default(),
+            opacity: 1.0,
         }
     }
 }
@@ -64,6 +68,7 @@ impl Content for VectorImage {
                 "transform",
                 self.rectangle.transform.to_svg_transform_attr_str(),
             )
+            .set("opacity", format!("{:.3}", self.opacity))
             .add(svg_root);
         let svg_data = rnote_compose::utils::svg_node_to_string(&group)?;
         let svg = render::Svg {
@@ -134,6 +139,10 @@ impl Transformable for VectorImage {
     fn scale(&mut self, scale: na::Vector2<f64>) {
         self.rectangle.scale(scale);
     }
+
+    fn shear(&mut self, shear: na::Vector2<f64>, pivot: na::Point2<f64>) {
+        self.rectangle.shear(shear, pivot);
+    }
 }
 
 impl VectorImage {
@@ -201,6 +210,7 @@ impl VectorImage {
             svg_data,
             intrinsic_size,
             rectangle,
+            ..Default::default()
         })
     }
 
@@ -339,4 +349,11 @@ impl VectorImage {
             })
             .collect()
     }
+
+    /// Resize the image to its intrinsic size about its center.
+    pub fn reset_to_original_size(&mut self) {
+        if self.intrinsic_size[0] > 0.0 && self.intrinsic_size[1] > 0.0 {
+            resize_rectangle_about_center(&mut self.rectangle, self.intrinsic_size);
+        }
+    }
 }
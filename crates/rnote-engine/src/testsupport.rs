@@ -0,0 +1,72 @@
+//! Fixtures shared by the unit tests of the crate.
+
+// Imports
+use crate::store::StrokeKey;
+use crate::strokes::{BrushStroke, ShapeStroke, Stroke};
+use crate::{Engine, StrokeStore};
+use approx::assert_relative_eq;
+use p2d::bounding_volume::Aabb;
+use rnote_compose::penevent::PenEvent;
+use rnote_compose::penpath::{Element, Segment};
+use rnote_compose::shapes::Rectangle;
+use rnote_compose::{PenPath, Shape, Style};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// A rectangle shape stroke with the default style, its outline running along `rect`.
+pub(crate) fn rect_stroke(rect: Aabb) -> Stroke {
+    Stroke::ShapeStroke(ShapeStroke::new(
+        Shape::Rectangle(Rectangle::from_p2d_aabb(rect)),
+        Style::default(),
+    ))
+}
+
+/// Insert a [rect_stroke()] along `rect` into the store.
+pub(crate) fn insert_rect(store: &mut StrokeStore, rect: Aabb) -> StrokeKey {
+    store.insert_stroke(rect_stroke(rect), None)
+}
+
+/// A brush stroke at height `y` along the x axis with six line segments, the pressure increasing with each element.
+pub(crate) fn insert_line_stroke(store: &mut StrokeStore, y: f64) -> StrokeKey {
+    let element = |i: usize| Element::new(na::vector![i as f64 * 10.0, y], i as f64 * 0.1);
+    let path = PenPath::new_w_segments(
+        element(0),
+        (1..=6).map(|i| Segment::LineTo { end: element(i) }),
+    );
+    store.insert_stroke(
+        Stroke::BrushStroke(BrushStroke::from_penpath(path, Style::default())),
+        None,
+    )
+}
+
+/// A new engine with a single recorded and selected [rect_stroke()] along `rect`.
+pub(crate) fn engine_w_selected_rect(rect: Aabb) -> Engine {
+    let mut engine = Engine::default();
+    let key = insert_rect(&mut engine.store, rect);
+    engine.store.record(Instant::now());
+    engine.store.set_selected(key, true);
+    engine
+}
+
+/// A pen down event of the primary button at `pos` without any pressed modifier keys.
+pub(crate) fn down_event(pos: na::Vector2<f64>) -> PenEvent {
+    PenEvent::down(Element::new(pos, Element::PRESSURE_DEFAULT), HashSet::new())
+}
+
+/// Assert that the bounds are equal, up to floating point errors.
+pub(crate) fn assert_bounds_eq(a: Aabb, b: Aabb) {
+    assert_relative_eq!(a.mins, b.mins);
+    assert_relative_eq!(a.maxs, b.maxs);
+}
+
+/// An empty directory for the test `name` in the temporary directory.
+///
+/// `prefix` separates the directories of the different test modules.
+pub(crate) fn test_dir(prefix: &str, name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("rnote-{prefix}-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -1,9 +1,17 @@
+// Imports
+use p2d::bounding_volume::{Aabb, BoundingVolume};
+
 /// Flags returned to the UI widget that holds the engine.
 #[must_use]
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WidgetFlags {
     /// Needs surface redrawing.
     pub redraw: bool,
+    /// The region that needs redrawing when `redraw` is set, in document coordinates.
+    ///
+    /// When None, the entire surface is redrawn.
+    /// Merging flags unions the regions, and a full redraw of one side results in a full redraw.
+    pub damage: Option<Aabb>,
     /// Needs surface resizing.
     pub resize: bool,
     /// Refresh the UI with the engine state.
@@ -18,6 +26,17 @@ pub struct WidgetFlags {
     pub zoomed: bool,
     /// Deselect the elements of the global color picker.
     pub deselect_color_setters: bool,
+    /// Indicates that strokes were added to or removed from the selection.
+    ///
+    /// The style of the new selection can be retrieved with [crate::Engine::selection_style_summary],
+    /// the number and kinds of the selected strokes with [crate::Engine::selection_summary].
+    pub selection_changed: bool,
+    /// Indicates that a modification was rejected because the engine is read-only, see [crate::Engine::set_read_only].
+    ///
+    /// The UI should hint this to the user in a non-destructive way.
+    pub read_only_rejected: bool,
+    /// Indicates that the cursor of the current pen might have changed, see [crate::Engine::current_cursor].
+    pub update_cursor: bool,
     /// Is Some when undo button visibility should be changed. Is None if should not be changed.
     pub hide_undo: Option<bool>,
     /// Is Some when redo button visibility should be changed. Is None if should not be changed.
@@ -32,6 +51,7 @@ impl Default for WidgetFlags {
     fn default() -> Self {
         Self {
             redraw: false,
+            damage: None,
             resize: false,
             refresh_ui: false,
             store_modified: false,
@@ -39,6 +59,9 @@ impl Default for WidgetFlags {
             zoomed_temporarily: false,
             zoomed: false,
             deselect_color_setters: false,
+            selection_changed: false,
+            read_only_rejected: false,
+            update_cursor: false,
             hide_undo: None,
             hide_redo: None,
             enable_text_preprocessing: None,
@@ -57,6 +80,12 @@ impl std::ops::BitOr for WidgetFlags {
 
 impl std::ops::BitOrAssign for WidgetFlags {
     fn bitor_assign(&mut self, rhs: Self) {
+        self.damage = match (self.redraw, self.damage, rhs.redraw, rhs.damage) {
+            (true, Some(damage), true, Some(rhs_damage)) => Some(damage.merged(&rhs_damage)),
+            (true, damage, false, _) => damage,
+            (false, _, true, rhs_damage) => rhs_damage,
+            (false, _, false, _) | (true, _, true, _) => None,
+        };
         self.redraw |= rhs.redraw;
         self.resize |= rhs.resize;
         self.refresh_ui |= rhs.refresh_ui;
@@ -65,6 +94,9 @@ impl std::ops::BitOrAssign for WidgetFlags {
         self.zoomed_temporarily |= rhs.zoomed_temporarily;
         self.zoomed |= rhs.zoomed;
         self.deselect_color_setters |= rhs.deselect_color_setters;
+        self.selection_changed |= rhs.selection_changed;
+        self.read_only_rejected |= rhs.read_only_rejected;
+        self.update_cursor |= rhs.update_cursor;
         if rhs.hide_undo.is_some() {
             self.hide_undo = rhs.hide_undo
         }
@@ -76,3 +108,59 @@ impl std::ops::BitOrAssign for WidgetFlags {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damaged(mins: na::Point2<f64>, maxs: na::Point2<f64>) -> WidgetFlags {
+        WidgetFlags {
+            redraw: true,
+            damage: Some(Aabb::new(mins, maxs)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_damage() {
+        let first = damaged(na::point![0.0, 0.0], na::point![10.0, 10.0]);
+        let second = damaged(na::point![20.0, 5.0], na::point![30.0, 15.0]);
+
+        // regions are unioned
+        let merged = first.clone() | second.clone();
+        assert!(merged.redraw);
+        assert_eq!(
+            merged.damage,
+            Some(Aabb::new(na::point![0.0, 0.0], na::point![30.0, 15.0]))
+        );
+
+        // flags without a redraw keep the region
+        let merged = first.clone() | WidgetFlags::default();
+        assert_eq!(merged.damage, first.damage);
+        let merged = WidgetFlags::default() | second.clone();
+        assert_eq!(merged.damage, second.damage);
+
+        // a full redraw on either side results in a full redraw
+        let full = WidgetFlags {
+            redraw: true,
+            ..Default::default()
+        };
+        let merged = first.clone() | full.clone();
+        assert!(merged.redraw);
+        assert_eq!(merged.damage, None);
+        let merged = full | second;
+        assert!(merged.redraw);
+        assert_eq!(merged.damage, None);
+
+        // a region without a redraw is ignored
+        let stale = WidgetFlags {
+            damage: Some(Aabb::new(
+                na::point![-50.0, -50.0],
+                na::point![-40.0, -40.0],
+            )),
+            ..Default::default()
+        };
+        let merged = first.clone() | stale;
+        assert_eq!(merged.damage, first.damage);
+    }
+}
@@ -3,6 +3,10 @@ use crate::RnApp;
 use adw::prelude::*;
 use gtk4::{gio, glib, glib::clone};
 
+/// The accelerators of the application actions.
+pub(crate) const APP_ACTION_ACCELS: &[(&str, &[&str])] =
+    &[("app.quit", &["<Ctrl>q"]), ("app.new-window", &["<Ctrl>n"])];
+
 impl RnApp {
     pub(crate) fn setup_actions(&self) {
         let action_quit = gio::SimpleAction::new("quit", None);
@@ -65,7 +69,8 @@ impl RnApp {
 
     // Accelerators / Keyboard Shortcuts
     pub(crate) fn setup_action_accels(&self) {
-        self.set_accels_for_action("app.quit", &["<Ctrl>q"]);
-        self.set_accels_for_action("app.new-window", &["<Ctrl>n"]);
+        for (action, accels) in APP_ACTION_ACCELS {
+            self.set_accels_for_action(action, accels);
+        }
     }
 }
@@ -1,5 +1,5 @@
 // Modules
-mod appactions;
+pub(crate) mod appactions;
 
 // Imports
 use crate::{
@@ -55,6 +55,13 @@ mod imp {
             self.setup_buildables();
             obj.setup_actions();
             obj.setup_action_accels();
+
+            if obj
+                .app_settings()
+                .is_some_and(|settings| settings.boolean("crash-recovery"))
+            {
+                rnote_engine::engine::recovery::install_crash_recovery_hook();
+            }
         }
 
         fn activate(&self) {
@@ -9,8 +9,10 @@ use gtk4::{
 use p2d::bounding_volume::BoundingVolume;
 use rnote_compose::penevent::ShortcutKey;
 use rnote_compose::SplitOrder;
-use rnote_engine::engine::StrokeContent;
-use rnote_engine::pens::PenStyle;
+use rnote_engine::engine::export::SelectionExportFormat;
+use rnote_engine::engine::{ClipboardStrokes, PastePlacement, StrokeContent};
+use rnote_engine::pens::{PenStyle, PensConfig};
+use rnote_engine::store::render_comp::RenderMode;
 use rnote_engine::strokes::resize::{ImageSizeOption, Resize};
 use rnote_engine::{Camera, Engine};
 use std::path::PathBuf;
@@ -20,6 +22,52 @@ use tracing::{debug, error};
 
 const CLIPBOARD_INPUT_STREAM_BUFSIZE: usize = 4096;
 
+/// The accelerators of the window actions.
+///
+/// Accelerators are handled before the canvas receives the key presses,
+/// so they must not be the same as any of the default pen shortcuts.
+const ACTION_ACCELS: &[(&str, &[&str])] = &[
+    ("win.active-tab-close", &["<Ctrl>w"]),
+    ("win.fullscreen", &["F11"]),
+    ("win.keyboard-shortcuts", &["<Ctrl>question"]),
+    ("win.open-canvasmenu", &["F9"]),
+    ("win.open-appmenu", &["F10"]),
+    ("win.open-doc", &["<Ctrl>o"]),
+    ("win.save-doc", &["<Ctrl>s"]),
+    ("win.save-doc-as", &["<Ctrl><Shift>s"]),
+    ("win.new-tab", &["<Ctrl>t"]),
+    ("win.snap-positions", &["<Ctrl><Shift>p"]),
+    ("win.clear-doc", &["<Ctrl>l"]),
+    ("win.print-doc", &["<Ctrl>p"]),
+    ("win.add-page-to-doc", &["<Ctrl><Shift>n"]),
+    ("win.remove-page-from-doc", &["<Ctrl><Shift>Delete"]),
+    ("win.zoom-in", &["<Ctrl>plus"]),
+    ("win.zoom-out", &["<Ctrl>minus"]),
+    ("win.import-file", &["<Ctrl><Shift>o"]),
+    ("win.undo", &["<Ctrl>z"]),
+    ("win.redo", &["<Ctrl><Shift>z"]),
+    ("win.clipboard-copy", &["<Ctrl>c"]),
+    ("win.clipboard-copy-selection-image", &["<Ctrl><Shift>c"]),
+    ("win.clipboard-cut", &["<Ctrl>x"]),
+    ("win.clipboard-paste", &["<Ctrl>v"]),
+    ("win.clipboard-paste-in-place", &["<Ctrl><Shift>v"]),
+    ("win.selection-select-recent", &["<Ctrl><Alt>r"]),
+    ("win.pen-style::brush", &["<Ctrl>1"]),
+    ("win.pen-style::shaper", &["<Ctrl>2"]),
+    ("win.pen-style::typewriter", &["<Ctrl>3"]),
+    ("win.pen-style::eraser", &["<Ctrl>4"]),
+    ("win.pen-style::selector", &["<Ctrl>5"]),
+    ("win.pen-style::tools", &["<Ctrl>6"]),
+];
+
+/// The accelerator of the visual debugging action, only set in the devel build.
+const VISUAL_DEBUG_ACCEL: &str = "F12";
+
+/// The accelerator to apply the pen preset at the position.
+fn pen_preset_accel(i: usize) -> String {
+    format!("<Ctrl><Alt>{}", i + 1)
+}
+
 impl RnAppWindow {
     /// Boolean actions have no target, and a boolean state. They have a default implementation for the activate signal,
     /// which requests the state to be inverted, and the default implementation for change_state, which sets the state
@@ -55,6 +103,12 @@ impl RnAppWindow {
         let action_visual_debug =
             gio::SimpleAction::new_stateful("visual-debug", None, &false.to_variant());
         self.add_action(&action_visual_debug);
+        let action_visual_debug_damage =
+            gio::SimpleAction::new_stateful("visual-debug-damage", None, &false.to_variant());
+        self.add_action(&action_visual_debug_damage);
+        let action_render_outlines =
+            gio::SimpleAction::new_stateful("render-outlines", None, &false.to_variant());
+        self.add_action(&action_render_outlines);
         let action_debug_export_engine_state =
             gio::SimpleAction::new("debug-export-engine-state", None);
         self.add_action(&action_debug_export_engine_state);
@@ -74,6 +128,9 @@ impl RnAppWindow {
         let action_snap_positions =
             gio::SimpleAction::new_stateful("snap-positions", None, &false.to_variant());
         self.add_action(&action_snap_positions);
+        let action_read_only =
+            gio::SimpleAction::new_stateful("read-only", None, &false.to_variant());
+        self.add_action(&action_read_only);
         let action_show_format_borders =
             gio::SimpleAction::new_stateful("show-format-borders", None, &true.to_variant());
         self.add_action(&action_show_format_borders);
@@ -86,12 +143,18 @@ impl RnAppWindow {
         let action_respect_borders =
             gio::PropertyAction::new("respect-borders", self, "respect-borders");
         self.add_action(&action_respect_borders);
+        let action_recolor_selection_only =
+            gio::PropertyAction::new("recolor-selection-only", self, "recolor-selection-only");
+        self.add_action(&action_recolor_selection_only);
         let action_pen_style = gio::SimpleAction::new_stateful(
             "pen-style",
             Some(&String::static_variant_type()),
             &String::from("brush").to_variant(),
         );
         self.add_action(&action_pen_style);
+        let action_apply_pen_preset =
+            gio::SimpleAction::new("apply-pen-preset", Some(&u32::static_variant_type()));
+        self.add_action(&action_apply_pen_preset);
         let action_undo_stroke = gio::SimpleAction::new("undo", None);
         self.add_action(&action_undo_stroke);
         let action_redo_stroke = gio::SimpleAction::new("redo", None);
@@ -122,6 +185,16 @@ impl RnAppWindow {
         self.add_action(&action_selection_select_all);
         let action_selection_deselect_all = gio::SimpleAction::new("selection-deselect-all", None);
         self.add_action(&action_selection_deselect_all);
+        let action_selection_annotate_measurements =
+            gio::SimpleAction::new("selection-annotate-measurements", None);
+        self.add_action(&action_selection_annotate_measurements);
+        let action_selection_select_recent =
+            gio::SimpleAction::new("selection-select-recent", None);
+        self.add_action(&action_selection_select_recent);
+        let action_selection_lock = gio::SimpleAction::new("selection-lock", None);
+        self.add_action(&action_selection_lock);
+        let action_unlock_all_strokes = gio::SimpleAction::new("unlock-all-strokes", None);
+        self.add_action(&action_unlock_all_strokes);
         let action_clear_doc = gio::SimpleAction::new("clear-doc", None);
         self.add_action(&action_clear_doc);
         let action_new_doc = gio::SimpleAction::new("new-doc", None);
@@ -138,6 +211,9 @@ impl RnAppWindow {
         self.add_action(&action_print_doc);
         let action_import_file = gio::SimpleAction::new("import-file", None);
         self.add_action(&action_import_file);
+        let action_import_rnote_file_contents =
+            gio::SimpleAction::new("import-rnote-file-contents", None);
+        self.add_action(&action_import_rnote_file_contents);
         let action_export_doc = gio::SimpleAction::new("export-doc", None);
         self.add_action(&action_export_doc);
         let action_export_doc_pages = gio::SimpleAction::new("export-doc-pages", None);
@@ -146,10 +222,16 @@ impl RnAppWindow {
         self.add_action(&action_export_selection);
         let action_clipboard_copy = gio::SimpleAction::new("clipboard-copy", None);
         self.add_action(&action_clipboard_copy);
+        let action_clipboard_copy_selection_image =
+            gio::SimpleAction::new("clipboard-copy-selection-image", None);
+        self.add_action(&action_clipboard_copy_selection_image);
         let action_clipboard_cut = gio::SimpleAction::new("clipboard-cut", None);
         self.add_action(&action_clipboard_cut);
         let action_clipboard_paste = gio::SimpleAction::new("clipboard-paste", None);
         self.add_action(&action_clipboard_paste);
+        let action_clipboard_paste_in_place =
+            gio::SimpleAction::new("clipboard-paste-in-place", None);
+        self.add_action(&action_clipboard_paste_in_place);
         let action_clipboard_paste_contextmenu =
             gio::SimpleAction::new("clipboard-paste-contextmenu", None);
         self.add_action(&action_clipboard_paste_contextmenu);
@@ -253,6 +335,10 @@ impl RnAppWindow {
             action_devel_menu,
             #[weak]
             action_visual_debug,
+            #[weak]
+            action_visual_debug_damage,
+            #[weak]
+            action_render_outlines,
             move |action, _| {
                 let state = action.state().unwrap().get::<bool>().unwrap();
 
@@ -263,6 +349,8 @@ impl RnAppWindow {
                 if state {
                     debug!("Disabling developer mode, disabling visual debugging.");
                     action_visual_debug.change_state(&false.to_variant());
+                    action_visual_debug_damage.change_state(&false.to_variant());
+                    action_render_outlines.change_state(&false.to_variant());
                 }
                 action.change_state(&(!state).to_variant());
             }
@@ -286,6 +374,38 @@ impl RnAppWindow {
             }
         ));
 
+        // Visualize the damage regions of redraws
+        action_visual_debug_damage.connect_change_state(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |action, state_request| {
+                let visual_debug_damage = state_request.unwrap().get::<bool>().unwrap();
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas
+                    .engine_mut()
+                    .set_visual_debug_damage(visual_debug_damage);
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+                action.set_state(&visual_debug_damage.to_variant());
+            }
+        ));
+
+        // Draw only the outlines of the strokes
+        action_render_outlines.connect_change_state(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |action, state_request| {
+                let render_outlines = state_request.unwrap().get::<bool>().unwrap();
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas.engine_mut().set_render_mode(if render_outlines {
+                    RenderMode::Outlines
+                } else {
+                    RenderMode::Full
+                });
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+                action.set_state(&render_outlines.to_variant());
+            }
+        ));
+
         // Create page
         action_new_tab.connect_activate(clone!(
             #[weak(rename_to=appwindow)]
@@ -360,11 +480,24 @@ impl RnAppWindow {
                     .canvas()
                     .engine_mut()
                     .document
-                    .snap_positions = snap_positions;
+                    .set_snap_enabled(snap_positions);
                 action.set_state(&snap_positions.to_variant());
             }
         ));
 
+        // Read-only
+        action_read_only.connect_change_state(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |action, state_request| {
+                let read_only = state_request.unwrap().get::<bool>().unwrap();
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas.engine_mut().set_read_only(read_only);
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+                action.set_state(&read_only.to_variant());
+            }
+        ));
+
         // Show format borders
         action_show_format_borders.connect_change_state(clone!(
             #[weak(rename_to=appwindow)]
@@ -418,6 +551,27 @@ impl RnAppWindow {
             }
         ));
 
+        // Apply pen preset
+        action_apply_pen_preset.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, target| {
+                let Some(i) = target.and_then(|target| target.get::<u32>()) else {
+                    error!("Activated apply-pen-preset action with invalid target");
+                    return;
+                };
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = match canvas.engine_mut().apply_preset_at(i as usize) {
+                    Ok(widget_flags) => widget_flags,
+                    Err(e) => {
+                        debug!("Applying pen preset at position {i} failed, Err: {e:?}");
+                        return;
+                    }
+                };
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+            }
+        ));
+
         // Tab actions
         action_active_tab_move_left.connect_activate(clone!(
             #[weak(rename_to=appwindow)]
@@ -530,6 +684,17 @@ impl RnAppWindow {
             }
         ));
 
+        // annotate selection measurements
+        action_selection_annotate_measurements.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, _| {
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas.engine_mut().annotate_selection_measurements();
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+            }
+        ));
+
         // invert color brightness of selection
         action_selection_invert_color.connect_activate(clone!(
             #[weak(rename_to=appwindow)]
@@ -563,6 +728,39 @@ impl RnAppWindow {
             }
         ));
 
+        // select the strokes drawn since the last pen style change
+        action_selection_select_recent.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, _| {
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas.engine_mut().select_strokes_since_pen_style_change();
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+            }
+        ));
+
+        // Lock selection
+        action_selection_lock.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, _| {
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas.engine_mut().lock_selection();
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+            }
+        ));
+
+        // Unlock all strokes
+        action_unlock_all_strokes.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, _| {
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let widget_flags = canvas.engine_mut().unlock_all_strokes();
+                appwindow.handle_widget_flags(widget_flags, &canvas);
+            }
+        ));
+
         // Clear doc
         action_clear_doc.connect_activate(clone!(
             #[weak(rename_to=appwindow)]
@@ -891,6 +1089,21 @@ impl RnAppWindow {
             }
         ));
 
+        // Import the strokes of another rnote file
+        action_import_rnote_file_contents.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, _| {
+                glib::spawn_future_local(clone!(
+                    #[weak]
+                    appwindow,
+                    async move {
+                        dialogs::import::filedialog_import_rnote_file_contents(&appwindow).await;
+                    }
+                ));
+            }
+        ));
+
         // Export document
         action_export_doc.connect_activate(clone!(
             #[weak(rename_to=appwindow)]
@@ -960,8 +1173,9 @@ impl RnAppWindow {
         action_clipboard_copy.connect_activate(clone!(#[weak(rename_to=appwindow)] self, move |_, _| {
             glib::spawn_future_local(clone!(#[weak] appwindow, async move {
                 let canvas = appwindow.active_tab_wrapper().canvas();
+                let clipboard_strokes = canvas.engine_ref().fetch_clipboard_selection();
                 let receiver = canvas.engine_ref().fetch_clipboard_content();
-                let (content, widget_flags) = match receiver.await {
+                let (mut content, widget_flags) = match receiver.await {
                     Ok(Ok((content, widget_flags))) => (content,widget_flags),
                     Ok(Err(e)) => {
                         error!("Fetching clipboard content failed in clipboard-copy action, Err: {e:?}");
@@ -973,6 +1187,10 @@ impl RnAppWindow {
                     }
                 };
 
+                if !clipboard_strokes.is_empty() {
+                    // Offered first, so that it is preferred when pasting into other rnote documents
+                    content.insert(0, (clipboard_strokes, ClipboardStrokes::MIME_TYPE.to_string()));
+                }
                 let gdk_content_provider = gdk::ContentProvider::new_union(content.into_iter().map(|(data, mime_type)| {
                     gdk::ContentProvider::for_bytes(mime_type.as_str(), &glib::Bytes::from_owned(data))
                 }).collect::<Vec<gdk::ContentProvider>>().as_slice());
@@ -985,12 +1203,41 @@ impl RnAppWindow {
             }));
         }));
 
+        // Clipboard copy selection as image
+        action_clipboard_copy_selection_image.connect_activate(clone!(#[weak(rename_to=appwindow)] self, move |_, _| {
+            glib::spawn_future_local(clone!(#[weak] appwindow, async move {
+                let canvas = appwindow.active_tab_wrapper().canvas();
+                let mut content = Vec::with_capacity(2);
+
+                for export_format in [SelectionExportFormat::Svg, SelectionExportFormat::Png] {
+                    let export = canvas.engine_ref().export_selection_w_format(export_format, None);
+                    match export.await {
+                        Ok(data) => content.push((data, export_format.mime_type())),
+                        Err(e) => {
+                            error!("Exporting selection failed in clipboard-copy-selection-image action, Err: {e:?}");
+                            return;
+                        }
+                    }
+                }
+
+                let gdk_content_provider = gdk::ContentProvider::new_union(content.into_iter().map(|(data, mime_type)| {
+                    gdk::ContentProvider::for_bytes(mime_type.as_str(), &glib::Bytes::from_owned(data))
+                }).collect::<Vec<gdk::ContentProvider>>().as_slice());
+
+                if let Err(e) = appwindow.clipboard().set_content(Some(&gdk_content_provider)) {
+                    error!("Set appwindow clipboard content failed in clipboard-copy-selection-image action, Err: {e:?}");
+                }
+            }));
+        }));
+
         // Clipboard cut
         action_clipboard_cut.connect_activate(clone!(#[weak(rename_to=appwindow)] self, move |_, _| {
             glib::spawn_future_local(clone!(#[weak] appwindow, async move {
                 let canvas = appwindow.active_tab_wrapper().canvas();
+                // needs to be fetched before the selection is cut
+                let clipboard_strokes = canvas.engine_ref().fetch_clipboard_selection();
                 let receiver = canvas.engine_mut().cut_clipboard_content();
-                let (content, widget_flags) = match receiver.await {
+                let (mut content, widget_flags) = match receiver.await {
                     Ok(Ok((content, widget_flags))) => (content,widget_flags),
                     Ok(Err(e)) => {
                         error!("Cutting clipboard content failed in clipboard-cut action, Err: {e:?}");
@@ -1001,6 +1248,10 @@ impl RnAppWindow {
                         return;
                     }
                 };
+                if !clipboard_strokes.is_empty() {
+                    // Offered first, so that it is preferred when pasting into other rnote documents
+                    content.insert(0, (clipboard_strokes, ClipboardStrokes::MIME_TYPE.to_string()));
+                }
                 let gdk_content_provider = gdk::ContentProvider::new_union(content.into_iter().map(|(data, mime_type)| {
                     gdk::ContentProvider::for_bytes(mime_type.as_str(), &glib::Bytes::from_owned(data))
                 }).collect::<Vec<gdk::ContentProvider>>().as_slice());
@@ -1018,7 +1269,20 @@ impl RnAppWindow {
             #[weak(rename_to=appwindow)]
             self,
             move |_, _| {
-                appwindow.clipboard_paste(None);
+                let canvas_wrapper = appwindow.active_tab_wrapper();
+                let pos = canvas_wrapper
+                    .pointer_doc_pos()
+                    .unwrap_or_else(|| canvas_wrapper.canvas().determine_stroke_import_pos(None));
+
+                appwindow.clipboard_paste(PastePlacement::AtPos(pos));
+            }
+        ));
+
+        action_clipboard_paste_in_place.connect_activate(clone!(
+            #[weak(rename_to=appwindow)]
+            self,
+            move |_, _| {
+                appwindow.clipboard_paste(PastePlacement::InPlace);
             }
         ));
 
@@ -1029,14 +1293,17 @@ impl RnAppWindow {
                 let canvas_wrapper = appwindow.active_tab_wrapper();
                 let canvas = canvas_wrapper.canvas();
 
-                let last_contextmenu_pos = canvas_wrapper.last_contextmenu_pos().map(|vec2| {
-                    let p = graphene::Point::new(vec2.x as f32, vec2.y as f32);
-                    (canvas.engine_ref().camera.transform().inverse()
-                        * na::point![p.x() as f64, p.y() as f64])
-                    .coords
-                });
-
-                appwindow.clipboard_paste(last_contextmenu_pos);
+                let last_contextmenu_pos = canvas_wrapper
+                    .last_contextmenu_pos()
+                    .map(|vec2| {
+                        let p = graphene::Point::new(vec2.x as f32, vec2.y as f32);
+                        (canvas.engine_ref().camera.transform().inverse()
+                            * na::point![p.x() as f64, p.y() as f64])
+                        .coords
+                    })
+                    .unwrap_or_else(|| canvas.determine_stroke_import_pos(None));
+
+                appwindow.clipboard_paste(PastePlacement::AtPos(last_contextmenu_pos));
             }
         ));
     }
@@ -1044,48 +1311,98 @@ impl RnAppWindow {
     pub(crate) fn setup_action_accels(&self) {
         let app = self.app();
 
-        app.set_accels_for_action("win.active-tab-close", &["<Ctrl>w"]);
-        app.set_accels_for_action("win.fullscreen", &["F11"]);
-        app.set_accels_for_action("win.keyboard-shortcuts", &["<Ctrl>question"]);
-        app.set_accels_for_action("win.open-canvasmenu", &["F9"]);
-        app.set_accels_for_action("win.open-appmenu", &["F10"]);
-        app.set_accels_for_action("win.open-doc", &["<Ctrl>o"]);
-        app.set_accels_for_action("win.save-doc", &["<Ctrl>s"]);
-        app.set_accels_for_action("win.save-doc-as", &["<Ctrl><Shift>s"]);
-        app.set_accels_for_action("win.new-tab", &["<Ctrl>t"]);
-        app.set_accels_for_action("win.snap-positions", &["<Ctrl><Shift>p"]);
-        app.set_accels_for_action("win.clear-doc", &["<Ctrl>l"]);
-        app.set_accels_for_action("win.print-doc", &["<Ctrl>p"]);
-        app.set_accels_for_action("win.add-page-to-doc", &["<Ctrl><Shift>a"]);
-        app.set_accels_for_action("win.remove-page-from-doc", &["<Ctrl><Shift>r"]);
-        app.set_accels_for_action("win.zoom-in", &["<Ctrl>plus"]);
-        app.set_accels_for_action("win.zoom-out", &["<Ctrl>minus"]);
-        app.set_accels_for_action("win.import-file", &["<Ctrl>i"]);
-        app.set_accels_for_action("win.undo", &["<Ctrl>z"]);
-        app.set_accels_for_action("win.redo", &["<Ctrl><Shift>z"]);
-        app.set_accels_for_action("win.clipboard-copy", &["<Ctrl>c"]);
-        app.set_accels_for_action("win.clipboard-cut", &["<Ctrl>x"]);
-        app.set_accels_for_action("win.clipboard-paste", &["<Ctrl>v"]);
-        app.set_accels_for_action("win.pen-style::brush", &["<Ctrl>1"]);
-        app.set_accels_for_action("win.pen-style::shaper", &["<Ctrl>2"]);
-        app.set_accels_for_action("win.pen-style::typewriter", &["<Ctrl>3"]);
-        app.set_accels_for_action("win.pen-style::eraser", &["<Ctrl>4"]);
-        app.set_accels_for_action("win.pen-style::selector", &["<Ctrl>5"]);
-        app.set_accels_for_action("win.pen-style::tools", &["<Ctrl>6"]);
+        for (action, accels) in ACTION_ACCELS {
+            app.set_accels_for_action(action, accels);
+        }
+        for i in 0..PensConfig::PRESETS_WITH_POSITION_MAX {
+            app.set_accels_for_action(
+                &format!("win.apply-pen-preset({i})"),
+                &[&pen_preset_accel(i)],
+            );
+        }
 
         // shortcuts for devel build
         if config::PROFILE.to_lowercase().as_str() == "devel" {
-            app.set_accels_for_action("win.visual-debug", &["<Ctrl><Shift>v"]);
+            app.set_accels_for_action("win.visual-debug", &[VISUAL_DEBUG_ACCEL]);
         }
     }
 
-    fn clipboard_paste(&self, target_pos: Option<na::Vector2<f64>>) {
+    /// Paste the clipboard content. Strokes in the rnote clipboard format are placed according to the placement,
+    /// other content is inserted at the paste position, or at the default import position when pasting in place.
+    fn clipboard_paste(&self, placement: PastePlacement) {
+        let target_pos = match placement {
+            PastePlacement::AtPos(pos) => Some(pos),
+            PastePlacement::InPlace | PastePlacement::Cascade { .. } => None,
+        };
         let canvas_wrapper = self.active_tab_wrapper();
         let canvas = canvas_wrapper.canvas();
         let content_formats = self.clipboard().formats();
 
         // Order matters here, we want to go from specific -> generic, mostly because `text/plain` is contained in other text based formats
-        if content_formats.contain_mime_type("text/uri-list") {
+        if content_formats.contain_mime_type(ClipboardStrokes::MIME_TYPE) {
+            glib::spawn_future_local(clone!(
+                #[weak]
+                canvas,
+                #[weak(rename_to=appwindow)]
+                self,
+                async move {
+                    debug!(
+                        "Recognized clipboard content format: {}",
+                        ClipboardStrokes::MIME_TYPE
+                    );
+
+                    match appwindow
+                        .clipboard()
+                        .read_future(
+                            &[ClipboardStrokes::MIME_TYPE],
+                            glib::source::Priority::DEFAULT,
+                        )
+                        .await
+                    {
+                        Ok((input_stream, _)) => {
+                            let mut acc = Vec::new();
+                            loop {
+                                match input_stream
+                                    .read_future(
+                                        vec![0; CLIPBOARD_INPUT_STREAM_BUFSIZE],
+                                        glib::source::Priority::DEFAULT,
+                                    )
+                                    .await
+                                {
+                                    Ok((mut bytes, n)) => {
+                                        if n == 0 {
+                                            break;
+                                        }
+                                        bytes.truncate(n);
+                                        acc.append(&mut bytes);
+                                    }
+                                    Err(e) => {
+                                        error!("Failed to read clipboard input stream, Err: {e:?}");
+                                        acc.clear();
+                                        break;
+                                    }
+                                }
+                            }
+
+                            if !acc.is_empty() {
+                                if let Err(e) = canvas.insert_clipboard_strokes(&acc, placement) {
+                                    error!("Failed to insert strokes while pasting as `{}`, Err: {e:?}", ClipboardStrokes::MIME_TYPE);
+                                    appwindow
+                                        .overlays()
+                                        .dispatch_toast_error(&gettext("Pasting strokes failed, they might be from an incompatible version"));
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!(
+                                "Reading clipboard failed while pasting as `{}`, Err: {e:?}",
+                                ClipboardStrokes::MIME_TYPE
+                            );
+                        }
+                    };
+                }
+            ));
+        } else if content_formats.contain_mime_type("text/uri-list") {
             glib::spawn_future_local(clone!(
                 #[weak(rename_to=appwindow)]
                 self,
@@ -1338,3 +1655,123 @@ impl RnAppWindow {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::appactions::APP_ACTION_ACCELS;
+    use rnote_compose::penevent::{KeyboardKey, ModifierKey};
+    use rnote_engine::pens::pensconfig::selectorshortcuts::{
+        KeyCombo, SelectorAction, SelectorShortcuts,
+    };
+    use rnote_engine::pens::Typewriter;
+    use std::collections::{BTreeSet, HashMap};
+
+    /// The pressed modifiers and the lower case key name of an accelerator.
+    ///
+    /// Accelerators only trigger when exactly their modifiers are pressed.
+    type Trigger = (BTreeSet<String>, String);
+
+    fn parse_accel(accel: &str) -> Trigger {
+        let mut modifiers = BTreeSet::new();
+        let mut rest = accel;
+        while let Some(stripped) = rest.strip_prefix('<') {
+            let (modifier, remainder) = stripped.split_once('>').unwrap();
+            modifiers.insert(modifier.to_lowercase());
+            rest = remainder;
+        }
+        let key = match rest {
+            "plus" => "+".to_string(),
+            "minus" => "-".to_string(),
+            "question" => "?".to_string(),
+            key => key.replace('_', "").to_lowercase(),
+        };
+        (modifiers, key)
+    }
+
+    fn combo_trigger(combo: &KeyCombo) -> Trigger {
+        let modifiers = combo
+            .modifiers
+            .iter()
+            .map(|m| {
+                match m {
+                    ModifierKey::KeyboardShift => "shift",
+                    ModifierKey::KeyboardCtrl => "ctrl",
+                    ModifierKey::KeyboardAlt => "alt",
+                }
+                .to_string()
+            })
+            .collect();
+        let key = match combo.key {
+            KeyboardKey::Unicode(keychar) => keychar.to_lowercase().to_string(),
+            KeyboardKey::CarriageReturn => "return".to_string(),
+            key => format!("{key:?}").to_lowercase(),
+        };
+        (modifiers, key)
+    }
+
+    fn all_accels() -> Vec<(String, String)> {
+        let mut accels = APP_ACTION_ACCELS
+            .iter()
+            .chain(ACTION_ACCELS)
+            .flat_map(|(action, accels)| {
+                accels
+                    .iter()
+                    .map(|accel| (action.to_string(), accel.to_string()))
+            })
+            .collect::<Vec<_>>();
+        accels.extend(
+            (0..PensConfig::PRESETS_WITH_POSITION_MAX)
+                .map(|i| (format!("win.apply-pen-preset({i})"), pen_preset_accel(i))),
+        );
+        accels.push((
+            "win.visual-debug".to_string(),
+            VISUAL_DEBUG_ACCEL.to_string(),
+        ));
+        accels
+    }
+
+    #[test]
+    fn accels_are_unique() {
+        let mut triggers = HashMap::<Trigger, String>::new();
+        for (action, accel) in all_accels() {
+            if let Some(other) = triggers.insert(parse_accel(&accel), action.clone()) {
+                panic!("`{accel}` of `{action}` is already the accelerator of `{other}`");
+            }
+        }
+    }
+
+    #[test]
+    fn accels_dont_shadow_pen_shortcuts() {
+        let shortcuts = SelectorShortcuts::default();
+        let mut pen_shortcuts = SelectorAction::ALL
+            .iter()
+            .flat_map(|&action| {
+                shortcuts
+                    .bindings(action)
+                    .iter()
+                    .map(move |combo| (combo_trigger(combo), format!("selector {action:?}")))
+            })
+            .collect::<Vec<_>>();
+        pen_shortcuts.extend(
+            ('a'..='z')
+                .filter(|&keychar| Typewriter::shortcut_text_attribute(keychar).is_some())
+                .map(|keychar| {
+                    (
+                        (BTreeSet::from(["ctrl".to_string()]), keychar.to_string()),
+                        format!("typewriter text attribute `{keychar}`"),
+                    )
+                }),
+        );
+
+        for (action, accel) in all_accels() {
+            let trigger = parse_accel(&accel);
+            for (pen_trigger, pen_shortcut) in pen_shortcuts.iter() {
+                assert_ne!(
+                    &trigger, pen_trigger,
+                    "`{accel}` of `{action}` shadows the pen shortcut {pen_shortcut}"
+                );
+            }
+        }
+    }
+}
@@ -70,6 +70,12 @@ impl RnAppWindow {
             .get_no_changes()
             .build();
 
+        // recolor selection only
+        app_settings
+            .bind("recolor-selection-only", self, "recolor-selection-only")
+            .get_no_changes()
+            .build();
+
         // show scrollbars
         app_settings
             .bind(
@@ -433,6 +439,40 @@ impl RnAppWindow {
                 .load_from_settings(&app_settings);
         }
 
+        {
+            // Colorpicker palettes
+            let colorpicker = self.overlays().colorpicker();
+            colorpicker.load_palettes_from_settings(&app_settings);
+
+            // Connect only after loading, else the bound setter colors would override the stored palettes
+            for setter in [
+                colorpicker.setter_1(),
+                colorpicker.setter_2(),
+                colorpicker.setter_3(),
+                colorpicker.setter_4(),
+                colorpicker.setter_5(),
+                colorpicker.setter_6(),
+                colorpicker.setter_7(),
+                colorpicker.setter_8(),
+                colorpicker.setter_9(),
+            ] {
+                for property in ["color", "has-color"] {
+                    setter.connect_notify_local(
+                        Some(property),
+                        clone!(
+                            #[weak]
+                            app_settings,
+                            #[weak]
+                            colorpicker,
+                            move |_, _| {
+                                colorpicker.save_palettes_to_settings(&app_settings);
+                            }
+                        ),
+                    );
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -467,6 +507,13 @@ impl RnAppWindow {
                 .save_to_settings(&app_settings);
         }
 
+        {
+            // Colorpicker palettes
+            self.overlays()
+                .colorpicker()
+                .save_palettes_to_settings(&app_settings);
+        }
+
         Ok(())
     }
 
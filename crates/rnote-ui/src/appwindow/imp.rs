@@ -20,11 +20,13 @@ pub(crate) struct RnAppWindow {
 
     pub(crate) save_in_progress: Cell<bool>,
     pub(crate) save_in_progress_toast: RefCell<Option<adw::Toast>>,
+    pub(crate) selection_summary_toast: RefCell<Option<adw::Toast>>,
     pub(crate) autosave: Cell<bool>,
     pub(crate) autosave_interval_secs: Cell<u32>,
     pub(crate) righthanded: Cell<bool>,
     pub(crate) block_pinch_zoom: Cell<bool>,
     pub(crate) respect_borders: Cell<bool>,
+    pub(crate) recolor_selection_only: Cell<bool>,
     pub(crate) touch_drawing: Cell<bool>,
     pub(crate) focus_mode: Cell<bool>,
     pub(crate) close_in_progress: Cell<bool>,
@@ -50,11 +52,13 @@ impl Default for RnAppWindow {
 
             save_in_progress: Cell::new(false),
             save_in_progress_toast: RefCell::new(None),
+            selection_summary_toast: RefCell::new(None),
             autosave: Cell::new(true),
             autosave_interval_secs: Cell::new(super::RnAppWindow::AUTOSAVE_INTERVAL_DEFAULT),
             righthanded: Cell::new(true),
             block_pinch_zoom: Cell::new(false),
             respect_borders: Cell::new(false),
+            recolor_selection_only: Cell::new(false),
             touch_drawing: Cell::new(false),
             focus_mode: Cell::new(false),
             close_in_progress: Cell::new(false),
@@ -142,6 +146,9 @@ impl ObjectImpl for RnAppWindow {
                 glib::ParamSpecBoolean::builder("respect-borders")
                     .default_value(false)
                     .build(),
+                glib::ParamSpecBoolean::builder("recolor-selection-only")
+                    .default_value(false)
+                    .build(),
                 glib::ParamSpecBoolean::builder("focus-mode")
                     .default_value(false)
                     .build(),
@@ -158,6 +165,7 @@ impl ObjectImpl for RnAppWindow {
             "righthanded" => self.righthanded.get().to_value(),
             "block-pinch-zoom" => self.block_pinch_zoom.get().to_value(),
             "respect-borders" => self.respect_borders.get().to_value(),
+            "recolor-selection-only" => self.recolor_selection_only.get().to_value(),
             "touch-drawing" => self.touch_drawing.get().to_value(),
             "focus-mode" => self.focus_mode.get().to_value(),
             _ => unimplemented!(),
@@ -185,6 +193,7 @@ impl ObjectImpl for RnAppWindow {
                 {
                     autosave_source_id.remove();
                 }
+                self.obj().update_recovery_intervals();
             }
             "autosave-interval-secs" => {
                 let autosave_interval_secs = value
@@ -196,6 +205,7 @@ impl ObjectImpl for RnAppWindow {
                 if self.autosave.get() {
                     self.update_autosave_handler();
                 }
+                self.obj().update_recovery_intervals();
             }
             "righthanded" => {
                 let righthanded = value
@@ -216,6 +226,11 @@ impl ObjectImpl for RnAppWindow {
                     value.get().expect("The value needs to be of type `bool`");
                 self.respect_borders.replace(respect_borders);
             }
+            "recolor-selection-only" => {
+                let recolor_selection_only: bool =
+                    value.get().expect("The value needs to be of type `bool`");
+                self.recolor_selection_only.replace(recolor_selection_only);
+            }
             "touch-drawing" => {
                 let touch_drawing: bool =
                     value.get().expect("The value needs to be of type `bool`");
@@ -314,7 +329,8 @@ impl RnAppWindow {
                                     "there are unsaved changes on the tab {:?} with a file on disk, saving",i
                                 );
                                 glib::spawn_future_local(clone!(#[weak] canvas, #[weak] appwindow ,async move {
-                                    if let Err(e) = canvas.save_document_to_file(&output_file).await {
+                                    // autosaves append to the journal, and are compacted into a full save when it grew too large
+                                    if let Err(e) = canvas.save_document_incremental(&output_file).await {
                                         error!("Saving document failed, Err: `{e:?}`");
                                         canvas.set_output_file(None);
                                         appwindow
@@ -619,6 +635,11 @@ impl RnAppWindow {
                 .eraser_page()
                 .stroke_width_picker()
                 .set_position(PositionType::Left);
+            obj.overlays()
+                .penssidebar()
+                .laser_page()
+                .stroke_width_picker()
+                .set_position(PositionType::Left);
             obj.overlays()
                 .penssidebar()
                 .tools_page()
@@ -746,6 +767,11 @@ impl RnAppWindow {
                 .eraser_page()
                 .stroke_width_picker()
                 .set_position(PositionType::Right);
+            obj.overlays()
+                .penssidebar()
+                .laser_page()
+                .stroke_width_picker()
+                .set_position(PositionType::Right);
             obj.overlays()
                 .penssidebar()
                 .tools_page()
@@ -9,6 +9,7 @@ use crate::{
     RnSidebar,
 };
 use adw::{prelude::*, subclass::prelude::*};
+use anyhow::Context;
 use gettextrs::gettext;
 use gtk4::{gdk, gio, glib, Application, IconTheme};
 use rnote_compose::Color;
@@ -18,6 +19,7 @@ use rnote_engine::pens::pensconfig::shaperconfig::ShaperStyle;
 use rnote_engine::pens::PenStyle;
 use rnote_engine::{engine::EngineTask, WidgetFlags};
 use std::path::Path;
+use std::time::Duration;
 use tracing::{error, warn};
 
 glib::wrapper! {
@@ -30,6 +32,28 @@ glib::wrapper! {
 impl RnAppWindow {
     const AUTOSAVE_INTERVAL_DEFAULT: u32 = 30;
     const PERIODIC_CONFIGSAVE_INTERVAL: u32 = 10;
+    const SELECTION_SUMMARY_TOAST_TIMEOUT: Duration = Duration::from_secs(2);
+    /// The actions that modify the document, disabled while it is read-only.
+    const READ_ONLY_DISABLED_ACTIONS: &'static [&'static str] = &[
+        "undo",
+        "redo",
+        "add-page-to-doc",
+        "remove-page-from-doc",
+        "resize-to-fit-content",
+        "selection-trash",
+        "selection-duplicate",
+        "selection-invert-color",
+        "selection-annotate-measurements",
+        "selection-lock",
+        "unlock-all-strokes",
+        "clear-doc",
+        "import-file",
+        "import-rnote-file-contents",
+        "clipboard-cut",
+        "clipboard-paste",
+        "clipboard-paste-in-place",
+        "clipboard-paste-contextmenu",
+    ];
 
     pub(crate) fn new(app: &Application) -> Self {
         glib::Object::builder().property("application", app).build()
@@ -65,6 +89,22 @@ impl RnAppWindow {
         self.set_property("autosave-interval-secs", autosave_interval_secs.to_value());
     }
 
+    /// The interval of the engine recovery snapshots, which follows the autosave settings.
+    pub(crate) fn recovery_interval(&self) -> Option<Duration> {
+        self.autosave()
+            .then(|| Duration::from_secs(u64::from(self.autosave_interval_secs())))
+    }
+
+    /// Apply the recovery snapshot interval to the engines of all tabs.
+    pub(crate) fn update_recovery_intervals(&self) {
+        let recovery_interval = self.recovery_interval();
+        for tab in self.get_all_tabs() {
+            tab.canvas()
+                .engine_mut()
+                .set_recovery_interval(recovery_interval);
+        }
+    }
+
     #[allow(unused)]
     pub(crate) fn righthanded(&self) -> bool {
         self.property::<bool>("righthanded")
@@ -100,6 +140,12 @@ impl RnAppWindow {
         self.property::<bool>("respect-borders")
     }
 
+    /// Whether picking a color while strokes are selected only recolors the selection,
+    /// leaving the pen colors untouched.
+    pub(crate) fn recolor_selection_only(&self) -> bool {
+        self.property::<bool>("recolor-selection-only")
+    }
+
     pub(crate) fn app(&self) -> RnApp {
         self.application().unwrap().downcast::<RnApp>().unwrap()
     }
@@ -204,7 +250,11 @@ impl RnAppWindow {
         //debug!("handling widget flags: '{widget_flags:?}'");
 
         if widget_flags.redraw {
-            canvas.queue_draw();
+            canvas.engine_mut().record_damage(&widget_flags);
+            match widget_flags.damage {
+                Some(damage) => canvas.queue_draw_damage(damage),
+                None => canvas.queue_draw(),
+            }
         }
         if widget_flags.resize {
             canvas.queue_resize();
@@ -212,7 +262,12 @@ impl RnAppWindow {
         if widget_flags.refresh_ui {
             self.refresh_ui_from_engine(&self.active_tab_wrapper());
         }
-        if widget_flags.store_modified {
+        if widget_flags.update_cursor {
+            canvas.refresh_cursor();
+        }
+        // Rejected modifications were discarded
+        if widget_flags.store_modified && !widget_flags.read_only_rejected {
+            canvas.engine_mut().mark_recovery_dirty();
             canvas.set_unsaved_changes(true);
             canvas.set_empty(false);
         }
@@ -245,6 +300,14 @@ impl RnAppWindow {
         if widget_flags.deselect_color_setters {
             self.overlays().colorpicker().deselect_setters();
         }
+        if widget_flags.selection_changed {
+            if let Some(summary) = canvas.engine_ref().selection_style_summary() {
+                self.overlays()
+                    .colorpicker()
+                    .reflect_selection_style(&summary);
+            }
+            self.announce_selection_summary(canvas);
+        }
         if let Some(hide_undo) = widget_flags.hide_undo {
             self.overlays()
                 .penpicker()
@@ -260,6 +323,37 @@ impl RnAppWindow {
         if let Some(enable_text_preprocessing) = widget_flags.enable_text_preprocessing {
             canvas.set_text_preprocessing(enable_text_preprocessing);
         }
+        if widget_flags.read_only_rejected {
+            canvas.error_bell();
+        }
+    }
+
+    /// Announce the number and kinds of the selected strokes to assistive technologies and show it in a toast.
+    fn announce_selection_summary(&self, canvas: &RnCanvas) {
+        let summary = canvas.engine_ref().selection_summary();
+        let text = if summary.count == 0 {
+            gettext("Selection cleared")
+        } else {
+            let kinds = [
+                (summary.n_brush, gettext("Brush strokes")),
+                (summary.n_shape, gettext("Shapes")),
+                (summary.n_text, gettext("Texts")),
+                (summary.n_image, gettext("Images")),
+            ]
+            .into_iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, label)| format!("{label}: {n}"))
+            .collect::<Vec<String>>()
+            .join(", ");
+            gettext("Selected strokes:") + &format!(" {} ({kinds})", summary.count)
+        };
+
+        canvas.announce(&text, gtk4::AccessibleAnnouncementPriority::Medium);
+        self.overlays().dispatch_toast_text_singleton(
+            &text,
+            Some(Self::SELECTION_SUMMARY_TOAST_TIMEOUT),
+            &mut self.imp().selection_summary_toast.borrow_mut(),
+        );
     }
 
     /// Get the active (selected) tab page.
@@ -540,7 +634,7 @@ impl RnAppWindow {
                 })?;
 
                 // If the file is already opened in a tab, simply switch to it
-                if let Some(page) = self.tabs_query_file_opened(input_file_path) {
+                if let Some(page) = self.tabs_query_file_opened(&input_file_path) {
                     self.overlays().tabview().set_selected_page(&page);
                     false
                 } else {
@@ -558,15 +652,21 @@ impl RnAppWindow {
                     } else {
                         self.active_tab_wrapper()
                     };
-                    let (bytes, _) = input_file.load_bytes_future().await?;
+                    let bytes = async_fs::read(crate::env::long_path(&input_file_path))
+                        .await
+                        .context(format!(
+                            "Failed to read file with path '{}'",
+                            input_file_path.display()
+                        ))?;
                     let widget_flags = wrapper
                         .canvas()
-                        .load_in_rnote_bytes(bytes.to_vec(), input_file.path())
+                        .load_in_rnote_bytes(bytes, Some(input_file_path))
                         .await?;
                     if rnote_file_new_tab {
                         self.append_wrapper_new_tab(&wrapper);
                     }
                     self.handle_widget_flags(widget_flags, &wrapper.canvas());
+                    wrapper.canvas().offer_recovery(self);
                     true
                 }
             }
@@ -634,9 +734,10 @@ impl RnAppWindow {
         let pen_sounds = canvas.engine_ref().pen_sounds();
         let doc_format = canvas.engine_ref().document.format;
         let total_zoom = canvas.engine_ref().camera.total_zoom();
-        let snap_positions = canvas.engine_ref().document.snap_positions;
-        let can_undo = canvas.engine_ref().can_undo();
-        let can_redo = canvas.engine_ref().can_redo();
+        let snap_positions = canvas.engine_ref().document.snap_config().enabled();
+        let read_only = canvas.engine_ref().read_only();
+        let can_undo = canvas.engine_ref().can_undo() && !read_only;
+        let can_redo = canvas.engine_ref().can_redo() && !read_only;
 
         self.overlays()
             .penpicker()
@@ -646,6 +747,14 @@ impl RnAppWindow {
             .penpicker()
             .redo_button()
             .set_sensitive(can_redo);
+        for action_name in Self::READ_ONLY_DISABLED_ACTIONS {
+            if let Some(action) = self
+                .lookup_action(action_name)
+                .and_then(|action| action.downcast::<gio::SimpleAction>().ok())
+            {
+                action.set_enabled(!read_only);
+            }
+        }
         self.main_header()
             .canvasmenu()
             .refresh_zoom_reset_label(total_zoom);
@@ -667,6 +776,11 @@ impl RnAppWindow {
             "snap-positions",
             &snap_positions.to_variant(),
         );
+        adw::prelude::ActionGroupExt::change_action_state(
+            self,
+            "read-only",
+            &read_only.to_variant(),
+        );
         adw::prelude::ActionGroupExt::change_action_state(
             self,
             "show-format-borders",
@@ -702,7 +816,7 @@ impl RnAppWindow {
                             .pens_config
                             .brush_config
                             .marker_options
-                            .fill_color
+                            .fill_color()
                             .unwrap_or(Color::TRANSPARENT);
                         self.overlays()
                             .colorpicker()
@@ -724,7 +838,7 @@ impl RnAppWindow {
                             .pens_config
                             .brush_config
                             .solid_options
-                            .fill_color
+                            .fill_color()
                             .unwrap_or(Color::TRANSPARENT);
                         self.overlays()
                             .colorpicker()
@@ -769,7 +883,7 @@ impl RnAppWindow {
                             .pens_config
                             .shaper_config
                             .smooth_options
-                            .fill_color
+                            .fill_color()
                             .unwrap_or(Color::TRANSPARENT);
                         self.overlays()
                             .colorpicker()
@@ -846,6 +960,18 @@ impl RnAppWindow {
                     .sidebar_stack()
                     .set_visible_child_name("tools_page");
             }
+            PenStyle::Laser => {
+                self.overlays().penpicker().laser_toggle().set_active(true);
+                self.overlays()
+                    .penssidebar()
+                    .sidebar_stack()
+                    .set_visible_child_name("laser_page");
+
+                let laser_color = canvas.engine_ref().pens_config.laser_config.color;
+                self.overlays()
+                    .colorpicker()
+                    .set_stroke_color(gdk::RGBA::from_compose_color(laser_color));
+            }
         }
 
         self.overlays()
@@ -872,6 +998,10 @@ impl RnAppWindow {
             .penssidebar()
             .tools_page()
             .refresh_ui(active_tab);
+        self.overlays()
+            .penssidebar()
+            .laser_page()
+            .refresh_ui(active_tab);
         self.sidebar().settings_panel().refresh_ui(active_tab);
         self.refresh_titles(active_tab);
     }
@@ -898,6 +1028,9 @@ impl RnAppWindow {
         widget_flags |= active_canvas
             .engine_mut()
             .set_visual_debug(prev_canvas.engine_mut().visual_debug());
+        widget_flags |= active_canvas
+            .engine_mut()
+            .set_visual_debug_damage(prev_canvas.engine_mut().visual_debug_damage());
 
         self.handle_widget_flags(widget_flags, &active_canvas);
     }
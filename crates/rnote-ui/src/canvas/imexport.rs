@@ -6,18 +6,20 @@ use futures::AsyncWriteExt;
 use gtk4::{gio, prelude::*};
 use rnote_compose::ext::Vector2Ext;
 use rnote_engine::engine::export::{DocExportPrefs, DocPagesExportPrefs, SelectionExportPrefs};
-use rnote_engine::engine::{EngineSnapshot, StrokeContent};
+use rnote_engine::engine::journal::journal_file_path;
+use rnote_engine::engine::{EngineSnapshot, IncrementalSave, PastePlacement, StrokeContent};
 use rnote_engine::strokes::resize::ImageSizeOption;
 use rnote_engine::strokes::Stroke;
 use rnote_engine::WidgetFlags;
 use std::ops::Range;
 use std::path::Path;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 impl RnCanvas {
     /// Load the bytes of a `.rnote` file and imports it into the engine.
     ///
     /// `file_path` is optional but needs to be supplied when the origin file should be tracked.
+    /// The journal of the incremental saves next to it is replayed when it exists.
     ///
     /// The function returns `WidgetFlags` instead of emitting the `handle_signal_flags` signal, because a signal
     /// handler might not yet be connected when this function is called.
@@ -29,7 +31,20 @@ impl RnCanvas {
     where
         P: AsRef<Path>,
     {
-        let engine_snapshot = EngineSnapshot::load_from_rnote_bytes(bytes).await?;
+        let journal_bytes = match file_path.as_ref() {
+            Some(file_path) => gio::File::for_path(journal_file_path(file_path))
+                .load_bytes_future()
+                .await
+                .ok()
+                .map(|(bytes, _)| bytes.to_vec()),
+            None => None,
+        };
+        let engine_snapshot = match journal_bytes {
+            Some(journal_bytes) => {
+                EngineSnapshot::load_from_rnote_bytes_w_journal(bytes, journal_bytes).await?
+            }
+            None => EngineSnapshot::load_from_rnote_bytes(bytes).await?,
+        };
         let mut widget_flags = self.engine_mut().load_snapshot(engine_snapshot);
         widget_flags |= self
             .engine_mut()
@@ -73,6 +88,21 @@ impl RnCanvas {
         Ok(())
     }
 
+    /// Imports the strokes of the rnote file bytes into the current document.
+    ///
+    /// `target_pos` is in coordinate space of the doc. The strokes are centered in the viewport when it is None.
+    pub(crate) fn load_in_rnote_bytes_as_strokes(
+        &self,
+        bytes: Vec<u8>,
+        target_pos: Option<na::Vector2<f64>>,
+    ) -> anyhow::Result<()> {
+        let (_, widget_flags) = self
+            .engine_mut()
+            .import_rnote_file_as_strokes(&bytes, target_pos)?;
+        self.emit_handle_widget_flags(widget_flags);
+        Ok(())
+    }
+
     /// Loads in bytes from a vector image and imports it.
     ///
     /// `target_pos` is in coordinate space of the doc.
@@ -149,6 +179,14 @@ impl RnCanvas {
         Ok(())
     }
 
+    /// Sets the pdf as underlay of the document, replacing the current one.
+    pub(crate) fn load_in_pdf_bytes_as_underlay(&self, bytes: Vec<u8>) -> anyhow::Result<()> {
+        let widget_flags = self.engine_mut().set_pdf_underlay(&bytes)?;
+
+        self.emit_handle_widget_flags(widget_flags);
+        Ok(())
+    }
+
     /// Imports a text.
     ///
     /// `target_pos` is in coordinate space of the doc.
@@ -165,6 +203,22 @@ impl RnCanvas {
         Ok(())
     }
 
+    /// Inserts strokes in the rnote clipboard format, which are usually copied from another document.
+    ///
+    /// Positions in the placement are in coordinate space of the doc.
+    pub(crate) fn insert_clipboard_strokes(
+        &self,
+        bytes: &[u8],
+        placement: PastePlacement,
+    ) -> anyhow::Result<()> {
+        let widget_flags = self
+            .engine_mut()
+            .insert_clipboard_strokes(bytes, placement)?;
+
+        self.emit_handle_widget_flags(widget_flags);
+        Ok(())
+    }
+
     /// Deserializes the stroke content and inserts it into the engine.
     ///
     /// The data is usually coming from the clipboard, drop source, etc.
@@ -217,8 +271,9 @@ impl RnCanvas {
         let basename = file
             .basename()
             .ok_or_else(|| anyhow::anyhow!("Could not retrieve basename for file: `{file:?}`."))?;
+        let state_marker = self.engine_ref().state_marker();
         let rnote_bytes_receiver = self
-            .engine_ref()
+            .engine_mut()
             .save_as_rnote_bytes(basename.to_string_lossy().to_string());
         let mut skip_set_output_file = false;
         if let Some(output_file_path) = self.output_file().and_then(|f| f.path()) {
@@ -235,7 +290,7 @@ impl RnCanvas {
                 .create(true)
                 .truncate(true)
                 .write(true)
-                .open(&file_path)
+                .open(crate::env::long_path(&file_path))
                 .await
                 .context(format!(
                     "Failed to create/open/truncate file for path '{}'",
@@ -264,7 +319,104 @@ impl RnCanvas {
             return Err(e);
         }
 
+        // The records of the journal belong to the previous full save
+        let journal_path = journal_file_path(&file_path);
+        match async_fs::remove_file(crate::env::long_path(&journal_path)).await {
+            Ok(()) => self.engine_mut().journal_full_save_finished(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.engine_mut().journal_full_save_finished()
+            }
+            Err(e) => {
+                // New records would be appended to stale ones and never be replayed
+                warn!(
+                    "Removing the journal file with path '{}' failed, incremental saves are disabled until the next full save, Err: {e:?}",
+                    journal_path.display()
+                );
+                self.engine_mut().journal_invalidate();
+            }
+        }
+
         debug!("Saving file has finished successfully");
+        self.engine_mut().discard_recovery();
+        self.engine_mut().set_saved_state_marker(state_marker);
+        self.set_unsaved_changes(false);
+        self.set_save_in_progress(false);
+
+        Ok(true)
+    }
+
+    /// Saves the changes since the last save to the journal next to the given file.
+    ///
+    /// Falls back to saving the document in full when the journal has no base or when it needs to be compacted.
+    /// The return values are the same as for [RnCanvas::save_document_to_file].
+    #[tracing::instrument(skip_all, fields(path = format!("{:?}", file.path())))]
+    pub(crate) async fn save_document_incremental(&self, file: &gio::File) -> anyhow::Result<bool> {
+        // skip saving when it is already in progress
+        if self.save_in_progress() {
+            debug!("Returning early, saving file is already in progress");
+            return Ok(false);
+        }
+        let file_path = file
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("Could not get a path for file: `{file:?}`."))?;
+        // The journal is based on the last full save, which must be of the same file
+        let same_file = self
+            .output_file()
+            .and_then(|f| f.path())
+            .is_some_and(|output_file_path| {
+                crate::utils::paths_abs_eq(output_file_path, &file_path).unwrap_or(false)
+            });
+        if !same_file {
+            return self.save_document_to_file(file).await;
+        }
+
+        let state_marker = self.engine_ref().state_marker();
+        let mut record_bytes = vec![];
+        match self.engine_mut().save_incremental(&mut record_bytes)? {
+            IncrementalSave::Appended => {}
+            IncrementalSave::FullSaveRequired => {
+                debug!("Saving the document in full, the journal requires a full save");
+                return self.save_document_to_file(file).await;
+            }
+        }
+        self.set_save_in_progress(true);
+        debug!("Saving file incrementally is now in progress");
+
+        let journal_path = journal_file_path(&file_path);
+        let journal_write_operation = async {
+            let mut journal_file = async_fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(crate::env::long_path(&journal_path))
+                .await
+                .context(format!(
+                    "Failed to create/open journal file for path '{}'",
+                    journal_path.display()
+                ))?;
+            journal_file
+                .write_all(&record_bytes)
+                .await
+                .context(format!(
+                    "Failed to append bytes to journal file with path '{}'",
+                    journal_path.display()
+                ))?;
+            journal_file.sync_all().await.context(format!(
+                "Failed to sync journal file after writing with path '{}'",
+                journal_path.display()
+            ))?;
+            Ok(())
+        };
+
+        if let Err(e) = journal_write_operation.await {
+            self.set_save_in_progress(false);
+            // The journal might be partially written, only a full save restores consistency
+            self.engine_mut().journal_invalidate();
+            return Err(e);
+        }
+
+        debug!("Saving file incrementally has finished successfully");
+        self.engine_mut().discard_recovery();
+        self.engine_mut().set_saved_state_marker(state_marker);
         self.set_unsaved_changes(false);
         self.set_save_in_progress(false);
 
@@ -366,7 +518,10 @@ impl RnCanvas {
         Ok(())
     }
 
-    fn determine_stroke_import_pos(
+    /// The position strokes are imported at, in coordinate space of the doc.
+    ///
+    /// Defaults to an offset from the top left corner of the viewport, when `target_pos` is None.
+    pub(crate) fn determine_stroke_import_pos(
         &self,
         target_pos: Option<na::Vector2<f64>>,
     ) -> na::Vector2<f64> {
@@ -1,12 +1,14 @@
 // Imports
 use super::RnCanvas;
 use gtk4::{gdk, glib, graphene, prelude::*, Native};
-use rnote_compose::penevent::{KeyboardKey, ModifierKey, PenEvent, PenState, ShortcutKey};
+use rnote_compose::penevent::{
+    InputSource, KeyboardKey, ModifierKey, PenButton, PenEvent, PenState, ShortcutKey,
+};
 use rnote_compose::penpath::Element;
 use rnote_engine::ext::EventPropagationExt;
 use rnote_engine::ext::GraphenePointExt;
 use rnote_engine::pens::penholder::BacklogPolicy;
-use rnote_engine::pens::PenMode;
+use rnote_engine::pens::{PenMode, PenStyle};
 use rnote_engine::WidgetFlags;
 use std::collections::HashSet;
 use std::time::{Duration, Instant};
@@ -30,6 +32,19 @@ pub(crate) fn handle_pointer_controller_event(
     //std::thread::sleep(std::time::Duration::from_millis(100));
     //super::input::debug_gdk_event(event);
 
+    // Swallow the next click or tap while the eyedropper is active, regardless of touch drawing
+    if canvas.eyedropper_active()
+        && matches!(
+            gdk_event_type,
+            gdk::EventType::ButtonPress | gdk::EventType::TouchBegin
+        )
+    {
+        let pos = retrieve_pointer_elements(canvas, now, event, backlog_policy)
+            .and_then(|elements| elements.last().map(|(element, _)| element.pos));
+        canvas.finish_eyedropper(pos);
+        return (glib::Propagation::Stop, pen_state);
+    }
+
     if reject_pointer_input(event, touch_drawing) {
         return (glib::Propagation::Proceed, pen_state);
     }
@@ -161,10 +176,11 @@ pub(crate) fn handle_pointer_controller_event(
             return (glib::Propagation::Proceed, pen_state);
         };
         let modifier_keys = retrieve_modifier_keys(event.modifier_state());
+        let pen_button = retrieve_pen_button(event.modifier_state(), is_stylus);
         let pen_mode = retrieve_pen_mode(event);
 
         for (element, event_time) in elements {
-            trace!(?element, ?pen_state, ?modifier_keys, ?pen_mode, event_time_delta=?now.duration_since(event_time), msg="handle pen event element");
+            trace!(?element, ?pen_state, ?modifier_keys, ?pen_button, ?pen_mode, event_time_delta=?now.duration_since(event_time), msg="handle pen event element");
 
             // Workaround for https://github.com/flxzt/rnote/issues/785
             // only one event is sent when the pen approaches the screen
@@ -183,10 +199,7 @@ pub(crate) fn handle_pointer_controller_event(
                     canvas.enable_drawing_cursor(false);
 
                     let (ep, wf) = canvas.engine_mut().handle_pen_event(
-                        PenEvent::Up {
-                            element,
-                            modifier_keys: modifier_keys.clone(),
-                        },
+                        PenEvent::up(element, modifier_keys.clone()).with_pen_button(pen_button),
                         pen_mode,
                         event_time,
                     );
@@ -197,10 +210,8 @@ pub(crate) fn handle_pointer_controller_event(
                     canvas.enable_drawing_cursor(false);
 
                     let (ep, wf) = canvas.engine_mut().handle_pen_event(
-                        PenEvent::Proximity {
-                            element,
-                            modifier_keys: modifier_keys.clone(),
-                        },
+                        PenEvent::proximity(element, modifier_keys.clone())
+                            .with_pen_button(pen_button),
                         pen_mode,
                         event_time,
                     );
@@ -212,10 +223,7 @@ pub(crate) fn handle_pointer_controller_event(
                     canvas.enable_drawing_cursor(true);
 
                     let (ep, wf) = canvas.engine_mut().handle_pen_event(
-                        PenEvent::Down {
-                            element,
-                            modifier_keys: modifier_keys.clone(),
-                        },
+                        PenEvent::down(element, modifier_keys.clone()).with_pen_button(pen_button),
                         pen_mode,
                         event_time,
                     );
@@ -238,6 +246,20 @@ pub(crate) fn handle_key_controller_key_pressed(
     trace!("canvas event key pressed - gdk_key: {gdk_key:?}, gdk_modifiers: {gdk_modifiers:?}");
     canvas.grab_focus();
 
+    if canvas.eyedropper_active() && gdk_key == gdk::Key::Escape {
+        canvas.finish_eyedropper(None);
+        return glib::Propagation::Stop;
+    }
+
+    if is_pan_key(canvas, gdk_key, gdk_modifiers) {
+        // Repeated key presses while the key is held down are swallowed
+        if canvas.engine_ref().penholder.pen_override().is_none() {
+            let widget_flags = canvas.engine_mut().push_pan_override();
+            canvas.emit_handle_widget_flags(widget_flags);
+        }
+        return glib::Propagation::Stop;
+    }
+
     let now = Instant::now();
     let keyboard_key = retrieve_keyboard_key(gdk_key);
     let modifier_keys = retrieve_modifier_keys(gdk_modifiers);
@@ -263,11 +285,25 @@ pub(crate) fn handle_key_controller_key_pressed(
 }
 
 pub(crate) fn handle_key_controller_key_released(
-    _canvas: &RnCanvas,
+    canvas: &RnCanvas,
     gdk_key: gdk::Key,
     gdk_modifiers: gdk::ModifierType,
 ) {
     trace!("canvas event key released - gdk_key: {gdk_key:?}, gdk_modifiers: {gdk_modifiers:?}");
+
+    if gdk_key == gdk::Key::space {
+        let widget_flags = canvas.engine_mut().release_pan_override();
+        canvas.emit_handle_widget_flags(widget_flags);
+    }
+}
+
+/// Whether the key temporarily switches to panning the view while it is held down.
+///
+/// The spacebar without modifiers, except when it is needed for typing.
+fn is_pan_key(canvas: &RnCanvas, gdk_key: gdk::Key, gdk_modifiers: gdk::ModifierType) -> bool {
+    gdk_key == gdk::Key::space
+        && retrieve_modifier_keys(gdk_modifiers).is_empty()
+        && canvas.engine_ref().penholder.current_pen_style_w_override() != PenStyle::Typewriter
 }
 
 pub(crate) fn handle_imcontext_text_commit(canvas: &RnCanvas, text: &str) {
@@ -324,6 +360,23 @@ fn event_is_stylus(event: &gdk::Event) -> bool {
     event.device_tool().is_some()
 }
 
+fn retrieve_input_source(event: &gdk::Event) -> InputSource {
+    if event_is_stylus(event) {
+        InputSource::Stylus
+    } else if matches!(
+        event.event_type(),
+        gdk::EventType::TouchBegin
+            | gdk::EventType::TouchUpdate
+            | gdk::EventType::TouchEnd
+            | gdk::EventType::TouchCancel
+    ) || event.device().map(|d| d.source()) == Some(gdk::InputSource::Touchscreen)
+    {
+        InputSource::Touch
+    } else {
+        InputSource::Mouse
+    }
+}
+
 fn retrieve_pointer_elements(
     canvas: &RnCanvas,
     now: Instant,
@@ -336,6 +389,7 @@ fn retrieve_pointer_elements(
     // retrieving the pressure only works when the event has a device tool (== is a stylus),
     // else we get SIGSEGV when trying to access (TODO: report this to gtk-rs)
     let is_stylus = event_is_stylus(event);
+    let input_source = retrieve_input_source(event);
     let event_time = event.time();
 
     let mut elements = Vec::with_capacity(1);
@@ -395,7 +449,10 @@ fn retrieve_pointer_elements(
                 Element::PRESSURE_DEFAULT
             };
 
-            entries.push((Element::new(pos, pressure), entry_time));
+            entries.push((
+                Element::new(pos, pressure).with_source(input_source),
+                entry_time,
+            ));
         }
 
         elements.extend(entries.into_iter().rev());
@@ -411,7 +468,7 @@ fn retrieve_pointer_elements(
         Element::PRESSURE_DEFAULT
     };
 
-    elements.push((Element::new(pos, pressure), now));
+    elements.push((Element::new(pos, pressure).with_source(input_source), now));
 
     Some(elements)
 }
@@ -445,6 +502,22 @@ pub(crate) fn retrieve_modifier_keys(modifier: gdk::ModifierType) -> HashSet<Mod
     keys
 }
 
+/// Retrieve the pen button that is held down during the event.
+///
+/// The stylus barrel buttons are reported through the button masks of the modifier state.
+pub(crate) fn retrieve_pen_button(modifier: gdk::ModifierType, is_stylus: bool) -> PenButton {
+    if !is_stylus {
+        return PenButton::Primary;
+    }
+    if modifier.contains(gdk::ModifierType::BUTTON3_MASK) {
+        PenButton::StylusPrimary
+    } else if modifier.contains(gdk::ModifierType::BUTTON2_MASK) {
+        PenButton::StylusSecondary
+    } else {
+        PenButton::Primary
+    }
+}
+
 fn retrieve_pen_mode(event: &gdk::Event) -> Option<PenMode> {
     let device_tool = event.device_tool()?;
     match device_tool.tool_type() {
@@ -487,6 +560,8 @@ pub(crate) fn retrieve_keyboard_key(gdk_key: gdk::Key) -> KeyboardKey {
             gdk::Key::Control_R => KeyboardKey::CtrlRight,
             gdk::Key::Home => KeyboardKey::Home,
             gdk::Key::End => KeyboardKey::End,
+            gdk::Key::Page_Up => KeyboardKey::PageUp,
+            gdk::Key::Page_Down => KeyboardKey::PageDown,
             _ => KeyboardKey::Unsupported,
         }
     }
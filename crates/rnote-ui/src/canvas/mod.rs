@@ -9,6 +9,7 @@ pub(crate) use canvaslayout::RnCanvasLayout;
 pub(crate) use widgetflagsboxed::WidgetFlagsBoxed;
 
 // Imports
+use crate::colorpicker::RnColorSetter;
 use crate::{config, RnAppWindow};
 use futures::StreamExt;
 use gettextrs::gettext;
@@ -21,11 +22,12 @@ use notify::event::{AccessKind, AccessMode, ModifyKind, RenameMode};
 use notify::EventKind;
 use notify_debouncer_full::notify::{self, Watcher};
 use once_cell::sync::Lazy;
-use p2d::bounding_volume::Aabb;
+use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::ext::AabbExt;
 use rnote_compose::penevent::PenState;
 use rnote_engine::ext::GraphenePointExt;
 use rnote_engine::ext::GrapheneRectExt;
+use rnote_engine::pens::CursorStyle;
 use rnote_engine::Camera;
 use rnote_engine::{Engine, WidgetFlags};
 use std::cell::{Cell, Ref, RefCell, RefMut};
@@ -71,6 +73,8 @@ mod imp {
         pub(crate) key_controller_im_context: IMMulticontext,
         pub(crate) drop_target: DropTarget,
         pub(crate) drawing_cursor_enabled: Cell<bool>,
+        /// The color setter the next click on the canvas samples a color into, while the eyedropper is active.
+        pub(crate) eyedropper_setter: glib::WeakRef<RnColorSetter>,
 
         pub(crate) engine: RefCell<Engine>,
         pub(crate) engine_task_handler_handle: RefCell<Option<glib::JoinHandle<()>>>,
@@ -164,6 +168,7 @@ mod imp {
                 key_controller_im_context,
                 drop_target,
                 drawing_cursor_enabled: Cell::new(false),
+                eyedropper_setter: glib::WeakRef::new(),
 
                 engine: RefCell::new(engine),
                 engine_task_handler_handle: RefCell::new(None),
@@ -362,16 +367,7 @@ mod imp {
                     let show_drawing_cursor: bool =
                         value.get().expect("The value needs to be of type `bool`");
                     self.show_drawing_cursor.replace(show_drawing_cursor);
-
-                    if self.drawing_cursor_enabled.get() {
-                        if show_drawing_cursor {
-                            obj.set_cursor(Some(&*self.drawing_cursor.borrow()));
-                        } else {
-                            obj.set_cursor(Some(&*self.invisible_cursor.borrow()));
-                        }
-                    } else {
-                        obj.set_cursor(Some(&*self.regular_cursor.borrow()));
-                    }
+                    obj.refresh_cursor();
                 }
                 "regular-cursor" => {
                     let icon_name = value.get().unwrap();
@@ -392,8 +388,7 @@ mod imp {
                     );
 
                     self.regular_cursor.replace(cursor);
-
-                    obj.set_cursor(Some(&*self.regular_cursor.borrow()));
+                    obj.refresh_cursor();
                 }
                 "drawing-cursor" => {
                     let icon_name = value.get().unwrap();
@@ -870,8 +865,19 @@ impl RnCanvas {
             return;
         };
         self.imp().drawing_cursor_enabled.set(drawing_cursor);
+        self.refresh_cursor();
+    }
 
-        if drawing_cursor {
+    /// Applies the cursor for the current state of the pen.
+    ///
+    /// Falls back to the regular or the drawing cursor when the pen doesn't request a specific one.
+    pub(crate) fn refresh_cursor(&self) {
+        if self.eyedropper_active() {
+            return;
+        }
+        if let Some(cursor) = cursor_for_style(self.engine_ref().current_cursor()) {
+            self.set_cursor(Some(&cursor));
+        } else if self.imp().drawing_cursor_enabled.get() {
             if self.imp().show_drawing_cursor.get() {
                 self.set_cursor(Some(&*self.imp().drawing_cursor.borrow()));
             } else {
@@ -882,6 +888,36 @@ impl RnCanvas {
         }
     }
 
+    /// Start sampling a color from the canvas into the given color setter.
+    ///
+    /// The next click on the canvas is swallowed and samples the color of the stroke below it.
+    /// Pressing Escape or clicking on empty canvas cancels and leaves the color of the setter unchanged.
+    pub(crate) fn start_eyedropper(&self, setter: &RnColorSetter) {
+        self.imp().eyedropper_setter.set(Some(setter));
+        self.set_cursor_from_name(Some("crosshair"));
+        self.grab_focus();
+    }
+
+    pub(crate) fn eyedropper_active(&self) -> bool {
+        self.imp().eyedropper_setter.upgrade().is_some()
+    }
+
+    /// Finish the eyedropper, sampling the color at the given position in document coordinates.
+    ///
+    /// Cancels when the position is None.
+    pub(crate) fn finish_eyedropper(&self, pos: Option<na::Vector2<f64>>) {
+        let Some(setter) = self.imp().eyedropper_setter.upgrade() else {
+            return;
+        };
+        self.imp().eyedropper_setter.set(None);
+
+        if let Some(color) = pos.and_then(|pos| self.engine_ref().sample_color_at(pos)) {
+            setter.set_sampled_color(color);
+        }
+        self.imp().drawing_cursor_enabled.set(false);
+        self.refresh_cursor();
+    }
+
     /// The document title for display. Can be used to get a string as the basename of the existing / a new save file.
     ///
     /// When there is no output-file, falls back to the "New document" string
@@ -1124,6 +1160,44 @@ impl RnCanvas {
 
     /// Initializes for the given appwindow. Usually `init()` is only called once, but since this widget can be moved between appwindows through tabs,
     /// this function also disconnects and replaces all existing old connections
+    /// Point the recovery snapshots of the engine to the recovery file of the current output file.
+    pub(crate) fn update_recovery_target(&self) {
+        let doc_file = self.output_file().and_then(|f| f.path());
+        self.engine_mut()
+            .set_recovery_target(Some(crate::env::recovery_dir()), doc_file);
+    }
+
+    /// Offer to restore the document from its recovery file, if one exists that is newer than the last save.
+    pub(crate) fn offer_recovery(&self, appwindow: &RnAppWindow) {
+        if !self.engine_ref().recovery_available() {
+            return;
+        }
+        appwindow.overlays().dispatch_toast_w_button(
+            &gettext("Unsaved changes of this document were recovered"),
+            &gettext("Restore"),
+            clone!(
+                #[weak(rename_to=canvas)]
+                self,
+                #[weak]
+                appwindow,
+                move |_| {
+                    let widget_flags = match canvas.engine_mut().load_recovery() {
+                        Ok(widget_flags) => widget_flags,
+                        Err(e) => {
+                            error!("Restoring document from recovery file failed, Err: {e:?}");
+                            appwindow
+                                .overlays()
+                                .dispatch_toast_error(&gettext("Restoring document failed"));
+                            return;
+                        }
+                    };
+                    appwindow.handle_widget_flags(widget_flags, &canvas);
+                }
+            ),
+            None,
+        );
+    }
+
     pub(crate) fn init_reconnect(&self, appwindow: &RnAppWindow) {
         // initialize file watcher, needed when the tab is moved to another window and is re-initializing
         self.reinstall_output_file_watcher(appwindow);
@@ -1140,12 +1214,18 @@ impl RnCanvas {
                         canvas.clear_output_file_watcher();
                         canvas.dismiss_output_file_modified_toast();
                     }
+                    canvas.update_recovery_target();
 
                     appwindow.refresh_titles(&appwindow.active_tab_wrapper());
                 }
             ),
         );
 
+        // recovery snapshots are written for the current output file
+        self.update_recovery_target();
+        self.engine_mut()
+            .set_recovery_interval(appwindow.recovery_interval());
+
         // set scale factor initially
         let _ = self
             .engine_mut()
@@ -1452,4 +1532,34 @@ impl RnCanvas {
             na::point![f64::from(self.width()), f64::from(self.height())],
         )
     }
+
+    /// Queue a redraw for the damaged region, in document coordinates.
+    ///
+    /// Widgets can only be invalidated as a whole, so the redraw is skipped when the region is not visible.
+    /// Limiting the redraw to the changed render nodes is left to the renderer.
+    pub(crate) fn queue_draw_damage(&self, damage: Aabb) {
+        let surface_damage = self.engine_ref().camera.transform_bounds(damage);
+        if surface_damage.intersects(&self.bounds()) {
+            self.queue_draw();
+        }
+    }
+}
+
+/// The named toolkit cursor for the cursor style of the pen, None for the default style.
+///
+/// Not every cursor theme provides all names, so every cursor has a more common fallback.
+fn cursor_for_style(style: CursorStyle) -> Option<gdk::Cursor> {
+    let (name, fallback) = match style {
+        CursorStyle::Default => return None,
+        CursorStyle::Crosshair => ("crosshair", "default"),
+        CursorStyle::Grab => ("grab", "pointer"),
+        CursorStyle::Grabbing => ("grabbing", "move"),
+        CursorStyle::ResizeNwSe => ("nwse-resize", "move"),
+        CursorStyle::ResizeNeSw => ("nesw-resize", "move"),
+        // There is no named rotate cursor
+        CursorStyle::Rotate => ("all-scroll", "move"),
+        CursorStyle::Text => ("text", "default"),
+        CursorStyle::Eraser => ("cell", "crosshair"),
+    };
+    gdk::Cursor::from_name(name, gdk::Cursor::from_name(fallback, None).as_ref())
 }
@@ -3,8 +3,8 @@ use crate::{RnAppWindow, RnCanvas, RnContextMenu};
 use gtk4::{
     gdk, glib, glib::clone, graphene, prelude::*, subclass::prelude::*, CompositeTemplate,
     CornerType, EventControllerMotion, EventControllerScroll, EventControllerScrollFlags,
-    EventSequenceState, GestureDrag, GestureLongPress, GestureZoom, PropagationPhase,
-    ScrolledWindow, Widget,
+    EventSequenceState, GestureDrag, GestureLongPress, GestureRotate, GestureZoom,
+    PropagationPhase, ScrolledWindow, Widget,
 };
 use once_cell::sync::Lazy;
 use rnote_compose::penevent::ShortcutKey;
@@ -39,6 +39,7 @@ mod imp {
         pub(crate) pointer_motion_controller: EventControllerMotion,
         pub(crate) canvas_drag_gesture: GestureDrag,
         pub(crate) canvas_zoom_gesture: GestureZoom,
+        pub(crate) canvas_rotate_gesture: GestureRotate,
         pub(crate) canvas_zoom_scroll_controller: EventControllerScroll,
         pub(crate) canvas_mouse_drag_middle_gesture: GestureDrag,
         pub(crate) canvas_alt_drag_gesture: GestureDrag,
@@ -75,6 +76,12 @@ mod imp {
                 .propagation_phase(PropagationPhase::Capture)
                 .build();
 
+            // Only used together with the zoom gesture, for rotating the selection
+            let canvas_rotate_gesture = GestureRotate::builder()
+                .name("canvas_rotate_gesture")
+                .propagation_phase(PropagationPhase::Capture)
+                .build();
+
             let canvas_zoom_scroll_controller = EventControllerScroll::builder()
                 .name("canvas_zoom_scroll_controller")
                 .propagation_phase(PropagationPhase::Bubble)
@@ -130,6 +137,7 @@ mod imp {
                 pointer_motion_controller,
                 canvas_drag_gesture,
                 canvas_zoom_gesture,
+                canvas_rotate_gesture,
                 canvas_zoom_scroll_controller,
                 canvas_mouse_drag_middle_gesture,
                 canvas_alt_drag_gesture,
@@ -171,6 +179,8 @@ mod imp {
                 .add_controller(self.canvas_drag_gesture.clone());
             self.scroller
                 .add_controller(self.canvas_zoom_gesture.clone());
+            self.scroller
+                .add_controller(self.canvas_rotate_gesture.clone());
             self.scroller
                 .add_controller(self.canvas_zoom_scroll_controller.clone());
             self.scroller
@@ -187,6 +197,8 @@ mod imp {
             // group
             self.touch_two_finger_long_press_gesture
                 .group_with(&self.canvas_zoom_gesture);
+            self.canvas_rotate_gesture
+                .group_with(&self.canvas_zoom_gesture);
 
             self.setup_input();
 
@@ -288,9 +300,13 @@ mod imp {
             if !self.block_pinch_zoom.get() && !self.canvas.touch_drawing() {
                 self.canvas_zoom_gesture
                     .set_propagation_phase(PropagationPhase::Capture);
+                self.canvas_rotate_gesture
+                    .set_propagation_phase(PropagationPhase::Capture);
             } else {
                 self.canvas_zoom_gesture
                     .set_propagation_phase(PropagationPhase::None);
+                self.canvas_rotate_gesture
+                    .set_propagation_phase(PropagationPhase::None);
             }
         }
 
@@ -397,6 +413,10 @@ mod imp {
                             canvaswrapper.canvas().hadjustment().unwrap().value(),
                             canvaswrapper.canvas().vadjustment().unwrap().value()
                         ]);
+                        let widget_flags = canvaswrapper.canvas().engine_mut().camera_drag_begin();
+                        canvaswrapper
+                            .canvas()
+                            .emit_handle_widget_flags(widget_flags);
                     }
                 ));
                 self.canvas_drag_gesture.connect_drag_update(clone!(
@@ -415,10 +435,7 @@ mod imp {
                     #[weak(rename_to=canvaswrapper)]
                     obj,
                     move |_, _, _| {
-                        let widget_flags = canvaswrapper
-                            .canvas()
-                            .engine_mut()
-                            .update_rendering_current_viewport();
+                        let widget_flags = canvaswrapper.canvas().engine_mut().camera_drag_end();
                         canvaswrapper
                             .canvas()
                             .emit_handle_widget_flags(widget_flags);
@@ -439,6 +456,11 @@ mod imp {
                         move |_, _, _| {
                             mouse_drag_start
                                 .set(canvaswrapper.canvas().engine_ref().camera.offset());
+                            let widget_flags =
+                                canvaswrapper.canvas().engine_mut().camera_drag_begin();
+                            canvaswrapper
+                                .canvas()
+                                .emit_handle_widget_flags(widget_flags);
                         }
                     ));
                 self.canvas_mouse_drag_middle_gesture
@@ -460,10 +482,8 @@ mod imp {
                         #[weak(rename_to=canvaswrapper)]
                         obj,
                         move |_, _, _| {
-                            let widget_flags = canvaswrapper
-                                .canvas()
-                                .engine_mut()
-                                .update_rendering_current_viewport();
+                            let widget_flags =
+                                canvaswrapper.canvas().engine_mut().camera_drag_end();
                             canvaswrapper
                                 .canvas()
                                 .emit_handle_widget_flags(widget_flags);
@@ -478,8 +498,41 @@ mod imp {
                 let new_zoom = Rc::new(Cell::new(1.0));
                 let bbcenter_begin: Rc<Cell<Option<na::Vector2<f64>>>> = Rc::new(Cell::new(None));
                 let offset_begin = Rc::new(Cell::new(na::vector![0.0, 0.0]));
+                // Whether the gesture started inside the selection and is scaling / rotating it
+                let selection_pinch = Rc::new(Cell::new(false));
+                let rotation_angle = Rc::new(Cell::new(0.0));
+
+                self.canvas_rotate_gesture.connect_angle_changed(clone!(
+                    #[strong]
+                    selection_pinch,
+                    #[strong]
+                    rotation_angle,
+                    #[weak(rename_to=canvaswrapper)]
+                    obj,
+                    move |gesture, _angle, angle_delta| {
+                        rotation_angle.set(angle_delta);
+
+                        if !selection_pinch.get() {
+                            return;
+                        }
+                        let Some(center) = gesture_center_on_doc(&canvaswrapper, gesture) else {
+                            return;
+                        };
+                        let canvas = canvaswrapper.canvas();
+                        let scale = canvaswrapper.imp().canvas_zoom_gesture.scale_delta();
+                        let widget_flags =
+                            canvas
+                                .engine_mut()
+                                .selection_pinch_update(scale, angle_delta, center);
+                        canvas.emit_handle_widget_flags(widget_flags);
+                    }
+                ));
 
                 self.canvas_zoom_gesture.connect_begin(clone!(
+                    #[strong]
+                    selection_pinch,
+                    #[strong]
+                    rotation_angle,
                     #[strong]
                     zoom_begin,
                     #[strong]
@@ -494,6 +547,19 @@ mod imp {
                     obj,
                     move |gesture, _| {
                         gesture.set_state(EventSequenceState::Claimed);
+                        rotation_angle.set(0.0);
+
+                        // Gestures starting inside the selection bounds scale and rotate the selection
+                        if let Some(center) = gesture_center_on_doc(&canvaswrapper, gesture) {
+                            let canvas = canvaswrapper.canvas();
+                            let (started, widget_flags) =
+                                canvas.engine_mut().selection_pinch_begin(center);
+                            canvas.emit_handle_widget_flags(widget_flags);
+                            selection_pinch.set(started);
+                            if started {
+                                return;
+                            }
+                        }
                         let current_zoom = canvaswrapper.canvas().engine_ref().camera.total_zoom();
 
                         zoom_begin.set(current_zoom);
@@ -510,6 +576,10 @@ mod imp {
                 ));
 
                 self.canvas_zoom_gesture.connect_scale_changed(clone!(
+                    #[strong]
+                    selection_pinch,
+                    #[strong]
+                    rotation_angle,
                     #[strong]
                     zoom_begin,
                     #[strong]
@@ -525,6 +595,18 @@ mod imp {
                     move |gesture, scale| {
                         let canvas = canvaswrapper.canvas();
 
+                        if selection_pinch.get() {
+                            if let Some(center) = gesture_center_on_doc(&canvaswrapper, gesture) {
+                                let widget_flags = canvas.engine_mut().selection_pinch_update(
+                                    scale,
+                                    rotation_angle.get(),
+                                    center,
+                                );
+                                canvas.emit_handle_widget_flags(widget_flags);
+                            }
+                            return;
+                        }
+
                         if (Camera::ZOOM_MIN..=Camera::ZOOM_MAX)
                             .contains(&(zoom_begin.get() * scale))
                         {
@@ -558,14 +640,20 @@ mod imp {
                 ));
 
                 self.canvas_zoom_gesture.connect_end(clone!(
+                    #[strong]
+                    selection_pinch,
                     #[weak(rename_to=canvaswrapper)]
                     obj,
                     move |gesture, _event_sequence| {
                         gesture.set_state(EventSequenceState::Denied);
-                        let widget_flags = canvaswrapper
-                            .canvas()
-                            .engine_mut()
-                            .update_rendering_current_viewport();
+                        let widget_flags = if selection_pinch.replace(false) {
+                            canvaswrapper.canvas().engine_mut().selection_pinch_end()
+                        } else {
+                            canvaswrapper
+                                .canvas()
+                                .engine_mut()
+                                .update_rendering_current_viewport()
+                        };
                         canvaswrapper
                             .canvas()
                             .emit_handle_widget_flags(widget_flags);
@@ -573,14 +661,20 @@ mod imp {
                 ));
 
                 self.canvas_zoom_gesture.connect_cancel(clone!(
+                    #[strong]
+                    selection_pinch,
                     #[weak(rename_to=canvaswrapper)]
                     obj,
                     move |gesture, _event_sequence| {
                         gesture.set_state(EventSequenceState::Denied);
-                        let widget_flags = canvaswrapper
-                            .canvas()
-                            .engine_mut()
-                            .update_rendering_current_viewport();
+                        let widget_flags = if selection_pinch.replace(false) {
+                            canvaswrapper.canvas().engine_mut().selection_pinch_end()
+                        } else {
+                            canvaswrapper
+                                .canvas()
+                                .engine_mut()
+                                .update_rendering_current_viewport()
+                        };
                         canvaswrapper
                             .canvas()
                             .emit_handle_widget_flags(widget_flags);
@@ -588,7 +682,8 @@ mod imp {
                 ));
             }
 
-            // Pan with alt + drag
+            // Pan with alt + drag.
+            // This applies to all pens, they don't use alt for pointer gestures.
             {
                 let offset_start = Rc::new(Cell::new(na::Vector2::<f64>::zeros()));
 
@@ -808,6 +903,18 @@ impl RnCanvasWrapper {
         self.imp().last_contextmenu_pos.get()
     }
 
+    /// The position of the pointer when it is above the canvas, in coordinate space of the doc.
+    pub(crate) fn pointer_doc_pos(&self) -> Option<na::Vector2<f64>> {
+        let canvas = self.canvas();
+        let pos = self
+            .compute_point(
+                &canvas,
+                &graphene::Point::from_na_vec(self.imp().pointer_pos.get()?),
+            )?
+            .to_na_vec();
+        Some((canvas.engine_ref().camera.transform().inverse() * na::Point2::from(pos)).coords)
+    }
+
     pub(crate) fn scroller(&self) -> ScrolledWindow {
         self.imp().scroller.get()
     }
@@ -919,3 +1026,13 @@ impl RnCanvasWrapper {
         self.canvas().connect_to_tab_page(page);
     }
 }
+
+/// The center of the gesture's touch points in document coordinates.
+fn gesture_center_on_doc(
+    canvaswrapper: &RnCanvasWrapper,
+    gesture: &impl IsA<gtk4::Gesture>,
+) -> Option<na::Vector2<f64>> {
+    let (x, y) = gesture.bounding_box_center()?;
+    let transform = canvaswrapper.canvas().engine_ref().camera.transform();
+    Some((transform.inverse() * na::point![x, y]).coords)
+}
@@ -1,20 +1,56 @@
 // Imports
 use gtk4::{
-    gdk, glib, prelude::*, subclass::prelude::*, Align, Button, CssProvider, PositionType,
-    ToggleButton, Widget,
+    gdk, glib, glib::clone, graphene, gsk, prelude::*, subclass::prelude::*, Align, Button,
+    DropTarget, EventSequenceState, GestureClick, GestureLongPress, PositionType, ToggleButton,
+    Widget,
 };
 use once_cell::sync::Lazy;
 use rnote_compose::{color, Color};
 use rnote_engine::ext::GdkRGBAExt;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use tracing::{debug, error};
 
 mod imp {
     use super::*;
 
+    /// The transition from the previously displayed color and active indicator to the current ones.
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Transition {
+        pub(crate) from_color: Color,
+        /// How far the active indicator was slid in, from 0.0 to 1.0.
+        pub(crate) from_indicator: f64,
+        /// The frame time when the transition started, in microseconds. None until the first frame is drawn.
+        pub(crate) start_time: Option<i64>,
+        /// The linear progress of the transition, from 0.0 to 1.0.
+        pub(crate) progress: f64,
+    }
+
+    impl Default for Transition {
+        fn default() -> Self {
+            Self {
+                from_color: super::RnColorSetter::COLOR_DEFAULT,
+                from_indicator: 0.0,
+                start_time: None,
+                progress: 1.0,
+            }
+        }
+    }
+
     #[derive(Debug)]
     pub(crate) struct RnColorSetter {
         pub(crate) color: Cell<gdk::RGBA>,
+        pub(crate) has_color: Cell<bool>,
         pub(crate) position: Cell<PositionType>,
+        /// Whether color changes and active state transitions are animated.
+        ///
+        /// They are never animated when animations are disabled in the GTK settings.
+        pub(crate) enable_animations: Cell<bool>,
+        /// The active state that is displayed once the transition has finished.
+        pub(crate) displayed_active: Cell<bool>,
+        pub(crate) transition: Cell<Transition>,
+        pub(crate) tick_callback: RefCell<Option<gtk4::TickCallbackId>>,
+        /// The position of the last primary button press, in widget coordinates.
+        pub(crate) press_pos: Cell<Option<(f64, f64)>>,
     }
 
     #[glib::object_subclass]
@@ -30,7 +66,13 @@ mod imp {
                 color: Cell::new(gdk::RGBA::from_compose_color(
                     super::RnColorSetter::COLOR_DEFAULT,
                 )),
+                has_color: Cell::new(true),
                 position: Cell::new(PositionType::Right),
+                enable_animations: Cell::new(true),
+                displayed_active: Cell::new(false),
+                transition: Cell::new(Transition::default()),
+                tick_callback: RefCell::new(None),
+                press_pos: Cell::new(None),
             }
         }
     }
@@ -48,18 +90,109 @@ mod imp {
             obj.set_height_request(34);
             obj.set_css_classes(&["colorsetter"]);
 
-            self.update_appearance(super::RnColorSetter::COLOR_DEFAULT);
+            let long_press = GestureLongPress::builder()
+                .name("colorsetter_long_press")
+                .build();
+            obj.add_controller(long_press.clone());
+            long_press.connect_pressed(clone!(
+                #[weak(rename_to=colorsetter)]
+                obj,
+                move |gesture, x, y| {
+                    // Claiming the sequence prevents the setter from being toggled on release
+                    gesture.set_state(EventSequenceState::Claimed);
+                    debug!("Colorsetter long-pressed at ({x}, {y})");
+                    colorsetter
+                        .emit_by_name::<()>("start-eyedropper", &[&colorsetter.color(), &x, &y]);
+                }
+            ));
+
+            // Only records where the button was pressed, the click itself is handled by the button
+            let primary_press = GestureClick::builder()
+                .name("colorsetter_primary_press")
+                .button(gdk::BUTTON_PRIMARY)
+                .propagation_phase(gtk4::PropagationPhase::Capture)
+                .build();
+            obj.add_controller(primary_press.clone());
+            primary_press.connect_pressed(clone!(
+                #[weak(rename_to=colorsetter)]
+                obj,
+                move |_, _, x, y| {
+                    colorsetter.imp().press_pos.set(Some((x, y)));
+                }
+            ));
+
+            let middle_click = GestureClick::builder()
+                .name("colorsetter_middle_click")
+                .button(gdk::BUTTON_MIDDLE)
+                .build();
+            obj.add_controller(middle_click.clone());
+            middle_click.connect_pressed(clone!(
+                #[weak(rename_to=colorsetter)]
+                obj,
+                move |gesture, _, _, _| {
+                    gesture.set_state(EventSequenceState::Claimed);
+                    colorsetter.clear_color();
+                }
+            ));
+
+            let drop_target = DropTarget::builder()
+                .name("colorsetter_drop_target")
+                .actions(gdk::DragAction::COPY)
+                .build();
+            drop_target.set_types(&[gdk::RGBA::static_type()]);
+            obj.add_controller(drop_target.clone());
+            drop_target.connect_drop(clone!(
+                #[weak(rename_to=colorsetter)]
+                obj,
+                #[upgrade_or]
+                false,
+                move |_, value, _, _| {
+                    let Ok(color) = value.get::<gdk::RGBA>() else {
+                        return false;
+                    };
+                    colorsetter.set_color(color);
+                    true
+                }
+            ));
+        }
+
+        fn signals() -> &'static [glib::subclass::Signal] {
+            static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+                vec![
+                    glib::subclass::Signal::builder("start-eyedropper")
+                        .param_types([
+                            gdk::RGBA::static_type(),
+                            f64::static_type(),
+                            f64::static_type(),
+                        ])
+                        .build(),
+                    glib::subclass::Signal::builder("request-set-color")
+                        .param_types([
+                            gdk::RGBA::static_type(),
+                            f64::static_type(),
+                            f64::static_type(),
+                        ])
+                        .build(),
+                ]
+            });
+            SIGNALS.as_ref()
         }
 
         fn properties() -> &'static [glib::ParamSpec] {
             static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
                 vec![
                     glib::ParamSpecBoxed::builder::<gdk::RGBA>("color").build(),
+                    glib::ParamSpecBoolean::builder("has-color")
+                        .default_value(true)
+                        .build(),
                     glib::ParamSpecEnum::builder_with_default::<PositionType>(
                         "position",
                         PositionType::Right,
                     )
                     .build(),
+                    glib::ParamSpecBoolean::builder("enable-animations")
+                        .default_value(true)
+                        .build(),
                 ]
             });
             PROPERTIES.as_ref()
@@ -71,9 +204,24 @@ mod imp {
                     let color = value
                         .get::<gdk::RGBA>()
                         .expect("value not of type `gdk::RGBA`");
+                    let was_empty = !self.has_color.get();
+                    // The transition starts from what is currently displayed
+                    self.start_transition();
                     self.color.set(color);
 
-                    self.update_appearance(color.into_compose_color());
+                    // Setting a color always fills an empty slot
+                    if was_empty {
+                        self.has_color.set(true);
+                        self.finish_transition();
+                        self.obj().notify("has-color");
+                    }
+                    self.obj().queue_draw();
+                }
+                "has-color" => {
+                    let has_color = value.get::<bool>().expect("value not of type `bool`");
+                    self.has_color.set(has_color);
+
+                    self.obj().queue_draw();
                 }
                 "position" => {
                     let position = value
@@ -82,6 +230,14 @@ mod imp {
 
                     self.position.replace(position);
                 }
+                "enable-animations" => {
+                    let enable_animations = value.get::<bool>().expect("value not of type `bool`");
+                    self.enable_animations.set(enable_animations);
+
+                    if !enable_animations {
+                        self.finish_transition();
+                    }
+                }
                 _ => panic!("invalid property name"),
             }
         }
@@ -89,46 +245,204 @@ mod imp {
         fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
             match pspec.name() {
                 "color" => self.color.get().to_value(),
+                "has-color" => self.has_color.get().to_value(),
                 "position" => self.position.get().to_value(),
+                "enable-animations" => self.enable_animations.get().to_value(),
                 _ => panic!("invalid property name"),
             }
         }
     }
 
-    impl WidgetImpl for RnColorSetter {}
+    impl WidgetImpl for RnColorSetter {
+        fn snapshot(&self, snapshot: &gtk4::Snapshot) {
+            self.parent_snapshot(snapshot);
 
-    impl ButtonImpl for RnColorSetter {}
+            if self.has_color.get() {
+                self.draw_color(snapshot);
+            } else {
+                self.draw_empty_slash(snapshot);
+            }
+        }
 
-    impl ToggleButtonImpl for RnColorSetter {}
+        fn unmap(&self) {
+            self.finish_transition();
+            self.parent_unmap();
+        }
+    }
+
+    impl ButtonImpl for RnColorSetter {
+        fn clicked(&self) {
+            let obj = self.obj();
+            // Activated by keyboard when there was no press, then the center is reported
+            let (x, y) = self
+                .press_pos
+                .take()
+                .unwrap_or((obj.width() as f64 * 0.5, obj.height() as f64 * 0.5));
+            if self.has_color.get() {
+                self.parent_clicked();
+            } else {
+                // An empty slot has nothing to apply, so it is not toggled and asks for a color instead
+                debug!("Empty colorsetter clicked at ({x}, {y})");
+                obj.emit_by_name::<()>("request-set-color", &[&self.color.get(), &x, &y]);
+            }
+        }
+    }
+
+    impl ToggleButtonImpl for RnColorSetter {
+        fn toggled(&self) {
+            // The active state is already updated when toggled is emitted
+            self.start_transition();
+            self.displayed_active.set(self.obj().is_active());
+            self.parent_toggled();
+            self.obj().queue_draw();
+        }
+    }
 
     impl RnColorSetter {
-        fn update_appearance(&self, color: Color) {
-            let css = CssProvider::new();
+        /// The duration of the transitions, in microseconds.
+        const TRANSITION_DURATION: i64 = 150_000;
+        /// The height of the active indicator bar.
+        const INDICATOR_HEIGHT: f32 = 3.0;
+        /// The border radius, matching the one of the stylesheet.
+        const BORDER_RADIUS: f32 = 2.0;
 
-            let colorsetter_color = color.to_css_color_attr();
-            let colorsetter_fg_color = if color.a == 0.0 {
-                String::from("@window_fg_color")
-            } else if color.luma() < color::FG_LUMINANCE_THRESHOLD {
-                String::from("@light_1")
+        fn animations_enabled(&self) -> bool {
+            let obj = self.obj();
+            self.enable_animations.get()
+                && obj.is_mapped()
+                && obj.settings().is_gtk_enable_animations()
+        }
+
+        /// The eased progress of the transition.
+        fn eased_progress(&self) -> f64 {
+            // ease-out cubic
+            1.0 - (1.0 - self.transition.get().progress).powi(3)
+        }
+
+        /// The currently displayed, possibly interpolated color.
+        fn displayed_color(&self) -> Color {
+            let from = self.transition.get().from_color;
+            let to = self.color.get().into_compose_color();
+            let t = self.eased_progress();
+            Color::new(
+                from.r + (to.r - from.r) * t,
+                from.g + (to.g - from.g) * t,
+                from.b + (to.b - from.b) * t,
+                from.a + (to.a - from.a) * t,
+            )
+        }
+
+        /// How far the active indicator is currently slid in, from 0.0 to 1.0.
+        fn displayed_indicator(&self) -> f64 {
+            let from = self.transition.get().from_indicator;
+            let to = if self.displayed_active.get() {
+                1.0
             } else {
-                String::from("@dark_5")
+                0.0
             };
+            from + (to - from) * self.eased_progress()
+        }
 
-            let custom_css = format!(
-                "@define-color colorsetter_color {colorsetter_color}; @define-color colorsetter_fg_color {colorsetter_fg_color};",
-            );
-            css.load_from_string(&custom_css);
+        /// Start a transition from what is currently displayed.
+        ///
+        /// Must be called before the color or the active state is updated. Successive calls restart the transition
+        /// cleanly from the interpolated state, so rapidly changing colors don't jump.
+        fn start_transition(&self) {
+            if !self.animations_enabled() {
+                self.finish_transition();
+                return;
+            }
+            self.transition.set(Transition {
+                from_color: self.displayed_color(),
+                from_indicator: self.displayed_indicator(),
+                start_time: None,
+                progress: 0.0,
+            });
+            if self.tick_callback.borrow().is_some() {
+                return;
+            }
 
-            // adding custom css is deprecated.
-            // TODO: We should refactor to drawing through snapshot().
-            // Doing this will also get rid of the css checkerboard glitches that appear on some devices and scaling levels.
-            #[allow(deprecated)]
-            self.obj()
-                .style_context()
-                .add_provider(&css, gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            let tick_callback = self.obj().add_tick_callback(|colorsetter, frame_clock| {
+                let imp = colorsetter.imp();
+                let mut transition = imp.transition.get();
+                let frame_time = frame_clock.frame_time();
+                let start_time = *transition.start_time.get_or_insert(frame_time);
+                transition.progress = ((frame_time - start_time) as f64
+                    / Self::TRANSITION_DURATION as f64)
+                    .clamp(0.0, 1.0);
+                imp.transition.set(transition);
+                colorsetter.queue_draw();
 
+                if transition.progress >= 1.0 {
+                    // Returning break removes the callback
+                    imp.tick_callback.take();
+                    glib::ControlFlow::Break
+                } else {
+                    glib::ControlFlow::Continue
+                }
+            });
+            self.tick_callback.replace(Some(tick_callback));
+        }
+
+        /// Jump to the end of the transition, displaying the current color and active state.
+        fn finish_transition(&self) {
+            if let Some(tick_callback) = self.tick_callback.take() {
+                tick_callback.remove();
+            }
+            self.transition.set(Transition::default());
             self.obj().queue_draw();
         }
+
+        /// Draw the color over the checkerboard, together with the active indicator bar.
+        fn draw_color(&self, snapshot: &gtk4::Snapshot) {
+            let obj = self.obj();
+            let bounds = graphene::Rect::new(0.0, 0.0, obj.width() as f32, obj.height() as f32);
+            let color = self.displayed_color();
+            let indicator_color = if color.a == 0.0 {
+                WidgetExt::color(&*obj)
+            } else if color.luma() < color::FG_LUMINANCE_THRESHOLD {
+                gdk::RGBA::from_piet_color(color::GNOME_BRIGHTS[0])
+            } else {
+                gdk::RGBA::from_piet_color(color::GNOME_DARKS[4])
+            };
+
+            snapshot.push_rounded_clip(&gsk::RoundedRect::from_rect(bounds, Self::BORDER_RADIUS));
+            snapshot.append_color(&gdk::RGBA::from_compose_color(color), &bounds);
+            // The indicator slides in from the bottom edge
+            let indicator_height = Self::INDICATOR_HEIGHT * self.displayed_indicator() as f32;
+            if indicator_height > 0.0 {
+                snapshot.append_color(
+                    &indicator_color,
+                    &graphene::Rect::new(
+                        0.0,
+                        bounds.height() - indicator_height,
+                        bounds.width(),
+                        indicator_height,
+                    ),
+                );
+            }
+            snapshot.pop();
+        }
+
+        /// Draw a diagonal slash over the checkerboard, marking the slot as empty.
+        fn draw_empty_slash(&self, snapshot: &gtk4::Snapshot) {
+            const INSET: f64 = 6.0;
+            const LINE_WIDTH: f64 = 2.0;
+            let obj = self.obj();
+            let size = (obj.width() as f64, obj.height() as f64);
+            let (r, g, b, a) = color::GNOME_REDS[3].as_rgba();
+
+            let cairo_cx =
+                snapshot.append_cairo(&graphene::Rect::new(0.0, 0.0, size.0 as f32, size.1 as f32));
+            cairo_cx.set_source_rgba(r, g, b, a);
+            cairo_cx.set_line_width(LINE_WIDTH);
+            cairo_cx.set_line_cap(gtk4::cairo::LineCap::Round);
+            cairo_cx.move_to(size.0 - INSET, INSET);
+            cairo_cx.line_to(INSET, size.1 - INSET);
+            if let Err(e) = cairo_cx.stroke() {
+                error!("Failed to draw empty colorsetter slash, stroke returned Err: {e:?}");
+            }
+        }
     }
 }
 
@@ -170,4 +484,86 @@ impl RnColorSetter {
     pub(crate) fn set_color(&self, color: gdk::RGBA) {
         self.set_property("color", color.to_value());
     }
+
+    #[allow(unused)]
+    pub(crate) fn has_color(&self) -> bool {
+        self.property::<bool>("has-color")
+    }
+
+    /// Clear the slot, it then holds no color.
+    ///
+    /// An empty slot is distinct from any color, including black or transparent.
+    pub(crate) fn clear_color(&self) {
+        self.set_property("has-color", false.to_value());
+    }
+
+    /// The held color, or None if the slot is empty.
+    pub(crate) fn slot_color(&self) -> Option<Color> {
+        self.has_color().then(|| self.color().into_compose_color())
+    }
+
+    /// Fill the slot with the given color, or clear it when None.
+    pub(crate) fn set_slot_color(&self, color: Option<Color>) {
+        match color {
+            Some(color) => self.set_color(gdk::RGBA::from_compose_color(color)),
+            None => self.clear_color(),
+        }
+    }
+
+    /// Set the color that was sampled from the canvas with the eyedropper.
+    pub(crate) fn set_sampled_color(&self, color: Color) {
+        self.set_color(gdk::RGBA::from_compose_color(color));
+    }
+
+    /// Connect to the signal that is emitted when the setter is long-pressed,
+    /// requesting to sample its color from the canvas.
+    ///
+    /// The handler receives the current color and the press position in widget coordinates.
+    pub(crate) fn connect_start_eyedropper<F: Fn(&Self, gdk::RGBA, f64, f64) + 'static>(
+        &self,
+        f: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_local("start-eyedropper", false, move |args| {
+            let colorsetter = args[0]
+                .get::<Self>()
+                .expect("first argument must be the color setter");
+            let color = args[1]
+                .get::<gdk::RGBA>()
+                .expect("second argument must be the color");
+            let x = args[2]
+                .get::<f64>()
+                .expect("third argument must be the x coordinate");
+            let y = args[3]
+                .get::<f64>()
+                .expect("fourth argument must be the y coordinate");
+            f(&colorsetter, color, x, y);
+            None
+        })
+    }
+
+    /// Connect to the signal that is emitted when an empty slot is clicked,
+    /// requesting a color to be chosen for it.
+    ///
+    /// The handler receives the current color and the press position in widget coordinates.
+    pub(crate) fn connect_request_set_color<F: Fn(&Self, gdk::RGBA, f64, f64) + 'static>(
+        &self,
+        f: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_local("request-set-color", false, move |args| {
+            let colorsetter = args[0]
+                .get::<Self>()
+                .expect("first argument must be the color setter");
+            let color = args[1]
+                .get::<gdk::RGBA>()
+                .expect("second argument must be the color");
+            let x = args[2]
+                .get::<f64>()
+                .expect("third argument must be the x coordinate");
+            let y = args[3]
+                .get::<f64>()
+                .expect("fourth argument must be the y coordinate");
+            f(&colorsetter, color, x, y);
+            None
+        })
+    }
 }
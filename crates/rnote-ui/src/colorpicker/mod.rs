@@ -8,15 +8,18 @@ pub(crate) use colorsetter::RnColorSetter;
 
 // Imports
 use crate::RnAppWindow;
+use gettextrs::gettext;
 use gtk4::{
-    gdk, glib, glib::clone, prelude::*, subclass::prelude::*, Button, ColorDialog,
-    CompositeTemplate, Label, Widget,
+    gdk, gio, glib, glib::clone, prelude::*, subclass::prelude::*, Button, ColorDialog,
+    CompositeTemplate, Label, MenuButton, Widget,
 };
 use once_cell::sync::Lazy;
 use rnote_compose::{color, Color};
 use rnote_engine::ext::GdkRGBAExt;
-use std::cell::RefCell;
-use tracing::debug;
+use rnote_engine::store::SelectionStyleSummary;
+use rnote_engine::{ColorPalette, ColorPalettes};
+use std::cell::{Cell, RefCell};
+use tracing::{debug, error};
 
 mod imp {
     use super::*;
@@ -27,6 +30,12 @@ mod imp {
         pub(crate) stroke_color: RefCell<gdk::RGBA>,
         pub(crate) fill_color: RefCell<gdk::RGBA>,
         pub(crate) color_dialog: glib::WeakRef<ColorDialog>,
+        pub(crate) palettes: RefCell<ColorPalettes>,
+        pub(crate) action_group: gio::SimpleActionGroup,
+        /// Set while a setter is activated only to reflect the selection, so that its color is not applied.
+        pub(crate) reflecting_selection: Cell<bool>,
+        /// The setter colors from before the shades were generated, restored when reverting them.
+        pub(crate) shades_backup: RefCell<Option<Vec<Option<Color>>>>,
 
         #[template_child]
         pub(crate) active_colors_box: TemplateChild<gtk4::Box>,
@@ -55,6 +64,10 @@ mod imp {
         #[template_child]
         pub(crate) setter_9: TemplateChild<RnColorSetter>,
         #[template_child]
+        pub(crate) shades_button: TemplateChild<Button>,
+        #[template_child]
+        pub(crate) palettes_menubutton: TemplateChild<MenuButton>,
+        #[template_child]
         pub(crate) colordialog_button: TemplateChild<Button>,
         #[template_child]
         pub(crate) active_color_label: TemplateChild<Label>,
@@ -68,6 +81,10 @@ mod imp {
                 )),
                 fill_color: RefCell::new(gdk::RGBA::from_compose_color(*super::FILL_COLOR_DEFAULT)),
                 color_dialog: glib::WeakRef::new(),
+                palettes: RefCell::new(ColorPalettes::default()),
+                action_group: gio::SimpleActionGroup::new(),
+                reflecting_selection: Cell::new(false),
+                shades_backup: RefCell::new(None),
 
                 active_colors_box: TemplateChild::default(),
                 stroke_color_pad: TemplateChild::default(),
@@ -82,6 +99,8 @@ mod imp {
                 setter_7: TemplateChild::default(),
                 setter_8: TemplateChild::default(),
                 setter_9: TemplateChild::default(),
+                shades_button: TemplateChild::default(),
+                palettes_menubutton: TemplateChild::default(),
                 colordialog_button: TemplateChild::default(),
                 active_color_label: TemplateChild::default(),
             }
@@ -109,6 +128,15 @@ mod imp {
             let obj = self.obj();
 
             self.setup_setters();
+            self.setup_palette_actions();
+
+            self.shades_button.connect_clicked(clone!(
+                #[weak(rename_to=colorpicker)]
+                obj,
+                move |_| {
+                    colorpicker.toggle_generated_shades();
+                }
+            ));
 
             self.stroke_color_pad
                 .bind_property("color", &*obj, "stroke-color")
@@ -194,6 +222,52 @@ mod imp {
     impl WidgetImpl for RnColorPicker {}
 
     impl RnColorPicker {
+        fn setup_palette_actions(&self) {
+            let obj = self.obj();
+            obj.insert_action_group("colorpicker", Some(&self.action_group));
+
+            let action_switch_palette = gio::SimpleAction::new_stateful(
+                "switch-palette",
+                Some(&u32::static_variant_type()),
+                &0u32.to_variant(),
+            );
+            self.action_group.add_action(&action_switch_palette);
+            let action_add_palette = gio::SimpleAction::new("add-palette", None);
+            self.action_group.add_action(&action_add_palette);
+            let action_remove_palette = gio::SimpleAction::new("remove-palette", None);
+            self.action_group.add_action(&action_remove_palette);
+
+            action_switch_palette.connect_activate(clone!(
+                #[weak(rename_to=colorpicker)]
+                obj,
+                move |_, target| {
+                    let Some(index) = target.and_then(|target| target.get::<u32>()) else {
+                        error!("switch-palette action invoked without a valid target.");
+                        return;
+                    };
+                    colorpicker.switch_palette(index as usize);
+                }
+            ));
+            action_add_palette.connect_activate(clone!(
+                #[weak(rename_to=colorpicker)]
+                obj,
+                move |_, _| {
+                    let n_palettes = colorpicker.imp().palettes.borrow().palettes().len();
+                    colorpicker.add_palette(gettext("Palette") + &format!(" {}", n_palettes + 1));
+                }
+            ));
+            action_remove_palette.connect_activate(clone!(
+                #[weak(rename_to=colorpicker)]
+                obj,
+                move |_, _| {
+                    let active_index = colorpicker.imp().palettes.borrow().active_index();
+                    colorpicker.remove_palette(active_index);
+                }
+            ));
+
+            obj.update_palettes_menu();
+        }
+
         fn setup_setters(&self) {
             let obj = self.obj();
 
@@ -207,6 +281,27 @@ mod imp {
             self.setter_8.set_color(Self::default_color(7));
             self.setter_9.set_color(Self::default_color(8));
 
+            for (i, setter) in obj.setters().into_iter().enumerate() {
+                for property in ["color", "has-color"] {
+                    setter.connect_notify_local(
+                        Some(property),
+                        clone!(
+                            #[weak(rename_to=colorpicker)]
+                            obj,
+                            move |setter, _| {
+                                let color = setter.slot_color();
+                                let mut palettes = colorpicker.imp().palettes.borrow_mut();
+                                let colors = &mut palettes.active_mut().colors;
+                                if colors.len() <= i {
+                                    colors.resize(i + 1, None);
+                                }
+                                colors[i] = color;
+                            }
+                        ),
+                    );
+                }
+            }
+
             self.setter_1.connect_active_notify(clone!(
                 #[weak(rename_to=colorpicker)]
                 obj,
@@ -221,7 +316,9 @@ mod imp {
                         colorpicker.setter_8().set_active(false);
                         colorpicker.setter_9().set_active(false);
                         // Must come after setting the other setters inactive
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -239,7 +336,9 @@ mod imp {
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_8().set_active(false);
                         colorpicker.setter_9().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -257,7 +356,9 @@ mod imp {
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_8().set_active(false);
                         colorpicker.setter_9().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -275,7 +376,9 @@ mod imp {
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_8().set_active(false);
                         colorpicker.setter_9().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -293,7 +396,9 @@ mod imp {
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_8().set_active(false);
                         colorpicker.setter_9().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -311,7 +416,9 @@ mod imp {
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_8().set_active(false);
                         colorpicker.setter_9().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -328,7 +435,9 @@ mod imp {
                         colorpicker.setter_5().set_active(false);
                         colorpicker.setter_6().set_active(false);
                         colorpicker.setter_8().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -346,7 +455,9 @@ mod imp {
                         colorpicker.setter_6().set_active(false);
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_9().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -364,7 +475,9 @@ mod imp {
                         colorpicker.setter_6().set_active(false);
                         colorpicker.setter_7().set_active(false);
                         colorpicker.setter_8().set_active(false);
-                        colorpicker.set_color_active_pad(setter.color());
+                        if setter.has_color() {
+                            colorpicker.set_color_active_pad(setter.color());
+                        }
                     }
                 }
             ));
@@ -486,6 +599,196 @@ impl RnColorPicker {
         self.imp().setter_9.get()
     }
 
+    fn setters(&self) -> [RnColorSetter; 9] {
+        [
+            self.setter_1(),
+            self.setter_2(),
+            self.setter_3(),
+            self.setter_4(),
+            self.setter_5(),
+            self.setter_6(),
+            self.setter_7(),
+            self.setter_8(),
+            self.setter_9(),
+        ]
+    }
+
+    /// Add a new palette with the current setter colors and make it active.
+    pub(crate) fn add_palette(&self, name: impl Into<String>) {
+        let colors = self
+            .setters()
+            .iter()
+            .map(|setter| setter.slot_color())
+            .collect();
+        {
+            let mut palettes = self.imp().palettes.borrow_mut();
+            let index = palettes.add(ColorPalette::new(name, colors));
+            palettes.switch(index);
+        }
+        self.update_palettes_menu();
+    }
+
+    /// Remove the palette at the given index and apply the then active palette to the setters.
+    pub(crate) fn remove_palette(&self, index: usize) {
+        if self.imp().palettes.borrow_mut().remove(index).is_some() {
+            self.apply_active_palette();
+        }
+    }
+
+    /// Switch to the palette at the given index.
+    ///
+    /// Updates the setters in place, the active stroke and fill colors are left untouched.
+    pub(crate) fn switch_palette(&self, index: usize) {
+        if self.imp().palettes.borrow_mut().switch(index).is_some() {
+            self.apply_active_palette();
+        }
+    }
+
+    fn apply_active_palette(&self) {
+        let colors = self.imp().palettes.borrow().active().colors.clone();
+        for (setter, color) in self.setters().into_iter().zip(colors) {
+            setter.set_slot_color(color);
+        }
+        // The generated shades belong to the previous palette
+        self.imp().shades_backup.replace(None);
+        self.update_shades_button();
+        self.update_palettes_menu();
+    }
+
+    /// Rebuild the palettes menu and update the state of the palette actions.
+    fn update_palettes_menu(&self) {
+        let palettes = self.imp().palettes.borrow();
+        let action_group = &self.imp().action_group;
+
+        let palettes_section = gio::Menu::new();
+        for (i, palette) in palettes.palettes().iter().enumerate() {
+            let item = gio::MenuItem::new(Some(&palette.name), None);
+            item.set_action_and_target_value(
+                Some("colorpicker.switch-palette"),
+                Some(&(i as u32).to_variant()),
+            );
+            palettes_section.append_item(&item);
+        }
+        let edit_section = gio::Menu::new();
+        edit_section.append(
+            Some(&gettext("Save Colors as New Palette")),
+            Some("colorpicker.add-palette"),
+        );
+        edit_section.append(
+            Some(&gettext("Remove Active Palette")),
+            Some("colorpicker.remove-palette"),
+        );
+        let menu = gio::Menu::new();
+        menu.append_section(None, &palettes_section);
+        menu.append_section(None, &edit_section);
+        self.imp().palettes_menubutton.set_menu_model(Some(&menu));
+
+        if let Some(action) = action_group
+            .lookup_action("switch-palette")
+            .and_downcast::<gio::SimpleAction>()
+        {
+            action.set_state(&(palettes.active_index() as u32).to_variant());
+        }
+        if let Some(action) = action_group
+            .lookup_action("remove-palette")
+            .and_downcast::<gio::SimpleAction>()
+        {
+            action.set_enabled(palettes.palettes().len() > 1);
+        }
+    }
+
+    /// Fill the other setters with tints and shades of the color of the active setter.
+    ///
+    /// The setters left of the active one get lighter, the ones right of it darker.
+    /// Invoking it again reverts the setters to their previous colors.
+    pub(crate) fn toggle_generated_shades(&self) {
+        let setters = self.setters();
+
+        if let Some(prev_colors) = self.imp().shades_backup.take() {
+            for (setter, color) in setters.iter().zip(prev_colors) {
+                setter.set_slot_color(color);
+            }
+            self.update_shades_button();
+            return;
+        }
+        let Some(active_index) = setters
+            .iter()
+            .position(|setter| setter.is_active() && setter.has_color())
+        else {
+            debug!("Not generating shades, no active color setter.");
+            return;
+        };
+        let base_color = setters[active_index].color().into_compose_color();
+        let prev_colors = setters
+            .iter()
+            .map(|setter| setter.slot_color())
+            .collect::<Vec<Option<Color>>>();
+
+        let tints = base_color.tints(active_index);
+        let shades = base_color.shades(setters.len() - active_index - 1);
+        for (setter, color) in setters[..active_index].iter().zip(tints.into_iter().rev()) {
+            setter.set_color(gdk::RGBA::from_compose_color(color));
+        }
+        for (setter, color) in setters[active_index + 1..].iter().zip(shades) {
+            setter.set_color(gdk::RGBA::from_compose_color(color));
+        }
+        self.imp().shades_backup.replace(Some(prev_colors));
+        self.update_shades_button();
+    }
+
+    fn update_shades_button(&self) {
+        let tooltip = if self.imp().shades_backup.borrow().is_some() {
+            gettext("Revert the Generated Shades")
+        } else {
+            gettext("Generate Shades of the Active Color")
+        };
+        self.imp().shades_button.set_tooltip_text(Some(&tooltip));
+    }
+
+    pub(crate) fn save_palettes_to_settings(&self, settings: &gio::Settings) {
+        let palettes_json = match serde_json::to_string(&*self.imp().palettes.borrow()) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Serializing colorpicker palettes failed, Err: {e:?}");
+                return;
+            }
+        };
+        if let Err(e) = settings.set_string("colorpicker-palettes", &palettes_json) {
+            error!("Saving `colorpicker-palettes` to settings failed, Err: {e:?}");
+        }
+    }
+
+    /// Load the palettes from the settings.
+    ///
+    /// The setter colors are bound to their own settings keys and are expected to be bound already,
+    /// so the active palette is taken over from them instead of being applied to them.
+    /// Only the empty slots are applied, because the settings keys can't represent them.
+    pub(crate) fn load_palettes_from_settings(&self, settings: &gio::Settings) {
+        let palettes_json = settings.string("colorpicker-palettes");
+        if palettes_json.is_empty() {
+            return;
+        }
+        let mut palettes = match serde_json::from_str::<ColorPalettes>(&palettes_json) {
+            Ok(palettes) => palettes,
+            Err(e) => {
+                error!("Deserializing colorpicker palettes failed, Err: {e:?}");
+                return;
+            }
+        };
+        for (setter, color) in self.setters().iter().zip(palettes.active().colors.iter()) {
+            if color.is_none() {
+                setter.clear_color();
+            }
+        }
+        palettes.active_mut().colors = self
+            .setters()
+            .iter()
+            .map(|setter| setter.slot_color())
+            .collect();
+        self.imp().palettes.replace(palettes);
+        self.update_palettes_menu();
+    }
+
     pub(crate) fn active_color_label(&self) -> Label {
         self.imp().active_color_label.get()
     }
@@ -520,6 +823,65 @@ impl RnColorPicker {
                 }
             }),
         );
+
+        for setter in self.setters() {
+            setter.connect_start_eyedropper(clone!(
+                #[weak]
+                appwindow,
+                move |setter, _, _, _| {
+                    appwindow
+                        .active_tab_wrapper()
+                        .canvas()
+                        .start_eyedropper(setter);
+                }
+            ));
+            setter.connect_request_set_color(clone!(
+                #[weak(rename_to=colorpicker)]
+                self,
+                #[weak]
+                appwindow,
+                move |setter, _, _, _| {
+                    colorpicker.choose_setter_color(setter, &appwindow);
+                }
+            ));
+        }
+    }
+
+    /// Open the color dialog to fill the given empty setter.
+    ///
+    /// The setter becomes active once a color was chosen, applying it to the active pad.
+    fn choose_setter_color(&self, setter: &RnColorSetter, appwindow: &RnAppWindow) {
+        if self.imp().color_dialog.upgrade().is_some() {
+            return;
+        }
+        glib::spawn_future_local(clone!(
+            #[weak(rename_to=colorpicker)]
+            self,
+            #[weak]
+            setter,
+            #[weak]
+            appwindow,
+            async move {
+                let dialog = ColorDialog::builder().modal(false).with_alpha(true).build();
+                colorpicker.imp().color_dialog.set(Some(&dialog));
+
+                match dialog.choose_rgba_future(Some(&appwindow), None).await {
+                    Ok(new_color) => {
+                        setter.set_color(new_color);
+                        if setter.is_active() {
+                            colorpicker.set_color_active_pad(new_color);
+                        } else {
+                            setter.set_active(true);
+                        }
+                    }
+                    Err(e) => debug!(
+                        "Did not choose new color (Error or dialog dismissed by user), Err: {e:?}"
+                    ),
+                }
+
+                colorpicker.imp().color_dialog.set(None);
+            }
+        ));
     }
 
     fn set_color_active_setter(&self, color: gdk::RGBA) {
@@ -557,6 +919,9 @@ impl RnColorPicker {
     }
 
     fn set_color_active_pad(&self, color: gdk::RGBA) {
+        if self.imp().reflecting_selection.get() {
+            return;
+        }
         if self.imp().stroke_color_pad.is_active() {
             self.set_stroke_color(color);
         } else if self.imp().fill_color_pad.is_active() {
@@ -564,6 +929,35 @@ impl RnColorPicker {
         }
     }
 
+    /// Activate the setter matching the color of the selection for the active pad.
+    ///
+    /// The color of the setter is not applied. When the color is mixed or no setter matches, all setters are deselected.
+    pub(crate) fn reflect_selection_style(&self, summary: &SelectionStyleSummary) {
+        const COLOR_EPSILON: f64 = 1e-3;
+
+        let color = if self.fill_color_pad_active() {
+            summary.fill_color
+        } else {
+            summary.stroke_color
+        };
+        self.deselect_setters();
+        let Some(color) = color else {
+            return;
+        };
+        let matches = |setter: &RnColorSetter| {
+            let setter_color = setter.color().into_compose_color();
+            (setter_color.r - color.r).abs() < COLOR_EPSILON
+                && (setter_color.g - color.g).abs() < COLOR_EPSILON
+                && (setter_color.b - color.b).abs() < COLOR_EPSILON
+                && (setter_color.a - color.a).abs() < COLOR_EPSILON
+        };
+        if let Some(setter) = self.setters().into_iter().find(matches) {
+            self.imp().reflecting_selection.set(true);
+            setter.set_active(true);
+            self.imp().reflecting_selection.set(false);
+        }
+    }
+
     pub(crate) fn deselect_setters(&self) {
         let imp = self.imp();
 
@@ -12,7 +12,7 @@ use rnote_compose::SplitOrder;
 use rnote_engine::document::Layout;
 use rnote_engine::engine::export::{
     DocExportFormat, DocExportPrefs, DocPagesExportFormat, DocPagesExportPrefs,
-    SelectionExportFormat, SelectionExportPrefs,
+    SelectionExportFormat, SelectionExportPrefs, SelectionPhysicalSize,
 };
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -817,6 +817,9 @@ pub(crate) async fn dialog_export_selection_w_prefs(appwindow: &RnAppWindow, can
     let jpeg_quality_row: adw::SpinRow =
         builder.object("export_selection_jpeg_quality_row").unwrap();
     let margin_row: adw::SpinRow = builder.object("export_selection_margin_row").unwrap();
+    let true_to_scale_row: adw::SwitchRow = builder
+        .object("export_selection_true_to_scale_row")
+        .unwrap();
     let preview: RnStrokeContentPreview = builder.object("export_selection_preview").unwrap();
     let export_selection_button_cancel: Button =
         builder.object("export_selection_button_cancel").unwrap();
@@ -856,6 +859,13 @@ pub(crate) async fn dialog_export_selection_w_prefs(appwindow: &RnAppWindow, can
         .set_sensitive(initial_selection_export_prefs.export_format == SelectionExportFormat::Jpeg);
     jpeg_quality_row.set_value(initial_selection_export_prefs.jpeg_quality as f64);
     margin_row.set_value(initial_selection_export_prefs.margin);
+    true_to_scale_row.set_sensitive(
+        initial_selection_export_prefs.export_format == SelectionExportFormat::Svg
+            || initial_selection_export_prefs.export_format == SelectionExportFormat::Pdf,
+    );
+    true_to_scale_row.set_active(
+        initial_selection_export_prefs.physical_size == SelectionPhysicalSize::TrueToScale,
+    );
     export_file_label.set_label(&gettext("- no file selected -"));
     button_confirm.set_sensitive(false);
 
@@ -961,6 +971,8 @@ pub(crate) async fn dialog_export_selection_w_prefs(appwindow: &RnAppWindow, can
         #[weak]
         jpeg_quality_row,
         #[weak]
+        true_to_scale_row,
+        #[weak]
         export_file_label,
         #[weak]
         canvas,
@@ -984,6 +996,27 @@ pub(crate) async fn dialog_export_selection_w_prefs(appwindow: &RnAppWindow, can
             );
             // Set the jpeg quality pref only sensitive when jpeg is actually selected
             jpeg_quality_row.set_sensitive(export_format == SelectionExportFormat::Jpeg);
+            // Physical units only apply to the vector formats
+            true_to_scale_row.set_sensitive(
+                export_format == SelectionExportFormat::Svg
+                    || export_format == SelectionExportFormat::Pdf,
+            );
+        }
+    ));
+
+    true_to_scale_row.connect_active_notify(clone!(
+        #[weak]
+        canvas,
+        move |true_to_scale_row| {
+            canvas
+                .engine_mut()
+                .export_prefs
+                .selection_export_prefs
+                .physical_size = if true_to_scale_row.is_active() {
+                SelectionPhysicalSize::TrueToScale
+            } else {
+                SelectionPhysicalSize::None
+            };
         }
     ));
 
@@ -1165,6 +1198,17 @@ fn create_filedialog_export_selection(
             }
             filter.set_name(Some(&gettext("Jpeg")));
         }
+        SelectionExportFormat::Pdf => {
+            if cfg!(target_os = "windows") {
+                filter.add_pattern("*.pdf");
+            } else {
+                filter.add_mime_type("application/pdf");
+            }
+            if cfg!(target_os = "macos") {
+                filter.add_suffix("pdf");
+            }
+            filter.set_name(Some(&gettext("Pdf")));
+        }
     }
     let file_ext = selection_export_prefs.export_format.file_ext();
     let file_name = crate::utils::default_file_title_for_export(
@@ -8,7 +8,7 @@ use futures::StreamExt;
 use gettextrs::gettext;
 use gtk4::{
     gio, glib, glib::clone, Builder, Button, CallbackAction, FileDialog, FileFilter, Label,
-    Shortcut, ShortcutController, ShortcutTrigger, ToggleButton,
+    Shortcut, ShortcutController, ShortcutTrigger, ToggleButton, Widget,
 };
 use num_traits::ToPrimitive;
 use rnote_engine::engine::import::{PdfImportPageSpacing, PdfImportPagesType};
@@ -111,6 +111,55 @@ pub(crate) async fn filedialog_import_file(appwindow: &RnAppWindow) {
     }
 }
 
+/// Imports the strokes of another rnote file into the document of the active tab.
+pub(crate) async fn filedialog_import_rnote_file_contents(appwindow: &RnAppWindow) {
+    let filter = FileFilter::new();
+    // note : mimetypes are not supported with the native file picker on windows
+    // See the limitations on FileChooserNative
+    // https://gtk-rs.org/gtk3-rs/stable/latest/docs/gtk/struct.FileChooserNative.html#win32-details--gtkfilechooserdialognative-win32
+    if cfg!(target_os = "windows") {
+        filter.add_pattern("*.rnote");
+    } else {
+        filter.add_mime_type("application/rnote");
+    }
+    filter.add_suffix("rnote");
+    filter.set_name(Some(&gettext(".rnote")));
+
+    let filter_list = gio::ListStore::new::<FileFilter>();
+    filter_list.append(&filter);
+
+    let dialog = FileDialog::builder()
+        .title(gettext("Import Rnote File Contents"))
+        .modal(true)
+        .accept_label(gettext("Import"))
+        .filters(&filter_list)
+        .default_filter(&filter)
+        .build();
+
+    if let Some(current_workspace_dir) = appwindow.sidebar().workspacebrowser().dir_list_dir() {
+        dialog.set_initial_folder(Some(&gio::File::for_path(current_workspace_dir)));
+    }
+
+    let selected_file = match dialog.open_future(Some(appwindow)).await {
+        Ok(selected_file) => selected_file,
+        Err(e) => {
+            debug!("Did not import rnote file contents (Error or dialog dismissed by user), Err: {e:?}");
+            return;
+        }
+    };
+    let canvas = appwindow.active_tab_wrapper().canvas();
+    let result = async {
+        let (bytes, _) = selected_file.load_bytes_future().await?;
+        canvas.load_in_rnote_bytes_as_strokes(bytes.to_vec(), None)
+    };
+    if let Err(e) = result.await {
+        error!("Importing rnote file contents failed, Err: {e:?}");
+        appwindow
+            .overlays()
+            .dispatch_toast_error(&gettext("Importing rnote file contents failed"));
+    }
+}
+
 /// Imports the file as Pdf with an import dialog.
 ///
 /// Returns true when the file was imported, else false.
@@ -140,6 +189,8 @@ pub(crate) async fn dialog_import_pdf_w_prefs(
         builder.object("pdf_import_page_borders_row").unwrap();
     let pdf_import_adjust_document_row: adw::SwitchRow =
         builder.object("pdf_import_adjust_document_row").unwrap();
+    let pdf_import_as_underlay_row: adw::SwitchRow =
+        builder.object("pdf_import_as_underlay_row").unwrap();
     let import_pdf_button_cancel: Button = builder.object("import_pdf_button_cancel").unwrap();
     let import_pdf_button_confirm: Button = builder.object("import_pdf_button_confirm").unwrap();
 
@@ -153,6 +204,19 @@ pub(crate) async fn dialog_import_pdf_w_prefs(
         .invert_boolean()
         .sync_create()
         .build();
+    // The underlay always contains all pages, drawn with their original content
+    for row in [
+        pdf_page_start_row.upcast_ref::<Widget>(),
+        pdf_page_end_row.upcast_ref(),
+        pdf_import_bitmap_scalefactor_row.upcast_ref(),
+        pdf_import_page_borders_row.upcast_ref(),
+    ] {
+        pdf_import_as_underlay_row
+            .bind_property("active", row, "sensitive")
+            .invert_boolean()
+            .sync_create()
+            .build();
+    }
 
     let pdf_import_prefs = canvas.engine_ref().import_prefs.pdf_import_prefs;
 
@@ -346,10 +410,11 @@ pub(crate) async fn dialog_import_pdf_w_prefs(
         }
     ));
 
-    import_pdf_button_confirm.connect_clicked(clone!(#[weak] pdf_page_start_row, #[weak] pdf_page_end_row, #[weak] input_file, #[weak] dialog, #[weak] canvas , move |_| {
+    import_pdf_button_confirm.connect_clicked(clone!(#[weak] pdf_page_start_row, #[weak] pdf_page_end_row, #[weak] pdf_import_as_underlay_row, #[weak] input_file, #[weak] dialog, #[weak] canvas , move |_| {
         dialog.close();
 
         let inner_tx_confirm = tx_confirm.clone();
+        let as_underlay = pdf_import_as_underlay_row.is_active();
 
         glib::spawn_future_local(clone!(#[weak] pdf_page_start_row, #[weak] pdf_page_end_row, #[weak] input_file, #[weak] canvas , async move {
             let page_range =
@@ -364,7 +429,12 @@ pub(crate) async fn dialog_import_pdf_w_prefs(
                     return;
                 }
             };
-            if let Err(e) = canvas.load_in_pdf_bytes(bytes.to_vec(), target_pos, Some(page_range)).await {
+            let result = if as_underlay {
+                canvas.load_in_pdf_bytes_as_underlay(bytes.to_vec())
+            } else {
+                canvas.load_in_pdf_bytes(bytes.to_vec(), target_pos, Some(page_range)).await
+            };
+            if let Err(e) = result {
                 if let Err(e) = inner_tx_confirm.unbounded_send(Err(e)) {
                     error!("Failed to load PDF, but failed to send signal through channel. Err: {e:?}");
                 }
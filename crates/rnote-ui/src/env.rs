@@ -1,5 +1,6 @@
 // Imports
 use crate::config;
+use gtk4::glib;
 use std::ffi::OsStr;
 use std::path::{Component, Path, PathBuf};
 
@@ -43,6 +44,13 @@ pub(crate) fn pkg_data_dir() -> anyhow::Result<PathBuf> {
     Ok(data_dir()?.join(config::APP_NAME))
 }
 
+/// The directory where recovery snapshots of opened documents are written into.
+pub(crate) fn recovery_dir() -> PathBuf {
+    glib::user_cache_dir()
+        .join(config::APP_NAME)
+        .join("recovery")
+}
+
 pub(crate) fn locale_dir() -> anyhow::Result<PathBuf> {
     if cfg!(target_os = "windows") {
         let exec_dir = exec_parent_dir()?;
@@ -71,10 +79,20 @@ pub(crate) fn setup_env() -> anyhow::Result<()> {
         let lib_dir = lib_dir()?;
 
         std::env::set_var("XDG_DATA_DIRS", data_dir);
-        std::env::set_var(
-            "GDK_PIXBUF_MODULEDIR",
-            lib_dir.join("gdk-pixbuf-2.0\\2.10.0\\loaders"),
-        );
+        // prefer the bundled loaders cache, so that the loaders don't need to be probed on every startup
+        let pixbuf_loaders_cache = lib_dir.join("gdk-pixbuf-2.0\\2.10.0\\loaders.cache");
+        if pixbuf_loaders_cache.exists() {
+            std::env::set_var("GDK_PIXBUF_MODULE_FILE", pixbuf_loaders_cache);
+            let gio_modules_dir = lib_dir.join("gio\\modules");
+            if gio_modules_dir.exists() {
+                std::env::set_var("GIO_EXTRA_MODULES", gio_modules_dir);
+            }
+        } else {
+            std::env::set_var(
+                "GDK_PIXBUF_MODULEDIR",
+                lib_dir.join("gdk-pixbuf-2.0\\2.10.0\\loaders"),
+            );
+        }
         //std::env::set_var("RUST_LOG", "rnote=debug");
     } else if cfg!(target_os = "macos") {
         let canonicalized_exec_dir = exec_parent_dir()?.canonicalize()?;
@@ -93,6 +111,71 @@ pub(crate) fn setup_env() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The maximum path length of the legacy Windows file APIs, including the terminating nul.
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// Makes the path usable for file I/O even when it exceeds the legacy Windows maximum path length,
+/// by prefixing it with the extended-length namespace `\\?\`.
+///
+/// Returns the path unchanged when it is short enough, relative or already namespaced, and on other platforms.
+pub(crate) fn long_path(path: impl AsRef<Path>) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        windows_long_path(path.as_ref())
+    } else {
+        path.as_ref().to_path_buf()
+    }
+}
+
+fn windows_long_path(path: &Path) -> PathBuf {
+    let Some(path_str) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    if path_str.encode_utf16().count() < WINDOWS_MAX_PATH
+        || path_str.starts_with(r"\\?\")
+        || path_str.starts_with(r"\\.\")
+    {
+        return path.to_path_buf();
+    }
+    // extended-length paths are passed to the file system unmodified,
+    // so separators and relative components need to be normalized beforehand
+    let path_str = path_str.replace('/', r"\");
+    let (prefix, root, rest) = if let Some(unc) = path_str.strip_prefix(r"\\") {
+        let mut parts = unc.splitn(3, '\\');
+        let (Some(server), Some(share)) = (parts.next(), parts.next()) else {
+            return path.to_path_buf();
+        };
+        (
+            r"\\?\UNC\",
+            format!(r"{server}\{share}"),
+            parts.next().unwrap_or(""),
+        )
+    } else {
+        let mut chars = path_str.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(drive), Some(':'), Some('\\')) if drive.is_ascii_alphabetic() => {
+                (r"\\?\", path_str[..2].to_string(), &path_str[3..])
+            }
+            _ => return path.to_path_buf(),
+        }
+    };
+    let mut components: Vec<&str> = vec![];
+    for component in rest.split('\\') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    let mut long_path = format!("{prefix}{root}");
+    for component in components {
+        long_path.push('\\');
+        long_path.push_str(component);
+    }
+    PathBuf::from(long_path)
+}
+
 fn exec_parent_dir() -> anyhow::Result<PathBuf> {
     Ok(std::env::current_exe()?
         .parent()
@@ -116,3 +199,69 @@ fn macos_is_in_app_bundle(canonicalized_exec_dir: impl AsRef<Path>) -> bool {
             }
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_segments(n: usize) -> String {
+        (0..n)
+            .map(|i| format!("directory-with-quite-a-long-name-{i:02}"))
+            .collect::<Vec<_>>()
+            .join("\\")
+    }
+
+    #[test]
+    fn windows_long_path_prefixing() {
+        let segments = long_segments(8);
+        assert!(segments.len() > WINDOWS_MAX_PATH);
+
+        let drive_path = format!(r"C:\{segments}\document.rnote");
+        assert_eq!(
+            windows_long_path(Path::new(&drive_path)),
+            PathBuf::from(format!(r"\\?\{drive_path}"))
+        );
+        // separators and relative components are normalized
+        let mixed_path = format!(
+            r"C:/{}/./unrelated\..\document.rnote",
+            segments.replace('\\', "/")
+        );
+        assert_eq!(
+            windows_long_path(Path::new(&mixed_path)),
+            PathBuf::from(format!(r"\\?\{drive_path}"))
+        );
+        let unc_path = format!(r"\\server\share\{segments}\document.rnote");
+        assert_eq!(
+            windows_long_path(Path::new(&unc_path)),
+            PathBuf::from(format!(r"\\?\UNC\server\share\{segments}\document.rnote"))
+        );
+
+        // already namespaced, relative and short paths stay untouched
+        let namespaced_path = format!(r"\\?\{drive_path}");
+        assert_eq!(
+            windows_long_path(Path::new(&namespaced_path)),
+            PathBuf::from(&namespaced_path)
+        );
+        let relative_path = format!(r"{segments}\document.rnote");
+        assert_eq!(
+            windows_long_path(Path::new(&relative_path)),
+            PathBuf::from(&relative_path)
+        );
+        let short_path = r"C:\Users\user\Documents\document.rnote";
+        assert_eq!(
+            windows_long_path(Path::new(short_path)),
+            PathBuf::from(short_path)
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn long_path_is_noop() {
+        let path = format!(
+            "/home/user/{}/document.rnote",
+            long_segments(8).replace('\\', "/")
+        );
+        assert!(path.len() > WINDOWS_MAX_PATH);
+        assert_eq!(long_path(&path), PathBuf::from(&path));
+    }
+}
@@ -9,6 +9,7 @@ use gtk4::{
 };
 use rnote_engine::ext::GdkRGBAExt;
 use rnote_engine::pens::PenStyle;
+use rnote_engine::store::StyleUpdate;
 use std::cell::{Cell, RefCell};
 use tracing::error;
 
@@ -147,6 +148,7 @@ impl RnOverlays {
         imp.penssidebar.get().eraser_page().init(appwindow);
         imp.penssidebar.get().selector_page().init(appwindow);
         imp.penssidebar.get().tools_page().init(appwindow);
+        imp.penssidebar.get().laser_page().init(appwindow);
 
         self.setup_colorpicker(appwindow);
         self.setup_tabview(appwindow);
@@ -172,10 +174,20 @@ impl RnOverlays {
                             appwindow.handle_widget_flags(widget_flags, &canvas);
                         }
                         PenStyle::Selector => {
-                            let widget_flags = canvas
-                                .engine_mut()
-                                .change_selection_stroke_colors(stroke_color);
+                            let (modified, widget_flags) =
+                                canvas.engine_mut().change_selection_style(StyleUpdate {
+                                    stroke_color: Some(stroke_color),
+                                    ..Default::default()
+                                });
                             appwindow.handle_widget_flags(widget_flags, &canvas);
+                            if !modified.is_empty() && appwindow.recolor_selection_only() {
+                                return;
+                            }
+                        }
+                        PenStyle::Laser => {
+                            // The laser pointer has its own color, which is not applied to the other pens
+                            canvas.engine_mut().pens_config.laser_config.color = stroke_color;
+                            return;
                         }
                         PenStyle::Brush | PenStyle::Shaper | PenStyle::Eraser | PenStyle::Tools => {
                         }
@@ -202,15 +214,22 @@ impl RnOverlays {
 
                     match stroke_style {
                         PenStyle::Selector => {
-                            let widget_flags =
-                                canvas.engine_mut().change_selection_fill_colors(fill_color);
+                            let (modified, widget_flags) =
+                                canvas.engine_mut().change_selection_style(StyleUpdate {
+                                    fill_color: Some(fill_color),
+                                    ..Default::default()
+                                });
                             appwindow.handle_widget_flags(widget_flags, &canvas);
+                            if !modified.is_empty() && appwindow.recolor_selection_only() {
+                                return;
+                            }
                         }
                         PenStyle::Typewriter
                         | PenStyle::Brush
                         | PenStyle::Shaper
                         | PenStyle::Eraser
-                        | PenStyle::Tools => {}
+                        | PenStyle::Tools
+                        | PenStyle::Laser => {}
                     }
 
                     // We have a global colorpicker, so we apply it to all styles
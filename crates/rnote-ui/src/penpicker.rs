@@ -25,6 +25,8 @@ mod imp {
         #[template_child]
         pub(crate) tools_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub(crate) laser_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub(crate) undo_button: TemplateChild<Button>,
         #[template_child]
         pub(crate) redo_button: TemplateChild<Button>,
@@ -97,6 +99,10 @@ impl RnPenPicker {
         self.imp().tools_toggle.get()
     }
 
+    pub(crate) fn laser_toggle(&self) -> ToggleButton {
+        self.imp().laser_toggle.get()
+    }
+
     pub(crate) fn undo_button(&self) -> Button {
         self.imp().undo_button.get()
     }
@@ -191,5 +197,19 @@ impl RnPenPicker {
                 }
             }
         ));
+
+        imp.laser_toggle.get().connect_toggled(clone!(
+            #[weak]
+            appwindow,
+            move |laser_toggle| {
+                if laser_toggle.is_active() {
+                    adw::prelude::ActionGroupExt::activate_action(
+                        &appwindow,
+                        "pen-style",
+                        Some(&PenStyle::Laser.to_string().to_variant()),
+                    );
+                }
+            }
+        ));
     }
 }
@@ -49,6 +49,8 @@ mod imp {
         #[template_child]
         pub(crate) solidstyle_pressure_curves_row: TemplateChild<adw::ComboRow>,
         #[template_child]
+        pub(crate) solidstyle_pressure_curve_steepness_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
         pub(crate) texturedstyle_density_row: TemplateChild<adw::SpinRow>,
         #[template_child]
         pub(crate) texturedstyle_distribution_row: TemplateChild<adw::ComboRow>,
@@ -156,17 +158,36 @@ impl RnBrushPage {
         }
     }
 
-    pub(crate) fn solidstyle_pressure_curve(&self) -> PressureCurve {
-        PressureCurve::try_from(self.imp().solidstyle_pressure_curves_row.get().selected()).unwrap()
+    /// The selected pressure curve.
+    ///
+    /// Keeps the control points of the given current curve when it is already a custom curve.
+    pub(crate) fn solidstyle_pressure_curve(&self, current: &PressureCurve) -> PressureCurve {
+        let imp = self.imp();
+        match PressureCurve::try_from(imp.solidstyle_pressure_curves_row.get().selected()).unwrap()
+        {
+            PressureCurve::SCurve { .. } => PressureCurve::SCurve {
+                steepness: imp.solidstyle_pressure_curve_steepness_row.get().value(),
+            },
+            PressureCurve::Custom { .. } if matches!(current, PressureCurve::Custom { .. }) => {
+                current.clone()
+            }
+            pressure_curve => pressure_curve,
+        }
     }
 
-    pub(crate) fn set_solidstyle_pressure_curve(&self, pressure_curve: PressureCurve) {
-        let position = pressure_curve.to_u32().unwrap();
-
-        self.imp()
-            .solidstyle_pressure_curves_row
+    pub(crate) fn set_solidstyle_pressure_curve(&self, pressure_curve: &PressureCurve) {
+        let imp = self.imp();
+        if let PressureCurve::SCurve { steepness } = pressure_curve {
+            imp.solidstyle_pressure_curve_steepness_row
+                .get()
+                .set_value(*steepness);
+        }
+        imp.solidstyle_pressure_curve_steepness_row
             .get()
-            .set_selected(position);
+            .set_visible(matches!(pressure_curve, PressureCurve::SCurve { .. }));
+        imp.solidstyle_pressure_curves_row
+            .get()
+            .set_selected(pressure_curve.index());
     }
 
     pub(crate) fn texturedstyle_dots_distribution(&self) -> TexturedDotsDistribution {
@@ -219,7 +240,7 @@ impl RnBrushPage {
             .set_range(BrushConfig::STROKE_WIDTH_MIN, BrushConfig::STROKE_WIDTH_MAX);
         // set value after the range!
         imp.stroke_width_picker
-            .set_stroke_width(SolidOptions::default().stroke_width);
+            .set_stroke_width(SolidOptions::default().stroke_width());
 
         imp.stroke_width_picker.connect_notify_local(
             Some("stroke-width"),
@@ -238,7 +259,7 @@ impl RnBrushPage {
                                 .pens_config
                                 .brush_config
                                 .marker_options
-                                .stroke_width = stroke_width;
+                                .set_stroke_width(stroke_width);
                         }
                         BrushStyle::Solid => {
                             canvas
@@ -246,7 +267,7 @@ impl RnBrushPage {
                                 .pens_config
                                 .brush_config
                                 .solid_options
-                                .stroke_width = stroke_width;
+                                .set_stroke_width(stroke_width);
                         }
                         BrushStyle::Textured => {
                             canvas
@@ -287,7 +308,7 @@ impl RnBrushPage {
                                 .pens_config
                                 .brush_config
                                 .marker_options
-                                .stroke_width;
+                                .stroke_width();
                             brushpage
                                 .imp()
                                 .stroke_width_picker
@@ -305,7 +326,7 @@ impl RnBrushPage {
                                 .pens_config
                                 .brush_config
                                 .solid_options
-                                .stroke_width;
+                                .stroke_width();
                             brushpage
                                 .imp()
                                 .stroke_width_picker
@@ -359,6 +380,15 @@ impl RnBrushPage {
 
         // Solid style
         // Pressure curve
+        imp.solidstyle_pressure_curve_steepness_row.get().set_range(
+            PressureCurve::SCURVE_STEEPNESS_MIN,
+            PressureCurve::SCURVE_STEEPNESS_MAX,
+        );
+        // set value after the range!
+        imp.solidstyle_pressure_curve_steepness_row
+            .get()
+            .set_value(PressureCurve::SCURVE_STEEPNESS_DEFAULT);
+
         imp.solidstyle_pressure_curves_row
             .get()
             .connect_selected_notify(clone!(
@@ -367,14 +397,39 @@ impl RnBrushPage {
                 #[weak]
                 appwindow,
                 move |_smoothstyle_pressure_curves_row| {
-                    appwindow
+                    let canvas = appwindow.active_tab_wrapper().canvas();
+                    let mut engine = canvas.engine_mut();
+                    let solid_options = &mut engine.pens_config.brush_config.solid_options;
+                    solid_options.pressure_curve =
+                        brushpage.solidstyle_pressure_curve(&solid_options.pressure_curve);
+                    brushpage
+                        .imp()
+                        .solidstyle_pressure_curve_steepness_row
+                        .get()
+                        .set_visible(matches!(
+                            solid_options.pressure_curve,
+                            PressureCurve::SCurve { .. }
+                        ));
+                }
+            ));
+
+        imp.solidstyle_pressure_curve_steepness_row
+            .get()
+            .connect_changed(clone!(
+                #[weak]
+                appwindow,
+                move |row| {
+                    if let PressureCurve::SCurve { steepness } = &mut appwindow
                         .active_tab_wrapper()
                         .canvas()
                         .engine_mut()
                         .pens_config
                         .brush_config
                         .solid_options
-                        .pressure_curve = brushpage.solidstyle_pressure_curve();
+                        .pressure_curve
+                    {
+                        *steepness = row.value();
+                    }
                 }
             ));
 
@@ -433,7 +488,7 @@ impl RnBrushPage {
             .brush_config
             .clone();
 
-        self.set_solidstyle_pressure_curve(brush_config.solid_options.pressure_curve);
+        self.set_solidstyle_pressure_curve(&brush_config.solid_options.pressure_curve);
         imp.texturedstyle_density_row
             .set_value(brush_config.textured_options.density);
         self.set_texturedstyle_distribution_variant(brush_config.textured_options.distribution);
@@ -444,11 +499,11 @@ impl RnBrushPage {
         match brush_config.style {
             BrushStyle::Marker => {
                 imp.stroke_width_picker
-                    .set_stroke_width(brush_config.marker_options.stroke_width);
+                    .set_stroke_width(brush_config.marker_options.stroke_width());
             }
             BrushStyle::Solid => {
                 imp.stroke_width_picker
-                    .set_stroke_width(brush_config.solid_options.stroke_width);
+                    .set_stroke_width(brush_config.solid_options.stroke_width());
             }
             BrushStyle::Textured => {
                 imp.stroke_width_picker
@@ -17,6 +17,10 @@ mod imp {
         #[template_child]
         pub(crate) eraserstyle_split_colliding_strokes_toggle: TemplateChild<ToggleButton>,
         #[template_child]
+        pub(crate) eraserstyle_split_strokes_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
+        pub(crate) restrict_to_selection_toggle: TemplateChild<ToggleButton>,
+        #[template_child]
         pub(crate) stroke_width_picker: TemplateChild<RnStrokeWidthPicker>,
     }
 
@@ -81,6 +85,8 @@ impl RnEraserPage {
             .is_active()
         {
             Some(EraserStyle::SplitCollidingStrokes)
+        } else if self.imp().eraserstyle_split_strokes_toggle.is_active() {
+            Some(EraserStyle::SplitStrokes)
         } else {
             None
         }
@@ -97,6 +103,9 @@ impl RnEraserPage {
                 .imp()
                 .eraserstyle_split_colliding_strokes_toggle
                 .set_active(true),
+            EraserStyle::SplitStrokes => {
+                self.imp().eraserstyle_split_strokes_toggle.set_active(true)
+            }
         }
     }
 
@@ -141,6 +150,36 @@ impl RnEraserPage {
                 }
             ));
 
+        imp.eraserstyle_split_strokes_toggle.connect_toggled(clone!(
+            #[weak]
+            appwindow,
+            move |eraserstyle_split_strokes_toggle| {
+                if eraserstyle_split_strokes_toggle.is_active() {
+                    appwindow
+                        .active_tab_wrapper()
+                        .canvas()
+                        .engine_mut()
+                        .pens_config
+                        .eraser_config
+                        .style = EraserStyle::SplitStrokes;
+                }
+            }
+        ));
+
+        imp.restrict_to_selection_toggle.connect_toggled(clone!(
+            #[weak]
+            appwindow,
+            move |restrict_to_selection_toggle| {
+                appwindow
+                    .active_tab_wrapper()
+                    .canvas()
+                    .engine_mut()
+                    .pens_config
+                    .eraser_config
+                    .restrict_to_selection = restrict_to_selection_toggle.is_active();
+            }
+        ));
+
         // width
         imp.stroke_width_picker.spinbutton().set_digits(0);
         imp.stroke_width_picker
@@ -186,5 +225,7 @@ impl RnEraserPage {
             .set_stroke_width(eraser_config.width);
 
         self.set_eraser_style(eraser_config.style);
+        imp.restrict_to_selection_toggle
+            .set_active(eraser_config.restrict_to_selection);
     }
 }
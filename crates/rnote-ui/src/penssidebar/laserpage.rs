@@ -0,0 +1,142 @@
+// Imports
+use crate::RnStrokeWidthPicker;
+use crate::{RnAppWindow, RnCanvasWrapper};
+use adw::prelude::*;
+use gtk4::{glib, glib::clone, subclass::prelude::*, CompositeTemplate, SpinButton};
+use rnote_engine::pens::pensconfig::LaserConfig;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, CompositeTemplate)]
+    #[template(resource = "/com/github/flxzt/rnote/ui/penssidebar/laserpage.ui")]
+    pub(crate) struct RnLaserPage {
+        #[template_child]
+        pub(crate) stroke_width_picker: TemplateChild<RnStrokeWidthPicker>,
+        #[template_child]
+        pub(crate) fade_duration_spinbutton: TemplateChild<SpinButton>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RnLaserPage {
+        const NAME: &'static str = "RnLaserPage";
+        type Type = super::RnLaserPage;
+        type ParentType = gtk4::Widget;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for RnLaserPage {
+        fn constructed(&self) {
+            self.parent_constructed();
+        }
+
+        fn dispose(&self) {
+            self.dispose_template();
+            while let Some(child) = self.obj().first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for RnLaserPage {}
+}
+
+glib::wrapper! {
+    pub(crate) struct RnLaserPage(ObjectSubclass<imp::RnLaserPage>)
+        @extends gtk4::Widget;
+}
+
+impl Default for RnLaserPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RnLaserPage {
+    pub(crate) fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub(crate) fn stroke_width_picker(&self) -> RnStrokeWidthPicker {
+        self.imp().stroke_width_picker.get()
+    }
+
+    pub(crate) fn init(&self, appwindow: &RnAppWindow) {
+        let imp = self.imp();
+
+        // width
+        imp.stroke_width_picker.spinbutton().set_digits(0);
+        imp.stroke_width_picker
+            .spinbutton()
+            .set_increments(1.0, 5.0);
+        imp.stroke_width_picker
+            .spinbutton()
+            .set_range(LaserConfig::WIDTH_MIN, LaserConfig::WIDTH_MAX);
+        // set value after the range!
+        imp.stroke_width_picker
+            .set_stroke_width(LaserConfig::WIDTH_DEFAULT);
+
+        imp.stroke_width_picker.connect_notify_local(
+            Some("stroke-width"),
+            clone!(
+                #[weak]
+                appwindow,
+                move |picker, _| {
+                    let stroke_width = picker.stroke_width();
+                    appwindow
+                        .active_tab_wrapper()
+                        .canvas()
+                        .engine_mut()
+                        .pens_config
+                        .laser_config
+                        .width = stroke_width;
+                }
+            ),
+        );
+
+        // fade duration
+        imp.fade_duration_spinbutton.set_increments(0.1, 1.0);
+        imp.fade_duration_spinbutton.set_range(
+            LaserConfig::FADE_DURATION_MIN,
+            LaserConfig::FADE_DURATION_MAX,
+        );
+        imp.fade_duration_spinbutton
+            .set_value(LaserConfig::FADE_DURATION_DEFAULT);
+
+        imp.fade_duration_spinbutton.connect_value_changed(clone!(
+            #[weak]
+            appwindow,
+            move |spinbutton| {
+                appwindow
+                    .active_tab_wrapper()
+                    .canvas()
+                    .engine_mut()
+                    .pens_config
+                    .laser_config
+                    .fade_duration = spinbutton.value();
+            }
+        ));
+    }
+
+    pub(crate) fn refresh_ui(&self, active_tab: &RnCanvasWrapper) {
+        let imp = self.imp();
+
+        let laser_config = active_tab
+            .canvas()
+            .engine_ref()
+            .pens_config
+            .laser_config
+            .clone();
+
+        imp.stroke_width_picker.set_stroke_width(laser_config.width);
+        imp.fade_duration_spinbutton
+            .set_value(laser_config.fade_duration);
+    }
+}
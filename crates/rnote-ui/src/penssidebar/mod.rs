@@ -1,6 +1,7 @@
 // Modules
 mod brushpage;
 mod eraserpage;
+mod laserpage;
 mod selectorpage;
 mod shaperpage;
 mod toolspage;
@@ -9,6 +10,7 @@ mod typewriterpage;
 // Re-exports
 pub(crate) use brushpage::RnBrushPage;
 pub(crate) use eraserpage::RnEraserPage;
+pub(crate) use laserpage::RnLaserPage;
 use rnote_engine::pens::PenStyle;
 pub(crate) use selectorpage::RnSelectorPage;
 pub(crate) use shaperpage::RnShaperPage;
@@ -54,6 +56,10 @@ mod imp {
         pub(crate) tools_stackpage: TemplateChild<StackPage>,
         #[template_child]
         pub(crate) tools_page: TemplateChild<RnToolsPage>,
+        #[template_child]
+        pub(crate) laser_stackpage: TemplateChild<StackPage>,
+        #[template_child]
+        pub(crate) laser_page: TemplateChild<RnLaserPage>,
     }
 
     #[glib::object_subclass]
@@ -130,6 +136,10 @@ impl RnPensSideBar {
         self.imp().tools_page.get()
     }
 
+    pub(crate) fn laser_page(&self) -> RnLaserPage {
+        self.imp().laser_page.get()
+    }
+
     pub(crate) fn init(&self, appwindow: &RnAppWindow) {
         self.imp()
             .sidebar_stack
@@ -182,6 +192,13 @@ impl RnPensSideBar {
                                     Some(&PenStyle::Tools.to_string().to_variant()),
                                 );
                             }
+                            "laser_page" => {
+                                adw::prelude::ActionGroupExt::activate_action(
+                                    &appwindow,
+                                    "pen-style",
+                                    Some(&PenStyle::Laser.to_string().to_variant()),
+                                );
+                            }
                             _ => {}
                         };
                     };
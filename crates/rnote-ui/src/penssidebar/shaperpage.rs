@@ -33,6 +33,10 @@ mod imp {
         #[template_child]
         pub(crate) shapeconfig_menubutton: TemplateChild<MenuButton>,
         #[template_child]
+        pub(crate) smoothstyle_fill_gradient_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub(crate) smoothstyle_fill_gradient_angle_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
         pub(crate) roughstyle_fillstyle_row: TemplateChild<adw::ComboRow>,
         #[template_child]
         pub(crate) roughstyle_hachure_angle_row: TemplateChild<adw::SpinRow>,
@@ -223,7 +227,7 @@ impl RnShaperPage {
         );
         // set value after the range!
         imp.stroke_width_picker
-            .set_stroke_width(SmoothOptions::default().stroke_width);
+            .set_stroke_width(SmoothOptions::default().stroke_width());
 
         imp.stroke_width_picker.connect_notify_local(
             Some("stroke-width"),
@@ -242,7 +246,7 @@ impl RnShaperPage {
                                 .pens_config
                                 .shaper_config
                                 .smooth_options
-                                .stroke_width = stroke_width;
+                                .set_stroke_width(stroke_width);
                         }
                         ShaperStyle::Rough => {
                             canvas
@@ -283,7 +287,7 @@ impl RnShaperPage {
                                 .pens_config
                                 .shaper_config
                                 .smooth_options
-                                .stroke_width;
+                                .stroke_width();
                             shaperpage
                                 .imp()
                                 .stroke_width_picker
@@ -316,6 +320,45 @@ impl RnShaperPage {
             }
         ));
 
+        // Smooth style
+        // Gradient fill
+        imp.smoothstyle_fill_gradient_row
+            .get()
+            .connect_active_notify(clone!(
+                #[weak]
+                appwindow,
+                move |row| {
+                    appwindow
+                        .active_tab_wrapper()
+                        .canvas()
+                        .engine_mut()
+                        .pens_config
+                        .shaper_config
+                        .smooth_fill_gradient = row.is_active();
+                }
+            ));
+
+        // Gradient angle
+        imp.smoothstyle_fill_gradient_angle_row
+            .get()
+            .connect_changed(clone!(
+                #[weak]
+                appwindow,
+                move |row| {
+                    appwindow
+                        .active_tab_wrapper()
+                        .canvas()
+                        .engine_mut()
+                        .pens_config
+                        .shaper_config
+                        .smooth_fill_gradient_angle = row
+                        .value()
+                        .round()
+                        .to_radians()
+                        .clamp(-std::f64::consts::PI, std::f64::consts::PI);
+                }
+            ));
+
         // Rough style
         // Fill style
         imp.roughstyle_fillstyle_row
@@ -516,7 +559,7 @@ impl RnShaperPage {
         match shaper_config.style {
             ShaperStyle::Smooth => {
                 imp.stroke_width_picker
-                    .set_stroke_width(shaper_config.smooth_options.stroke_width);
+                    .set_stroke_width(shaper_config.smooth_options.stroke_width());
             }
             ShaperStyle::Rough => {
                 imp.stroke_width_picker
@@ -527,6 +570,12 @@ impl RnShaperPage {
         // builder type
         self.set_shapebuildertype(shaper_config.builder_type);
 
+        // Smooth style
+        imp.smoothstyle_fill_gradient_row
+            .set_active(shaper_config.smooth_fill_gradient);
+        imp.smoothstyle_fill_gradient_angle_row
+            .set_value(shaper_config.smooth_fill_gradient_angle.to_degrees());
+
         // Rough style
         self.set_roughstyle_fillstyle(shaper_config.rough_options.fill_style);
         imp.roughstyle_hachure_angle_row
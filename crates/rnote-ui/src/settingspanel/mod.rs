@@ -18,7 +18,7 @@ use num_traits::ToPrimitive;
 use rnote_compose::penevent::ShortcutKey;
 use rnote_engine::document::background::PatternStyle;
 use rnote_engine::document::format::{self, Format, PredefinedFormat};
-use rnote_engine::document::Layout;
+use rnote_engine::document::{Layout, PageMargins};
 use rnote_engine::ext::GdkRGBAExt;
 use std::cell::RefCell;
 
@@ -94,6 +94,14 @@ mod imp {
         #[template_child]
         pub(crate) doc_background_pattern_height_unitentry: TemplateChild<RnUnitEntry>,
         #[template_child]
+        pub(crate) doc_margin_top_unitentry: TemplateChild<RnUnitEntry>,
+        #[template_child]
+        pub(crate) doc_margin_bottom_unitentry: TemplateChild<RnUnitEntry>,
+        #[template_child]
+        pub(crate) doc_margin_left_unitentry: TemplateChild<RnUnitEntry>,
+        #[template_child]
+        pub(crate) doc_margin_right_unitentry: TemplateChild<RnUnitEntry>,
+        #[template_child]
         pub(crate) background_pattern_invert_color_button: TemplateChild<Button>,
         #[template_child]
         pub(crate) penshortcut_stylus_button_primary_row: TemplateChild<RnPenShortcutRow>,
@@ -430,9 +438,10 @@ impl RnSettingsPanel {
     fn refresh_doc_ui(&self, active_tab: &RnCanvasWrapper) {
         let imp = self.imp();
         let canvas = active_tab.canvas();
-        let background = canvas.engine_ref().document.background;
+        let background = canvas.engine_ref().document.background.clone();
         let format = canvas.engine_ref().document.format;
         let document_layout = canvas.engine_ref().document.layout;
+        let margins = canvas.engine_ref().document.margins();
 
         imp.doc_background_color_button
             .set_rgba(&gdk::RGBA::from_compose_color(background.color));
@@ -447,6 +456,15 @@ impl RnSettingsPanel {
             .set_dpi(format.dpi());
         imp.doc_background_pattern_height_unitentry
             .set_value_in_px(background.pattern_size[1]);
+        for (unit_entry, margin) in [
+            (&imp.doc_margin_top_unitentry, margins.top),
+            (&imp.doc_margin_bottom_unitentry, margins.bottom),
+            (&imp.doc_margin_left_unitentry, margins.left),
+            (&imp.doc_margin_right_unitentry, margins.right),
+        ] {
+            unit_entry.set_dpi(format.dpi());
+            unit_entry.set_value_in_px(margin);
+        }
         self.set_document_layout(&document_layout);
     }
 
@@ -900,6 +918,41 @@ impl RnSettingsPanel {
                 ),
             );
 
+        let margin_setters: [(RnUnitEntry, fn(&mut PageMargins, f64)); 4] = [
+            (imp.doc_margin_top_unitentry.get(), |margins, v| {
+                margins.top = v
+            }),
+            (imp.doc_margin_bottom_unitentry.get(), |margins, v| {
+                margins.bottom = v
+            }),
+            (imp.doc_margin_left_unitentry.get(), |margins, v| {
+                margins.left = v
+            }),
+            (imp.doc_margin_right_unitentry.get(), |margins, v| {
+                margins.right = v
+            }),
+        ];
+        for (unit_entry, set_margin) in margin_setters {
+            unit_entry.connect_notify_local(
+                Some("value"),
+                clone!(
+                    #[weak]
+                    appwindow,
+                    move |unit_entry, _| {
+                        let canvas = appwindow.active_tab_wrapper().canvas();
+                        let mut margins = canvas.engine_ref().document.margins();
+                        set_margin(&mut margins, unit_entry.value_in_px());
+
+                        let mut widget_flags = canvas.engine_mut().document.set_margins(margins);
+                        if widget_flags.redraw {
+                            widget_flags.store_modified = true;
+                        }
+                        appwindow.handle_widget_flags(widget_flags, &canvas);
+                    }
+                ),
+            );
+        }
+
         imp.background_pattern_invert_color_button
             .get()
             .connect_clicked(clone!(
@@ -1184,6 +1237,14 @@ impl RnSettingsPanel {
             .set_dpi_keep_value(temporary_format.dpi());
         imp.doc_background_pattern_height_unitentry
             .set_dpi_keep_value(temporary_format.dpi());
+        for unit_entry in [
+            &imp.doc_margin_top_unitentry,
+            &imp.doc_margin_bottom_unitentry,
+            &imp.doc_margin_left_unitentry,
+            &imp.doc_margin_right_unitentry,
+        ] {
+            unit_entry.set_dpi_keep_value(temporary_format.dpi());
+        }
 
         canvas.engine_mut().document.format = temporary_format;
         let mut widget_flags = canvas.engine_mut().doc_resize_to_fit_content();
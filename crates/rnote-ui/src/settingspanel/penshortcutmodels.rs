@@ -21,6 +21,7 @@ impl Default for ChangePenStyleListModel {
             &PenStyle::Eraser.to_string(),
             &PenStyle::Selector.to_string(),
             &PenStyle::Tools.to_string(),
+            &PenStyle::Laser.to_string(),
         ]))
     }
 }
@@ -84,6 +85,7 @@ impl Default for ChangePenStyleListFactory {
                         PenStyle::Eraser => gettext("Eraser"),
                         PenStyle::Selector => gettext("Selector"),
                         PenStyle::Tools => gettext("Tools"),
+                        PenStyle::Laser => gettext("Laser Pointer"),
                     };
                     next_child
                         .downcast_ref::<Label>()
@@ -29,7 +29,7 @@ pub(crate) async fn create_replace_file_future(
         .create(true)
         .truncate(true)
         .write(true)
-        .open(&file_path)
+        .open(crate::env::long_path(&file_path))
         .await
         .context(format!(
             "Failed to create/open/truncate file for path '{}'",